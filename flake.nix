@@ -79,6 +79,14 @@
             enableTranscriber = true;
           });
 
+          # The transcriber and the other optional features gate large parts
+          # of the code, so checks also run with every feature on
+          allFeaturesArgs = plentysound-full.passthru.baseArgs // {
+            cargoExtraArgs = "--offline --all-features";
+            buildInputs = plentysound-full.passthru.baseArgs.buildInputs ++ [ pkgs.libopus ];
+          };
+          allFeaturesArtifacts = craneLib.buildDepsOnly allFeaturesArgs;
+
           devBuildInputs = with pkgs; [
             # Rust tooling
             rust-analyzer
@@ -144,6 +152,15 @@
               inherit (plentysound.passthru) cargoArtifacts;
             });
 
+            clippy-all-features = craneLib.cargoClippy (allFeaturesArgs // {
+              cargoArtifacts = allFeaturesArtifacts;
+              cargoClippyExtraArgs = "--all-targets -- --deny warnings";
+            });
+
+            tests-all-features = craneLib.cargoTest (allFeaturesArgs // {
+              cargoArtifacts = allFeaturesArtifacts;
+            });
+
             fmt = craneLib.cargoFmt { inherit src; };
 
             # audit = craneLib.cargoAudit {
@@ -1,30 +1,335 @@
 use biquad::{Biquad, Coefficients, DirectForm2Transposed, Hertz, Type, Q_BUTTERWORTH_F64};
 use strsim::jaro_winkler;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
 
 // ── Config ───────────────────────────────────────────────────────────────────
 
+/// Rate the recognizer is fed at. Preprocessing, dedup positions and
+/// captures are all in samples at this rate.
 pub const SAMPLE_RATE: f64 = 16_000.0;
 pub const CHANNELS: u32 = 1;
 
-pub const CHUNK_SECS: f64 = 1.5;
-pub const OVERLAP_SECS: f64 = 0.75;
-pub const CHUNK_SAMPLES: usize = (SAMPLE_RATE * CHUNK_SECS) as usize; // 24000
-pub const OVERLAP_SAMPLES: usize = (SAMPLE_RATE * OVERLAP_SECS) as usize; // 12000
+/// Ignore the same keyword if detected again within this many seconds of audio.
+pub const DEDUP_COOLDOWN_SECS: f64 = 3.0;
 
-/// Minimum number of samples in a tail chunk worth sending to the recognizer.
-/// Below this (~200ms) there's too little speech to recognize.
-pub const MIN_TAIL_SAMPLES: usize = (SAMPLE_RATE * 0.2) as usize; // 3200
+/// How the detector captures, cuts and matches audio. The live detector,
+/// offline runs and the accuracy benchmark all start from `DEFAULT`; build
+/// variants with `..DetectorParams::DEFAULT` and check them with `validated`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectorParams {
+    /// Rate PipeWire delivers captured audio at, downsampled to `SAMPLE_RATE`.
+    pub capture_rate: u32,
+    /// Channels PipeWire delivers, mixed down to mono.
+    pub capture_channels: u32,
+    /// Length of each chunk handed to the recognizer.
+    pub chunk_secs: f64,
+    /// How much of a chunk is repeated at the start of the next one, so a
+    /// word cut at a boundary is heard whole. Must be shorter than a chunk.
+    pub overlap_secs: f64,
+    /// Shortest leftover worth recognizing when the stream ends; below this
+    /// there's too little speech to recognize.
+    pub min_tail_secs: f64,
+    /// How recognized text is compared with keywords.
+    pub matcher: MatcherSettings,
+}
+
+impl DetectorParams {
+    pub const DEFAULT: DetectorParams = DetectorParams {
+        capture_rate: 48_000,
+        capture_channels: 2,
+        chunk_secs: 1.5,
+        overlap_secs: 0.75,
+        min_tail_secs: 0.2,
+        matcher: MatcherSettings::GENERIC,
+    };
+
+    /// `chunk_secs` in samples at `SAMPLE_RATE` (24000 by default).
+    pub const fn chunk_samples(&self) -> usize {
+        (SAMPLE_RATE * self.chunk_secs) as usize
+    }
+
+    /// `overlap_secs` in samples at `SAMPLE_RATE` (12000 by default).
+    pub const fn overlap_samples(&self) -> usize {
+        (SAMPLE_RATE * self.overlap_secs) as usize
+    }
+
+    /// `min_tail_secs` in samples at `SAMPLE_RATE` (3200 by default).
+    pub const fn min_tail_samples(&self) -> usize {
+        (SAMPLE_RATE * self.min_tail_secs) as usize
+    }
+
+    /// The params back if they describe a detector that can run: chunks
+    /// advance (overlap shorter than a chunk), tails fit in a chunk, and the
+    /// capture side only ever downsamples.
+    pub fn validated(self) -> Result<Self, String> {
+        if self.capture_rate < SAMPLE_RATE as u32 {
+            return Err(format!(
+                "capture rate {} Hz is below the recognizer's {} Hz",
+                self.capture_rate, SAMPLE_RATE
+            ));
+        }
+        if self.capture_channels == 0 {
+            return Err("capture needs at least one channel".to_string());
+        }
+        if self.chunk_samples() == 0 {
+            return Err(format!("chunk of {}s is empty", self.chunk_secs));
+        }
+        if self.overlap_secs < 0.0 || self.overlap_samples() >= self.chunk_samples() {
+            return Err(format!(
+                "overlap of {}s must be shorter than the {}s chunk",
+                self.overlap_secs, self.chunk_secs
+            ));
+        }
+        if self.min_tail_samples() == 0 || self.min_tail_samples() > self.chunk_samples() {
+            return Err(format!(
+                "minimum tail of {}s must be positive and fit in the {}s chunk",
+                self.min_tail_secs, self.chunk_secs
+            ));
+        }
+        self.matcher.validated()?;
+        Ok(self)
+    }
+}
+
+impl Default for DetectorParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Keyword matching settings. Jaro-Winkler rates long words with a shared
+/// stem highly, so what works for one language's word lengths misfires on
+/// another's; each model gets its own, see `for_model`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatcherSettings {
+    /// Jaro-Winkler similarity a word needs to fuzzy-match a keyword.
+    pub fuzzy_threshold: f64,
+    /// Keywords shorter than this many characters only match exactly.
+    pub min_fuzzy_len: usize,
+    /// Compare with accents folded away, so "olá" matches "ola".
+    pub fold_accents: bool,
+}
+
+impl MatcherSettings {
+    /// For models without tuned settings.
+    pub const GENERIC: MatcherSettings = MatcherSettings {
+        fuzzy_threshold: 0.85,
+        min_fuzzy_len: 3,
+        fold_accents: true,
+    };
 
-pub const FUZZY_THRESHOLD: f64 = 0.85; // Jaro-Winkler similarity threshold
+    /// Tuned for the bundled Portuguese model. Inflections share long stems,
+    /// so the generic threshold lets "cantar" fire on "cantou".
+    pub const PORTUGUESE: MatcherSettings = MatcherSettings {
+        fuzzy_threshold: 0.88,
+        min_fuzzy_len: 4,
+        fold_accents: true,
+    };
+
+    /// Defaults for the model in directory `name`, going by the language
+    /// code in Vosk's `vosk-model-small-pt-0.3` naming.
+    pub fn for_model(name: &str) -> Self {
+        if name.split('-').any(|part| part == "pt") {
+            Self::PORTUGUESE
+        } else {
+            Self::GENERIC
+        }
+    }
+
+    /// The settings back if the threshold is in (0, 1].
+    pub fn validated(self) -> Result<Self, String> {
+        if !(self.fuzzy_threshold > 0.0 && self.fuzzy_threshold <= 1.0) {
+            return Err(format!(
+                "fuzzy threshold {} is outside (0, 1]",
+                self.fuzzy_threshold
+            ));
+        }
+        Ok(self)
+    }
+
+    /// `text` lowercased, and accent-folded if `fold_accents` is set.
+    pub fn normalize(&self, text: &str) -> String {
+        if self.fold_accents {
+            normalize_text(text)
+        } else {
+            text.to_lowercase()
+        }
+    }
+}
+
+impl Default for MatcherSettings {
+    fn default() -> Self {
+        Self::GENERIC
+    }
+}
+
+// Chunks that don't advance would stall the detector.
+const _: () =
+    assert!(DetectorParams::DEFAULT.overlap_samples() < DetectorParams::DEFAULT.chunk_samples());
+
+// ── Buffer layout ────────────────────────────────────────────────────────────
+
+/// Interleave planar audio (one slice per channel) into frame order.
+/// Planes of unequal length are cut to the shortest one.
+pub fn interleave_planes(planes: &[Vec<i16>]) -> Vec<i16> {
+    let frames = planes.iter().map(Vec::len).min().unwrap_or(0);
+    let mut out = Vec::with_capacity(frames * planes.len());
+    for frame in 0..frames {
+        out.extend(planes.iter().map(|plane| plane[frame]));
+    }
+    out
+}
 
 // ── Audio preprocessing ──────────────────────────────────────────────────────
 
-/// High-pass filter (80 Hz Butterworth) to remove rumble/hum/DC offset
-pub fn highpass_filter(samples: &[i16]) -> Vec<i16> {
+/// Tunables for the cleanup each chunk gets before it reaches Vosk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preprocess {
+    /// High-pass cutoff in Hz; 0 turns the filter off.
+    pub highpass_hz: f64,
+    /// RMS level normalization brings speech up to.
+    pub target_rms: f64,
+    /// Largest gain normalization may apply.
+    pub max_gain: f64,
+    /// Chunks below this RMS are treated as silence and left untouched.
+    pub silence_rms: f64,
+}
+
+impl Preprocess {
+    pub const DEFAULT: Preprocess = Preprocess {
+        highpass_hz: 80.0,
+        target_rms: 3000.0,
+        max_gain: 10.0,
+        silence_rms: 100.0,
+    };
+
+    /// High-pass filter then normalize a chunk.
+    pub fn apply(&self, samples: &[i16]) -> Vec<i16> {
+        let mut out = highpass_filter(samples, self.highpass_hz);
+        normalize(&mut out, self.target_rms, self.max_gain, self.silence_rms);
+        out
+    }
+}
+
+impl Default for Preprocess {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// How much CPU the live detector spends while nobody talks. The lighter
+/// modes keep silence away from the recognizer and wake up less often, at
+/// the cost of a little latency.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PowerMode {
+    /// Every chunk is recognized as soon as it is buffered.
+    #[default]
+    Performance,
+    /// Chunks below `Preprocess::silence_rms` are skipped.
+    Balanced,
+    /// Skips chunks below twice `silence_rms`, wakes four times a second
+    /// and recognizes one chunk per wake-up.
+    Efficiency,
+}
+
+impl PowerMode {
+    pub const ALL: [PowerMode; 3] = [
+        PowerMode::Performance,
+        PowerMode::Balanced,
+        PowerMode::Efficiency,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerMode::Performance => "performance",
+            PowerMode::Balanced => "balanced",
+            PowerMode::Efficiency => "efficiency",
+        }
+    }
+
+    /// How often captured audio is picked up.
+    pub fn tick(self) -> std::time::Duration {
+        match self {
+            PowerMode::Performance | PowerMode::Balanced => std::time::Duration::from_millis(100),
+            PowerMode::Efficiency => std::time::Duration::from_millis(250),
+        }
+    }
+
+    /// Full chunks recognized per tick; the others stay buffered for the
+    /// next one. One chunk covers three ticks of audio, so bursts catch up.
+    pub fn chunks_per_tick(self) -> usize {
+        match self {
+            PowerMode::Performance | PowerMode::Balanced => usize::MAX,
+            PowerMode::Efficiency => 1,
+        }
+    }
+
+    /// What keeps silence from the recognizer in this mode, if anything.
+    pub fn silence_gate(self, preprocess: &Preprocess) -> Option<SilenceGate> {
+        match self {
+            PowerMode::Performance => None,
+            PowerMode::Balanced => Some(SilenceGate::new(preprocess.silence_rms)),
+            PowerMode::Efficiency => Some(SilenceGate::new(preprocess.silence_rms * 2.0)),
+        }
+    }
+}
+
+/// Quiet chunks still recognized after speech, so the recognizer hears the
+/// pause that ends an utterance and finalizes it.
+pub const GATE_HANGOVER_CHUNKS: u32 = 2;
+
+/// Skips the recognizer on silence. A chunk passes when its RMS reaches
+/// `threshold`, and for `GATE_HANGOVER_CHUNKS` quiet ones after that.
+#[derive(Debug, Clone)]
+pub struct SilenceGate {
+    threshold: f64,
+    /// Quiet chunks since the last loud one.
+    quiet: u32,
+}
+
+impl SilenceGate {
+    /// Starts closed: nothing was heard yet.
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            quiet: GATE_HANGOVER_CHUNKS + 1,
+        }
+    }
+
+    /// Whether a full chunk of raw (not yet preprocessed) audio should be
+    /// recognized.
+    pub fn admit(&mut self, samples: &[i16]) -> bool {
+        if rms(samples) >= self.threshold {
+            self.quiet = 0;
+        } else {
+            self.quiet = self.quiet.saturating_add(1);
+        }
+        self.quiet <= GATE_HANGOVER_CHUNKS
+    }
+
+    /// Whether a tail is worth recognizing: speech passed recently, or the
+    /// tail's own `samples` are loud enough. Leaves the gate as it is.
+    pub fn hears(&self, samples: &[i16]) -> bool {
+        self.quiet <= GATE_HANGOVER_CHUNKS || rms(samples) >= self.threshold
+    }
+}
+
+fn rms(samples: &[i16]) -> f64 {
+    let sum_sq: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum_sq / samples.len().max(1) as f64).sqrt()
+}
+
+/// High-pass filter (Butterworth) to remove rumble/hum/DC offset.
+/// A cutoff of 0 or at/above Nyquist leaves the samples unchanged.
+pub fn highpass_filter(samples: &[i16], cutoff_hz: f64) -> Vec<i16> {
+    if !(cutoff_hz > 0.0 && cutoff_hz < SAMPLE_RATE / 2.0) {
+        return samples.to_vec();
+    }
     let coeffs = Coefficients::<f64>::from_params(
         Type::HighPass,
         Hertz::<f64>::from_hz(SAMPLE_RATE).unwrap(),
-        Hertz::<f64>::from_hz(80.0).unwrap(),
+        Hertz::<f64>::from_hz(cutoff_hz).unwrap(),
         Q_BUTTERWORTH_F64,
     )
     .unwrap();
@@ -39,15 +344,18 @@ pub fn highpass_filter(samples: &[i16]) -> Vec<i16> {
         .collect()
 }
 
-/// Normalize audio to a target RMS level for consistent volume
-pub fn normalize(samples: &mut [i16]) {
-    let target_rms: f64 = 3000.0;
-    let sum_sq: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
-    let rms = (sum_sq / samples.len() as f64).sqrt();
-    if rms < 100.0 {
+/// Normalize audio to `target_rms` for consistent volume, applying at most
+/// `max_gain`. Chunks quieter than `silence_rms` are left alone so noise
+/// isn't amplified.
+pub fn normalize(samples: &mut [i16], target_rms: f64, max_gain: f64, silence_rms: f64) {
+    if samples.is_empty() {
+        return;
+    }
+    let rms = rms(samples);
+    if rms < silence_rms {
         return; // silence — don't amplify noise
     }
-    let gain = (target_rms / rms).min(10.0);
+    let gain = (target_rms / rms).min(max_gain);
     for s in samples.iter_mut() {
         let v = (*s as f64 * gain).clamp(i16::MIN as f64, i16::MAX as f64);
         *s = v as i16;
@@ -56,30 +364,94 @@ pub fn normalize(samples: &mut [i16]) {
 
 // ── Keyword matching ─────────────────────────────────────────────────────────
 
-/// Check text against the given keywords with exact + fuzzy matching.
-/// Returns the first matched keyword, if any.
-pub fn check_keywords_matched(text: &str, keywords: &[&str]) -> Option<String> {
-    if text.is_empty() {
-        return None;
+/// Fold text for comparison: lowercase, then drop accents so "Olá", "ola"
+/// and "OLA" compare equal. The text is NFKD-decomposed and every combining
+/// mark is removed, so compatibility forms ("ﬁ", fullwidth letters) fold too.
+pub fn normalize_text(text: &str) -> String {
+    text.nfkd()
+        .filter(|&c| !is_combining_mark(c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Repeat filter for keyword hits. Hits are placed by their position in the
+/// captured audio (16 kHz samples since the stream started) rather than
+/// wall-clock, so a backlog processed in one burst keeps its real spacing and
+/// a paused stream doesn't age out the cooldown.
+pub struct MatchDedup {
+    cooldown_samples: u64,
+    last: Option<(String, u64)>,
+}
+
+impl MatchDedup {
+    pub fn new(cooldown_secs: f64) -> Self {
+        Self {
+            cooldown_samples: (cooldown_secs * SAMPLE_RATE) as u64,
+            last: None,
+        }
     }
-    let text_lower = text.to_lowercase();
-    for &keyword in keywords {
-        if text_lower.contains(keyword) || fuzzy_match(&text_lower, keyword) {
-            return Some(keyword.to_string());
+
+    /// Whether a hit on `keyword` at audio position `at` repeats the last
+    /// accepted hit within the cooldown. Accepted hits restart the cooldown.
+    pub fn is_duplicate(&mut self, keyword: &str, at: u64) -> bool {
+        let dup = matches!(
+            &self.last,
+            Some((last_kw, last_at)) if last_kw == keyword
+                && at.saturating_sub(*last_at) < self.cooldown_samples
+        );
+        if !dup {
+            self.last = Some((keyword.to_string(), at));
         }
+        dup
     }
-    None
 }
 
-/// Check text against keywords using exact `contains()` only (no fuzzy).
-/// Returns the first matched keyword, if any.
+impl Default for MatchDedup {
+    fn default() -> Self {
+        Self::new(DEDUP_COOLDOWN_SECS)
+    }
+}
+
+/// Check text against the given keywords with exact + fuzzy matching under
+/// the generic settings. Both sides go through `normalize_text`, so matching
+/// ignores case and accents. Returns the first matched keyword as given, if any.
+pub fn check_keywords_matched(text: &str, keywords: &[&str]) -> Option<String> {
+    check_keywords_fuzzy(text, keywords, &MatcherSettings::GENERIC)
+}
+
+/// `check_keywords_matched` under an explicit `matcher`.
+pub fn check_keywords_fuzzy(
+    text: &str,
+    keywords: &[&str],
+    matcher: &MatcherSettings,
+) -> Option<String> {
+    check_keywords(text, keywords, matcher, true)
+}
+
+/// Check text against keywords using exact `contains()` only (no fuzzy),
+/// after `normalize_text` on both sides.
+/// Returns the first matched keyword as given, if any.
 pub fn check_keywords_exact(text: &str, keywords: &[&str]) -> Option<String> {
+    check_keywords(text, keywords, &MatcherSettings::GENERIC, false)
+}
+
+/// Exact `contains()` after `matcher.normalize` on both sides, plus
+/// Jaro-Winkler when `fuzzy`. Returns the first matched keyword as given.
+pub fn check_keywords(
+    text: &str,
+    keywords: &[&str],
+    matcher: &MatcherSettings,
+    fuzzy: bool,
+) -> Option<String> {
     if text.is_empty() {
         return None;
     }
-    let text_lower = text.to_lowercase();
+    let text_norm = matcher.normalize(text);
     for &keyword in keywords {
-        if text_lower.contains(keyword) {
+        let keyword_norm = matcher.normalize(keyword);
+        if text_norm.contains(&keyword_norm)
+            || (fuzzy && fuzzy_match(&text_norm, &keyword_norm, matcher))
+        {
             return Some(keyword.to_string());
         }
     }
@@ -87,10 +459,356 @@ pub fn check_keywords_exact(text: &str, keywords: &[&str]) -> Option<String> {
 }
 
 /// Fuzzy match using Jaro-Winkler similarity (good for short strings/typos)
-pub fn fuzzy_match(text: &str, keyword: &str) -> bool {
-    if keyword.chars().count() < 3 {
+pub fn fuzzy_match(text: &str, keyword: &str, matcher: &MatcherSettings) -> bool {
+    if keyword.chars().count() < matcher.min_fuzzy_len {
         return false;
     }
     text.split_whitespace()
-        .any(|word| jaro_winkler(word, keyword) >= FUZZY_THRESHOLD)
+        .any(|word| jaro_winkler(word, keyword) >= matcher.fuzzy_threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunking::ChunkIter;
+
+    #[test]
+    fn interleaves_stereo_planes_into_frames() {
+        let left = vec![1, 2, 3];
+        let right = vec![-1, -2, -3];
+        assert_eq!(interleave_planes(&[left, right]), vec![1, -1, 2, -2, 3, -3]);
+    }
+
+    #[test]
+    fn single_plane_is_unchanged() {
+        let mono = vec![5, 6, 7, 8];
+        assert_eq!(interleave_planes(std::slice::from_ref(&mono)), mono);
+    }
+
+    #[test]
+    fn uneven_planes_are_cut_to_the_shortest() {
+        let planes = vec![vec![1, 2, 3, 4], vec![10, 20], vec![100, 200, 300]];
+        assert_eq!(interleave_planes(&planes), vec![1, 10, 100, 2, 20, 200]);
+    }
+
+    #[test]
+    fn no_planes_gives_no_samples() {
+        assert!(interleave_planes(&[]).is_empty());
+    }
+
+    const LOUD: [i16; 4] = [2000, -2000, 2000, -2000];
+    const QUIET: [i16; 4] = [50, -50, 50, -50];
+
+    #[test]
+    fn gate_lets_speech_and_the_pause_after_it_through() {
+        let mut gate = SilenceGate::new(100.0);
+        // Nothing heard yet: silence is skipped
+        assert!(!gate.admit(&QUIET));
+        assert!(gate.admit(&LOUD));
+        for i in 0..GATE_HANGOVER_CHUNKS {
+            assert!(gate.admit(&QUIET), "quiet chunk {i} after speech");
+        }
+        assert!(!gate.admit(&QUIET));
+        assert!(!gate.admit(&QUIET));
+        assert!(gate.admit(&LOUD));
+    }
+
+    #[test]
+    fn tails_are_heard_while_the_gate_is_open_or_when_loud() {
+        let mut gate = SilenceGate::new(100.0);
+        assert!(!gate.hears(&QUIET));
+        assert!(gate.hears(&LOUD));
+        assert!(!gate.hears(&[]));
+
+        gate.admit(&LOUD);
+        assert!(gate.hears(&QUIET));
+    }
+
+    #[test]
+    fn lighter_modes_gate_harder_and_still_keep_up() {
+        let preprocess = Preprocess {
+            silence_rms: 100.0,
+            ..Preprocess::DEFAULT
+        };
+        let at_150 = [150i16, -150];
+        assert!(PowerMode::Performance.silence_gate(&preprocess).is_none());
+        assert!(PowerMode::Balanced
+            .silence_gate(&preprocess)
+            .unwrap()
+            .admit(&at_150));
+        assert!(!PowerMode::Efficiency
+            .silence_gate(&preprocess)
+            .unwrap()
+            .admit(&at_150));
+
+        // However few chunks a tick takes, they cover more audio than arrives
+        // in the meantime, so the buffer drains after a burst
+        let params = DetectorParams::DEFAULT;
+        let advance_secs = (params.chunk_samples() - params.overlap_samples()) as f64 / SAMPLE_RATE;
+        for mode in PowerMode::ALL {
+            let per_tick = mode.chunks_per_tick().min(1_000) as f64 * advance_secs;
+            assert!(per_tick > mode.tick().as_secs_f64(), "{}", mode.name());
+        }
+    }
+
+    fn rms(samples: &[i16]) -> f64 {
+        let sum_sq: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+        (sum_sq / samples.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn gain_is_capped_by_max_gain() {
+        let mut quiet = [500i16, -500].repeat(100);
+        normalize(&mut quiet, 3000.0, 2.0, 100.0);
+        assert_eq!(rms(&quiet).round(), 1000.0);
+    }
+
+    #[test]
+    fn chunks_below_the_silence_floor_are_untouched() {
+        let original = [300i16, -300].repeat(100);
+        let mut samples = original.clone();
+        normalize(&mut samples, 3000.0, 10.0, 400.0);
+        assert_eq!(samples, original);
+
+        normalize(&mut samples, 3000.0, 10.0, 100.0);
+        assert_eq!(rms(&samples).round(), 3000.0);
+    }
+
+    #[test]
+    fn highpass_cutoff_outside_the_band_disables_the_filter() {
+        let dc = vec![1000i16; 16_000];
+        assert_eq!(highpass_filter(&dc, 0.0), dc);
+        assert_eq!(highpass_filter(&dc, 9000.0), dc);
+        // A real cutoff strips the DC offset
+        let filtered = highpass_filter(&dc, Preprocess::DEFAULT.highpass_hz);
+        assert!(filtered.last().unwrap().abs() < 100);
+    }
+
+    #[test]
+    fn strips_portuguese_accents_and_case() {
+        assert_eq!(normalize_text("Olá"), "ola");
+        assert_eq!(normalize_text("AÇÃO"), "acao");
+        assert_eq!(normalize_text("café"), "cafe");
+        assert_eq!(normalize_text("pão de açúcar"), "pao de acucar");
+        assert_eq!(normalize_text("Você está aí?"), "voce esta ai?");
+        assert_eq!(normalize_text("ôi"), "oi");
+    }
+
+    #[test]
+    fn strips_decomposed_combining_marks() {
+        // "e" + U+0301 COMBINING ACUTE ACCENT, "c" + U+0327 COMBINING CEDILLA
+        assert_eq!(normalize_text("e\u{301}"), "e");
+        assert_eq!(normalize_text("c\u{327}a"), "ca");
+    }
+
+    #[test]
+    fn folds_compatibility_forms() {
+        // Ligature, fullwidth letters and a dotted capital I
+        assert_eq!(normalize_text("ﬁm"), "fim");
+        assert_eq!(normalize_text("ＯＬÁ"), "ola");
+        assert_eq!(normalize_text("İstanbul"), "istanbul");
+    }
+
+    #[test]
+    fn exact_matching_ignores_accents() {
+        assert_eq!(
+            check_keywords_exact("ola pessoal", &["olá"]),
+            Some("olá".to_string())
+        );
+        assert_eq!(
+            check_keywords_exact("olá pessoal", &["ola"]),
+            Some("ola".to_string())
+        );
+        // Too short for the fuzzy path, so only normalization can match these
+        assert_eq!(
+            check_keywords_exact("oi gente", &["ôi"]),
+            Some("ôi".to_string())
+        );
+        assert_eq!(
+            check_keywords_matched("Ôi gente", &["oi"]),
+            Some("oi".to_string())
+        );
+    }
+
+    #[test]
+    fn different_words_still_do_not_match() {
+        assert_eq!(check_keywords_exact("pao quente", &["pão doce"]), None);
+        assert_eq!(check_keywords_matched("", &["olá"]), None);
+    }
+
+    #[test]
+    fn models_get_matchers_for_their_language() {
+        let for_model = MatcherSettings::for_model;
+        assert_eq!(
+            for_model("vosk-model-small-pt-0.3"),
+            MatcherSettings::PORTUGUESE
+        );
+        assert_eq!(
+            for_model("vosk-model-small-en-us-0.15"),
+            MatcherSettings::GENERIC
+        );
+        assert_eq!(for_model("my-model"), MatcherSettings::GENERIC);
+    }
+
+    #[test]
+    fn portuguese_matcher_keeps_inflections_apart() {
+        let (pt, generic) = (MatcherSettings::PORTUGUESE, MatcherSettings::GENERIC);
+        let hit = Some("cantar".to_string());
+        assert_eq!(
+            check_keywords_fuzzy("ele cantou", &["cantar"], &generic),
+            hit
+        );
+        assert_eq!(check_keywords_fuzzy("ele cantou", &["cantar"], &pt), None);
+        // Near-misses on the same form still match
+        assert_eq!(check_keywords_fuzzy("vamo cantarr", &["cantar"], &pt), hit);
+    }
+
+    #[test]
+    fn accent_folding_can_be_turned_off() {
+        let strict = MatcherSettings {
+            fold_accents: false,
+            ..MatcherSettings::GENERIC
+        };
+        assert_eq!(
+            check_keywords("Olá pessoal", &["olá"], &strict, false),
+            Some("olá".to_string())
+        );
+        assert_eq!(
+            check_keywords("ola pessoal", &["olá"], &strict, false),
+            None
+        );
+        // Fuzzy matching needs a longer keyword than the generic minimum
+        let short = MatcherSettings {
+            min_fuzzy_len: 6,
+            ..MatcherSettings::GENERIC
+        };
+        let generic = MatcherSettings::GENERIC;
+        assert_eq!(check_keywords("bumba", &["bomba"], &short, true), None);
+        assert_eq!(
+            check_keywords("bumba", &["bomba"], &generic, true),
+            Some("bomba".to_string())
+        );
+    }
+
+    #[test]
+    fn defaults_are_valid_and_advance() {
+        let params = DetectorParams::DEFAULT.validated().unwrap();
+        assert!(params.overlap_samples() < params.chunk_samples());
+        assert!(params.min_tail_samples() <= params.chunk_samples());
+    }
+
+    #[test]
+    fn overlap_must_be_shorter_than_a_chunk() {
+        for overlap_secs in [1.5, 2.0, -0.1] {
+            let params = DetectorParams {
+                overlap_secs,
+                ..DetectorParams::DEFAULT
+            };
+            assert!(
+                params.validated().is_err(),
+                "overlap {overlap_secs}s accepted"
+            );
+        }
+        let params = DetectorParams {
+            chunk_secs: 2.0,
+            overlap_secs: 1.0,
+            ..DetectorParams::DEFAULT
+        };
+        assert_eq!(params.validated(), Ok(params));
+    }
+
+    #[test]
+    fn out_of_range_settings_are_rejected() {
+        let bad = [
+            DetectorParams {
+                capture_rate: 8_000,
+                ..DetectorParams::DEFAULT
+            },
+            DetectorParams {
+                capture_channels: 0,
+                ..DetectorParams::DEFAULT
+            },
+            DetectorParams {
+                min_tail_secs: 0.0,
+                ..DetectorParams::DEFAULT
+            },
+            DetectorParams {
+                min_tail_secs: 2.0,
+                ..DetectorParams::DEFAULT
+            },
+            DetectorParams {
+                matcher: MatcherSettings {
+                    fuzzy_threshold: 0.0,
+                    ..MatcherSettings::GENERIC
+                },
+                ..DetectorParams::DEFAULT
+            },
+            DetectorParams {
+                matcher: MatcherSettings {
+                    fuzzy_threshold: 1.5,
+                    ..MatcherSettings::GENERIC
+                },
+                ..DetectorParams::DEFAULT
+            },
+        ];
+        for params in bad {
+            assert!(params.validated().is_err(), "{params:?} accepted");
+        }
+    }
+
+    /// Audio position of `secs` into the stream.
+    fn at(secs: f64) -> u64 {
+        (secs * SAMPLE_RATE) as u64
+    }
+
+    #[test]
+    fn repeats_within_the_cooldown_are_duplicates() {
+        let mut dedup = MatchDedup::new(3.0);
+        assert!(!dedup.is_duplicate("bomba", at(1.0)));
+        assert!(dedup.is_duplicate("bomba", at(1.75)));
+        assert!(dedup.is_duplicate("bomba", at(3.9)));
+        assert!(!dedup.is_duplicate("bomba", at(4.0)));
+    }
+
+    #[test]
+    fn backlog_keeps_audio_spacing() {
+        // A stalled callback hands over several seconds at once: the hits arrive
+        // back to back in wall-clock time but are far apart in the audio
+        let mut dedup = MatchDedup::new(3.0);
+        let verdicts: Vec<bool> = [0.5, 4.0, 8.0]
+            .iter()
+            .map(|&secs| dedup.is_duplicate("bomba", at(secs)))
+            .collect();
+        assert_eq!(verdicts, [false, false, false]);
+    }
+
+    #[test]
+    fn paused_stream_does_not_age_out_the_cooldown() {
+        // Nothing arrives while the stream is paused, so however long the pause
+        // lasts the chunk after it ends one advance past the one before
+        let params = DetectorParams::DEFAULT;
+        let advance = params.chunk_samples() - params.overlap_samples();
+        let mut chunks = ChunkIter::new();
+        let mut dedup = MatchDedup::new(3.0);
+        chunks.push(&vec![0; params.chunk_samples()]);
+        let before = chunks.next().unwrap();
+        assert!(!dedup.is_duplicate("bomba", before.end));
+
+        chunks.push(&vec![0; advance]);
+        let after = chunks.next().unwrap();
+        assert_eq!(after.end - before.end, advance as u64);
+        assert!(dedup.is_duplicate("bomba", after.end));
+    }
+
+    #[test]
+    fn duplicates_keep_the_cooldown_and_another_keyword_ends_it() {
+        let mut dedup = MatchDedup::new(3.0);
+        assert!(!dedup.is_duplicate("bomba", at(0.0)));
+        assert!(dedup.is_duplicate("bomba", at(2.0)));
+        // The duplicate at 2s didn't move the reference point
+        assert!(!dedup.is_duplicate("bomba", at(3.0)));
+        // Only the last accepted hit is remembered
+        assert!(!dedup.is_duplicate("olá", at(3.5)));
+        assert!(!dedup.is_duplicate("bomba", at(4.0)));
+    }
 }
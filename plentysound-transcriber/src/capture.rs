@@ -0,0 +1,135 @@
+use crate::audio::{DetectorParams, SAMPLE_RATE};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Longest capture the detector records before closing the file on its own.
+pub const MAX_CAPTURE_SAMPLES: u64 = 60 * SAMPLE_RATE as u64;
+
+/// Where the daemon parks a capture for the running detector to fill.
+pub type CaptureSlot = Arc<Mutex<Option<CaptureWriter>>>;
+
+/// Records what the recognizer is fed (16 kHz mono, after preprocessing) to
+/// a WAV file. Chunks overlap, so only the part of each chunk that wasn't in
+/// the previous one is written; the file plays back as one continuous stream.
+pub struct CaptureWriter {
+    writer: hound::WavWriter<BufWriter<File>>,
+    path: PathBuf,
+    written: u64,
+    limit: u64,
+}
+
+impl CaptureWriter {
+    /// Start a capture at `path` that stops after `limit` samples.
+    pub fn create(path: &Path, limit: u64) -> Result<Self> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE as u32,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(path, spec)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self {
+            writer,
+            path: path.to_path_buf(),
+            written: 0,
+            limit,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Seconds of audio written so far.
+    pub fn secs(&self) -> f64 {
+        self.written as f64 / SAMPLE_RATE
+    }
+
+    /// Append one preprocessed chunk as cut by `ChunkIter`. Returns false
+    /// once the limit is reached; call `finish` then.
+    pub fn push_chunk(&mut self, chunk: &[i16]) -> Result<bool> {
+        let fresh = if self.written == 0 {
+            chunk
+        } else {
+            &chunk[chunk.len().min(DetectorParams::DEFAULT.overlap_samples())..]
+        };
+        let room = (self.limit - self.written) as usize;
+        for &sample in &fresh[..fresh.len().min(room)] {
+            self.writer.write_sample(sample)?;
+        }
+        self.written += fresh.len().min(room) as u64;
+        Ok(self.written < self.limit)
+    }
+
+    /// Write the WAV header and close the file.
+    pub fn finish(self) -> Result<PathBuf> {
+        self.writer
+            .finalize()
+            .with_context(|| format!("Failed to finish {}", self.path.display()))?;
+        Ok(self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNK_SAMPLES: usize = DetectorParams::DEFAULT.chunk_samples();
+    const OVERLAP_SAMPLES: usize = DetectorParams::DEFAULT.overlap_samples();
+
+    fn scratch_wav(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "plentysound-capture-{}-{name}.wav",
+            std::process::id()
+        ))
+    }
+
+    fn read_back(path: &PathBuf) -> (hound::WavSpec, Vec<i16>) {
+        let reader = hound::WavReader::open(path).unwrap();
+        let spec = reader.spec();
+        let samples = reader.into_samples::<i16>().map(Result::unwrap).collect();
+        (spec, samples)
+    }
+
+    #[test]
+    fn overlapping_chunks_are_written_once() {
+        let path = scratch_wav("overlap");
+        let mut writer = CaptureWriter::create(&path, u64::MAX).unwrap();
+        let first: Vec<i16> = (0..CHUNK_SAMPLES).map(|i| i as i16).collect();
+        // The next chunk starts where the overlap begins
+        let second: Vec<i16> = (CHUNK_SAMPLES - OVERLAP_SAMPLES
+            ..2 * CHUNK_SAMPLES - OVERLAP_SAMPLES)
+            .map(|i| i as i16)
+            .collect();
+        assert!(writer.push_chunk(&first).unwrap());
+        assert!(writer.push_chunk(&second).unwrap());
+        assert_eq!(writer.finish().unwrap(), path);
+
+        let (spec, samples) = read_back(&path);
+        assert_eq!((spec.channels, spec.sample_rate), (1, SAMPLE_RATE as u32));
+        let expected: Vec<i16> = (0..2 * CHUNK_SAMPLES - OVERLAP_SAMPLES)
+            .map(|i| i as i16)
+            .collect();
+        assert_eq!(samples, expected);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn capture_stops_at_the_limit() {
+        let path = scratch_wav("limit");
+        let limit = CHUNK_SAMPLES as u64 + 100;
+        let mut writer = CaptureWriter::create(&path, limit).unwrap();
+        let chunk = vec![1i16; CHUNK_SAMPLES];
+        assert!(writer.push_chunk(&chunk).unwrap());
+        assert!(!writer.push_chunk(&chunk).unwrap());
+        writer.finish().unwrap();
+
+        let (_, samples) = read_back(&path);
+        assert_eq!(samples.len() as u64, limit);
+        let _ = std::fs::remove_file(&path);
+    }
+}
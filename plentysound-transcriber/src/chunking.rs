@@ -0,0 +1,394 @@
+use crate::audio::{
+    check_keywords, DetectorParams, MatchDedup, MatcherSettings, Preprocess, DEDUP_COOLDOWN_SECS,
+    SAMPLE_RATE,
+};
+use anyhow::{Context, Result};
+use vosk::{DecodingState, Model, Recognizer};
+
+// ── Chunking ─────────────────────────────────────────────────────────────────
+
+/// A slice of audio ready for the recognizer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Always a full chunk long; tails are padded with silence.
+    pub samples: Vec<i16>,
+    /// Audio position (16 kHz samples since the start) just past the last
+    /// real sample in the chunk.
+    pub end: u64,
+}
+
+/// Cuts a 16 kHz mono stream into chunks that overlap as `DetectorParams`
+/// says. Push audio as it arrives and iterate for the full chunks; whatever
+/// is left over is available through `tail`.
+pub struct ChunkIter {
+    buf: Vec<i16>,
+    received: u64,
+    chunk: usize,
+    overlap: usize,
+    min_tail: usize,
+}
+
+impl ChunkIter {
+    /// Chunks the way the live detector cuts them.
+    pub fn new() -> Self {
+        Self::with_params(&DetectorParams::DEFAULT)
+    }
+
+    pub fn with_params(params: &DetectorParams) -> Self {
+        Self {
+            buf: Vec::new(),
+            received: 0,
+            chunk: params.chunk_samples(),
+            overlap: params.overlap_samples(),
+            min_tail: params.min_tail_samples(),
+        }
+    }
+
+    pub fn push(&mut self, samples: &[i16]) {
+        self.buf.extend_from_slice(samples);
+        self.received += samples.len() as u64;
+    }
+
+    /// Samples waiting for the next full chunk.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// The leftover samples padded to a full chunk, if there are enough
+    /// (the params' minimum tail) to be worth recognizing. They stay
+    /// buffered and come back as part of the next full chunk.
+    pub fn tail(&self) -> Option<Chunk> {
+        if self.buf.len() < self.min_tail || self.buf.len() >= self.chunk {
+            return None;
+        }
+        let mut samples = self.buf.clone();
+        samples.resize(self.chunk, 0);
+        Some(Chunk {
+            samples,
+            end: self.received,
+        })
+    }
+}
+
+impl Default for ChunkIter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for ChunkIter {
+    type Item = Chunk;
+
+    fn next(&mut self) -> Option<Chunk> {
+        if self.buf.len() < self.chunk {
+            return None;
+        }
+        let samples = self.buf[..self.chunk].to_vec();
+        let end = self.received - self.buf.len() as u64 + self.chunk as u64;
+        self.buf.drain(..self.chunk - self.overlap);
+        Some(Chunk { samples, end })
+    }
+}
+
+// ── Recognition ──────────────────────────────────────────────────────────────
+
+/// Keywords lowercased and deduplicated, in their original order.
+pub fn unique_keywords<S: AsRef<str>>(keywords: &[S]) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for kw in keywords {
+        let lower = kw.as_ref().to_lowercase();
+        if !unique.contains(&lower) {
+            unique.push(lower);
+        }
+    }
+    unique
+}
+
+/// Recognizer restricted to `keywords` plus the unknown token. Keep the
+/// original spelling (accents included): the model only knows its own
+/// orthography. Accent-insensitive comparison happens in the keyword matchers.
+pub fn keyword_recognizer(model: &Model, keywords: &[String]) -> Result<Recognizer> {
+    let grammar: Vec<&str> = keywords
+        .iter()
+        .map(|s| s.as_str())
+        .chain(std::iter::once("[unk]"))
+        .collect();
+    Recognizer::new_with_grammar(model, SAMPLE_RATE as f32, &grammar)
+        .context("Failed to create Vosk recognizer")
+}
+
+/// Which recognizer output a keyword was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitSource {
+    Final,
+    Partial,
+    Tail,
+}
+
+impl HitSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HitSource::Final => "final",
+            HitSource::Partial => "partial",
+            HitSource::Tail => "tail",
+        }
+    }
+}
+
+/// What the recognizer made of one chunk.
+pub struct Recognized {
+    /// Final text if the recognizer finalized an utterance, otherwise the
+    /// partial (always empty for tails).
+    pub text: String,
+    pub source: HitSource,
+    pub keyword: Option<String>,
+}
+
+/// Feed one chunk to the recognizer and look for keywords under `matcher`.
+/// Final results use exact + fuzzy matching when `fuzzy` is set; partials
+/// change quickly and only ever match exactly. Tails only check final results.
+pub fn recognize_chunk(
+    rec: &mut Recognizer,
+    samples: &[i16],
+    keywords: &[&str],
+    matcher: &MatcherSettings,
+    fuzzy: bool,
+    tail: bool,
+) -> Recognized {
+    let is_speech = |text: &str| !text.is_empty() && text != "[unk]";
+    let state = rec.accept_waveform(samples);
+
+    if matches!(state, DecodingState::Finalized) {
+        let text = rec
+            .final_result()
+            .single()
+            .map(|r| r.text.to_string())
+            .unwrap_or_default();
+        let keyword = is_speech(&text)
+            .then(|| check_keywords(&text, keywords, matcher, fuzzy))
+            .flatten();
+        let source = if tail {
+            HitSource::Tail
+        } else {
+            HitSource::Final
+        };
+        Recognized {
+            text,
+            source,
+            keyword,
+        }
+    } else if tail {
+        Recognized {
+            text: String::new(),
+            source: HitSource::Tail,
+            keyword: None,
+        }
+    } else {
+        let text = rec.partial_result().partial.to_string();
+        let keyword = is_speech(&text)
+            .then(|| check_keywords(&text, keywords, matcher, false))
+            .flatten();
+        Recognized {
+            text,
+            source: HitSource::Partial,
+            keyword,
+        }
+    }
+}
+
+// ── Offline runs ─────────────────────────────────────────────────────────────
+
+/// How repeated hits on the same keyword are collapsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DedupStrategy {
+    /// Count every hit.
+    Off,
+    /// Drop a repeat of the last counted keyword heard within this many
+    /// seconds of audio.
+    Cooldown(f64),
+}
+
+impl DedupStrategy {
+    /// What the live detector uses.
+    pub const LIVE: DedupStrategy = DedupStrategy::Cooldown(DEDUP_COOLDOWN_SECS);
+
+    pub fn filter(self) -> MatchDedup {
+        match self {
+            DedupStrategy::Off => MatchDedup::new(0.0),
+            DedupStrategy::Cooldown(secs) => MatchDedup::new(secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OfflineOptions {
+    /// `None` feeds the audio to the recognizer untouched.
+    pub preprocess: Option<Preprocess>,
+    /// Allow fuzzy matches on final results, under `params.matcher`.
+    pub fuzzy: bool,
+    pub dedup: DedupStrategy,
+    /// Chunking and matching settings.
+    pub params: DetectorParams,
+}
+
+impl Default for OfflineOptions {
+    /// The live detector's settings.
+    fn default() -> Self {
+        Self {
+            preprocess: Some(Preprocess::DEFAULT),
+            fuzzy: true,
+            dedup: DedupStrategy::LIVE,
+            params: DetectorParams::DEFAULT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub keyword: String,
+    /// Audio position of the end of the chunk it was heard in.
+    pub at: u64,
+    pub source: HitSource,
+    pub duplicate: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DetectionReport {
+    pub hits: Vec<Hit>,
+}
+
+impl DetectionReport {
+    /// Counted (non-duplicate) hits on `keyword`, compared lowercased.
+    pub fn count(&self, keyword: &str) -> usize {
+        let keyword = keyword.to_lowercase();
+        self.hits
+            .iter()
+            .filter(|h| !h.duplicate && h.keyword == keyword)
+            .count()
+    }
+}
+
+/// Run recorded 16 kHz mono audio through the same chunking, recognition
+/// and dedup as the live detector, with the stream ending after `audio`.
+pub fn run_offline(
+    model: &Model,
+    audio: &[i16],
+    keywords: &[&str],
+    options: &OfflineOptions,
+) -> Result<DetectionReport> {
+    let keywords = unique_keywords(keywords);
+    let keyword_refs: Vec<&str> = keywords.iter().map(|s| s.as_str()).collect();
+    let mut rec = keyword_recognizer(model, &keywords)?;
+    let params = options.params.validated().map_err(anyhow::Error::msg)?;
+    let mut dedup = options.dedup.filter();
+    let mut report = DetectionReport::default();
+
+    let mut scan = |chunk: Chunk, tail: bool| {
+        let samples = match &options.preprocess {
+            Some(params) => params.apply(&chunk.samples),
+            None => chunk.samples,
+        };
+        let matcher = &params.matcher;
+        let recognized = recognize_chunk(
+            &mut rec,
+            &samples,
+            &keyword_refs,
+            matcher,
+            options.fuzzy,
+            tail,
+        );
+        if let Some(keyword) = recognized.keyword {
+            let duplicate = dedup.is_duplicate(&keyword, chunk.end);
+            report.hits.push(Hit {
+                keyword,
+                at: chunk.end,
+                source: recognized.source,
+                duplicate,
+            });
+        }
+    };
+
+    let mut chunks = ChunkIter::with_params(&params);
+    chunks.push(audio);
+    for chunk in chunks.by_ref() {
+        scan(chunk, false);
+    }
+    if let Some(tail) = chunks.tail() {
+        scan(tail, true);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNK_SAMPLES: usize = DetectorParams::DEFAULT.chunk_samples();
+    const OVERLAP_SAMPLES: usize = DetectorParams::DEFAULT.overlap_samples();
+    const MIN_TAIL_SAMPLES: usize = DetectorParams::DEFAULT.min_tail_samples();
+    const ADVANCE: usize = CHUNK_SAMPLES - OVERLAP_SAMPLES;
+
+    /// Samples numbered by their position, so chunks show where they came from.
+    fn ramp(len: usize) -> Vec<i16> {
+        (0..len).map(|i| (i % 30_000) as i16).collect()
+    }
+
+    #[test]
+    fn full_chunks_overlap_and_report_their_end() {
+        let audio = ramp(CHUNK_SAMPLES + 2 * ADVANCE);
+        let mut chunks = ChunkIter::new();
+        chunks.push(&audio);
+
+        let got: Vec<_> = chunks.by_ref().collect();
+        assert_eq!(got.len(), 3);
+        for (i, chunk) in got.iter().enumerate() {
+            let start = i * ADVANCE;
+            assert_eq!(chunk.samples, audio[start..start + CHUNK_SAMPLES]);
+            assert_eq!(chunk.end, (start + CHUNK_SAMPLES) as u64);
+        }
+        // The overlap of the last chunk is still waiting
+        assert_eq!(chunks.buffered(), OVERLAP_SAMPLES);
+    }
+
+    #[test]
+    fn pushing_in_pieces_gives_the_same_chunks() {
+        let audio = ramp(3 * CHUNK_SAMPLES);
+        let mut whole = ChunkIter::new();
+        whole.push(&audio);
+        let expected: Vec<_> = whole.collect();
+
+        let mut pieces = ChunkIter::new();
+        let mut got = Vec::new();
+        for piece in audio.chunks(1_600) {
+            pieces.push(piece);
+            got.extend(pieces.by_ref());
+        }
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn tail_is_padded_and_kept_until_a_full_chunk_forms() {
+        let mut chunks = ChunkIter::new();
+        chunks.push(&ramp(MIN_TAIL_SAMPLES - 1));
+        assert!(chunks.tail().is_none());
+
+        chunks.push(&[1]);
+        let tail = chunks.tail().unwrap();
+        assert_eq!(tail.samples.len(), CHUNK_SAMPLES);
+        assert_eq!(tail.end, MIN_TAIL_SAMPLES as u64);
+        assert!(tail.samples[MIN_TAIL_SAMPLES..].iter().all(|&s| s == 0));
+        assert_eq!(chunks.buffered(), MIN_TAIL_SAMPLES);
+
+        chunks.push(&ramp(CHUNK_SAMPLES - MIN_TAIL_SAMPLES));
+        assert!(chunks.tail().is_none());
+        assert!(chunks.next().is_some());
+    }
+
+    #[test]
+    fn keywords_are_lowercased_and_deduplicated_in_order() {
+        assert_eq!(
+            unique_keywords(&["Bomba", "olá", "BOMBA"]),
+            ["bomba", "olá"]
+        );
+    }
+}
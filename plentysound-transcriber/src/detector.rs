@@ -1,7 +1,9 @@
 use crate::audio::{
-    check_keywords_matched, check_keywords_exact, highpass_filter, normalize,
-    CHUNK_SAMPLES, MIN_TAIL_SAMPLES, OVERLAP_SAMPLES, SAMPLE_RATE,
+    interleave_planes, DetectorParams, MatchDedup, MatcherSettings, PowerMode, Preprocess,
+    SAMPLE_RATE,
 };
+use crate::capture::CaptureSlot;
+use crate::chunking::{keyword_recognizer, recognize_chunk, unique_keywords, ChunkIter, HitSource};
 use anyhow::{Context, Result};
 use biquad::Biquad;
 use pipewire::{
@@ -20,19 +22,21 @@ use pipewire::{
 };
 use std::cell::RefCell;
 use std::sync::mpsc;
-use vosk::{Model, Recognizer};
-
-/// PipeWire will likely deliver at this rate regardless of what we request.
-const PW_SAMPLE_RATE: u32 = 48_000;
-
-/// Number of channels PipeWire will likely deliver (stereo).
-const PW_CHANNELS: u32 = 2;
-
-/// Vosk expects this rate (matches SAMPLE_RATE from audio.rs = 16000).
-const VOSK_SAMPLE_RATE: u32 = SAMPLE_RATE as u32;
-
-/// Cooldown: ignore same keyword if detected again within this many seconds.
-const DEDUP_COOLDOWN_SECS: f64 = 3.0;
+use vosk::Model;
+
+/// What the detector reports for each keyword hit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DetectorEvent {
+    /// A keyword was recognized and passed the repeat cooldown, at the
+    /// given instant.
+    Matched(String, std::time::Instant),
+    /// The same keyword was recognized again within the cooldown and dropped.
+    Duplicate(String),
+    /// A debug capture hit its length limit and was closed.
+    CaptureSaved(std::path::PathBuf),
+    /// Writing a debug capture failed; the capture was dropped.
+    CaptureFailed(String),
+}
 
 /// Mix interleaved samples down to mono, then downsample with a low-pass
 /// anti-aliasing filter to avoid spectral aliasing that corrupts speech.
@@ -74,45 +78,68 @@ fn stereo_to_mono_and_downsample(samples: &[i16], channels: u32, src_rate: u32,
         .collect()
 }
 
+/// Decode the valid S16LE region of one buffer data (the whole mapping when
+/// the chunk reports no size).
+fn read_s16(data: &mut pipewire::spa::buffer::Data) -> Vec<i16> {
+    let offset = data.chunk().offset() as usize;
+    let size = data.chunk().size() as usize;
+    let Some(slice) = data.data() else {
+        return Vec::new();
+    };
+    let end = (offset + size).min(slice.len());
+    let valid = if size > 0 && end > offset {
+        &slice[offset..end]
+    } else {
+        slice
+    };
+    valid
+        .chunks_exact(2)
+        .map(|c| i16::from_le_bytes([c[0], c[1]]))
+        .collect()
+}
+
 /// Run the word detector loop.
 ///
 /// Captures audio from the given PipeWire node, preprocesses it (highpass
 /// filter + normalization), runs Vosk recognition with overlapping chunks,
-/// and calls `on_match` for each deduplicated keyword detection using
-/// exact + fuzzy (Jaro-Winkler) matching.
+/// and calls `on_event` for each keyword detection using exact + fuzzy
+/// (Jaro-Winkler) matching under `matcher`: `Matched` when it passes the repeat cooldown,
+/// `Duplicate` when the cooldown drops it. `power` sets how often audio is
+/// picked up and whether silence reaches Vosk at all.
 ///
-/// Returns when `stop_rx` receives a message or the channel is closed.
+/// Returns when `stop_rx` receives a message or the channel is closed. `log`
+/// gets what is worth keeping; `debug` gets the per-chunk detail.
+#[allow(clippy::too_many_arguments)]
 pub fn run_detector(
     model_path: &str,
     keywords: &[String],
     pw_target_node: u32,
+    preprocess: Preprocess,
+    matcher: MatcherSettings,
+    power: PowerMode,
+    capture: CaptureSlot,
     stop_rx: mpsc::Receiver<()>,
-    on_match: impl Fn(String) + Send + 'static,
+    on_event: impl Fn(DetectorEvent) + Send + 'static,
     log: impl Fn(&str) + 'static,
+    debug: impl Fn(&str) + 'static,
 ) -> Result<()> {
+    // PipeWire delivers `capture_rate`/`capture_channels` regardless of what
+    // we request; the recognizer gets SAMPLE_RATE mono.
+    let params = DetectorParams {
+        matcher,
+        ..DetectorParams::DEFAULT
+    };
     let log = std::sync::Arc::new(log);
     log(&format!("Loading Vosk model from: {}", model_path));
     let model = Model::new(model_path).context("Failed to load Vosk model")?;
     log("Vosk model loaded");
 
-    // Deduplicate keywords for grammar
-    let mut unique_keywords: Vec<String> = Vec::new();
-    for kw in keywords {
-        let lower = kw.to_lowercase();
-        if !unique_keywords.contains(&lower) {
-            unique_keywords.push(lower);
-        }
-    }
-
-    // Build grammar: unique keywords + unknown token
-    let grammar: Vec<&str> = unique_keywords
-        .iter()
-        .map(|s| s.as_str())
-        .chain(std::iter::once("[unk]"))
-        .collect();
-    log(&format!("Creating recognizer with grammar: {:?}", grammar));
-    let recognizer = Recognizer::new_with_grammar(&model, VOSK_SAMPLE_RATE as f32, &grammar)
-        .context("Failed to create Vosk recognizer")?;
+    let unique_keywords = unique_keywords(keywords);
+    log(&format!(
+        "Creating recognizer with keywords: {:?}",
+        unique_keywords
+    ));
+    let recognizer = keyword_recognizer(&model, &unique_keywords)?;
 
     // Set up PipeWire capture
     let mainloop = MainLoop::new(None)?;
@@ -122,7 +149,25 @@ pub fn run_detector(
     let target_str = pw_target_node.to_string();
     log(&format!(
         "PipeWire capture: node={}, {}Hz {}ch -> {}Hz mono, chunk={} overlap={} samples",
-        pw_target_node, PW_SAMPLE_RATE, PW_CHANNELS, VOSK_SAMPLE_RATE, CHUNK_SAMPLES, OVERLAP_SAMPLES
+        pw_target_node,
+        params.capture_rate,
+        params.capture_channels,
+        SAMPLE_RATE,
+        params.chunk_samples(),
+        params.overlap_samples()
+    ));
+    log(&format!(
+        "Preprocess: highpass={}Hz target_rms={} max_gain={} silence_rms={}",
+        preprocess.highpass_hz, preprocess.target_rms, preprocess.max_gain, preprocess.silence_rms
+    ));
+    log(&format!(
+        "Matcher: fuzzy_threshold={} min_fuzzy_len={} fold_accents={}",
+        matcher.fuzzy_threshold, matcher.min_fuzzy_len, matcher.fold_accents
+    ));
+    log(&format!(
+        "Power mode: {} (tick={}ms)",
+        power.name(),
+        power.tick().as_millis()
     ));
     let stream = Stream::new(
         &core,
@@ -145,23 +190,17 @@ pub fn run_detector(
         .process(move |stream, _: &mut ()| {
             if let Some(mut buf) = stream.dequeue_buffer() {
                 let datas = buf.datas_mut();
-                if let Some(data) = datas.first_mut() {
-                    let offset = data.chunk().offset() as usize;
-                    let size = data.chunk().size() as usize;
-                    if let Some(slice) = data.data() {
-                        let end = (offset + size).min(slice.len());
-                        let valid = if size > 0 && end > offset {
-                            &slice[offset..end]
-                        } else {
-                            slice
-                        };
-                        let samples: Vec<i16> = valid
-                            .chunks_exact(2)
-                            .map(|c| i16::from_le_bytes([c[0], c[1]]))
-                            .collect();
-                        audio_buf_pw.lock().unwrap().extend_from_slice(&samples);
-                    }
-                }
+                // Planar streams deliver one data per channel; with a single
+                // channel both layouts are the same
+                let samples = if datas.len() > 1 {
+                    let planes: Vec<Vec<i16>> = datas.iter_mut().map(read_s16).collect();
+                    interleave_planes(&planes)
+                } else if let Some(data) = datas.first_mut() {
+                    read_s16(data)
+                } else {
+                    return;
+                };
+                audio_buf_pw.lock().unwrap().extend_from_slice(&samples);
             }
         })
         .register()?;
@@ -169,8 +208,8 @@ pub fn run_detector(
     // Request S16LE stereo at PW native rate
     let mut audio_info = AudioInfoRaw::new();
     audio_info.set_format(AudioFormat::S16LE);
-    audio_info.set_rate(PW_SAMPLE_RATE);
-    audio_info.set_channels(PW_CHANNELS);
+    audio_info.set_rate(params.capture_rate);
+    audio_info.set_channels(params.capture_channels);
 
     let obj = Object {
         type_: SpaTypes::ObjectParamFormat.as_raw(),
@@ -202,13 +241,13 @@ pub fn run_detector(
 
     // Mutable state in RefCell (timer callback is Fn, not FnMut)
     let recognizer = RefCell::new(recognizer);
-    // Mono 16kHz buffer that accumulates converted samples for chunked processing
-    let mono_buf: RefCell<Vec<i16>> = RefCell::new(Vec::new());
+    // Converted 16kHz mono audio, cut into overlapping chunks
+    let chunks = RefCell::new(ChunkIter::with_params(&params));
     let chunk_count: RefCell<u64> = RefCell::new(0);
-    // Dedup: last keyword + timestamp
-    let last_match: RefCell<Option<(String, std::time::Instant)>> = RefCell::new(None);
-
-    let advance = CHUNK_SAMPLES - OVERLAP_SAMPLES;
+    // Chunks the gate kept from preprocessing and Vosk
+    let gated_count: RefCell<u64> = RefCell::new(0);
+    let dedup = RefCell::new(MatchDedup::default());
+    let gate = RefCell::new(power.silence_gate(&preprocess));
 
     // Timer callback: convert audio, preprocess in chunks, feed to Vosk
     let timer = mainloop.loop_().add_timer({
@@ -219,6 +258,12 @@ pub fn run_detector(
         let keyword_strs_owned: Vec<String> = unique_keywords.clone();
         move |_| {
             if stop_flag.load(std::sync::atomic::Ordering::SeqCst) {
+                log(&format!(
+                    "Detector stopping: mode={} chunks={} gated={}",
+                    power.name(),
+                    chunk_count.borrow(),
+                    gated_count.borrow()
+                ));
                 if let Some(ml) = mainloop_weak.upgrade() {
                     ml.quit();
                 }
@@ -234,125 +279,129 @@ pub fn run_detector(
                     return;
                 }
                 let raw: Vec<i16> = buf.drain(..).collect();
-                stereo_to_mono_and_downsample(&raw, PW_CHANNELS, PW_SAMPLE_RATE, VOSK_SAMPLE_RATE)
+                stereo_to_mono_and_downsample(
+                    &raw,
+                    params.capture_channels,
+                    params.capture_rate,
+                    SAMPLE_RATE as u32,
+                )
             };
 
-            // Append to mono accumulation buffer
-            let mut mbuf = mono_buf.borrow_mut();
-            mbuf.extend_from_slice(&new_mono);
+            let mut chunks = chunks.borrow_mut();
+            chunks.push(&new_mono);
 
-            // Process overlapping chunks: CHUNK_SAMPLES (1.5s) with OVERLAP_SAMPLES (0.75s)
-            while mbuf.len() >= CHUNK_SAMPLES {
-                let chunk: Vec<i16> = mbuf[..CHUNK_SAMPLES].to_vec();
-                mbuf.drain(..advance);
-
-                // Audio preprocessing: highpass filter + RMS normalization
-                let mut processed = highpass_filter(&chunk);
-                normalize(&mut processed);
-
-                // Compute RMS for logging
+            // Process overlapping chunks as cut by `params` (1.5s, 0.75s overlap),
+            // as many as the power mode allows per tick
+            let mut budget = power.chunks_per_tick();
+            while budget > 0 {
+                let Some(chunk) = chunks.next() else {
+                    break;
+                };
+                budget -= 1;
                 let current_count = {
                     let mut cc = chunk_count.borrow_mut();
                     *cc += 1;
                     *cc
                 };
 
+                // Once the gate closes silence costs nothing: it is neither
+                // preprocessed, captured nor sent to Vosk
+                if let Some(gate) = gate.borrow_mut().as_mut() {
+                    if !gate.admit(&chunk.samples) {
+                        *gated_count.borrow_mut() += 1;
+                        continue;
+                    }
+                }
+
+                // Audio preprocessing: highpass filter + RMS normalization
+                let processed = preprocess.apply(&chunk.samples);
+                capture_chunk(&capture, &processed, &on_event, log.as_ref());
+
+                // Compute RMS for logging
                 if current_count % 30 == 0 {
                     let sum_sq: f64 = processed.iter().map(|&s| (s as f64) * (s as f64)).sum();
                     let rms = (sum_sq / processed.len().max(1) as f64).sqrt();
-                    log(&format!(
-                        "Chunk {}: {} samples, RMS={:.0}, buf_remaining={}",
-                        current_count, processed.len(), rms, mbuf.len()
+                    debug(&format!(
+                        "Chunk chunk={} samples={} rms={:.0} buf={} mode={} gated={}",
+                        current_count,
+                        processed.len(),
+                        rms,
+                        chunks.buffered(),
+                        power.name(),
+                        gated_count.borrow()
                     ));
                 }
 
-                // Feed preprocessed chunk to Vosk
-                let mut rec = recognizer.borrow_mut();
-                let state = rec.accept_waveform(&processed);
-
-                if matches!(state, vosk::DecodingState::Finalized) {
-                    let text = rec
-                        .final_result()
-                        .single()
-                        .map(|r| r.text.to_string())
-                        .unwrap_or_default();
-
-                    if !text.is_empty() && text != "[unk]" {
-                        log(&format!("Vosk final: \"{}\"", text));
-
-                        // Use full matching (exact + fuzzy) on final results
-                        if let Some(keyword) = check_keywords_matched(&text, &keyword_refs) {
-                            try_emit_match(
-                                &keyword, &last_match, &on_match, log.as_ref(),
-                                "final",
-                            );
-                        }
-                    } else if current_count % 30 == 0 {
-                        log(&format!("Vosk final (silence): \"{}\"", text));
+                let recognized = recognize_chunk(
+                    &mut recognizer.borrow_mut(),
+                    &processed,
+                    &keyword_refs,
+                    &params.matcher,
+                    true,
+                    false,
+                );
+                let is_speech = !recognized.text.is_empty() && recognized.text != "[unk]";
+                match recognized.source {
+                    HitSource::Final if is_speech => {
+                        log(&format!("Vosk final: \"{}\"", recognized.text));
                     }
-                } else {
-                    // Check partial results for early detection
-                    let partial = rec.partial_result().partial.to_string();
-
-                    if !partial.is_empty() && partial != "[unk]" {
-                        if current_count % 15 == 0 {
-                            log(&format!("Vosk partial: \"{}\"", partial));
-                        }
-
-                        // Use exact-only matching on partials (avoids false positives
-                        // from rapidly changing partial text)
-                        if let Some(keyword) = check_keywords_exact(&partial, &keyword_refs) {
-                            try_emit_match(
-                                &keyword, &last_match, &on_match, log.as_ref(),
-                                "partial",
-                            );
-                        }
+                    HitSource::Final if current_count % 30 == 0 => {
+                        debug(&format!("Vosk final (silence): \"{}\"", recognized.text));
                     }
+                    HitSource::Partial if is_speech && current_count % 15 == 0 => {
+                        debug(&format!("Vosk partial: \"{}\"", recognized.text));
+                    }
+                    _ => {}
+                }
+                if let Some(keyword) = recognized.keyword {
+                    try_emit_match(
+                        &keyword,
+                        chunk.end,
+                        &dedup,
+                        &on_event,
+                        log.as_ref(),
+                        recognized.source.as_str(),
+                    );
                 }
-                drop(rec);
             }
 
             // Process tail: if there are leftover samples that haven't formed
             // a full chunk, pad with silence and feed to Vosk so words spoken
-            // near the end of a burst aren't lost.
-            if !mbuf.is_empty() && mbuf.len() >= MIN_TAIL_SAMPLES && mbuf.len() < CHUNK_SAMPLES {
-                let mut tail = mbuf.to_vec();
-                tail.resize(CHUNK_SAMPLES, 0);
-
-                let mut processed = highpass_filter(&tail);
-                normalize(&mut processed);
-
-                let mut rec = recognizer.borrow_mut();
-                let state = rec.accept_waveform(&processed);
-
-                if matches!(state, vosk::DecodingState::Finalized) {
-                    let text = rec
-                        .final_result()
-                        .single()
-                        .map(|r| r.text.to_string())
-                        .unwrap_or_default();
-
-                    if !text.is_empty() && text != "[unk]" {
-                        log(&format!("Vosk final (tail): \"{}\"", text));
-                        if let Some(keyword) = check_keywords_matched(&text, &keyword_refs) {
-                            try_emit_match(
-                                &keyword, &last_match, &on_match, log.as_ref(),
-                                "tail",
-                            );
-                        }
-                    }
+            // near the end of a burst aren't lost. The samples stay buffered
+            // and accumulate into a full chunk next time.
+            let tail = chunks.tail().filter(|tail| match gate.borrow().as_ref() {
+                Some(gate) => gate.hears(&tail.samples[..chunks.buffered()]),
+                None => true,
+            });
+            if let Some(tail) = tail {
+                let processed = preprocess.apply(&tail.samples);
+                let recognized = recognize_chunk(
+                    &mut recognizer.borrow_mut(),
+                    &processed,
+                    &keyword_refs,
+                    &params.matcher,
+                    true,
+                    true,
+                );
+                if !recognized.text.is_empty() && recognized.text != "[unk]" {
+                    log(&format!("Vosk final (tail): \"{}\"", recognized.text));
+                }
+                if let Some(keyword) = recognized.keyword {
+                    try_emit_match(
+                        &keyword,
+                        tail.end,
+                        &dedup,
+                        &on_event,
+                        log.as_ref(),
+                        recognized.source.as_str(),
+                    );
                 }
-                drop(rec);
-                // Don't drain — let it accumulate into a full chunk next time
             }
         }
     });
 
-    // Fire every 100ms
-    timer.update_timer(
-        Some(std::time::Duration::from_millis(100)),
-        Some(std::time::Duration::from_millis(100)),
-    );
+    // Fire every 100ms, or less often in efficiency mode
+    timer.update_timer(Some(power.tick()), Some(power.tick()));
 
     log("Detector mainloop starting");
     mainloop.run();
@@ -365,37 +414,159 @@ pub fn run_detector(
     Ok(())
 }
 
-/// Try to emit a keyword match, applying time-based deduplication.
+/// Append a chunk to the debug capture, if one is running, closing it once
+/// it is full or broken.
+fn capture_chunk(
+    capture: &CaptureSlot,
+    samples: &[i16],
+    on_event: &dyn Fn(DetectorEvent),
+    log: &dyn Fn(&str),
+) {
+    let mut slot = capture.lock().unwrap();
+    let Some(writer) = slot.as_mut() else {
+        return;
+    };
+    let done = match writer.push_chunk(samples) {
+        Ok(true) => return,
+        Ok(false) => slot.take().map(|w| w.finish()),
+        Err(e) => {
+            slot.take();
+            Some(Err(e))
+        }
+    };
+    match done {
+        Some(Ok(path)) => {
+            log(&format!("Capture full, saved {}", path.display()));
+            on_event(DetectorEvent::CaptureSaved(path));
+        }
+        Some(Err(e)) => {
+            log(&format!("Capture failed: {e:#}"));
+            on_event(DetectorEvent::CaptureFailed(format!("{e:#}")));
+        }
+        None => {}
+    }
+}
+
+/// Try to emit a keyword match heard in audio ending at sample `at`,
+/// applying audio-time deduplication.
 fn try_emit_match(
     keyword: &str,
-    last_match: &RefCell<Option<(String, std::time::Instant)>>,
-    on_match: &dyn Fn(String),
+    at: u64,
+    dedup: &RefCell<MatchDedup>,
+    on_event: &dyn Fn(DetectorEvent),
     log: &dyn Fn(&str),
     source: &str,
 ) {
-    let now = std::time::Instant::now();
-    let is_dup = {
-        let last = last_match.borrow();
-        if let Some((ref last_kw, ref last_time)) = *last {
-            last_kw == keyword && now.duration_since(*last_time).as_secs_f64() < DEDUP_COOLDOWN_SECS
-        } else {
-            false
-        }
-    };
+    let is_dup = dedup.borrow_mut().is_duplicate(keyword, at);
 
     log(&format!(
-        "Keyword matched ({}): \"{}\" (dup={})",
-        source, keyword, is_dup
+        "Keyword matched ({}): \"{}\" at {:.2}s (dup={})",
+        source,
+        keyword,
+        at as f64 / SAMPLE_RATE,
+        is_dup
     ));
 
-    if !is_dup {
-        on_match(keyword.to_string());
-        *last_match.borrow_mut() = Some((keyword.to_string(), now));
+    if is_dup {
+        on_event(DetectorEvent::Duplicate(keyword.to_string()));
+    } else {
+        on_event(DetectorEvent::Matched(
+            keyword.to_string(),
+            std::time::Instant::now(),
+        ));
     }
 }
 
-/// Check if the Vosk library is available/loadable.
+/// libvosk functions the detector ends up calling. With lazy binding a
+/// missing one only shows up as a crash in the detector thread.
+const VOSK_SYMBOLS: &[&str] = &[
+    "vosk_set_log_level",
+    "vosk_model_new",
+    "vosk_model_free",
+    "vosk_recognizer_new_grm",
+    "vosk_recognizer_accept_waveform_s",
+    "vosk_recognizer_partial_result",
+    "vosk_recognizer_final_result",
+    "vosk_recognizer_free",
+];
+
+/// Check that libvosk is loaded and exports everything the detector uses,
+/// so a broken install is reported up front instead of on first use.
 pub fn check_vosk_available() -> Result<()> {
+    check_symbols("libvosk.so", VOSK_SYMBOLS)?;
     vosk::set_log_level(vosk::LogLevel::Error);
     Ok(())
 }
+
+/// Check that every symbol in `symbols` resolves, either from what the
+/// process already loaded or from `library` opened by name.
+pub fn check_symbols(library: &str, symbols: &[&str]) -> Result<()> {
+    let names: Vec<std::ffi::CString> = symbols
+        .iter()
+        .map(|s| std::ffi::CString::new(*s))
+        .collect::<Result<_, _>>()
+        .context("Symbol name contains a NUL byte")?;
+    let missing = |handle: *mut libc::c_void| -> Vec<&str> {
+        symbols
+            .iter()
+            .zip(&names)
+            // SAFETY: `handle` is RTLD_DEFAULT or a live dlopen handle and
+            // `name` is NUL-terminated
+            .filter(|(_, name)| unsafe { libc::dlsym(handle, name.as_ptr()) }.is_null())
+            .map(|(symbol, _)| *symbol)
+            .collect()
+    };
+    if missing(libc::RTLD_DEFAULT).is_empty() {
+        return Ok(());
+    }
+
+    let c_library = std::ffi::CString::new(library).context("Library name contains a NUL byte")?;
+    // SAFETY: the name is NUL-terminated; the handle is never closed, as
+    // the detector goes on to use the library
+    let handle = unsafe { libc::dlopen(c_library.as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL) };
+    if handle.is_null() {
+        // SAFETY: dlerror returns null or a NUL-terminated message
+        let reason = unsafe { libc::dlerror() };
+        let reason = if reason.is_null() {
+            "not found".to_string()
+        } else {
+            unsafe { std::ffi::CStr::from_ptr(reason) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        anyhow::bail!("Cannot load {}: {}", library, reason);
+    }
+    let missing = missing(handle);
+    if !missing.is_empty() {
+        anyhow::bail!("{} lacks {}", library, missing.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn symbols_already_loaded_resolve() {
+        assert!(check_symbols("libc.so.6", &["malloc", "free"]).is_ok());
+    }
+
+    #[test]
+    fn missing_library_is_named_in_the_error() {
+        let err =
+            check_symbols("libplentysound-absent.so", &["plentysound_absent_fn"]).unwrap_err();
+        assert!(
+            err.to_string().contains("libplentysound-absent.so"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn missing_symbols_are_listed() {
+        let err = check_symbols("libc.so.6", &["malloc", "plentysound_absent_fn"]).unwrap_err();
+        assert_eq!(err.to_string(), "libc.so.6 lacks plentysound_absent_fn");
+    }
+}
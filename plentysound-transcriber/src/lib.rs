@@ -1,2 +1,4 @@
 pub mod audio;
+pub mod capture;
+pub mod chunking;
 pub mod detector;
@@ -1,13 +1,11 @@
-use plentysound_transcriber::audio::{
-    self, check_keywords_exact, check_keywords_matched, highpass_filter, normalize,
-    CHUNK_SAMPLES, MIN_TAIL_SAMPLES, OVERLAP_SAMPLES, SAMPLE_RATE,
-};
+use plentysound_transcriber::audio::{DetectorParams, MatcherSettings, Preprocess, SAMPLE_RATE};
+use plentysound_transcriber::chunking::{run_offline, DedupStrategy, OfflineOptions};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::sync::Mutex;
 use std::time::Instant;
-use vosk::{Model, Recognizer};
+use vosk::Model;
 
 
 // ── Manifest types ───────────────────────────────────────────────────────────
@@ -15,6 +13,28 @@ use vosk::{Model, Recognizer};
 #[derive(Deserialize)]
 struct Manifest {
     samples: Vec<SampleEntry>,
+    /// Matcher overrides per model directory name, laid out like the
+    /// daemon's `detector_matchers` config section.
+    #[serde(default)]
+    matchers: BTreeMap<String, MatcherOverride>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct MatcherOverride {
+    fuzzy_threshold: Option<f64>,
+    min_fuzzy_len: Option<usize>,
+    fold_accents: Option<bool>,
+}
+
+impl MatcherOverride {
+    fn apply(&self, base: MatcherSettings) -> MatcherSettings {
+        MatcherSettings {
+            fuzzy_threshold: self.fuzzy_threshold.unwrap_or(base.fuzzy_threshold),
+            min_fuzzy_len: self.min_fuzzy_len.unwrap_or(base.min_fuzzy_len),
+            fold_accents: self.fold_accents.unwrap_or(base.fold_accents),
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -49,31 +69,95 @@ struct KeywordResult {
 
 struct Strategy {
     name: &'static str,
-    cooldown: usize,
+    dedup: DedupStrategy,
 }
 
 const STRATEGIES: &[Strategy] = &[
-    Strategy { name: "no-dedup", cooldown: 0 },
-    Strategy { name: "consec", cooldown: 1 },
-    Strategy { name: "gap-2", cooldown: 2 },
-    Strategy { name: "gap-3", cooldown: 3 },
+    Strategy {
+        name: "no-dedup",
+        dedup: DedupStrategy::Off,
+    },
+    Strategy {
+        name: "cd-1.5s",
+        dedup: DedupStrategy::Cooldown(1.5),
+    },
+    Strategy {
+        name: "live",
+        dedup: DedupStrategy::LIVE,
+    },
 ];
 
 struct RecognitionVariant {
     name: &'static str,
-    preprocess: bool,
+    preprocess: Option<Preprocess>,
     use_fuzzy: bool,
 }
 
-const RECOGNITION_VARIANTS: &[RecognitionVariant] = &[
-    RecognitionVariant { name: "base", preprocess: false, use_fuzzy: false },
-    RecognitionVariant { name: "enh", preprocess: true, use_fuzzy: true },
+/// Preprocessing settings swept by the enhanced variants. Copy the winner
+/// into the `detector_preprocess` config section.
+const PREPROCESS_SWEEP: &[(&str, Preprocess)] = &[
+    ("enh", Preprocess::DEFAULT),
+    (
+        "enh-soft",
+        Preprocess {
+            target_rms: 2000.0,
+            max_gain: 3.0,
+            ..Preprocess::DEFAULT
+        },
+    ),
+    (
+        "enh-hot",
+        Preprocess {
+            highpass_hz: 120.0,
+            target_rms: 2500.0,
+            max_gain: 1.5,
+            ..Preprocess::DEFAULT
+        },
+    ),
 ];
 
+fn recognition_variants() -> Vec<RecognitionVariant> {
+    std::iter::once(RecognitionVariant {
+        name: "base",
+        preprocess: None,
+        use_fuzzy: false,
+    })
+    .chain(
+        PREPROCESS_SWEEP
+            .iter()
+            .map(|&(name, params)| RecognitionVariant {
+                name,
+                preprocess: Some(params),
+                use_fuzzy: true,
+            }),
+    )
+    .collect()
+}
+
 // ── Test ─────────────────────────────────────────────────────────────────────
 
 const ROUNDS: usize = 5;
 
+/// Chunking and matching settings every variant runs with: the defaults,
+/// with the matcher the daemon would pick for `model_path` plus any
+/// override for it in the manifest.
+fn params_for(model_path: &str, manifest: &Manifest) -> DetectorParams {
+    let model_name = Path::new(model_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut matcher = MatcherSettings::for_model(&model_name);
+    if let Some(overrides) = manifest.matchers.get(&model_name) {
+        matcher = overrides.apply(matcher);
+    }
+    DetectorParams {
+        matcher,
+        ..DetectorParams::DEFAULT
+    }
+    .validated()
+    .expect("Invalid matcher settings in manifest.toml")
+}
+
 #[test]
 fn accuracy_benchmark() {
     let samples_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/samples");
@@ -110,21 +194,37 @@ fn accuracy_benchmark() {
         }
     };
     let model = Model::new(&model_path).expect("Failed to load Vosk model");
+    let params = params_for(&model_path, &manifest);
 
     let mut results: Vec<KeywordResult> = Vec::new();
     let mut sample_timings: Vec<(String, std::time::Duration)> = Vec::new();
     let total_start = Instant::now();
 
-    let total_jobs = available.iter().map(|e| ROUNDS * STRATEGIES.len() * RECOGNITION_VARIANTS.len()).sum::<usize>();
-    eprintln!("Spawning {total_jobs} jobs across {} sample(s)...", available.len());
+    let recognition = recognition_variants();
+    let total_jobs = available
+        .iter()
+        .map(|_| ROUNDS * STRATEGIES.len() * recognition.len())
+        .sum::<usize>();
+    eprintln!(
+        "Spawning {total_jobs} jobs across {} sample(s)...",
+        available.len()
+    );
+    eprintln!(
+        "Params: chunk={}s overlap={}s min_tail={}s fuzzy={} min_fuzzy_len={} fold_accents={}",
+        params.chunk_secs,
+        params.overlap_secs,
+        params.min_tail_secs,
+        params.matcher.fuzzy_threshold,
+        params.matcher.min_fuzzy_len,
+        params.matcher.fold_accents
+    );
 
     for entry in &available {
         let wav_path = samples_dir.join(&entry.file);
         let pcm = read_wav_i16(&wav_path);
         let keyword_words: Vec<&str> = entry.keywords.iter().map(|k| k.word.as_str()).collect();
-        let chunks = chunk_audio(&pcm);
 
-        let num_combos = STRATEGIES.len() * RECOGNITION_VARIANTS.len();
+        let num_combos = STRATEGIES.len() * recognition.len();
 
         // round_counts[keyword_idx][combo_idx] -> Vec<usize>
         let round_counts: Vec<Vec<Mutex<Vec<usize>>>> = entry
@@ -143,10 +243,10 @@ fn accuracy_benchmark() {
         std::thread::scope(|s| {
             for _round in 0..ROUNDS {
                 for (si, strategy) in STRATEGIES.iter().enumerate() {
-                    for (ri, recog) in RECOGNITION_VARIANTS.iter().enumerate() {
-                        let combo_idx = si * RECOGNITION_VARIANTS.len() + ri;
+                    for (ri, recog) in recognition.iter().enumerate() {
+                        let combo_idx = si * recognition.len() + ri;
                         let model = &model;
-                        let chunks = &chunks;
+                        let pcm = &pcm;
                         let keyword_words = &keyword_words;
                         let keywords_meta = &entry.keywords;
                         let round_counts = &round_counts;
@@ -154,20 +254,20 @@ fn accuracy_benchmark() {
 
                         s.spawn(move || {
                             let start = Instant::now();
-                            let counts = run_variant(
-                                model,
-                                chunks,
-                                keyword_words,
-                                recog.preprocess,
-                                recog.use_fuzzy,
-                                strategy.cooldown,
-                            );
+                            let options = OfflineOptions {
+                                preprocess: recog.preprocess,
+                                fuzzy: recog.use_fuzzy,
+                                dedup: strategy.dedup,
+                                params,
+                            };
+                            let report = run_offline(model, pcm, keyword_words, &options)
+                                .expect("Failed to run detection");
                             let elapsed = start.elapsed();
 
                             variant_durations[combo_idx].lock().unwrap().push(elapsed);
 
                             for (ki, kw) in keywords_meta.iter().enumerate() {
-                                let val = *counts.get(&kw.word).unwrap_or(&0);
+                                let val = report.count(&kw.word);
                                 round_counts[ki][combo_idx].lock().unwrap().push(val);
                             }
                         });
@@ -181,8 +281,8 @@ fn accuracy_benchmark() {
         for (ki, kw) in entry.keywords.iter().enumerate() {
             let mut variants = Vec::new();
             for (si, strategy) in STRATEGIES.iter().enumerate() {
-                for (ri, recog) in RECOGNITION_VARIANTS.iter().enumerate() {
-                    let combo_idx = si * RECOGNITION_VARIANTS.len() + ri;
+                for (ri, recog) in recognition.iter().enumerate() {
+                    let combo_idx = si * recognition.len() + ri;
                     variants.push(VariantResult {
                         strategy: strategy.name.to_string(),
                         recognition: recog.name.to_string(),
@@ -218,112 +318,18 @@ fn read_wav_i16(path: &Path) -> Vec<i16> {
         "Expected {}Hz WAV, got {}Hz", SAMPLE_RATE as u32, spec.sample_rate);
 
     match spec.sample_format {
-        hound::SampleFormat::Int => {
-            reader.into_samples::<i16>()
-                .map(|s| s.expect("Failed to read sample"))
-                .collect()
-        }
-        hound::SampleFormat::Float => {
-            reader.into_samples::<f32>()
-                .map(|s| {
-                    let v = s.expect("Failed to read sample");
-                    (v * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16
-                })
-                .collect()
-        }
-    }
-}
-
-// ── Chunking ─────────────────────────────────────────────────────────────────
-
-fn chunk_audio(pcm: &[i16]) -> Vec<Vec<i16>> {
-    let mut chunks = Vec::new();
-    let advance = CHUNK_SAMPLES - OVERLAP_SAMPLES;
-    let mut offset = 0;
-
-    while offset + CHUNK_SAMPLES <= pcm.len() {
-        chunks.push(pcm[offset..offset + CHUNK_SAMPLES].to_vec());
-        offset += advance;
-    }
-
-    // Include trailing partial chunk if enough samples remain to be useful
-    if offset < pcm.len() && (pcm.len() - offset) >= MIN_TAIL_SAMPLES {
-        let mut tail = pcm[offset..].to_vec();
-        // Pad with silence to fill a full chunk
-        tail.resize(CHUNK_SAMPLES, 0);
-        chunks.push(tail);
-    }
-
-    chunks
-}
-
-// ── Generic variant runner ──────────────────────────────────────────────────
-
-fn run_variant(
-    model: &Model,
-    chunks: &[Vec<i16>],
-    keywords: &[&str],
-    preprocess: bool,
-    use_fuzzy: bool,
-    cooldown: usize,
-) -> HashMap<String, usize> {
-    let grammar: Vec<&str> = keywords.iter().copied().chain(std::iter::once("[unk]")).collect();
-    let mut recognizer = Recognizer::new_with_grammar(model, SAMPLE_RATE as f32, &grammar)
-        .expect("Failed to create recognizer");
-
-    let mut counts: HashMap<String, usize> = HashMap::new();
-    // Per-keyword: last chunk index where it was detected
-    let mut last_detected: HashMap<String, usize> = HashMap::new();
-
-    for (chunk_idx, chunk) in chunks.iter().enumerate() {
-        let audio: Vec<i16>;
-        let samples = if preprocess {
-            audio = {
-                let mut clean = highpass_filter(chunk);
-                normalize(&mut clean);
-                clean
-            };
-            &audio
-        } else {
-            chunk
-        };
-
-        recognizer.accept_waveform(samples);
-
-        let text = recognizer
-            .final_result()
-            .single()
-            .map(|r| r.text.to_string())
-            .unwrap_or_default();
-
-        let matched = if use_fuzzy {
-            check_keywords_matched(&text, keywords)
-        } else {
-            check_keywords_exact(&text, keywords)
-        };
-
-        if let Some(keyword) = matched {
-            // Dedup logic: only count if enough chunks have elapsed since last detection
-            let should_count = if cooldown == 0 {
-                // No dedup: always count
-                true
-            } else if let Some(&last_idx) = last_detected.get(&keyword) {
-                // Count only if current - last >= cooldown + 1
-                chunk_idx >= last_idx + cooldown + 1
-            } else {
-                // First detection of this keyword
-                true
-            };
-
-            if should_count {
-                *counts.entry(keyword.clone()).or_insert(0) += 1;
-                last_detected.insert(keyword, chunk_idx);
-            }
-        }
-
-        recognizer.reset();
+        hound::SampleFormat::Int => reader
+            .into_samples::<i16>()
+            .map(|s| s.expect("Failed to read sample"))
+            .collect(),
+        hound::SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .map(|s| {
+                let v = s.expect("Failed to read sample");
+                (v * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16
+            })
+            .collect(),
     }
-    counts
 }
 
 // ── Output table ─────────────────────────────────────────────────────────────
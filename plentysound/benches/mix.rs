@@ -0,0 +1,84 @@
+//! The playback callback's inner loop on a 1024-sample stereo block, flat
+//! and with the mid EQ on: the old loop (read position behind a `Mutex`,
+//! EQ branch per sample) against the current one (`mix_frames`, then the
+//! copy into buffer memory). Run with `cargo bench -p plentysound --bench mix`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use std::sync::atomic::AtomicU64;
+use std::sync::Mutex;
+
+// Built in from source like benches/pcm.rs; the unit tests that come along
+// when clippy checks this as a test go unused, and bring a second copy of
+// the baseline with them
+#[path = "../src/mix/baseline.rs"]
+#[cfg_attr(test, allow(clippy::duplicate_mod))]
+mod baseline;
+#[path = "../src/mix.rs"]
+#[allow(dead_code)]
+#[cfg_attr(test, allow(unused_imports))]
+mod mix;
+#[path = "../src/pcm.rs"]
+#[cfg_attr(test, allow(dead_code, unused_imports))]
+mod pcm;
+
+const CHANNELS: usize = 2;
+const SAMPLES: usize = 1024;
+const SAMPLE_RATE: u32 = 48000;
+
+fn inner_loop(c: &mut Criterion) {
+    let clip: Vec<f32> = (0..SAMPLES).map(|i| (i as f32 * 0.01).sin()).collect();
+    let mut storage = vec![0.0f32; SAMPLES];
+    for (name, boost) in [("flat", 1.0), ("eq", 1.5)] {
+        let offset = Mutex::new(0);
+        let rng = AtomicU64::new(0xDEADBEEFCAFE);
+        let biquad = baseline::compute_biquad(SAMPLE_RATE as f32, boost);
+        let mut eq_state = [[0.0; 4]; 8];
+        c.bench_function(&format!("old/{name}"), |b| {
+            b.iter(|| {
+                *offset.lock().unwrap() = 0;
+                baseline::process(
+                    black_box(&clip),
+                    &offset,
+                    &mut storage,
+                    CHANNELS,
+                    0.7,
+                    boost != 1.0,
+                    biquad,
+                    &mut eq_state,
+                    0.01,
+                    &rng,
+                )
+            })
+        });
+
+        let gains = mix::EqGains {
+            low: 1.0,
+            mid: boost,
+            high: 1.0,
+        };
+        let mut equalizer = mix::Equalizer::new(SAMPLE_RATE, gains);
+        let mut meter = mix::LevelMeter::new();
+        let mut block = vec![0.0f32; SAMPLES];
+        let mut noise = mix::Noise::new(mix::NoiseKind::White, 0xDEADBEEFCAFE);
+        c.bench_function(&format!("new/{name}"), |b| {
+            b.iter(|| {
+                block.copy_from_slice(black_box(&clip));
+                mix::mix_frames(
+                    &mut block,
+                    CHANNELS,
+                    0.7,
+                    None,
+                    &mut equalizer,
+                    false,
+                    &mut meter,
+                    || noise.next() * 0.01,
+                );
+                pcm::copy_f32(bytemuck::cast_slice_mut(&mut storage), &block)
+            })
+        });
+    }
+}
+
+criterion_group!(benches, inner_loop);
+criterion_main!(benches);
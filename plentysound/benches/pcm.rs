@@ -0,0 +1,58 @@
+//! Writing a 1024-sample stereo block into buffer memory, the way every
+//! playback callback does, for aligned and unaligned memory. Run with
+//! `cargo bench -p plentysound --bench pcm`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+// plentysound is a binary crate, so the module is built in from its source.
+// Its unit tests come along when clippy checks this as a test, unused
+#[path = "../src/pcm.rs"]
+#[cfg_attr(test, allow(dead_code, unused_imports))]
+mod pcm;
+
+const CHANNELS: usize = 2;
+const SAMPLES: usize = 1024;
+
+// `len` bytes of buffer memory starting `offset` bytes into an f32-aligned
+// allocation
+fn memory(storage: &mut [f32], offset: usize, len: usize) -> &mut [u8] {
+    &mut bytemuck::cast_slice_mut(storage)[offset..offset + len]
+}
+
+fn buffer_writes(c: &mut Criterion) {
+    let block: Vec<f32> = (0..SAMPLES).map(|i| (i as f32 * 0.01).sin()).collect();
+    let bytes = SAMPLES * std::mem::size_of::<f32>();
+    for (name, offset) in [("aligned", 0), ("unaligned", 1)] {
+        let mut storage = vec![0.0f32; SAMPLES + 1];
+        let out = memory(&mut storage, offset, bytes);
+        c.bench_function(&format!("interleaved/{name}"), |b| {
+            b.iter(|| pcm::copy_f32(out, black_box(&block)))
+        });
+
+        let mut left = vec![0.0f32; SAMPLES / CHANNELS + 1];
+        let mut right = vec![0.0f32; SAMPLES / CHANNELS + 1];
+        let plane = bytes / CHANNELS;
+        let mut planes = [
+            memory(&mut left, offset, plane),
+            memory(&mut right, offset, plane),
+        ];
+        c.bench_function(&format!("planar/{name}"), |b| {
+            b.iter(|| pcm::deinterleave(black_box(&block), CHANNELS, &mut planes))
+        });
+
+        let mut noise = 0.0f32;
+        c.bench_function(&format!("generated/{name}"), |b| {
+            b.iter(|| {
+                let samples = std::iter::repeat_with(|| {
+                    noise = 0.5 - noise;
+                    noise
+                });
+                pcm::fill_f32(out, samples)
+            })
+        });
+    }
+}
+
+criterion_group!(benches, buffer_writes);
+criterion_main!(benches);
@@ -1,74 +1,776 @@
-use crate::pipewire::{DeviceKind, PwCommand, PwEvent, PwSink};
-use crate::protocol::{ClientCommand, DaemonEvent, DaemonState, SinkInfo, SongInfo};
+use crate::log::LogRotation;
+use crate::pipewire::{
+    DeviceKind, LiveParams, PlaybackOptions, PwCommand, PwEvent, PwSink, StreamProperties,
+};
+use crate::protocol::{
+    ClientCommand, CommandResult, DaemonEvent, DaemonState, EqBand, EqGains, FinishReason,
+    FxSettings, HistoryStats, LogLevel, NoiseKind, SinkInfo, SongInfo, SortKey, StatusFile,
+    TagColor, COMFORT_NOISE_MAX, DEFAULT_SINK_ID, EQ_GAIN_MAX, FADE_SECS_MAX, SONG_PAGE_LIMIT,
+    SONG_VOLUME_MAX, VOLUME_MAX,
+};
+use crate::ring::Ring;
+use crate::sidechain::SidechainSettings;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
 
 #[cfg(feature = "transcriber")]
-use crate::protocol::{WordDetectorStatus, WordMapping};
+use crate::protocol::{
+    DetectorPowerMode, MappingAction, SongSelection, WordDetectorStatus, WordMapping, WordStats,
+};
+#[cfg(feature = "transcriber")]
+use plentysound_transcriber::{
+    audio::{MatcherSettings, PowerMode, Preprocess},
+    capture::{CaptureSlot, CaptureWriter, MAX_CAPTURE_SAMPLES},
+    detector::DetectorEvent,
+};
+
+const LOG: crate::log::Logger = crate::log::log_scope("daemon");
+#[cfg(feature = "transcriber")]
+const DETECTOR_LOG: crate::log::Logger = crate::log::log_scope("detector");
 
 #[derive(Debug, Clone)]
 pub struct Song {
     pub path: PathBuf,
     pub name: String,
+    /// Set once the background probe found no way to decode the file.
+    pub undecodable: bool,
+    /// The file wasn't found at load or at the last play. Kept so the song
+    /// comes back with the file, e.g. once a network share is mounted.
+    pub missing: bool,
+    pub tag_color: Option<String>,
+    /// Gain on top of the global volume.
+    pub volume: Option<f32>,
+    /// Seconds, once probed.
+    pub duration: Option<f64>,
+    /// Unix seconds; unknown for songs added before this was kept.
+    pub added: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
 struct Config {
-    songs: Vec<String>,
+    #[serde(deserialize_with = "saved_songs")]
+    songs: Vec<SavedSong>,
+    /// Per-song settings from before `songs` kept them, keyed by path; read
+    /// once and moved into `songs`.
+    #[serde(default, skip_serializing)]
+    song_tags: BTreeMap<String, String>,
+    #[serde(default, skip_serializing)]
+    song_volumes: BTreeMap<String, f32>,
+    #[serde(default, skip_serializing)]
+    song_durations: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing)]
+    song_added: BTreeMap<String, u64>,
+    /// Keep `songs` sorted by this, new ones included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    song_sort: Option<SortKey>,
     #[serde(default = "default_volume")]
     volume: f32,
     #[serde(default = "default_comfort_noise")]
     comfort_noise: f32,
-    #[serde(default = "default_eq_mid_boost")]
-    eq_mid_boost: f32,
+    #[serde(default)]
+    noise_kind: NoiseKind,
+    /// The single mid-boost knob from before the 3-band EQ; read once and
+    /// moved into `eq.mid`.
+    #[serde(default, skip_serializing)]
+    eq_mid_boost: Option<f32>,
+    #[serde(default)]
+    eq: EqGains,
+    /// Fade-in and fade-out applied to every clip.
+    #[serde(default)]
+    fade_secs: f32,
+    /// Soft-limit peaks so high volume and EQ settings don't clip.
+    #[serde(default)]
+    limiter: bool,
+    /// Length of the comfort-noise fade after a clip's last sample.
+    #[serde(default = "default_noise_tail_ms")]
+    noise_tail_ms: u32,
+    /// Keep comfort noise running on an injection target between clips.
+    #[serde(default)]
+    continuous_noise: bool,
+    #[serde(default)]
+    sidechain_enabled: bool,
+    /// Set volume up to 1.0 on the clip streams themselves, so system mixers
+    /// show it, rather than scaling samples.
+    #[serde(default)]
+    native_volume: bool,
+    /// Description of the mic to listen to; empty means the first
+    /// microphone, or the first input without one.
+    #[serde(default)]
+    sidechain_source: String,
+    /// Description of the output that also plays injected clips.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    monitor_sink: Option<String>,
+    /// The device songs play on, found again by name whenever devices change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selected_sink: Option<SavedSink>,
+    #[serde(default = "default_sidechain_threshold_db")]
+    sidechain_threshold_db: f32,
+    #[serde(default = "default_sidechain_reduction_db")]
+    sidechain_reduction_db: f32,
+    #[serde(default = "default_sidechain_hold_ms")]
+    sidechain_hold_ms: u32,
+    /// Where to keep a `StatusFile` JSON document for status bars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status_file: Option<PathBuf>,
+    /// Show a desktop notification when a heard word plays something.
+    #[serde(default)]
+    notifications: bool,
+    /// With `notifications`, also show one whenever a song starts.
+    #[serde(default)]
+    notify_on_play: bool,
+    /// Lines above this level stay out of the log.
+    #[serde(default)]
+    log_level: LogLevel,
+    #[serde(default)]
+    log_rotation: LogRotation,
+    /// The TUI asks before deleting a song or word binding.
+    #[serde(default = "default_enabled")]
+    confirm_delete: bool,
+    /// Song tags in the order `c` cycles through them; empty for the
+    /// built-in `theme::TAG_PALETTE`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tag_palette: Vec<TagColor>,
+    /// The first-run wizard was finished or skipped.
+    #[serde(default)]
+    setup_done: bool,
+    #[serde(default)]
+    playback_mode: PlaybackMode,
+    /// Extra PipeWire properties for clip streams, over the built-in ones.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    stream_properties: BTreeMap<String, String>,
+    #[serde(default)]
+    injection_hints: InjectionHints,
+    /// Replay the current song each time it ends.
+    #[serde(default)]
+    loop_enabled: bool,
+    #[serde(default)]
+    history_limits: HistoryLimits,
     #[cfg(feature = "transcriber")]
     #[serde(default)]
     word_mappings: Vec<WordMappingConfig>,
+    #[cfg(feature = "transcriber")]
+    #[serde(default)]
+    word_mappings_muted: bool,
+    #[cfg(feature = "transcriber")]
+    #[serde(default)]
+    detector_preprocess: PreprocessConfig,
+    /// Trade some detection latency for less CPU while nobody talks.
+    #[cfg(feature = "transcriber")]
+    #[serde(default)]
+    detector_power_mode: DetectorPowerMode,
+    /// Keyword matching overrides per model, keyed by model directory name.
+    #[cfg(feature = "transcriber")]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    detector_matchers: BTreeMap<String, MatcherConfig>,
+    /// Command that renders Speak phrases to WAV, split on whitespace with
+    /// `{out}` and `{text}` filled in, e.g.
+    /// `piper --model voice.onnx --output_file {out}` (text on stdin).
+    #[cfg(feature = "transcriber")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tts_command: Option<String>,
+    /// Address to serve the live Ogg/Opus stream on, e.g. `127.0.0.1:8930`.
+    #[cfg(feature = "live-stream")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    live_stream: Option<String>,
+}
+
+/// A library entry as the config keeps it. Only the path is written out
+/// until something else is set.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+struct SavedSong {
+    path: String,
+    /// Volume factor on top of the global volume.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    volume: Option<f32>,
+    /// Color tag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    /// Length in seconds, so the file isn't probed again at every start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<f64>,
+    /// When the song was added, in Unix seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    added: Option<u64>,
+}
+
+/// A `songs` entry as read: older configs list plain paths.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SongEntry {
+    Path(String),
+    Song(SavedSong),
 }
 
-fn default_volume() -> f32 { 1.0 }
-fn default_comfort_noise() -> f32 { 0.01 }
-fn default_eq_mid_boost() -> f32 { 1.5 }
+impl From<SongEntry> for SavedSong {
+    fn from(entry: SongEntry) -> Self {
+        match entry {
+            SongEntry::Path(path) => SavedSong {
+                path,
+                ..SavedSong::default()
+            },
+            SongEntry::Song(song) => song,
+        }
+    }
+}
+
+fn saved_songs<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<SavedSong>, D::Error> {
+    let entries = Vec::<SongEntry>::deserialize(d)?;
+    Ok(entries.into_iter().map(SavedSong::from).collect())
+}
+
+/// What a new Play does while a clip is still playing.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum PlaybackMode {
+    /// Cut the playing clip short.
+    #[default]
+    Interrupt,
+    /// Play the new clip once the current one is done.
+    Queue,
+}
+
+/// A device as the config remembers it: PipeWire's `node.name`, which
+/// survives restarts and re-plugging, and its kind (`Output` or `Input`).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct SavedSink {
+    name: String,
+    kind: String,
+}
+
+impl SavedSink {
+    fn of(sink: &PwSink) -> Self {
+        SavedSink {
+            name: sink.name.clone(),
+            kind: sink.kind.name().to_string(),
+        }
+    }
+}
+
+/// Where the saved device is in `sinks`, or the first output while it is
+/// missing.
+fn resolve_sink(sinks: &[PwSink], saved: &SavedSink) -> Option<usize> {
+    sinks
+        .iter()
+        .position(|s| s.name == saved.name && s.kind.name() == saved.kind)
+        .or_else(|| sinks.iter().position(|s| s.kind == DeviceKind::Output))
+}
+
+/// Named routing hints for clips injected into another app's capture, so
+/// echo-cancel and noise-suppression filters treat them as voice.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct InjectionHints {
+    /// Set `media.role=Communication`.
+    #[serde(default)]
+    communication_role: bool,
+    /// Set `filter.want=echo-cancel`.
+    #[serde(default)]
+    echo_cancel: bool,
+}
+
+/// Entries kept in each in-memory history before the oldest are dropped.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+struct HistoryLimits {
+    playback: usize,
+    detections: usize,
+}
+
+impl Default for HistoryLimits {
+    fn default() -> Self {
+        Self {
+            playback: 100,
+            detections: 1000,
+        }
+    }
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+fn default_comfort_noise() -> f32 {
+    0.01
+}
+fn default_noise_tail_ms() -> u32 {
+    50
+}
+fn default_sidechain_threshold_db() -> f32 {
+    -35.0
+}
+fn default_sidechain_reduction_db() -> f32 {
+    12.0
+}
+fn default_sidechain_hold_ms() -> u32 {
+    600
+}
+fn default_enabled() -> bool {
+    true
+}
 
 #[cfg(feature = "transcriber")]
 #[derive(Serialize, Deserialize, Clone)]
 struct WordMappingConfig {
     word: String,
-    song_path: String,
+    /// The single song from before bindings took several; read once and
+    /// moved into `song_paths`.
+    #[serde(default, skip_serializing)]
+    song_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    song_paths: Vec<String>,
+    #[serde(default, skip_serializing_if = "SongSelection::is_random")]
+    selection: SongSelection,
     #[serde(default)]
     source_description: String,
     #[serde(default)]
     output_description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    only_from_source: Option<String>,
+    #[serde(default)]
+    stats: WordStats,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(default, skip_serializing_if = "MappingAction::is_play_song")]
+    action: MappingAction,
+}
+
+/// Cleanup applied to captured audio before recognition. Lower
+/// `max_gain`/`target_rms` for hot mics that clip after normalization.
+#[cfg(feature = "transcriber")]
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+struct PreprocessConfig {
+    highpass_hz: f64,
+    target_rms: f64,
+    max_gain: f64,
+    silence_rms: f64,
+}
+
+#[cfg(feature = "transcriber")]
+impl Default for PreprocessConfig {
+    fn default() -> Self {
+        Self::from(Preprocess::DEFAULT)
+    }
+}
+
+#[cfg(feature = "transcriber")]
+impl From<Preprocess> for PreprocessConfig {
+    fn from(p: Preprocess) -> Self {
+        Self {
+            highpass_hz: p.highpass_hz,
+            target_rms: p.target_rms,
+            max_gain: p.max_gain,
+            silence_rms: p.silence_rms,
+        }
+    }
+}
+
+#[cfg(feature = "transcriber")]
+impl PreprocessConfig {
+    /// Detector settings, with negative or non-finite values reset to defaults.
+    fn to_preprocess(self) -> Preprocess {
+        let fix = |v: f64, default: f64| {
+            if v.is_finite() && v >= 0.0 {
+                v
+            } else {
+                default
+            }
+        };
+        let d = Preprocess::DEFAULT;
+        Preprocess {
+            highpass_hz: fix(self.highpass_hz, d.highpass_hz),
+            target_rms: fix(self.target_rms, d.target_rms),
+            max_gain: fix(self.max_gain, d.max_gain),
+            silence_rms: fix(self.silence_rms, d.silence_rms),
+        }
+    }
+}
+
+#[cfg(feature = "transcriber")]
+impl From<DetectorPowerMode> for PowerMode {
+    fn from(mode: DetectorPowerMode) -> Self {
+        match mode {
+            DetectorPowerMode::Performance => PowerMode::Performance,
+            DetectorPowerMode::Balanced => PowerMode::Balanced,
+            DetectorPowerMode::Efficiency => PowerMode::Efficiency,
+        }
+    }
+}
+
+/// Overrides for one model's keyword matching; unset fields keep the
+/// defaults `MatcherSettings::for_model` picks for it.
+#[cfg(feature = "transcriber")]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(default)]
+struct MatcherConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fuzzy_threshold: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_fuzzy_len: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fold_accents: Option<bool>,
+}
+
+#[cfg(feature = "transcriber")]
+impl MatcherConfig {
+    /// `base` with the set fields applied; an out-of-range threshold keeps
+    /// `base`'s.
+    fn apply(self, base: MatcherSettings) -> MatcherSettings {
+        let threshold = self.fuzzy_threshold.filter(|t| *t > 0.0 && *t <= 1.0);
+        MatcherSettings {
+            fuzzy_threshold: threshold.unwrap_or(base.fuzzy_threshold),
+            min_fuzzy_len: self.min_fuzzy_len.unwrap_or(base.min_fuzzy_len),
+            fold_accents: self.fold_accents.unwrap_or(base.fold_accents),
+        }
+    }
+}
+
+/// The enabled binding that handles `word` heard on input `source`: one
+/// restricted to that input wins over the first unrestricted one.
+#[cfg(feature = "transcriber")]
+fn resolve_binding(mappings: &[WordMapping], word: &str, source: Option<&str>) -> Option<usize> {
+    use plentysound_transcriber::audio::normalize_text;
+
+    let word = normalize_text(word);
+    let candidates = || {
+        mappings
+            .iter()
+            .enumerate()
+            .filter(|(_, wm)| wm.enabled && normalize_text(&wm.word) == word)
+    };
+    candidates()
+        .find(|(_, wm)| source.is_some() && wm.only_from_source.as_deref() == source)
+        .or_else(|| candidates().find(|(_, wm)| wm.only_from_source.is_none()))
+        .map(|(idx, _)| idx)
+}
+
+/// Matching settings for the model in directory `model`: its defaults with
+/// any configured overrides applied.
+#[cfg(feature = "transcriber")]
+fn matcher_for(matchers: &BTreeMap<String, MatcherConfig>, model: &str) -> MatcherSettings {
+    let base = MatcherSettings::for_model(model);
+    matchers
+        .get(model)
+        .map_or(base, |overrides| overrides.apply(base))
 }
 
 impl Config {
     fn path() -> PathBuf {
-        let mut p = dirs_fallback_config_dir();
-        p.push("plentysound");
-        p.push("config.yaml");
-        p
+        crate::protocol::instance()
+            .dir_in(dirs_fallback_config_dir())
+            .join("config.yaml")
     }
 
-    fn load() -> Self {
-        let path = Self::path();
-        std::fs::read_to_string(&path)
+    fn load(path: &Path) -> Self {
+        let mut config: Self = std::fs::read_to_string(path)
             .ok()
             .and_then(|s| serde_yaml::from_str(&s).ok())
-            .unwrap_or_default()
+            .unwrap_or_default();
+        config.sanitize();
+        config
+    }
+
+    /// Like `load`, but a missing or unparsable file is an error rather than
+    /// the defaults, so reloading a half-saved edit can't wipe the library.
+    fn reload(path: &Path) -> Result<Self, String> {
+        let yaml = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut config: Self = serde_yaml::from_str(&yaml).map_err(|e| e.to_string())?;
+        config.sanitize();
+        Ok(config)
+    }
+
+    /// Replace non-finite values left by older versions and clamp the rest.
+    fn sanitize(&mut self) {
+        let fix = |v: f32, default: f32, max: f32| {
+            if v.is_finite() {
+                v.clamp(0.0, max)
+            } else {
+                default
+            }
+        };
+        self.volume = fix(self.volume, default_volume(), VOLUME_MAX);
+        self.comfort_noise = fix(
+            self.comfort_noise,
+            default_comfort_noise(),
+            COMFORT_NOISE_MAX,
+        );
+        if let Some(mid) = self.eq_mid_boost.take() {
+            self.eq.mid = mid;
+        }
+        let mut tags = std::mem::take(&mut self.song_tags);
+        let mut volumes = std::mem::take(&mut self.song_volumes);
+        let mut durations = std::mem::take(&mut self.song_durations);
+        let mut added = std::mem::take(&mut self.song_added);
+        for song in &mut self.songs {
+            song.tag = song.tag.take().or_else(|| tags.remove(&song.path));
+            song.volume = song.volume.or_else(|| volumes.remove(&song.path));
+            song.duration = song.duration.or_else(|| durations.remove(&song.path));
+            song.added = song.added.or_else(|| added.remove(&song.path));
+        }
+        #[cfg(feature = "transcriber")]
+        for wm in &mut self.word_mappings {
+            if let Some(path) = wm.song_path.take().filter(|p| !p.is_empty()) {
+                if wm.song_paths.is_empty() {
+                    wm.song_paths.push(path);
+                }
+            }
+        }
+        for band in EqBand::ALL {
+            let gain = self.eq.get_mut(band);
+            *gain = fix(*gain, EqGains::default().get(band), EQ_GAIN_MAX);
+        }
+        self.fade_secs = fix(self.fade_secs, 0.0, FADE_SECS_MAX);
+        for volume in self.songs.iter_mut().filter_map(|s| s.volume.as_mut()) {
+            *volume = fix(*volume, 1.0, SONG_VOLUME_MAX);
+        }
+        self.noise_tail_ms = self.noise_tail_ms.min(NOISE_TAIL_MS_MAX);
+        // Tags that can't be drawn or repeat an earlier name are left out
+        let mut names = HashSet::new();
+        self.tag_palette.retain(|tag| {
+            let usable = !tag.name.is_empty()
+                && tag.name.len() <= MAX_TAG_LEN
+                && tag.color.parse::<ratatui::style::Color>().is_ok()
+                && names.insert(tag.name.clone());
+            if !usable {
+                LOG.warn(&format!(
+                    "Ignoring tag '{}' with color '{}'",
+                    tag.name, tag.color
+                ));
+            }
+            usable
+        });
+        self.history_limits.playback = self.history_limits.playback.min(HISTORY_LIMIT_MAX);
+        self.history_limits.detections = self.history_limits.detections.min(HISTORY_LIMIT_MAX);
+        if !self.sidechain_threshold_db.is_finite() {
+            self.sidechain_threshold_db = default_sidechain_threshold_db();
+        }
+        if !self.sidechain_reduction_db.is_finite() {
+            self.sidechain_reduction_db = default_sidechain_reduction_db();
+        }
     }
 
-    fn save(&self) {
-        let path = Self::path();
+    fn save(&self, path: &Path) {
         if let Some(parent) = path.parent() {
             let _ = std::fs::create_dir_all(parent);
         }
         if let Ok(yaml) = serde_yaml::to_string(self) {
-            let _ = std::fs::write(&path, yaml);
+            let _ = std::fs::write(path, yaml);
         }
     }
 }
 
+/// What the probe thread learned about a song file.
+enum Probe {
+    Undecodable(String),
+    /// Length in seconds.
+    Duration(f64),
+}
+
+/// What the daemon was doing, kept in `state.yaml` next to the config so a
+/// restart picks up where it left off. Unlike the config it records no
+/// intent: anything that no longer resolves is skipped, and the file is safe
+/// to delete.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+struct RuntimeState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selected_song: Option<PathBuf>,
+    /// Songs waiting to play, next first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    queue: Vec<PathBuf>,
+    /// The word detector was stopped by hand, so it isn't started on its own.
+    #[cfg(feature = "transcriber")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    detector_stopped: bool,
+}
+
+impl RuntimeState {
+    fn path(config_path: &Path) -> PathBuf {
+        config_path.with_file_name("state.yaml")
+    }
+
+    fn load(path: &Path) -> Self {
+        let Ok(yaml) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_yaml::from_str(&yaml).unwrap_or_else(|e| {
+            LOG.error(&format!("Ignoring {}: {e}", path.display()));
+            Self::default()
+        })
+    }
+
+    /// Written aside and renamed, so a crash mid-write leaves the old state.
+    fn save(&self, path: &Path) {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let written = serde_yaml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|yaml| {
+                let mut tmp = path.as_os_str().to_owned();
+                tmp.push(".tmp");
+                std::fs::write(&tmp, yaml)
+                    .and_then(|()| std::fs::rename(&tmp, path))
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = written {
+            LOG.error(&format!("Failed to save {}: {e}", path.display()));
+        }
+    }
+}
+
+/// Validate a numeric setting from a client: non-finite values are rejected,
+/// anything else is clamped to `0.0..=max` like the TUI does.
+fn checked_setting(name: &str, v: f32, max: f32) -> Result<f32, CommandResult> {
+    if v.is_finite() {
+        Ok(v.clamp(0.0, max))
+    } else {
+        Err(CommandResult::Error(format!("Invalid {name}: {v}")))
+    }
+}
+
+/// Backoff bounds for re-enumerating devices while the list is empty.
+const SINK_RETRY_MIN: std::time::Duration = std::time::Duration::from_secs(1);
+const SINK_RETRY_MAX: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Upper bound for `noise_tail_ms`; anything longer is a second clip of noise.
+const NOISE_TAIL_MS_MAX: u32 = 500;
+
+/// Upper bound for each `history_limits` entry.
+const HISTORY_LIMIT_MAX: usize = 100_000;
+
+/// Longest tag name accepted from a client.
+const MAX_TAG_LEN: usize = 32;
+
+/// How often every song's file is looked for again, so songs deleted or
+/// brought back while the daemon runs are flagged before anyone plays them.
+const PRESENCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// What `Play` and `Enqueue` answer while PipeWire reports no devices.
+fn no_devices_error() -> DaemonEvent {
+    DaemonEvent::Error {
+        context: "playback".to_string(),
+        message: "no audio devices found".to_string(),
+    }
+}
+
+/// Serve the live stream on `address`, logging why if it can't.
+#[cfg(feature = "live-stream")]
+fn start_live_stream(address: &str) -> Option<crate::livestream::LiveServer> {
+    match crate::livestream::LiveServer::start(address) {
+        Ok(server) => {
+            LOG.info(&format!("Live stream at {}", server.url()));
+            Some(server)
+        }
+        Err(e) => {
+            LOG.error(&format!("{e:#}"));
+            None
+        }
+    }
+}
+
+fn checked_index(what: &str, idx: usize, len: usize) -> Result<usize, CommandResult> {
+    if idx < len {
+        Ok(idx)
+    } else {
+        Err(CommandResult::Error(format!(
+            "No {what} at index {idx} ({len} available)"
+        )))
+    }
+}
+
+fn compare_songs(key: SortKey, a: &Song, b: &Song) -> std::cmp::Ordering {
+    match key {
+        SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        // Newest first; songs without a date go last
+        SortKey::Recent => b.added.cmp(&a.added),
+        // Shortest first; unknown lengths go last
+        SortKey::Duration => match (a.duration, b.duration) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        },
+    }
+}
+
+/// Where the item at `idx` ends up once the one at `from` is moved to `to`.
+fn moved_index(idx: usize, from: usize, to: usize) -> usize {
+    if idx == from {
+        to
+    } else if from < idx && idx <= to {
+        idx - 1
+    } else if to <= idx && idx < from {
+        idx + 1
+    } else {
+        idx
+    }
+}
+
+/// The library's resolved paths and content fingerprints, each with the
+/// name of the first song they belong to. Worked out once per batch of
+/// additions rather than once per added file.
+struct LibraryIndex {
+    paths: HashMap<PathBuf, String>,
+    fingerprints: HashMap<(u64, u64), String>,
+}
+
+impl LibraryIndex {
+    fn new(songs: &[Song]) -> Self {
+        let mut index = LibraryIndex {
+            paths: HashMap::new(),
+            fingerprints: HashMap::new(),
+        };
+        for song in songs {
+            if let Ok(canonical) = std::fs::canonicalize(&song.path) {
+                let fingerprint = content_fingerprint(&canonical);
+                index.insert(canonical, fingerprint, &song.name);
+            }
+        }
+        index
+    }
+
+    fn insert(&mut self, canonical: PathBuf, fingerprint: Option<(u64, u64)>, name: &str) {
+        if let Some(fingerprint) = fingerprint {
+            self.fingerprints
+                .entry(fingerprint)
+                .or_insert_with(|| name.to_string());
+        }
+        self.paths
+            .entry(canonical)
+            .or_insert_with(|| name.to_string());
+    }
+}
+
+/// Cheap content identity: file size plus a hash of the first 64 KB.
+fn content_fingerprint(path: &Path) -> Option<(u64, u64)> {
+    const PREFIX_LEN: u64 = 64 * 1024;
+    let file = std::fs::File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    let mut prefix = Vec::new();
+    file.take(PREFIX_LEN).read_to_end(&mut prefix).ok()?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    prefix.hash(&mut hasher);
+    Some((size, hasher.finish()))
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// An index below `len` (0 for an empty range), from std's randomly keyed
+/// hasher.
+#[cfg(feature = "transcriber")]
+fn random_index(len: usize) -> usize {
+    use std::hash::BuildHasher;
+    let seed =
+        std::collections::hash_map::RandomState::new().hash_one(std::time::SystemTime::now());
+    (seed % len.max(1) as u64) as usize
+}
+
 fn dirs_fallback_config_dir() -> PathBuf {
     if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME") {
         PathBuf::from(dir)
@@ -79,25 +781,209 @@ fn dirs_fallback_config_dir() -> PathBuf {
     }
 }
 
+/// Timestamps of a word trigger on its way to the first audio buffer.
+#[cfg(feature = "transcriber")]
+struct TriggerTiming {
+    word: String,
+    matched: std::time::Instant,
+    applied: std::time::Instant,
+    decoded: Option<std::time::Instant>,
+    /// The playback carrying the trigger, once it was sent.
+    id: Option<u64>,
+}
+
+/// One ended playback in the playback history.
+#[derive(Serialize, Debug, Clone)]
+struct PlayedSong {
+    song: String,
+    reason: FinishReason,
+    /// Unix seconds.
+    ended_at: u64,
+}
+
+/// One recognized word that played its song, in the detection history.
+#[cfg(feature = "transcriber")]
+#[derive(Serialize, Debug, Clone)]
+struct DetectedWord {
+    word: String,
+    simulated: bool,
+    /// Input the binding that handled it is restricted to; unset when an
+    /// unrestricted binding did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    only_from_source: Option<String>,
+    /// Unix seconds.
+    at: u64,
+}
+
+/// A song or phrase sent to the backend. Its playback holds it until it
+/// finishes, so loop mode can replay it; each replay streams the file again.
+struct Clip {
+    path: PathBuf,
+    name: String,
+    /// The song's volume factor when it was started.
+    volume: f32,
+    sink: PwSink,
+}
+
 pub struct DaemonApp {
     pub sinks: Vec<PwSink>,
     pub selected_sink: usize,
     pub songs: Vec<Song>,
     pub selected_song: usize,
+    pub songs_revision: u64,
     pub volume: f32,
+    native_volume: bool,
     pub comfort_noise: f32,
-    pub eq_mid_boost: f32,
+    pub noise_kind: NoiseKind,
+    pub eq: EqGains,
+    pub fade_secs: f32,
+    pub limiter: bool,
+    noise_tail_ms: u32,
     pub now_playing: Option<String>,
+    pub paused: bool,
+    playback_mode: PlaybackMode,
+    stream_properties: BTreeMap<String, String>,
+    injection_hints: InjectionHints,
+    pub song_sort: Option<SortKey>,
+    pub loop_enabled: bool,
+    /// Plays sent to the backend and not finished yet with their clips,
+    /// oldest (the one playing) first.
+    pending_playbacks: VecDeque<(u64, Arc<Clip>)>,
+    next_playback_id: u64,
+    /// Clips that failed to decode since the last `process_pw_events`,
+    /// which reports them.
+    decode_errors: Vec<String>,
+    /// Songs waiting for the pending playbacks to end, next first.
+    queue: VecDeque<PathBuf>,
+    history_limits: HistoryLimits,
+    /// Recently ended playbacks, oldest first.
+    playback_history: Ring<PlayedSong>,
+    /// Recent word triggers, oldest first.
+    #[cfg(feature = "transcriber")]
+    detection_history: Ring<DetectedWord>,
     pub pw_cmd_tx: Sender<PwCommand>,
     pub pw_evt_rx: Receiver<PwEvent>,
+    /// The PipeWire thread, joined on shutdown. `None` with a test backend.
+    pw_thread: Option<std::thread::JoinHandle<()>>,
+    config_path: PathBuf,
+    /// Where `RuntimeState` lives, next to the config.
+    state_path: PathBuf,
+    /// Last runtime state saved and when, for change detection and
+    /// throttling.
+    state_written: Option<(RuntimeState, std::time::Instant)>,
+    /// The device picked last, selected again whenever it is connected.
+    saved_sink: Option<SavedSink>,
+    /// `node.name` of the desktop's default output.
+    default_sink: Option<String>,
+    /// Songs the probe thread could not decode, with the reason.
+    probe_tx: Sender<(PathBuf, Probe)>,
+    probe_rx: Receiver<(PathBuf, Probe)>,
+    /// Finished waveform workers: the song and its envelope, or why it failed.
+    waveform_tx: Sender<(PathBuf, anyhow::Result<Vec<u8>>)>,
+    waveform_rx: Receiver<(PathBuf, anyhow::Result<Vec<u8>>)>,
+    /// Songs a waveform worker is running for, with the clients that asked,
+    /// so repeated asks share it and a failure goes only to them.
+    waveforms_pending: HashMap<PathBuf, Vec<Option<u64>>>,
+    /// Where envelopes are cached; tests point it at their own scratch
+    /// directory.
+    waveform_cache: PathBuf,
+    /// Songs a presence check found gone or back, with whether the file is
+    /// there now.
+    presence_tx: Sender<Vec<(PathBuf, bool)>>,
+    presence_rx: Receiver<Vec<(PathBuf, bool)>>,
+    /// When the next presence check starts; `None` while one runs.
+    presence_due: Option<std::time::Instant>,
+    pub continuous_noise: bool,
+    pub sidechain_enabled: bool,
+    pub sidechain_source: String,
+    /// Description of the output that also plays injected clips.
+    monitor_sink: Option<String>,
+    pub sidechain_settings: SidechainSettings,
+    pub sidechain_stop_tx: Option<Sender<()>>,
+    /// The last sidechain started; stopped ones end on their own.
+    sidechain_thread: Option<std::thread::JoinHandle<()>>,
+    /// When to ask for devices again, and the current backoff, while none
+    /// are known.
+    sink_retry: Option<(std::time::Instant, std::time::Duration)>,
+    pub status_file: Option<PathBuf>,
+    /// Last status written (with `ts` zeroed) and when, for change detection
+    /// and throttling.
+    status_written: Option<(StatusFile, std::time::Instant)>,
+    pub notifications: bool,
+    pub notify_on_play: bool,
+    /// The config's `log_level`; `SetLogLevel` changes only the running one.
+    pub log_level: LogLevel,
+    pub log_rotation: LogRotation,
+    pub confirm_delete: bool,
+    tag_palette: Vec<TagColor>,
+    pub last_detected_word: Option<String>,
+    /// No config file existed at load and the wizard hasn't been dismissed.
+    pub first_run: bool,
     #[cfg(feature = "transcriber")]
     pub word_mappings: Vec<WordMapping>,
     #[cfg(feature = "transcriber")]
+    pub word_mappings_muted: bool,
+    /// Index into `song_paths` each binding played last, for the next
+    /// pick, in `word_mappings` order. Bindings past its end haven't
+    /// picked yet.
+    #[cfg(feature = "transcriber")]
+    song_picks: Vec<Option<usize>>,
+    #[cfg(feature = "transcriber")]
     pub word_detector_status: WordDetectorStatus,
+    /// Set by `StopWordDetector`, cleared by `StartWordDetector`; keeps the
+    /// detector from auto-starting in between.
+    #[cfg(feature = "transcriber")]
+    detector_stopped: bool,
+    /// Why the startup check couldn't use libvosk, if it couldn't.
+    #[cfg(feature = "transcriber")]
+    vosk_missing: Option<String>,
+    /// Raised to stop the running model download; each download gets a fresh flag.
+    #[cfg(feature = "transcriber")]
+    pub download_cancel: std::sync::Arc<std::sync::atomic::AtomicBool>,
     #[cfg(feature = "transcriber")]
     pub detector_stop_tx: Option<std::sync::mpsc::Sender<()>>,
+    /// The last detector started, joined on shutdown.
+    #[cfg(feature = "transcriber")]
+    detector_thread: Option<std::thread::JoinHandle<()>>,
+    #[cfg(feature = "transcriber")]
+    pub detector_match_rx: Option<std::sync::mpsc::Receiver<DetectorEvent>>,
+    /// The word trigger waiting for its first audio; plays are only timed
+    /// while one is in flight.
+    #[cfg(feature = "transcriber")]
+    trigger_timing: Option<TriggerTiming>,
+    #[cfg(feature = "transcriber")]
+    pub detector_source: Option<String>,
+    /// Input node the running detector listens to.
+    #[cfg(feature = "transcriber")]
+    detector_node: Option<u32>,
+    #[cfg(feature = "transcriber")]
+    detector_capture: CaptureSlot,
+    #[cfg(feature = "transcriber")]
+    word_stats_dirty: std::cell::Cell<bool>,
+    #[cfg(feature = "transcriber")]
+    word_stats_saved_at: std::cell::Cell<std::time::Instant>,
+    #[cfg(feature = "transcriber")]
+    pub detector_preprocess: Preprocess,
+    #[cfg(feature = "transcriber")]
+    detector_power_mode: DetectorPowerMode,
+    #[cfg(feature = "transcriber")]
+    detector_matchers: BTreeMap<String, MatcherConfig>,
+    #[cfg(feature = "transcriber")]
+    tts_command: Option<String>,
+    /// Where synthesized phrases are cached; tests point it at their own
+    /// scratch directory.
+    #[cfg(feature = "transcriber")]
+    tts_cache: PathBuf,
+    /// Finished TTS runs: the spoken text and the WAV, or why it failed.
+    #[cfg(feature = "transcriber")]
+    speech_tx: Sender<(String, anyhow::Result<PathBuf>)>,
     #[cfg(feature = "transcriber")]
-    pub detector_match_rx: Option<std::sync::mpsc::Receiver<String>>,
+    speech_rx: Receiver<(String, anyhow::Result<PathBuf>)>,
+    /// Configured `live_stream` address, kept even when it failed to start.
+    #[cfg(feature = "live-stream")]
+    live_stream: Option<String>,
+    #[cfg(feature = "live-stream")]
+    live_server: Option<crate::livestream::LiveServer>,
 }
 
 impl DaemonApp {
@@ -105,30 +991,44 @@ impl DaemonApp {
         let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
         let (evt_tx, evt_rx) = std::sync::mpsc::channel();
 
-        crate::pipewire::spawn_pw_thread(cmd_rx, evt_tx);
+        let pw_thread = crate::pipewire::spawn_pw_thread(cmd_rx, evt_tx);
 
-        let config = Config::load();
-        let songs: Vec<Song> = config
-            .songs
-            .iter()
-            .filter_map(|p| {
-                let path = PathBuf::from(p);
-                if path.exists() {
-                    let name = path
-                        .file_name()
-                        .map(|n| n.to_string_lossy().to_string())
-                        .unwrap_or_else(|| path.display().to_string());
-                    Some(Song { path, name })
-                } else {
-                    None
-                }
-            })
-            .collect();
+        let mut app = Self::with_backend(Config::path(), cmd_tx, evt_rx);
+        app.pw_thread = Some(pw_thread);
+        #[cfg(feature = "transcriber")]
+        if let Err(e) = plentysound_transcriber::detector::check_vosk_available() {
+            app.set_vosk_missing(format!("{e:#}"));
+        }
+        app
+    }
+
+    /// Keep the word detector unavailable for good: without libvosk neither
+    /// a model nor a detector thread can do anything.
+    #[cfg(feature = "transcriber")]
+    fn set_vosk_missing(&mut self, reason: String) {
+        LOG.error(&format!("Vosk library missing: {reason}"));
+        self.word_detector_status = WordDetectorStatus::Unavailable;
+        self.vosk_missing = Some(reason);
+    }
+
+    /// Build the daemon state around an already-running PipeWire backend,
+    /// loading the library from `config_path`.
+    fn with_backend(
+        config_path: PathBuf,
+        cmd_tx: Sender<PwCommand>,
+        evt_rx: Receiver<PwEvent>,
+    ) -> Self {
+        let first_run = !config_path.exists();
+        let config = Config::load(&config_path);
+        let songs = Self::load_songs(&config);
 
         #[cfg(feature = "transcriber")]
         let word_mappings = Self::load_word_mappings(&config, &songs);
         #[cfg(feature = "transcriber")]
-        crate::log::log_info(&format!("Loaded {} word mappings from config", word_mappings.len()));
+        LOG.info(&format!(
+            "Loaded {} word mappings from config",
+            word_mappings.len()
+        ));
 
         #[cfg(feature = "transcriber")]
         let word_detector_status = if crate::protocol::model_path().exists() {
@@ -137,26 +1037,355 @@ impl DaemonApp {
             WordDetectorStatus::Unavailable
         };
 
-        DaemonApp {
+        // Restore what still resolves; the sink waits for PipeWire
+        let state_path = RuntimeState::path(&config_path);
+        let state = RuntimeState::load(&state_path);
+        let selected_song = state.selected_song.as_ref().map_or(0, |path| {
+            songs
+                .iter()
+                .position(|s| s.path == *path)
+                .unwrap_or_else(|| {
+                    let path = path.display();
+                    LOG.info(&format!("Not restoring selection {path}: not in library"));
+                    0
+                })
+        });
+        let queue: VecDeque<PathBuf> = state
+            .queue
+            .iter()
+            .filter(|path| {
+                let found = songs.iter().any(|s| s.path == **path);
+                if !found {
+                    let path = path.display();
+                    LOG.info(&format!("Not restoring queued {path}: not in library"));
+                }
+                found
+            })
+            .cloned()
+            .collect();
+
+        let (probe_tx, probe_rx) = std::sync::mpsc::channel();
+        let (waveform_tx, waveform_rx) = std::sync::mpsc::channel();
+        let (presence_tx, presence_rx) = std::sync::mpsc::channel();
+        #[cfg(feature = "transcriber")]
+        let (speech_tx, speech_rx) = std::sync::mpsc::channel();
+
+        let app = DaemonApp {
             sinks: Vec::new(),
             selected_sink: 0,
             songs,
-            selected_song: 0,
+            selected_song,
+            songs_revision: 0,
             volume: config.volume,
+            native_volume: config.native_volume,
             comfort_noise: config.comfort_noise,
-            eq_mid_boost: config.eq_mid_boost,
+            noise_kind: config.noise_kind,
+            eq: config.eq,
+            fade_secs: config.fade_secs,
+            limiter: config.limiter,
+            noise_tail_ms: config.noise_tail_ms,
             now_playing: None,
+            paused: false,
+            playback_mode: config.playback_mode,
+            song_sort: config.song_sort,
+            stream_properties: config.stream_properties.clone(),
+            injection_hints: config.injection_hints,
+            loop_enabled: config.loop_enabled,
+            pending_playbacks: VecDeque::new(),
+            next_playback_id: 0,
+            decode_errors: Vec::new(),
+            queue,
+            history_limits: config.history_limits,
+            playback_history: Ring::new(config.history_limits.playback),
+            #[cfg(feature = "transcriber")]
+            detection_history: Ring::new(config.history_limits.detections),
             pw_cmd_tx: cmd_tx,
             pw_evt_rx: evt_rx,
+            pw_thread: None,
+            config_path,
+            state_path,
+            state_written: None,
+            saved_sink: config.selected_sink.clone(),
+            default_sink: None,
+            probe_tx,
+            probe_rx,
+            waveform_tx,
+            waveform_rx,
+            waveforms_pending: HashMap::new(),
+            waveform_cache: crate::waveform::cache_dir(),
+            presence_tx,
+            presence_rx,
+            presence_due: Some(std::time::Instant::now() + PRESENCE_INTERVAL),
+            continuous_noise: config.continuous_noise,
+            sidechain_enabled: config.sidechain_enabled,
+            sidechain_source: config.sidechain_source.clone(),
+            monitor_sink: config.monitor_sink.clone(),
+            sidechain_settings: SidechainSettings {
+                threshold_db: config.sidechain_threshold_db,
+                reduction_db: config.sidechain_reduction_db,
+                hold_ms: config.sidechain_hold_ms,
+            },
+            sidechain_stop_tx: None,
+            sidechain_thread: None,
+            sink_retry: None,
+            status_file: config.status_file.clone(),
+            status_written: None,
+            notifications: config.notifications,
+            notify_on_play: config.notify_on_play,
+            log_level: config.log_level,
+            log_rotation: config.log_rotation,
+            confirm_delete: config.confirm_delete,
+            tag_palette: config.tag_palette.clone(),
+            last_detected_word: None,
+            first_run: first_run && !config.setup_done,
             #[cfg(feature = "transcriber")]
             word_mappings,
             #[cfg(feature = "transcriber")]
+            word_mappings_muted: config.word_mappings_muted,
+            #[cfg(feature = "transcriber")]
+            song_picks: Vec::new(),
+            #[cfg(feature = "transcriber")]
             word_detector_status,
             #[cfg(feature = "transcriber")]
+            detector_stopped: state.detector_stopped,
+            #[cfg(feature = "transcriber")]
+            vosk_missing: None,
+            #[cfg(feature = "transcriber")]
+            download_cancel: std::sync::Arc::default(),
+            #[cfg(feature = "transcriber")]
             detector_stop_tx: None,
             #[cfg(feature = "transcriber")]
+            detector_thread: None,
+            #[cfg(feature = "transcriber")]
             detector_match_rx: None,
-        }
+            #[cfg(feature = "transcriber")]
+            trigger_timing: None,
+            #[cfg(feature = "transcriber")]
+            detector_source: None,
+            #[cfg(feature = "transcriber")]
+            detector_node: None,
+            #[cfg(feature = "transcriber")]
+            detector_capture: CaptureSlot::default(),
+            #[cfg(feature = "transcriber")]
+            word_stats_dirty: std::cell::Cell::new(false),
+            #[cfg(feature = "transcriber")]
+            word_stats_saved_at: std::cell::Cell::new(std::time::Instant::now()),
+            #[cfg(feature = "transcriber")]
+            detector_preprocess: config.detector_preprocess.to_preprocess(),
+            #[cfg(feature = "transcriber")]
+            detector_power_mode: config.detector_power_mode,
+            #[cfg(feature = "transcriber")]
+            detector_matchers: config.detector_matchers.clone(),
+            #[cfg(feature = "transcriber")]
+            tts_command: config.tts_command.clone(),
+            #[cfg(feature = "transcriber")]
+            tts_cache: crate::tts::cache_dir(),
+            #[cfg(feature = "transcriber")]
+            speech_tx,
+            #[cfg(feature = "transcriber")]
+            speech_rx,
+            #[cfg(feature = "live-stream")]
+            live_stream: config.live_stream.clone(),
+            #[cfg(feature = "live-stream")]
+            live_server: config.live_stream.as_deref().and_then(start_live_stream),
+        };
+        app.spawn_probe(
+            app.songs
+                .iter()
+                .filter(|s| !s.missing)
+                .map(|s| s.path.clone())
+                .collect(),
+        );
+        app.sync_stream_properties();
+        app.sync_params();
+        app
+    }
+
+    /// Start, move or stop the live stream server to match `live_stream`.
+    /// A failure to start is retried on the next config reload.
+    #[cfg(feature = "live-stream")]
+    fn sync_live_stream(&mut self) {
+        let running = self.live_server.as_ref().map(|s| s.address());
+        if running != self.live_stream.as_deref() {
+            // The old listener must be gone before the address is bound again
+            self.live_server = None;
+            self.live_server = self.live_stream.as_deref().and_then(start_live_stream);
+        }
+    }
+
+    /// The configured songs, flagging the ones whose files are gone.
+    fn load_songs(config: &Config) -> Vec<Song> {
+        config
+            .songs
+            .iter()
+            .map(|saved| {
+                let path = PathBuf::from(&saved.path);
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string());
+                let missing = !path.exists();
+                if missing {
+                    LOG.info(&format!(
+                        "Song file missing, keeping it listed: {}",
+                        saved.path
+                    ));
+                }
+                Song {
+                    path,
+                    name,
+                    undecodable: false,
+                    missing,
+                    tag_color: saved.tag.clone(),
+                    volume: saved.volume,
+                    duration: saved.duration,
+                    added: saved.added,
+                }
+            })
+            .collect()
+    }
+
+    /// Re-read the config file after it was edited by hand. Sinks, playback
+    /// and a running detector are left alone; songs keep their probe results
+    /// and only newly listed files are probed.
+    fn reload_config(&mut self) -> Vec<DaemonEvent> {
+        let config = match Config::reload(&self.config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                LOG.error(&format!(
+                    "Not reloading {}: {e}",
+                    self.config_path.display()
+                ));
+                return self.rejected(CommandResult::Error(format!("Config not reloaded: {e}")));
+            }
+        };
+
+        let selected_path = self.songs.get(self.selected_song).map(|s| s.path.clone());
+        let mut songs = Self::load_songs(&config);
+        let mut new_paths = Vec::new();
+        for song in &mut songs {
+            match self.songs.iter().find(|s| s.path == song.path) {
+                Some(old) => {
+                    song.undecodable = old.undecodable;
+                    song.duration = song.duration.or(old.duration);
+                }
+                None if !song.missing => new_paths.push(song.path.clone()),
+                None => {}
+            }
+        }
+        self.selected_song = selected_path
+            .and_then(|p| songs.iter().position(|s| s.path == p))
+            .unwrap_or(0);
+        self.songs = songs;
+        self.songs_revision += 1;
+        let songs = &self.songs;
+        self.queue
+            .retain(|path| songs.iter().any(|s| s.path == *path));
+        self.spawn_probe(new_paths);
+
+        self.volume = config.volume;
+        self.native_volume = config.native_volume;
+        self.playback_mode = config.playback_mode;
+        self.song_sort = config.song_sort;
+        if config.stream_properties != self.stream_properties
+            || config.injection_hints != self.injection_hints
+        {
+            self.stream_properties = config.stream_properties.clone();
+            self.injection_hints = config.injection_hints;
+            self.sync_stream_properties();
+        }
+        self.loop_enabled = config.loop_enabled;
+        self.history_limits = config.history_limits;
+        self.playback_history
+            .set_cap(config.history_limits.playback);
+        #[cfg(feature = "transcriber")]
+        self.detection_history
+            .set_cap(config.history_limits.detections);
+        self.comfort_noise = config.comfort_noise;
+        self.noise_kind = config.noise_kind;
+        self.eq = config.eq;
+        self.fade_secs = config.fade_secs;
+        self.limiter = config.limiter;
+        self.noise_tail_ms = config.noise_tail_ms;
+        self.continuous_noise = config.continuous_noise;
+        self.sync_continuous_noise();
+
+        let sidechain_settings = SidechainSettings {
+            threshold_db: config.sidechain_threshold_db,
+            reduction_db: config.sidechain_reduction_db,
+            hold_ms: config.sidechain_hold_ms,
+        };
+        if sidechain_settings != self.sidechain_settings
+            || config.sidechain_source != self.sidechain_source
+        {
+            // Restart a running sidechain so it picks up the new source and settings
+            if let Some(tx) = self.sidechain_stop_tx.take() {
+                let _ = tx.send(());
+            }
+        }
+        self.sidechain_enabled = config.sidechain_enabled;
+        self.sidechain_source = config.sidechain_source.clone();
+        self.monitor_sink = config.monitor_sink.clone();
+        self.sidechain_settings = sidechain_settings;
+        self.sync_sidechain();
+        if config.selected_sink != self.saved_sink {
+            self.saved_sink = config.selected_sink.clone();
+            let found = self
+                .saved_sink
+                .as_ref()
+                .and_then(|saved| resolve_sink(&self.sinks, saved));
+            if let Some(idx) = found {
+                self.selected_sink = idx;
+                self.sync_continuous_noise();
+            }
+        }
+
+        if config.status_file != self.status_file {
+            if let Some(old) = &self.status_file {
+                let _ = std::fs::remove_file(old);
+            }
+            self.status_file = config.status_file.clone();
+            self.status_written = None;
+        }
+        self.notifications = config.notifications;
+        self.notify_on_play = config.notify_on_play;
+        if config.log_level != self.log_level {
+            self.log_level = config.log_level;
+            crate::log::set_level(self.log_level);
+        }
+        self.log_rotation = config.log_rotation;
+        crate::log::set_rotation(self.log_rotation);
+        self.confirm_delete = config.confirm_delete;
+        self.tag_palette = config.tag_palette.clone();
+
+        #[cfg(feature = "transcriber")]
+        {
+            self.word_mappings = Self::load_word_mappings(&config, &self.songs);
+            self.song_picks.clear();
+            self.word_mappings_muted = config.word_mappings_muted;
+            self.refresh_detector_keywords();
+            // Takes effect the next time the detector starts
+            self.detector_preprocess = config.detector_preprocess.to_preprocess();
+            self.detector_power_mode = config.detector_power_mode;
+            self.detector_matchers = config.detector_matchers.clone();
+            self.tts_command = config.tts_command.clone();
+        }
+
+        #[cfg(feature = "live-stream")]
+        {
+            self.live_stream = config.live_stream.clone();
+            self.sync_live_stream();
+        }
+
+        LOG.info(&format!(
+            "Reloaded {} ({} songs)",
+            self.config_path.display(),
+            self.songs.len()
+        ));
+        vec![
+            DaemonEvent::State(Box::new(self.snapshot())),
+            DaemonEvent::CommandResult(CommandResult::Ok("Config reloaded".to_string())),
+        ]
     }
 
     #[cfg(feature = "transcriber")]
@@ -165,15 +1394,32 @@ impl DaemonApp {
             .word_mappings
             .iter()
             .filter_map(|wm| {
-                let song = songs
+                // Songs that left the library are dropped; a song binding
+                // with none left goes too. Speak bindings have no songs.
+                let (song_paths, song_names): (Vec<String>, Vec<String>) = wm
+                    .song_paths
                     .iter()
-                    .find(|s| s.path.display().to_string() == wm.song_path)?;
+                    .filter_map(|path| {
+                        let song = songs
+                            .iter()
+                            .find(|s| s.path.display().to_string() == *path)?;
+                        Some((path.clone(), song.name.clone()))
+                    })
+                    .unzip();
+                if song_paths.is_empty() && wm.action == MappingAction::PlaySong {
+                    return None;
+                }
                 Some(WordMapping {
                     word: wm.word.clone(),
-                    song_name: song.name.clone(),
-                    song_path: wm.song_path.clone(),
+                    song_names,
+                    song_paths,
+                    selection: wm.selection,
                     source_description: wm.source_description.clone(),
                     output_description: wm.output_description.clone(),
+                    only_from_source: wm.only_from_source.clone(),
+                    stats: wm.stats,
+                    enabled: wm.enabled,
+                    action: wm.action.clone(),
                 })
             })
             .collect()
@@ -184,375 +1430,5010 @@ impl DaemonApp {
             songs: self
                 .songs
                 .iter()
-                .map(|s| s.path.display().to_string())
+                .map(|s| SavedSong {
+                    path: s.path.display().to_string(),
+                    volume: s.volume,
+                    tag: s.tag_color.clone(),
+                    duration: s.duration,
+                    added: s.added,
+                })
                 .collect(),
+            song_tags: BTreeMap::new(),
+            song_volumes: BTreeMap::new(),
+            song_durations: BTreeMap::new(),
+            song_added: BTreeMap::new(),
+            song_sort: self.song_sort,
             volume: self.volume,
             comfort_noise: self.comfort_noise,
-            eq_mid_boost: self.eq_mid_boost,
+            noise_kind: self.noise_kind,
+            eq_mid_boost: None,
+            eq: self.eq,
+            fade_secs: self.fade_secs,
+            limiter: self.limiter,
+            noise_tail_ms: self.noise_tail_ms,
+            continuous_noise: self.continuous_noise,
+            sidechain_enabled: self.sidechain_enabled,
+            native_volume: self.native_volume,
+            sidechain_source: self.sidechain_source.clone(),
+            monitor_sink: self.monitor_sink.clone(),
+            selected_sink: self.saved_sink.clone(),
+            sidechain_threshold_db: self.sidechain_settings.threshold_db,
+            sidechain_reduction_db: self.sidechain_settings.reduction_db,
+            sidechain_hold_ms: self.sidechain_settings.hold_ms,
+            status_file: self.status_file.clone(),
+            notifications: self.notifications,
+            notify_on_play: self.notify_on_play,
+            log_level: self.log_level,
+            log_rotation: self.log_rotation,
+            confirm_delete: self.confirm_delete,
+            tag_palette: self.tag_palette.clone(),
+            setup_done: !self.first_run,
+            playback_mode: self.playback_mode,
+            stream_properties: self.stream_properties.clone(),
+            injection_hints: self.injection_hints,
+            loop_enabled: self.loop_enabled,
+            history_limits: self.history_limits,
             #[cfg(feature = "transcriber")]
             word_mappings: self
                 .word_mappings
                 .iter()
                 .map(|wm| WordMappingConfig {
                     word: wm.word.clone(),
-                    song_path: wm.song_path.clone(),
+                    song_path: None,
+                    song_paths: wm.song_paths.clone(),
+                    selection: wm.selection,
                     source_description: wm.source_description.clone(),
                     output_description: wm.output_description.clone(),
+                    only_from_source: wm.only_from_source.clone(),
+                    stats: wm.stats,
+                    enabled: wm.enabled,
+                    action: wm.action.clone(),
                 })
                 .collect(),
+            #[cfg(feature = "transcriber")]
+            word_mappings_muted: self.word_mappings_muted,
+            #[cfg(feature = "transcriber")]
+            detector_preprocess: self.detector_preprocess.into(),
+            #[cfg(feature = "transcriber")]
+            detector_power_mode: self.detector_power_mode,
+            #[cfg(feature = "transcriber")]
+            detector_matchers: self.detector_matchers.clone(),
+            #[cfg(feature = "transcriber")]
+            tts_command: self.tts_command.clone(),
+            #[cfg(feature = "live-stream")]
+            live_stream: self.live_stream.clone(),
         };
-        config.save();
+        config.save(&self.config_path);
+        #[cfg(feature = "transcriber")]
+        {
+            self.word_stats_dirty.set(false);
+            self.word_stats_saved_at.set(std::time::Instant::now());
+        }
     }
 
     pub fn process_pw_events(&mut self) -> Vec<DaemonEvent> {
         let mut events = Vec::new();
+        let revision = self.songs_revision;
         while let Ok(evt) = self.pw_evt_rx.try_recv() {
             match evt {
-                PwEvent::SinksUpdated(new_sinks) => {
+                PwEvent::SinksUpdated(mut new_sinks) => {
+                    // Registry order changes between enumerations; sort so the
+                    // list stays put and the selection follows its device
+                    new_sinks.sort_by_key(|s| {
+                        (s.kind, s.id != DEFAULT_SINK_ID, s.description.clone(), s.id)
+                    });
+                    let selected_index = self.selected_sink;
+                    let previous = self.sinks.get(self.selected_sink).cloned();
+                    let selected_id = previous.as_ref().map(|s| s.id);
                     self.sinks = new_sinks;
+                    // Gone, or back as something that can't play, e.g. after a
+                    // profile switch
+                    let vanished = previous.filter(|old| {
+                        !self
+                            .sinks
+                            .iter()
+                            .any(|s| s.id == old.id && s.kind == old.kind)
+                    });
+                    let found = match &self.saved_sink {
+                        Some(saved) => resolve_sink(&self.sinks, saved),
+                        None if vanished.is_some() => {
+                            self.sinks.iter().position(|s| s.kind == DeviceKind::Output)
+                        }
+                        None => {
+                            selected_id.and_then(|id| self.sinks.iter().position(|s| s.id == id))
+                        }
+                    };
+                    if let Some(idx) = found {
+                        self.selected_sink = idx;
+                    }
+                    self.sink_retry = match self.sink_retry {
+                        _ if !self.sinks.is_empty() => None,
+                        Some(retry) => Some(retry),
+                        None => {
+                            LOG.error("No audio devices found, retrying");
+                            let delay = SINK_RETRY_MIN;
+                            Some((std::time::Instant::now() + delay, delay))
+                        }
+                    };
                     if self.selected_sink >= self.sinks.len() && !self.sinks.is_empty() {
                         self.selected_sink = self.sinks.len() - 1;
                     }
+                    let selected = self.sinks.get(self.selected_sink);
+                    if let Some(old) = &vanished {
+                        let message = match selected.filter(|s| s.kind == DeviceKind::Output) {
+                            Some(sink) => format!(
+                                "{} disconnected, playing on {} instead",
+                                old.description, sink.description
+                            ),
+                            None => format!("{} disconnected, no output left", old.description),
+                        };
+                        LOG.error(&message);
+                        events.push(DaemonEvent::Error {
+                            context: "sink".to_string(),
+                            message,
+                        });
+                        // Its streams would sit on a node that no longer
+                        // exists; those on other devices play on
+                        let stranded: Vec<u64> = self
+                            .pending_playbacks
+                            .iter()
+                            .filter(|(_, clip)| clip.sink.id == old.id)
+                            .map(|(id, ..)| *id)
+                            .collect();
+                        if !stranded.is_empty() {
+                            LOG.info("Stopping playback on the disconnected sink");
+                            events.extend(self.stop_playbacks(&stranded));
+                        }
+                    } else if let (Some(saved), Some(sink)) = (&self.saved_sink, selected) {
+                        if sink.name != saved.name && selected.map(|s| s.id) != selected_id {
+                            LOG.info(&format!(
+                                "Sink {} is not connected, playing on {} meanwhile",
+                                saved.name, sink.description
+                            ));
+                        }
+                    }
+                    self.sync_sidechain();
+                    self.sync_continuous_noise();
+                    events.push(DaemonEvent::SinksUpdated(self.sinks_to_info()));
+                    // Clients track the selection by index
+                    if self.selected_sink != selected_index {
+                        events.push(DaemonEvent::State(Box::new(self.snapshot())));
+                    }
+                }
+                PwEvent::DefaultSinkChanged(name) => {
+                    let shown = name.as_deref().unwrap_or("none");
+                    LOG.info(&format!("System default output is now {shown}"));
+                    self.default_sink = name;
                     events.push(DaemonEvent::SinksUpdated(self.sinks_to_info()));
                 }
-                PwEvent::PlaybackFinished => {
-                    self.now_playing = None;
-                    events.push(DaemonEvent::PlaybackFinished);
-                    events.push(DaemonEvent::NowPlaying(None));
+                PwEvent::OutputLevel {
+                    peak,
+                    rms,
+                    limiting,
+                } => {
+                    events.push(DaemonEvent::OutputLevel {
+                        peak,
+                        rms,
+                        limiting,
+                    });
+                }
+                // A stream that was just stopped may still report once
+                PwEvent::PlaybackProgress {
+                    position_secs,
+                    total_secs,
+                } => {
+                    if self.now_playing.is_some() {
+                        events.push(DaemonEvent::PlaybackProgress {
+                            position_secs,
+                            total_secs,
+                        });
+                    }
+                }
+                PwEvent::PlaybackStarted {
+                    id,
+                    connected,
+                    first_audio,
+                } => {
+                    #[cfg(feature = "transcriber")]
+                    events.extend(self.trigger_latency(id, connected, first_audio));
+                    #[cfg(not(feature = "transcriber"))]
+                    let _ = (id, connected, first_audio);
+                }
+                PwEvent::PlaybackFinished { id, song, reason } => {
+                    #[cfg(feature = "transcriber")]
+                    if self
+                        .trigger_timing
+                        .as_ref()
+                        .is_some_and(|t| t.id == Some(id))
+                    {
+                        // Ended before any audio went out
+                        self.trigger_timing = None;
+                    }
+                    self.playback_history.push(PlayedSong {
+                        song: song.clone(),
+                        reason,
+                        ended_at: unix_now(),
+                    });
+                    events.push(DaemonEvent::SongEnded { song, reason });
+                    // Interrupted and stopped plays were already dropped when
+                    // they were cut short; only the end of a playback moves the
+                    // queue on. An error ends it too: the worker has moved on.
+                    if matches!(reason, FinishReason::Stopped | FinishReason::Replaced) {
+                        continue;
+                    }
+                    let pending = self.pending_playbacks.iter().position(|(p, _)| *p == id);
+                    let Some((_, finished)) =
+                        pending.and_then(|i| self.pending_playbacks.remove(i))
+                    else {
+                        continue;
+                    };
+                    // Only songs still in the library come round again
+                    let looping = self.loop_enabled
+                        && reason == FinishReason::Completed
+                        && self.pending_playbacks.is_empty()
+                        && self.songs.iter().any(|s| s.path == finished.path);
+                    if looping {
+                        // Queued songs wait until the loop is switched off
+                        self.start_clip(finished);
+                    }
+                    if self.pending_playbacks.is_empty() && !self.queue.is_empty() {
+                        self.play_next_queued();
+                        events.push(DaemonEvent::QueueUpdated(self.queue_info()));
+                    }
+                    self.now_playing = self
+                        .pending_playbacks
+                        .front()
+                        .map(|(_, clip)| clip.name.clone());
+                    if self.now_playing.is_none() {
+                        self.paused = false;
+                        events.push(DaemonEvent::PlaybackFinished);
+                    }
+                    events.push(DaemonEvent::NowPlaying(self.now_playing.clone()));
+                }
+                PwEvent::PlaybackError(message) => {
+                    events.push(DaemonEvent::Error {
+                        context: "playback".to_string(),
+                        message,
+                    });
                 }
             }
         }
+        // A queued song found missing on the way was flagged
+        if self.songs_revision != revision {
+            events.push(self.songs_updated());
+        }
+        events.extend(
+            self.decode_errors
+                .drain(..)
+                .map(|message| DaemonEvent::Error {
+                    context: "decode".to_string(),
+                    message,
+                }),
+        );
         events
     }
 
     pub fn apply_command(&mut self, cmd: ClientCommand) -> Vec<DaemonEvent> {
         match cmd {
+            // Connection-level; the daemon loop answers these itself
+            ClientCommand::Identify { .. } | ClientCommand::GetStats | ClientCommand::DumpState => {
+                vec![]
+            }
+            // The daemon loop unwraps the outer one
+            ClientCommand::Request { command, .. } => self.apply_command(*command),
             ClientCommand::GetState => {
-                vec![DaemonEvent::State(self.snapshot())]
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
             }
-            ClientCommand::SelectSink(idx) => {
-                if idx < self.sinks.len() {
+            ClientCommand::ReloadConfig => self.reload_config(),
+            ClientCommand::SetLogLevel(level) => {
+                crate::log::set_level(level);
+                let msg = format!("Logging at {} level", level.name());
+                LOG.warn(&msg);
+                vec![DaemonEvent::CommandResult(CommandResult::Ok(msg))]
+            }
+            ClientCommand::SelectSink(idx) => match checked_index("sink", idx, self.sinks.len()) {
+                Ok(idx) if self.sinks[idx].kind == DeviceKind::Source => {
+                    let msg = format!("'{}' is a capture device", self.sinks[idx].description);
+                    self.rejected(CommandResult::Error(msg))
+                }
+                Ok(idx) => {
                     self.selected_sink = idx;
+                    self.saved_sink = Some(SavedSink::of(&self.sinks[idx]));
+                    self.save_config();
+                    self.sync_continuous_noise();
+                    vec![DaemonEvent::State(Box::new(self.snapshot()))]
+                }
+                Err(e) => self.rejected(e),
+            },
+            ClientCommand::SetMonitorSink(None) => {
+                self.monitor_sink = None;
+                self.save_config();
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
+            }
+            ClientCommand::SetMonitorSink(Some(idx)) => {
+                let idx = match checked_index("sink", idx, self.sinks.len()) {
+                    Ok(idx) => idx,
+                    Err(e) => return self.rejected(e),
+                };
+                if self.sinks[idx].kind != DeviceKind::Output {
+                    let msg = format!("'{}' is not an output device", self.sinks[idx].description);
+                    return self.rejected(CommandResult::Error(msg));
                 }
-                vec![DaemonEvent::State(self.snapshot())]
+                self.monitor_sink = Some(self.sinks[idx].description.clone());
+                self.save_config();
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
             }
-            ClientCommand::SelectSong(idx) => {
-                if idx < self.songs.len() {
+            ClientCommand::SelectSong(idx) => match checked_index("song", idx, self.songs.len()) {
+                Ok(idx) => {
                     self.selected_song = idx;
+                    vec![DaemonEvent::State(Box::new(self.snapshot()))]
+                }
+                Err(e) => self.rejected(e),
+            },
+            ClientCommand::Play => self.play(self.selected_song),
+            ClientCommand::PlaySong(idx) => match checked_index("song", idx, self.songs.len()) {
+                Ok(idx) => self.play(idx),
+                Err(e) => self.rejected(e),
+            },
+            ClientCommand::Pause => self.set_paused(true),
+            ClientCommand::Resume => self.set_paused(false),
+            ClientCommand::StopPlayback { clear_queue } => {
+                let mut events = vec![];
+                if clear_queue && !self.queue.is_empty() {
+                    self.queue.clear();
+                    events.push(DaemonEvent::QueueUpdated(vec![]));
                 }
-                vec![DaemonEvent::State(self.snapshot())]
+                if self.now_playing.is_none() {
+                    return events;
+                }
+                events.extend(self.stop_playback());
+                events
             }
-            ClientCommand::Play => {
-                self.play_selected_song();
-                vec![DaemonEvent::NowPlaying(self.now_playing.clone())]
+            ClientCommand::Next => {
+                let mut events = vec![];
+                if self.now_playing.is_some() {
+                    events.extend(self.stop_playback());
+                }
+                if !self.queue.is_empty() {
+                    self.play_next_queued();
+                    events.push(DaemonEvent::QueueUpdated(self.queue_info()));
+                    events.push(DaemonEvent::NowPlaying(self.now_playing.clone()));
+                }
+                events
             }
-            ClientCommand::SetVolume(v) => {
-                self.volume = v.clamp(0.0, 5.0);
+            ClientCommand::Enqueue(idx) => match checked_index("song", idx, self.songs.len())
+                .and_then(|idx| self.check_present(idx).map(|()| idx))
+            {
+                Ok(idx) if self.pending_playbacks.is_empty() => {
+                    if self.sinks.is_empty() {
+                        return vec![no_devices_error()];
+                    }
+                    if let Some(message) = self.unplayable_sink() {
+                        return self.rejected(CommandResult::Error(message));
+                    }
+                    self.play_song(idx, self.selected_sink);
+                    vec![DaemonEvent::NowPlaying(self.now_playing.clone())]
+                }
+                Ok(idx) => {
+                    self.queue.push_back(self.songs[idx].path.clone());
+                    vec![DaemonEvent::QueueUpdated(self.queue_info())]
+                }
+                Err(e) => self.rejected(e),
+            },
+            ClientCommand::SetLoop(enabled) => {
+                self.loop_enabled = enabled;
                 self.save_config();
-                vec![DaemonEvent::State(self.snapshot())]
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
+            }
+            ClientCommand::ClearQueue => {
+                self.queue.clear();
+                vec![DaemonEvent::QueueUpdated(vec![])]
+            }
+            ClientCommand::RemoveFromQueue(pos) => {
+                if let Err(e) = checked_index("queue entry", pos, self.queue.len()) {
+                    return self.rejected(e);
+                }
+                self.queue.remove(pos);
+                vec![DaemonEvent::QueueUpdated(self.queue_info())]
+            }
+            ClientCommand::ReorderQueue { from, to } => {
+                let len = self.queue.len();
+                let checked = checked_index("queue entry", from, len).and(checked_index(
+                    "queue entry",
+                    to,
+                    len,
+                ));
+                if let Err(e) = checked {
+                    return self.rejected(e);
+                }
+                if let Some(path) = self.queue.remove(from) {
+                    self.queue.insert(to, path);
+                }
+                vec![DaemonEvent::QueueUpdated(self.queue_info())]
             }
+            ClientCommand::SetVolume(v) => match checked_setting("volume", v, VOLUME_MAX) {
+                Ok(v) => {
+                    self.volume = v;
+                    self.sync_params();
+                    self.save_config();
+                    vec![DaemonEvent::VolumeChanged(v)]
+                }
+                Err(e) => self.rejected(e),
+            },
             ClientCommand::SetComfortNoise(v) => {
-                self.comfort_noise = v.clamp(0.0, 0.05);
+                match checked_setting("comfort noise", v, COMFORT_NOISE_MAX) {
+                    Ok(v) => {
+                        self.comfort_noise = v;
+                        self.save_config();
+                        self.sync_continuous_noise();
+                        vec![DaemonEvent::FxChanged(self.fx())]
+                    }
+                    Err(e) => self.rejected(e),
+                }
+            }
+            ClientCommand::SetNoiseKind(kind) => {
+                self.noise_kind = kind;
+                self.save_config();
+                self.sync_continuous_noise();
+                vec![DaemonEvent::FxChanged(self.fx())]
+            }
+            ClientCommand::SetEqBand { band, gain } => {
+                match checked_setting(&format!("EQ {} gain", band.name()), gain, EQ_GAIN_MAX) {
+                    Ok(v) => {
+                        *self.eq.get_mut(band) = v;
+                        self.save_config();
+                        self.sync_params();
+                        vec![DaemonEvent::FxChanged(self.fx())]
+                    }
+                    Err(e) => self.rejected(e),
+                }
+            }
+            ClientCommand::SetFade(v) => match checked_setting("fade", v, FADE_SECS_MAX) {
+                Ok(v) => {
+                    self.fade_secs = v;
+                    self.save_config();
+                    vec![DaemonEvent::FxChanged(self.fx())]
+                }
+                Err(e) => self.rejected(e),
+            },
+            ClientCommand::SetLimiter(enabled) => {
+                self.limiter = enabled;
+                self.save_config();
+                vec![DaemonEvent::FxChanged(self.fx())]
+            }
+            ClientCommand::SetContinuousNoise(enabled) => {
+                self.continuous_noise = enabled;
                 self.save_config();
-                vec![DaemonEvent::State(self.snapshot())]
+                self.sync_continuous_noise();
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
             }
-            ClientCommand::SetEqMidBoost(v) => {
-                self.eq_mid_boost = v.clamp(0.0, 3.0);
+            ClientCommand::SetSidechain(enabled) => {
+                self.sidechain_enabled = enabled;
                 self.save_config();
-                vec![DaemonEvent::State(self.snapshot())]
+                self.sync_sidechain();
+                if enabled && self.sidechain_stop_tx.is_none() {
+                    return vec![
+                        DaemonEvent::State(Box::new(self.snapshot())),
+                        DaemonEvent::CommandResult(CommandResult::Warning(
+                            "Duck on talk enabled, waiting for a mic source".to_string(),
+                        )),
+                    ];
+                }
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
             }
             ClientCommand::AddSong(path_str) => {
-                let path = PathBuf::from(&path_str);
-                if path.exists() {
-                    let name = path
-                        .file_name()
-                        .map(|n| n.to_string_lossy().to_string())
-                        .unwrap_or_else(|| path.display().to_string());
-                    self.songs.push(Song { path, name });
+                let mut library = LibraryIndex::new(&self.songs);
+                let result = match self.add_song(&path_str, &mut library) {
+                    Ok((path, result)) => {
+                        self.songs_added(vec![path]);
+                        self.save_config();
+                        result
+                    }
+                    Err(e) => CommandResult::Error(e),
+                };
+                vec![
+                    DaemonEvent::State(Box::new(self.snapshot())),
+                    DaemonEvent::CommandResult(result),
+                ]
+            }
+            ClientCommand::AddSongs(paths) => {
+                let (mut skipped, mut failed) = (0, 0);
+                let mut added_paths = Vec::new();
+                let mut library = LibraryIndex::new(&self.songs);
+                for path in &paths {
+                    let canonical = std::fs::canonicalize(path);
+                    if canonical.is_ok_and(|p| library.paths.contains_key(&p)) {
+                        skipped += 1;
+                        continue;
+                    }
+                    match self.add_song(path, &mut library) {
+                        Ok((added, _)) => added_paths.push(added),
+                        Err(e) => {
+                            LOG.warn(&format!("Not adding {path}: {e}"));
+                            failed += 1;
+                        }
+                    }
+                }
+                let added = added_paths.len();
+                if added > 0 {
+                    self.songs_added(added_paths);
                     self.save_config();
                 }
-                vec![DaemonEvent::State(self.snapshot())]
+                let mut msg = format!("Added {added}");
+                if skipped > 0 {
+                    let s = if skipped == 1 { "" } else { "s" };
+                    msg.push_str(&format!(", skipped {skipped} duplicate{s}"));
+                }
+                let result = if failed > 0 {
+                    CommandResult::Warning(format!("{msg}, {failed} failed"))
+                } else {
+                    CommandResult::Ok(msg)
+                };
+                let state = Box::new(self.snapshot());
+                vec![
+                    DaemonEvent::State(state),
+                    DaemonEvent::CommandResult(result),
+                ]
             }
             ClientCommand::RemoveSong(idx) => {
-                if idx < self.songs.len() {
-                    self.songs.remove(idx);
-                    if self.selected_song >= self.songs.len() && !self.songs.is_empty() {
-                        self.selected_song = self.songs.len() - 1;
+                if let Err(e) = checked_index("song", idx, self.songs.len()) {
+                    return self.rejected(e);
+                }
+                let removed = self.songs.remove(idx);
+                self.songs_revision += 1;
+                self.queue.retain(|path| *path != removed.path);
+                if self.selected_song >= self.songs.len() && !self.songs.is_empty() {
+                    self.selected_song = self.songs.len() - 1;
+                }
+                self.save_config();
+                vec![
+                    DaemonEvent::State(Box::new(self.snapshot())),
+                    DaemonEvent::CommandResult(CommandResult::Ok(format!(
+                        "Removed '{}'",
+                        removed.name
+                    ))),
+                ]
+            }
+            ClientCommand::MoveSong { from, to } => {
+                let len = self.songs.len();
+                let checked = checked_index("song", from, len).and(checked_index("song", to, len));
+                if let Err(e) = checked {
+                    return self.rejected(e);
+                }
+                let song = self.songs.remove(from);
+                self.songs.insert(to, song);
+                self.selected_song = moved_index(self.selected_song, from, to);
+                // The order is the user's own from now on
+                self.song_sort = None;
+                self.songs_revision += 1;
+                self.save_config();
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
+            }
+            ClientCommand::SortSongs(key) => {
+                self.song_sort = Some(key);
+                self.sort_songs();
+                self.save_config();
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
+            }
+            ClientCommand::PruneMissing => self.prune_missing(),
+            ClientCommand::SetSongTag { index, tag_color } => {
+                if let Err(e) = checked_index("song", index, self.songs.len()) {
+                    return self.rejected(e);
+                }
+                if let Some(tag) = &tag_color {
+                    if tag.is_empty() || tag.len() > MAX_TAG_LEN {
+                        return self
+                            .rejected(CommandResult::Error(format!("Invalid tag color: '{tag}'")));
                     }
-                    self.save_config();
                 }
-                vec![DaemonEvent::State(self.snapshot())]
+                self.songs[index].tag_color = tag_color;
+                // Pages fetched earlier carry the old tag
+                self.songs_revision += 1;
+                self.save_config();
+                vec![self.songs_updated()]
+            }
+            ClientCommand::SetSongVolume { index, volume } => {
+                if let Err(e) = checked_index("song", index, self.songs.len()) {
+                    return self.rejected(e);
+                }
+                let volume =
+                    match volume.map(|v| checked_setting("song volume", v, SONG_VOLUME_MAX)) {
+                        Some(Err(e)) => return self.rejected(e),
+                        Some(Ok(v)) => Some(v),
+                        None => None,
+                    };
+                self.songs[index].volume = volume;
+                self.songs_revision += 1;
+                self.save_config();
+                vec![self.songs_updated()]
+            }
+            ClientCommand::GetSongs { offset, limit } => {
+                vec![DaemonEvent::Songs {
+                    revision: self.songs_revision,
+                    offset,
+                    total: self.songs.len(),
+                    songs: self.song_page(offset, limit),
+                }]
             }
+            ClientCommand::GetWaveform(index) => self.request_waveform(index, None),
             ClientCommand::RefreshSinks => {
                 let _ = self.pw_cmd_tx.send(PwCommand::ListSinks);
                 vec![]
             }
+            ClientCommand::FinishSetup => {
+                self.first_run = false;
+                self.save_config();
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
+            }
             ClientCommand::Quit => {
                 vec![DaemonEvent::Shutdown]
             }
             #[cfg(feature = "transcriber")]
             ClientCommand::StartModelDownload => {
+                if let Some(reason) = &self.vosk_missing {
+                    let message = format!("Vosk library missing: {reason}");
+                    return self.rejected(CommandResult::Error(message));
+                }
+                if self.word_detector_status != WordDetectorStatus::Downloading {
+                    self.download_cancel = std::sync::Arc::default();
+                }
                 self.word_detector_status = WordDetectorStatus::Downloading;
-                vec![DaemonEvent::State(self.snapshot())]
-            }
-            #[cfg(feature = "transcriber")]
-            ClientCommand::AddWordMapping { word, song_index, source_description, output_description } => {
-                if song_index < self.songs.len() {
-                    let song = &self.songs[song_index];
-                    self.word_mappings.push(WordMapping {
-                        word,
-                        song_name: song.name.clone(),
-                        song_path: song.path.display().to_string(),
-                        source_description,
-                        output_description,
-                    });
-                    self.save_config();
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::CancelModelDownload => {
+                if self.word_detector_status != WordDetectorStatus::Downloading {
+                    return self.rejected(CommandResult::Error(
+                        "No model download running".to_string(),
+                    ));
                 }
-                vec![DaemonEvent::State(self.snapshot())]
+                self.download_cancel
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+                self.word_detector_status = WordDetectorStatus::Unavailable;
+                DETECTOR_LOG.info("Model download cancelled");
+                vec![
+                    DaemonEvent::State(Box::new(self.snapshot())),
+                    DaemonEvent::CommandResult(CommandResult::Ok(
+                        "Model download cancelled".to_string(),
+                    )),
+                ]
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::AddWordMapping {
+                word,
+                song_indices,
+                selection,
+                source_description,
+                output_description,
+            } => {
+                if song_indices.is_empty() {
+                    return self.rejected(CommandResult::Error("No songs to bind".to_string()));
+                }
+                for &index in &song_indices {
+                    if let Err(e) = checked_index("song", index, self.songs.len()) {
+                        return self.rejected(e);
+                    }
+                }
+                let songs = song_indices.iter().map(|&i| &self.songs[i]);
+                self.word_mappings.push(WordMapping {
+                    word,
+                    song_names: songs.clone().map(|s| s.name.clone()).collect(),
+                    song_paths: songs.map(|s| s.path.display().to_string()).collect(),
+                    selection,
+                    source_description,
+                    output_description,
+                    only_from_source: None,
+                    stats: WordStats::default(),
+                    enabled: true,
+                    action: MappingAction::PlaySong,
+                });
+                self.save_config();
+                vec![DaemonEvent::MappingsUpdated(self.word_mappings.clone())]
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::AddSpeakMapping {
+                word,
+                phrase,
+                source_description,
+                output_description,
+            } => {
+                if phrase.trim().is_empty() {
+                    return self.rejected(CommandResult::Error("Empty phrase".to_string()));
+                }
+                self.word_mappings.push(WordMapping {
+                    word,
+                    song_names: Vec::new(),
+                    song_paths: Vec::new(),
+                    selection: SongSelection::default(),
+                    source_description,
+                    output_description,
+                    only_from_source: None,
+                    stats: WordStats::default(),
+                    enabled: true,
+                    action: MappingAction::Speak(phrase),
+                });
+                self.save_config();
+                self.refresh_detector_keywords();
+                vec![DaemonEvent::MappingsUpdated(self.word_mappings.clone())]
             }
             #[cfg(feature = "transcriber")]
             ClientCommand::RemoveWordMapping(idx) => {
-                if idx < self.word_mappings.len() {
-                    self.word_mappings.remove(idx);
-                    self.save_config();
+                if let Err(e) = checked_index("word binding", idx, self.word_mappings.len()) {
+                    return self.rejected(e);
+                }
+                self.word_mappings.remove(idx);
+                if idx < self.song_picks.len() {
+                    self.song_picks.remove(idx);
+                }
+                self.save_config();
+                vec![DaemonEvent::MappingsUpdated(self.word_mappings.clone())]
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::SetWordMappingEnabled { index, enabled } => {
+                if let Err(e) = checked_index("word binding", index, self.word_mappings.len()) {
+                    return self.rejected(e);
+                }
+                self.word_mappings[index].enabled = enabled;
+                self.save_config();
+                self.refresh_detector_keywords();
+                vec![DaemonEvent::MappingsUpdated(self.word_mappings.clone())]
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::SetWordMappingsMuted(muted) => {
+                self.word_mappings_muted = muted;
+                self.save_config();
+                self.refresh_detector_keywords();
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::SetWordMappingSourceOnly { index, only } => {
+                if let Err(e) = checked_index("word binding", index, self.word_mappings.len()) {
+                    return self.rejected(e);
+                }
+                let wm = &mut self.word_mappings[index];
+                if only && wm.source_description.is_empty() {
+                    let message = format!("'{}' has no input to restrict it to", wm.word);
+                    return self.rejected(CommandResult::Error(message));
+                }
+                wm.only_from_source = only.then(|| wm.source_description.clone());
+                self.save_config();
+                vec![DaemonEvent::MappingsUpdated(self.word_mappings.clone())]
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::SetDetectorPowerMode(mode) => {
+                self.detector_power_mode = mode;
+                self.save_config();
+                if let Some(node_id) = self.detector_node {
+                    DETECTOR_LOG.info(&format!("Restarting detector in {} mode", mode.name()));
+                    self.start_detector(node_id);
                 }
-                vec![DaemonEvent::State(self.snapshot())]
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
             }
             #[cfg(feature = "transcriber")]
             ClientCommand::StartWordDetector(node_id) => {
+                if let Some(reason) = &self.vosk_missing {
+                    let message = format!("Vosk library missing: {reason}");
+                    return self.rejected(CommandResult::Error(message));
+                }
+                if !matches!(
+                    self.word_detector_status,
+                    WordDetectorStatus::Ready | WordDetectorStatus::Running
+                ) {
+                    let message = "Word detector needs the model downloaded first".to_string();
+                    return self.rejected(CommandResult::Error(message));
+                }
+                self.detector_stopped = false;
                 self.start_detector(node_id);
-                vec![DaemonEvent::State(self.snapshot())]
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
             }
             #[cfg(feature = "transcriber")]
             ClientCommand::StopWordDetector => {
+                self.detector_stopped = true;
                 self.stop_detector();
-                vec![DaemonEvent::State(self.snapshot())]
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
             }
             #[cfg(feature = "transcriber")]
             ClientCommand::ModelDownloadComplete => {
-                crate::log::log_info("ModelDownloadComplete: setting status to Ready");
-                self.word_detector_status = WordDetectorStatus::Ready;
+                // A download started before libvosk went missing still lands,
+                // but nothing can load the model
+                if self.vosk_missing.is_some() {
+                    DETECTOR_LOG.info("ModelDownloadComplete: no libvosk, staying Unavailable");
+                    self.word_detector_status = WordDetectorStatus::Unavailable;
+                } else {
+                    DETECTOR_LOG.info("ModelDownloadComplete: setting status to Ready");
+                    self.word_detector_status = WordDetectorStatus::Ready;
+                }
                 let snap = self.snapshot();
-                crate::log::log_info(&format!(
+                DETECTOR_LOG.info(&format!(
                     "ModelDownloadComplete: snapshot status = {:?}",
                     snap.word_detector_status
                 ));
-                vec![DaemonEvent::State(snap)]
+                vec![DaemonEvent::State(Box::new(snap))]
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::StartDetectorCapture(path) => {
+                let result = self.start_capture(Path::new(&path));
+                let state = Box::new(self.snapshot());
+                vec![
+                    DaemonEvent::State(state),
+                    DaemonEvent::CommandResult(result),
+                ]
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::StopDetectorCapture => {
+                let result = match self.detector_capture.lock().unwrap().take() {
+                    Some(writer) => {
+                        let secs = writer.secs();
+                        match writer.finish() {
+                            Ok(path) => CommandResult::Ok(format!(
+                                "Detector capture saved to {} ({secs:.1} s)",
+                                path.display()
+                            )),
+                            Err(e) => CommandResult::Error(format!("{e:#}")),
+                        }
+                    }
+                    None => CommandResult::Error("No detector capture running".to_string()),
+                };
+                let state = Box::new(self.snapshot());
+                vec![
+                    DaemonEvent::State(state),
+                    DaemonEvent::CommandResult(result),
+                ]
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::SimulateWord(word) => {
+                let mut events = Vec::new();
+                let detection = DetectorEvent::Matched(word.clone(), std::time::Instant::now());
+                let result = if self.handle_detection(detection, true, &mut events) {
+                    CommandResult::Ok(format!("Simulated \"{}\"", word))
+                } else {
+                    CommandResult::Error(format!("No binding for \"{}\"", word))
+                };
+                events.push(DaemonEvent::State(Box::new(self.snapshot())));
+                events.push(DaemonEvent::CommandResult(result));
+                events
+            }
+            #[cfg(feature = "transcriber")]
+            ClientCommand::ResetWordStats => {
+                for wm in &mut self.word_mappings {
+                    wm.stats = WordStats::default();
+                }
+                self.save_config();
+                vec![DaemonEvent::MappingsUpdated(self.word_mappings.clone())]
             }
             #[cfg(feature = "transcriber")]
             ClientCommand::ModelDownloadFailed(msg) => {
-                self.word_detector_status = WordDetectorStatus::DownloadFailed(msg);
-                vec![DaemonEvent::State(self.snapshot())]
+                // A cancelled download may still report in; the status already moved on
+                if self.word_detector_status == WordDetectorStatus::Downloading {
+                    self.word_detector_status = WordDetectorStatus::DownloadFailed(msg);
+                }
+                vec![DaemonEvent::State(Box::new(self.snapshot()))]
             }
         }
     }
 
-    pub fn snapshot(&self) -> DaemonState {
-        DaemonState {
-            sinks: self.sinks_to_info(),
-            songs: self
-                .songs
-                .iter()
-                .map(|s| SongInfo {
-                    path: s.path.display().to_string(),
-                    name: s.name.clone(),
-                })
-                .collect(),
-            selected_sink: self.selected_sink,
-            selected_song: self.selected_song,
-            volume: self.volume,
-            comfort_noise: self.comfort_noise,
-            eq_mid_boost: self.eq_mid_boost,
-            now_playing: self.now_playing.clone(),
-            #[cfg(feature = "transcriber")]
-            word_detector_status: self.word_detector_status.clone(),
-            #[cfg(feature = "transcriber")]
-            word_mappings: self.word_mappings.clone(),
+    /// Reply for a command that was refused: unchanged state plus the reason.
+    fn rejected(&self, result: CommandResult) -> Vec<DaemonEvent> {
+        vec![
+            DaemonEvent::State(Box::new(self.snapshot())),
+            DaemonEvent::CommandResult(result),
+        ]
+    }
+
+    /// Add a file to the library under its canonical path, rejecting paths
+    /// that resolve to a song already present. Files whose contents look
+    /// identical to an existing song are still added, but reported with a
+    /// warning. Returns the stored path for `songs_added`, which finishes
+    /// the job once the whole batch is in.
+    fn add_song(
+        &mut self,
+        path_str: &str,
+        library: &mut LibraryIndex,
+    ) -> Result<(PathBuf, CommandResult), String> {
+        let Ok(path) = std::fs::canonicalize(path_str) else {
+            return Err(format!("File not found: {}", path_str));
+        };
+
+        if let Some(existing) = library.paths.get(&path) {
+            return Err(format!("already in library as '{existing}'"));
         }
+
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+
+        let fingerprint = content_fingerprint(&path);
+        let content_dup = fingerprint.and_then(|fp| library.fingerprints.get(&fp).cloned());
+        library.insert(path.clone(), fingerprint, &name);
+
+        self.songs.push(Song {
+            path: path.clone(),
+            name: name.clone(),
+            undecodable: false,
+            missing: false,
+            tag_color: None,
+            volume: None,
+            duration: None,
+            added: Some(unix_now()),
+        });
+
+        let result = match content_dup {
+            Some(other) => CommandResult::Warning(format!(
+                "Added '{}', but it looks identical to '{}'",
+                name, other
+            )),
+            None => CommandResult::Ok(format!("Added '{}'", name)),
+        };
+        Ok((path, result))
     }
 
-    fn sinks_to_info(&self) -> Vec<SinkInfo> {
-        self.sinks
-            .iter()
-            .map(|s| SinkInfo {
-                id: s.id,
-                name: s.name.clone(),
-                description: s.description.clone(),
-                kind: match s.kind {
-                    DeviceKind::Output => "Output".to_string(),
-                    DeviceKind::Input => "Input".to_string(),
-                },
-            })
-            .collect()
+    /// Sort the library once after a batch of `add_song` calls and probe
+    /// the new songs on a single worker.
+    fn songs_added(&mut self, paths: Vec<PathBuf>) {
+        self.sort_songs();
+        self.songs_revision += 1;
+        self.spawn_probe(paths);
     }
 
-    fn play_selected_song(&mut self) {
-        if self.songs.is_empty() || self.sinks.is_empty() {
+    /// Put the library in `song_sort` order, if one is set. The sort is
+    /// stable and the selection stays on the same song.
+    fn sort_songs(&mut self) {
+        let Some(key) = self.song_sort else {
             return;
-        }
-
-        let song = &self.songs[self.selected_song];
-        let sink = &self.sinks[self.selected_sink];
-
-        match crate::audio::decode_file(&song.path) {
-            Ok(decoded) => {
-                self.now_playing = Some(song.name.clone());
-                let _ = self.pw_cmd_tx.send(PwCommand::Play {
-                    sink_id: sink.id,
-                    kind: sink.kind,
-                    node_name: sink.name.clone(),
-                    samples: decoded.samples,
-                    sample_rate: decoded.sample_rate,
-                    channels: decoded.channels,
-                    volume: self.volume,
-                    comfort_noise: self.comfort_noise,
-                    eq_mid_boost: self.eq_mid_boost,
-                });
-            }
-            Err(e) => {
-                crate::log::log_error(&format!("Failed to decode {}: {e}", song.name));
-            }
-        }
+        };
+        let selected = self.songs.get(self.selected_song).map(|s| s.path.clone());
+        self.songs.sort_by(|a, b| compare_songs(key, a, b));
+        self.selected_song = selected
+            .and_then(|path| self.songs.iter().position(|s| s.path == path))
+            .unwrap_or(0);
+        self.songs_revision += 1;
     }
 
-    #[cfg(feature = "transcriber")]
-    pub fn play_song_by_path(&mut self, song_path: &str) {
-        let song_idx = self
-            .songs
-            .iter()
-            .position(|s| s.path.display().to_string() == song_path);
-        if let Some(idx) = song_idx {
-            self.selected_song = idx;
-            self.play_selected_song();
+    /// Check on a worker thread that those of `paths` without a cached
+    /// length can be decoded, reading their length from the same probe;
+    /// results come back through `poll_probes`.
+    fn spawn_probe(&self, paths: Vec<PathBuf>) {
+        // A cached length means the file was probed before
+        let paths: Vec<PathBuf> = paths
+            .into_iter()
+            .filter(|path| {
+                !self
+                    .songs
+                    .iter()
+                    .any(|s| &s.path == path && s.duration.is_some())
+            })
+            .collect();
+        if paths.is_empty() {
+            return;
         }
+        let tx = self.probe_tx.clone();
+        std::thread::spawn(move || {
+            for path in paths {
+                let probe = match crate::audio::probe_decodable(&path) {
+                    Err(e) => Probe::Undecodable(e.to_string()),
+                    Ok(Some(length)) => Probe::Duration(length.as_secs_f64()),
+                    Ok(None) => continue,
+                };
+                if tx.send((path, probe)).is_err() {
+                    break;
+                }
+            }
+        });
     }
 
-    /// Try to auto-start the detector if the model is ready, there are word
-    /// mappings, and we can find a matching input source among discovered sinks.
-    #[cfg(feature = "transcriber")]
-    pub fn try_autostart_detector(&mut self) {
-        if self.word_detector_status != WordDetectorStatus::Ready {
-            return;
+    /// Flag songs the probe thread reported as undecodable and fill in the
+    /// lengths it read, keeping those in the config.
+    pub fn poll_probes(&mut self) -> Vec<DaemonEvent> {
+        let mut results = Vec::new();
+        let mut timed = false;
+        while let Ok((path, probe)) = self.probe_rx.try_recv() {
+            // The song may have been removed while it was being probed
+            let Some(song) = self
+                .songs
+                .iter_mut()
+                .find(|s| s.path == path && !s.undecodable)
+            else {
+                continue;
+            };
+            match probe {
+                Probe::Duration(secs) => {
+                    song.duration = Some(secs);
+                    timed = true;
+                }
+                Probe::Undecodable(reason) => {
+                    song.undecodable = true;
+                    LOG.error(&format!("Cannot decode {}: {reason}", song.name));
+                    results.push(DaemonEvent::CommandResult(CommandResult::Error(format!(
+                        "Cannot decode '{}': {}",
+                        song.name, reason
+                    ))));
+                }
+            }
         }
-        if self.word_mappings.is_empty() || self.sinks.is_empty() {
-            return;
+        if results.is_empty() && !timed {
+            return results;
         }
-        // Already running
-        if self.detector_stop_tx.is_some() {
-            return;
+        if timed {
+            if self.song_sort == Some(SortKey::Duration) {
+                self.sort_songs();
+            }
+            self.save_config();
         }
+        self.songs_revision += 1;
+        let mut events = vec![DaemonEvent::State(Box::new(self.snapshot()))];
+        events.extend(results);
+        events
+    }
 
-        // Try to match the source_description from the first mapping that has one
-        let saved_desc = self.word_mappings.iter()
-            .map(|wm| wm.source_description.as_str())
-            .find(|d| !d.is_empty());
+    /// Compute the envelope of song `index` on a worker thread for
+    /// `client`; it comes back through `poll_waveforms`.
+    pub fn request_waveform(&mut self, index: usize, client: Option<u64>) -> Vec<DaemonEvent> {
+        let index = match checked_index("song", index, self.songs.len()) {
+            Ok(index) => index,
+            Err(e) => return self.rejected(e),
+        };
+        let path = self.songs[index].path.clone();
+        let requesters = self.waveforms_pending.entry(path.clone()).or_default();
+        let running = !requesters.is_empty();
+        if !requesters.contains(&client) {
+            requesters.push(client);
+        }
+        if running {
+            return vec![];
+        }
+        let tx = self.waveform_tx.clone();
+        let cache = self.waveform_cache.clone();
+        std::thread::spawn(move || {
+            let result = crate::waveform::peaks(&path, &cache);
+            let _ = tx.send((path, result));
+        });
+        vec![]
+    }
 
-        let input_node = if let Some(desc) = saved_desc {
-            // Prefer the saved source
-            self.sinks.iter()
-                .find(|s| s.kind == crate::pipewire::DeviceKind::Input && s.description == desc)
-                .or_else(|| self.sinks.iter().find(|s| s.kind == crate::pipewire::DeviceKind::Input))
+    /// Finished envelopes, for every client, and why the others failed,
+    /// addressed to the clients that asked for them.
+    pub fn poll_waveforms(&mut self) -> (Vec<DaemonEvent>, Vec<(Option<u64>, DaemonEvent)>) {
+        let mut done = Vec::new();
+        let mut failed = Vec::new();
+        while let Ok((path, result)) = self.waveform_rx.try_recv() {
+            let requesters = self.waveforms_pending.remove(&path).unwrap_or_default();
+            match result {
+                Ok(peaks) => {
+                    done.push(DaemonEvent::Waveform {
+                        path: path.display().to_string(),
+                        peaks,
+                    });
+                }
+                Err(e) => {
+                    LOG.error(&format!("Waveform of {} failed: {e:#}", path.display()));
+                    let song = self.songs.iter().find(|s| s.path == path);
+                    let name = song.map_or("song", |s| &s.name);
+                    let event = DaemonEvent::CommandResult(CommandResult::Error(format!(
+                        "No waveform for '{name}': {e:#}"
+                    )));
+                    failed.extend(requesters.into_iter().map(|client| (client, event.clone())));
+                }
+            }
+        }
+        (done, failed)
+    }
+
+    /// Flag the songs a presence check found gone or back, and start the
+    /// next check once `PRESENCE_INTERVAL` has passed. The files are looked
+    /// for on a worker, so a slow network mount holds up that rather than
+    /// the daemon.
+    pub fn poll_presence(&mut self, now: std::time::Instant) -> Vec<DaemonEvent> {
+        let mut changed = false;
+        while let Ok(found) = self.presence_rx.try_recv() {
+            self.presence_due = Some(now + PRESENCE_INTERVAL);
+            for (path, present) in found {
+                // Removed meanwhile, or already flagged by a play
+                let song = self.songs.iter_mut().find(|s| s.path == path);
+                let Some(song) = song.filter(|s| s.missing == present) else {
+                    continue;
+                };
+                song.missing = !present;
+                let change = if present { "is back" } else { "went missing" };
+                LOG.info(&format!("Song file {change}: {}", path.display()));
+                changed = true;
+            }
+        }
+        if self.presence_due.is_some_and(|due| now >= due) {
+            self.presence_due = None;
+            let songs: Vec<(PathBuf, bool)> = self
+                .songs
+                .iter()
+                .map(|s| (s.path.clone(), s.missing))
+                .collect();
+            let tx = self.presence_tx.clone();
+            std::thread::spawn(move || {
+                let found = songs
+                    .into_iter()
+                    .filter_map(|(path, missing)| {
+                        let present = path.exists();
+                        (present == missing).then_some((path, present))
+                    })
+                    .collect();
+                let _ = tx.send(found);
+            });
+        }
+        if !changed {
+            return Vec::new();
+        }
+        self.songs_revision += 1;
+        vec![self.songs_updated()]
+    }
+
+    /// Fill levels and eviction counts of the in-memory histories, for `GetStats`.
+    pub fn history_stats(&self) -> Vec<HistoryStats> {
+        fn stats<T>(name: &str, ring: &Ring<T>) -> HistoryStats {
+            HistoryStats {
+                name: name.to_string(),
+                len: ring.len(),
+                cap: ring.cap(),
+                evicted: ring.evicted(),
+            }
+        }
+        #[cfg(feature = "transcriber")]
+        let detections = Some(stats("detections", &self.detection_history));
+        #[cfg(not(feature = "transcriber"))]
+        let detections = None;
+        std::iter::once(stats("playback", &self.playback_history))
+            .chain(detections)
+            .collect()
+    }
+
+    /// The retained history entries, for state dumps.
+    pub fn history_snapshot(&self) -> serde_json::Value {
+        #[cfg(feature = "transcriber")]
+        let detections = Some(&self.detection_history);
+        #[cfg(not(feature = "transcriber"))]
+        let detections: Option<&Ring<()>> = None;
+        serde_json::json!({ "playback": self.playback_history, "detections": detections })
+    }
+
+    fn fx(&self) -> FxSettings {
+        FxSettings {
+            comfort_noise: self.comfort_noise,
+            noise_kind: self.noise_kind,
+            eq: self.eq,
+            fade_secs: self.fade_secs,
+            limiter: self.limiter,
+        }
+    }
+
+    fn songs_updated(&self) -> DaemonEvent {
+        DaemonEvent::SongsUpdated {
+            revision: self.songs_revision,
+            songs: self.song_page(0, SONG_PAGE_LIMIT),
+        }
+    }
+
+    pub fn snapshot(&self) -> DaemonState {
+        DaemonState {
+            sinks: self.sinks_to_info(),
+            songs: self.song_page(0, SONG_PAGE_LIMIT),
+            songs_total: self.songs.len(),
+            songs_revision: self.songs_revision,
+            selected_sink: self.selected_sink,
+            monitor_sink: self.monitor_index(),
+            selected_song: self.selected_song,
+            volume: self.volume,
+            comfort_noise: self.comfort_noise,
+            noise_kind: self.noise_kind,
+            eq: self.eq,
+            fade_secs: self.fade_secs,
+            limiter: self.limiter,
+            now_playing: self.now_playing.clone(),
+            paused: self.paused,
+            queue: self.queue_info(),
+            loop_enabled: self.loop_enabled,
+            continuous_noise: self.continuous_noise,
+            sidechain_enabled: self.sidechain_enabled,
+            first_run: self.first_run && self.songs.is_empty(),
+            confirm_delete: self.confirm_delete,
+            tag_palette: self.tag_palette.clone(),
+            song_sort: self.song_sort,
+            #[cfg(feature = "transcriber")]
+            word_detector_status: self.word_detector_status.clone(),
+            #[cfg(feature = "transcriber")]
+            word_mappings: self.word_mappings.clone(),
+            #[cfg(feature = "transcriber")]
+            word_mappings_muted: self.word_mappings_muted,
+            #[cfg(feature = "transcriber")]
+            detector_capturing: self.detector_capture.lock().unwrap().is_some(),
+            #[cfg(feature = "transcriber")]
+            detector_source: self.detector_source.clone(),
+            #[cfg(feature = "transcriber")]
+            detector_power_mode: self.detector_power_mode,
+            #[cfg(feature = "transcriber")]
+            vosk_missing: self.vosk_missing.clone(),
+        }
+    }
+
+    fn queue_info(&self) -> Vec<String> {
+        self.queue
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect()
+    }
+
+    fn song_page(&self, offset: usize, limit: usize) -> Vec<SongInfo> {
+        self.songs
+            .iter()
+            .skip(offset)
+            .take(limit.min(SONG_PAGE_LIMIT))
+            .map(|s| SongInfo {
+                path: s.path.display().to_string(),
+                name: s.name.clone(),
+                undecodable: s.undecodable,
+                missing: s.missing,
+                tag_color: s.tag_color.clone(),
+                volume: s.volume,
+                duration: s.duration,
+            })
+            .collect()
+    }
+
+    fn sinks_to_info(&self) -> Vec<SinkInfo> {
+        self.sinks
+            .iter()
+            .map(|s| SinkInfo {
+                id: s.id,
+                name: s.name.clone(),
+                description: s.description.clone(),
+                kind: s.kind.name().to_string(),
+                is_default: s.id != DEFAULT_SINK_ID && self.default_sink.as_ref() == Some(&s.name),
+            })
+            .collect()
+    }
+
+    /// FX for a normal playback, taken from the current global settings.
+    fn playback_options(&self) -> PlaybackOptions {
+        let fade_ms = (self.fade_secs * 1000.0).round() as u32;
+        PlaybackOptions {
+            // The clip's own factor; the global volume is sent on its own
+            volume: 1.0,
+            noise_kind: self.noise_kind,
+            fade_in_ms: fade_ms,
+            fade_out_ms: fade_ms,
+            noise_tail_ms: self.noise_tail_ms,
+            limiter: self.limiter,
+            mono_downmix: false,
+            sidechain: self.sidechain_enabled,
+            native_volume: self.native_volume,
+        }
+    }
+
+    /// Injection target for continuous noise: the selected sink, when it is
+    /// a capture stream and the mode is on.
+    fn noise_target(&self) -> Option<u32> {
+        if !self.continuous_noise {
+            return None;
+        }
+        self.sinks
+            .get(self.selected_sink)
+            .filter(|s| s.kind == DeviceKind::Input)
+            .map(|s| s.id)
+    }
+
+    /// Send volume, comfort noise and EQ, which also reach the clip
+    /// playing now.
+    fn sync_params(&self) {
+        // Clips go to the selected sink; if the continuous stream already
+        // covers it, don't stack a second layer of noise on top
+        let comfort_noise = if self.noise_target().is_some() {
+            0.0
         } else {
-            // Fallback: first available input
-            self.sinks.iter().find(|s| s.kind == crate::pipewire::DeviceKind::Input)
+            self.comfort_noise
         };
+        let _ = self.pw_cmd_tx.send(PwCommand::UpdateParams(LiveParams {
+            volume: self.volume,
+            comfort_noise,
+            eq: self.eq,
+        }));
+    }
 
-        if let Some(node) = input_node {
-            let node_id = node.id;
-            crate::log::log_info(&format!(
-                "Auto-starting detector with input node {} ({})",
-                node_id, node.description
+    /// Also resends the clip params, since the noise target decides
+    /// whether clips carry their own noise.
+    fn sync_continuous_noise(&self) {
+        let _ = self.pw_cmd_tx.send(PwCommand::ContinuousNoise {
+            target_id: self.noise_target(),
+            level: self.comfort_noise,
+            kind: self.noise_kind,
+        });
+        self.sync_params();
+    }
+
+    /// The monitor output among the current sinks, if it is connected.
+    fn monitor_index(&self) -> Option<usize> {
+        let description = self.monitor_sink.as_deref()?;
+        self.sinks
+            .iter()
+            .position(|s| s.kind == DeviceKind::Output && s.description == description)
+    }
+
+    /// Hand the configured stream properties to the backend; injection
+    /// streams get the enabled hints first, so explicit entries override them.
+    fn sync_stream_properties(&self) {
+        let playback: Vec<(String, String)> = self
+            .stream_properties
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let hints = [
+            (
+                self.injection_hints.communication_role,
+                "media.role",
+                "Communication",
+            ),
+            (
+                self.injection_hints.echo_cancel,
+                "filter.want",
+                "echo-cancel",
+            ),
+        ];
+        let injection = hints
+            .into_iter()
+            .filter(|(enabled, ..)| *enabled)
+            .map(|(_, key, value)| (key.to_string(), value.to_string()))
+            .chain(playback.iter().cloned())
+            .collect();
+        let _ = self
+            .pw_cmd_tx
+            .send(PwCommand::SetStreamProperties(StreamProperties {
+                playback,
+                injection,
+            }));
+    }
+
+    /// Persist word statistics if they changed and the last save is older
+    /// than a minute, so a busy detector doesn't rewrite the config per hit.
+    #[cfg(feature = "transcriber")]
+    pub fn flush_word_stats(&self) {
+        const STATS_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+        if self.word_stats_dirty.get()
+            && self.word_stats_saved_at.get().elapsed() >= STATS_SAVE_INTERVAL
+        {
+            self.save_config();
+        }
+    }
+
+    fn status(&self) -> StatusFile {
+        #[cfg(feature = "transcriber")]
+        let detector_status = match self.word_detector_status {
+            WordDetectorStatus::Unavailable => "unavailable",
+            WordDetectorStatus::Downloading => "downloading",
+            WordDetectorStatus::DownloadFailed(_) => "download_failed",
+            WordDetectorStatus::Ready => "ready",
+            WordDetectorStatus::Running => "running",
+        };
+        #[cfg(not(feature = "transcriber"))]
+        let detector_status = "unavailable";
+
+        StatusFile {
+            now_playing: self.now_playing.clone(),
+            volume: self.volume,
+            detector_status: detector_status.to_string(),
+            last_detected_word: self.last_detected_word.clone(),
+            ts: 0,
+        }
+    }
+
+    /// Rewrite the status file if something in it changed, at most four
+    /// times a second. The write goes through a temp file and a rename so
+    /// readers never see a partial document.
+    pub fn write_status(&mut self) {
+        const STATUS_WRITE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+        let Some(path) = &self.status_file else {
+            return;
+        };
+        let status = self.status();
+        if let Some((last, at)) = &self.status_written {
+            if *last == status || at.elapsed() < STATUS_WRITE_INTERVAL {
+                return;
+            }
+        }
+
+        let json = match serde_json::to_vec(&StatusFile {
+            ts: unix_now(),
+            ..status.clone()
+        }) {
+            Ok(json) => json,
+            Err(e) => {
+                LOG.error(&format!("Failed to encode status: {e}"));
+                return;
+            }
+        };
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        let written = std::fs::write(&tmp, json).and_then(|()| std::fs::rename(&tmp, path));
+        if let Err(e) = written {
+            LOG.error(&format!(
+                "Failed to write status file {}: {e}",
+                path.display()
             ));
-            self.start_detector(node_id);
         }
+        // Recorded even on failure so a broken path isn't retried every tick
+        self.status_written = Some((status, std::time::Instant::now()));
     }
 
-    #[cfg(feature = "transcriber")]
-    fn start_detector(&mut self, node_id: u32) {
-        crate::log::log_info(&format!("start_detector called with node_id={}", node_id));
+    fn runtime_state(&self) -> RuntimeState {
+        RuntimeState {
+            selected_song: self.songs.get(self.selected_song).map(|s| s.path.clone()),
+            queue: self.queue.iter().cloned().collect(),
+            #[cfg(feature = "transcriber")]
+            detector_stopped: self.detector_stopped,
+        }
+    }
+
+    /// Save the runtime state if it changed, at most once a second; `force`
+    /// skips the wait, for shutdown.
+    pub fn save_state(&mut self, force: bool) {
+        const STATE_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+        let state = self.runtime_state();
+        if let Some((last, at)) = &self.state_written {
+            if *last == state || (!force && at.elapsed() < STATE_SAVE_INTERVAL) {
+                return;
+            }
+        }
+        state.save(&self.state_path);
+        self.state_written = Some((state, std::time::Instant::now()));
+    }
+
+    /// Ask PipeWire for devices again while none are known, backing off
+    /// from `SINK_RETRY_MIN` to `SINK_RETRY_MAX`.
+    pub fn retry_sinks(&mut self) {
+        let Some((at, delay)) = self.sink_retry else {
+            return;
+        };
+        if std::time::Instant::now() < at {
+            return;
+        }
+        let _ = self.pw_cmd_tx.send(PwCommand::ListSinks);
+        let delay = (delay * 2).min(SINK_RETRY_MAX);
+        self.sink_retry = Some((std::time::Instant::now() + delay, delay));
+    }
+
+    /// Close the long-running streams and wait for their threads before the
+    /// daemon exits.
+    pub fn shutdown(&mut self) {
+        self.save_state(true);
+        #[cfg(feature = "transcriber")]
+        if self.word_stats_dirty.get() {
+            self.save_config();
+        }
+        if let Some(path) = &self.status_file {
+            let _ = std::fs::remove_file(path);
+        }
+        let _ = self.pw_cmd_tx.send(PwCommand::ContinuousNoise {
+            target_id: None,
+            level: 0.0,
+            kind: NoiseKind::Off,
+        });
+        self.sidechain_enabled = false;
+        self.sync_sidechain();
+        #[cfg(feature = "transcriber")]
         self.stop_detector();
+        #[cfg(feature = "live-stream")]
+        {
+            self.live_server = None;
+        }
+        let _ = self.pw_cmd_tx.send(PwCommand::Shutdown);
 
-        let model = crate::protocol::model_path();
-        let model_str = model.display().to_string();
-        let keywords: Vec<String> = self.word_mappings.iter().map(|wm| wm.word.clone()).collect();
+        let threads = [
+            ("PipeWire", self.pw_thread.take()),
+            ("Sidechain", self.sidechain_thread.take()),
+            #[cfg(feature = "transcriber")]
+            ("Detector", self.detector_thread.take()),
+        ];
+        for (name, thread) in threads {
+            if thread.is_some_and(|t| t.join().is_err()) {
+                LOG.error(&format!("{name} thread panicked"));
+            }
+        }
+    }
 
-        if keywords.is_empty() {
-            crate::log::log_info("start_detector: no keywords, returning");
+    /// Start or stop the mic sidechain so it matches `sidechain_enabled`.
+    /// Starting waits until an input source shows up among the sinks.
+    fn sync_sidechain(&mut self) {
+        if !self.sidechain_enabled {
+            if let Some(tx) = self.sidechain_stop_tx.take() {
+                let _ = tx.send(());
+            }
+            return;
+        }
+        if self.sidechain_stop_tx.is_some() {
             return;
         }
 
-        crate::log::log_info(&format!(
-            "Starting detector: model={}, keywords={:?}, node={}",
-            model_str, keywords, node_id
-        ));
+        let inputs = || self.sinks.iter().filter(|s| s.kind.is_capture());
+        let source = inputs()
+            .find(|s| s.description == self.sidechain_source)
+            .or_else(|| inputs().find(|s| s.kind == DeviceKind::Source))
+            .or_else(|| inputs().next());
 
-        let (stop_tx, stop_rx) = std::sync::mpsc::channel();
-        let (match_tx, match_rx) = std::sync::mpsc::channel();
+        if let Some(source) = source {
+            LOG.info(&format!(
+                "Starting sidechain on node {} ({})",
+                source.id, source.description
+            ));
+            let (stop_tx, stop_rx) = std::sync::mpsc::channel();
+            let thread =
+                crate::sidechain::spawn_sidechain(source.id, self.sidechain_settings, stop_rx);
+            self.sidechain_thread = Some(thread);
+            self.sidechain_stop_tx = Some(stop_tx);
+        }
+    }
 
-        self.detector_stop_tx = Some(stop_tx);
-        self.detector_match_rx = Some(match_rx);
-        self.word_detector_status = WordDetectorStatus::Running;
+    fn set_paused(&mut self, paused: bool) -> Vec<DaemonEvent> {
+        if self.now_playing.is_none() || self.paused == paused {
+            return vec![];
+        }
+        self.paused = paused;
+        let _ = self.pw_cmd_tx.send(PwCommand::SetPaused(paused));
+        vec![DaemonEvent::Paused(paused)]
+    }
 
-        std::thread::spawn(move || {
-            crate::log::log_info("Detector thread started");
-            if let Err(e) = plentysound_transcriber::detector::run_detector(
-                &model_str,
-                &keywords,
-                node_id,
-                stop_rx,
-                move |word| {
-                    crate::log::log_info(&format!("Detector matched word: \"{}\"", word));
-                    let _ = match_tx.send(word);
-                },
-                |msg| {
-                    crate::log::log_info(msg);
-                },
-            ) {
-                crate::log::log_error(&format!("Detector error: {e:#}"));
+    /// `Play` and `PlaySong`: start song `index` on the selected sink, or
+    /// say why it can't.
+    fn play(&mut self, index: usize) -> Vec<DaemonEvent> {
+        if self.sinks.is_empty() {
+            return vec![no_devices_error()];
+        }
+        if let Some(message) = self.unplayable_sink() {
+            return self.rejected(CommandResult::Error(message));
+        }
+        if let Err(e) = self.check_present(index) {
+            return self.rejected(e);
+        }
+        if !self.songs.is_empty() {
+            self.play_song(index, self.selected_sink);
+        }
+        vec![DaemonEvent::NowPlaying(self.now_playing.clone())]
+    }
+
+    /// Start the first queued song that still decodes, on the selected sink.
+    /// With no sinks the queue is left for later.
+    fn play_next_queued(&mut self) {
+        while !self.sinks.is_empty() {
+            let Some(path) = self.queue.pop_front() else {
+                return;
+            };
+            let index = self.songs.iter().position(|s| s.path == path);
+            if index.is_some_and(|index| self.play_song(index, self.selected_sink)) {
+                return;
             }
-            crate::log::log_info("Detector thread exiting");
+        }
+    }
+
+    /// Send song `index` to sink `sink`, leaving both selections alone.
+    /// Detector and queue playback go through here so they never move what
+    /// the user picked. False if there is no such sink or the file didn't
+    /// decode.
+    fn play_song(&mut self, index: usize, sink: usize) -> bool {
+        if let Err(e) = self.check_present(index) {
+            self.decode_errors.push(e.message().to_string());
+            return false;
+        }
+        let (Some(song), Some(sink)) = (self.songs.get(index), self.sinks.get(sink)) else {
+            return false;
+        };
+
+        let clip = Arc::new(Clip {
+            path: song.path.clone(),
+            name: song.name.clone(),
+            volume: song.volume.unwrap_or(1.0),
+            sink: sink.clone(),
         });
+        self.start_clip(clip)
     }
 
-    #[cfg(feature = "transcriber")]
-    fn stop_detector(&mut self) {
-        crate::log::log_info("stop_detector called");
-        if let Some(tx) = self.detector_stop_tx.take() {
-            let _ = tx.send(());
+    /// Look for song `index`'s file again, flagging the song missing or
+    /// clearing the flag once the file is back. Out-of-range indices pass.
+    fn check_present(&mut self, index: usize) -> Result<(), CommandResult> {
+        let Some(song) = self.songs.get_mut(index) else {
+            return Ok(());
+        };
+        let missing = !song.path.exists();
+        if missing != song.missing {
+            song.missing = missing;
+            self.songs_revision += 1;
         }
-        self.detector_match_rx = None;
-        if self.word_detector_status == WordDetectorStatus::Running {
-            self.word_detector_status = WordDetectorStatus::Ready;
+        if missing {
+            let (name, path) = (&song.name, song.path.display());
+            return Err(CommandResult::Error(format!(
+                "'{name}' is missing: {path} not found"
+            )));
         }
+        Ok(())
     }
 
-    #[cfg(feature = "transcriber")]
-    pub fn poll_detector_matches(&mut self) -> Vec<DaemonEvent> {
-        // Drain all matches first to release the borrow on self
-        let words: Vec<String> = self
-            .detector_match_rx
-            .as_ref()
-            .map(|rx| {
-                let mut v = Vec::new();
-                while let Ok(word) = rx.try_recv() {
-                    v.push(word);
-                }
-                v
-            })
-            .unwrap_or_default();
+    /// Drop the songs whose files are gone, checking each one again first.
+    /// Bindings lose those songs too, and go once none are left.
+    fn prune_missing(&mut self) -> Vec<DaemonEvent> {
+        for index in 0..self.songs.len() {
+            let _ = self.check_present(index);
+        }
+        let selected_path = self.songs.get(self.selected_song).map(|s| s.path.clone());
+        let (missing, kept): (Vec<Song>, Vec<Song>) = std::mem::take(&mut self.songs)
+            .into_iter()
+            .partition(|s| s.missing);
+        self.songs = kept;
+        if missing.is_empty() {
+            return self.rejected(CommandResult::Ok("No missing songs".to_string()));
+        }
+        self.songs_revision += 1;
+        self.selected_song = selected_path
+            .and_then(|p| self.songs.iter().position(|s| s.path == p))
+            .unwrap_or(0);
+        let gone = |path: &PathBuf| missing.iter().any(|s| s.path == *path);
+        self.queue.retain(|path| !gone(path));
+        for song in &missing {
+            LOG.info(&format!("Pruned missing song {}", song.path.display()));
+        }
 
-        let mut events = Vec::new();
-        for word in words {
-            let mapping = self
-                .word_mappings
+        #[allow(unused_mut)]
+        let mut summary = match missing.len() {
+            1 => format!("Removed missing '{}'", missing[0].name),
+            n => format!("Removed {n} missing songs"),
+        };
+        #[cfg(feature = "transcriber")]
+        {
+            let paths: Vec<String> = missing
                 .iter()
-                .find(|wm| wm.word == word)
-                .cloned();
-            if let Some(mapping) = mapping {
-                self.play_song_by_path(&mapping.song_path);
-                events.push(DaemonEvent::WordDetected(word));
+                .map(|s| s.path.display().to_string())
+                .collect();
+            let bindings = self.drop_bindings_to(&paths);
+            if bindings > 0 {
+                let plural = if bindings == 1 { "" } else { "s" };
+                let bound = format!(" and {bindings} word binding{plural} left without songs");
+                summary.push_str(&bound);
             }
         }
-        events
+        self.save_config();
+        vec![
+            DaemonEvent::State(Box::new(self.snapshot())),
+            DaemonEvent::CommandResult(CommandResult::Ok(summary)),
+        ]
+    }
+
+    /// Take `paths` out of every binding, removing song bindings left with
+    /// none. Returns how many bindings were removed.
+    #[cfg(feature = "transcriber")]
+    fn drop_bindings_to(&mut self, paths: &[String]) -> usize {
+        let before = self.word_mappings.len();
+        self.song_picks.resize(before, None);
+        for (wm, last) in self.word_mappings.iter_mut().zip(&mut self.song_picks) {
+            if !wm.song_paths.iter().any(|p| paths.contains(p)) {
+                continue;
+            }
+            let (song_paths, song_names) = wm
+                .song_paths
+                .drain(..)
+                .zip(wm.song_names.drain(..))
+                .filter(|(path, _)| !paths.contains(path))
+                .unzip();
+            wm.song_paths = song_paths;
+            wm.song_names = song_names;
+            // The next pick indexes into the shortened list
+            *last = None;
+        }
+        let dropped =
+            |wm: &WordMapping| wm.song_paths.is_empty() && wm.action == MappingAction::PlaySong;
+        let mut mappings = self.word_mappings.iter();
+        self.song_picks
+            .retain(|_| !mappings.next().is_some_and(dropped));
+        self.word_mappings.retain(|wm| !dropped(wm));
+        let removed = before - self.word_mappings.len();
+        if removed > 0 {
+            self.refresh_detector_keywords();
+        }
+        removed
+    }
+
+    /// Start decoding `clip` and hand the stream to the backend as a new
+    /// playback. False if the file couldn't be opened for decoding.
+    fn start_clip(&mut self, clip: Arc<Clip>) -> bool {
+        match crate::audio::stream_file(&clip.path) {
+            Ok(audio) => {
+                #[cfg(feature = "transcriber")]
+                if let Some(timing) = self.trigger_timing.as_mut().filter(|t| t.id.is_none()) {
+                    timing.decoded = Some(std::time::Instant::now());
+                }
+                self.send_clip(clip, audio);
+                true
+            }
+            Err(e) => {
+                let message = format!("Failed to decode {}: {e}", clip.name);
+                LOG.error(&message);
+                self.decode_errors.push(message);
+                false
+            }
+        }
+    }
+
+    /// Cut every playback short, leaving the queue alone.
+    fn stop_playback(&mut self) -> [DaemonEvent; 2] {
+        self.pending_playbacks.clear();
+        self.now_playing = None;
+        self.paused = false;
+        let _ = self.pw_cmd_tx.send(PwCommand::StopPlayback);
+        [DaemonEvent::PlaybackFinished, DaemonEvent::NowPlaying(None)]
+    }
+
+    /// Cut just the playbacks `ids` short, leaving the others and the queue.
+    fn stop_playbacks(&mut self, ids: &[u64]) -> Vec<DaemonEvent> {
+        self.pending_playbacks.retain(|(id, ..)| !ids.contains(id));
+        let _ = self.pw_cmd_tx.send(PwCommand::StopPlaybacks(ids.to_vec()));
+        let playing = self
+            .pending_playbacks
+            .front()
+            .map(|(_, clip)| clip.name.clone());
+        if playing.is_some() {
+            if playing == self.now_playing {
+                return vec![];
+            }
+            self.now_playing = playing;
+            return vec![DaemonEvent::NowPlaying(self.now_playing.clone())];
+        }
+        self.now_playing = None;
+        if self.paused {
+            self.paused = false;
+            let _ = self.pw_cmd_tx.send(PwCommand::SetPaused(false));
+        }
+        vec![DaemonEvent::PlaybackFinished, DaemonEvent::NowPlaying(None)]
+    }
+
+    /// Why the selected sink can't take a song, if it can't: a capture
+    /// device, or an app's capture stream the selection only fell back to
+    /// because the outputs went away.
+    fn unplayable_sink(&self) -> Option<String> {
+        let sink = self.sinks.get(self.selected_sink)?;
+        let picked = self
+            .saved_sink
+            .as_ref()
+            .is_some_and(|saved| saved.name == sink.name);
+        match sink.kind {
+            DeviceKind::Output => None,
+            DeviceKind::Input if picked => None,
+            _ => Some(format!(
+                "'{}' is not an output; pick one to play on",
+                sink.description
+            )),
+        }
+    }
+
+    fn send_clip(&mut self, clip: Arc<Clip>, audio: crate::audio::AudioStream) {
+        let id = self.next_playback_id;
+        self.next_playback_id += 1;
+        let interrupt = self.playback_mode == PlaybackMode::Interrupt;
+        if interrupt {
+            self.pending_playbacks.clear();
+        }
+        #[cfg(feature = "transcriber")]
+        let timed = match self.trigger_timing.as_mut() {
+            Some(timing) if timing.id.is_none() => {
+                timing.id = Some(id);
+                true
+            }
+            _ => false,
+        };
+        #[cfg(not(feature = "transcriber"))]
+        let timed = false;
+        self.pending_playbacks.push_back((id, clip.clone()));
+        self.now_playing = self
+            .pending_playbacks
+            .front()
+            .map(|(_, clip)| clip.name.clone());
+        if self.paused {
+            // A new clip shouldn't start out frozen
+            self.paused = false;
+            let _ = self.pw_cmd_tx.send(PwCommand::SetPaused(false));
+        }
+        let _ = self.pw_cmd_tx.send(PwCommand::Play {
+            id,
+            song: clip.name.clone(),
+            interrupt,
+            sink_id: clip.sink.id,
+            kind: clip.sink.kind,
+            node_name: clip.sink.name.clone(),
+            audio,
+            options: PlaybackOptions {
+                volume: clip.volume,
+                ..self.playback_options()
+            },
+            timed,
+            monitor_sink: match clip.sink.kind {
+                DeviceKind::Input => self.monitor_index().map(|i| self.sinks[i].id),
+                DeviceKind::Output | DeviceKind::Source => None,
+            },
+        });
+    }
+
+    /// Play a bound song on the selected sink without selecting it.
+    #[cfg(feature = "transcriber")]
+    pub fn play_song_by_path(&mut self, song_path: &str) {
+        let song_idx = self
+            .songs
+            .iter()
+            .position(|s| s.path.display().to_string() == song_path);
+        if let Some(idx) = song_idx {
+            self.play_song(idx, self.selected_sink);
+        }
+    }
+
+    /// Try to auto-start the detector if the model is ready, there are word
+    /// mappings, and we can find a matching input source among discovered sinks.
+    /// A detector stopped by hand stays stopped, across restarts too.
+    #[cfg(feature = "transcriber")]
+    pub fn try_autostart_detector(&mut self) {
+        if self.word_detector_status != WordDetectorStatus::Ready {
+            return;
+        }
+        if self.word_mappings.is_empty() || self.sinks.is_empty() || self.detector_stopped {
+            return;
+        }
+        // Already running
+        if self.detector_stop_tx.is_some() {
+            return;
+        }
+
+        // Try to match the source_description from the first mapping that has one
+        let saved_desc = self.word_mappings.iter()
+            .map(|wm| wm.source_description.as_str())
+            .find(|d| !d.is_empty());
+
+        let input_node = if let Some(desc) = saved_desc {
+            // Prefer the saved source
+            self.sinks.iter()
+                .find(|s| s.kind.is_capture() && s.description == desc)
+                .or_else(|| self.sinks.iter().find(|s| s.kind.is_capture()))
+        } else {
+            // Fallback: first available input
+            self.sinks.iter().find(|s| s.kind.is_capture())
+        };
+
+        if let Some(node) = input_node {
+            let node_id = node.id;
+            DETECTOR_LOG.info(&format!(
+                "Auto-starting detector with input node {} ({})",
+                node_id, node.description
+            ));
+            self.start_detector(node_id);
+        }
+    }
+
+    #[cfg(feature = "transcriber")]
+    fn start_detector(&mut self, node_id: u32) {
+        DETECTOR_LOG.info(&format!("start_detector called with node_id={}", node_id));
+        self.stop_detector();
+
+        let model = crate::protocol::model_path();
+        let model_str = model.display().to_string();
+        let keywords = self.active_keywords();
+        let preprocess = self.detector_preprocess;
+        let power = PowerMode::from(self.detector_power_mode);
+        let matcher = matcher_for(&self.detector_matchers, crate::protocol::MODEL_SUBDIR);
+        let capture = self.detector_capture.clone();
+
+        if keywords.is_empty() {
+            DETECTOR_LOG.info("start_detector: no keywords, returning");
+            return;
+        }
+
+        DETECTOR_LOG.info(&format!(
+            "Starting detector: model={}, keywords={:?}, node={}",
+            model_str, keywords, node_id
+        ));
+
+        let (stop_tx, stop_rx) = std::sync::mpsc::channel();
+        let (match_tx, match_rx) = std::sync::mpsc::channel::<DetectorEvent>();
+
+        let source = self
+            .sinks
+            .iter()
+            .find(|s| s.id == node_id)
+            .map(|s| s.description.as_str())
+            .unwrap_or("unknown source");
+
+        self.detector_stop_tx = Some(stop_tx);
+        self.detector_match_rx = Some(match_rx);
+        self.detector_node = Some(node_id);
+        self.detector_source = Some(format!("{} (node {})", source, node_id));
+        self.word_detector_status = WordDetectorStatus::Running;
+
+        self.detector_thread = Some(std::thread::spawn(move || {
+            DETECTOR_LOG.info("Detector thread started");
+            if let Err(e) = plentysound_transcriber::detector::run_detector(
+                &model_str,
+                &keywords,
+                node_id,
+                preprocess,
+                matcher,
+                power,
+                capture,
+                stop_rx,
+                move |event| {
+                    DETECTOR_LOG.info(&format!("Detector event: {:?}", event));
+                    let _ = match_tx.send(event);
+                },
+                |msg| DETECTOR_LOG.info(msg),
+                |msg| DETECTOR_LOG.debug(msg),
+            ) {
+                DETECTOR_LOG.error(&format!("Detector error: {e:#}"));
+            }
+            DETECTOR_LOG.info("Detector thread exiting");
+        }));
+    }
+
+    /// Start writing the detector's input to `path`. Only while it runs, and
+    /// one capture at a time.
+    #[cfg(feature = "transcriber")]
+    fn start_capture(&mut self, path: &Path) -> CommandResult {
+        if self.detector_stop_tx.is_none() {
+            return CommandResult::Error("Word detector is not running".to_string());
+        }
+        let mut slot = self.detector_capture.lock().unwrap();
+        if let Some(writer) = slot.as_ref() {
+            return CommandResult::Error(format!(
+                "Already capturing to {}",
+                writer.path().display()
+            ));
+        }
+        match CaptureWriter::create(path, MAX_CAPTURE_SAMPLES) {
+            Ok(writer) => {
+                *slot = Some(writer);
+                DETECTOR_LOG.info(&format!("Capturing detector input to {}", path.display()));
+                CommandResult::Ok(format!("Capturing detector input to {}", path.display()))
+            }
+            Err(e) => CommandResult::Error(format!("{e:#}")),
+        }
+    }
+
+    /// Words of the bindings that may fire right now.
+    #[cfg(feature = "transcriber")]
+    fn active_keywords(&self) -> Vec<String> {
+        if self.word_mappings_muted {
+            return Vec::new();
+        }
+        self.word_mappings
+            .iter()
+            .filter(|wm| wm.enabled)
+            .map(|wm| wm.word.clone())
+            .collect()
+    }
+
+    /// Description of the input the running detector listens to, which
+    /// `only_from_source` restrictions are matched against.
+    #[cfg(feature = "transcriber")]
+    fn detector_input(&self) -> Option<String> {
+        let node = self.detector_node?;
+        self.sinks
+            .iter()
+            .find(|s| s.id == node)
+            .map(|s| s.description.clone())
+    }
+
+    /// Restart a running detector so its grammar matches the enabled
+    /// bindings. With nothing enabled it keeps its old grammar; the hits
+    /// are dropped in `handle_detection`.
+    #[cfg(feature = "transcriber")]
+    fn refresh_detector_keywords(&mut self) {
+        let Some(node_id) = self.detector_node else {
+            return;
+        };
+        if self.active_keywords().is_empty() {
+            return;
+        }
+        DETECTOR_LOG.info("Enabled bindings changed, restarting detector");
+        self.start_detector(node_id);
+    }
+
+    #[cfg(feature = "transcriber")]
+    fn stop_detector(&mut self) {
+        DETECTOR_LOG.info("stop_detector called");
+        if let Some(tx) = self.detector_stop_tx.take() {
+            let _ = tx.send(());
+        }
+        if let Some(writer) = self.detector_capture.lock().unwrap().take() {
+            match writer.finish() {
+                Ok(path) => DETECTOR_LOG.info(&format!(
+                    "Detector stopped, capture saved to {}",
+                    path.display()
+                )),
+                Err(e) => DETECTOR_LOG.error(&format!("{e:#}")),
+            }
+        }
+        self.detector_match_rx = None;
+        self.detector_source = None;
+        self.detector_node = None;
+        if self.word_detector_status == WordDetectorStatus::Running {
+            self.word_detector_status = WordDetectorStatus::Ready;
+        }
+    }
+
+    /// Route one detector hit to its mapping: update the binding's stats and
+    /// play its song. `simulated` hits come from `SimulateWord` and follow the
+    /// same path. Returns whether a mapping matched.
+    #[cfg(feature = "transcriber")]
+    fn handle_detection(
+        &mut self,
+        detection: DetectorEvent,
+        simulated: bool,
+        events: &mut Vec<DaemonEvent>,
+    ) -> bool {
+        let (word, matched) = match detection {
+            DetectorEvent::Matched(word, at) => (word, Some(at)),
+            DetectorEvent::Duplicate(word) => (word, None),
+            DetectorEvent::CaptureSaved(_) | DetectorEvent::CaptureFailed(_) => return false,
+        };
+        if self.word_mappings_muted {
+            return false;
+        }
+        let source = self.detector_input();
+        let Some(idx) = resolve_binding(&self.word_mappings, &word, source.as_deref()) else {
+            return false;
+        };
+        let only_from_source = self.word_mappings[idx].only_from_source.clone();
+        self.word_stats_dirty.set(true);
+        let Some(matched) = matched else {
+            self.word_mappings[idx].stats.suppressed_duplicate += 1;
+            return true;
+        };
+
+        if let MappingAction::Speak(phrase) = &self.word_mappings[idx].action {
+            let phrase = phrase.clone();
+            self.word_mappings[idx].stats.fired += 1;
+            if let Err(e) = self.speak(&phrase) {
+                LOG.error(&format!("Speak \"{word}\": {e:#}"));
+                events.push(DaemonEvent::CommandResult(CommandResult::Error(format!(
+                    "{e:#}"
+                ))));
+            }
+            self.last_detected_word = Some(word.clone());
+            self.detection_history.push(DetectedWord {
+                word: word.clone(),
+                simulated,
+                only_from_source: only_from_source.clone(),
+                at: unix_now(),
+            });
+            events.push(DaemonEvent::WordDetected {
+                word,
+                simulated,
+                song: None,
+            });
+            return true;
+        }
+
+        if let Some((song_path, song)) = self.pick_song(idx) {
+            self.word_mappings[idx].stats.fired += 1;
+            self.trigger_timing = Some(TriggerTiming {
+                word: word.clone(),
+                matched,
+                applied: std::time::Instant::now(),
+                decoded: None,
+                id: None,
+            });
+            self.play_song_by_path(&song_path);
+            if self.trigger_timing.as_ref().is_some_and(|t| t.id.is_none()) {
+                // Nothing was sent, so no first audio will come
+                self.trigger_timing = None;
+            }
+            self.last_detected_word = Some(word.clone());
+            self.detection_history.push(DetectedWord {
+                word: word.clone(),
+                simulated,
+                only_from_source: only_from_source.clone(),
+                at: unix_now(),
+            });
+            events.push(DaemonEvent::WordDetected {
+                word,
+                simulated,
+                song: Some(song),
+            });
+            events.push(DaemonEvent::NowPlaying(self.now_playing.clone()));
+        } else {
+            self.word_mappings[idx].stats.suppressed_excluded += 1;
+        }
+        true
+    }
+
+    /// Path and name of the song binding `idx` plays now, among its songs
+    /// still in the library. None if all of them are gone.
+    #[cfg(feature = "transcriber")]
+    fn pick_song(&mut self, idx: usize) -> Option<(String, String)> {
+        let wm = &self.word_mappings[idx];
+        let available: Vec<usize> = (0..wm.song_paths.len())
+            .filter(|&i| {
+                self.songs
+                    .iter()
+                    .any(|s| s.path.display().to_string() == wm.song_paths[i])
+            })
+            .collect();
+        let last = self.song_picks.get(idx).copied().flatten();
+        let pick = match wm.selection {
+            SongSelection::RoundRobin => available
+                .iter()
+                .copied()
+                .find(|&i| last.is_none_or(|last| i > last))
+                .or(available.first().copied())?,
+            SongSelection::Random => {
+                let fresh: Vec<usize> = available
+                    .iter()
+                    .copied()
+                    .filter(|&i| available.len() == 1 || Some(i) != last)
+                    .collect();
+                *fresh.get(random_index(fresh.len()))?
+            }
+        };
+        if self.song_picks.len() <= idx {
+            self.song_picks.resize(idx + 1, None);
+        }
+        self.song_picks[idx] = Some(pick);
+        Some((wm.song_paths[pick].clone(), wm.song_names[pick].clone()))
+    }
+
+    /// Render `phrase` on a worker thread; `poll_speech` plays the result.
+    #[cfg(feature = "transcriber")]
+    fn speak(&mut self, phrase: &str) -> anyhow::Result<()> {
+        let Some(template) = self.tts_command.clone() else {
+            anyhow::bail!("No tts_command configured for Speak bindings");
+        };
+        let text = crate::tts::expand_phrase(phrase, crate::tts::local_time());
+        let tx = self.speech_tx.clone();
+        let cache = self.tts_cache.clone();
+        std::thread::spawn(move || {
+            let result = crate::tts::synthesize(&template, &text, &cache);
+            let _ = tx.send((text, result));
+        });
+        Ok(())
+    }
+
+    /// Play finished speech on the selected sink, or report why TTS failed.
+    #[cfg(feature = "transcriber")]
+    pub fn poll_speech(&mut self) -> Vec<DaemonEvent> {
+        let mut events = Vec::new();
+        while let Ok((text, result)) = self.speech_rx.try_recv() {
+            let path = match result {
+                Ok(path) => path,
+                Err(e) => {
+                    LOG.error(&format!("Speech \"{text}\" failed: {e:#}"));
+                    events.push(DaemonEvent::CommandResult(CommandResult::Error(format!(
+                        "Speech failed: {e:#}"
+                    ))));
+                    continue;
+                }
+            };
+            let Some(sink) = self.sinks.get(self.selected_sink) else {
+                continue;
+            };
+            let clip = Arc::new(Clip {
+                path,
+                name: format!("\"{text}\""),
+                volume: 1.0,
+                sink: sink.clone(),
+            });
+            if self.start_clip(clip) {
+                events.push(DaemonEvent::NowPlaying(self.now_playing.clone()));
+            }
+        }
+        events
+    }
+
+    /// Close out the in-flight trigger once its playback's first audio is written.
+    #[cfg(feature = "transcriber")]
+    fn trigger_latency(
+        &mut self,
+        id: u64,
+        connected: std::time::Instant,
+        first_audio: std::time::Instant,
+    ) -> Option<DaemonEvent> {
+        let timing = self.trigger_timing.take_if(|t| t.id == Some(id))?;
+        let ms = |from: std::time::Instant, to: std::time::Instant| {
+            to.saturating_duration_since(from).as_millis() as u64
+        };
+        let decoded = timing.decoded.unwrap_or(timing.applied);
+        let total_ms = ms(timing.matched, first_audio);
+        let decode_ms = ms(timing.applied, decoded);
+        let connect_ms = ms(decoded, connected);
+        DETECTOR_LOG.log(
+            LogLevel::Debug,
+            "Trigger timing",
+            &[
+                ("word", &timing.word),
+                ("total_ms", &total_ms),
+                ("match_ms", &ms(timing.matched, timing.applied)),
+                ("decode_ms", &decode_ms),
+                ("connect_ms", &connect_ms),
+                ("first_buffer_ms", &ms(connected, first_audio)),
+            ],
+        );
+        Some(DaemonEvent::TriggerLatency {
+            word: timing.word,
+            total_ms,
+            decode_ms,
+            connect_ms,
+        })
+    }
+
+    #[cfg(feature = "transcriber")]
+    pub fn poll_detector_matches(&mut self) -> Vec<DaemonEvent> {
+        use std::sync::mpsc::TryRecvError;
+
+        // Drain all matches first to release the borrow on self
+        let mut detections = Vec::new();
+        let mut exited = false;
+        if let Some(rx) = &self.detector_match_rx {
+            loop {
+                match rx.try_recv() {
+                    Ok(event) => detections.push(event),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        exited = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut events = Vec::new();
+        let mut stats_changed = false;
+        for detection in detections {
+            let result = match detection {
+                DetectorEvent::CaptureSaved(path) => {
+                    CommandResult::Ok(format!("Detector capture saved to {}", path.display()))
+                }
+                DetectorEvent::CaptureFailed(e) => {
+                    CommandResult::Error(format!("Detector capture failed: {e}"))
+                }
+                hit => {
+                    stats_changed |= self.handle_detection(hit, false, &mut events);
+                    continue;
+                }
+            };
+            events.push(DaemonEvent::CommandResult(result));
+            stats_changed = true;
+        }
+        if stats_changed {
+            events.push(DaemonEvent::State(Box::new(self.snapshot())));
+        }
+
+        // The detector thread dropped its sender: it failed or gave up on its own
+        if exited {
+            DETECTOR_LOG.info("Detector thread exited, clearing detector source");
+            self.stop_detector();
+            events.push(DaemonEvent::State(Box::new(self.snapshot())));
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A daemon with no PipeWire thread behind it, keeping its config in a
+    /// scratch directory that is removed on drop.
+    pub(crate) struct TestDaemon {
+        pub app: DaemonApp,
+        pub dir: PathBuf,
+    }
+
+    impl Drop for TestDaemon {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    pub(crate) fn scratch_dir(tag: &str) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "plentysound-test-{}-{}-{}",
+            std::process::id(),
+            tag,
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    pub(crate) fn test_daemon(tag: &str) -> TestDaemon {
+        let dir = scratch_dir(tag);
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        app.waveform_cache = dir.join("waveforms");
+        TestDaemon { app, dir }
+    }
+
+    pub(crate) fn write_file(dir: &Path, name: &str, contents: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub(crate) fn command_result(events: Vec<DaemonEvent>) -> Option<CommandResult> {
+        events.into_iter().find_map(|e| match e {
+            DaemonEvent::CommandResult(r) => Some(r),
+            _ => None,
+        })
+    }
+
+    fn add(app: &mut DaemonApp, path: &Path) -> CommandResult {
+        let events = app.apply_command(ClientCommand::AddSong(path.display().to_string()));
+        command_result(events).expect("AddSong should reply with a CommandResult")
+    }
+
+    fn assert_settings_finite(app: &DaemonApp) {
+        assert!((0.0..=VOLUME_MAX).contains(&app.volume));
+        assert!((0.0..=COMFORT_NOISE_MAX).contains(&app.comfort_noise));
+        for band in EqBand::ALL {
+            assert!((0.0..=EQ_GAIN_MAX).contains(&app.eq.get(band)));
+        }
+        assert!((0.0..=FADE_SECS_MAX).contains(&app.fade_secs));
+    }
+
+    #[test]
+    fn rejects_same_path_twice() {
+        let mut t = test_daemon("dup-same");
+        let song = write_file(&t.dir, "airhorn.wav", b"airhorn");
+
+        assert!(matches!(add(&mut t.app, &song), CommandResult::Ok(_)));
+        let second = add(&mut t.app, &song);
+        assert_eq!(
+            second,
+            CommandResult::Error("already in library as 'airhorn.wav'".to_string())
+        );
+        assert_eq!(t.app.songs.len(), 1);
+    }
+
+    #[test]
+    fn rejects_symlinked_duplicate() {
+        let mut t = test_daemon("dup-symlink");
+        let song = write_file(&t.dir, "airhorn.wav", b"airhorn");
+        let link = t.dir.join("link.wav");
+        std::os::unix::fs::symlink(&song, &link).unwrap();
+
+        add(&mut t.app, &song);
+        assert!(add(&mut t.app, &link).is_error());
+        assert_eq!(t.app.songs.len(), 1);
+    }
+
+    #[test]
+    fn rejects_unnormalized_relative_duplicate() {
+        let mut t = test_daemon("dup-relative");
+        std::fs::create_dir_all(t.dir.join("sub")).unwrap();
+        let song = write_file(&t.dir, "airhorn.wav", b"airhorn");
+        let roundabout = t.dir.join("sub").join("..").join(".").join("airhorn.wav");
+
+        add(&mut t.app, &song);
+        assert!(add(&mut t.app, &roundabout).is_error());
+        assert_eq!(t.app.songs.len(), 1);
+    }
+
+    #[test]
+    fn songs_are_stored_under_their_canonical_path() {
+        let mut t = test_daemon("canonical");
+        std::fs::create_dir_all(t.dir.join("sub")).unwrap();
+        let song = write_file(&t.dir, "airhorn.wav", b"airhorn");
+        let roundabout = t.dir.join("sub").join("..").join("airhorn.wav");
+
+        assert!(!add(&mut t.app, &roundabout).is_error());
+        let canonical = std::fs::canonicalize(&song).unwrap();
+        assert_eq!(t.app.songs[0].path, canonical);
+        assert_eq!(
+            Config::load(&t.app.config_path).songs[0].path,
+            canonical.display().to_string()
+        );
+    }
+
+    #[test]
+    fn identical_content_is_added_with_warning() {
+        let mut t = test_daemon("dup-content");
+        let a = write_file(&t.dir, "a.wav", b"same bytes");
+        let b = write_file(&t.dir, "b.wav", b"same bytes");
+
+        add(&mut t.app, &a);
+        assert!(matches!(add(&mut t.app, &b), CommandResult::Warning(_)));
+        assert_eq!(t.app.songs.len(), 2);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let mut t = test_daemon("missing");
+        let missing = t.dir.join("nope.wav");
+        assert!(add(&mut t.app, &missing).is_error());
+        assert!(t.app.songs.is_empty());
+    }
+
+    #[test]
+    fn non_finite_settings_are_rejected() {
+        let mut t = test_daemon("hostile-floats");
+        let before = (t.app.volume, t.app.comfort_noise, t.app.eq);
+
+        for v in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            for cmd in [
+                ClientCommand::SetVolume(v),
+                ClientCommand::SetComfortNoise(v),
+                ClientCommand::SetEqBand {
+                    band: EqBand::Low,
+                    gain: v,
+                },
+                ClientCommand::SetEqBand {
+                    band: EqBand::High,
+                    gain: v,
+                },
+                ClientCommand::SetFade(v),
+            ] {
+                let result = command_result(t.app.apply_command(cmd));
+                assert!(result.is_some_and(|r| r.is_error()));
+                assert_settings_finite(&t.app);
+            }
+        }
+        assert_eq!(before, (t.app.volume, t.app.comfort_noise, t.app.eq));
+    }
+
+    #[test]
+    fn out_of_range_settings_are_clamped() {
+        let mut t = test_daemon("clamp-floats");
+
+        t.app.apply_command(ClientCommand::SetVolume(1e30));
+        t.app.apply_command(ClientCommand::SetComfortNoise(-3.0));
+        t.app.apply_command(ClientCommand::SetEqBand {
+            band: EqBand::Mid,
+            gain: f32::MAX,
+        });
+        t.app.apply_command(ClientCommand::SetEqBand {
+            band: EqBand::High,
+            gain: -1.0,
+        });
+        t.app.apply_command(ClientCommand::SetFade(60.0));
+
+        assert_eq!(t.app.volume, VOLUME_MAX);
+        assert_eq!(t.app.comfort_noise, 0.0);
+        assert_eq!(
+            (t.app.eq.low, t.app.eq.mid, t.app.eq.high),
+            (1.0, EQ_GAIN_MAX, 0.0)
+        );
+        assert_eq!(t.app.fade_secs, FADE_SECS_MAX);
+    }
+
+    #[test]
+    fn out_of_range_indices_are_errors() {
+        let mut t = test_daemon("hostile-indices");
+        let song = write_file(&t.dir, "airhorn.wav", b"airhorn");
+        add(&mut t.app, &song);
+
+        for cmd in [
+            ClientCommand::SelectSong(1),
+            ClientCommand::SelectSong(usize::MAX),
+            ClientCommand::SelectSink(0),
+            ClientCommand::RemoveSong(1),
+        ] {
+            let result = command_result(t.app.apply_command(cmd));
+            assert!(result.is_some_and(|r| r.is_error()));
+        }
+        assert_eq!(t.app.songs.len(), 1);
+        assert_eq!(t.app.selected_song, 0);
+        assert_eq!(t.app.selected_sink, 0);
+    }
+
+    #[test]
+    fn config_never_stores_non_finite_floats() {
+        let mut t = test_daemon("config-finite");
+        t.app.apply_command(ClientCommand::SetVolume(f32::NAN));
+        t.app.apply_command(ClientCommand::SetEqBand {
+            band: EqBand::Mid,
+            gain: f32::INFINITY,
+        });
+        t.app.apply_command(ClientCommand::SetComfortNoise(0.02));
+
+        let config = Config::load(&t.app.config_path);
+        assert!(config.volume.is_finite());
+        assert!(config.eq.mid.is_finite());
+        assert_eq!(config.comfort_noise, 0.02);
+    }
+
+    #[test]
+    fn non_finite_values_in_config_file_are_replaced() {
+        let dir = scratch_dir("config-nan");
+        let path = write_file(
+            &dir,
+            "config.yaml",
+            b"songs: []\nvolume: .nan\ncomfort_noise: .inf\neq: { low: .nan, high: 9.0 }\n",
+        );
+
+        let config = Config::load(&path);
+        assert_eq!(config.volume, default_volume());
+        assert_eq!(config.comfort_noise, default_comfort_noise());
+        assert_eq!(
+            (config.eq.low, config.eq.mid, config.eq.high),
+            (1.0, 1.5, EQ_GAIN_MAX)
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn huge_library_snapshot_stays_small_and_pages_cover_it() {
+        let mut t = test_daemon("huge-library");
+        let total = SONG_PAGE_LIMIT * 3 + 7;
+        // Long paths push every entry towards the worst case; the files don't
+        // need to exist for snapshots and paging
+        for i in 0..total {
+            let name = format!("{:0>200}.wav", i);
+            t.app.songs.push(Song {
+                path: t.dir.join("x".repeat(3000)).join(&name),
+                name,
+                undecodable: false,
+                missing: false,
+                tag_color: None,
+                volume: None,
+                duration: None,
+                added: None,
+            });
+        }
+        t.app.songs_revision += 1;
+
+        let snapshot = t.app.snapshot();
+        assert_eq!(snapshot.songs.len(), SONG_PAGE_LIMIT);
+        assert_eq!(snapshot.songs_total, total);
+        let encoded = serde_json::to_vec(&DaemonEvent::State(Box::new(snapshot.clone()))).unwrap();
+        assert!(encoded.len() < crate::protocol::MAX_MESSAGE_LEN / 4);
+
+        let mut songs = snapshot.songs;
+        while songs.len() < total {
+            let events = t.app.apply_command(ClientCommand::GetSongs {
+                offset: songs.len(),
+                limit: usize::MAX,
+            });
+            match events.into_iter().next() {
+                Some(DaemonEvent::Songs {
+                    revision,
+                    offset,
+                    songs: page,
+                    ..
+                }) => {
+                    assert_eq!(revision, snapshot.songs_revision);
+                    assert_eq!(offset, songs.len());
+                    assert!(!page.is_empty() && page.len() <= SONG_PAGE_LIMIT);
+                    songs.extend(page);
+                }
+                other => panic!("expected a Songs page, got {:?}", other),
+            }
+        }
+        assert_eq!(songs.len(), total);
+        assert_eq!(songs[total - 1].name, format!("{:0>200}.wav", total - 1));
+    }
+
+    #[test]
+    fn small_changes_go_out_as_small_events() {
+        let mut t = test_daemon("small-changes");
+        for i in 0..300 {
+            let name = format!("song-{i}.wav");
+            t.app.songs.push(Song {
+                path: t.dir.join(&name),
+                name,
+                undecodable: false,
+                missing: false,
+                tag_color: None,
+                volume: None,
+                duration: None,
+                added: None,
+            });
+        }
+        let state = DaemonEvent::State(Box::new(t.app.snapshot()));
+        let state_len = serde_json::to_vec(&state).unwrap().len();
+        let sent = |events: &[DaemonEvent]| -> usize {
+            events
+                .iter()
+                .map(|e| serde_json::to_vec(e).unwrap().len())
+                .sum()
+        };
+
+        // Dragging the volume bar is a stream of these
+        let events = t.app.apply_command(ClientCommand::SetVolume(0.5));
+        assert!(matches!(events[..], [DaemonEvent::VolumeChanged(v)] if v == 0.5));
+        assert!(
+            sent(&events) <= 32,
+            "{} bytes for a volume change",
+            sent(&events)
+        );
+        let events = t.app.apply_command(ClientCommand::SetEqBand {
+            band: EqBand::Low,
+            gain: 1.5,
+        });
+        assert!(matches!(&events[..], [DaemonEvent::FxChanged(fx)] if fx.eq.low == 1.5));
+        assert!(
+            sent(&events) <= 256,
+            "{} bytes for an EQ change",
+            sent(&events)
+        );
+        assert!(state_len > 100 * 256);
+
+        // Refusals still carry the whole state, to undo the client's guess
+        let events = t.app.apply_command(ClientCommand::SetVolume(f32::NAN));
+        assert!(matches!(events[..], [DaemonEvent::State(ref s), _] if s.volume == 0.5));
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn probe_accepts_known_codecs() {
+        assert!(crate::audio::probe_decodable(&fixture("silence.wav")).is_ok());
+        let err = crate::audio::probe_decodable(&fixture("silence.opus")).unwrap_err();
+        assert_eq!(err.to_string(), "missing Opus support");
+    }
+
+    #[test]
+    fn duration_comes_from_the_headers() {
+        let d = crate::audio::probe_decodable(&fixture("silence.wav"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(d.as_millis(), 100);
+    }
+
+    #[test]
+    fn song_lengths_are_probed_once_and_kept_in_the_config() {
+        let mut t = test_daemon("durations");
+        let path = fixture("silence.wav");
+        assert!(matches!(add(&mut t.app, &path), CommandResult::Ok(_)));
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while t.app.poll_probes().is_empty() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(t.app.snapshot().songs[0].duration, Some(0.1));
+
+        // A restart takes the length from the config instead of the file,
+        // and doesn't probe it again
+        let mut config = Config::load(&t.app.config_path);
+        let cached = &mut config.songs[0].duration;
+        assert_eq!(*cached, Some(0.1));
+        *cached = Some(7.0);
+        config.save(&t.app.config_path);
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let mut restarted = DaemonApp::with_backend(t.app.config_path.clone(), cmd_tx, evt_rx);
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(restarted.poll_probes().is_empty());
+        assert_eq!(restarted.songs[0].duration, Some(7.0));
+    }
+
+    #[test]
+    fn waveform_failures_go_only_to_the_client_that_asked() {
+        let mut t = test_daemon("waveform-fail");
+        let song = write_file(&t.dir, "broken.wav", b"not audio");
+        assert!(matches!(add(&mut t.app, &song), CommandResult::Ok(_)));
+
+        let rejected = t.app.request_waveform(3, Some(7));
+        assert_eq!(
+            command_result(rejected),
+            Some(CommandResult::Error(
+                "No song at index 3 (1 available)".to_string()
+            ))
+        );
+
+        assert!(t.app.request_waveform(0, Some(7)).is_empty());
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let (done, failed) = loop {
+            let (done, failed) = t.app.poll_waveforms();
+            if !failed.is_empty() || std::time::Instant::now() > deadline {
+                break (done, failed);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert!(done.is_empty());
+        let [(client, DaemonEvent::CommandResult(CommandResult::Error(msg)))] = &failed[..] else {
+            panic!("expected one failure, got {failed:?}");
+        };
+        assert_eq!(*client, Some(7));
+        assert!(msg.starts_with("No waveform for 'broken.wav'"), "{msg}");
+        assert!(t.app.waveforms_pending.is_empty());
+    }
+
+    #[test]
+    fn undecodable_song_is_kept_but_flagged() {
+        let mut t = test_daemon("undecodable");
+        let path = fixture("silence.opus");
+        assert!(matches!(add(&mut t.app, &path), CommandResult::Ok(_)));
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let events = loop {
+            let events = t.app.poll_probes();
+            if !events.is_empty() || std::time::Instant::now() > deadline {
+                break events;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+
+        let result = command_result(events).expect("probe failure should be reported");
+        assert_eq!(
+            result,
+            CommandResult::Error("Cannot decode 'silence.opus': missing Opus support".to_string())
+        );
+        assert_eq!(t.app.songs.len(), 1);
+        assert!(t.app.snapshot().songs[0].undecodable);
+    }
+
+    #[test]
+    fn vanished_song_files_stay_listed_until_pruned() {
+        let dir = scratch_dir("songs-vanish");
+        let config = dir.join("config.yaml");
+        let start = || {
+            let (cmd_tx, _) = std::sync::mpsc::channel();
+            let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+            let mut app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+            let kind = DeviceKind::Output;
+            let sink = PwSink {
+                id: 1,
+                name: "out".into(),
+                description: "Out".into(),
+                kind,
+            };
+            evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+            app.process_pw_events();
+            app
+        };
+        let mut app = start();
+        let [horn, bell] = ["horn.wav", "bell.wav"].map(|name| dir.join(name));
+        for song in [&horn, &bell] {
+            std::fs::copy(fixture("silence.wav"), song).unwrap();
+            add(&mut app, song);
+        }
+        #[cfg(feature = "transcriber")]
+        for (word, song_indices) in [("honk", vec![0]), ("ring", vec![0, 1])] {
+            app.apply_command(ClientCommand::AddWordMapping {
+                word: word.to_string(),
+                song_indices,
+                selection: Default::default(),
+                source_description: String::new(),
+                output_description: String::new(),
+            });
+        }
+
+        // Deleted while running: Play says so instead of failing to decode
+        std::fs::remove_file(&horn).unwrap();
+        let result = command_result(app.apply_command(ClientCommand::Play)).unwrap();
+        let expected = format!("'horn.wav' is missing: {} not found", horn.display());
+        assert_eq!(result.message(), expected);
+        assert!(app.now_playing.is_none());
+        assert!(app.snapshot().songs[0].missing);
+        let result = command_result(app.apply_command(ClientCommand::Enqueue(0))).unwrap();
+        assert!(result.is_error());
+
+        // Still listed, and still bound, after a restart; back once the file is
+        let mut app = start();
+        assert_eq!(app.songs.len(), 2);
+        assert!(app.songs[0].missing && !app.songs[1].missing);
+        #[cfg(feature = "transcriber")]
+        assert_eq!(app.word_mappings.len(), 2);
+        std::fs::copy(fixture("silence.wav"), &horn).unwrap();
+        app.apply_command(ClientCommand::Play);
+        assert_eq!(app.now_playing.as_deref(), Some("horn.wav"));
+        assert!(!app.songs[0].missing);
+
+        // Pruning is the only way it leaves, taking bindings left songless
+        std::fs::remove_file(&horn).unwrap();
+        let result = command_result(app.apply_command(ClientCommand::PruneMissing)).unwrap();
+        #[cfg(not(feature = "transcriber"))]
+        assert_eq!(result.message(), "Removed missing 'horn.wav'");
+        #[cfg(feature = "transcriber")]
+        {
+            let expected = "Removed missing 'horn.wav' and 1 word binding left without songs";
+            assert_eq!(result.message(), expected);
+            assert_eq!(app.word_mappings.len(), 1);
+            assert_eq!(app.word_mappings[0].song_names, ["bell.wav"]);
+        }
+        assert_eq!(app.songs.len(), 1);
+        assert!(!std::fs::read_to_string(&config)
+            .unwrap()
+            .contains("horn.wav"));
+        let result = command_result(app.apply_command(ClientCommand::PruneMissing)).unwrap();
+        assert_eq!(result.message(), "No missing songs");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn song_files_are_looked_for_again_without_being_played() {
+        let mut t = test_daemon("presence");
+        let horn = t.dir.join("horn.wav");
+        std::fs::copy(fixture("silence.wav"), &horn).unwrap();
+        add(&mut t.app, &horn);
+        let mut later = std::time::Instant::now();
+        let mut check = |app: &mut DaemonApp| {
+            later += PRESENCE_INTERVAL;
+            assert!(app.poll_presence(later).is_empty());
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+            loop {
+                let events = app.poll_presence(later);
+                if !events.is_empty() || std::time::Instant::now() > deadline {
+                    break events;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+        };
+
+        // Only the song list goes out, not a whole state
+        std::fs::remove_file(&horn).unwrap();
+        let events = check(&mut t.app);
+        let [DaemonEvent::SongsUpdated { ref songs, .. }] = events[..] else {
+            panic!("expected just the song list, got {events:?}");
+        };
+        assert!(songs[0].missing);
+        assert!(t.app.songs[0].missing);
+
+        std::fs::copy(fixture("silence.wav"), &horn).unwrap();
+        assert!(!check(&mut t.app).is_empty());
+        assert!(!t.app.songs[0].missing);
+    }
+
+    #[test]
+    fn status_file_is_written_on_change_and_removed_on_shutdown() {
+        let mut t = test_daemon("status");
+        let path = t.dir.join("status.json");
+        t.app.status_file = Some(path.clone());
+
+        t.app.write_status();
+        let status: StatusFile = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(status.now_playing, None);
+        assert_eq!(status.last_detected_word, None);
+        assert!(status.ts > 0);
+
+        t.app.now_playing = Some("horn.wav".to_string());
+        t.app.write_status();
+        // Throttled: the change lands on a later tick
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        t.app.write_status();
+        let status: StatusFile = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(status.now_playing.as_deref(), Some("horn.wav"));
+
+        t.app.shutdown();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn shutdown_ends_the_backend_and_waits_for_its_thread() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (_evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("shutdown-threads");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        // Stands in for the PipeWire thread: only `Shutdown` ends it, since
+        // the app keeps the command channel open
+        let (ended_tx, ended_rx) = std::sync::mpsc::channel();
+        app.pw_thread = Some(std::thread::spawn(move || {
+            let shutdown = cmd_rx.iter().any(|cmd| matches!(cmd, PwCommand::Shutdown));
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            let _ = ended_tx.send(shutdown);
+        }));
+
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            app.shutdown();
+            let _ = done_tx.send(app.pw_thread.is_none());
+        });
+        let timeout = std::time::Duration::from_secs(5);
+        assert_eq!(done_rx.recv_timeout(timeout), Ok(true));
+        // Joined, so the thread had finished by the time shutdown returned
+        assert_eq!(ended_rx.try_recv(), Ok(true));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn first_run_is_offered_once() {
+        let mut t = test_daemon("first-run");
+        assert!(t.app.snapshot().first_run);
+        t.app.apply_command(ClientCommand::FinishSetup);
+        assert!(!t.app.snapshot().first_run);
+
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let reloaded = DaemonApp::with_backend(t.dir.join("config.yaml"), cmd_tx, evt_rx);
+        assert!(!reloaded.snapshot().first_run);
+    }
+
+    #[test]
+    fn song_tags_persist_and_bad_tags_are_rejected() {
+        let mut t = test_daemon("tags");
+        let path = write_file(&t.dir, "horn.wav", b"horn");
+        add(&mut t.app, &path);
+
+        let bad = ClientCommand::SetSongTag {
+            index: 0,
+            tag_color: Some(String::new()),
+        };
+        assert!(command_result(t.app.apply_command(bad)).is_some_and(|r| r.is_error()));
+        let revision = t.app.songs_revision;
+        t.app.apply_command(ClientCommand::SetSongTag {
+            index: 0,
+            tag_color: Some("green".to_string()),
+        });
+        assert!(t.app.songs_revision > revision);
+
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let reloaded = DaemonApp::with_backend(t.dir.join("config.yaml"), cmd_tx, evt_rx);
+        assert_eq!(
+            reloaded.snapshot().songs[0].tag_color.as_deref(),
+            Some("green")
+        );
+    }
+
+    #[test]
+    fn tag_palette_comes_from_the_config_without_unusable_tags() {
+        let dir = scratch_dir("tag-palette");
+        let yaml = "songs: []\ntag_palette:\n- {name: work, color: '#ff8800'}\n\
+                    - {name: home, color: no-such-color}\n- {name: work, color: red}\n\
+                    - {name: '', color: blue}\n- {name: urgent, color: light red}\n";
+        let config = write_file(&dir, "config.yaml", yaml.as_bytes());
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+
+        let names: Vec<String> = app
+            .snapshot()
+            .tag_palette
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, ["work", "urgent"]);
+        app.save_config();
+        assert_eq!(Config::load(&config).tag_palette, app.tag_palette);
+        // Left unset, the config keeps saying nothing about it
+        let t = test_daemon("tag-palette-default");
+        t.app.save_config();
+        let saved = std::fs::read_to_string(t.dir.join("config.yaml")).unwrap();
+        assert!(t.app.snapshot().tag_palette.is_empty() && !saved.contains("tag_palette"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn playing_a_song_by_index_leaves_the_selection_alone() {
+        let (cmd_tx, _cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("play-index");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let kind = DeviceKind::Output;
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        for name in ["horn.wav", "bell.wav"] {
+            std::fs::copy(fixture("silence.wav"), dir.join(name)).unwrap();
+            add(&mut app, &dir.join(name));
+        }
+
+        let events = app.apply_command(ClientCommand::PlaySong(1));
+        assert!(matches!(&events[..], [DaemonEvent::NowPlaying(Some(name))] if name == "bell.wav"));
+        assert_eq!(app.selected_song, 0);
+        let result = command_result(app.apply_command(ClientCommand::PlaySong(2))).unwrap();
+        assert!(result.is_error());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn song_volume_scales_the_global_volume_and_persists() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("song-volume");
+        let config = dir.join("config.yaml");
+        let mut app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind: DeviceKind::Output,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        add(&mut app, &fixture("silence.wav"));
+
+        let bad = ClientCommand::SetSongVolume {
+            index: 0,
+            volume: Some(f32::NAN),
+        };
+        assert!(command_result(app.apply_command(bad)).is_some_and(|r| r.is_error()));
+        app.apply_command(ClientCommand::SetVolume(0.5));
+        app.apply_command(ClientCommand::SetSongVolume {
+            index: 0,
+            volume: Some(0.6),
+        });
+        assert_eq!(app.snapshot().songs[0].volume, Some(0.6));
+        app.apply_command(ClientCommand::Play);
+        let (mut global, mut song) = (None, None);
+        for cmd in cmd_rx.try_iter() {
+            match cmd {
+                PwCommand::UpdateParams(live) => global = Some(live.volume),
+                PwCommand::Play { options, .. } => song = Some(options.volume),
+                _ => {}
+            }
+        }
+        assert_eq!(song, Some(0.6));
+        assert!((global.expect("volume sent") * song.unwrap() - 0.3).abs() < 1e-6);
+
+        // A change mid-song goes straight to the backend, not only to the next play
+        app.apply_command(ClientCommand::SetVolume(0.8));
+        let sent: Vec<_> = cmd_rx.try_iter().collect();
+        assert!(matches!(sent[..], [PwCommand::UpdateParams(live)] if live.volume == 0.8));
+
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let reloaded = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+        assert_eq!(reloaded.songs[0].volume, Some(0.6));
+
+        // Clearing the override drops it from the config
+        app.apply_command(ClientCommand::SetSongVolume {
+            index: 0,
+            volume: None,
+        });
+        assert_eq!(Config::load(&config).songs[0].volume, None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn plain_paths_and_the_old_per_song_maps_load_as_songs() {
+        let dir = scratch_dir("saved-songs");
+        let horn = dir.join("horn.wav").display().to_string();
+        let bell = dir.join("bell.wav").display().to_string();
+        let yaml = format!(
+            "songs:\n- {horn}\n- path: {bell}\n  volume: 0.5\n\
+             song_volumes:\n  {horn}: 0.6\n  {bell}: 2.0\nsong_tags:\n  {bell}: red\n\
+             song_durations:\n  {horn}: 2.5\nsong_added:\n  {bell}: 1700000000\n"
+        );
+        let config = write_file(&dir, "config.yaml", yaml.as_bytes());
+
+        // A setting already on the song wins over the old map's
+        let loaded = Config::load(&config);
+        let expected = [
+            SavedSong {
+                path: horn,
+                volume: Some(0.6),
+                duration: Some(2.5),
+                ..SavedSong::default()
+            },
+            SavedSong {
+                path: bell,
+                volume: Some(0.5),
+                tag: Some("red".to_string()),
+                added: Some(1_700_000_000),
+                ..SavedSong::default()
+            },
+        ];
+        assert_eq!(loaded.songs, expected);
+
+        loaded.save(&config);
+        let saved = std::fs::read_to_string(&config).unwrap();
+        assert!(!saved.contains("song_volumes"), "{saved}");
+        assert_eq!(Config::load(&config).songs, expected);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stream_properties_and_injection_hints_reach_the_backend() {
+        let dir = scratch_dir("stream-props");
+        let config = write_file(
+            &dir,
+            "config.yaml",
+            b"songs: []\nstream_properties:\n  node.latency: 256/48000\n  media.role: Game\ninjection_hints:\n  communication_role: true\n  echo_cancel: true\n",
+        );
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (_evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let mut app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+        let last_sent = || {
+            cmd_rx
+                .try_iter()
+                .filter_map(|c| match c {
+                    PwCommand::SetStreamProperties(p) => Some(p),
+                    _ => None,
+                })
+                .last()
+        };
+        let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+
+        let sent = last_sent().expect("sent at startup");
+        assert_eq!(
+            sent.playback,
+            [
+                pair("media.role", "Game"),
+                pair("node.latency", "256/48000")
+            ]
+        );
+        // Hints come first so the explicit media.role wins
+        assert_eq!(
+            sent.injection,
+            [
+                pair("media.role", "Communication"),
+                pair("filter.want", "echo-cancel"),
+                pair("media.role", "Game"),
+                pair("node.latency", "256/48000"),
+            ]
+        );
+
+        // Saving keeps them, and a reload without changes sends nothing new
+        app.apply_command(ClientCommand::SetVolume(0.5));
+        app.apply_command(ClientCommand::ReloadConfig);
+        assert!(last_sent().is_none());
+
+        std::fs::write(&config, "songs: []\n").unwrap();
+        app.apply_command(ClientCommand::ReloadConfig);
+        assert_eq!(last_sent(), Some(StreamProperties::default()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn injected_clips_also_play_on_the_monitor_output() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("monitor");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let sinks = vec![
+            PwSink {
+                id: 10,
+                name: "spk".into(),
+                description: "Speakers".into(),
+                kind: DeviceKind::Output,
+            },
+            PwSink {
+                id: 20,
+                name: "discord".into(),
+                description: "Discord".into(),
+                kind: DeviceKind::Input,
+            },
+        ];
+        evt_tx.send(PwEvent::SinksUpdated(sinks.clone())).unwrap();
+        app.process_pw_events();
+        add(&mut app, &fixture("silence.wav"));
+        let monitors = || -> Vec<Option<u32>> {
+            cmd_rx
+                .try_iter()
+                .filter_map(|c| match c {
+                    PwCommand::Play { monitor_sink, .. } => Some(monitor_sink),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let wrong_kind = app.apply_command(ClientCommand::SetMonitorSink(Some(1)));
+        assert!(command_result(wrong_kind).is_some_and(|r| r.is_error()));
+        app.apply_command(ClientCommand::SetMonitorSink(Some(0)));
+        assert_eq!(app.snapshot().monitor_sink, Some(0));
+
+        app.apply_command(ClientCommand::SelectSink(1));
+        app.apply_command(ClientCommand::Play);
+        assert_eq!(monitors(), [Some(10)]);
+        // Playing straight to the monitor output needs no second stream
+        app.apply_command(ClientCommand::SelectSink(0));
+        app.apply_command(ClientCommand::Play);
+        assert_eq!(monitors(), [None]);
+
+        // Remembered by description, so it survives a restart
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let mut reloaded = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        evt_tx
+            .send(PwEvent::SinksUpdated(sinks.into_iter().rev().collect()))
+            .unwrap();
+        reloaded.process_pw_events();
+        assert_eq!(reloaded.snapshot().monitor_sink, Some(0));
+
+        app.apply_command(ClientCommand::SetMonitorSink(None));
+        app.apply_command(ClientCommand::SelectSink(1));
+        app.apply_command(ClientCommand::Play);
+        assert_eq!(monitors(), [None]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn runtime_state_round_trips_and_tolerates_a_bad_file() {
+        let dir = scratch_dir("state-file");
+        let state = RuntimeState {
+            selected_song: Some(dir.join("horn.wav")),
+            queue: vec![dir.join("bell.wav"), dir.join("horn.wav")],
+            #[cfg(feature = "transcriber")]
+            detector_stopped: true,
+        };
+        let yaml = serde_yaml::to_string(&state).unwrap();
+        assert_eq!(serde_yaml::from_str::<RuntimeState>(&yaml).unwrap(), state);
+
+        let path = RuntimeState::path(&dir.join("config.yaml"));
+        assert_eq!(RuntimeState::load(&path), RuntimeState::default());
+        state.save(&path);
+        assert_eq!(RuntimeState::load(&path), state);
+        std::fs::write(&path, "queue: [unterminated").unwrap();
+        assert_eq!(RuntimeState::load(&path), RuntimeState::default());
+        // An idle daemon's state is an empty document
+        assert_eq!(
+            serde_yaml::to_string(&RuntimeState::default())
+                .unwrap()
+                .trim(),
+            "{}"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn restart_restores_what_still_resolves() {
+        let dir = scratch_dir("state-restore");
+        let [a, b] = ["a.wav", "b.wav"].map(|name| write_file(&dir, name, b"RIFF"));
+        let config = dir.join("config.yaml");
+        std::fs::write(
+            &config,
+            format!("songs:\n- {}\n- {}\n", a.display(), b.display()),
+        )
+        .unwrap();
+        RuntimeState {
+            selected_song: Some(b.clone()),
+            queue: vec![dir.join("deleted.wav"), a.clone(), b.clone()],
+            #[cfg(feature = "transcriber")]
+            detector_stopped: true,
+        }
+        .save(&RuntimeState::path(&config));
+
+        let (cmd_tx, _cmd_rx) = std::sync::mpsc::channel();
+        let (_evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let mut app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+        assert_eq!(app.selected_song, 1);
+        assert_eq!(app.queue, [a.clone(), b.clone()]);
+        #[cfg(feature = "transcriber")]
+        assert!(app.detector_stopped);
+
+        // Skipped entries are gone from the next save
+        app.save_state(false);
+        let saved = RuntimeState::load(&RuntimeState::path(&config));
+        assert_eq!(saved.selected_song, Some(b.clone()));
+        assert_eq!(saved.queue, [a, b]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn saved_sink_resolves_by_name_or_falls_back_to_the_first_output() {
+        let sink = |id: u32, name: &str, kind: DeviceKind| PwSink {
+            id,
+            name: name.into(),
+            description: name.into(),
+            kind,
+        };
+        let saved = SavedSink {
+            name: "usb".into(),
+            kind: "Output".into(),
+        };
+        let sinks = [
+            sink(7, "hdmi", DeviceKind::Output),
+            sink(3, "usb", DeviceKind::Output),
+            sink(5, "mic", DeviceKind::Source),
+        ];
+        assert_eq!(resolve_sink(&sinks, &saved), Some(1));
+        // Registry order and ids change across restarts; the name doesn't
+        let reordered = [
+            sink(40, "usb", DeviceKind::Output),
+            sink(41, "hdmi", DeviceKind::Output),
+            sink(5, "mic", DeviceKind::Source),
+        ];
+        assert_eq!(resolve_sink(&reordered, &saved), Some(0));
+        // Missing, or only there as another kind: the first output stands in
+        let missing = [
+            sink(9, "usb", DeviceKind::Input),
+            sink(5, "mic", DeviceKind::Source),
+            sink(7, "hdmi", DeviceKind::Output),
+        ];
+        assert_eq!(resolve_sink(&missing, &saved), Some(2));
+        assert_eq!(resolve_sink(&missing[..2], &saved), None);
+        assert_eq!(resolve_sink(&[], &saved), None);
+    }
+
+    #[test]
+    fn selected_sink_is_kept_by_name_across_restarts_and_replugging() {
+        let dir = scratch_dir("saved-sink");
+        let config = dir.join("config.yaml");
+        let sink = |id: u32, name: &str, description: &str| PwSink {
+            id,
+            name: name.into(),
+            description: description.into(),
+            kind: DeviceKind::Output,
+        };
+        let (cmd_tx, _cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let mut app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+        let first = vec![sink(1, "hdmi", "HDMI"), sink(2, "usb", "USB")];
+        evt_tx.send(PwEvent::SinksUpdated(first)).unwrap();
+        app.process_pw_events();
+        app.apply_command(ClientCommand::SelectSink(1));
+        assert!(std::fs::read_to_string(&config).unwrap().contains("usb"));
+
+        // After a restart the device has a new id and comes first from the registry
+        let (cmd_tx, _cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let mut app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+        let usb_first = vec![sink(8, "usb", "USB"), sink(9, "hdmi", "HDMI")];
+        evt_tx.send(PwEvent::SinksUpdated(usb_first)).unwrap();
+        app.process_pw_events();
+        assert_eq!(app.sinks[app.selected_sink].name, "usb");
+        assert_eq!(app.selected_sink, 1);
+
+        // Unplugged, the first output plays and clients hear of the move;
+        // plugged back in, the selection returns to it
+        let unplugged = vec![sink(9, "hdmi", "HDMI"), sink(10, "bt", "BT")];
+        evt_tx.send(PwEvent::SinksUpdated(unplugged)).unwrap();
+        let events = app.process_pw_events();
+        assert_eq!(app.sinks[app.selected_sink].name, "bt");
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::State(s) if s.selected_sink == 0)));
+        let replugged = vec![
+            sink(9, "hdmi", "HDMI"),
+            sink(10, "bt", "BT"),
+            sink(11, "usb", "USB"),
+        ];
+        evt_tx.send(PwEvent::SinksUpdated(replugged)).unwrap();
+        app.process_pw_events();
+        assert_eq!(app.sinks[app.selected_sink].name, "usb");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn vanished_sink_falls_back_and_stops_its_playback() {
+        let dir = scratch_dir("sink-vanished");
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let sink = |id: u32, name: &str, kind: DeviceKind| PwSink {
+            id,
+            name: name.to_lowercase(),
+            description: name.into(),
+            kind,
+        };
+        let sink_errors = |events: &[DaemonEvent]| -> Vec<String> {
+            events
+                .iter()
+                .filter_map(|e| match e {
+                    DaemonEvent::Error { context, message } if context == "sink" => {
+                        Some(message.clone())
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+        let song = dir.join("horn.wav");
+        std::fs::copy(fixture("silence.wav"), &song).unwrap();
+        add(&mut app, &song);
+        let all = vec![
+            sink(1, "HDMI", DeviceKind::Output),
+            sink(2, "USB", DeviceKind::Output),
+            sink(3, "Mic", DeviceKind::Input),
+        ];
+        evt_tx.send(PwEvent::SinksUpdated(all)).unwrap();
+        app.process_pw_events();
+        app.apply_command(ClientCommand::SelectSink(1));
+        app.apply_command(ClientCommand::Play);
+        assert!(app.now_playing.is_some());
+        let on_usb = app.pending_playbacks[0].0;
+        // Queued behind one playing on HDMI, which has nothing to do with it
+        let hdmi = sink(1, "HDMI", DeviceKind::Output);
+        let bell = Clip {
+            path: dir.join("bell.wav"),
+            name: "bell.wav".into(),
+            volume: 1.0,
+            sink: hdmi,
+        };
+        app.pending_playbacks
+            .push_front((on_usb + 1, Arc::new(bell)));
+        cmd_rx.try_iter().for_each(drop);
+
+        // Unplugged: the first output takes over and only the stream on the
+        // missing node is stopped
+        let unplugged = vec![
+            sink(1, "HDMI", DeviceKind::Output),
+            sink(3, "Mic", DeviceKind::Input),
+        ];
+        evt_tx.send(PwEvent::SinksUpdated(unplugged)).unwrap();
+        let events = app.process_pw_events();
+        assert_eq!(
+            sink_errors(&events),
+            ["USB disconnected, playing on HDMI instead"]
+        );
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::PlaybackFinished)));
+        let stopped = |c: PwCommand| matches!(c, PwCommand::StopPlaybacks(ids) if ids == [on_usb]);
+        assert!(cmd_rx.try_iter().any(stopped));
+        assert_eq!(app.sinks[app.selected_sink].name, "hdmi");
+        assert_eq!(app.now_playing.as_deref(), Some("bell.wav"));
+
+        // Re-added under a new id: selected again, nothing to report
+        let replugged = vec![
+            sink(1, "HDMI", DeviceKind::Output),
+            sink(3, "Mic", DeviceKind::Input),
+            sink(4, "USB", DeviceKind::Output),
+        ];
+        evt_tx.send(PwEvent::SinksUpdated(replugged)).unwrap();
+        let events = app.process_pw_events();
+        assert!(sink_errors(&events).is_empty());
+        assert_eq!(app.sinks[app.selected_sink].id, 4);
+
+        // Switched to an input-only profile: same id, but it can't play
+        let input_only = vec![
+            sink(1, "HDMI", DeviceKind::Output),
+            sink(3, "Mic", DeviceKind::Input),
+            sink(4, "USB", DeviceKind::Input),
+        ];
+        evt_tx.send(PwEvent::SinksUpdated(input_only)).unwrap();
+        let events = app.process_pw_events();
+        assert_eq!(
+            sink_errors(&events),
+            ["USB disconnected, playing on HDMI instead"]
+        );
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::PlaybackFinished)));
+        assert_eq!(app.sinks[app.selected_sink].name, "hdmi");
+
+        // The last output gone, along with what played on it. The selection
+        // lands on the app stream, which is not somewhere to play unasked
+        evt_tx
+            .send(PwEvent::SinksUpdated(vec![sink(
+                3,
+                "Mic",
+                DeviceKind::Input,
+            )]))
+            .unwrap();
+        let events = app.process_pw_events();
+        assert_eq!(sink_errors(&events), ["HDMI disconnected, no output left"]);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::PlaybackFinished)));
+        cmd_rx.try_iter().for_each(drop);
+        let events = app.apply_command(ClientCommand::Play);
+        assert!(matches!(
+            command_result(events),
+            Some(CommandResult::Error(_))
+        ));
+        assert!(!cmd_rx
+            .try_iter()
+            .any(|c| matches!(c, PwCommand::Play { .. })));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sinks_are_sorted_and_the_selection_follows_its_id() {
+        let (cmd_tx, _cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("sink-order");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let sink = |id: u32, description: &str, kind: DeviceKind| PwSink {
+            id,
+            name: format!("node{id}"),
+            description: description.into(),
+            kind,
+        };
+        let names = |app: &DaemonApp| -> Vec<String> {
+            app.snapshot()
+                .sinks
+                .into_iter()
+                .map(|s| s.description)
+                .collect()
+        };
+
+        evt_tx
+            .send(PwEvent::SinksUpdated(vec![
+                sink(3, "Mic", DeviceKind::Input),
+                sink(1, "Speakers", DeviceKind::Output),
+                sink(2, "Headphones", DeviceKind::Output),
+            ]))
+            .unwrap();
+        app.process_pw_events();
+        assert_eq!(names(&app), ["Headphones", "Speakers", "Mic"]);
+        app.apply_command(ClientCommand::SelectSink(1));
+
+        // A new device appears ahead of the selection in registry order
+        evt_tx
+            .send(PwEvent::SinksUpdated(vec![
+                sink(5, "Built-in Mic", DeviceKind::Source),
+                sink(1, "Speakers", DeviceKind::Output),
+                sink(4, "HDMI", DeviceKind::Output),
+                sink(2, "Headphones", DeviceKind::Output),
+                sink(3, "Mic", DeviceKind::Input),
+            ]))
+            .unwrap();
+        app.process_pw_events();
+        assert_eq!(
+            names(&app),
+            ["HDMI", "Headphones", "Speakers", "Mic", "Built-in Mic"]
+        );
+        assert_eq!(app.snapshot().sinks[app.selected_sink].id, 1);
+        assert_eq!(app.snapshot().sinks[4].kind, "Source");
+
+        // Songs can't play into a microphone
+        let result = command_result(app.apply_command(ClientCommand::SelectSink(4)));
+        assert!(result.is_some_and(|r| r.is_error()));
+        assert_eq!(app.snapshot().sinks[app.selected_sink].id, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn system_default_leads_the_list_and_plays_without_a_target() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("default-sink");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        add(&mut app, &fixture("silence.wav"));
+        let sink = |id: u32, name: &str, description: &str| PwSink {
+            id,
+            name: name.into(),
+            description: description.into(),
+            kind: DeviceKind::Output,
+        };
+        let sinks = vec![
+            sink(4, "usb", "A USB headset"),
+            sink(
+                DEFAULT_SINK_ID,
+                crate::pipewire::DEFAULT_SINK_NAME,
+                "System Default",
+            ),
+            sink(5, "hdmi", "HDMI"),
+        ];
+        evt_tx.send(PwEvent::SinksUpdated(sinks)).unwrap();
+        evt_tx
+            .send(PwEvent::DefaultSinkChanged(Some("hdmi".into())))
+            .unwrap();
+        let events = app.process_pw_events();
+        let Some(DaemonEvent::SinksUpdated(listed)) = events.last() else {
+            panic!("no SinksUpdated for the new default: {events:?}");
+        };
+        let listed: Vec<_> = listed
+            .iter()
+            .map(|s| (s.description.as_str(), s.is_default))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("System Default", false),
+                ("A USB headset", false),
+                ("HDMI", true)
+            ]
+        );
+
+        // The session manager picks the device, so it stays the same id
+        app.apply_command(ClientCommand::SelectSink(0));
+        app.apply_command(ClientCommand::Play);
+        let targets: Vec<u32> = cmd_rx
+            .try_iter()
+            .filter_map(|c| match c {
+                PwCommand::Play { sink_id, .. } => Some(sink_id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(targets, [DEFAULT_SINK_ID]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn playback_history_is_capped_and_counts_evictions() {
+        let (cmd_tx, _cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("history");
+        let config = dir.join("config.yaml");
+        std::fs::write(&config, "songs: []\nhistory_limits:\n  playback: 2\n").unwrap();
+        let mut app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+        for id in 0..3 {
+            let song = format!("{id}.wav");
+            evt_tx
+                .send(PwEvent::PlaybackFinished {
+                    id,
+                    song,
+                    reason: FinishReason::Completed,
+                })
+                .unwrap();
+        }
+        app.process_pw_events();
+        let playback = |app: &DaemonApp| {
+            app.history_stats()
+                .into_iter()
+                .find(|h| h.name == "playback")
+                .unwrap()
+        };
+        assert_eq!(
+            playback(&app),
+            HistoryStats {
+                name: "playback".into(),
+                len: 2,
+                cap: 2,
+                evicted: 1
+            }
+        );
+        let snapshot = app.history_snapshot();
+        let songs: Vec<&str> = snapshot["playback"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["song"].as_str().unwrap())
+            .collect();
+        assert_eq!(songs, ["1.wav", "2.wav"]);
+
+        // A lower cap from a reload trims right away, and is kept on save
+        std::fs::write(&config, "songs: []\nhistory_limits:\n  playback: 1\n").unwrap();
+        app.apply_command(ClientCommand::ReloadConfig);
+        let stats = playback(&app);
+        assert_eq!((stats.len, stats.cap, stats.evicted), (1, 1, 2));
+        app.apply_command(ClientCommand::SetLoop(true));
+        assert_eq!(Config::load(&config).history_limits.playback, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn moving_a_song_keeps_the_selection_on_the_same_song() {
+        // (selected, from, to) and where the selection ends up
+        for (idx, from, to, moved) in [
+            (2, 2, 0, 0),
+            (0, 2, 0, 1),
+            (1, 2, 0, 2),
+            (3, 2, 0, 3),
+            (0, 0, 2, 2),
+            (1, 0, 2, 0),
+            (2, 0, 2, 1),
+            (3, 0, 2, 3),
+            (1, 1, 1, 1),
+        ] {
+            assert_eq!(
+                moved_index(idx, from, to),
+                moved,
+                "{idx} after {from} -> {to}"
+            );
+        }
+
+        let mut t = test_daemon("move-song");
+        let names = ["horn.wav", "bell.wav", "drum.wav"];
+        for name in names {
+            let path = write_file(&t.dir, name, name.as_bytes());
+            add(&mut t.app, &path);
+        }
+        t.app.apply_command(ClientCommand::SelectSong(1));
+        let events = t
+            .app
+            .apply_command(ClientCommand::MoveSong { from: 2, to: 0 });
+        let Some(DaemonEvent::State(state)) = events.first() else {
+            panic!("a move broadcasts the new order: {events:?}");
+        };
+        let order: Vec<&str> = state.songs.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(order, ["drum.wav", "horn.wav", "bell.wav"]);
+        assert_eq!(state.songs[state.selected_song].name, "bell.wav");
+        let saved = Config::load(&t.app.config_path).songs;
+        assert!(saved[0].path.ends_with("drum.wav"), "{saved:?}");
+
+        let past_the_end = ClientCommand::MoveSong { from: 0, to: 3 };
+        let result = command_result(t.app.apply_command(past_the_end));
+        assert!(result.is_some_and(|r| r.is_error()));
+    }
+
+    #[test]
+    fn a_batch_of_songs_is_added_with_one_reply() {
+        let mut t = test_daemon("add-songs");
+        let horn = write_file(&t.dir, "horn.wav", b"horn");
+        add(&mut t.app, &horn);
+        let bell = write_file(&t.dir, "bell.wav", b"bell");
+        let drum = write_file(&t.dir, "drum.wav", b"drum");
+        let paths = [&horn, &bell, &drum, &bell, &t.dir.join("gone.wav")];
+        let paths = paths.iter().map(|p| p.display().to_string()).collect();
+
+        let events = t.app.apply_command(ClientCommand::AddSongs(paths));
+        let states = events
+            .iter()
+            .filter(|e| matches!(e, DaemonEvent::State(_)))
+            .count();
+        assert_eq!(states, 1);
+        assert_eq!(
+            command_result(events),
+            Some(CommandResult::Warning(
+                "Added 2, skipped 2 duplicates, 1 failed".to_string()
+            ))
+        );
+        assert_eq!(t.app.songs.len(), 3);
+        assert_eq!(Config::load(&t.app.config_path).songs.len(), 3);
+    }
+
+    #[test]
+    fn sorting_is_stable_keeps_the_selection_and_places_new_songs() {
+        let mut t = test_daemon("sort-songs");
+        let song = |name: &str, duration, added| Song {
+            path: t.dir.join(name),
+            name: name.to_string(),
+            undecodable: false,
+            missing: false,
+            tag_color: None,
+            volume: None,
+            duration,
+            added,
+        };
+        t.app.songs = vec![
+            song("drum.wav", Some(3.0), Some(30)),
+            song("Bell.wav", None, Some(10)),
+            song("bell.wav", Some(1.0), None),
+            song("horn.wav", Some(1.0), Some(20)),
+        ];
+        t.app.selected_song = 3;
+        let names = |app: &DaemonApp| app.songs.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+
+        t.app.apply_command(ClientCommand::SortSongs(SortKey::Name));
+        assert_eq!(
+            names(&t.app),
+            ["Bell.wav", "bell.wav", "drum.wav", "horn.wav"]
+        );
+        t.app
+            .apply_command(ClientCommand::SortSongs(SortKey::Duration));
+        assert_eq!(
+            names(&t.app),
+            ["bell.wav", "horn.wav", "drum.wav", "Bell.wav"]
+        );
+        t.app
+            .apply_command(ClientCommand::SortSongs(SortKey::Recent));
+        assert_eq!(
+            names(&t.app),
+            ["drum.wav", "horn.wav", "Bell.wav", "bell.wav"]
+        );
+        assert_eq!(t.app.songs[t.app.selected_song].name, "horn.wav");
+
+        // Added songs land in place, and the key survives a restart
+        t.app.apply_command(ClientCommand::SortSongs(SortKey::Name));
+        let clap = write_file(&t.dir, "clap.wav", b"clap");
+        add(&mut t.app, &clap);
+        assert_eq!(names(&t.app)[2], "clap.wav");
+        assert_eq!(t.app.songs[t.app.selected_song].name, "horn.wav");
+        assert_eq!(
+            Config::load(&t.app.config_path).song_sort,
+            Some(SortKey::Name)
+        );
+
+        // Moving a song by hand drops the sort
+        let events = t
+            .app
+            .apply_command(ClientCommand::MoveSong { from: 0, to: 4 });
+        assert!(matches!(&events[..], [DaemonEvent::State(s)] if s.song_sort.is_none()));
+    }
+
+    #[test]
+    fn reload_applies_edits_and_keeps_the_library_on_bad_yaml() {
+        let mut t = test_daemon("reload");
+        let horn = write_file(&t.dir, "horn.wav", b"horn");
+        let bell = write_file(&t.dir, "bell.wav", b"bell");
+        add(&mut t.app, &horn);
+        add(&mut t.app, &bell);
+        t.app.apply_command(ClientCommand::SelectSong(1));
+
+        let mut config = Config::load(&t.app.config_path);
+        config.songs.remove(0);
+        config.volume = 0.5;
+        config.save(&t.app.config_path);
+        let events = t.app.apply_command(ClientCommand::ReloadConfig);
+        assert!(command_result(events).is_some_and(|r| !r.is_error()));
+        assert_eq!(t.app.songs.len(), 1);
+        assert_eq!(t.app.songs[t.app.selected_song].name, "bell.wav");
+        assert_eq!(t.app.volume, 0.5);
+
+        std::fs::write(&t.app.config_path, "songs: [unterminated").unwrap();
+        let events = t.app.apply_command(ClientCommand::ReloadConfig);
+        assert!(command_result(events).is_some_and(|r| r.is_error()));
+        assert_eq!(t.app.songs.len(), 1);
+    }
+
+    #[test]
+    fn log_level_follows_the_config_and_can_be_raised_for_a_while() {
+        let mut t = test_daemon("loglevel");
+        let mut config = Config::load(&t.app.config_path);
+        config.log_level = LogLevel::Warn;
+        config.save(&t.app.config_path);
+        t.app.apply_command(ClientCommand::ReloadConfig);
+        assert_eq!(crate::log::level(), LogLevel::Warn);
+
+        let events = t
+            .app
+            .apply_command(ClientCommand::SetLogLevel(LogLevel::Debug));
+        assert!(command_result(events).is_some_and(|r| !r.is_error()));
+        assert_eq!(crate::log::level(), LogLevel::Debug);
+        // Only for this run: the config keeps its own level
+        t.app.save_config();
+        assert_eq!(Config::load(&t.app.config_path).log_level, LogLevel::Warn);
+        // Reloading an unchanged level leaves the raised one alone
+        t.app.apply_command(ClientCommand::ReloadConfig);
+        assert_eq!(crate::log::level(), LogLevel::Debug);
+        crate::log::set_level(LogLevel::Info);
+    }
+
+    #[test]
+    fn pause_only_applies_to_active_playback() {
+        let mut t = test_daemon("pause");
+        assert!(t.app.apply_command(ClientCommand::Pause).is_empty());
+        assert!(!t.app.snapshot().paused);
+
+        t.app.now_playing = Some("horn.wav".to_string());
+        let events = t.app.apply_command(ClientCommand::Pause);
+        assert!(matches!(events[..], [DaemonEvent::Paused(true)]));
+        assert!(t.app.apply_command(ClientCommand::Pause).is_empty());
+        assert!(t.app.snapshot().paused);
+
+        let events = t.app.apply_command(ClientCommand::Resume);
+        assert!(matches!(events[..], [DaemonEvent::Paused(false)]));
+        assert!(!t.app.paused);
+    }
+
+    #[test]
+    fn empty_device_list_is_retried_with_backoff() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("sink-retry");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let list_requests = || {
+            cmd_rx
+                .try_iter()
+                .filter(|c| matches!(c, PwCommand::ListSinks))
+                .count()
+        };
+
+        let events = app.apply_command(ClientCommand::Play);
+        assert!(matches!(&events[..], [DaemonEvent::Error { .. }]));
+
+        evt_tx.send(PwEvent::SinksUpdated(Vec::new())).unwrap();
+        app.process_pw_events();
+        let (_, delay) = app.sink_retry.unwrap();
+        assert_eq!(delay, SINK_RETRY_MIN);
+        // Due now: one request, and the next wait doubles
+        app.sink_retry = Some((std::time::Instant::now(), delay));
+        app.retry_sinks();
+        app.retry_sinks();
+        assert_eq!(list_requests(), 1);
+        assert_eq!(app.sink_retry.unwrap().1, SINK_RETRY_MIN * 2);
+
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind: DeviceKind::Output,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        assert!(app.sink_retry.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stop_playback_goes_through_the_backend() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("stop-playback");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+
+        assert!(app
+            .apply_command(ClientCommand::StopPlayback { clear_queue: false })
+            .is_empty());
+        assert!(!cmd_rx
+            .try_iter()
+            .any(|c| matches!(c, PwCommand::StopPlayback)));
+
+        app.now_playing = Some("horn.wav".to_string());
+        app.apply_command(ClientCommand::Pause);
+        let events = app.apply_command(ClientCommand::StopPlayback { clear_queue: false });
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::NowPlaying(None))));
+        assert!(cmd_rx
+            .try_iter()
+            .any(|c| matches!(c, PwCommand::StopPlayback)));
+        assert!(!app.paused);
+        assert_eq!(app.now_playing, None);
+
+        // The stopped stream's own report comes later and is only passed on
+        let reason = FinishReason::Stopped;
+        evt_tx
+            .send(PwEvent::PlaybackFinished {
+                id: 0,
+                song: "horn.wav".into(),
+                reason,
+            })
+            .unwrap();
+        assert!(matches!(
+            &app.process_pw_events()[..],
+            [DaemonEvent::SongEnded {
+                reason: FinishReason::Stopped,
+                ..
+            }]
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn playback_and_decode_failures_reach_clients() {
+        let (cmd_tx, _cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("playback-errors");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let kind = DeviceKind::Output;
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        let errors = |events: Vec<DaemonEvent>| -> Vec<(String, String)> {
+            events
+                .into_iter()
+                .filter_map(|e| match e {
+                    DaemonEvent::Error { context, message } => Some((context, message)),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        add(
+            &mut app,
+            &write_file(&dir, "broken.wav", b"not audio at all"),
+        );
+        app.apply_command(ClientCommand::Play);
+        let decode = errors(app.process_pw_events());
+        assert_eq!(decode.len(), 1);
+        assert_eq!(decode[0].0, "decode");
+        assert!(
+            decode[0].1.starts_with("Failed to decode broken.wav: "),
+            "{}",
+            decode[0].1
+        );
+        // Reported once
+        assert!(errors(app.process_pw_events()).is_empty());
+
+        let message = "Failed to play horn.wav: stream rejected the format".to_string();
+        evt_tx
+            .send(PwEvent::PlaybackError(message.clone()))
+            .unwrap();
+        let reason = FinishReason::Error;
+        evt_tx
+            .send(PwEvent::PlaybackFinished {
+                id: 0,
+                song: "horn.wav".into(),
+                reason,
+            })
+            .unwrap();
+        let events = app.process_pw_events();
+        assert!(matches!(
+            &events[..],
+            [DaemonEvent::Error { .. }, DaemonEvent::SongEnded { .. }, ..]
+        ));
+        assert_eq!(errors(events), [("playback".to_string(), message)]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn new_play_interrupts_or_queues_per_config() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("play-mode");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let kind = DeviceKind::Output;
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        add(&mut app, &fixture("silence.wav"));
+        let plays = || {
+            cmd_rx
+                .try_iter()
+                .filter_map(|c| match c {
+                    PwCommand::Play { id, interrupt, .. } => Some((id, interrupt)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let finish = |app: &mut DaemonApp, id, reason| {
+            let song = "silence.wav".to_string();
+            evt_tx
+                .send(PwEvent::PlaybackFinished { id, song, reason })
+                .unwrap();
+            app.process_pw_events()
+        };
+        let completed = FinishReason::Completed;
+
+        app.apply_command(ClientCommand::Play);
+        app.apply_command(ClientCommand::Play);
+        assert_eq!(plays(), [(0, true), (1, true)]);
+        assert!(matches!(
+            &finish(&mut app, 0, FinishReason::Replaced)[..],
+            [DaemonEvent::SongEnded {
+                reason: FinishReason::Replaced,
+                ..
+            }]
+        ));
+        assert_eq!(app.now_playing.as_deref(), Some("silence.wav"));
+        let events = finish(&mut app, 1, completed);
+        assert!(matches!(
+            events[0],
+            DaemonEvent::SongEnded {
+                reason: FinishReason::Completed,
+                ..
+            }
+        ));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::PlaybackFinished)));
+        assert_eq!(app.now_playing, None);
+
+        app.playback_mode = PlaybackMode::Queue;
+        app.apply_command(ClientCommand::Play);
+        app.apply_command(ClientCommand::Play);
+        assert_eq!(plays(), [(2, false), (3, false)]);
+        let events = finish(&mut app, 2, completed);
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::PlaybackFinished)));
+        assert_eq!(app.now_playing.as_deref(), Some("silence.wav"));
+        finish(&mut app, 3, completed);
+        assert_eq!(app.now_playing, None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn queued_songs_play_in_turn_on_the_selected_sink() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("queue");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let sink = |id: u32| PwSink {
+            id,
+            name: format!("out{id}"),
+            description: format!("Out {id}"),
+            kind: DeviceKind::Output,
+        };
+        evt_tx
+            .send(PwEvent::SinksUpdated(vec![sink(1), sink(2)]))
+            .unwrap();
+        app.process_pw_events();
+        let other = dir.join("other.wav");
+        std::fs::copy(fixture("silence.wav"), &other).unwrap();
+        add(&mut app, &fixture("silence.wav"));
+        add(&mut app, &other);
+        let plays = || {
+            cmd_rx
+                .try_iter()
+                .filter_map(|c| match c {
+                    PwCommand::Play {
+                        id, song, sink_id, ..
+                    } => Some((id, song, sink_id)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let finish = |app: &mut DaemonApp, id| {
+            let (song, reason) = (String::new(), FinishReason::Completed);
+            evt_tx
+                .send(PwEvent::PlaybackFinished { id, song, reason })
+                .unwrap();
+            app.process_pw_events()
+        };
+
+        // Idle, so the first one plays right away
+        app.apply_command(ClientCommand::Enqueue(0));
+        assert_eq!(plays(), [(0, "silence.wav".to_string(), 1)]);
+        app.apply_command(ClientCommand::Enqueue(1));
+        app.apply_command(ClientCommand::Enqueue(0));
+        assert_eq!(app.snapshot().queue.len(), 2);
+        assert!(plays().is_empty());
+
+        app.apply_command(ClientCommand::SelectSink(1));
+        let events = finish(&mut app, 0);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::QueueUpdated(q) if q.len() == 1)));
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::PlaybackFinished)));
+        assert_eq!(plays(), [(1, "other.wav".to_string(), 2)]);
+
+        // Removing a queued song takes it out of the queue
+        app.apply_command(ClientCommand::RemoveSong(0));
+        assert!(app.snapshot().queue.is_empty());
+        assert!(finish(&mut app, 1)
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::PlaybackFinished)));
+        assert!(plays().is_empty());
+
+        app.apply_command(ClientCommand::Enqueue(0));
+        app.apply_command(ClientCommand::Enqueue(0));
+        assert_eq!(app.snapshot().queue.len(), 1);
+        // Next cuts the playing one short for the queued one, then just stops
+        assert_eq!(plays(), [(2, "other.wav".to_string(), 2)]);
+        let events = app.apply_command(ClientCommand::Next);
+        assert!(matches!(
+            events.last(),
+            Some(DaemonEvent::NowPlaying(Some(_)))
+        ));
+        assert_eq!(plays(), [(3, "other.wav".to_string(), 2)]);
+        assert!(app.snapshot().queue.is_empty());
+        app.apply_command(ClientCommand::Next);
+        assert_eq!(app.now_playing, None);
+
+        app.apply_command(ClientCommand::Enqueue(0));
+        app.apply_command(ClientCommand::Enqueue(0));
+        assert_eq!(app.snapshot().queue.len(), 1);
+        app.apply_command(ClientCommand::StopPlayback { clear_queue: true });
+        assert!(app.snapshot().queue.is_empty());
+        assert_eq!(app.now_playing, None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn old_mid_boost_migrates_and_eq_bands_reach_the_stream() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("eq-bands");
+        let config = write_file(&dir, "config.yaml", b"songs: []\neq_mid_boost: 2.0\n");
+        let mut app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+        assert_eq!(
+            app.snapshot().eq,
+            EqGains {
+                low: 1.0,
+                mid: 2.0,
+                high: 1.0
+            }
+        );
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind: DeviceKind::Output,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        add(&mut app, &fixture("silence.wav"));
+
+        app.apply_command(ClientCommand::Play);
+        let _ = cmd_rx.try_iter().count();
+        // Sent straight away, so the clip playing now is retuned too
+        app.apply_command(ClientCommand::SetEqBand {
+            band: EqBand::High,
+            gain: 0.5,
+        });
+        let yaml = std::fs::read_to_string(&config).unwrap();
+        assert!(!yaml.contains("eq_mid_boost"));
+        assert_eq!(
+            Config::load(&config).eq,
+            EqGains {
+                low: 1.0,
+                mid: 2.0,
+                high: 0.5
+            }
+        );
+        let eq = cmd_rx.try_iter().find_map(|c| match c {
+            PwCommand::UpdateParams(live) => Some(live.eq),
+            _ => None,
+        });
+        assert_eq!(
+            eq,
+            Some(EqGains {
+                low: 1.0,
+                mid: 2.0,
+                high: 0.5
+            })
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn limiter_setting_is_kept_and_reaches_every_play() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("limiter");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let kind = DeviceKind::Output;
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        add(&mut app, &fixture("silence.wav"));
+        let limiter = || {
+            cmd_rx.try_iter().find_map(|c| match c {
+                PwCommand::Play { options, .. } => Some(options.limiter),
+                _ => None,
+            })
+        };
+
+        app.apply_command(ClientCommand::Play);
+        assert_eq!(limiter(), Some(false));
+        app.apply_command(ClientCommand::SetLimiter(true));
+        assert!(Config::load(&dir.join("config.yaml")).limiter);
+        assert!(app.snapshot().limiter);
+        app.apply_command(ClientCommand::Play);
+        assert_eq!(limiter(), Some(true));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn noise_kind_is_kept_and_reaches_every_stream() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("noise-kind");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let kind = DeviceKind::Output;
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        add(&mut app, &fixture("silence.wav"));
+        assert_eq!(app.noise_kind, NoiseKind::White);
+
+        app.apply_command(ClientCommand::SetNoiseKind(NoiseKind::Pink));
+        assert_eq!(
+            Config::load(&dir.join("config.yaml")).noise_kind,
+            NoiseKind::Pink
+        );
+        assert_eq!(app.snapshot().noise_kind, NoiseKind::Pink);
+        app.apply_command(ClientCommand::Play);
+        let (mut play, mut continuous) = (None, None);
+        for c in cmd_rx.try_iter() {
+            match c {
+                PwCommand::Play { options, .. } => play = Some(options.noise_kind),
+                PwCommand::ContinuousNoise { kind, .. } => continuous = Some(kind),
+                _ => {}
+            }
+        }
+        assert_eq!(play, Some(NoiseKind::Pink));
+        assert_eq!(continuous, Some(NoiseKind::Pink));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn comfort_noise_reaches_the_playing_clip_unless_continuous_noise_covers_it() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("live-noise");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let sink = PwSink {
+            id: 1,
+            name: "mic".into(),
+            description: "Mic".into(),
+            kind: DeviceKind::Input,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        add(&mut app, &fixture("silence.wav"));
+        app.apply_command(ClientCommand::Play);
+        let last_noise = || {
+            cmd_rx.try_iter().fold(None, |last, c| match c {
+                PwCommand::UpdateParams(live) => Some(live.comfort_noise),
+                _ => last,
+            })
+        };
+        let _ = last_noise();
+
+        app.apply_command(ClientCommand::SetComfortNoise(0.03));
+        assert_eq!(last_noise(), Some(0.03));
+        // The continuous stream takes over the noise for this input
+        app.apply_command(ClientCommand::SetContinuousNoise(true));
+        assert_eq!(last_noise(), Some(0.0));
+        app.apply_command(ClientCommand::SetContinuousNoise(false));
+        assert_eq!(last_noise(), Some(0.03));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn fade_setting_reaches_every_play() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("fade");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let kind = DeviceKind::Output;
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        add(&mut app, &fixture("silence.wav"));
+
+        app.apply_command(ClientCommand::SetFade(0.25));
+        assert_eq!(Config::load(&dir.join("config.yaml")).fade_secs, 0.25);
+        assert_eq!(app.snapshot().fade_secs, 0.25);
+        app.apply_command(ClientCommand::Play);
+        let options = cmd_rx.try_iter().find_map(|c| match c {
+            PwCommand::Play { options, .. } => Some(options),
+            _ => None,
+        });
+        let options = options.expect("play sent");
+        assert_eq!((options.fade_in_ms, options.fade_out_ms), (250, 250));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn loop_replays_the_clip_until_switched_off() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("loop");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let kind = DeviceKind::Output;
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        let rain = dir.join("rain.wav");
+        std::fs::copy(fixture("silence.wav"), &rain).unwrap();
+        add(&mut app, &rain);
+        let plays = || {
+            cmd_rx
+                .try_iter()
+                .filter(|c| matches!(c, PwCommand::Play { .. }))
+                .count()
+        };
+        let finish = |app: &mut DaemonApp, id, reason| {
+            let song = "rain.wav".to_string();
+            evt_tx
+                .send(PwEvent::PlaybackFinished { id, song, reason })
+                .unwrap();
+            app.process_pw_events()
+        };
+
+        app.apply_command(ClientCommand::SetLoop(true));
+        assert!(Config::load(&dir.join("config.yaml")).loop_enabled);
+        app.apply_command(ClientCommand::Play);
+        assert_eq!(plays(), 1);
+        finish(&mut app, 0, FinishReason::Completed);
+        finish(&mut app, 1, FinishReason::Completed);
+        assert_eq!(plays(), 2);
+        assert_eq!(app.now_playing.as_deref(), Some("rain.wav"));
+
+        // Errors and stops never loop
+        finish(&mut app, 2, FinishReason::Error);
+        assert_eq!((plays(), app.now_playing.as_deref()), (0, None));
+
+        // Each replay streams the file again, so a deleted file ends the loop
+        app.apply_command(ClientCommand::Play);
+        std::fs::remove_file(&rain).unwrap();
+        finish(&mut app, 3, FinishReason::Completed);
+        assert_eq!((plays(), app.now_playing.as_deref()), (1, None));
+
+        std::fs::copy(fixture("silence.wav"), &rain).unwrap();
+        app.apply_command(ClientCommand::Play);
+        app.apply_command(ClientCommand::SetLoop(false));
+        finish(&mut app, 4, FinishReason::Completed);
+        assert_eq!((plays(), app.now_playing.as_deref()), (1, None));
+
+        // Switched on while the song plays, it is that song that comes round
+        app.apply_command(ClientCommand::Play);
+        app.apply_command(ClientCommand::SetLoop(true));
+        finish(&mut app, 5, FinishReason::Completed);
+        assert_eq!((plays(), app.now_playing.as_deref()), (2, Some("rain.wav")));
+
+        app.apply_command(ClientCommand::RemoveSong(0));
+        finish(&mut app, 6, FinishReason::Completed);
+        assert_eq!((plays(), app.now_playing.as_deref()), (0, None));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn queue_entries_can_be_reordered_and_removed() {
+        let mut t = test_daemon("queue-edit");
+        let [a, b, c] = ["a.wav", "b.wav", "c.wav"].map(|name| t.dir.join(name));
+        t.app.queue = [a.clone(), b.clone(), c.clone()].into();
+        let shown = |events: Vec<DaemonEvent>| match &events[..] {
+            [DaemonEvent::QueueUpdated(queue)] => queue.clone(),
+            other => panic!("{other:?}"),
+        };
+
+        let queue = shown(
+            t.app
+                .apply_command(ClientCommand::ReorderQueue { from: 2, to: 0 }),
+        );
+        assert_eq!(queue, [&c, &a, &b].map(|p| p.display().to_string()));
+        let queue = shown(t.app.apply_command(ClientCommand::RemoveFromQueue(1)));
+        assert_eq!(queue, [&c, &b].map(|p| p.display().to_string()));
+
+        // Positions past the end are refused and change nothing
+        let events = t
+            .app
+            .apply_command(ClientCommand::ReorderQueue { from: 0, to: 2 });
+        assert!(matches!(
+            command_result(events),
+            Some(CommandResult::Error(_))
+        ));
+        let events = t.app.apply_command(ClientCommand::RemoveFromQueue(2));
+        assert!(matches!(
+            command_result(events),
+            Some(CommandResult::Error(_))
+        ));
+        assert_eq!(t.app.queue, [c, b]);
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn trigger_latency_is_reported_when_first_audio_flows() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("trigger-latency");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let kind = DeviceKind::Output;
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        add(&mut app, &fixture("silence.wav"));
+        app.apply_command(ClientCommand::AddWordMapping {
+            word: "lol".to_string(),
+            song_indices: vec![0],
+            selection: SongSelection::Random,
+            source_description: String::new(),
+            output_description: String::new(),
+        });
+        let timed_plays = |rx: &std::sync::mpsc::Receiver<PwCommand>| -> Vec<(u64, bool)> {
+            rx.try_iter()
+                .filter_map(|c| match c {
+                    PwCommand::Play { id, timed, .. } => Some((id, timed)),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // Manual plays carry no timing
+        app.apply_command(ClientCommand::Play);
+        assert_eq!(timed_plays(&cmd_rx), [(0, false)]);
+
+        let (match_tx, match_rx) = std::sync::mpsc::channel();
+        app.detector_match_rx = Some(match_rx);
+        let matched = std::time::Instant::now() - std::time::Duration::from_millis(120);
+        match_tx
+            .send(DetectorEvent::Matched("lol".to_string(), matched))
+            .unwrap();
+        app.poll_detector_matches();
+        assert_eq!(timed_plays(&cmd_rx), [(1, true)]);
+
+        let now = std::time::Instant::now();
+        evt_tx
+            .send(PwEvent::PlaybackStarted {
+                id: 1,
+                connected: now,
+                first_audio: now,
+            })
+            .unwrap();
+        evt_tx
+            .send(PwEvent::PlaybackStarted {
+                id: 1,
+                connected: now,
+                first_audio: now,
+            })
+            .unwrap();
+        let latencies: Vec<u64> = app
+            .process_pw_events()
+            .into_iter()
+            .filter_map(|e| match e {
+                DaemonEvent::TriggerLatency {
+                    word,
+                    total_ms,
+                    decode_ms,
+                    connect_ms,
+                } => {
+                    assert_eq!(word, "lol");
+                    assert!(decode_ms <= total_ms && connect_ms <= total_ms);
+                    Some(total_ms)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(latencies.len(), 1);
+        assert!(latencies[0] >= 120);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn detector_refuses_to_start_while_unavailable() {
+        let mut t = test_daemon("vosk-missing");
+        t.app.word_detector_status = WordDetectorStatus::Unavailable;
+        let events = t.app.apply_command(ClientCommand::StartWordDetector(5));
+        assert!(command_result(events).is_some_and(|r| r.is_error()));
+        assert!(t.app.detector_stop_tx.is_none());
+
+        t.app.word_detector_status = WordDetectorStatus::Ready;
+        t.app
+            .set_vosk_missing("Cannot load libvosk.so: not found".to_string());
+        assert_eq!(t.app.word_detector_status, WordDetectorStatus::Unavailable);
+        assert!(t.app.snapshot().vosk_missing.is_some());
+        for cmd in [
+            ClientCommand::StartWordDetector(5),
+            ClientCommand::StartModelDownload,
+        ] {
+            let result = command_result(t.app.apply_command(cmd)).unwrap();
+            assert!(result.is_error() && result.message().contains("Vosk library missing"));
+        }
+        assert!(t.app.detector_stop_tx.is_none());
+        assert_eq!(t.app.word_detector_status, WordDetectorStatus::Unavailable);
+
+        // Nor does a model that finishes downloading make it Ready
+        t.app.apply_command(ClientCommand::ModelDownloadComplete);
+        assert_eq!(t.app.word_detector_status, WordDetectorStatus::Unavailable);
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn cancelled_download_goes_back_to_unavailable() {
+        use std::sync::atomic::Ordering;
+        let mut t = test_daemon("cancel-download");
+        let idle = t.app.apply_command(ClientCommand::CancelModelDownload);
+        assert!(command_result(idle).is_some_and(|r| r.is_error()));
+
+        t.app.apply_command(ClientCommand::StartModelDownload);
+        let flag = t.app.download_cancel.clone();
+        let events = t.app.apply_command(ClientCommand::CancelModelDownload);
+        assert!(flag.load(Ordering::Relaxed));
+        assert_eq!(t.app.word_detector_status, WordDetectorStatus::Unavailable);
+        assert!(command_result(events).is_some_and(|r| !r.is_error()));
+
+        // The aborted thread's late failure doesn't overwrite the status
+        t.app
+            .apply_command(ClientCommand::ModelDownloadFailed("cancelled".to_string()));
+        assert_eq!(t.app.word_detector_status, WordDetectorStatus::Unavailable);
+
+        // A new download starts with its own flag
+        t.app.apply_command(ClientCommand::StartModelDownload);
+        assert!(!t.app.download_cancel.load(Ordering::Relaxed));
+        assert!(flag.load(Ordering::Relaxed));
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn word_trigger_leaves_the_selection_alone() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("trigger-selection");
+        let mut app = DaemonApp::with_backend(dir.join("config.yaml"), cmd_tx, evt_rx);
+        let kind = DeviceKind::Output;
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        let horn = dir.join("horn.wav");
+        std::fs::copy(fixture("silence.wav"), &horn).unwrap();
+        add(&mut app, &fixture("silence.wav"));
+        add(&mut app, &horn);
+        app.apply_command(ClientCommand::AddWordMapping {
+            word: "lol".to_string(),
+            song_indices: vec![1],
+            selection: SongSelection::Random,
+            source_description: String::new(),
+            output_description: String::new(),
+        });
+        app.apply_command(ClientCommand::SelectSong(0));
+
+        let (match_tx, match_rx) = std::sync::mpsc::channel();
+        app.detector_match_rx = Some(match_rx);
+        match_tx
+            .send(DetectorEvent::Matched(
+                "lol".to_string(),
+                std::time::Instant::now(),
+            ))
+            .unwrap();
+        let events = app.poll_detector_matches();
+
+        assert_eq!(app.selected_song, 0);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, DaemonEvent::NowPlaying(Some(np)) if np == "horn.wav")));
+        assert!(events
+            .iter()
+            .all(|e| !matches!(e, DaemonEvent::State(s) if s.selected_song != 0)));
+        let played: Vec<String> = cmd_rx
+            .try_iter()
+            .filter_map(|c| match c {
+                PwCommand::Play { song, .. } => Some(song),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(played, ["horn.wav"]);
+
+        // The next manual Play is still the user's pick
+        app.apply_command(ClientCommand::Play);
+        assert!(cmd_rx
+            .try_iter()
+            .any(|c| matches!(c, PwCommand::Play { song, .. } if song == "silence.wav")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn multi_song_bindings_pick_in_turn_or_at_random() {
+        let mut t = test_daemon("multi-song");
+        for name in ["a.wav", "b.wav", "c.wav"] {
+            let path = write_file(&t.dir, name, name.as_bytes());
+            add(&mut t.app, &path);
+        }
+        let names: Vec<String> = t.app.songs.iter().map(|s| s.name.clone()).collect();
+        let modes = [
+            ("lol", SongSelection::RoundRobin),
+            ("rofl", SongSelection::Random),
+        ];
+        for (word, selection) in modes {
+            t.app.apply_command(ClientCommand::AddWordMapping {
+                word: word.to_string(),
+                song_indices: vec![0, 1, 2],
+                selection,
+                source_description: String::new(),
+                output_description: String::new(),
+            });
+        }
+        let fire = |app: &mut DaemonApp, word: &str| {
+            app.apply_command(ClientCommand::SimulateWord(word.to_string()))
+                .into_iter()
+                .find_map(|e| match e {
+                    DaemonEvent::WordDetected { song, .. } => song,
+                    _ => None,
+                })
+                .expect("a song was picked")
+        };
+
+        let in_turn: Vec<String> = (0..4).map(|_| fire(&mut t.app, "lol")).collect();
+        assert_eq!(in_turn, [0, 1, 2, 0].map(|i| names[i].clone()));
+        let random: Vec<String> = (0..20).map(|_| fire(&mut t.app, "rofl")).collect();
+        assert!(random.windows(2).all(|w| w[0] != w[1]), "{random:?}");
+        assert!(random.iter().all(|song| names.contains(song)));
+
+        // Songs that leave the library are skipped
+        t.app.apply_command(ClientCommand::RemoveSong(1));
+        assert_eq!(fire(&mut t.app, "lol"), names[2]);
+        assert_eq!(fire(&mut t.app, "lol"), names[0]);
+        assert_eq!(t.app.word_mappings[0].stats.fired, 6);
+
+        // A new binding for the same word starts its own turn
+        t.app.apply_command(ClientCommand::RemoveWordMapping(0));
+        t.app.apply_command(ClientCommand::AddWordMapping {
+            word: "lol".to_string(),
+            song_indices: vec![0, 1],
+            selection: SongSelection::RoundRobin,
+            source_description: String::new(),
+            output_description: String::new(),
+        });
+        assert_eq!(fire(&mut t.app, "lol"), names[0]);
+        assert_eq!(fire(&mut t.app, "lol"), names[2]);
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn single_song_bindings_from_old_configs_still_load() {
+        let dir = scratch_dir("old-binding");
+        let song = dir.join("horn.wav");
+        std::fs::copy(fixture("silence.wav"), &song).unwrap();
+        let yaml = format!(
+            "songs:\n- {0}\nword_mappings:\n- word: lol\n  song_path: {0}\n",
+            song.display()
+        );
+        let config = write_file(&dir, "config.yaml", yaml.as_bytes());
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+        assert_eq!(
+            app.word_mappings[0].song_paths,
+            [song.display().to_string()]
+        );
+        assert_eq!(app.word_mappings[0].song_names, ["horn.wav"]);
+
+        app.save_config();
+        let saved = std::fs::read_to_string(&config).unwrap();
+        assert!(
+            saved.contains("song_paths:") && !saved.contains("song_path:"),
+            "{saved}"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn matcher_overrides_apply_per_model_and_survive_a_save() {
+        let dir = scratch_dir("matchers");
+        let yaml = "songs: []\ndetector_matchers:\n  vosk-model-small-pt-0.3:\n    \
+                    fuzzy_threshold: 0.9\n  vosk-model-small-en-us-0.15:\n    \
+                    fuzzy_threshold: 7.0\n    fold_accents: false\n";
+        let config = write_file(&dir, "config.yaml", yaml.as_bytes());
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+
+        let pt = matcher_for(&app.detector_matchers, "vosk-model-small-pt-0.3");
+        assert_eq!(
+            pt,
+            MatcherSettings {
+                fuzzy_threshold: 0.9,
+                ..MatcherSettings::PORTUGUESE
+            }
+        );
+        // An out-of-range threshold keeps the model's default
+        let en = matcher_for(&app.detector_matchers, "vosk-model-small-en-us-0.15");
+        assert_eq!(
+            en,
+            MatcherSettings {
+                fold_accents: false,
+                ..MatcherSettings::GENERIC
+            }
+        );
+        assert_eq!(
+            matcher_for(&app.detector_matchers, "other"),
+            MatcherSettings::GENERIC
+        );
+
+        app.save_config();
+        let saved = Config::load(&config);
+        assert_eq!(saved.detector_matchers, app.detector_matchers);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn detector_power_mode_is_saved_and_reloaded() {
+        let mut t = test_daemon("power-mode");
+        assert_eq!(
+            t.app.snapshot().detector_power_mode,
+            DetectorPowerMode::Performance
+        );
+
+        t.app.apply_command(ClientCommand::SetDetectorPowerMode(
+            DetectorPowerMode::Efficiency,
+        ));
+        assert_eq!(
+            t.app.snapshot().detector_power_mode,
+            DetectorPowerMode::Efficiency
+        );
+        let text = std::fs::read_to_string(&t.app.config_path).unwrap();
+        assert!(text.contains("detector_power_mode: efficiency"), "{text}");
+
+        std::fs::write(
+            &t.app.config_path,
+            "songs: []\ndetector_power_mode: balanced\n",
+        )
+        .unwrap();
+        t.app.apply_command(ClientCommand::ReloadConfig);
+        assert_eq!(
+            t.app.snapshot().detector_power_mode,
+            DetectorPowerMode::Balanced
+        );
+        assert_eq!(
+            PowerMode::from(t.app.detector_power_mode),
+            PowerMode::Balanced
+        );
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn disabled_and_muted_bindings_do_not_fire() {
+        let mut t = test_daemon("bindings-off");
+        let horn = write_file(&t.dir, "horn.wav", b"horn");
+        add(&mut t.app, &horn);
+        t.app.apply_command(ClientCommand::AddWordMapping {
+            word: "lol".to_string(),
+            song_indices: vec![0],
+            selection: SongSelection::Random,
+            source_description: String::new(),
+            output_description: String::new(),
+        });
+        let simulate = |app: &mut DaemonApp| {
+            command_result(app.apply_command(ClientCommand::SimulateWord("lol".to_string())))
+                .is_some_and(|r| !r.is_error())
+        };
+
+        t.app.apply_command(ClientCommand::SetWordMappingEnabled {
+            index: 0,
+            enabled: false,
+        });
+        assert!(!simulate(&mut t.app));
+        assert!(t.app.active_keywords().is_empty());
+
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let mut reloaded = DaemonApp::with_backend(t.dir.join("config.yaml"), cmd_tx, evt_rx);
+        assert!(!reloaded.word_mappings[0].enabled);
+
+        reloaded.apply_command(ClientCommand::SetWordMappingEnabled {
+            index: 0,
+            enabled: true,
+        });
+        assert!(simulate(&mut reloaded));
+        reloaded.apply_command(ClientCommand::SetWordMappingsMuted(true));
+        assert!(!simulate(&mut reloaded));
+        assert!(reloaded.word_mappings[0].enabled);
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn bindings_restricted_to_the_input_win_over_generic_ones() {
+        let binding = |word: &str, only: Option<&str>| WordMapping {
+            word: word.to_string(),
+            song_names: Vec::new(),
+            song_paths: Vec::new(),
+            selection: SongSelection::Random,
+            source_description: String::new(),
+            output_description: String::new(),
+            only_from_source: only.map(String::from),
+            stats: WordStats::default(),
+            enabled: true,
+            action: MappingAction::PlaySong,
+        };
+        let mappings = [
+            binding("alerta", None),
+            binding("alerta", Some("Discord")),
+            binding("alerta", Some("USB Mic")),
+            binding("bomba", Some("USB Mic")),
+        ];
+
+        assert_eq!(
+            resolve_binding(&mappings, "Alerta", Some("USB Mic")),
+            Some(2)
+        );
+        assert_eq!(
+            resolve_binding(&mappings, "alerta", Some("Discord")),
+            Some(1)
+        );
+        // No restricted binding for this input, or no input at all
+        assert_eq!(
+            resolve_binding(&mappings, "alerta", Some("Firefox")),
+            Some(0)
+        );
+        assert_eq!(resolve_binding(&mappings, "alerta", None), Some(0));
+        // A restricted binding never fires elsewhere, even without a fallback
+        assert_eq!(resolve_binding(&mappings, "bomba", Some("Discord")), None);
+        assert_eq!(
+            resolve_binding(&mappings, "bomba", Some("USB Mic")),
+            Some(3)
+        );
+
+        let mut disabled = mappings.clone();
+        disabled[2].enabled = false;
+        assert_eq!(
+            resolve_binding(&disabled, "alerta", Some("USB Mic")),
+            Some(0)
+        );
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn bindings_can_be_restricted_to_their_own_input() {
+        let mut t = test_daemon("source-only");
+        let horn = write_file(&t.dir, "horn.wav", b"horn");
+        add(&mut t.app, &horn);
+        for source in ["", "USB Mic"] {
+            t.app.apply_command(ClientCommand::AddWordMapping {
+                word: "alerta".to_string(),
+                song_indices: vec![0],
+                selection: SongSelection::Random,
+                source_description: source.to_string(),
+                output_description: String::new(),
+            });
+        }
+
+        let restrict = ClientCommand::SetWordMappingSourceOnly {
+            index: 0,
+            only: true,
+        };
+        assert!(command_result(t.app.apply_command(restrict)).is_some_and(|r| r.is_error()));
+        t.app
+            .apply_command(ClientCommand::SetWordMappingSourceOnly {
+                index: 1,
+                only: true,
+            });
+        assert_eq!(
+            t.app.word_mappings[1].only_from_source.as_deref(),
+            Some("USB Mic")
+        );
+
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let mut reloaded = DaemonApp::with_backend(t.dir.join("config.yaml"), cmd_tx, evt_rx);
+        assert_eq!(
+            reloaded.word_mappings[1].only_from_source.as_deref(),
+            Some("USB Mic")
+        );
+        reloaded.apply_command(ClientCommand::SetWordMappingSourceOnly {
+            index: 1,
+            only: false,
+        });
+        assert_eq!(reloaded.word_mappings[1].only_from_source, None);
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn speak_bindings_play_synthesized_phrases() {
+        let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let dir = scratch_dir("speak-binding");
+        let script = format!("cp {} \"$1\"\n", fixture("silence.wav").display());
+        let script = write_file(&dir, "tts.sh", script.as_bytes());
+        let config = format!("songs: []\ntts_command: sh {} {{out}}\n", script.display());
+        let config = write_file(&dir, "config.yaml", config.as_bytes());
+        let mut app = DaemonApp::with_backend(config.clone(), cmd_tx, evt_rx);
+        app.tts_cache = dir.join("tts");
+        let sink = PwSink {
+            id: 1,
+            name: "out".into(),
+            description: "Out".into(),
+            kind: DeviceKind::Output,
+        };
+        evt_tx.send(PwEvent::SinksUpdated(vec![sink])).unwrap();
+        app.process_pw_events();
+        app.apply_command(ClientCommand::AddSpeakMapping {
+            word: "hora".to_string(),
+            phrase: "São {time}".to_string(),
+            source_description: String::new(),
+            output_description: String::new(),
+        });
+
+        let events = app.apply_command(ClientCommand::SimulateWord("hora".to_string()));
+        assert!(command_result(events).is_some_and(|r| !r.is_error()));
+        let mut events = Vec::new();
+        for _ in 0..500 {
+            events = app.poll_speech();
+            if !events.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(events.iter().any(|e| {
+            matches!(e, DaemonEvent::NowPlaying(Some(np)) if np.starts_with("\"São "))
+        }));
+        assert!(cmd_rx
+            .try_iter()
+            .any(|c| matches!(c, PwCommand::Play { .. })));
+        assert_eq!(app.word_mappings[0].stats.fired, 1);
+
+        // The binding survives a reload without a song behind it
+        let (cmd_tx, _) = std::sync::mpsc::channel();
+        let (_, evt_rx) = std::sync::mpsc::channel();
+        let reloaded = DaemonApp::with_backend(config, cmd_tx, evt_rx);
+        assert_eq!(reloaded.word_mappings.len(), 1);
+        let speak = MappingAction::Speak("São {time}".to_string());
+        assert_eq!(reloaded.word_mappings[0].action, speak);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn speak_without_a_tts_command_reports_why() {
+        let mut t = test_daemon("speak-no-tts");
+        t.app.apply_command(ClientCommand::AddSpeakMapping {
+            word: "hora".to_string(),
+            phrase: "hello".to_string(),
+            source_description: String::new(),
+            output_description: String::new(),
+        });
+        let events = t
+            .app
+            .apply_command(ClientCommand::SimulateWord("hora".to_string()));
+        assert!(events
+            .iter()
+            .any(|e| { matches!(e, DaemonEvent::CommandResult(r) if r.is_error()) }));
     }
 }
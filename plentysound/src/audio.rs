@@ -1,19 +1,18 @@
 use anyhow::{Context, Result};
 use std::path::Path;
+use std::sync::mpsc::{Receiver, SyncSender, TryRecvError};
 use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::DecoderOptions;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::codecs::{CodecParameters, CodecType, Decoder, DecoderOptions};
+use symphonia::core::formats::{FormatOptions, FormatReader};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 
-pub struct DecodedAudio {
-    pub samples: Vec<f32>,
-    pub sample_rate: u32,
-    pub channels: u32,
-}
-
-pub fn decode_file(path: &Path) -> Result<DecodedAudio> {
+/// Check that `path` can be played without decoding it: the container must
+/// be recognized and its default track must use a codec we have a decoder for.
+/// Returns the track's length from the container headers, `None` when they
+/// don't say.
+pub fn probe_decodable(path: &Path) -> Result<Option<std::time::Duration>> {
     let file = std::fs::File::open(path)
         .with_context(|| format!("Failed to open {}", path.display()))?;
 
@@ -26,58 +25,269 @@ pub fn decode_file(path: &Path) -> Result<DecodedAudio> {
 
     let probed = symphonia::default::get_probe()
         .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
-        .context("Unsupported audio format")?;
+        .context("unsupported audio format")?;
 
-    let mut format = probed.format;
-
-    let track = format
+    let track = probed
+        .format
         .default_track()
-        .context("No audio track found")?;
-
-    let sample_rate = track
-        .codec_params
-        .sample_rate
-        .context("Unknown sample rate")?;
-    let channels = track
-        .codec_params
-        .channels
-        .map(|c| c.count() as u32)
-        .context("Unknown channel count")?;
-    let track_id = track.id;
-
-    let mut decoder = symphonia::default::get_codecs()
-        .make(&track.codec_params, &DecoderOptions::default())
-        .context("Failed to create decoder")?;
-
-    let mut samples = Vec::new();
-
-    loop {
-        let packet = match format.next_packet() {
-            Ok(p) => p,
-            Err(symphonia::core::errors::Error::IoError(ref e))
-                if e.kind() == std::io::ErrorKind::UnexpectedEof =>
-            {
-                break;
+        .context("no audio track found")?;
+
+    let codec = track.codec_params.codec;
+    if symphonia::default::get_codecs().get_codec(codec).is_none() {
+        anyhow::bail!("missing {} support", codec_name(codec));
+    }
+    Ok(duration(&track.codec_params))
+}
+
+fn duration(params: &CodecParameters) -> Option<std::time::Duration> {
+    let frames = params.n_frames?;
+    let time = match params.time_base {
+        Some(tb) => tb.calc_time(frames),
+        None => symphonia::core::units::TimeBase::new(1, params.sample_rate?).calc_time(frames),
+    };
+    Some(std::time::Duration::from_secs_f64(
+        time.seconds as f64 + time.frac,
+    ))
+}
+
+// Symphonia only names the codecs it registers, so spell out the common
+// ones it knows about but can't decode.
+fn codec_name(codec: CodecType) -> String {
+    use symphonia::core::codecs::{
+        CODEC_TYPE_DCA, CODEC_TYPE_EAC3, CODEC_TYPE_MONKEYS_AUDIO, CODEC_TYPE_MUSEPACK,
+        CODEC_TYPE_OPUS, CODEC_TYPE_SPEEX, CODEC_TYPE_TTA, CODEC_TYPE_WAVPACK, CODEC_TYPE_WMA,
+    };
+    let name = match codec {
+        CODEC_TYPE_OPUS => "Opus",
+        CODEC_TYPE_SPEEX => "Speex",
+        CODEC_TYPE_WAVPACK => "WavPack",
+        CODEC_TYPE_MONKEYS_AUDIO => "Monkey's Audio",
+        CODEC_TYPE_MUSEPACK => "Musepack",
+        CODEC_TYPE_EAC3 => "E-AC-3",
+        CODEC_TYPE_DCA => "DTS",
+        CODEC_TYPE_WMA => "WMA",
+        CODEC_TYPE_TTA => "TTA",
+        _ => return format!("codec {codec}"),
+    };
+    name.to_string()
+}
+
+/// An opened file's default track, decoded a packet at a time.
+struct Decoding {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    channels: u32,
+    /// Frame count from the container headers, if it says.
+    frames: Option<u64>,
+}
+
+impl Decoding {
+    fn open(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                mss,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .context("Unsupported audio format")?;
+
+        let format = probed.format;
+
+        let track = format.default_track().context("No audio track found")?;
+
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .context("Unknown sample rate")?;
+        let channels = track
+            .codec_params
+            .channels
+            .map(|c| c.count() as u32)
+            .context("Unknown channel count")?;
+        let track_id = track.id;
+        let frames = track.codec_params.n_frames;
+
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .context("Failed to create decoder")?;
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            sample_rate,
+            channels,
+            frames,
+        })
+    }
+
+    /// Interleaved samples of the next packet on our track, `None` at the end.
+    fn next_samples(&mut self) -> Result<Option<Vec<f32>>> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(p) => p,
+                Err(symphonia::core::errors::Error::IoError(ref e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(None);
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
             }
-            Err(e) => return Err(e.into()),
-        };
 
-        if packet.track_id() != track_id {
-            continue;
+            let decoded = self.decoder.decode(&packet)?;
+            let spec = *decoded.spec();
+            let duration = decoded.capacity();
+
+            let mut sample_buf = SampleBuffer::<f32>::new(duration as u64, spec);
+            sample_buf.copy_interleaved_ref(decoded);
+            return Ok(Some(sample_buf.samples().to_vec()));
         }
+    }
+}
+
+/// Audio the decoder thread hands over per chunk, about 100 ms of it.
+const STREAM_CHUNK_MS: u32 = 100;
+
+/// Chunks the decoder may run ahead of playback before it waits, so a
+/// streamed file holds about three seconds in memory however long it is.
+pub const STREAM_BUFFER_CHUNKS: usize = 30;
+
+/// A file decoding on its own thread. Chunks of interleaved samples come
+/// through a bounded channel, so playback can start as soon as the first
+/// ones arrive; the channel closing marks the end of the clip.
+pub struct AudioStream {
+    pub sample_rate: u32,
+    pub channels: u32,
+    /// Interleaved sample count from the container headers, if they say.
+    pub total_samples: Option<usize>,
+    chunks: Receiver<Result<Vec<f32>>>,
+}
 
-        let decoded = decoder.decode(&packet)?;
-        let spec = *decoded.spec();
-        let duration = decoded.capacity();
+/// What `AudioStream` had ready when asked.
+pub enum StreamChunk {
+    Samples(Vec<f32>),
+    /// The decoder hasn't caught up yet.
+    Pending,
+    Ended,
+    /// Decoding stopped part way; nothing more will come.
+    Failed(anyhow::Error),
+}
 
-        let mut sample_buf = SampleBuffer::<f32>::new(duration as u64, spec);
-        sample_buf.copy_interleaved_ref(decoded);
-        samples.extend_from_slice(sample_buf.samples());
+impl AudioStream {
+    /// A stream fed by hand through `tx`, at most `capacity` chunks ahead.
+    pub fn channel(
+        sample_rate: u32,
+        channels: u32,
+        total_samples: Option<usize>,
+        capacity: usize,
+    ) -> (SyncSender<Result<Vec<f32>>>, Self) {
+        let (tx, chunks) = std::sync::mpsc::sync_channel(capacity);
+        (
+            tx,
+            Self {
+                sample_rate,
+                channels,
+                total_samples,
+                chunks,
+            },
+        )
     }
 
-    Ok(DecodedAudio {
-        samples,
-        sample_rate,
-        channels,
-    })
+    /// The next chunk if one is ready, without waiting.
+    pub fn try_next(&self) -> StreamChunk {
+        match self.chunks.try_recv() {
+            Ok(chunk) => chunk.into(),
+            Err(TryRecvError::Empty) => StreamChunk::Pending,
+            Err(TryRecvError::Disconnected) => StreamChunk::Ended,
+        }
+    }
+
+    /// The next chunk, waiting for the decoder if needed.
+    pub fn next(&self) -> StreamChunk {
+        match self.chunks.recv() {
+            Ok(chunk) => chunk.into(),
+            Err(_) => StreamChunk::Ended,
+        }
+    }
+}
+
+impl From<Result<Vec<f32>>> for StreamChunk {
+    fn from(chunk: Result<Vec<f32>>) -> Self {
+        match chunk {
+            Ok(samples) => StreamChunk::Samples(samples),
+            Err(e) => StreamChunk::Failed(e),
+        }
+    }
+}
+
+/// Open `path` and start decoding it in the background. Errors that show up
+/// before the first packet (missing file, unknown format or codec) come back
+/// here; later ones arrive through the stream.
+pub fn stream_file(path: &Path) -> Result<AudioStream> {
+    let mut decoding = Decoding::open(path)?;
+    let channels = decoding.channels.max(1) as usize;
+    let total_samples = decoding.frames.map(|frames| frames as usize * channels);
+    let (tx, stream) = AudioStream::channel(
+        decoding.sample_rate,
+        decoding.channels,
+        total_samples,
+        STREAM_BUFFER_CHUNKS,
+    );
+    let chunk_len = (decoding.sample_rate * STREAM_CHUNK_MS / 1000).max(1) as usize * channels;
+
+    std::thread::spawn(move || {
+        let mut chunk = Vec::with_capacity(chunk_len);
+        loop {
+            match decoding.next_samples() {
+                Ok(Some(packet)) => {
+                    chunk.extend_from_slice(&packet);
+                    if chunk.len() < chunk_len {
+                        continue;
+                    }
+                }
+                Ok(None) => {
+                    if !chunk.is_empty() {
+                        let _ = tx.send(Ok(chunk));
+                    }
+                    return;
+                }
+                Err(e) => {
+                    if !chunk.is_empty() {
+                        let _ = tx.send(Ok(std::mem::take(&mut chunk)));
+                    }
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+            }
+            // A dropped receiver means the playback was stopped
+            if tx
+                .send(Ok(std::mem::replace(
+                    &mut chunk,
+                    Vec::with_capacity(chunk_len),
+                )))
+                .is_err()
+            {
+                return;
+            }
+        }
+    });
+
+    Ok(stream)
 }
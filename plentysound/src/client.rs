@@ -1,24 +1,28 @@
 use crate::filebrowser::FileBrowser;
 use crate::protocol::{
-    socket_path, ClientCommand, DaemonEvent, DaemonState, SinkInfo, SongInfo,
-    recv_message, send_message,
+    check_peer, recv_message, send_message, socket_candidates, ClientCommand, DaemonEvent,
+    DaemonState, EqBand, FrameReader, LogLevel, NoiseKind, SinkInfo, SongInfo, SortKey,
+    COMFORT_NOISE_MAX, EQ_GAIN_MAX, FADE_SECS_MAX, SONG_PAGE_LIMIT, SONG_VOLUME_MAX, VOLUME_MAX,
 };
+use crate::textinput::TextInput;
+use crate::ui::Action;
 use anyhow::{Context, Result};
 use crossterm::event::{
-    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, MouseButton, MouseEvent,
-    MouseEventKind,
+    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton,
+    MouseEvent, MouseEventKind,
 };
 use crossterm::{execute, terminal};
-use ratatui::layout::Rect;
+use ratatui::layout::{Position, Rect};
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
 use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
 #[cfg(feature = "transcriber")]
-use crate::textinput::TextInput;
-#[cfg(feature = "transcriber")]
-use crate::protocol::WordDetectorStatus;
+use crate::protocol::{SongSelection, WordDetectorStatus};
+
+const LOG: crate::log::Logger = crate::log::log_scope("client");
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Panel {
@@ -29,10 +33,90 @@ pub enum Panel {
     #[cfg(feature = "transcriber")]
     WordDetectorButton,
     Songs,
+    /// Only while toggled on with `Q`.
+    Queue,
     #[cfg(feature = "transcriber")]
     WordBindings,
 }
 
+/// Tab order of the panels. Both builds walk the same list; the word
+/// detector's panels are only in it with the transcriber.
+pub const PANEL_ORDER: &[Panel] = &[
+    Panel::Sinks,
+    Panel::Volume,
+    Panel::AudioFx,
+    Panel::AddButton,
+    #[cfg(feature = "transcriber")]
+    Panel::WordDetectorButton,
+    Panel::Songs,
+    Panel::Queue,
+    #[cfg(feature = "transcriber")]
+    Panel::WordBindings,
+];
+
+/// Sections of the devices panel in display order, keyed by `SinkInfo::kind`.
+const SINK_GROUPS: [(&str, &str); 3] = [
+    ("Output", "Outputs"),
+    ("Input", "Inputs / App streams"),
+    ("Monitor", "Monitors"),
+];
+
+/// Audio FX rows are the noise slider, the three EQ bands and the fade,
+/// then this toggle.
+pub const FX_LIMITER_ROW: usize = 5;
+
+/// One row of the grouped devices panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkRow {
+    Header(&'static str),
+    /// Index into `DaemonState::sinks`.
+    Sink(usize),
+}
+
+/// How `name` matches a lowercase `query`: 0 if it contains it, 1 if it
+/// has its letters in order, `None` if neither.
+fn filter_rank(name: &str, query: &str) -> Option<u8> {
+    let name = name.to_lowercase();
+    if name.contains(query) {
+        return Some(0);
+    }
+    let mut letters = name.chars();
+    query.chars().all(|q| letters.any(|c| c == q)).then_some(1)
+}
+
+/// Position `delta` rows away from `pos` in a list of `len`, stopping at
+/// either end.
+fn clamped_step(pos: usize, delta: isize, len: usize) -> usize {
+    pos.saturating_add_signed(delta).min(len.saturating_sub(1))
+}
+
+/// Rows a page key moves in a bordered list drawn in `area`.
+fn page_rows(area: Rect) -> isize {
+    area.height.saturating_sub(2).max(1) as isize
+}
+
+/// Group sinks under a header per kind, skipping empty groups. Kinds
+/// without a group of their own go last, under "Other". Microphones are left
+/// out: songs can't play into them, and the detector's source picker lists
+/// them instead.
+pub fn sink_rows(sinks: &[SinkInfo]) -> Vec<SinkRow> {
+    let mut rows = Vec::new();
+    let mut push_group = |title: &'static str, matches: &dyn Fn(&SinkInfo) -> bool| {
+        let members: Vec<usize> = (0..sinks.len()).filter(|&i| matches(&sinks[i])).collect();
+        if !members.is_empty() {
+            rows.push(SinkRow::Header(title));
+            rows.extend(members.into_iter().map(SinkRow::Sink));
+        }
+    };
+    for (kind, title) in SINK_GROUPS {
+        push_group(title, &|s| s.kind == kind);
+    }
+    push_group("Other", &|s| {
+        s.kind != "Source" && SINK_GROUPS.iter().all(|(kind, _)| s.kind != *kind)
+    });
+    rows
+}
+
 #[derive(Default, Clone, Copy)]
 pub struct AppLayout {
     pub sinks_area: Rect,
@@ -42,8 +126,52 @@ pub struct AppLayout {
     #[cfg(feature = "transcriber")]
     pub word_detector_button_area: Rect,
     pub songs_area: Rect,
+    /// Empty while the queue is hidden.
+    pub queue_area: Rect,
     #[cfg(feature = "transcriber")]
     pub word_bindings_area: Rect,
+    /// Where the file browser was last drawn; it sets the page size there.
+    pub file_browser_area: Rect,
+}
+
+/// Output level of the active playback as last reported by the daemon,
+/// decayed frame by frame once reports stop.
+#[derive(Default)]
+pub struct OutputMeter {
+    pub rms: f32,
+    pub peak_hold: f32,
+    pub limiting: bool,
+    hold_frames: u32,
+    fresh: bool,
+}
+
+impl OutputMeter {
+    /// Frames (~50 ms each) the peak marker stays put before falling.
+    const PEAK_HOLD_FRAMES: u32 = 20;
+
+    fn update(&mut self, peak: f32, rms: f32, limiting: bool) {
+        self.rms = rms;
+        self.limiting = limiting;
+        if peak >= self.peak_hold {
+            self.peak_hold = peak;
+            self.hold_frames = Self::PEAK_HOLD_FRAMES;
+        }
+        self.fresh = true;
+    }
+
+    /// Called once per drawn frame.
+    fn decay(&mut self) {
+        if std::mem::take(&mut self.fresh) {
+            return;
+        }
+        self.rms *= 0.8;
+        self.limiting = false;
+        if self.hold_frames > 0 {
+            self.hold_frames -= 1;
+        } else {
+            self.peak_hold *= 0.85;
+        }
+    }
 }
 
 #[cfg(feature = "transcriber")]
@@ -51,14 +179,87 @@ pub enum TranscriberOverlay {
     SelectSource { selected: usize },
     SelectOutput { selected: usize },
     EnterWord { input: TextInput },
-    PickSong { word: String, selected: usize },
+    /// Space marks several songs for one binding and Tab switches how it
+    /// picks among them; without marks Enter binds the highlighted song.
+    PickSong {
+        word: String,
+        selected: usize,
+        chosen: Vec<usize>,
+        selection: SongSelection,
+    },
+}
+
+/// Steps of the first-run wizard, in order. Esc skips the current one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupStep {
+    PickSink {
+        selected: usize,
+    },
+    /// Shown through the file browser.
+    AddSongs,
+    #[cfg(feature = "transcriber")]
+    EnableDetector,
+    Finish,
+}
+
+impl SetupStep {
+    const COUNT: usize = if cfg!(feature = "transcriber") { 4 } else { 3 };
+
+    /// 1-based position and the total, for the overlay title.
+    pub fn progress(&self) -> (usize, usize) {
+        let n = match self {
+            SetupStep::PickSink { .. } => 1,
+            SetupStep::AddSongs => 2,
+            #[cfg(feature = "transcriber")]
+            SetupStep::EnableDetector => 3,
+            SetupStep::Finish => Self::COUNT,
+        };
+        (n, Self::COUNT)
+    }
+}
+
+/// Text the songs panel matches names against: songs containing it come
+/// first, then songs with its letters in order.
+pub struct SongFilter {
+    pub input: TextInput,
+    /// Keys go to the filter rather than the panel, until Enter or Esc.
+    pub editing: bool,
+}
+
+/// What `d` is about to remove, shown in a dialog until confirmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PendingDelete {
+    /// Path of the song, looked up again on confirm since the library may
+    /// change while the dialog is open.
+    Song(String),
+    /// Index into `DaemonState::word_mappings`.
+    #[cfg(feature = "transcriber")]
+    WordBinding(usize),
 }
 
 pub struct ClientApp {
     pub state: DaemonState,
     pub focus: Panel,
     pub selected_fx: usize,
+    /// Songs panel shows only songs with this tag; cycled with `F`.
+    pub tag_filter: Option<String>,
+    /// Typed after `/` in the songs panel; narrows it and the song picker.
+    pub song_filter: Option<SongFilter>,
+    /// Waiting for `y` or `n` on a delete.
+    pub pending_delete: Option<PendingDelete>,
     pub file_browser: Option<FileBrowser>,
+    pub setup: Option<SetupStep>,
+    /// First row the devices panel shows; kept by the draw code so a device
+    /// refresh doesn't scroll the list.
+    pub sink_scroll: usize,
+    /// First row the songs panel shows, kept like `sink_scroll`.
+    pub song_scroll: usize,
+    /// The queue section under the songs panel is open.
+    pub show_queue: bool,
+    /// Position in `DaemonState::queue` the queue section highlights.
+    pub selected_queue: usize,
+    /// First queue entry the queue section shows, kept like `sink_scroll`.
+    pub queue_scroll: usize,
     #[cfg(feature = "transcriber")]
     pub transcriber_overlay: Option<TranscriberOverlay>,
     #[cfg(feature = "transcriber")]
@@ -69,28 +270,89 @@ pub struct ClientApp {
     pub detector_output_description: Option<String>,
     #[cfg(feature = "transcriber")]
     pub selected_word_binding: usize,
+    /// First binding the Word Bindings panel shows; kept by the draw code so
+    /// the selection stays in view.
+    #[cfg(feature = "transcriber")]
+    pub word_binding_scroll: usize,
+    /// Global `word_mappings` indices bound to the selected song, rebuilt by
+    /// `refresh_bindings` whenever either of those changes.
+    #[cfg(feature = "transcriber")]
+    binding_indices: Vec<usize>,
     pub layout: AppLayout,
+    /// Clear the whole screen before the next draw.
+    pub needs_clear: bool,
     pub should_quit: bool,
     pub status_message: Option<String>,
-    stream: UnixStream,
+    pub meter: OutputMeter,
+    /// Position and length of the playing song, in seconds.
+    pub progress: Option<(f64, f64)>,
+    /// Envelope fetched with `v` and the song it belongs to; drawn while
+    /// that song is highlighted.
+    pub waveform: Option<(String, Vec<u8>)>,
+    /// Song whose envelope was asked for last, so ones other clients asked
+    /// for are ignored.
+    waveform_requested: Option<String>,
+    /// `None` while the daemon is gone and `reconnect` is trying again.
+    stream: Option<FrameReader<UnixStream>>,
+    reconnect: Option<Reconnect>,
 }
 
-impl ClientApp {
-    fn new(mut stream: UnixStream) -> Result<Self> {
-        let event: DaemonEvent = recv_message(&mut stream)
-            .context("Failed to receive initial state from daemon")?;
-        let state = match event {
-            DaemonEvent::State(s) => s,
-            _ => anyhow::bail!("Expected State event from daemon, got {:?}", event),
-        };
+/// How often a disconnected TUI tries to reach the daemon again.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reconnection after the daemon went away: the attempt running in the
+/// background, if any, and when to start the next one.
+struct Reconnect {
+    next_attempt: std::time::Instant,
+    pending: Option<std::sync::mpsc::Receiver<Result<Connection>>>,
+}
+
+/// A greeted daemon connection and the state it started with.
+type Connection = (FrameReader<UnixStream>, DaemonState);
+
+/// The TUI's side of connecting: take the initial `State` and introduce
+/// ourselves. The socket stays blocking for sends; polling never waits.
+fn greet_daemon(stream: UnixStream) -> Result<Connection> {
+    let mut stream = FrameReader::new(stream);
+    let event: DaemonEvent = stream
+        .recv()
+        .context("Failed to receive initial state from daemon")?;
+    let state = match event {
+        DaemonEvent::State(s) => *s,
+        _ => anyhow::bail!("Expected State event from daemon, got {:?}", event),
+    };
+
+    send_message(
+        stream.get_mut(),
+        &ClientCommand::Identify {
+            name: "tui".to_string(),
+            kind: "tui".to_string(),
+        },
+    )?;
+    Ok((stream, state))
+}
 
-        stream.set_nonblocking(true)?;
+impl ClientApp {
+    pub fn new(stream: UnixStream) -> Result<Self> {
+        let (stream, state) = greet_daemon(stream)?;
 
-        Ok(ClientApp {
+        let setup = state
+            .first_run
+            .then_some(SetupStep::PickSink { selected: 0 });
+        let mut app = ClientApp {
             state,
             focus: Panel::Sinks,
             selected_fx: 0,
+            tag_filter: None,
+            song_filter: None,
+            pending_delete: None,
             file_browser: None,
+            setup,
+            sink_scroll: 0,
+            song_scroll: 0,
+            show_queue: false,
+            selected_queue: 0,
+            queue_scroll: 0,
             #[cfg(feature = "transcriber")]
             transcriber_overlay: None,
             #[cfg(feature = "transcriber")]
@@ -101,64 +363,278 @@ impl ClientApp {
             detector_output_description: None,
             #[cfg(feature = "transcriber")]
             selected_word_binding: 0,
+            #[cfg(feature = "transcriber")]
+            word_binding_scroll: 0,
+            #[cfg(feature = "transcriber")]
+            binding_indices: Vec::new(),
             layout: AppLayout::default(),
+            needs_clear: false,
             should_quit: false,
             status_message: None,
-            stream,
-        })
+            meter: OutputMeter::default(),
+            progress: None,
+            waveform: None,
+            waveform_requested: None,
+            stream: Some(stream),
+            reconnect: None,
+        };
+        app.request_missing_songs();
+        #[cfg(feature = "transcriber")]
+        app.refresh_bindings();
+        Ok(app)
+    }
+
+    /// Ask for the next page of songs if the snapshot didn't carry them all.
+    fn request_missing_songs(&mut self) {
+        let offset = self.state.songs.len();
+        if offset < self.state.songs_total {
+            self.send_command(ClientCommand::GetSongs {
+                offset,
+                limit: SONG_PAGE_LIMIT,
+            });
+        }
     }
 
     fn send_command(&mut self, cmd: ClientCommand) {
-        self.stream.set_nonblocking(false).ok();
-        if let Err(e) = send_message(&mut self.stream, &cmd) {
-            crate::log::log_error(&format!("Failed to send command: {e}"));
+        let Some(stream) = self.stream.as_mut() else {
+            self.status_message = Some("Not connected to the daemon, reconnecting...".to_string());
+            return;
+        };
+        if let Err(e) = send_message(stream.get_mut(), &cmd) {
+            LOG.error(&format!("Failed to send command: {e}"));
+        }
+    }
+
+    pub fn disconnected(&self) -> bool {
+        self.stream.is_none()
+    }
+
+    /// The daemon went away: drop what only a live daemon can report and
+    /// start trying to reconnect.
+    fn disconnect(&mut self, why: &str) {
+        LOG.info(&format!("Disconnected from daemon: {why}"));
+        self.stream = None;
+        self.reconnect = Some(Reconnect {
+            next_attempt: std::time::Instant::now(),
+            pending: None,
+        });
+        self.state.now_playing = None;
+        self.state.paused = false;
+        self.progress = None;
+        self.meter = OutputMeter::default();
+        self.status_message = Some(format!("{why}, reconnecting..."));
+    }
+
+    /// While disconnected, try `connect` once every `RECONNECT_INTERVAL`.
+    /// Each attempt runs on its own thread, so a slow daemon never holds up
+    /// drawing or input; a successful one resumes with the fresh state.
+    fn poll_reconnect<F>(&mut self, connect: F)
+    where
+        F: FnOnce() -> Result<UnixStream> + Send + 'static,
+    {
+        let Some(reconnect) = self.reconnect.as_mut() else {
+            return;
+        };
+        match &reconnect.pending {
+            Some(rx) => match rx.try_recv() {
+                Ok(Ok((stream, state))) => {
+                    self.reconnect = None;
+                    self.stream = Some(stream);
+                    self.state = state;
+                    self.request_missing_songs();
+                    #[cfg(feature = "transcriber")]
+                    self.refresh_bindings();
+                    self.needs_clear = true;
+                    self.status_message = Some("Reconnected to the daemon".to_string());
+                }
+                Ok(Err(_)) | Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    reconnect.pending = None;
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => {}
+            },
+            None if std::time::Instant::now() >= reconnect.next_attempt => {
+                reconnect.next_attempt = std::time::Instant::now() + RECONNECT_INTERVAL;
+                let (tx, rx) = std::sync::mpsc::channel();
+                std::thread::spawn(move || {
+                    let attempt = connect().and_then(|stream| {
+                        stream.set_read_timeout(Some(RECONNECT_INTERVAL))?;
+                        greet_daemon(stream)
+                    });
+                    let _ = tx.send(attempt);
+                });
+                reconnect.pending = Some(rx);
+            }
+            None => {}
         }
-        self.stream.set_nonblocking(true).ok();
     }
 
     fn poll_daemon_events(&mut self) {
         loop {
-            match recv_message::<DaemonEvent>(&mut self.stream) {
+            let Some(stream) = self.stream.as_mut() else {
+                return;
+            };
+            match stream.try_recv::<DaemonEvent>() {
                 Ok(event) => match event {
-                    DaemonEvent::State(s) => {
+                    DaemonEvent::State(mut s) => {
                         #[cfg(feature = "transcriber")]
                         {
-                            crate::log::log_info(&format!(
+                            LOG.info(&format!(
                                 "Client received State: detector_status={:?}",
                                 s.word_detector_status
                             ));
-                            if let WordDetectorStatus::DownloadFailed(ref msg) = s.word_detector_status {
-                                self.status_message = Some(format!("Model download failed: {}", msg));
+                            if let WordDetectorStatus::DownloadFailed(ref msg) =
+                                s.word_detector_status
+                            {
+                                self.status_message =
+                                    Some(format!("Model download failed: {}", msg));
                             }
                         }
-                        self.state = s;
+                        // Keep pages fetched earlier if the library hasn't changed since
+                        if s.songs_revision == self.state.songs_revision
+                            && self.state.songs.len() > s.songs.len()
+                        {
+                            let tail = self.state.songs.split_off(s.songs.len());
+                            s.songs.extend(tail);
+                        }
+                        self.state = *s;
+                        self.request_missing_songs();
+                        self.clamp_queue_selection();
+                        #[cfg(feature = "transcriber")]
+                        self.refresh_bindings();
+                    }
+                    DaemonEvent::Songs {
+                        revision,
+                        offset,
+                        total,
+                        songs,
+                    } => {
+                        if revision == self.state.songs_revision
+                            && offset == self.state.songs.len()
+                            && !songs.is_empty()
+                        {
+                            self.state.songs.extend(songs);
+                            self.state.songs_total = total;
+                            self.request_missing_songs();
+                        }
                     }
                     DaemonEvent::SinksUpdated(sinks) => {
+                        // Same rule as the daemon: stay on the same device by id
+                        let selected_id =
+                            self.state.sinks.get(self.state.selected_sink).map(|s| s.id);
                         self.state.sinks = sinks;
-                        if self.state.selected_sink >= self.state.sinks.len()
+                        let followed = selected_id
+                            .and_then(|id| self.state.sinks.iter().position(|s| s.id == id));
+                        if let Some(idx) = followed {
+                            self.state.selected_sink = idx;
+                        } else if self.state.selected_sink >= self.state.sinks.len()
                             && !self.state.sinks.is_empty()
                         {
                             self.state.selected_sink = self.state.sinks.len() - 1;
                         }
                     }
+                    DaemonEvent::VolumeChanged(volume) => {
+                        self.state.volume = volume;
+                    }
+                    DaemonEvent::FxChanged(fx) => {
+                        self.state.comfort_noise = fx.comfort_noise;
+                        self.state.noise_kind = fx.noise_kind;
+                        self.state.eq = fx.eq;
+                        self.state.fade_secs = fx.fade_secs;
+                        self.state.limiter = fx.limiter;
+                    }
+                    DaemonEvent::SongsUpdated { revision, songs } => {
+                        self.state.songs = songs;
+                        self.state.songs_revision = revision;
+                        self.request_missing_songs();
+                    }
+                    #[cfg(feature = "transcriber")]
+                    DaemonEvent::MappingsUpdated(mappings) => {
+                        self.state.word_mappings = mappings;
+                        self.refresh_bindings();
+                    }
                     DaemonEvent::PlaybackFinished => {
                         self.state.now_playing = None;
+                        self.state.paused = false;
+                        self.progress = None;
+                    }
+                    // A failed song was already explained by the `Error`
+                    // sent just before
+                    DaemonEvent::SongEnded { song, .. } => {
+                        // Progress belongs to whatever is playing now, which
+                        // may already be the next song
+                        if self.state.now_playing.as_deref() == Some(song.as_str()) {
+                            self.progress = None;
+                        }
                     }
                     DaemonEvent::NowPlaying(np) => {
                         self.state.now_playing = np;
+                        self.state.paused = false;
+                        self.progress = None;
+                    }
+                    DaemonEvent::QueueUpdated(queue) => {
+                        self.state.queue = queue;
+                        self.clamp_queue_selection();
+                    }
+                    DaemonEvent::Error { message, .. } => {
+                        self.status_message = Some(message);
                     }
+                    DaemonEvent::Paused(paused) => {
+                        self.state.paused = paused;
+                    }
+                    DaemonEvent::OutputLevel {
+                        peak,
+                        rms,
+                        limiting,
+                    } => {
+                        self.meter.update(peak, rms, limiting);
+                    }
+                    DaemonEvent::PlaybackProgress {
+                        position_secs,
+                        total_secs,
+                    } => {
+                        self.progress = Some((position_secs, total_secs));
+                    }
+                    DaemonEvent::CommandResult(result) => {
+                        self.status_message = Some(result.message().to_string());
+                    }
+                    DaemonEvent::Waveform { path, peaks } => {
+                        if self.waveform_requested.as_ref() == Some(&path) {
+                            self.waveform_requested = None;
+                            self.waveform = Some((path, peaks));
+                        }
+                    }
+                    // The TUI sends no requests; nothing to match these to
+                    DaemonEvent::Stats(_) | DaemonEvent::Reply { .. } => {}
                     DaemonEvent::Shutdown => {
-                        self.should_quit = true;
+                        self.disconnect("The daemon stopped");
                         return;
                     }
                     #[cfg(feature = "transcriber")]
-                    DaemonEvent::WordDetected(word) => {
-                        self.status_message = Some(format!("Word detected: \"{}\"", word));
+                    DaemonEvent::WordDetected {
+                        word,
+                        simulated,
+                        song,
+                    } => {
+                        let kind = if simulated { "simulated" } else { "detected" };
+                        let mut message = format!("Word {}: \"{}\"", kind, word);
+                        if let Some(song) = song {
+                            message.push_str(&format!(" -> {song}"));
+                        }
+                        self.status_message = Some(message);
+                    }
+                    #[cfg(feature = "transcriber")]
+                    DaemonEvent::TriggerLatency { word, total_ms, .. } => {
+                        let song = self.state.now_playing.clone().unwrap_or(word);
+                        self.status_message = Some(format!("Triggered {song} in {total_ms} ms"));
                     }
                 },
                 Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                // Oversized or malformed frame: it was skipped, the stream is still aligned
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    LOG.error(&format!("Dropped daemon message: {e}"));
+                }
                 Err(_) => {
-                    self.should_quit = true;
+                    self.disconnect("Lost the connection to the daemon");
                     break;
                 }
             }
@@ -168,49 +644,200 @@ impl ClientApp {
     pub fn handle_event(&mut self, ev: Event) {
         match ev {
             Event::Key(key) => {
+                if self.setup.is_some() {
+                    self.handle_setup_key(key);
+                    return;
+                }
                 #[cfg(feature = "transcriber")]
                 if self.transcriber_overlay.is_some() {
                     self.handle_overlay_key(key);
                     return;
                 }
-                if self.file_browser.is_some() {
+                if self.pending_delete.is_some() {
+                    self.handle_confirm_delete_key(key);
+                    return;
+                }
+                if self
+                    .song_filter
+                    .as_ref()
+                    .is_some_and(|filter| filter.editing)
+                {
+                    self.handle_song_filter_key(key);
+                } else if self.file_browser.is_some() {
                     self.handle_filebrowser_key(key);
                 } else {
                     self.handle_main_key(key);
                 }
             }
             Event::Mouse(mouse) => {
+                if self.setup.is_some() {
+                    return;
+                }
                 #[cfg(feature = "transcriber")]
                 if self.transcriber_overlay.is_some() {
                     return;
                 }
-                if self.file_browser.is_none() {
+                if self.pending_delete.is_some() {
+                    return;
+                }
+                if let Some(fb) = &mut self.file_browser {
+                    // Only the wheel reaches the browser, and only over it
+                    let pos = Position::new(mouse.column, mouse.row);
+                    if self.layout.file_browser_area.contains(pos) {
+                        match mouse.kind {
+                            MouseEventKind::ScrollUp => fb.move_by(-1),
+                            MouseEventKind::ScrollDown => fb.move_by(1),
+                            _ => {}
+                        }
+                    }
+                } else {
                     self.handle_mouse(mouse);
                 }
             }
+            // Some terminals leave stale cells behind after a resize
+            Event::Resize(..) => self.needs_clear = true,
             _ => {}
         }
     }
 
     fn handle_main_key(&mut self, key: KeyEvent) {
         self.status_message = None;
+        let shifted = key.modifiers.contains(KeyModifiers::SHIFT);
         match key.code {
             KeyCode::Char('q') => self.should_quit = true,
             KeyCode::Tab => self.cycle_focus(),
             KeyCode::BackTab => self.cycle_focus_back(),
             KeyCode::Left => self.handle_left(),
             KeyCode::Right => self.handle_right(),
+            KeyCode::Up if shifted && self.focus == Panel::Songs => self.move_song(-1),
+            KeyCode::Down if shifted && self.focus == Panel::Songs => self.move_song(1),
+            KeyCode::Up if shifted && self.focus == Panel::Queue => self.move_queued(-1),
+            KeyCode::Down if shifted && self.focus == Panel::Queue => self.move_queued(1),
             KeyCode::Up => self.move_up(),
             KeyCode::Down => self.move_down(),
+            KeyCode::PageUp => self.move_page(-1),
+            KeyCode::PageDown => self.move_page(1),
+            KeyCode::Home => self.step_list(isize::MIN),
+            KeyCode::End => self.step_list(isize::MAX),
             KeyCode::Enter => self.activate(),
             KeyCode::Char('d') | KeyCode::Delete => self.delete_selected(),
             KeyCode::Char('r') => {
                 self.send_command(ClientCommand::RefreshSinks);
             }
+            #[cfg(feature = "transcriber")]
+            KeyCode::Char(' ') if self.focus == Panel::WordBindings => {
+                self.toggle_selected_binding()
+            }
+            #[cfg(feature = "transcriber")]
+            KeyCode::Char('x') if self.focus == Panel::WordBindings => {
+                self.send_command(ClientCommand::ResetWordStats);
+            }
+            #[cfg(feature = "transcriber")]
+            KeyCode::Char('p') if self.focus == Panel::WordBindings => self.cycle_power_mode(),
+            #[cfg(feature = "transcriber")]
+            KeyCode::Char('o') if self.focus == Panel::WordBindings => {
+                self.toggle_binding_source_only();
+            }
+            KeyCode::Char('m') if self.focus == Panel::Sinks => self.toggle_monitor_sink(),
+            KeyCode::Char('c') if self.focus == Panel::Songs => self.cycle_song_tag(),
+            KeyCode::Char('o') if self.focus == Panel::Songs => self.cycle_song_sort(),
+            KeyCode::Char('v') if self.focus == Panel::Songs => self.toggle_waveform(),
+            KeyCode::Char('+') | KeyCode::Char('=') if self.focus == Panel::Songs => {
+                self.step_song_volume(0.1)
+            }
+            KeyCode::Char('-') if self.focus == Panel::Songs => self.step_song_volume(-0.1),
+            KeyCode::Char(' ') if self.focus == Panel::Songs => self.toggle_pause(),
+            KeyCode::Char('F') => self.cycle_tag_filter(),
+            KeyCode::Char('/') if self.focus == Panel::Songs => {
+                let filter = self.song_filter.get_or_insert_with(|| SongFilter {
+                    input: TextInput::new(),
+                    editing: true,
+                });
+                filter.editing = true;
+            }
+            KeyCode::Esc if self.focus == Panel::Songs => self.song_filter = None,
+            KeyCode::Char('S') if self.state.now_playing.is_some() => {
+                self.send_command(ClientCommand::StopPlayback { clear_queue: false });
+            }
+            KeyCode::Char('e') if self.focus == Panel::Songs && !self.state.songs.is_empty() => {
+                self.send_command(ClientCommand::Enqueue(self.state.selected_song));
+            }
+            KeyCode::Char('E') => self.send_command(ClientCommand::ClearQueue),
+            KeyCode::Char('C') if self.focus == Panel::Queue => {
+                self.send_command(ClientCommand::ClearQueue);
+            }
+            KeyCode::Char('Q') => self.toggle_queue(),
+            KeyCode::Char('l') => {
+                self.state.loop_enabled = !self.state.loop_enabled;
+                self.send_command(ClientCommand::SetLoop(self.state.loop_enabled));
+            }
+            KeyCode::Char('n') => {
+                self.state.continuous_noise = !self.state.continuous_noise;
+                self.send_command(ClientCommand::SetContinuousNoise(
+                    self.state.continuous_noise,
+                ));
+            }
+            KeyCode::Char('s') => {
+                self.state.sidechain_enabled = !self.state.sidechain_enabled;
+                self.send_command(ClientCommand::SetSidechain(self.state.sidechain_enabled));
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_song_filter_key(&mut self, key: KeyEvent) {
+        let Some(filter) = &mut self.song_filter else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.song_filter = None,
+            KeyCode::Enter => {
+                filter.editing = false;
+                if self.song_order().contains(&self.state.selected_song) {
+                    self.send_command(ClientCommand::Play);
+                } else {
+                    self.status_message = Some("No song matches the filter".to_string());
+                }
+            }
+            KeyCode::Up => self.step_song(-1),
+            KeyCode::Down => self.step_song(1),
+            KeyCode::Backspace | KeyCode::Char(_) => {
+                match key.code {
+                    KeyCode::Char(c) => filter.input.push_char(c),
+                    _ => filter.input.backspace(),
+                }
+                // Like other pickers, the best match is highlighted as you type
+                if let Some(&best) = self.song_order().first() {
+                    if best != self.state.selected_song {
+                        self.select_song(best);
+                    }
+                }
+            }
             _ => {}
         }
     }
 
+    /// Send the audio files in `dir` as one batch; false when there are
+    /// none.
+    fn add_folder(&mut self, dir: &std::path::Path, depth: usize) -> bool {
+        let files = crate::filebrowser::audio_files_in(dir, depth);
+        if files.is_empty() {
+            self.status_message = Some(format!("No audio files in {}", dir.display()));
+            return false;
+        }
+        self.status_message = Some(format!(
+            "Adding {} songs from {}",
+            files.len(),
+            dir.display()
+        ));
+        let paths = files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        self.send_command(ClientCommand::AddSongs(paths));
+        true
+    }
+
     fn handle_filebrowser_key(&mut self, key: KeyEvent) {
         match key.code {
             KeyCode::Esc => {
@@ -226,6 +853,17 @@ impl ClientApp {
                     fb.move_down();
                 }
             }
+            KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End => {
+                let page = page_rows(self.layout.file_browser_area);
+                if let Some(fb) = &mut self.file_browser {
+                    fb.move_by(match key.code {
+                        KeyCode::PageUp => -page,
+                        KeyCode::PageDown => page,
+                        KeyCode::Home => isize::MIN,
+                        _ => isize::MAX,
+                    });
+                }
+            }
             KeyCode::Enter => {
                 let selected_path = self.file_browser.as_mut().and_then(|fb| fb.select());
                 if let Some(path) = selected_path {
@@ -238,10 +876,114 @@ impl ClientApp {
                     fb.navigate_parent();
                 }
             }
+            // `A` also takes the files one folder further down
+            KeyCode::Char(c @ ('a' | 'A')) => {
+                let Some(fb) = self.file_browser.take() else {
+                    return;
+                };
+                let depth = if c == 'A' { 1 } else { 0 };
+                if !self.add_folder(fb.folder_to_add(), depth) {
+                    self.file_browser = Some(fb);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_setup_key(&mut self, key: KeyEvent) {
+        let Some(step) = self.setup else {
+            return;
+        };
+        match (step, key.code) {
+            (SetupStep::Finish, KeyCode::Esc | KeyCode::Enter) => self.finish_setup(),
+            (SetupStep::AddSongs, KeyCode::Esc) => {
+                self.file_browser = None;
+                self.advance_setup();
+            }
+            (_, KeyCode::Esc) => self.advance_setup(),
+            (SetupStep::PickSink { selected }, KeyCode::Up) => {
+                self.setup = Some(SetupStep::PickSink {
+                    selected: selected.saturating_sub(1),
+                });
+            }
+            (SetupStep::PickSink { selected }, KeyCode::Down) => {
+                let last = self.output_sinks().len().saturating_sub(1);
+                self.setup = Some(SetupStep::PickSink {
+                    selected: (selected + 1).min(last),
+                });
+            }
+            (SetupStep::PickSink { selected }, KeyCode::Enter) => {
+                if let Some(&idx) = self.output_sinks().get(selected) {
+                    self.state.selected_sink = idx;
+                    self.send_command(ClientCommand::SelectSink(idx));
+                }
+                self.advance_setup();
+            }
+            (SetupStep::AddSongs, KeyCode::Char('a')) => {
+                let Some(fb) = self.file_browser.take() else {
+                    return;
+                };
+                self.add_folder(&fb.current_dir, 0);
+                self.advance_setup();
+            }
+            (SetupStep::AddSongs, _) => {
+                self.handle_filebrowser_key(key);
+                // Picking a single file closes the browser
+                if self.file_browser.is_none() {
+                    self.advance_setup();
+                }
+            }
+            #[cfg(feature = "transcriber")]
+            (SetupStep::EnableDetector, KeyCode::Enter) => {
+                if self.state.vosk_missing.is_none()
+                    && matches!(
+                        self.state.word_detector_status,
+                        WordDetectorStatus::Unavailable | WordDetectorStatus::DownloadFailed(_)
+                    )
+                {
+                    self.send_command(ClientCommand::StartModelDownload);
+                    self.status_message = Some("Starting model download...".to_string());
+                }
+                self.advance_setup();
+            }
             _ => {}
         }
     }
 
+    fn advance_setup(&mut self) {
+        let next = match self.setup {
+            Some(SetupStep::PickSink { .. }) => {
+                self.file_browser = Some(FileBrowser::new());
+                SetupStep::AddSongs
+            }
+            #[cfg(feature = "transcriber")]
+            Some(SetupStep::AddSongs) => SetupStep::EnableDetector,
+            #[cfg(not(feature = "transcriber"))]
+            Some(SetupStep::AddSongs) => SetupStep::Finish,
+            #[cfg(feature = "transcriber")]
+            Some(SetupStep::EnableDetector) => SetupStep::Finish,
+            Some(SetupStep::Finish) | None => return self.finish_setup(),
+        };
+        self.setup = Some(next);
+    }
+
+    fn finish_setup(&mut self) {
+        self.setup = None;
+        self.file_browser = None;
+        self.send_command(ClientCommand::FinishSetup);
+    }
+
+    /// Indices of the playback outputs, as offered by the setup wizard.
+    pub fn output_sinks(&self) -> Vec<usize> {
+        self.state
+            .sinks
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.kind == "Output")
+            .map(|(i, _)| i)
+            .collect()
+    }
+
     #[cfg(feature = "transcriber")]
     fn handle_overlay_key(&mut self, key: KeyEvent) {
         match key.code {
@@ -252,12 +994,8 @@ impl ClientApp {
                 let overlay = self.transcriber_overlay.take();
                 match overlay {
                     Some(TranscriberOverlay::SelectSource { mut selected }) => {
-                        let input_sinks: Vec<_> = self
-                            .state
-                            .sinks
-                            .iter()
-                            .filter(|s| s.kind == "Input")
-                            .collect();
+                        let input_sinks: Vec<_> =
+                            self.state.sinks.iter().filter(|s| s.is_capture()).collect();
                         match key.code {
                             KeyCode::Up => {
                                 if selected > 0 {
@@ -276,12 +1014,14 @@ impl ClientApp {
                                     self.detector_source_node = Some(sink.id);
                                     self.detector_source_description = Some(sink.description.clone());
                                     self.transcriber_overlay =
-                                        Some(TranscriberOverlay::SelectOutput {
-                                            selected: 0,
-                                        });
+                                        Some(TranscriberOverlay::SelectOutput { selected: 0 });
                                     return;
                                 }
                             }
+                            KeyCode::Char('m') => {
+                                let muted = !self.state.word_mappings_muted;
+                                self.send_command(ClientCommand::SetWordMappingsMuted(muted));
+                            }
                             _ => {}
                         }
                         self.transcriber_overlay =
@@ -331,11 +1071,12 @@ impl ClientApp {
                             KeyCode::Enter => {
                                 if !input.is_empty() {
                                     let word = input.as_str().to_string();
-                                    self.transcriber_overlay =
-                                        Some(TranscriberOverlay::PickSong {
-                                            word,
-                                            selected: 0,
-                                        });
+                                    self.transcriber_overlay = Some(TranscriberOverlay::PickSong {
+                                        word,
+                                        selected: 0,
+                                        chosen: Vec::new(),
+                                        selection: SongSelection::default(),
+                                    });
                                     return;
                                 }
                             }
@@ -353,7 +1094,11 @@ impl ClientApp {
                     Some(TranscriberOverlay::PickSong {
                         word,
                         mut selected,
+                        mut chosen,
+                        mut selection,
                     }) => {
+                        // `selected` counts rows of the filtered list
+                        let order = self.song_order();
                         match key.code {
                             KeyCode::Up => {
                                 if selected > 0 {
@@ -361,17 +1106,61 @@ impl ClientApp {
                                 }
                             }
                             KeyCode::Down => {
-                                if !self.state.songs.is_empty()
-                                    && selected < self.state.songs.len() - 1
-                                {
+                                if selected + 1 < order.len() {
                                     selected += 1;
                                 }
                             }
+                            KeyCode::Char(' ') if selected < order.len() => {
+                                let song = order[selected];
+                                match chosen.iter().position(|&i| i == song) {
+                                    Some(pos) => {
+                                        chosen.remove(pos);
+                                    }
+                                    None => chosen.push(song),
+                                }
+                            }
+                            // Typing narrows the list like `/` in the songs panel
+                            KeyCode::Char(c) => {
+                                self.song_filter
+                                    .get_or_insert_with(|| SongFilter {
+                                        input: TextInput::new(),
+                                        editing: false,
+                                    })
+                                    .input
+                                    .push_char(c);
+                                selected = 0;
+                            }
+                            KeyCode::Backspace => {
+                                if let Some(filter) = &mut self.song_filter {
+                                    filter.input.backspace();
+                                }
+                                selected = 0;
+                            }
+                            KeyCode::Tab => {
+                                selection = match selection {
+                                    SongSelection::Random => SongSelection::RoundRobin,
+                                    SongSelection::RoundRobin => SongSelection::Random,
+                                };
+                            }
                             KeyCode::Enter => {
-                                if selected < self.state.songs.len() {
+                                if selected < order.len() {
+                                    let song_indices = if chosen.is_empty() {
+                                        vec![order[selected]]
+                                    } else {
+                                        chosen
+                                    };
+                                    let target = match song_indices[..] {
+                                        [one] => self.state.songs[one].name.clone(),
+                                        _ => format!(
+                                            "{} songs ({})",
+                                            song_indices.len(),
+                                            selection.name()
+                                        ),
+                                    };
                                     self.send_command(ClientCommand::AddWordMapping {
                                         word: word.clone(),
-                                        song_index: selected,
+                                        song_indices,
+                                        selection,
                                         source_description: self.detector_source_description.clone().unwrap_or_default(),
                                         output_description: self.detector_output_description.clone().unwrap_or_default(),
                                     });
@@ -382,18 +1171,19 @@ impl ClientApp {
                                         );
                                     }
                                     self.transcriber_overlay = None;
-                                    self.status_message = Some(format!(
-                                        "Mapped \"{}\" -> {}",
-                                        word,
-                                        self.state.songs[selected].name
-                                    ));
+                                    self.status_message =
+                                        Some(format!("Mapped \"{}\" -> {}", word, target));
                                     return;
                                 }
                             }
                             _ => {}
                         }
-                        self.transcriber_overlay =
-                            Some(TranscriberOverlay::PickSong { word, selected });
+                        self.transcriber_overlay = Some(TranscriberOverlay::PickSong {
+                            word,
+                            selected,
+                            chosen,
+                            selection,
+                        });
                     }
                     None => {}
                 }
@@ -402,137 +1192,125 @@ impl ClientApp {
     }
 
     fn handle_mouse(&mut self, mouse: MouseEvent) {
-        if mouse.kind != MouseEventKind::Down(MouseButton::Left) {
-            return;
+        let pos = Position::new(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(action) = crate::ui::hit_test(self, pos) {
+                    self.apply_action(action);
+                }
+            }
+            // Dragging a queue entry moves it row by row under the pointer
+            MouseEventKind::Drag(MouseButton::Left) if self.focus == Panel::Queue => {
+                if let Some(Action::SelectQueued(to)) = crate::ui::hit_test(self, pos) {
+                    self.move_queued(to as isize - self.selected_queue as isize);
+                }
+            }
+            MouseEventKind::ScrollUp => self.scroll_at(pos, -1),
+            MouseEventKind::ScrollDown => self.scroll_at(pos, 1),
+            _ => {}
         }
+    }
 
-        let col = mouse.column;
-        let row = mouse.row;
+    /// The wheel turned one notch over `pos`, `delta` rows down the screen:
+    /// lists under it step their selection and the volume bar nudges the
+    /// volume, up for louder. Elsewhere it does nothing.
+    fn scroll_at(&mut self, pos: Position, delta: isize) {
+        let layout = self.layout;
+        if layout.sinks_area.contains(pos) {
+            self.step_sink(delta);
+        } else if layout.songs_area.contains(pos) {
+            self.step_song(delta);
+        } else if layout.queue_area.contains(pos) {
+            self.step_queued(delta);
+        } else if layout.volume_area.contains(pos) {
+            self.step_volume(-delta as f32 * 0.05);
+        }
+        #[cfg(feature = "transcriber")]
+        if layout.word_bindings_area.contains(pos) {
+            let count = self.binding_count();
+            self.selected_word_binding = clamped_step(self.selected_word_binding, delta, count);
+        }
+    }
 
-        if self.layout.sinks_area.contains((col, row).into()) {
-            self.focus = Panel::Sinks;
-            let inner_y = row.saturating_sub(self.layout.sinks_area.y + 1);
-            let idx = inner_y as usize;
-            if idx < self.state.sinks.len() {
+    fn apply_action(&mut self, action: Action) {
+        match action {
+            Action::Focus(panel) => self.focus = panel,
+            Action::SelectSink(idx) => {
+                self.focus = Panel::Sinks;
                 self.send_command(ClientCommand::SelectSink(idx));
             }
-        } else if self.layout.volume_area.contains((col, row).into()) {
-            self.focus = Panel::Volume;
-            let inner_x = col.saturating_sub(self.layout.volume_area.x + 1);
-            let inner_width = self.layout.volume_area.width.saturating_sub(2);
-            if inner_width > 0 {
-                let vol = (inner_x as f32 / inner_width as f32 * 5.0).clamp(0.0, 5.0);
-                self.state.volume = vol;
-                self.send_command(ClientCommand::SetVolume(vol));
-            }
-        } else if self.layout.audio_fx_area.contains((col, row).into()) {
-            self.focus = Panel::AudioFx;
-            let inner_y = row.saturating_sub(self.layout.audio_fx_area.y + 1);
-            let inner_x = col.saturating_sub(self.layout.audio_fx_area.x + 1);
-            let inner_width = self.layout.audio_fx_area.width.saturating_sub(2);
-            if inner_y < 2 {
-                self.selected_fx = inner_y as usize;
-                if inner_width > 0 {
-                    let ratio = inner_x as f32 / inner_width as f32;
-                    match self.selected_fx {
-                        0 => {
-                            let v = (ratio * 0.05).clamp(0.0, 0.05);
-                            self.state.comfort_noise = v;
-                            self.send_command(ClientCommand::SetComfortNoise(v));
-                        }
-                        1 => {
-                            let v = (ratio * 3.0).clamp(0.0, 3.0);
-                            self.state.eq_mid_boost = v;
-                            self.send_command(ClientCommand::SetEqMidBoost(v));
-                        }
-                        _ => {}
-                    }
+            Action::SetVolume(volume) => {
+                self.focus = Panel::Volume;
+                self.state.volume = volume;
+                self.send_command(ClientCommand::SetVolume(volume));
+            }
+            Action::Fx { row, ratio } => {
+                self.focus = Panel::AudioFx;
+                self.selected_fx = row;
+                if row == FX_LIMITER_ROW {
+                    self.toggle_limiter();
+                } else if let Some(ratio) = ratio {
+                    self.set_fx(row, ratio);
                 }
             }
-        } else if self.layout.add_button_area.contains((col, row).into()) {
-            self.focus = Panel::AddButton;
-            self.activate();
-        }
-        #[cfg(feature = "transcriber")]
-        if self.layout.word_detector_button_area.contains((col, row).into()) {
-            self.focus = Panel::WordDetectorButton;
-            self.activate();
-            return;
-        }
-        #[cfg(feature = "transcriber")]
-        if self.layout.word_bindings_area.contains((col, row).into()) {
-            self.focus = Panel::WordBindings;
-            let inner_y = row.saturating_sub(self.layout.word_bindings_area.y + 1);
-            let bindings = self.bindings_for_selected_song();
-            if !bindings.is_empty() {
-                self.selected_word_binding = (inner_y as usize).min(bindings.len() - 1);
+            Action::Activate(panel) => {
+                self.focus = panel;
+                self.activate();
             }
-            return;
-        }
-        if self.layout.songs_area.contains((col, row).into()) {
-            self.focus = Panel::Songs;
-            let inner_y = row.saturating_sub(self.layout.songs_area.y + 1);
-            let idx = inner_y as usize;
-            if !self.state.songs.is_empty() && idx < self.state.songs.len() {
+            Action::PlaySong(idx) => {
+                self.focus = Panel::Songs;
                 self.send_command(ClientCommand::SelectSong(idx));
                 self.send_command(ClientCommand::Play);
             }
+            Action::SelectQueued(pos) => {
+                self.focus = Panel::Queue;
+                self.selected_queue = pos;
+            }
+            #[cfg(feature = "transcriber")]
+            Action::SelectBinding(pos) => {
+                self.focus = Panel::WordBindings;
+                self.selected_word_binding = pos;
+            }
         }
     }
 
     fn cycle_focus(&mut self) {
-        self.focus = match self.focus {
-            Panel::Sinks => Panel::Volume,
-            Panel::Volume => Panel::AudioFx,
-            Panel::AudioFx => Panel::AddButton,
-            #[cfg(feature = "transcriber")]
-            Panel::AddButton => Panel::WordDetectorButton,
-            #[cfg(feature = "transcriber")]
-            Panel::WordDetectorButton => Panel::Songs,
-            #[cfg(not(feature = "transcriber"))]
-            Panel::AddButton => Panel::Songs,
-            #[cfg(feature = "transcriber")]
-            Panel::Songs => {
-                if self.show_word_bindings_panel() {
-                    Panel::WordBindings
-                } else {
-                    Panel::Sinks
-                }
-            }
-            #[cfg(not(feature = "transcriber"))]
-            Panel::Songs => Panel::Sinks,
-            #[cfg(feature = "transcriber")]
-            Panel::WordBindings => Panel::Sinks,
-        };
+        self.step_focus(true);
     }
 
     fn cycle_focus_back(&mut self) {
-        self.focus = match self.focus {
-            #[cfg(feature = "transcriber")]
-            Panel::Sinks => {
-                if self.show_word_bindings_panel() {
-                    Panel::WordBindings
+        self.step_focus(false);
+    }
+
+    /// Move focus to the next (or previous) panel in `PANEL_ORDER` that is
+    /// on screen, wrapping around.
+    fn step_focus(&mut self, forward: bool) {
+        let n = PANEL_ORDER.len();
+        let at = PANEL_ORDER
+            .iter()
+            .position(|&p| p == self.focus)
+            .unwrap_or(0);
+        self.focus = (1..=n)
+            .map(|step| {
+                if forward {
+                    (at + step) % n
                 } else {
-                    Panel::Songs
+                    (at + n - step) % n
                 }
-            }
-            #[cfg(not(feature = "transcriber"))]
-            Panel::Sinks => Panel::Songs,
-            Panel::Volume => Panel::Sinks,
-            Panel::AudioFx => Panel::Volume,
-            #[cfg(feature = "transcriber")]
-            Panel::AddButton => Panel::AudioFx,
-            #[cfg(feature = "transcriber")]
-            Panel::WordDetectorButton => Panel::AddButton,
-            #[cfg(feature = "transcriber")]
-            Panel::Songs => Panel::WordDetectorButton,
-            #[cfg(not(feature = "transcriber"))]
-            Panel::AddButton => Panel::AudioFx,
-            #[cfg(not(feature = "transcriber"))]
-            Panel::Songs => Panel::AddButton,
+            })
+            .map(|i| PANEL_ORDER[i])
+            .find(|&panel| self.panel_shown(panel))
+            .unwrap_or(Panel::Sinks);
+    }
+
+    /// Whether `panel` is drawn, and so can take focus.
+    pub fn panel_shown(&self, panel: Panel) -> bool {
+        match panel {
+            Panel::Queue => self.show_queue,
             #[cfg(feature = "transcriber")]
-            Panel::WordBindings => Panel::Songs,
-        };
+            Panel::WordBindings => self.show_word_bindings_panel(),
+            _ => true,
+        }
     }
 
     #[cfg(feature = "transcriber")]
@@ -545,68 +1323,100 @@ impl ClientApp {
 
     fn handle_left(&mut self) {
         match self.focus {
-            Panel::Volume => {
-                self.state.volume = (self.state.volume - 0.05).clamp(0.0, 5.0);
-                self.send_command(ClientCommand::SetVolume(self.state.volume));
-            }
-            Panel::AudioFx => match self.selected_fx {
-                0 => {
-                    self.state.comfort_noise =
-                        (self.state.comfort_noise - 0.005).clamp(0.0, 0.05);
-                    self.send_command(ClientCommand::SetComfortNoise(self.state.comfort_noise));
-                }
-                1 => {
-                    self.state.eq_mid_boost =
-                        (self.state.eq_mid_boost - 0.1).clamp(0.0, 3.0);
-                    self.send_command(ClientCommand::SetEqMidBoost(self.state.eq_mid_boost));
-                }
-                _ => {}
-            },
+            Panel::Volume => self.step_volume(-0.05),
+            Panel::AudioFx => self.step_fx(-1.0),
             _ => self.cycle_focus_back(),
         }
     }
 
     fn handle_right(&mut self) {
         match self.focus {
-            Panel::Volume => {
-                self.state.volume = (self.state.volume + 0.05).clamp(0.0, 5.0);
-                self.send_command(ClientCommand::SetVolume(self.state.volume));
-            }
-            Panel::AudioFx => match self.selected_fx {
-                0 => {
-                    self.state.comfort_noise =
-                        (self.state.comfort_noise + 0.005).clamp(0.0, 0.05);
-                    self.send_command(ClientCommand::SetComfortNoise(self.state.comfort_noise));
-                }
-                1 => {
-                    self.state.eq_mid_boost =
-                        (self.state.eq_mid_boost + 0.1).clamp(0.0, 3.0);
-                    self.send_command(ClientCommand::SetEqMidBoost(self.state.eq_mid_boost));
-                }
-                _ => {}
-            },
+            Panel::Volume => self.step_volume(0.05),
+            Panel::AudioFx => self.step_fx(1.0),
             _ => self.cycle_focus(),
         }
     }
 
-    fn move_up(&mut self) {
-        match self.focus {
-            Panel::Sinks => {
-                if self.state.selected_sink > 0 {
-                    self.state.selected_sink -= 1;
-                    self.send_command(ClientCommand::SelectSink(self.state.selected_sink));
-                }
+    fn step_volume(&mut self, delta: f32) {
+        self.state.volume = (self.state.volume + delta).clamp(0.0, VOLUME_MAX);
+        self.send_command(ClientCommand::SetVolume(self.state.volume));
+    }
+
+    /// Nudge the selected Audio FX row one step in `direction`.
+    fn step_fx(&mut self, direction: f32) {
+        match self.selected_fx {
+            0 => {
+                self.state.comfort_noise =
+                    (self.state.comfort_noise + direction * 0.005).clamp(0.0, COMFORT_NOISE_MAX);
+                self.send_command(ClientCommand::SetComfortNoise(self.state.comfort_noise));
             }
-            Panel::Songs => {
-                if self.state.selected_song > 0 {
-                    self.state.selected_song -= 1;
-                    self.send_command(ClientCommand::SelectSong(self.state.selected_song));
-                    #[cfg(feature = "transcriber")]
-                    {
-                        self.selected_word_binding = 0;
-                    }
-                }
+            row @ 1..=3 => {
+                let band = EqBand::ALL[row - 1];
+                self.set_eq_band(band, self.state.eq.get(band) + direction * 0.1);
+            }
+            4 => {
+                self.state.fade_secs =
+                    (self.state.fade_secs + direction * 0.05).clamp(0.0, FADE_SECS_MAX);
+                self.send_command(ClientCommand::SetFade(self.state.fade_secs));
+            }
+            FX_LIMITER_ROW => self.toggle_limiter(),
+            _ => {}
+        }
+    }
+
+    /// Jump Audio FX slider `row` to `ratio` of its range.
+    fn set_fx(&mut self, row: usize, ratio: f32) {
+        match row {
+            0 => {
+                let v = (ratio * COMFORT_NOISE_MAX).clamp(0.0, COMFORT_NOISE_MAX);
+                self.state.comfort_noise = v;
+                self.send_command(ClientCommand::SetComfortNoise(v));
             }
+            row @ 1..=3 => self.set_eq_band(EqBand::ALL[row - 1], ratio * EQ_GAIN_MAX),
+            4 => {
+                let v = (ratio * FADE_SECS_MAX).clamp(0.0, FADE_SECS_MAX);
+                self.state.fade_secs = v;
+                self.send_command(ClientCommand::SetFade(v));
+            }
+            _ => {}
+        }
+    }
+
+    fn set_eq_band(&mut self, band: EqBand, gain: f32) {
+        let gain = gain.clamp(0.0, EQ_GAIN_MAX);
+        *self.state.eq.get_mut(band) = gain;
+        self.send_command(ClientCommand::SetEqBand { band, gain });
+    }
+
+    fn cycle_noise_kind(&mut self) {
+        self.state.noise_kind = self.state.noise_kind.next();
+        self.send_command(ClientCommand::SetNoiseKind(self.state.noise_kind));
+    }
+
+    #[cfg(feature = "transcriber")]
+    fn cycle_power_mode(&mut self) {
+        let mode = self.state.detector_power_mode.next();
+        self.state.detector_power_mode = mode;
+        self.send_command(ClientCommand::SetDetectorPowerMode(mode));
+        self.status_message = Some(format!("Detector power mode: {}", mode.name()));
+    }
+
+    fn cycle_song_sort(&mut self) {
+        let key = self.state.song_sort.map_or(SortKey::Name, SortKey::next);
+        self.send_command(ClientCommand::SortSongs(key));
+        self.status_message = Some(format!("Songs sorted by {}", key.name()));
+    }
+
+    fn toggle_limiter(&mut self) {
+        self.state.limiter = !self.state.limiter;
+        self.send_command(ClientCommand::SetLimiter(self.state.limiter));
+    }
+
+    fn move_up(&mut self) {
+        match self.focus {
+            Panel::Sinks => self.step_sink(-1),
+            Panel::Songs => self.step_song(-1),
+            Panel::Queue => self.step_queued(-1),
             Panel::AudioFx => {
                 if self.selected_fx > 0 {
                     self.selected_fx -= 1;
@@ -624,34 +1434,17 @@ impl ClientApp {
 
     fn move_down(&mut self) {
         match self.focus {
-            Panel::Sinks => {
-                if !self.state.sinks.is_empty()
-                    && self.state.selected_sink < self.state.sinks.len() - 1
-                {
-                    self.state.selected_sink += 1;
-                    self.send_command(ClientCommand::SelectSink(self.state.selected_sink));
-                }
-            }
-            Panel::Songs => {
-                if !self.state.songs.is_empty()
-                    && self.state.selected_song < self.state.songs.len() - 1
-                {
-                    self.state.selected_song += 1;
-                    self.send_command(ClientCommand::SelectSong(self.state.selected_song));
-                    #[cfg(feature = "transcriber")]
-                    {
-                        self.selected_word_binding = 0;
-                    }
-                }
-            }
+            Panel::Sinks => self.step_sink(1),
+            Panel::Songs => self.step_song(1),
+            Panel::Queue => self.step_queued(1),
             Panel::AudioFx => {
-                if self.selected_fx < 1 {
+                if self.selected_fx < FX_LIMITER_ROW {
                     self.selected_fx += 1;
                 }
             }
             #[cfg(feature = "transcriber")]
             Panel::WordBindings => {
-                let count = self.bindings_for_selected_song().len();
+                let count = self.binding_count();
                 if count > 0 && self.selected_word_binding < count - 1 {
                     self.selected_word_binding += 1;
                 }
@@ -660,6 +1453,28 @@ impl ClientApp {
         }
     }
 
+    /// Move the sinks, songs or queue selection by `delta`, stopping at the
+    /// ends.
+    fn step_list(&mut self, delta: isize) {
+        match self.focus {
+            Panel::Sinks => self.step_sink(delta),
+            Panel::Songs => self.step_song(delta),
+            Panel::Queue => self.step_queued(delta),
+            _ => {}
+        }
+    }
+
+    /// Move the sinks, songs or queue selection by `pages` panel heights.
+    fn move_page(&mut self, pages: isize) {
+        let area = match self.focus {
+            Panel::Sinks => self.layout.sinks_area,
+            Panel::Songs => self.layout.songs_area,
+            Panel::Queue => self.layout.queue_area,
+            _ => return,
+        };
+        self.step_list(pages * page_rows(area));
+    }
+
     fn activate(&mut self) {
         match self.focus {
             Panel::AddButton => {
@@ -668,23 +1483,39 @@ impl ClientApp {
             Panel::Songs => {
                 self.send_command(ClientCommand::Play);
             }
+            Panel::AudioFx if self.selected_fx == 0 => self.cycle_noise_kind(),
+            Panel::AudioFx if self.selected_fx == FX_LIMITER_ROW => self.toggle_limiter(),
             #[cfg(feature = "transcriber")]
             Panel::WordDetectorButton => {
                 self.activate_word_detector();
             }
+            #[cfg(feature = "transcriber")]
+            Panel::WordBindings => {
+                let word = self.selected_binding().map(|(_, wm)| wm.word.clone());
+                if let Some(word) = word {
+                    self.send_command(ClientCommand::SimulateWord(word));
+                }
+            }
             _ => {}
         }
     }
 
     #[cfg(feature = "transcriber")]
     fn activate_word_detector(&mut self) {
+        if let Some(reason) = &self.state.vosk_missing {
+            self.status_message = Some(format!(
+                "Install libvosk to use the word detector ({reason})"
+            ));
+            return;
+        }
         match &self.state.word_detector_status {
             WordDetectorStatus::Unavailable | WordDetectorStatus::DownloadFailed(_) => {
                 self.send_command(ClientCommand::StartModelDownload);
                 self.status_message = Some("Starting model download...".to_string());
             }
             WordDetectorStatus::Downloading => {
-                self.status_message = Some("Model download in progress...".to_string());
+                self.send_command(ClientCommand::CancelModelDownload);
+                self.status_message = Some("Cancelling model download...".to_string());
             }
             WordDetectorStatus::Ready => {
                 // Open source selection overlay
@@ -699,42 +1530,345 @@ impl ClientApp {
         }
     }
 
+    /// Delete the highlighted song or word binding, after asking unless
+    /// the config turned that off.
     fn delete_selected(&mut self) {
-        match self.focus {
-            Panel::Songs => {
-                if !self.state.songs.is_empty() {
-                    self.send_command(ClientCommand::RemoveSong(self.state.selected_song));
+        let target = match self.focus {
+            // Only leaves the queue, so there is nothing to confirm
+            Panel::Queue => return self.unqueue_selected(),
+            Panel::Songs => match self.state.songs.get(self.state.selected_song) {
+                Some(song) => PendingDelete::Song(song.path.clone()),
+                None => return,
+            },
+            #[cfg(feature = "transcriber")]
+            Panel::WordBindings => match self.selected_binding() {
+                Some((global_idx, _)) => PendingDelete::WordBinding(global_idx),
+                None => return,
+            },
+            _ => return,
+        };
+        if self.state.confirm_delete {
+            self.pending_delete = Some(target);
+        } else {
+            self.delete(target);
+        }
+    }
+
+    fn handle_confirm_delete_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                if let Some(target) = self.pending_delete.take() {
+                    self.delete(target);
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => self.pending_delete = None,
+            _ => {}
+        }
+    }
+
+    fn delete(&mut self, target: PendingDelete) {
+        match target {
+            PendingDelete::Song(path) => {
+                match self.state.songs.iter().position(|song| song.path == path) {
+                    Some(idx) => self.send_command(ClientCommand::RemoveSong(idx)),
+                    None => self.status_message = Some("That song is already gone".to_string()),
                 }
             }
             #[cfg(feature = "transcriber")]
-            Panel::WordBindings => {
-                let bindings = self.bindings_for_selected_song();
-                let count = bindings.len();
-                if let Some(&(global_idx, _)) = bindings.get(self.selected_word_binding) {
-                    drop(bindings);
-                    self.send_command(ClientCommand::RemoveWordMapping(global_idx));
-                    if self.selected_word_binding > 0
-                        && self.selected_word_binding >= count - 1
-                    {
-                        self.selected_word_binding -= 1;
-                    }
+            PendingDelete::WordBinding(global_idx) => {
+                let count = self.binding_count();
+                self.send_command(ClientCommand::RemoveWordMapping(global_idx));
+                if self.selected_word_binding > 0 && self.selected_word_binding >= count - 1 {
+                    self.selected_word_binding -= 1;
                 }
             }
-            _ => {}
         }
     }
 
     #[cfg(feature = "transcriber")]
-    pub fn bindings_for_selected_song(&self) -> Vec<(usize, &crate::protocol::WordMapping)> {
-        if self.state.songs.is_empty() {
-            return Vec::new();
+    fn toggle_selected_binding(&mut self) {
+        let binding = self
+            .selected_binding()
+            .map(|(index, wm)| (index, wm.enabled));
+        if let Some((index, enabled)) = binding {
+            self.send_command(ClientCommand::SetWordMappingEnabled {
+                index,
+                enabled: !enabled,
+            });
         }
-        let selected_path = &self.state.songs[self.state.selected_song].path;
-        self.state
+    }
+
+    #[cfg(feature = "transcriber")]
+    fn toggle_binding_source_only(&mut self) {
+        let binding = self
+            .selected_binding()
+            .map(|(i, wm)| (i, wm.only_from_source.is_some()));
+        if let Some((index, only)) = binding {
+            self.send_command(ClientCommand::SetWordMappingSourceOnly { index, only: !only });
+        }
+    }
+
+    /// Rebuild the selected song's binding list and keep the selection on it.
+    #[cfg(feature = "transcriber")]
+    fn refresh_bindings(&mut self) {
+        let selected_path = self
+            .state
+            .songs
+            .get(self.state.selected_song)
+            .map(|s| s.path.as_str());
+        self.binding_indices = self
+            .state
             .word_mappings
             .iter()
             .enumerate()
-            .filter(|(_, wm)| wm.song_path == *selected_path)
+            .filter(|(_, wm)| {
+                wm.song_paths
+                    .iter()
+                    .any(|p| Some(p.as_str()) == selected_path)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        let last = self.binding_indices.len().saturating_sub(1);
+        self.selected_word_binding = self.selected_word_binding.min(last);
+        self.word_binding_scroll = self.word_binding_scroll.min(last);
+    }
+
+    #[cfg(feature = "transcriber")]
+    pub fn binding_count(&self) -> usize {
+        self.binding_indices.len()
+    }
+
+    /// The selected song's `pos`th binding with its global index, which is
+    /// what commands take.
+    #[cfg(feature = "transcriber")]
+    pub fn binding(&self, pos: usize) -> Option<(usize, &crate::protocol::WordMapping)> {
+        let index = *self.binding_indices.get(pos)?;
+        Some((index, self.state.word_mappings.get(index)?))
+    }
+
+    #[cfg(feature = "transcriber")]
+    fn selected_binding(&self) -> Option<(usize, &crate::protocol::WordMapping)> {
+        self.binding(self.selected_word_binding)
+    }
+
+    /// Lines the `pos`th binding takes in the panel: word, input and output,
+    /// plus a stats line for the focused selection.
+    #[cfg(feature = "transcriber")]
+    pub fn binding_row_height(&self, pos: usize) -> u16 {
+        let expanded = self.focus == Panel::WordBindings && pos == self.selected_word_binding;
+        if expanded {
+            4
+        } else {
+            3
+        }
+    }
+
+    /// Move the sink selection by `delta` in display order, skipping headers
+    /// and stopping at the first and last sink.
+    fn step_sink(&mut self, delta: isize) {
+        let order = self.sink_order();
+        let Some(pos) = order.iter().position(|&i| i == self.state.selected_sink) else {
+            return;
+        };
+        let next = order[clamped_step(pos, delta, order.len())];
+        if next != self.state.selected_sink {
+            self.state.selected_sink = next;
+            self.send_command(ClientCommand::SelectSink(next));
+        }
+    }
+
+    /// Check or uncheck the highlighted output as the monitor for injected clips.
+    fn toggle_monitor_sink(&mut self) {
+        let idx = self.state.selected_sink;
+        let Some(sink) = self.state.sinks.get(idx) else {
+            return;
+        };
+        if sink.kind != "Output" {
+            self.status_message = Some("Only an output can monitor injected clips".to_string());
+            return;
+        }
+        let monitor = (self.state.monitor_sink != Some(idx)).then_some(idx);
+        self.state.monitor_sink = monitor;
+        self.send_command(ClientCommand::SetMonitorSink(monitor));
+    }
+
+    /// Move the song selection by `delta` among the songs the filters show,
+    /// stopping at the first and last. A hidden selection jumps to the
+    /// first visible song.
+    fn step_song(&mut self, delta: isize) {
+        let order = self.song_order();
+        let next = match order.iter().position(|&i| i == self.state.selected_song) {
+            Some(pos) => order[clamped_step(pos, delta, order.len())],
+            None => match order.first() {
+                Some(&first) => first,
+                None => return,
+            },
+        };
+        if next != self.state.selected_song {
+            self.select_song(next);
+        }
+    }
+
+    /// Swap the highlighted song with its neighbour `delta` rows away in
+    /// the panel; the daemon moves it and keeps it selected.
+    fn move_song(&mut self, delta: isize) {
+        let order = self.song_order();
+        let Some(pos) = order.iter().position(|&i| i == self.state.selected_song) else {
+            return;
+        };
+        let to = order[clamped_step(pos, delta, order.len())];
+        if to != self.state.selected_song {
+            self.send_command(ClientCommand::MoveSong {
+                from: self.state.selected_song,
+                to,
+            });
+        }
+    }
+
+    /// Show or hide the queue section, focusing it when it opens.
+    fn toggle_queue(&mut self) {
+        self.show_queue = !self.show_queue;
+        if self.show_queue {
+            self.focus = Panel::Queue;
+        } else if self.focus == Panel::Queue {
+            self.focus = Panel::Songs;
+        }
+    }
+
+    fn step_queued(&mut self, delta: isize) {
+        self.selected_queue = clamped_step(self.selected_queue, delta, self.state.queue.len());
+    }
+
+    /// Move the highlighted queue entry `delta` places, keeping it
+    /// highlighted. The local copy moves too, so a drag can go on before
+    /// the daemon's update arrives.
+    fn move_queued(&mut self, delta: isize) {
+        let from = self.selected_queue;
+        if from >= self.state.queue.len() {
+            return;
+        }
+        let to = clamped_step(from, delta, self.state.queue.len());
+        if to != from {
+            let entry = self.state.queue.remove(from);
+            self.state.queue.insert(to, entry);
+            self.selected_queue = to;
+            self.send_command(ClientCommand::ReorderQueue { from, to });
+        }
+    }
+
+    fn unqueue_selected(&mut self) {
+        if self.selected_queue < self.state.queue.len() {
+            self.send_command(ClientCommand::RemoveFromQueue(self.selected_queue));
+        }
+    }
+
+    fn clamp_queue_selection(&mut self) {
+        self.selected_queue = self
+            .selected_queue
+            .min(self.state.queue.len().saturating_sub(1));
+    }
+
+    fn select_song(&mut self, idx: usize) {
+        self.state.selected_song = idx;
+        self.send_command(ClientCommand::SelectSong(idx));
+        #[cfg(feature = "transcriber")]
+        {
+            self.selected_word_binding = 0;
+            self.word_binding_scroll = 0;
+            self.refresh_bindings();
+        }
+    }
+
+    /// Song indices the songs panel shows, in order.
+    pub fn song_order(&self) -> Vec<usize> {
+        let query = match &self.song_filter {
+            Some(filter) => filter.input.as_str().to_lowercase(),
+            None => String::new(),
+        };
+        let mut ranked: Vec<(u8, usize)> = self
+            .state
+            .songs
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| self.tag_filter.is_none() || s.tag_color == self.tag_filter)
+            .filter_map(|(i, s)| Some((filter_rank(&s.name, &query)?, i)))
+            .collect();
+        // Stable, so each rank keeps the library order
+        ranked.sort_by_key(|&(rank, _)| rank);
+        ranked.into_iter().map(|(_, i)| i).collect()
+    }
+
+    fn cycle_song_tag(&mut self) {
+        let Some(song) = self.state.songs.get(self.state.selected_song) else {
+            return;
+        };
+        let palette = &self.state.tag_palette;
+        let tag_color =
+            crate::theme::next_tag(palette, song.tag_color.as_deref()).map(str::to_string);
+        self.send_command(ClientCommand::SetSongTag {
+            index: self.state.selected_song,
+            tag_color,
+        });
+    }
+
+    /// Ask for the highlighted song's envelope, or hide it if it is shown.
+    fn toggle_waveform(&mut self) {
+        let Some(song) = self.state.songs.get(self.state.selected_song) else {
+            return;
+        };
+        if self.selected_waveform().is_some() {
+            self.waveform = None;
+            return;
+        }
+        self.waveform_requested = Some(song.path.clone());
+        self.send_command(ClientCommand::GetWaveform(self.state.selected_song));
+    }
+
+    /// The fetched envelope, while its song is the highlighted one.
+    pub fn selected_waveform(&self) -> Option<&[u8]> {
+        let (path, peaks) = self.waveform.as_ref()?;
+        let song = self.state.songs.get(self.state.selected_song)?;
+        (song.path == *path).then_some(peaks.as_slice())
+    }
+
+    /// Nudge the highlighted song's volume factor; landing on 1.0x clears it.
+    fn step_song_volume(&mut self, delta: f32) {
+        let index = self.state.selected_song;
+        let Some(song) = self.state.songs.get_mut(index) else {
+            return;
+        };
+        let v = (song.volume.unwrap_or(1.0) + delta).clamp(0.0, SONG_VOLUME_MAX);
+        // Round away float drift so stepping back lands exactly on 1.0x
+        let v = (v * 10.0).round() / 10.0;
+        let volume = (v != 1.0).then_some(v);
+        song.volume = volume;
+        self.send_command(ClientCommand::SetSongVolume { index, volume });
+    }
+
+    fn cycle_tag_filter(&mut self) {
+        let palette = &self.state.tag_palette;
+        let next = crate::theme::next_tag(palette, self.tag_filter.as_deref());
+        self.tag_filter = next.map(str::to_string);
+        let order = self.song_order();
+        if !order.contains(&self.state.selected_song) {
+            if let Some(&first) = order.first() {
+                self.select_song(first);
+            }
+        }
+    }
+
+    pub fn sink_rows(&self) -> Vec<SinkRow> {
+        sink_rows(&self.state.sinks)
+    }
+
+    /// Sink indices in the order the devices panel shows them.
+    pub fn sink_order(&self) -> Vec<usize> {
+        self.sink_rows()
+            .into_iter()
+            .filter_map(|row| match row {
+                SinkRow::Sink(i) => Some(i),
+                SinkRow::Header(_) => None,
+            })
             .collect()
     }
 
@@ -748,6 +1882,9 @@ impl ClientApp {
     pub fn selected_sink(&self) -> usize {
         self.state.selected_sink
     }
+    pub fn monitor_sink(&self) -> Option<usize> {
+        self.state.monitor_sink
+    }
     pub fn selected_song(&self) -> usize {
         self.state.selected_song
     }
@@ -757,17 +1894,59 @@ impl ClientApp {
     pub fn comfort_noise(&self) -> f32 {
         self.state.comfort_noise
     }
-    pub fn eq_mid_boost(&self) -> f32 {
-        self.state.eq_mid_boost
+    pub fn eq_gain(&self, band: EqBand) -> f32 {
+        self.state.eq.get(band)
+    }
+    pub fn fade_secs(&self) -> f32 {
+        self.state.fade_secs
+    }
+    pub fn noise_kind(&self) -> NoiseKind {
+        self.state.noise_kind
+    }
+    pub fn limiter(&self) -> bool {
+        self.state.limiter
     }
     pub fn now_playing(&self) -> Option<&str> {
         self.state.now_playing.as_deref()
     }
+
+    pub fn paused(&self) -> bool {
+        self.state.paused
+    }
+
+    /// Pause or resume the current playback; the daemon confirms with `Paused`.
+    fn toggle_pause(&mut self) {
+        if self.state.now_playing.is_none() {
+            return;
+        }
+        let cmd = if self.state.paused {
+            ClientCommand::Resume
+        } else {
+            ClientCommand::Pause
+        };
+        self.send_command(cmd);
+    }
 }
 
+/// Connect to the daemon, looking in the same places it may have put its
+/// socket, and make sure it runs as us.
 fn connect_to_daemon() -> Result<UnixStream> {
-    let path = socket_path();
-    UnixStream::connect(&path).with_context(|| format!("Cannot connect to daemon at {}", path.display()))
+    connect_at(&socket_candidates())
+}
+
+/// Connect to the first of `candidates` that answers.
+pub(crate) fn connect_at(candidates: &[std::path::PathBuf]) -> Result<UnixStream> {
+    let (path, stream) = candidates
+        .iter()
+        .find_map(|path| UnixStream::connect(path).ok().map(|s| (path, s)))
+        .with_context(|| {
+            format!(
+                "Cannot connect to daemon (tried {})",
+                candidates[0].display()
+            )
+        })?;
+    check_peer(&stream).with_context(|| format!("Refusing daemon socket at {}", path.display()))?;
+    Ok(stream)
 }
 
 fn spawn_daemon() -> Result<()> {
@@ -777,7 +1956,9 @@ fn spawn_daemon() -> Result<()> {
         Some(f) => std::process::Stdio::from(f),
         None => std::process::Stdio::null(),
     };
+    // Same instance as the one we're about to look for
     std::process::Command::new(exe)
+        .args(crate::protocol::instance().args())
         .arg("daemon")
         .stdin(std::process::Stdio::null())
         .stdout(std::process::Stdio::null())
@@ -795,11 +1976,10 @@ pub fn run_or_start() -> Result<()> {
             // No daemon running, spawn one
             spawn_daemon()?;
             // Wait for socket to appear
-            let path = socket_path();
             let mut connected = None;
             for _ in 0..50 {
                 std::thread::sleep(Duration::from_millis(100));
-                if let Ok(s) = UnixStream::connect(&path) {
+                if let Ok(s) = connect_to_daemon() {
                     connected = Some(s);
                     break;
                 }
@@ -812,24 +1992,50 @@ pub fn run_or_start() -> Result<()> {
     run_tui(&mut app)
 }
 
-fn setup_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
-    terminal::enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, terminal::EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let terminal = Terminal::new(backend)?;
-    Ok(terminal)
+/// Owns the TUI terminal and puts the tty back (raw mode off, main screen,
+/// no mouse capture, cursor shown) when dropped, so every way out of
+/// `run_tui` restores it, including errors from `draw`.
+struct TerminalGuard<W: io::Write> {
+    terminal: Terminal<CrosstermBackend<W>>,
+    raw_mode: bool,
 }
 
-fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
-    terminal::disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        terminal::LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
-    Ok(())
+impl TerminalGuard<io::Stdout> {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        let terminal = execute!(stdout, terminal::EnterAlternateScreen, EnableMouseCapture)
+            .and_then(|_| Terminal::new(CrosstermBackend::new(stdout)));
+        match terminal {
+            Ok(terminal) => Ok(Self {
+                terminal,
+                raw_mode: true,
+            }),
+            Err(e) => {
+                let _ = terminal::disable_raw_mode();
+                let _ = execute!(
+                    io::stdout(),
+                    terminal::LeaveAlternateScreen,
+                    DisableMouseCapture
+                );
+                Err(e.into())
+            }
+        }
+    }
+}
+
+impl<W: io::Write> Drop for TerminalGuard<W> {
+    fn drop(&mut self) {
+        if self.raw_mode {
+            let _ = terminal::disable_raw_mode();
+        }
+        let _ = execute!(
+            self.terminal.backend_mut(),
+            terminal::LeaveAlternateScreen,
+            DisableMouseCapture
+        );
+        let _ = self.terminal.show_cursor();
+    }
 }
 
 fn run_tui(app: &mut ClientApp) -> Result<()> {
@@ -844,35 +2050,1456 @@ fn run_tui(app: &mut ClientApp) -> Result<()> {
         original_hook(info);
     }));
 
-    let mut terminal = setup_terminal()?;
+    let mut guard = TerminalGuard::enter()?;
 
     loop {
-        terminal.draw(|f| crate::ui::draw(f, app))?;
+        if std::mem::take(&mut app.needs_clear) {
+            guard.terminal.clear()?;
+        }
+        guard.terminal.draw(|f| crate::ui::draw(f, app))?;
 
         if let Some(ev) = crate::event::poll_event(Duration::from_millis(50)) {
             app.handle_event(ev);
         }
 
         app.poll_daemon_events();
+        app.poll_reconnect(connect_to_daemon);
+        app.meter.decay();
 
         if app.should_quit {
             break;
         }
     }
 
-    restore_terminal(&mut terminal)?;
     Ok(())
 }
 
+/// `Identify` for the one-shot CLI subcommands.
+fn identify(subcommand: &str) -> ClientCommand {
+    ClientCommand::Identify {
+        name: format!("cli {subcommand}"),
+        kind: "cli".to_string(),
+    }
+}
+
 pub fn send_stop() -> Result<()> {
+    send_oneshot("stop", ClientCommand::Quit)?;
+    println!("Sent stop signal to daemon.");
+    Ok(())
+}
+
+/// `plentysound stop-playback [--clear-queue]`: end the current song,
+/// leaving the daemon up.
+pub fn send_stop_playback(args: &[String]) -> Result<()> {
+    let clear_queue = match args {
+        [] => false,
+        [flag] if flag == "--clear-queue" => true,
+        _ => anyhow::bail!("Usage: plentysound stop-playback [--clear-queue]"),
+    };
+    send_oneshot("stop-playback", ClientCommand::StopPlayback { clear_queue })
+}
+
+/// Connect, send a single command and hang up without waiting for a reply.
+fn send_oneshot(name: &str, cmd: ClientCommand) -> Result<()> {
     let mut stream = connect_to_daemon().context("No daemon is running")?;
     stream.set_nonblocking(false)?;
     // Must read the initial State the daemon sends on connect,
     // otherwise the daemon's handle_new_client bails before spawning
-    // the reader thread and our Quit command is never processed.
-    let _initial: DaemonEvent = recv_message(&mut stream)
-        .context("Failed to receive initial state from daemon")?;
-    send_message(&mut stream, &ClientCommand::Quit)?;
-    println!("Sent stop signal to daemon.");
+    // the reader thread and our command is never processed.
+    let _initial: DaemonEvent =
+        recv_message(&mut stream).context("Failed to receive initial state from daemon")?;
+    send_message(&mut stream, &identify(name))?;
+    send_message(&mut stream, &cmd)?;
+    Ok(())
+}
+
+const SONGS_USAGE: &str =
+    "Usage: plentysound songs list | add <path>... | remove <path-or-index>... | prune-missing";
+
+/// `plentysound songs ...`: manage the library from scripts. Fails if any
+/// item failed, after trying all of them.
+pub fn run_songs_command(args: &[String]) -> Result<()> {
+    let (subcommand, items) = args.split_first().context(SONGS_USAGE)?;
+    if !matches!(subcommand.as_str(), "list" | "prune-missing") && items.is_empty() {
+        anyhow::bail!(SONGS_USAGE);
+    }
+
+    let (mut stream, state) = open_session("songs")?;
+
+    let failed = match subcommand.as_str() {
+        "list" => {
+            for (i, song) in fetch_all_songs(&mut stream, state)?.iter().enumerate() {
+                let duration = song
+                    .duration
+                    .map(|secs| format!("{}:{:02}", secs as u64 / 60, secs as u64 % 60))
+                    .unwrap_or_else(|| "?".to_string());
+                println!("{}\t{}\t{}\t{}", i, song.name, song.path, duration);
+            }
+            0
+        }
+        "add" => {
+            let mut failed = 0;
+            for item in items {
+                let outcome = std::fs::canonicalize(item)
+                    .with_context(|| format!("{}: file not found", item))
+                    .and_then(|path| {
+                        let cmd = ClientCommand::AddSong(path.display().to_string());
+                        command_outcome(&mut stream, cmd)
+                    });
+                failed += report_item(item, outcome);
+            }
+            failed
+        }
+        "remove" => {
+            let songs = fetch_all_songs(&mut stream, state)?;
+            let mut failed = 0;
+            let mut targets = Vec::new();
+            for item in items {
+                match resolve_song(&songs, item) {
+                    Some(idx) => targets.push((idx, item)),
+                    None => failed += report_item(item, Err(anyhow::anyhow!("not in library"))),
+                }
+            }
+            // Highest index first so earlier removals don't shift later ones
+            targets.sort_by_key(|t| std::cmp::Reverse(t.0));
+            targets.dedup_by_key(|t| t.0);
+            for (idx, item) in targets {
+                failed += report_item(
+                    item,
+                    command_outcome(&mut stream, ClientCommand::RemoveSong(idx)),
+                );
+            }
+            failed
+        }
+        "prune-missing" => {
+            println!(
+                "{}",
+                command_outcome(&mut stream, ClientCommand::PruneMissing)?
+            );
+            0
+        }
+        _ => anyhow::bail!(SONGS_USAGE),
+    };
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} item(s) failed", failed, items.len());
+    }
     Ok(())
 }
+
+/// Connect for a CLI subcommand: the stream, with replies timing out, and
+/// the state the daemon greets every client with.
+fn open_session(name: &str) -> Result<(UnixStream, DaemonState)> {
+    let mut stream = connect_to_daemon().context("No daemon is running")?;
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let state =
+        match recv_message(&mut stream).context("Failed to receive initial state from daemon")? {
+            DaemonEvent::State(s) => *s,
+            event => anyhow::bail!("Expected State event from daemon, got {:?}", event),
+        };
+    send_message(&mut stream, &identify(name))?;
+    Ok((stream, state))
+}
+
+/// `plentysound play <name-or-index>`: play a song on the selected sink
+/// and wait until it starts. The TUI's song selection stays put.
+pub fn send_play(query: Option<&str>) -> Result<()> {
+    let query = query.context("Usage: plentysound play <name-or-index>")?;
+    let (mut stream, state) = open_session("play")?;
+    let songs = fetch_all_songs(&mut stream, state)?;
+    let index = match_song(&songs, query)?;
+    for event in request(&mut stream, ClientCommand::PlaySong(index))? {
+        match event {
+            DaemonEvent::NowPlaying(Some(name)) => {
+                println!("Playing {}", name);
+                return Ok(());
+            }
+            DaemonEvent::Error { message, .. } => anyhow::bail!("{}", message),
+            DaemonEvent::CommandResult(result) if result.is_error() => {
+                anyhow::bail!("{}", result.message())
+            }
+            _ => {}
+        }
+    }
+    anyhow::bail!("'{}' did not start", songs[index].name)
+}
+
+/// The song `query` names: an index as `list` prints it, a name, or the
+/// start of exactly one name, ignoring case.
+fn match_song(songs: &[SongInfo], query: &str) -> Result<usize> {
+    if let Some(index) = query.parse().ok().filter(|&i: &usize| i < songs.len()) {
+        return Ok(index);
+    }
+    let query_lower = query.to_lowercase();
+    if let Some(index) = songs
+        .iter()
+        .position(|s| s.name.to_lowercase() == query_lower)
+    {
+        return Ok(index);
+    }
+    let matches: Vec<usize> = (0..songs.len())
+        .filter(|&i| songs[i].name.to_lowercase().starts_with(&query_lower))
+        .collect();
+    match matches[..] {
+        [index] => Ok(index),
+        [] => anyhow::bail!("No song matches '{}'; see plentysound list", query),
+        _ => {
+            let names: Vec<String> = matches
+                .iter()
+                .map(|&i| format!("  {}\t{}", i, songs[i].name))
+                .collect();
+            anyhow::bail!("'{}' matches several songs:\n{}", query, names.join("\n"))
+        }
+    }
+}
+
+/// Whether a subcommand that takes nothing but `--json` got it.
+fn json_flag(args: &[String], usage: &str) -> Result<bool> {
+    match args {
+        [] => Ok(false),
+        [flag] if flag == "--json" => Ok(true),
+        _ => anyhow::bail!("{}", usage),
+    }
+}
+
+/// What `plentysound list --json` prints; fields are named as in
+/// `DaemonState`.
+#[derive(serde::Serialize)]
+struct ListOutput<'a> {
+    songs: &'a [SongInfo],
+    sinks: &'a [SinkInfo],
+    selected_song: usize,
+    selected_sink: usize,
+}
+
+/// `plentysound list [--json]`: the library and the devices, marking the
+/// selection.
+pub fn print_list(args: &[String]) -> Result<()> {
+    let json = json_flag(args, "Usage: plentysound list [--json]")?;
+    let (mut stream, state) = open_session("list")?;
+    let (selected_song, selected_sink) = (state.selected_song, state.selected_sink);
+    let sinks = state.sinks.clone();
+    let songs = fetch_all_songs(&mut stream, state)?;
+    if json {
+        let output = ListOutput {
+            songs: &songs,
+            sinks: &sinks,
+            selected_song,
+            selected_sink,
+        };
+        println!("{}", serde_json::to_string(&output)?);
+        return Ok(());
+    }
+    println!("Songs:");
+    for (i, song) in songs.iter().enumerate() {
+        let mark = if i == selected_song { '*' } else { ' ' };
+        let flag = if song.missing { " (missing)" } else { "" };
+        println!("{} {}\t{}{}", mark, i, song.name, flag);
+    }
+    println!("Sinks:");
+    for (i, sink) in sinks.iter().enumerate() {
+        let mark = if i == selected_sink { '*' } else { ' ' };
+        println!("{} {}\t{} ({})", mark, i, sink.description, sink.kind);
+    }
+    Ok(())
+}
+
+/// `plentysound volume <value>`: set the global volume, as a factor
+/// (`0.8`) or a percentage (`80%`).
+pub fn send_volume(value: Option<&str>) -> Result<()> {
+    const USAGE: &str = "Usage: plentysound volume <0.8|80%>";
+    let value = value.context(USAGE)?;
+    let volume = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().map(|p| p / 100.0),
+        None => value.parse::<f32>(),
+    }
+    .ok()
+    .context(USAGE)?;
+    let (mut stream, _) = open_session("volume")?;
+    for event in request(&mut stream, ClientCommand::SetVolume(volume))? {
+        match event {
+            DaemonEvent::CommandResult(result) if result.is_error() => {
+                anyhow::bail!("{}", result.message())
+            }
+            DaemonEvent::VolumeChanged(volume) => {
+                println!("Volume {:.0}%", volume * 100.0);
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+    anyhow::bail!("The daemon did not take the volume")
+}
+
+/// `plentysound status [--json]`: what is playing, where, and the
+/// detector. As JSON it is the whole `DaemonState`, for status bars.
+pub fn print_status(args: &[String]) -> Result<()> {
+    let json = json_flag(args, "Usage: plentysound status [--json]")?;
+    let (mut stream, mut state) = open_session("status")?;
+    if json {
+        // The greeting carries only the first page of the library
+        state.songs = fetch_all_songs(&mut stream, state.clone())?;
+        println!("{}", status_json(&state)?);
+        return Ok(());
+    }
+    let playing = match &state.now_playing {
+        Some(name) if state.paused => format!("{} (paused)", name),
+        Some(name) => name.clone(),
+        None => "nothing".to_string(),
+    };
+    println!("Now playing: {}", playing);
+    let sink = state
+        .sinks
+        .get(state.selected_sink)
+        .map_or("none", |s| s.description.as_str());
+    println!("Sink: {}", sink);
+    println!("Volume: {:.0}%", state.volume * 100.0);
+    #[cfg(feature = "transcriber")]
+    {
+        let detector = match &state.word_detector_status {
+            WordDetectorStatus::Unavailable => "unavailable".to_string(),
+            WordDetectorStatus::Downloading => "downloading model".to_string(),
+            WordDetectorStatus::DownloadFailed(e) => format!("download failed: {}", e),
+            WordDetectorStatus::Ready => "stopped".to_string(),
+            WordDetectorStatus::Running => match &state.detector_source {
+                Some(source) => format!("listening to {}", source),
+                None => "running".to_string(),
+            },
+        };
+        println!("Detector: {}", detector);
+    }
+    Ok(())
+}
+
+/// `status --json` output: one line holding one object.
+fn status_json(state: &DaemonState) -> Result<String> {
+    Ok(serde_json::to_string(state)?)
+}
+
+/// The whole library: the songs in the snapshot plus the remaining pages.
+fn fetch_all_songs(stream: &mut UnixStream, state: DaemonState) -> Result<Vec<SongInfo>> {
+    let mut songs = state.songs;
+    while songs.len() < state.songs_total {
+        let offset = songs.len();
+        let cmd = ClientCommand::GetSongs {
+            offset,
+            limit: SONG_PAGE_LIMIT,
+        };
+        let page = request(stream, cmd)?
+            .into_iter()
+            .find_map(|event| match event {
+                DaemonEvent::Songs {
+                    revision, songs, ..
+                } => Some((revision, songs)),
+                _ => None,
+            });
+        let Some((revision, page)) = page else {
+            anyhow::bail!("The daemon sent no songs");
+        };
+        if revision != state.songs_revision {
+            anyhow::bail!("The library changed while reading it; try again");
+        }
+        if page.is_empty() {
+            break;
+        }
+        songs.extend(page);
+    }
+    Ok(songs)
+}
+
+/// Send `cmd` as a request and wait for its reply, skipping the broadcasts
+/// meant for everyone.
+fn request(stream: &mut UnixStream, cmd: ClientCommand) -> Result<Vec<DaemonEvent>> {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    let request_id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    send_message(
+        stream,
+        &ClientCommand::Request {
+            request_id,
+            command: Box::new(cmd),
+        },
+    )?;
+    loop {
+        let event: DaemonEvent = recv_message(stream).context("No reply from daemon")?;
+        match event {
+            DaemonEvent::Reply {
+                request_id: id,
+                events,
+            } if id == request_id => return Ok(events),
+            DaemonEvent::Shutdown => anyhow::bail!("The daemon stopped"),
+            _ => {}
+        }
+    }
+}
+
+/// Send `cmd` and return its `CommandResult`, failing on errors.
+fn command_outcome(stream: &mut UnixStream, cmd: ClientCommand) -> Result<String> {
+    let result = request(stream, cmd)?
+        .into_iter()
+        .find_map(|event| match event {
+            DaemonEvent::CommandResult(result) => Some(result),
+            _ => None,
+        });
+    let result = result.context("The daemon sent no result")?;
+    if result.is_error() {
+        anyhow::bail!("{}", result.message());
+    }
+    Ok(result.message().to_string())
+}
+
+/// Print one item's outcome; returns 1 if it failed.
+fn report_item(item: &str, outcome: Result<String>) -> usize {
+    match outcome {
+        Ok(msg) => {
+            println!("{}: {}", item, msg);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}: {:#}", item, e);
+            1
+        }
+    }
+}
+
+/// Library index for a path (compared canonicalized, so relative paths
+/// work) or, when no such file exists, a numeric index as `list` prints it.
+fn resolve_song(songs: &[SongInfo], item: &str) -> Option<usize> {
+    if let Ok(target) = std::fs::canonicalize(item) {
+        return songs.iter().position(|s| {
+            s.path == item || std::fs::canonicalize(&s.path).is_ok_and(|p| p == target)
+        });
+    }
+    if let Some(idx) = songs.iter().position(|s| s.path == item) {
+        return Some(idx);
+    }
+    item.parse().ok().filter(|&idx| idx < songs.len())
+}
+
+/// `plentysound trigger <word>`: fire a word binding as if it was spoken.
+#[cfg(feature = "transcriber")]
+pub fn send_trigger(word: Option<&str>) -> Result<()> {
+    let word = word.context("Usage: plentysound trigger <word>")?;
+    send_and_report("trigger", ClientCommand::SimulateWord(word.to_string()))
+}
+
+/// `plentysound capture <file.wav>` / `plentysound capture stop`.
+#[cfg(feature = "transcriber")]
+pub fn send_capture(arg: Option<&str>) -> Result<()> {
+    let cmd = match arg.context("Usage: plentysound capture <file.wav>|stop")? {
+        "stop" => ClientCommand::StopDetectorCapture,
+        // The daemon runs elsewhere; resolve relative paths here
+        path => ClientCommand::StartDetectorCapture(
+            std::env::current_dir()?.join(path).display().to_string(),
+        ),
+    };
+    send_and_report("capture", cmd)
+}
+
+/// `plentysound log-level <error|warn|info|debug>`: change how much the
+/// running daemon logs, without restarting it.
+pub fn send_log_level(arg: Option<&str>) -> Result<()> {
+    let level = arg
+        .and_then(LogLevel::from_name)
+        .context("Usage: plentysound log-level <error|warn|info|debug>")?;
+    send_and_report("log-level", ClientCommand::SetLogLevel(level))
+}
+
+/// Send one command and print the daemon's `CommandResult`, failing on errors.
+fn send_and_report(name: &str, cmd: ClientCommand) -> Result<()> {
+    let (mut stream, _) = open_session(name)?;
+    println!("{}", command_outcome(&mut stream, cmd)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{EqGains, FinishReason, FxSettings, TagColor};
+    use ratatui::backend::TestBackend;
+    use ratatui::style::Color;
+    use ratatui::{TerminalOptions, Viewport};
+    use std::sync::{Arc, Mutex};
+
+    /// Writer standing in for stdout; clones share the captured bytes.
+    #[derive(Clone, Default)]
+    struct FakeTty(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for FakeTty {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn fake_guard(tty: &FakeTty) -> TerminalGuard<FakeTty> {
+        let terminal = Terminal::with_options(
+            CrosstermBackend::new(tty.clone()),
+            TerminalOptions {
+                viewport: Viewport::Fixed(Rect::new(0, 0, 20, 5)),
+            },
+        )
+        .unwrap();
+        TerminalGuard {
+            terminal,
+            raw_mode: false,
+        }
+    }
+
+    fn drop_output(f: impl FnOnce(&FakeTty)) -> String {
+        let tty = FakeTty::default();
+        f(&tty);
+        let bytes = tty.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    fn sink(id: u32, kind: &str) -> SinkInfo {
+        SinkInfo {
+            id,
+            name: format!("node{id}"),
+            description: format!("Node {id}"),
+            kind: kind.to_string(),
+            is_default: false,
+        }
+    }
+
+    #[test]
+    fn sink_rows_group_by_kind_under_headers() {
+        let sinks = vec![
+            sink(1, "Input"),
+            sink(2, "Output"),
+            sink(3, "Input"),
+            sink(4, "Output"),
+        ];
+        assert_eq!(
+            sink_rows(&sinks),
+            vec![
+                SinkRow::Header("Outputs"),
+                SinkRow::Sink(1),
+                SinkRow::Sink(3),
+                SinkRow::Header("Inputs / App streams"),
+                SinkRow::Sink(0),
+                SinkRow::Sink(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn sink_rows_skip_empty_groups_and_microphones_and_keep_unknown_kinds() {
+        let sinks = vec![sink(1, "Input"), sink(2, "Bridge"), sink(3, "Source")];
+        assert_eq!(
+            sink_rows(&sinks),
+            vec![
+                SinkRow::Header("Inputs / App streams"),
+                SinkRow::Sink(0),
+                SinkRow::Header("Other"),
+                SinkRow::Sink(1),
+            ]
+        );
+        assert!(sink_rows(&[]).is_empty());
+    }
+
+    fn key(app: &mut ClientApp, code: KeyCode) {
+        app.handle_event(Event::Key(KeyEvent::from(code)));
+    }
+
+    /// A library entry under /tmp with nothing but its name set.
+    fn song(name: &str) -> SongInfo {
+        SongInfo {
+            path: format!("/tmp/{name}"),
+            name: name.to_string(),
+            undecodable: false,
+            missing: false,
+            tag_color: None,
+            volume: None,
+            duration: None,
+        }
+    }
+
+    /// A client greeted with `state`, and the daemon's end of its socket
+    /// with the client's `Identify` already taken off.
+    fn test_client(state: DaemonState) -> (ClientApp, UnixStream) {
+        let (client, mut daemon) = UnixStream::pair().unwrap();
+        send_message(&mut daemon, &DaemonEvent::State(Box::new(state))).unwrap();
+        let app = ClientApp::new(client).unwrap();
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::Identify { .. }
+        ));
+        (app, daemon)
+    }
+
+    #[test]
+    fn setup_wizard_picks_output_and_finishes_after_skips() {
+        let state = DaemonState {
+            sinks: vec![sink(1, "Input"), sink(2, "Output"), sink(3, "Output")],
+            first_run: true,
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        assert_eq!(app.setup, Some(SetupStep::PickSink { selected: 0 }));
+
+        key(&mut app, KeyCode::Down);
+        key(&mut app, KeyCode::Enter);
+        assert_eq!(app.setup, Some(SetupStep::AddSongs));
+        assert!(app.file_browser.is_some());
+
+        // Esc skips every remaining step, the last one finishes
+        while app.setup.is_some() {
+            key(&mut app, KeyCode::Esc);
+        }
+        assert!(app.file_browser.is_none());
+
+        let cmd: ClientCommand = recv_message(&mut daemon).unwrap();
+        assert!(matches!(cmd, ClientCommand::SelectSink(2)));
+        let cmd: ClientCommand = recv_message(&mut daemon).unwrap();
+        assert!(matches!(cmd, ClientCommand::FinishSetup));
+    }
+
+    #[test]
+    fn songs_resolve_by_path_or_index() {
+        let dir = crate::app::tests::scratch_dir("resolve");
+        let horn = crate::app::tests::write_file(&dir, "horn.wav", b"horn");
+        let song = |path: &std::path::Path| SongInfo {
+            path: path.display().to_string(),
+            name: String::new(),
+            undecodable: false,
+            missing: false,
+            tag_color: None,
+            volume: None,
+            duration: None,
+        };
+        // Stored through a non-canonical path
+        let songs = vec![
+            song(&dir.join("missing.wav")),
+            song(&dir.join(".").join("horn.wav")),
+        ];
+
+        assert_eq!(resolve_song(&songs, horn.to_str().unwrap()), Some(1));
+        assert_eq!(
+            resolve_song(&songs, &dir.join("missing.wav").display().to_string()),
+            Some(0)
+        );
+        assert_eq!(resolve_song(&songs, "1"), Some(1));
+        assert_eq!(resolve_song(&songs, "2"), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn play_matches_an_index_a_name_or_a_unique_prefix() {
+        let songs = vec![song("Airhorn"), song("Air"), song("Applause"), song("Boo")];
+
+        assert_eq!(match_song(&songs, "3").unwrap(), 3);
+        assert_eq!(match_song(&songs, "boo").unwrap(), 3);
+        assert_eq!(match_song(&songs, "APP").unwrap(), 2);
+        // A full name wins over the longer names it starts
+        assert_eq!(match_song(&songs, "air").unwrap(), 1);
+        let ambiguous = match_song(&songs, "a").unwrap_err().to_string();
+        assert!(ambiguous.contains("0\tAirhorn") && ambiguous.contains("2\tApplause"));
+        assert!(match_song(&songs, "crickets").is_err());
+    }
+
+    #[test]
+    fn status_json_reads_back_as_the_state() {
+        let state = DaemonState {
+            sinks: vec![sink(3, "Output")],
+            selected_sink: 0,
+            volume: 0.8,
+            now_playing: Some("horn".to_string()),
+            paused: true,
+            queue: vec!["/sounds/boo.wav".to_string()],
+            ..Default::default()
+        };
+        let printed = status_json(&state).unwrap();
+        assert!(!printed.contains('\n'));
+        let read: DaemonState = serde_json::from_str(&printed).unwrap();
+        assert_eq!(serde_json::to_string(&read).unwrap(), printed);
+        assert_eq!(read.now_playing.as_deref(), Some("horn"));
+        assert_eq!(read.sinks[0].description, "Node 3");
+
+        assert!(json_flag(&["--json".to_string()], "usage").unwrap());
+        assert!(!json_flag(&[], "usage").unwrap());
+        assert!(json_flag(&["-j".to_string()], "usage").is_err());
+    }
+
+    fn layout_rects(layout: &AppLayout) -> Vec<Rect> {
+        vec![
+            layout.sinks_area,
+            layout.volume_area,
+            layout.audio_fx_area,
+            layout.add_button_area,
+            #[cfg(feature = "transcriber")]
+            layout.word_detector_button_area,
+            layout.songs_area,
+            layout.queue_area,
+            #[cfg(feature = "transcriber")]
+            layout.word_bindings_area,
+        ]
+    }
+
+    #[test]
+    fn layout_stays_in_bounds_at_any_size() {
+        let long = SongInfo {
+            tag_color: Some("green".to_string()),
+            ..song("a-song-with-a-rather-long-file-name.mp3")
+        };
+        let state = DaemonState {
+            sinks: vec![sink(1, "Input"), sink(2, "Output")],
+            songs: vec![long],
+            songs_total: 1,
+            ..Default::default()
+        };
+        let (mut app, _daemon) = test_client(state);
+
+        let widths = (1..=140)
+            .step_by(7)
+            .chain([crate::ui::MIN_WIDTH - 1, crate::ui::MIN_WIDTH]);
+        for width in widths {
+            let heights = (1..=45).step_by(2).chain([crate::ui::MIN_HEIGHT - 1]);
+            for height in heights {
+                for setup in [None, Some(SetupStep::Finish)] {
+                    app.setup = setup;
+                    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+                    let frame = terminal.draw(|f| crate::ui::draw(f, &mut app)).unwrap();
+                    let area = frame.area;
+                    for r in layout_rects(&app.layout) {
+                        assert!(
+                            r.right() <= area.right() && r.bottom() <= area.bottom(),
+                            "{r:?} outside {area:?}"
+                        );
+                    }
+                    let text: String = frame.buffer.content().iter().map(|c| c.symbol()).collect();
+                    let too_small = width < crate::ui::MIN_WIDTH || height < crate::ui::MIN_HEIGHT;
+                    if too_small && width >= 45 && height >= 3 {
+                        assert!(text.contains("terminal too small"), "{width}x{height}");
+                    }
+                    if !too_small {
+                        assert!(!text.contains("terminal too small"), "{width}x{height}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn resize_forces_a_full_redraw() {
+        let (mut app, _daemon) = test_client(DaemonState::default());
+        assert!(!app.needs_clear);
+        app.handle_event(Event::Resize(45, 10));
+        assert!(app.needs_clear);
+    }
+
+    #[test]
+    fn playback_errors_replace_the_help_line() {
+        let (mut app, mut daemon) = test_client(DaemonState::default());
+        let message = "Failed to decode foo.mp3: end of stream".to_string();
+        let error = DaemonEvent::Error {
+            context: "decode".into(),
+            message: message.clone(),
+        };
+        send_message(&mut daemon, &error).unwrap();
+        // The song's own end comes after and keeps the cause on screen
+        let ended = DaemonEvent::SongEnded {
+            song: "foo.mp3".into(),
+            reason: FinishReason::Error,
+        };
+        send_message(&mut daemon, &ended).unwrap();
+        app.poll_daemon_events();
+        assert_eq!(app.status_message, Some(message));
+    }
+
+    #[test]
+    fn tags_cycle_through_the_daemons_palette() {
+        let tag = |name: &str, color: &str| TagColor {
+            name: name.into(),
+            color: color.into(),
+        };
+        let state = DaemonState {
+            songs: vec![SongInfo {
+                tag_color: Some("work".to_string()),
+                ..song("horn.wav")
+            }],
+            songs_total: 1,
+            tag_palette: vec![tag("work", "#ff8800"), tag("home", "light cyan")],
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        let palette = &app.state.tag_palette;
+        assert_eq!(
+            crate::theme::tag_color(palette, "work"),
+            Color::Rgb(0xff, 0x88, 0)
+        );
+        assert_eq!(crate::theme::tag_color(palette, "green"), Color::Gray);
+
+        app.focus = Panel::Songs;
+        key(&mut app, KeyCode::Char('c'));
+        let cmd = recv_message(&mut daemon).unwrap();
+        let home = Some("home".to_string());
+        let sent = match cmd {
+            ClientCommand::SetSongTag {
+                index: 0,
+                tag_color,
+            } => tag_color,
+            _ => panic!("expected a tag change"),
+        };
+        assert_eq!(sent, home);
+
+        let filters: Vec<Option<String>> = (0..3)
+            .map(|_| {
+                key(&mut app, KeyCode::Char('F'));
+                app.tag_filter.clone()
+            })
+            .collect();
+        assert_eq!(filters, [Some("work".to_string()), home, None]);
+
+        // Without a configured palette the built-in one applies
+        app.state.tag_palette.clear();
+        assert_eq!(
+            crate::theme::next_tag(&app.state.tag_palette, None),
+            Some("green")
+        );
+    }
+
+    #[test]
+    fn small_updates_change_only_their_part_of_the_state() {
+        let tagged = |tag: Option<&str>| SongInfo {
+            tag_color: tag.map(str::to_string),
+            ..song("horn.wav")
+        };
+        let state = DaemonState {
+            songs: vec![tagged(None)],
+            songs_total: 1,
+            now_playing: Some("horn.wav".to_string()),
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+
+        send_message(&mut daemon, &DaemonEvent::VolumeChanged(0.4)).unwrap();
+        let mut fx = FxSettings {
+            comfort_noise: 0.2,
+            noise_kind: NoiseKind::Pink,
+            eq: EqGains::default(),
+            fade_secs: 1.5,
+            limiter: true,
+        };
+        fx.eq.high = 0.5;
+        send_message(&mut daemon, &DaemonEvent::FxChanged(fx)).unwrap();
+        let songs = DaemonEvent::SongsUpdated {
+            revision: 3,
+            songs: vec![tagged(Some("green"))],
+        };
+        send_message(&mut daemon, &songs).unwrap();
+        app.poll_daemon_events();
+
+        assert_eq!(app.volume(), 0.4);
+        assert_eq!(
+            (app.comfort_noise(), app.noise_kind()),
+            (0.2, NoiseKind::Pink)
+        );
+        assert_eq!(
+            (app.eq_gain(EqBand::High), app.fade_secs(), app.limiter()),
+            (0.5, 1.5, true)
+        );
+        assert_eq!(app.songs()[0].tag_color.as_deref(), Some("green"));
+        assert_eq!(app.state.songs_revision, 3);
+        assert_eq!(app.now_playing(), Some("horn.wav"));
+    }
+
+    #[test]
+    fn progress_gauge_follows_playback() {
+        let (mut app, mut daemon) = test_client(DaemonState::default());
+        let screen = |app: &mut ClientApp| {
+            let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+            let frame = terminal.draw(|f| crate::ui::draw(f, app)).unwrap();
+            frame
+                .buffer
+                .content()
+                .iter()
+                .map(|c| c.symbol())
+                .collect::<String>()
+        };
+
+        send_message(
+            &mut daemon,
+            &DaemonEvent::NowPlaying(Some("horn.wav".into())),
+        )
+        .unwrap();
+        let progress = DaemonEvent::PlaybackProgress {
+            position_secs: 72.4,
+            total_secs: 95.0,
+        };
+        send_message(&mut daemon, &progress).unwrap();
+        app.poll_daemon_events();
+        assert_eq!(app.progress, Some((72.4, 95.0)));
+        assert!(screen(&mut app).contains("1:12 / 1:35"));
+
+        // The song this one replaced ending doesn't touch its progress
+        let replaced = FinishReason::Replaced;
+        let ended = DaemonEvent::SongEnded {
+            song: "intro.wav".into(),
+            reason: replaced,
+        };
+        send_message(&mut daemon, &ended).unwrap();
+        app.poll_daemon_events();
+        assert_eq!(app.progress, Some((72.4, 95.0)));
+
+        send_message(&mut daemon, &DaemonEvent::PlaybackFinished).unwrap();
+        app.poll_daemon_events();
+        assert_eq!(app.progress, None);
+        assert!(!screen(&mut app).contains(" / "));
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn detector_sources_include_microphones() {
+        let sinks = vec![sink(1, "Output"), sink(2, "Input"), sink(3, "Source")];
+        let state = DaemonState {
+            sinks,
+            ..Default::default()
+        };
+        let (mut app, _daemon) = test_client(state);
+        app.transcriber_overlay = Some(TranscriberOverlay::SelectSource { selected: 0 });
+        key(&mut app, KeyCode::Down);
+        key(&mut app, KeyCode::Enter);
+        assert_eq!(app.detector_source_node, Some(3));
+        assert!(matches!(
+            app.transcriber_overlay,
+            Some(TranscriberOverlay::SelectOutput { .. })
+        ));
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn missing_vosk_library_is_shown_instead_of_offering_a_download() {
+        let state = DaemonState {
+            vosk_missing: Some("Cannot load libvosk.so: not found".to_string()),
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+
+        let mut terminal = Terminal::new(TestBackend::new(140, 45)).unwrap();
+        let frame = terminal.draw(|f| crate::ui::draw(f, &mut app)).unwrap();
+        let text: String = frame.buffer.content().iter().map(|c| c.symbol()).collect();
+        assert!(text.contains("Vosk library missing"));
+
+        // Skip whatever the client sent while starting up
+        daemon.set_nonblocking(true).unwrap();
+        while recv_message::<ClientCommand>(&mut daemon).is_ok() {}
+
+        app.focus = Panel::WordDetectorButton;
+        key(&mut app, KeyCode::Enter);
+        let hint = app.status_message.clone().unwrap_or_default();
+        assert!(hint.contains("libvosk.so: not found"), "{hint}");
+        assert!(recv_message::<ClientCommand>(&mut daemon).is_err());
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn long_binding_lists_scroll_and_keep_global_indices() {
+        use crate::protocol::WordMapping;
+        // Every other binding belongs to the unselected song
+        let word_mappings = (0..400)
+            .map(|i| {
+                let name = if i % 2 == 0 { "a.wav" } else { "b.wav" };
+                WordMapping {
+                    word: format!("word{i}"),
+                    song_names: vec![name.to_string()],
+                    song_paths: vec![format!("/tmp/{name}")],
+                    selection: Default::default(),
+                    source_description: String::new(),
+                    output_description: String::new(),
+                    only_from_source: None,
+                    stats: Default::default(),
+                    enabled: true,
+                    action: Default::default(),
+                }
+            })
+            .collect();
+        let state = DaemonState {
+            songs: vec![song("a.wav"), song("b.wav")],
+            songs_total: 2,
+            word_mappings,
+            word_detector_status: WordDetectorStatus::Ready,
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        assert_eq!(app.binding_count(), 200);
+
+        app.focus = Panel::WordBindings;
+        for _ in 0..150 {
+            app.handle_event(Event::Key(KeyEvent::from(KeyCode::Down)));
+        }
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        let frame = terminal.draw(|f| crate::ui::draw(f, &mut app)).unwrap();
+        let text: String = frame.buffer.content().iter().map(|c| c.symbol()).collect();
+        assert!(text.contains("word300 "), "selection scrolled into view");
+        assert!(!text.contains("word0 "));
+        assert!(app.word_binding_scroll > 140);
+
+        // Clicking the first visible row picks that binding, not the list's first
+        let area = app.layout.word_bindings_area;
+        let scroll = app.word_binding_scroll;
+        app.handle_event(Event::Mouse(MouseEvent {
+            kind: crossterm::event::MouseEventKind::Down(MouseButton::Left),
+            column: area.x + 2,
+            row: area.y + 1,
+            modifiers: crossterm::event::KeyModifiers::NONE,
+        }));
+        assert_eq!(app.selected_word_binding, scroll);
+
+        app.handle_event(Event::Key(KeyEvent::from(KeyCode::Delete)));
+        let cmd = recv_message(&mut daemon).unwrap();
+        assert!(
+            matches!(cmd, ClientCommand::RemoveWordMapping(i) if i == 2 * scroll),
+            "{cmd:?}"
+        );
+    }
+
+    fn assert_restored(out: &str) {
+        assert!(
+            out.contains("\x1b[?1049l"),
+            "alternate screen left: {out:?}"
+        );
+        assert!(
+            out.contains("\x1b[?1000l"),
+            "mouse capture disabled: {out:?}"
+        );
+        assert!(out.contains("\x1b[?25h"), "cursor shown: {out:?}");
+    }
+
+    #[test]
+    fn guard_restores_terminal_on_drop() {
+        let out = drop_output(|tty| drop(fake_guard(tty)));
+        assert_restored(&out);
+    }
+
+    #[test]
+    fn guard_restores_terminal_when_draw_fails() {
+        let out = drop_output(|tty| {
+            let run = || -> Result<()> {
+                let mut guard = fake_guard(tty);
+                guard.terminal.draw(|_| {})?;
+                anyhow::bail!("stdout closed")
+            };
+            assert!(run().is_err());
+        });
+        assert_restored(&out);
+    }
+
+    #[test]
+    fn waveforms_are_kept_only_for_the_song_asked_about() {
+        let state = DaemonState {
+            songs: vec![song("a.wav"), song("b.wav")],
+            songs_total: 2,
+            selected_song: 1,
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        app.focus = Panel::Songs;
+
+        key(&mut app, KeyCode::Char('v'));
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::GetWaveform(1)
+        ));
+
+        // Another client's envelope is broadcast too, but not shown here
+        let waveform = |path: &str| DaemonEvent::Waveform {
+            path: path.to_string(),
+            peaks: vec![9; 4],
+        };
+        send_message(&mut daemon, &waveform("/tmp/a.wav")).unwrap();
+        send_message(&mut daemon, &waveform("/tmp/b.wav")).unwrap();
+        app.poll_daemon_events();
+        assert_eq!(app.selected_waveform(), Some(&[9u8; 4][..]));
+        assert_eq!(
+            app.waveform.as_ref().map(|(path, _)| path.as_str()),
+            Some("/tmp/b.wav")
+        );
+
+        // Hidden while another song is highlighted, and `v` on its own song hides it
+        app.state.selected_song = 0;
+        assert_eq!(app.selected_waveform(), None);
+        app.state.selected_song = 1;
+        key(&mut app, KeyCode::Char('v'));
+        assert_eq!(app.waveform, None);
+    }
+
+    #[test]
+    fn typing_a_filter_narrows_the_songs_and_plays_a_match() {
+        let names = ["hot rain.wav", "bell.wav", "horn2.wav", "Airhorn.wav"];
+        let state = DaemonState {
+            songs: names.iter().map(|name| song(name)).collect(),
+            songs_total: 4,
+            selected_song: 1,
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        app.focus = Panel::Songs;
+
+        key(&mut app, KeyCode::Char('/'));
+        for c in "HORN".chars() {
+            key(&mut app, KeyCode::Char(c));
+        }
+        // Containing the text beats having its letters in order
+        assert_eq!(app.song_order(), [2, 3, 0]);
+        // The best match is highlighted as the text grows, by its real index
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::SelectSong(0)
+        ));
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::SelectSong(2)
+        ));
+        key(&mut app, KeyCode::Down);
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::SelectSong(3)
+        ));
+        key(&mut app, KeyCode::Enter);
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::Play
+        ));
+
+        // Done typing: keys act on the panel again, with the list still narrowed
+        assert!(app
+            .song_filter
+            .as_ref()
+            .is_some_and(|filter| !filter.editing));
+        assert_eq!(app.song_order(), [2, 3, 0]);
+        key(&mut app, KeyCode::Esc);
+        assert!(app.song_filter.is_none());
+        assert_eq!(app.song_order(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn page_keys_move_a_panel_height_and_stop_at_the_ends() {
+        let state = DaemonState {
+            songs: (0..50).map(|i| song(&format!("clip{i}.wav"))).collect(),
+            songs_total: 50,
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        app.focus = Panel::Songs;
+        // Ten rows inside the border
+        app.layout.songs_area = Rect::new(0, 0, 40, 12);
+
+        key(&mut app, KeyCode::PageDown);
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::SelectSong(10)
+        ));
+        key(&mut app, KeyCode::End);
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::SelectSong(49)
+        ));
+        // Already at the end: nothing to send
+        key(&mut app, KeyCode::PageDown);
+        key(&mut app, KeyCode::PageUp);
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::SelectSong(39)
+        ));
+        key(&mut app, KeyCode::Home);
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::SelectSong(0)
+        ));
+    }
+
+    #[test]
+    fn shift_arrows_move_the_highlighted_song_past_its_neighbour() {
+        let state = DaemonState {
+            songs: vec![song("horn.wav"), song("bell.wav"), song("horn2.wav")],
+            songs_total: 3,
+            selected_song: 1,
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        app.focus = Panel::Songs;
+        let shift = |app: &mut ClientApp, code| {
+            app.handle_event(Event::Key(KeyEvent::new(code, KeyModifiers::SHIFT)));
+        };
+
+        shift(&mut app, KeyCode::Up);
+        let cmd = recv_message(&mut daemon).unwrap();
+        assert!(
+            matches!(cmd, ClientCommand::MoveSong { from: 1, to: 0 }),
+            "{cmd:?}"
+        );
+        // With a filter the neighbour is the next song shown, not the next index
+        app.song_filter = Some(SongFilter {
+            input: TextInput::new(),
+            editing: false,
+        });
+        for c in "horn".chars() {
+            app.song_filter.as_mut().unwrap().input.push_char(c);
+        }
+        app.state.selected_song = 0;
+        shift(&mut app, KeyCode::Down);
+        let cmd = recv_message(&mut daemon).unwrap();
+        assert!(
+            matches!(cmd, ClientCommand::MoveSong { from: 0, to: 2 }),
+            "{cmd:?}"
+        );
+    }
+
+    #[test]
+    fn the_queue_panel_reorders_removes_and_clears_entries() {
+        let state = DaemonState {
+            queue: ["/tmp/a.wav", "/tmp/b.wav", "/tmp/c.wav"]
+                .map(str::to_string)
+                .into(),
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        assert!(!app.panel_shown(Panel::Queue));
+        key(&mut app, KeyCode::Char('Q'));
+        assert_eq!(app.focus, Panel::Queue);
+
+        app.handle_event(Event::Key(KeyEvent::new(
+            KeyCode::Down,
+            KeyModifiers::SHIFT,
+        )));
+        let cmd = recv_message(&mut daemon).unwrap();
+        assert!(
+            matches!(cmd, ClientCommand::ReorderQueue { from: 0, to: 1 }),
+            "{cmd:?}"
+        );
+        // The entry stays highlighted where it went
+        assert_eq!(app.selected_queue, 1);
+        assert_eq!(app.state.queue[1], "/tmp/a.wav");
+
+        key(&mut app, KeyCode::Char('d'));
+        assert!(app.pending_delete.is_none());
+        let cmd = recv_message(&mut daemon).unwrap();
+        assert!(matches!(cmd, ClientCommand::RemoveFromQueue(1)), "{cmd:?}");
+        key(&mut app, KeyCode::Char('C'));
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::ClearQueue
+        ));
+
+        send_message(&mut daemon, &DaemonEvent::QueueUpdated(vec![])).unwrap();
+        app.poll_daemon_events();
+        assert_eq!(app.selected_queue, 0);
+        key(&mut app, KeyCode::Char('Q'));
+        assert_eq!(app.focus, Panel::Songs);
+    }
+
+    #[test]
+    fn a_folder_is_added_in_one_batch_from_the_file_browser() {
+        let (mut app, mut daemon) = test_client(DaemonState::default());
+
+        let dir = crate::app::tests::scratch_dir("add-folder");
+        let pack = dir.join("pack");
+        std::fs::create_dir_all(pack.join("extra")).unwrap();
+        for file in ["a.wav", "b.MP3", "notes.txt", ".hidden.wav", "extra/c.flac"] {
+            std::fs::write(pack.join(file), b"").unwrap();
+        }
+        let open_at = |app: &mut ClientApp| {
+            let mut fb = FileBrowser::new();
+            fb.current_dir = dir.clone();
+            fb.refresh();
+            app.file_browser = Some(fb);
+        };
+        let names = |cmd| match cmd {
+            ClientCommand::AddSongs(paths) => paths
+                .iter()
+                .map(|p| p.strip_prefix(pack.to_str().unwrap()).unwrap().to_string())
+                .collect::<Vec<_>>(),
+            other => panic!("expected a batch, got {other:?}"),
+        };
+
+        // The highlighted folder goes, not the one being browsed
+        open_at(&mut app);
+        key(&mut app, KeyCode::Char('a'));
+        assert!(app.file_browser.is_none());
+        assert_eq!(
+            names(recv_message(&mut daemon).unwrap()),
+            ["/a.wav", "/b.MP3"]
+        );
+        open_at(&mut app);
+        key(&mut app, KeyCode::Char('A'));
+        let all = names(recv_message(&mut daemon).unwrap());
+        assert_eq!(all, ["/a.wav", "/b.MP3", "/extra/c.flac"]);
+
+        // Nothing to add keeps the browser open
+        let mut fb = FileBrowser::new();
+        fb.current_dir = dir.join("empty");
+        fb.refresh();
+        app.file_browser = Some(fb);
+        key(&mut app, KeyCode::Char('a'));
+        assert!(app.file_browser.is_some());
+        assert!(app
+            .status_message
+            .as_deref()
+            .is_some_and(|m| m.starts_with("No audio files")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn deleting_a_song_waits_for_a_yes() {
+        let state = DaemonState {
+            songs: vec![song("a.wav"), song("b.wav")],
+            songs_total: 2,
+            selected_song: 1,
+            confirm_delete: true,
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        app.focus = Panel::Songs;
+
+        key(&mut app, KeyCode::Char('d'));
+        assert_eq!(
+            app.pending_delete,
+            Some(PendingDelete::Song("/tmp/b.wav".to_string()))
+        );
+        // Other keys wait on the answer instead of reaching the panel
+        key(&mut app, KeyCode::Down);
+        key(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.pending_delete, None);
+        assert_eq!(app.focus, Panel::Songs);
+
+        key(&mut app, KeyCode::Delete);
+        key(&mut app, KeyCode::Char('y'));
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::RemoveSong(1)
+        ));
+
+        // The library changing under the dialog doesn't move what goes
+        key(&mut app, KeyCode::Char('d'));
+        app.state.songs.insert(0, song("0.wav"));
+        key(&mut app, KeyCode::Char('y'));
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::RemoveSong(2)
+        ));
+
+        // With the config's escape hatch, `d` deletes straight away
+        app.state.confirm_delete = false;
+        key(&mut app, KeyCode::Char('d'));
+        assert!(app.pending_delete.is_none());
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::RemoveSong(1)
+        ));
+    }
+
+    #[test]
+    fn the_wheel_steps_whatever_is_under_it() {
+        let state = DaemonState {
+            songs: vec![song("a.wav"), song("b.wav")],
+            songs_total: 2,
+            volume: 1.0,
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        app.layout.volume_area = Rect::new(0, 0, 20, 3);
+        app.layout.songs_area = Rect::new(20, 0, 40, 10);
+        let wheel = |app: &mut ClientApp, kind, column| {
+            app.handle_event(Event::Mouse(MouseEvent {
+                kind,
+                column,
+                row: 1,
+                modifiers: crossterm::event::KeyModifiers::NONE,
+            }));
+        };
+
+        wheel(&mut app, MouseEventKind::ScrollDown, 30);
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::SelectSong(1)
+        ));
+        // Past the last song, and over nothing at all: no commands
+        wheel(&mut app, MouseEventKind::ScrollDown, 30);
+        wheel(&mut app, MouseEventKind::ScrollDown, 70);
+        wheel(&mut app, MouseEventKind::ScrollUp, 5);
+        let ClientCommand::SetVolume(volume) = recv_message(&mut daemon).unwrap() else {
+            panic!("wheel over the volume bar");
+        };
+        assert!((volume - 1.05).abs() < 1e-6, "{volume}");
+        assert_eq!(app.focus, Panel::Sinks, "the wheel leaves focus alone");
+
+        app.file_browser = Some(FileBrowser {
+            current_dir: "/tmp".into(),
+            entries: (0..3)
+                .map(|i| crate::filebrowser::Entry {
+                    name: format!("{i}.wav"),
+                    path: format!("/tmp/{i}.wav").into(),
+                    is_dir: false,
+                })
+                .collect(),
+            selected: 0,
+            scroll: 0,
+        });
+        app.layout.file_browser_area = Rect::new(10, 0, 40, 10);
+        for _ in 0..5 {
+            wheel(&mut app, MouseEventKind::ScrollDown, 30);
+        }
+        assert_eq!(app.file_browser.as_ref().unwrap().selected, 2);
+        wheel(&mut app, MouseEventKind::ScrollUp, 5);
+        assert_eq!(app.file_browser.as_ref().unwrap().selected, 2);
+    }
+
+    #[test]
+    fn tui_waits_out_a_daemon_restart_and_resumes() {
+        let state = DaemonState {
+            now_playing: Some("horn".to_string()),
+            ..Default::default()
+        };
+        let (mut app, mut daemon) = test_client(state);
+        send_message(&mut daemon, &DaemonEvent::Shutdown).unwrap();
+        drop(daemon);
+
+        app.poll_daemon_events();
+        assert!(app.disconnected() && !app.should_quit);
+        assert_eq!(app.state.now_playing, None);
+        key(&mut app, KeyCode::Char('r'));
+        assert!(app
+            .status_message
+            .as_deref()
+            .is_some_and(|m| m.contains("Not connected")));
+
+        // The restarted daemon, on a fresh socket
+        let dir = crate::app::tests::scratch_dir("reconnect");
+        let path = dir.join("daemon.sock");
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let restarted = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let state = DaemonState {
+                volume: 0.3,
+                ..Default::default()
+            };
+            send_message(&mut stream, &DaemonEvent::State(Box::new(state))).unwrap();
+            assert!(matches!(
+                recv_message(&mut stream).unwrap(),
+                ClientCommand::Identify { .. }
+            ));
+            stream
+        });
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while app.disconnected() && std::time::Instant::now() < deadline {
+            let path = path.clone();
+            app.poll_reconnect(move || Ok(UnixStream::connect(path)?));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!app.disconnected());
+        assert_eq!(app.state.volume, 0.3);
+        let mut daemon = restarted.join().unwrap();
+        key(&mut app, KeyCode::Char('r'));
+        assert!(matches!(
+            recv_message(&mut daemon).unwrap(),
+            ClientCommand::RefreshSinks
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,52 +1,172 @@
 use crate::app::DaemonApp;
-use crate::protocol::{socket_path, ClientCommand, DaemonEvent, recv_message, send_message};
+use crate::notify::Notifier;
+use crate::protocol::{
+    bind_private, check_peer, send_message, socket_candidates, socket_path, ClientCommand,
+    ClientInfo, DaemonEvent, DaemonStats, FrameReader, LogLevel,
+};
+use crate::tray::NowPlaying;
 use anyhow::{Context, Result};
-use std::os::unix::net::{UnixListener, UnixStream};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::time::Duration;
 
 #[cfg(feature = "transcriber")]
 use crate::protocol::WordDetectorStatus;
 
-pub fn run_daemon() -> Result<()> {
-    let sock_path = socket_path();
+const LOG: crate::log::Logger = crate::log::log_scope("daemon");
 
-    // Check for stale socket
-    if sock_path.exists() {
-        match UnixStream::connect(&sock_path) {
-            Ok(_) => {
+/// A connected client's event channel and how it introduced itself.
+struct Client {
+    id: u64,
+    info: ClientInfo,
+    tx: mpsc::Sender<DaemonEvent>,
+}
+
+type Clients = Arc<Mutex<Vec<Client>>>;
+
+/// The client list, even if a thread panicked while holding it: every
+/// change to it is a single push, retain or field update, so it can't be
+/// left half-written.
+fn lock_clients(client_senders: &Clients) -> MutexGuard<'_, Vec<Client>> {
+    client_senders
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// How long a client has to finish a message once it has started one.
+/// Waiting for the next message has no limit; idle clients are fine.
+const FRAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Commands tagged with the sending client; `None` for the daemon's own threads.
+pub(crate) type TaggedCommand = (Option<u64>, ClientCommand);
+
+/// Exclusive `flock` on the lock file next to the socket, which also holds
+/// the owner's PID. Only its holder may touch the socket; the kernel lets
+/// go of it when the process dies, however it dies.
+struct DaemonLock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl DaemonLock {
+    fn acquire(sock_path: &Path) -> Result<Self> {
+        let path = sock_path.with_extension("lock");
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .mode(0o600)
+                .open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != std::io::ErrorKind::WouldBlock {
+                    return Err(err).with_context(|| format!("Failed to lock {}", path.display()));
+                }
+                let mut pid = String::new();
+                let _ = file.read_to_string(&mut pid);
                 anyhow::bail!(
-                    "Another daemon is already running (socket {} is active)",
-                    sock_path.display()
+                    "Another daemon is already running (pid {}, lock {})",
+                    pid.trim(),
+                    path.display()
                 );
             }
-            Err(_) => {
-                let _ = std::fs::remove_file(&sock_path);
+            // The previous owner may have removed the file between our open
+            // and the lock; a lock on an unlinked file guards nothing
+            let same_file = std::fs::metadata(&path)
+                .is_ok_and(|m| file.metadata().is_ok_and(|f| f.ino() == m.ino()));
+            if !same_file {
+                continue;
             }
+            file.set_len(0)?;
+            write!(file, "{}", std::process::id())?;
+            return Ok(DaemonLock { path, _file: file });
+        }
+    }
+}
+
+impl Drop for DaemonLock {
+    fn drop(&mut self) {
+        // Still locked until the file closes, so nobody can take it in between
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Lock the socket and clear whatever a dead daemon left at it.
+fn claim_socket(sock_path: &Path) -> Result<DaemonLock> {
+    let lock = DaemonLock::acquire(sock_path)?;
+    if sock_path.exists() {
+        // Daemons from before the lock file only show up by answering
+        if UnixStream::connect(sock_path).is_ok() {
+            anyhow::bail!(
+                "Another daemon is already running (socket {} is active)",
+                sock_path.display()
+            );
         }
+        LOG.info(&format!("Removing stale socket {}", sock_path.display()));
+        std::fs::remove_file(sock_path)
+            .with_context(|| format!("Failed to remove stale socket {}", sock_path.display()))?;
     }
+    Ok(lock)
+}
 
-    let listener = UnixListener::bind(&sock_path)
+/// Serve clients until told to stop. `verbose` logs at debug level from the
+/// start, whatever the config says.
+pub fn run_daemon(verbose: bool) -> Result<()> {
+    let sock_path = socket_path().context("No usable directory for the daemon socket")?;
+    let in_runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").is_some_and(|d| !d.is_empty())
+        && sock_path == socket_candidates()[0];
+    if in_runtime_dir || crate::protocol::instance().socket.is_some() {
+        LOG.info(&format!("Using socket {}", sock_path.display()));
+    } else {
+        LOG.warn(&format!(
+            "XDG_RUNTIME_DIR is unusable, falling back to socket {}",
+            sock_path.display()
+        ));
+    }
+
+    let lock = claim_socket(&sock_path)?;
+    let listener = bind_private(&sock_path)
         .with_context(|| format!("Failed to bind socket at {}", sock_path.display()))?;
     listener.set_nonblocking(true)?;
 
+    // Channel for client commands forwarded to daemon main loop
+    let (cmd_tx, cmd_rx) = mpsc::channel::<TaggedCommand>();
+
     let shutdown = Arc::new(AtomicBool::new(false));
-    setup_signal_handler(shutdown.clone());
+    setup_signal_handler(shutdown.clone(), cmd_tx.clone());
 
     let mut app = DaemonApp::new();
+    // PLENTYSOUND_DEBUG is the older way to ask for this
+    let debug = verbose || std::env::var_os("PLENTYSOUND_DEBUG").is_some();
+    crate::log::set_level(if debug {
+        LogLevel::Debug
+    } else {
+        app.log_level
+    });
+    crate::log::set_rotation(app.log_rotation);
 
     // Broadcast channels: each client writer thread gets a receiver
-    let client_senders: Arc<Mutex<Vec<mpsc::Sender<DaemonEvent>>>> =
-        Arc::new(Mutex::new(Vec::new()));
-
-    // Channel for client commands forwarded to daemon main loop
-    let (cmd_tx, cmd_rx) = mpsc::channel::<ClientCommand>();
+    let client_senders: Clients = Arc::new(Mutex::new(Vec::new()));
+    let mut next_client_id = 1;
 
     // Tray state
-    let tray_now_playing: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
-    crate::tray::spawn_tray(shutdown.clone(), tray_now_playing.clone());
+    let tray_now_playing: Arc<Mutex<NowPlaying>> = Arc::default();
+    let tray = crate::tray::spawn_tray(shutdown.clone(), tray_now_playing.clone());
+    #[cfg(feature = "mpris")]
+    let mpris = crate::mpris::spawn_mpris(cmd_tx.clone(), tray_now_playing.clone());
+    // Started on the first notification, so it costs nothing while they're off
+    let mut notifier: Option<Notifier> = None;
 
     #[cfg(feature = "transcriber")]
     let mut download_spawned = false;
@@ -60,26 +180,74 @@ pub fn run_daemon() -> Result<()> {
         // Accept new connections
         match listener.accept() {
             Ok((stream, _)) => {
-                handle_new_client(stream, &app, &cmd_tx, &client_senders);
+                handle_new_client(stream, next_client_id, &app, &cmd_tx, &client_senders);
+                next_client_id += 1;
             }
             Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
             Err(e) => {
-                crate::log::log_error(&format!("Accept error: {e}"));
+                LOG.error(&format!("Accept error: {e}"));
             }
         }
 
         // Process commands from clients
-        while let Ok(cmd) = cmd_rx.try_recv() {
-            crate::log::log_info(&format!("Processing command: {:?}", cmd));
+        while let Ok((client_id, cmd)) = cmd_rx.try_recv() {
+            let (request_id, cmd) = cmd.into_request();
+            match cmd {
+                ClientCommand::Identify { name, kind } => {
+                    identify_client(&client_senders, client_id, name, kind);
+                    reply(&client_senders, client_id, request_id, vec![]);
+                    continue;
+                }
+                ClientCommand::GetStats => {
+                    let stats = daemon_stats(&app, &lock_clients(&client_senders));
+                    let event = DaemonEvent::Stats(stats);
+                    if request_id.is_some() {
+                        reply(&client_senders, client_id, request_id, vec![event]);
+                    } else {
+                        send_to(&client_senders, client_id, event);
+                    }
+                    continue;
+                }
+                ClientCommand::DumpState => {
+                    dump_state(&app, &client_senders);
+                    reply(&client_senders, client_id, request_id, vec![]);
+                    continue;
+                }
+                ClientCommand::GetWaveform(index) => {
+                    // The envelope goes to everyone once it's ready; a bad
+                    // index only concerns the client that asked
+                    let events = app.request_waveform(index, client_id);
+                    if request_id.is_some() {
+                        reply(&client_senders, client_id, request_id, events);
+                    } else {
+                        for event in events {
+                            send_to(&client_senders, client_id, event);
+                        }
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let query = cmd.is_query();
+            let label = client_label(&client_senders, client_id);
+            LOG.log(
+                LogLevel::Debug,
+                "Processing command",
+                &[("client", &label), ("cmd", &format!("{cmd:?}"))],
+            );
             let events = app.apply_command(cmd);
-            crate::log::log_info(&format!("Command produced {} events, broadcasting", events.len()));
+            LOG.debug(&format!(
+                "Command produced {} events, broadcasting",
+                events.len()
+            ));
             for event in &events {
                 if matches!(event, DaemonEvent::Shutdown) {
                     shutdown.store(true, Ordering::SeqCst);
                 }
                 update_tray_np(&tray_now_playing, event);
             }
-            broadcast(&client_senders, &events);
+            notify(&mut notifier, &app, &events);
+            deliver(&client_senders, client_id, request_id, query, events);
         }
 
         // Process PipeWire events
@@ -99,30 +267,61 @@ pub fn run_daemon() -> Result<()> {
             for event in &pw_events {
                 update_tray_np(&tray_now_playing, event);
             }
+            notify(&mut notifier, &app, &pw_events);
             broadcast(&client_senders, &pw_events);
             #[cfg(feature = "transcriber")]
             if autostarted {
-                broadcast(&client_senders, &[DaemonEvent::State(app.snapshot())]);
+                broadcast(
+                    &client_senders,
+                    &[DaemonEvent::State(Box::new(app.snapshot()))],
+                );
             }
         }
 
+        // Songs the decode probe rejected
+        let probe_events = app.poll_probes();
+        if !probe_events.is_empty() {
+            broadcast(&client_senders, &probe_events);
+        }
+
+        let (waveforms, waveform_failures) = app.poll_waveforms();
+        if !waveforms.is_empty() {
+            broadcast(&client_senders, &waveforms);
+        }
+        // A failed envelope only concerns the clients that asked for it
+        for (client_id, event) in waveform_failures {
+            send_to(&client_senders, client_id, event);
+        }
+
+        // Song files deleted or restored behind the daemon's back
+        let presence_events = app.poll_presence(std::time::Instant::now());
+        if !presence_events.is_empty() {
+            broadcast(&client_senders, &presence_events);
+        }
+
         // Transcriber: spawn download thread if needed, poll detector matches
         #[cfg(feature = "transcriber")]
         {
             if app.word_detector_status == WordDetectorStatus::Downloading && !download_spawned {
                 download_spawned = true;
-                crate::log::log_info("Spawning model download thread");
+                LOG.info("Spawning model download thread");
                 let dl_cmd_tx = cmd_tx.clone();
+                let cancel = app.download_cancel.clone();
                 std::thread::spawn(move || {
-                    match download_model() {
+                    // A cancel that lands during extraction still wins: the
+                    // client already went back to Unavailable
+                    match download_model(&cancel) {
+                        _ if cancel.load(Ordering::Relaxed) => {
+                            LOG.info("Download thread: stopped after cancel");
+                        }
                         Ok(()) => {
-                            crate::log::log_info("Download thread: sending ModelDownloadComplete");
-                            let _ = dl_cmd_tx.send(ClientCommand::ModelDownloadComplete);
+                            LOG.info("Download thread: sending ModelDownloadComplete");
+                            let _ = dl_cmd_tx.send((None, ClientCommand::ModelDownloadComplete));
                         }
                         Err(e) => {
-                            crate::log::log_error(&format!("Download thread failed: {e:#}"));
+                            LOG.error(&format!("Download thread failed: {e:#}"));
                             let _ = dl_cmd_tx
-                                .send(ClientCommand::ModelDownloadFailed(e.to_string()));
+                                .send((None, ClientCommand::ModelDownloadFailed(e.to_string())));
                         }
                     }
                 });
@@ -132,14 +331,29 @@ pub fn run_daemon() -> Result<()> {
             }
 
             let det_events = app.poll_detector_matches();
+            app.flush_word_stats();
             if !det_events.is_empty() {
                 for event in &det_events {
                     update_tray_np(&tray_now_playing, event);
                 }
+                notify(&mut notifier, &app, &det_events);
                 broadcast(&client_senders, &det_events);
             }
+
+            let speech_events = app.poll_speech();
+            if !speech_events.is_empty() {
+                for event in &speech_events {
+                    update_tray_np(&tray_now_playing, event);
+                }
+                notify(&mut notifier, &app, &speech_events);
+                broadcast(&client_senders, &speech_events);
+            }
         }
 
+        app.retry_sinks();
+        app.write_status();
+        app.save_state(false);
+
         if shutdown.load(Ordering::SeqCst) {
             broadcast(&client_senders, &[DaemonEvent::Shutdown]);
             break;
@@ -148,19 +362,27 @@ pub fn run_daemon() -> Result<()> {
         std::thread::sleep(Duration::from_millis(20));
     }
 
+    app.shutdown();
+    tray.shutdown();
+    #[cfg(feature = "mpris")]
+    mpris.shutdown();
     let _ = std::fs::remove_file(&sock_path);
+    drop(lock);
     eprintln!("plentysound daemon stopped.");
-    // Force exit: tray thread (ksni D-Bus loop) and PipeWire playback threads
-    // may keep the process alive otherwise.
-    std::process::exit(0);
+    Ok(())
 }
 
 fn handle_new_client(
     stream: UnixStream,
+    id: u64,
     app: &DaemonApp,
-    cmd_tx: &mpsc::Sender<ClientCommand>,
-    client_senders: &Arc<Mutex<Vec<mpsc::Sender<DaemonEvent>>>>,
+    cmd_tx: &mpsc::Sender<TaggedCommand>,
+    client_senders: &Clients,
 ) {
+    if let Err(e) = check_peer(&stream) {
+        LOG.error(&format!("Rejected client-{id}: {e}"));
+        return;
+    }
     let snapshot = app.snapshot();
     let (event_tx, event_rx) = mpsc::channel::<DaemonEvent>();
 
@@ -170,68 +392,244 @@ fn handle_new_client(
     };
 
     // Send initial state
-    if send_message(&mut write_stream, &DaemonEvent::State(snapshot)).is_err() {
+    if send_message(&mut write_stream, &DaemonEvent::State(Box::new(snapshot))).is_err() {
         return;
     }
 
-    client_senders.lock().unwrap().push(event_tx);
+    // Labeled anonymously until it sends Identify
+    let info = ClientInfo {
+        name: format!("client-{id}"),
+        kind: String::new(),
+    };
+    lock_clients(client_senders).push(Client {
+        id,
+        info,
+        tx: event_tx,
+    });
 
     // Reader thread
     let read_cmd_tx = cmd_tx.clone();
+    let read_socket = stream.try_clone().ok();
     std::thread::spawn(move || {
-        let mut read_stream = stream;
-        read_stream.set_nonblocking(false).ok();
-        loop {
-            match recv_message::<ClientCommand>(&mut read_stream) {
-                Ok(cmd) => {
-                    if read_cmd_tx.send(cmd).is_err() {
+        isolate_client(id, "reader", read_socket, || {
+            stream.set_nonblocking(false).ok();
+            let mut reader = FrameReader::new(stream);
+            loop {
+                match reader.recv_timeout::<ClientCommand>(FRAME_TIMEOUT) {
+                    Ok(cmd) => {
+                        if read_cmd_tx.send((Some(id), cmd)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                        LOG.error(&format!("Dropped client message: {e}"));
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                        LOG.error(&format!(
+                            "client-{id} stalled in the middle of a message, dropping it"
+                        ));
+                        // Ends the writer too, and tells the client
+                        let _ = reader.get_mut().shutdown(Shutdown::Both);
                         break;
                     }
+                    Err(_) => break,
                 }
-                Err(_) => break,
             }
-        }
+        })
     });
 
     // Writer thread
+    let write_socket = write_stream.try_clone().ok();
     std::thread::spawn(move || {
-        for event in event_rx {
-            let is_shutdown = matches!(event, DaemonEvent::Shutdown);
-            if send_message(&mut write_stream, &event).is_err() {
-                break;
-            }
-            if is_shutdown {
-                break;
+        isolate_client(id, "writer", write_socket, || {
+            write_events(id, write_stream, event_rx)
+        })
+    });
+}
+
+/// Run one of a client's threads. A panic is logged and the socket shut
+/// down, so the client sees the connection close instead of hanging, and
+/// its other thread and the broadcast list let go of it in turn.
+fn isolate_client(id: u64, role: &str, socket: Option<UnixStream>, body: impl FnOnce()) {
+    let Err(panic) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)) else {
+        return;
+    };
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    LOG.error(&format!(
+        "client-{id} {role} thread panicked, dropping it: {message}"
+    ));
+    if let Some(socket) = socket {
+        let _ = socket.shutdown(Shutdown::Both);
+    }
+}
+
+fn write_events(id: u64, mut write_stream: UnixStream, event_rx: mpsc::Receiver<DaemonEvent>) {
+    for event in event_rx {
+        #[cfg(test)]
+        if tests::take_injected_panic(id) {
+            panic!("injected serializer panic");
+        }
+        let is_shutdown = matches!(event, DaemonEvent::Shutdown);
+        match send_message(&mut write_stream, &event) {
+            Ok(()) => {}
+            // Too big to frame; nothing was written, so keep the client
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+                LOG.error(&format!("Dropped event for client-{id}: {e}"));
             }
+            Err(_) => break,
         }
-    });
+        if is_shutdown {
+            break;
+        }
+    }
 }
 
-fn broadcast(client_senders: &Arc<Mutex<Vec<mpsc::Sender<DaemonEvent>>>>, events: &[DaemonEvent]) {
-    let mut senders = client_senders.lock().unwrap();
+fn broadcast(client_senders: &Clients, events: &[DaemonEvent]) {
+    let mut clients = lock_clients(client_senders);
     for event in events {
-        senders.retain(|tx| tx.send(event.clone()).is_ok());
+        clients.retain(|c| c.tx.send(event.clone()).is_ok());
     }
 }
 
-fn update_tray_np(tray_np: &Arc<Mutex<Option<String>>>, event: &DaemonEvent) {
+/// Send what a command from client `id` produced: to everyone, or for a
+/// `Request`, in a reply to the requester and to the others unless it was
+/// only a query.
+fn deliver(
+    client_senders: &Clients,
+    id: Option<u64>,
+    request_id: Option<u64>,
+    query: bool,
+    events: Vec<DaemonEvent>,
+) {
+    if request_id.is_none() {
+        broadcast(client_senders, &events);
+        return;
+    }
+    if !query {
+        broadcast_except(client_senders, id, &events);
+    }
+    reply(client_senders, id, request_id, events);
+}
+
+/// `broadcast` to everyone but client `id`.
+fn broadcast_except(client_senders: &Clients, id: Option<u64>, events: &[DaemonEvent]) {
+    let mut clients = lock_clients(client_senders);
+    for event in events {
+        clients.retain(|c| Some(c.id) == id || c.tx.send(event.clone()).is_ok());
+    }
+}
+
+/// Answer a `Request` from client `id` with the events it produced. Does
+/// nothing for plain commands, or for the daemon's own.
+fn reply(
+    client_senders: &Clients,
+    id: Option<u64>,
+    request_id: Option<u64>,
+    events: Vec<DaemonEvent>,
+) {
+    if let Some(request_id) = request_id {
+        send_to(
+            client_senders,
+            id,
+            DaemonEvent::Reply { request_id, events },
+        );
+    }
+}
+
+fn send_to(client_senders: &Clients, id: Option<u64>, event: DaemonEvent) {
+    if let Some(client) = lock_clients(client_senders)
+        .iter()
+        .find(|c| Some(c.id) == id)
+    {
+        let _ = client.tx.send(event);
+    }
+}
+
+/// Name to use in log lines for the sender of a command.
+fn client_label(client_senders: &Clients, id: Option<u64>) -> String {
+    let Some(id) = id else {
+        return "daemon".to_string();
+    };
+    lock_clients(client_senders)
+        .iter()
+        .find(|c| c.id == id)
+        .map(|c| c.info.name.clone())
+        .unwrap_or_else(|| format!("client-{id}"))
+}
+
+fn identify_client(client_senders: &Clients, id: Option<u64>, name: String, kind: String) {
+    let mut clients = lock_clients(client_senders);
+    if let Some(client) = clients.iter_mut().find(|c| Some(c.id) == id) {
+        LOG.info(&format!(
+            "Client '{}' identified as '{}' ({})",
+            client.info.name, name, kind
+        ));
+        client.info = ClientInfo { name, kind };
+    }
+}
+
+fn daemon_stats(app: &DaemonApp, clients: &[Client]) -> DaemonStats {
+    DaemonStats {
+        clients: clients.iter().map(|c| c.info.clone()).collect(),
+        history: app.history_stats(),
+    }
+}
+
+/// Write the full state and stats to the log as pretty JSON, for debugging.
+fn dump_state(app: &DaemonApp, client_senders: &Clients) {
+    let stats = daemon_stats(app, &lock_clients(client_senders));
+    let dump = serde_json::json!({ "state": app.snapshot(), "stats": stats, "history": app.history_snapshot() });
+    match serde_json::to_string_pretty(&dump) {
+        Ok(json) => LOG.info(&format!("State dump:\n{json}")),
+        Err(e) => LOG.error(&format!("Failed to encode state dump: {e}")),
+    }
+}
+
+/// Keep what the tray and MPRIS show in step with `event`.
+fn update_tray_np(tray_np: &Arc<Mutex<NowPlaying>>, event: &DaemonEvent) {
+    let mut np = tray_np.lock().unwrap_or_else(PoisonError::into_inner);
     match event {
-        DaemonEvent::NowPlaying(np) => {
-            *tray_np.lock().unwrap() = np.clone();
+        DaemonEvent::NowPlaying(song) => {
+            *np = NowPlaying {
+                song: song.clone(),
+                paused: false,
+            };
         }
         DaemonEvent::State(state) => {
-            *tray_np.lock().unwrap() = state.now_playing.clone();
+            *np = NowPlaying {
+                song: state.now_playing.clone(),
+                paused: state.paused,
+            };
         }
         DaemonEvent::PlaybackFinished => {
-            *tray_np.lock().unwrap() = None;
+            *np = NowPlaying::default();
+        }
+        DaemonEvent::Paused(paused) => {
+            np.paused = *paused;
         }
         _ => {}
     }
 }
 
+/// Pass on what `events` announce, if the config asks for notifications.
+fn notify(notifier: &mut Option<Notifier>, app: &DaemonApp, events: &[DaemonEvent]) {
+    if !app.notifications {
+        return;
+    }
+    for text in crate::notify::announcements(events, app.notify_on_play) {
+        notifier.get_or_insert_with(Notifier::spawn).send(text);
+    }
+}
+
 static SIGNAL_PIPE_WRITE: AtomicI32 = AtomicI32::new(-1);
 
-fn setup_signal_handler(shutdown: Arc<AtomicBool>) {
+/// Route signals through a self-pipe to a watcher thread: SIGINT/SIGTERM
+/// shut down, SIGHUP reloads the config and SIGUSR1 dumps the state to the log.
+fn setup_signal_handler(shutdown: Arc<AtomicBool>, cmd_tx: mpsc::Sender<TaggedCommand>) {
     let mut fds = [0i32; 2];
     unsafe {
         libc::pipe(fds.as_mut_ptr());
@@ -239,15 +637,28 @@ fn setup_signal_handler(shutdown: Arc<AtomicBool>) {
     let (read_fd, write_fd) = (fds[0], fds[1]);
     SIGNAL_PIPE_WRITE.store(write_fd, Ordering::SeqCst);
 
+    let handler = signal_handler as extern "C" fn(i32) as libc::sighandler_t;
     unsafe {
-        libc::signal(libc::SIGINT, signal_handler as usize);
-        libc::signal(libc::SIGTERM, signal_handler as usize);
+        for sig in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGUSR1] {
+            libc::signal(sig, handler);
+        }
     }
 
     std::thread::spawn(move || {
-        let mut buf = [0u8; 1];
-        unsafe {
-            libc::read(read_fd, buf.as_mut_ptr() as *mut _, 1);
+        loop {
+            let mut buf = [0u8; 1];
+            let n = unsafe { libc::read(read_fd, buf.as_mut_ptr() as *mut _, 1) };
+            if n < 0 && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            let cmd = match buf[0] {
+                b'h' if n == 1 => ClientCommand::ReloadConfig,
+                b'u' if n == 1 => ClientCommand::DumpState,
+                _ => break,
+            };
+            if cmd_tx.send((None, cmd)).is_err() {
+                break;
+            }
         }
         shutdown.store(true, Ordering::SeqCst);
         unsafe {
@@ -257,23 +668,31 @@ fn setup_signal_handler(shutdown: Arc<AtomicBool>) {
     });
 }
 
-extern "C" fn signal_handler(_sig: i32) {
+extern "C" fn signal_handler(sig: i32) {
+    // Only the write() below is allowed here; the watcher thread does the rest
+    let byte: &[u8; 1] = match sig {
+        libc::SIGHUP => b"h",
+        libc::SIGUSR1 => b"u",
+        _ => b"x",
+    };
     let fd = SIGNAL_PIPE_WRITE.load(Ordering::SeqCst);
     if fd >= 0 {
         unsafe {
-            libc::write(fd, b"x".as_ptr() as *const _, 1);
+            libc::write(fd, byte.as_ptr() as *const _, 1);
         }
     }
 }
 
 #[cfg(feature = "transcriber")]
-fn download_model() -> anyhow::Result<()> {
+fn download_model(cancel: &AtomicBool) -> anyhow::Result<()> {
+    use std::io::{Read, Write};
+
     use crate::protocol::{default_model_dir, MODEL_ASSET_NAME, MODEL_REPO};
 
-    crate::log::log_info("Model download started");
+    LOG.info("Model download started");
 
     let model_dir = default_model_dir();
-    crate::log::log_info(&format!("Model directory: {}", model_dir.display()));
+    LOG.info(&format!("Model directory: {}", model_dir.display()));
     std::fs::create_dir_all(&model_dir)
         .with_context(|| format!("Failed to create model directory: {}", model_dir.display()))?;
 
@@ -281,7 +700,7 @@ fn download_model() -> anyhow::Result<()> {
     let api_url = format!(
         "https://api.github.com/repos/{MODEL_REPO}/releases/latest"
     );
-    crate::log::log_info(&format!("Querying GitHub API: {}", api_url));
+    LOG.info(&format!("Querying GitHub API: {}", api_url));
     let agent = ureq::Agent::new_with_config(
         ureq::config::Config::builder()
             .timeout_global(Some(std::time::Duration::from_secs(120)))
@@ -295,7 +714,7 @@ fn download_model() -> anyhow::Result<()> {
         .body_mut()
         .read_to_string()
         .context("Failed to read GitHub releases response")?;
-    crate::log::log_info(&format!("GitHub API response length: {} bytes", body.len()));
+    LOG.info(&format!("GitHub API response length: {} bytes", body.len()));
 
     let response: serde_json::Value = serde_json::from_str(&body)
         .context("Failed to parse GitHub releases response")?;
@@ -305,7 +724,7 @@ fn download_model() -> anyhow::Result<()> {
         .as_array()
         .context("No assets in release")?;
 
-    crate::log::log_info(&format!(
+    LOG.info(&format!(
         "Found {} assets, looking for '{}'",
         assets.len(),
         MODEL_ASSET_NAME
@@ -324,10 +743,14 @@ fn download_model() -> anyhow::Result<()> {
         .as_str()
         .context("No download URL for asset")?;
 
-    crate::log::log_info(&format!("Downloading asset from: {}", download_url));
+    LOG.info(&format!("Downloading asset from: {}", download_url));
 
-    // Download the asset (5 min timeout for large files)
-    let dest_file = model_dir.join(MODEL_ASSET_NAME);
+    // Download the asset (5 min timeout for large files). Each attempt gets
+    // its own file, so a cancelled one cleaning up can't take a retry's
+    static ATTEMPT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    let attempt = ATTEMPT.fetch_add(1, Ordering::Relaxed);
+    let part = format!("{MODEL_ASSET_NAME}.{}-{attempt}.part", std::process::id());
+    let dest_file = model_dir.join(part);
     let dl_agent = ureq::Agent::new_with_config(
         ureq::config::Config::builder()
             .timeout_global(Some(std::time::Duration::from_secs(300)))
@@ -340,52 +763,267 @@ fn download_model() -> anyhow::Result<()> {
 
     let mut file = std::fs::File::create(&dest_file)
         .with_context(|| format!("Failed to create file: {}", dest_file.display()))?;
-    let bytes_written = std::io::copy(&mut response.body_mut().as_reader(), &mut file)
-        .context("Failed to write downloaded file")?;
+    // Copy by hand so a cancel is noticed between chunks
+    let mut reader = response.body_mut().as_reader();
+    let mut chunk = vec![0u8; 64 * 1024];
+    let mut bytes_written = 0u64;
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            drop(file);
+            let _ = std::fs::remove_file(&dest_file);
+            anyhow::bail!("Model download cancelled after {} bytes", bytes_written);
+        }
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).context("Failed to read model download"),
+        };
+        file.write_all(&chunk[..n])
+            .context("Failed to write downloaded file")?;
+        bytes_written += n as u64;
+    }
     drop(file);
-    crate::log::log_info(&format!(
+    LOG.info(&format!(
         "Downloaded {} bytes to {}",
         bytes_written,
         dest_file.display()
     ));
+    if cancel.load(Ordering::Relaxed) {
+        let _ = std::fs::remove_file(&dest_file);
+        anyhow::bail!("Model download cancelled before extraction");
+    }
 
-    // Extract: determine extraction method by file extension
+    // Extract: decompress with the external tool and unpack the tar stream
+    // ourselves, so every entry is checked against the model directory
     let asset_name = MODEL_ASSET_NAME;
-    crate::log::log_info(&format!("Extracting {} into {}", asset_name, model_dir.display()));
-    let extract_result = if asset_name.ends_with(".tar.zst") || asset_name.ends_with(".tar.zstd") {
-        std::process::Command::new("tar")
-            .args(["--zstd", "-xf"])
-            .arg(&dest_file)
-            .arg("-C")
-            .arg(&model_dir)
-            .status()
+    LOG.info(&format!(
+        "Extracting {} into {}",
+        asset_name,
+        model_dir.display()
+    ));
+    let decompressor = if asset_name.ends_with(".tar.zst") || asset_name.ends_with(".tar.zstd") {
+        "zstd"
     } else if asset_name.ends_with(".tar.gz") || asset_name.ends_with(".tgz") {
-        std::process::Command::new("tar")
-            .args(["-xzf"])
-            .arg(&dest_file)
-            .arg("-C")
-            .arg(&model_dir)
-            .status()
-    } else if asset_name.ends_with(".zip") {
-        std::process::Command::new("unzip")
-            .arg("-o")
-            .arg(&dest_file)
-            .arg("-d")
-            .arg(&model_dir)
-            .status()
+        "gzip"
     } else {
         anyhow::bail!("Unsupported archive format: {}", asset_name);
     };
-
-    let status = extract_result.context("Failed to run extraction command")?;
-    if !status.success() {
-        anyhow::bail!("Extraction failed with status: {}", status);
+    let mut child = std::process::Command::new(decompressor)
+        .args(["-dc"])
+        .arg(&dest_file)
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", decompressor))?;
+    let stdout = child.stdout.take().context("No output from decompressor")?;
+    let unpacked =
+        crate::extract::unpack_tar(stdout, &model_dir, crate::extract::MAX_EXTRACTED_BYTES);
+    if unpacked.is_err() {
+        let _ = child.kill();
     }
-    crate::log::log_info("Extraction complete");
-
-    // Clean up the archive file
+    let status = child.wait().context("Failed to wait for decompressor")?;
     let _ = std::fs::remove_file(&dest_file);
-    crate::log::log_info("Model download finished successfully");
+    let written = unpacked?;
+    if !status.success() {
+        anyhow::bail!("Decompression failed with status: {}", status);
+    }
+    LOG.info(&format!("Extraction complete ({} bytes)", written));
+    LOG.info("Model download finished successfully");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::recv_message;
+    use std::collections::HashSet;
+
+    /// Client ids whose writer panics on its next event, standing in for a
+    /// serializer bug.
+    static PANIC_ON_WRITE: Mutex<Option<HashSet<u64>>> = Mutex::new(None);
+
+    pub(super) fn take_injected_panic(id: u64) -> bool {
+        let mut ids = PANIC_ON_WRITE
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        ids.as_mut().is_some_and(|ids| ids.remove(&id))
+    }
+
+    fn connect(
+        id: u64,
+        app: &DaemonApp,
+        cmd_tx: &mpsc::Sender<TaggedCommand>,
+        clients: &Clients,
+    ) -> UnixStream {
+        let (server, mut client) = UnixStream::pair().unwrap();
+        handle_new_client(server, id, app, cmd_tx, clients);
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert!(matches!(
+            recv_message(&mut client),
+            Ok(DaemonEvent::State(_))
+        ));
+        client
+    }
+
+    #[test]
+    fn a_panicking_client_thread_leaves_the_others_served() {
+        let t = crate::app::tests::test_daemon("client-panic");
+        let (cmd_tx, _cmd_rx) = mpsc::channel();
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let mut broken = connect(1, &t.app, &cmd_tx, &clients);
+        let mut healthy = connect(2, &t.app, &cmd_tx, &clients);
+
+        PANIC_ON_WRITE
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashSet::new)
+            .insert(1);
+        broadcast(
+            &clients,
+            &[DaemonEvent::NowPlaying(Some("horn.wav".into()))],
+        );
+        assert!(matches!(
+            recv_message(&mut healthy),
+            Ok(DaemonEvent::NowPlaying(Some(_)))
+        ));
+        // The broken client sees its connection close rather than hanging
+        assert!(recv_message::<DaemonEvent>(&mut broken).is_err());
+
+        // A panic while holding the list doesn't take broadcasting down with it
+        let poisoner = clients.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poison the client list");
+        })
+        .join();
+        assert!(clients.is_poisoned());
+        broadcast(&clients, &[DaemonEvent::PlaybackFinished]);
+        assert!(matches!(
+            recv_message(&mut healthy),
+            Ok(DaemonEvent::PlaybackFinished)
+        ));
+        assert_eq!(
+            lock_clients(&clients)
+                .iter()
+                .map(|c| c.id)
+                .collect::<Vec<_>>(),
+            [2]
+        );
+        let _ = std::fs::remove_dir_all(&t.dir);
+    }
+
+    #[test]
+    fn requests_are_answered_to_the_requester_alone() {
+        let t = crate::app::tests::test_daemon("client-request");
+        let (cmd_tx, _cmd_rx) = mpsc::channel();
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let mut asker = connect(1, &t.app, &cmd_tx, &clients);
+        let mut other = connect(2, &t.app, &cmd_tx, &clients);
+        let playing = || vec![DaemonEvent::NowPlaying(Some("horn.wav".into()))];
+
+        // A change: the others hear of it as usual, the requester in its reply
+        deliver(&clients, Some(1), Some(7), false, playing());
+        let Ok(DaemonEvent::Reply {
+            request_id: 7,
+            events,
+        }) = recv_message(&mut asker)
+        else {
+            panic!("expected the reply to request 7");
+        };
+        assert!(matches!(events[..], [DaemonEvent::NowPlaying(Some(_))]));
+        assert!(matches!(
+            recv_message(&mut other),
+            Ok(DaemonEvent::NowPlaying(Some(_)))
+        ));
+
+        // A query is nobody else's business
+        deliver(&clients, Some(1), Some(8), true, playing());
+        deliver(
+            &clients,
+            Some(1),
+            None,
+            false,
+            vec![DaemonEvent::NowPlaying(None)],
+        );
+        let Ok(DaemonEvent::Reply { request_id: 8, .. }) = recv_message(&mut asker) else {
+            panic!("expected the reply to request 8");
+        };
+        assert!(matches!(
+            recv_message(&mut asker),
+            Ok(DaemonEvent::NowPlaying(None))
+        ));
+        assert!(matches!(
+            recv_message(&mut other),
+            Ok(DaemonEvent::NowPlaying(None))
+        ));
+    }
+
+    #[test]
+    fn a_client_finds_the_daemon_on_a_chosen_socket() {
+        let t = crate::app::tests::test_daemon("chosen-socket");
+        let instance = crate::protocol::Instance {
+            socket: Some(t.dir.join("second.sock")),
+            name: Some("second".to_string()),
+        };
+        let listener = bind_private(&instance.socket_candidates()[0]).unwrap();
+        let (cmd_tx, cmd_rx) = mpsc::channel();
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+
+        let mut client = crate::client::connect_at(&instance.socket_candidates()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        handle_new_client(server, 1, &t.app, &cmd_tx, &clients);
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert!(matches!(
+            recv_message(&mut client),
+            Ok(DaemonEvent::State(_))
+        ));
+        send_message(&mut client, &ClientCommand::GetState).unwrap();
+        let (id, cmd) = cmd_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(id, Some(1));
+        assert!(matches!(cmd, ClientCommand::GetState));
+        let _ = std::fs::remove_dir_all(&t.dir);
+    }
+
+    #[test]
+    fn a_dead_daemons_socket_and_lock_are_taken_over() {
+        let dir = crate::app::tests::scratch_dir("stale-socket");
+        let sock_path = dir.join("plentysound.sock");
+        // Bound and dropped: the file stays with nobody behind it
+        drop(bind_private(&sock_path).unwrap());
+        std::fs::write(dir.join("plentysound.lock"), "999999999").unwrap();
+
+        let lock = claim_socket(&sock_path).unwrap();
+        assert!(!sock_path.exists());
+        let pid = std::fs::read_to_string(dir.join("plentysound.lock")).unwrap();
+        assert_eq!(pid, std::process::id().to_string());
+        drop(lock);
+        assert!(!dir.join("plentysound.lock").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_held_lock_blocks_a_second_daemon() {
+        let dir = crate::app::tests::scratch_dir("held-lock");
+        let sock_path = dir.join("plentysound.sock");
+        let lock = claim_socket(&sock_path).unwrap();
+        // Not bound yet, as when two daemons start together
+        let err = claim_socket(&sock_path).err().unwrap().to_string();
+        assert!(err.contains("already running"), "{err}");
+        assert!(
+            err.contains(&format!("pid {}", std::process::id())),
+            "{err}"
+        );
+
+        // Nor is a socket from an old daemon, still answering, taken over
+        drop(lock);
+        let _listener = bind_private(&sock_path).unwrap();
+        assert!(claim_socket(&sock_path).is_err());
+        assert!(sock_path.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,495 @@
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// Largest total size a model archive may unpack to.
+pub const MAX_EXTRACTED_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+const BLOCK: usize = 512;
+
+/// Unpack a plain tar stream into `dest`.
+///
+/// Entries are written to a staging directory first and only moved into
+/// `dest` once the whole archive checked out, so a rejected archive leaves
+/// nothing behind. Every entry must stay under `dest`: absolute paths, `..`
+/// escapes, symlinks pointing outside and entries written through a symlink
+/// an earlier entry made are refused, as are hard links, device nodes and
+/// archives expanding past `max_bytes`. Returns the number of bytes written.
+pub fn unpack_tar(reader: impl Read, dest: &Path, max_bytes: u64) -> Result<u64> {
+    std::fs::create_dir_all(dest)
+        .with_context(|| format!("Failed to create {}", dest.display()))?;
+    // Per call, so an abandoned unpack cleaning up can't take a newer one's
+    static CALLS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    let call = CALLS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let staging = dest.join(format!(".extract-{}-{call}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir(&staging)
+        .with_context(|| format!("Failed to create {}", staging.display()))?;
+
+    let result = unpack_entries(reader, &staging, max_bytes)
+        .and_then(|written| move_into(&staging, dest).map(|()| written));
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn unpack_entries(mut reader: impl Read, root: &Path, max_bytes: u64) -> Result<u64> {
+    let mut written = 0u64;
+    // Overrides for the next entry from GNU long-name or pax headers
+    let mut long_path: Option<String> = None;
+    let mut long_link: Option<String> = None;
+
+    loop {
+        let mut header = [0u8; BLOCK];
+        if !read_block(&mut reader, &mut header)? || header.iter().all(|&b| b == 0) {
+            break;
+        }
+        verify_checksum(&header)?;
+
+        let size = parse_number(&header[124..136]).context("corrupt tar header: bad size")?;
+        let path = long_path.take().unwrap_or_else(|| header_path(&header));
+        let link = long_link
+            .take()
+            .unwrap_or_else(|| field_str(&header[157..257]));
+        let reject =
+            |reason: &str| anyhow::anyhow!("Rejected archive entry '{}': {}", path, reason);
+
+        match header[156] {
+            b'L' | b'K' | b'x' => {
+                let data = read_data(&mut reader, size, max_bytes)?;
+                match header[156] {
+                    b'L' => long_path = Some(field_str(&data)),
+                    b'K' => long_link = Some(field_str(&data)),
+                    _ => {
+                        for (key, value) in pax_records(&data) {
+                            match key.as_str() {
+                                "path" => long_path = Some(value),
+                                "linkpath" => long_link = Some(value),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                continue;
+            }
+            b'g' => {
+                read_data(&mut reader, size, max_bytes)?;
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some(target) = contained_path(root, Path::new(&path)).map_err(&reject)? else {
+            // "./" and friends: nothing to create
+            skip_data(&mut reader, size)?;
+            continue;
+        };
+        // Paths are only checked as text above; on disk an earlier symlink
+        // could still lead the write anywhere
+        no_symlink_on_the_way(root, &target).map_err(&reject)?;
+
+        match header[156] {
+            b'0' | b'\0' | b'7' => {
+                if written.saturating_add(size) > max_bytes {
+                    return Err(reject(&format!("archive expands past {} bytes", max_bytes)));
+                }
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut file = std::fs::File::create(&target)
+                    .with_context(|| format!("Failed to create {}", target.display()))?;
+                let copied = std::io::copy(&mut (&mut reader).take(size), &mut file)?;
+                if copied != size {
+                    anyhow::bail!("Archive truncated in entry '{}'", path);
+                }
+                skip_padding(&mut reader, size)?;
+                written += size;
+            }
+            b'5' => {
+                std::fs::create_dir_all(&target)?;
+                skip_data(&mut reader, size)?;
+            }
+            b'2' => {
+                // Symlink targets are relative to the link's own directory
+                let base = Path::new(&path).parent().unwrap_or(Path::new(""));
+                if Path::new(&link).is_absolute() {
+                    return Err(reject("symlink to an absolute path"));
+                }
+                // `..` is only followed lexically, which is wrong once it
+                // comes after a name that may itself be a link
+                let mut entered = false;
+                for component in Path::new(&link).components() {
+                    match component {
+                        Component::ParentDir if entered => {
+                            return Err(reject("symlink climbs back out of a path it entered"));
+                        }
+                        Component::Normal(_) => entered = true,
+                        _ => {}
+                    }
+                }
+                contained_path(root, &base.join(&link))
+                    .map_err(|_| reject("symlink points outside the model directory"))?;
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::os::unix::fs::symlink(&link, &target)?;
+                skip_data(&mut reader, size)?;
+            }
+            // Models don't ship them, and their source path is resolved
+            // through whatever symlinks the archive made
+            b'1' => return Err(reject("hard links are not supported")),
+            other => {
+                return Err(reject(&format!(
+                    "unsupported entry type '{}'",
+                    other as char
+                )));
+            }
+        }
+    }
+    Ok(written)
+}
+
+/// Join an archive path onto `root` without leaving it. `Ok(None)` means the
+/// path names `root` itself.
+fn contained_path(root: &Path, path: &Path) -> Result<Option<PathBuf>, &'static str> {
+    let mut parts: Vec<&std::ffi::OsStr> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if parts.pop().is_none() {
+                    return Err("path escapes the model directory");
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return Err("absolute path"),
+        }
+    }
+    if parts.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        parts
+            .iter()
+            .fold(root.to_path_buf(), |p, part| p.join(part)),
+    ))
+}
+
+/// Refuse `target` if it, or a directory on the way to it from `root`, is a
+/// symlink already on disk.
+fn no_symlink_on_the_way(root: &Path, target: &Path) -> Result<(), &'static str> {
+    let rel = target
+        .strip_prefix(root)
+        .map_err(|_| "path escapes the model directory")?;
+    let mut path = root.to_path_buf();
+    for part in rel.components() {
+        path.push(part);
+        match std::fs::symlink_metadata(&path) {
+            Ok(meta) if meta.file_type().is_symlink() => return Err("path goes through a symlink"),
+            Ok(_) => {}
+            // Nothing further down exists yet
+            Err(_) => break,
+        }
+    }
+    Ok(())
+}
+
+/// Replace the matching entries of `dest` with the staged ones.
+fn move_into(staging: &Path, dest: &Path) -> Result<()> {
+    for entry in std::fs::read_dir(staging)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+        if target.is_dir() && !target.is_symlink() {
+            std::fs::remove_dir_all(&target)?;
+        } else if target.symlink_metadata().is_ok() {
+            std::fs::remove_file(&target)?;
+        }
+        std::fs::rename(entry.path(), &target)
+            .with_context(|| format!("Failed to move {} into place", target.display()))?;
+    }
+    Ok(())
+}
+
+/// Read one block; `false` at a clean end of stream.
+fn read_block(reader: &mut impl Read, block: &mut [u8; BLOCK]) -> Result<bool> {
+    let mut filled = 0;
+    while filled < BLOCK {
+        let n = reader.read(&mut block[filled..])?;
+        if n == 0 {
+            if filled == 0 {
+                return Ok(false);
+            }
+            anyhow::bail!("Archive truncated mid-header");
+        }
+        filled += n;
+    }
+    Ok(true)
+}
+
+fn read_data(reader: &mut impl Read, size: u64, max_bytes: u64) -> Result<Vec<u8>> {
+    // Metadata records are tiny; a huge one is a broken or hostile archive
+    if size > max_bytes.min(1024 * 1024) {
+        anyhow::bail!("Archive metadata record too large ({} bytes)", size);
+    }
+    let mut data = vec![0u8; size as usize];
+    reader.read_exact(&mut data).context("Archive truncated")?;
+    skip_padding(reader, size)?;
+    Ok(data)
+}
+
+fn skip_data(reader: &mut impl Read, size: u64) -> Result<()> {
+    let padded = size.div_ceil(BLOCK as u64) * BLOCK as u64;
+    let skipped = std::io::copy(&mut reader.take(padded), &mut std::io::sink())?;
+    if skipped != padded {
+        anyhow::bail!("Archive truncated");
+    }
+    Ok(())
+}
+
+fn skip_padding(reader: &mut impl Read, size: u64) -> Result<()> {
+    let rem = (size % BLOCK as u64) as usize;
+    if rem != 0 {
+        let mut pad = vec![0u8; BLOCK - rem];
+        reader.read_exact(&mut pad).context("Archive truncated")?;
+    }
+    Ok(())
+}
+
+fn verify_checksum(header: &[u8; BLOCK]) -> Result<()> {
+    let expected = parse_number(&header[148..156]).context("corrupt tar header: bad checksum")?;
+    let actual: u64 = header
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| {
+            if (148..156).contains(&i) {
+                b' ' as u64
+            } else {
+                b as u64
+            }
+        })
+        .sum();
+    if actual != expected {
+        anyhow::bail!("corrupt tar header: checksum mismatch");
+    }
+    Ok(())
+}
+
+/// Octal field, or GNU base-256 when the high bit of the first byte is set.
+fn parse_number(field: &[u8]) -> Option<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return field[1..]
+            .iter()
+            .try_fold(0u64, |acc, &b| acc.checked_mul(256).map(|v| v | b as u64));
+    }
+    let text = field_str(field);
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, 8).ok()
+}
+
+fn field_str(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn header_path(header: &[u8; BLOCK]) -> String {
+    let name = field_str(&header[0..100]);
+    let prefix = if &header[257..262] == b"ustar" {
+        field_str(&header[345..500])
+    } else {
+        String::new()
+    };
+    if prefix.is_empty() {
+        name
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+/// `"<len> key=value\n"` records of a pax extended header.
+fn pax_records(data: &[u8]) -> Vec<(String, String)> {
+    let mut records = Vec::new();
+    let mut rest = data;
+    while let Some(space) = rest.iter().position(|&b| b == b' ') {
+        let Some(len) = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+        else {
+            break;
+        };
+        if len <= space || len > rest.len() {
+            break;
+        }
+        let record = String::from_utf8_lossy(&rest[space + 1..len]);
+        if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+            records.push((key.to_string(), value.to_string()));
+        }
+        rest = &rest[len..];
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::tests::scratch_dir;
+
+    /// Minimal ustar writer for building test archives by hand.
+    #[derive(Default)]
+    struct TarBuilder(Vec<u8>);
+
+    impl TarBuilder {
+        fn entry(mut self, kind: u8, path: &str, link: &str, data: &[u8]) -> Self {
+            let mut header = [0u8; BLOCK];
+            header[..path.len()].copy_from_slice(path.as_bytes());
+            header[100..107].copy_from_slice(b"0000644");
+            header[108..115].copy_from_slice(b"0000000");
+            header[116..123].copy_from_slice(b"0000000");
+            header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+            header[136..147].copy_from_slice(b"00000000000");
+            header[156] = kind;
+            header[157..157 + link.len()].copy_from_slice(link.as_bytes());
+            header[257..263].copy_from_slice(b"ustar\0");
+            header[263..265].copy_from_slice(b"00");
+            header[148..156].copy_from_slice(b"        ");
+            let sum: u32 = header.iter().map(|&b| b as u32).sum();
+            header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+            self.0.extend_from_slice(&header);
+            self.0.extend_from_slice(data);
+            self.0.resize(self.0.len().div_ceil(BLOCK) * BLOCK, 0);
+            self
+        }
+
+        fn file(self, path: &str, data: &[u8]) -> Self {
+            self.entry(b'0', path, "", data)
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            self.0.resize(self.0.len() + 2 * BLOCK, 0);
+            self.0
+        }
+    }
+
+    /// Unpack into `<scratch>/models`, returning the scratch dir and the error.
+    fn unpack_rejected(tag: &str, archive: Vec<u8>, max_bytes: u64) -> (PathBuf, String) {
+        let dir = scratch_dir(tag);
+        let err = unpack_tar(archive.as_slice(), &dir.join("models"), max_bytes)
+            .expect_err("archive should be rejected");
+        (dir, err.to_string())
+    }
+
+    /// Nothing may be left besides the (empty) model directory itself.
+    fn assert_untouched(dir: &Path) {
+        let top: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(top, vec![std::ffi::OsString::from("models")]);
+        assert_eq!(std::fs::read_dir(dir.join("models")).unwrap().count(), 0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn well_formed_archive_is_unpacked() {
+        let dir = scratch_dir("tar-ok");
+        let archive = TarBuilder::default()
+            .entry(b'5', "model/", "", b"")
+            .file("model/conf/model.conf", b"--min-active=200\n")
+            .entry(b'2', "model/current.conf", "conf/model.conf", b"")
+            .finish();
+        let written = unpack_tar(archive.as_slice(), &dir, MAX_EXTRACTED_BYTES).unwrap();
+        assert_eq!(written, 17);
+        let linked = std::fs::read_to_string(dir.join("model/current.conf")).unwrap();
+        assert_eq!(linked, "--min-active=200\n");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parent_dir_escape_is_rejected() {
+        let archive = TarBuilder::default()
+            .file("model/ok.txt", b"fine")
+            .file("model/../../evil.txt", b"pwned")
+            .finish();
+        let (dir, err) = unpack_rejected("tar-dotdot", archive, MAX_EXTRACTED_BYTES);
+        assert!(err.contains("'model/../../evil.txt'"), "{err}");
+        assert_untouched(&dir);
+    }
+
+    #[test]
+    fn absolute_path_is_rejected() {
+        let archive = TarBuilder::default()
+            .file("/tmp/evil.txt", b"pwned")
+            .finish();
+        let (dir, err) = unpack_rejected("tar-abs", archive, MAX_EXTRACTED_BYTES);
+        assert_eq!(err, "Rejected archive entry '/tmp/evil.txt': absolute path");
+        assert_untouched(&dir);
+    }
+
+    #[test]
+    fn symlink_out_of_the_model_dir_is_rejected() {
+        let archive = TarBuilder::default()
+            .entry(b'2', "model/home", "../../..", b"")
+            .file("model/home/.bashrc", b"pwned")
+            .finish();
+        let (dir, err) = unpack_rejected("tar-symlink", archive, MAX_EXTRACTED_BYTES);
+        assert_eq!(
+            err,
+            "Rejected archive entry 'model/home': symlink points outside the model directory"
+        );
+        assert_untouched(&dir);
+    }
+
+    #[test]
+    fn symlink_chains_and_hard_links_cannot_escape() {
+        // "a" is the model dir itself, so "a/b" -> ".." would be its parent
+        let chained = TarBuilder::default()
+            .entry(b'2', "a", ".", b"")
+            .entry(b'2', "a/b", "..", b"")
+            .file("a/b/evil.txt", b"pwned")
+            .finish();
+        let (dir, err) = unpack_rejected("tar-chain", chained, MAX_EXTRACTED_BYTES);
+        assert_eq!(
+            err,
+            "Rejected archive entry 'a/b': path goes through a symlink"
+        );
+        assert_untouched(&dir);
+
+        // Lexically "model/a/../.." is the model dir; once "a" -> "." it is not
+        let late = TarBuilder::default()
+            .entry(b'2', "model/up", "a/../..", b"")
+            .entry(b'2', "model/a", ".", b"")
+            .finish();
+        let (dir, err) = unpack_rejected("tar-late-link", late, MAX_EXTRACTED_BYTES);
+        assert_eq!(
+            err,
+            "Rejected archive entry 'model/up': symlink climbs back out of a path it entered"
+        );
+        assert_untouched(&dir);
+
+        let hard = TarBuilder::default()
+            .file("model/a.txt", b"fine")
+            .entry(b'1', "model/b.txt", "model/a.txt", b"")
+            .finish();
+        let (dir, err) = unpack_rejected("tar-hardlink", hard, MAX_EXTRACTED_BYTES);
+        assert_eq!(
+            err,
+            "Rejected archive entry 'model/b.txt': hard links are not supported"
+        );
+        assert_untouched(&dir);
+    }
+
+    #[test]
+    fn oversized_archive_is_rejected() {
+        let archive = TarBuilder::default()
+            .file("model/a.bin", &[0u8; 600])
+            .file("model/b.bin", &[0u8; 600])
+            .finish();
+        let (dir, err) = unpack_rejected("tar-bomb", archive, 1000);
+        assert_eq!(
+            err,
+            "Rejected archive entry 'model/b.bin': archive expands past 1000 bytes"
+        );
+        assert_untouched(&dir);
+    }
+}
@@ -1,7 +1,37 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 const AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "flac", "ogg", "opus"];
 
+fn is_audio(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Audio files in `dir` and, `depth` levels down, in its folders; hidden
+/// ones are left out. Sorted by path.
+pub fn audio_files_in(dir: &Path, depth: usize) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in read_dir.flatten() {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            if depth > 0 {
+                files.extend(audio_files_in(&path, depth - 1));
+            }
+        } else if is_audio(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
 #[derive(Debug, Clone)]
 pub struct Entry {
     pub name: String,
@@ -13,6 +43,8 @@ pub struct FileBrowser {
     pub current_dir: PathBuf,
     pub entries: Vec<Entry>,
     pub selected: usize,
+    /// First entry the popup shows; kept by the draw code.
+    pub scroll: usize,
 }
 
 impl FileBrowser {
@@ -24,6 +56,7 @@ impl FileBrowser {
             current_dir: home,
             entries: Vec::new(),
             selected: 0,
+            scroll: 0,
         };
         fb.refresh();
         fb
@@ -48,14 +81,12 @@ impl FileBrowser {
                         path,
                         is_dir: true,
                     });
-                } else if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                    if AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
-                        files.push(Entry {
-                            name,
-                            path,
-                            is_dir: false,
-                        });
-                    }
+                } else if is_audio(&path) {
+                    files.push(Entry {
+                        name,
+                        path,
+                        is_dir: false,
+                    });
                 }
             }
         }
@@ -66,6 +97,7 @@ impl FileBrowser {
         self.entries = dirs;
         self.entries.extend(files);
         self.selected = 0;
+        self.scroll = 0;
     }
 
     pub fn navigate_parent(&mut self) {
@@ -110,6 +142,21 @@ impl FileBrowser {
         }
     }
 
+    /// Move the selection by `delta` entries, stopping at the first and last.
+    pub fn move_by(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// The highlighted folder, or the one being browsed when a file is
+    /// highlighted.
+    pub fn folder_to_add(&self) -> &Path {
+        match self.entries.get(self.selected) {
+            Some(entry) if entry.is_dir => &entry.path,
+            _ => &self.current_dir,
+        }
+    }
+
     pub fn entry_count(&self) -> usize {
         self.entries.len()
     }
@@ -0,0 +1,658 @@
+use anyhow::{bail, Context, Result};
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const LOG: crate::log::Logger = crate::log::log_scope("livestream");
+
+/// Opus always decodes at 48 kHz; playback audio is converted to this.
+const RATE: u32 = 48_000;
+const CHANNELS: usize = 2;
+/// Samples per channel in one 20 ms Opus frame.
+const FRAME: usize = RATE as usize / 50;
+const FRAME_TIME: Duration = Duration::from_millis(20);
+/// An Ogg page is closed every this many packets, so about every 100 ms.
+const PACKETS_PER_PAGE: u64 = 5;
+/// After running dry, this much audio is gathered before it goes out again,
+/// so a playback's uneven buffers don't come out chopped.
+const JITTER_MS: usize = 100;
+/// Queued audio beyond this means playback ran ahead of the stream clock;
+/// the oldest is dropped.
+const MAX_QUEUED_MS: usize = 1000;
+/// A client that has fallen this many bytes behind is dropped.
+const MAX_CLIENT_BACKLOG: usize = 256 * 1024;
+/// Listeners served at once, counting requests still being read; more are
+/// turned away rather than each getting a thread.
+const MAX_LISTENERS: usize = 16;
+/// Samples `feed` can hold for the server thread, which takes them every
+/// 20 ms: over a second of 48 kHz stereo.
+const TAP_CAPACITY: usize = 128 * 1024;
+const STREAM_PATH: &str = "/live.ogg";
+
+/// Where `feed` puts playback output while a `LiveServer` runs.
+static TAP: Mutex<Option<Tap>> = Mutex::new(None);
+static TAP_OPEN: AtomicBool = AtomicBool::new(false);
+
+/// Copy a block of post-FX playback output to the live stream, if one is
+/// running, scaled by `gain`: the volume PipeWire applies to the stream
+/// itself. Called from playback threads; never blocks or allocates.
+pub fn feed(samples: &[f32], gain: f32, rate: u32, channels: u32) {
+    if samples.is_empty() || !TAP_OPEN.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Ok(mut tap) = TAP.try_lock() {
+        if let Some(tap) = tap.as_mut() {
+            tap.push(samples, gain, rate, channels);
+        }
+    }
+}
+
+/// Playback output waiting for the server thread, in the playback's own
+/// format. A ring allocated once when the server starts.
+struct Tap {
+    ring: Box<[f32]>,
+    start: usize,
+    len: usize,
+    rate: u32,
+    channels: u32,
+}
+
+impl Tap {
+    fn new(capacity: usize) -> Self {
+        let ring = vec![0.0; capacity].into_boxed_slice();
+        Self {
+            ring,
+            start: 0,
+            len: 0,
+            rate: RATE,
+            channels: CHANNELS as u32,
+        }
+    }
+
+    /// Queue `samples` scaled by `gain`; whole frames that don't fit are
+    /// dropped. What is left of a previous format is dropped too: that
+    /// playback has ended.
+    fn push(&mut self, samples: &[f32], gain: f32, rate: u32, channels: u32) {
+        if (rate, channels) != (self.rate, self.channels) {
+            (self.rate, self.channels, self.len) = (rate, channels, 0);
+        }
+        let frame = channels.max(1) as usize;
+        let fits = samples.len().min(self.ring.len() - self.len) / frame * frame;
+        let capacity = self.ring.len();
+        for (i, sample) in samples[..fits].iter().enumerate() {
+            self.ring[(self.start + self.len + i) % capacity] = sample * gain;
+        }
+        self.len += fits;
+    }
+
+    /// Move everything queued to the end of `out`; returns its rate and
+    /// channel count.
+    fn take(&mut self, out: &mut Vec<f32>) -> (u32, u32) {
+        let end = self.start + self.len;
+        let capacity = self.ring.len();
+        out.extend_from_slice(&self.ring[self.start..end.min(capacity)]);
+        out.extend_from_slice(&self.ring[..end.saturating_sub(capacity)]);
+        (self.start, self.len) = (end % capacity.max(1), 0);
+        (self.rate, self.channels)
+    }
+}
+
+/// Serves `GET /live.ogg`: an endless Ogg/Opus stream of whatever the daemon
+/// plays, and silence in between. Stopped on drop, which ends every
+/// client's stream with an end-of-stream page.
+pub struct LiveServer {
+    address: String,
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl LiveServer {
+    pub fn start(address: &str) -> Result<Self> {
+        let listener = TcpListener::bind(address)
+            .with_context(|| format!("Failed to listen on {address} for the live stream"))?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let mut encoder =
+            opus::Encoder::new(RATE, opus::Channels::Stereo, opus::Application::Audio)
+                .context("Failed to create the Opus encoder")?;
+        let pre_skip = encoder
+            .get_lookahead()
+            .unwrap_or(0)
+            .clamp(0, u16::MAX as i32) as u16;
+
+        *TAP.lock().unwrap() = Some(Tap::new(TAP_CAPACITY));
+        TAP_OPEN.store(true, Ordering::Relaxed);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = std::thread::spawn(move || {
+            serve(listener, encoder, pre_skip, &thread_stop);
+        });
+        Ok(Self {
+            address: address.to_string(),
+            local_addr,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// The address from the config, as passed to `start`.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Where listeners find the stream, with the port actually bound.
+    pub fn url(&self) -> String {
+        format!("http://{}{STREAM_PATH}", self.local_addr)
+    }
+}
+
+impl Drop for LiveServer {
+    fn drop(&mut self) {
+        TAP_OPEN.store(false, Ordering::Relaxed);
+        TAP.lock().unwrap().take();
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Paces out one Opus frame every 20 ms to every client, from queued
+/// playback audio or silence, until `stop` is set.
+fn serve(listener: TcpListener, mut encoder: opus::Encoder, pre_skip: u16, stop: &AtomicBool) {
+    let (client_tx, client_rx) = std::sync::mpsc::channel();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let mut clients: Vec<Client> = Vec::new();
+    let mut audio = AudioQueue::default();
+    let mut fed = Vec::with_capacity(TAP_CAPACITY);
+    let mut frame = vec![0.0f32; FRAME * CHANNELS];
+    let mut packet = vec![0u8; 4000];
+    let mut next = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        accept(&listener, &client_tx, &requests, clients.len());
+        clients.extend(
+            client_rx
+                .try_iter()
+                .map(|socket| Client::new(socket, pre_skip)),
+        );
+        // Copied out so `feed` finds the lock free again right away
+        let format = TAP.lock().unwrap().as_mut().map(|tap| tap.take(&mut fed));
+        if let Some((rate, channels)) = format.filter(|_| !fed.is_empty()) {
+            audio.push(&fed, rate, channels);
+        }
+        fed.clear();
+
+        audio.take_frame(&mut frame);
+        // Without listeners the audio is still taken, to stay in real time
+        if !clients.is_empty() {
+            match encoder.encode_float(&frame, &mut packet) {
+                Ok(len) => clients.retain_mut(|c| c.send(&packet[..len], false)),
+                Err(e) => LOG.error(&format!("Live stream: Opus encoding failed: {e}")),
+            }
+        }
+
+        next += FRAME_TIME;
+        let now = Instant::now();
+        match next.checked_duration_since(now) {
+            Some(wait) => std::thread::sleep(wait),
+            // Fell far behind (suspend, overload); pick up from now
+            None if now - next > FRAME_TIME * 10 => next = now,
+            None => {}
+        }
+    }
+
+    // Close every stream properly with a last silent frame
+    frame.fill(0.0);
+    if let Ok(len) = encoder.encode_float(&frame, &mut packet) {
+        for mut client in clients {
+            client.send(&packet[..len], true);
+            client.close();
+        }
+    }
+}
+
+/// Take pending connections; each reads its request on its own thread and,
+/// for the stream path, comes back through `client_tx`. `requests` counts
+/// those threads; with `clients` they make up the `MAX_LISTENERS`, and
+/// connections past that are answered 503 straight away.
+fn accept(
+    listener: &TcpListener,
+    client_tx: &Sender<TcpStream>,
+    requests: &Arc<AtomicUsize>,
+    clients: usize,
+) {
+    loop {
+        let full = clients + requests.load(Ordering::Relaxed) >= MAX_LISTENERS;
+        match listener.accept() {
+            Ok((mut socket, peer)) if full => {
+                LOG.info(&format!(
+                    "Live stream: turned away {peer}, at {MAX_LISTENERS} listeners"
+                ));
+                // Taking the request that is already in keeps closing from
+                // resetting the connection before the answer is read
+                let _ = socket.set_nonblocking(true);
+                let _ = socket.read(&mut [0u8; 1024]);
+                let _ = socket.write_all(
+                    b"HTTP/1.1 503 Service Unavailable\r\n\
+                      Content-Length: 0\r\n\
+                      Connection: close\r\n\r\n",
+                );
+                let _ = socket.shutdown(std::net::Shutdown::Write);
+            }
+            Ok((socket, _)) => {
+                let client_tx = client_tx.clone();
+                let requests = requests.clone();
+                requests.fetch_add(1, Ordering::Relaxed);
+                std::thread::spawn(move || {
+                    match answer_request(socket) {
+                        Ok(Some(socket)) => {
+                            let _ = client_tx.send(socket);
+                        }
+                        Ok(None) => {}
+                        Err(e) => LOG.info(&format!("Live stream: dropped a request: {e}")),
+                    }
+                    requests.fetch_sub(1, Ordering::Relaxed);
+                });
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+            Err(e) => {
+                LOG.error(&format!("Live stream: accept failed: {e}"));
+                return;
+            }
+        }
+    }
+}
+
+/// Read the HTTP request and answer it. The socket comes back, ready for
+/// the stream, only for `GET /live.ogg`.
+fn answer_request(mut socket: TcpStream) -> Result<Option<TcpStream>> {
+    socket.set_nonblocking(false)?;
+    socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = socket.read(&mut buf)?;
+        if n == 0 {
+            bail!("connection closed mid-request");
+        }
+        request.extend_from_slice(&buf[..n]);
+        if request.len() > 16 * 1024 {
+            bail!("request headers too large");
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut words = request.lines().next().unwrap_or("").split_whitespace();
+    let (method, target) = (words.next(), words.next().unwrap_or(""));
+    // Browser sources add query strings to defeat caching
+    let path = target.split('?').next().unwrap_or("");
+    if method != Some("GET") || path != STREAM_PATH {
+        socket.write_all(
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        )?;
+        return Ok(None);
+    }
+    socket.write_all(
+        b"HTTP/1.1 200 OK\r\n\
+          Content-Type: audio/ogg\r\n\
+          Cache-Control: no-cache, no-store\r\n\
+          Access-Control-Allow-Origin: *\r\n\
+          Connection: close\r\n\r\n",
+    )?;
+    socket.set_nonblocking(true)?;
+    Ok(Some(socket))
+}
+
+/// Playback audio converted to 48 kHz stereo, waiting to be encoded.
+#[derive(Default)]
+struct AudioQueue {
+    samples: VecDeque<f32>,
+    resampler: Option<Resampler>,
+    /// Ran dry and is gathering `JITTER_MS` before playing again.
+    refilling: bool,
+    last_push: Option<Instant>,
+}
+
+impl AudioQueue {
+    /// Queue a block of playback output in its own format.
+    fn push(&mut self, samples: &[f32], rate: u32, channels: u32) {
+        let format = (rate.max(1), channels.max(1));
+        let resampler = match &mut self.resampler {
+            Some(r) if (r.rate, r.channels) == format => r,
+            slot => slot.insert(Resampler::new(format.0, format.1)),
+        };
+        resampler.convert(samples, &mut self.samples);
+        let max = RATE as usize * CHANNELS * MAX_QUEUED_MS / 1000;
+        if self.samples.len() > max {
+            let excess = self.samples.len() - max;
+            self.samples.drain(..excess);
+        }
+        self.last_push = Some(Instant::now());
+    }
+
+    /// Fill `frame` with the next 20 ms, padding with silence. A clip's end
+    /// goes out as soon as nothing more arrives.
+    fn take_frame(&mut self, frame: &mut [f32]) {
+        let jitter = RATE as usize * CHANNELS * JITTER_MS / 1000;
+        let quiet = self
+            .last_push
+            .is_none_or(|t| t.elapsed().as_millis() as usize > JITTER_MS);
+        if self.samples.is_empty() {
+            self.refilling = true;
+        } else if self.samples.len() >= jitter || quiet {
+            self.refilling = false;
+        }
+        let n = if self.refilling {
+            0
+        } else {
+            self.samples.len().min(frame.len())
+        };
+        for (out, sample) in frame.iter_mut().zip(self.samples.drain(..n)) {
+            *out = sample;
+        }
+        frame[n..].fill(0.0);
+    }
+}
+
+/// Linear-interpolating conversion of one playback format to 48 kHz
+/// stereo. Mono is doubled; channels past the second are dropped.
+struct Resampler {
+    rate: u32,
+    channels: u32,
+    /// Source position of the next output frame, counted from `last`.
+    pos: f64,
+    /// Final frame of the previous block.
+    last: [f32; 2],
+}
+
+impl Resampler {
+    fn new(rate: u32, channels: u32) -> Self {
+        Self {
+            rate,
+            channels,
+            pos: 1.0,
+            last: [0.0; 2],
+        }
+    }
+
+    fn convert(&mut self, samples: &[f32], out: &mut VecDeque<f32>) {
+        let channels = self.channels as usize;
+        let frames: Vec<[f32; 2]> = samples
+            .chunks_exact(channels)
+            .map(|f| {
+                if channels == 1 {
+                    [f[0], f[0]]
+                } else {
+                    [f[0], f[1]]
+                }
+            })
+            .collect();
+        let Some(&end) = frames.last() else {
+            return;
+        };
+        // Index 0 is `last`, index i the block's frame i - 1
+        let at = |i: usize| if i == 0 { self.last } else { frames[i - 1] };
+        let step = self.rate as f64 / RATE as f64;
+        while self.pos <= frames.len() as f64 {
+            let i = self.pos as usize;
+            let t = (self.pos - i as f64) as f32;
+            let (a, b) = (at(i), if t > 0.0 { at(i + 1) } else { at(i) });
+            out.extend([a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]);
+            self.pos += step;
+        }
+        self.pos -= frames.len() as f64;
+        self.last = end;
+    }
+}
+
+/// One listener, with its own Ogg stream: headers first, then the shared
+/// Opus packets under its own serial number and sample count.
+struct Client {
+    socket: TcpStream,
+    /// Pages not yet taken by the socket collect in the inner buffer.
+    pages: PacketWriter<'static, Vec<u8>>,
+    serial: u32,
+    packets: u64,
+}
+
+impl Client {
+    fn new(socket: TcpStream, pre_skip: u16) -> Self {
+        use std::hash::BuildHasher;
+        let serial = std::collections::hash_map::RandomState::new().hash_one(Instant::now()) as u32;
+        let mut client = Self {
+            socket,
+            pages: PacketWriter::new(Vec::new()),
+            serial,
+            packets: 0,
+        };
+        // Each header gets a page of its own, as Ogg Opus requires
+        for header in [opus_head(pre_skip), opus_tags()] {
+            let _ = client
+                .pages
+                .write_packet(header, serial, PacketWriteEndInfo::EndPage, 0);
+        }
+        client
+    }
+
+    /// Queue `packet` and write what the socket takes. False once the
+    /// listener is gone or too far behind.
+    fn send(&mut self, packet: &[u8], last: bool) -> bool {
+        self.packets += 1;
+        let end = if last {
+            PacketWriteEndInfo::EndStream
+        } else if self.packets.is_multiple_of(PACKETS_PER_PAGE) {
+            PacketWriteEndInfo::EndPage
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        let granule = self.packets * FRAME as u64;
+        if self
+            .pages
+            .write_packet(packet.to_vec(), self.serial, end, granule)
+            .is_err()
+        {
+            return false;
+        }
+        let pending = self.pages.inner_mut();
+        while !pending.is_empty() {
+            match self.socket.write(pending) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    pending.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => return false,
+            }
+        }
+        pending.len() <= MAX_CLIENT_BACKLOG
+    }
+
+    /// Flush the last pages, waiting briefly, and hang up.
+    fn close(mut self) {
+        let _ = self.socket.set_nonblocking(false);
+        let _ = self.socket.set_write_timeout(Some(Duration::from_secs(1)));
+        let _ = self.socket.write_all(self.pages.inner());
+        let _ = self.socket.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+/// Identification header: version 1, stereo, no channel mapping.
+fn opus_head(pre_skip: u16) -> Vec<u8> {
+    let mut head = b"OpusHead".to_vec();
+    head.push(1);
+    head.push(CHANNELS as u8);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&RATE.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes());
+    head.push(0);
+    head
+}
+
+fn opus_tags() -> Vec<u8> {
+    let vendor = b"plentysound";
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes());
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ogg pages split out of a byte stream: (flags, granule, serial,
+    /// sequence, packet bytes).
+    fn pages(mut bytes: &[u8]) -> Vec<(u8, u64, u32, u32, Vec<u8>)> {
+        let mut pages = Vec::new();
+        while !bytes.is_empty() {
+            assert_eq!(&bytes[..5], b"OggS\0", "lost page sync");
+            let flags = bytes[5];
+            let granule = u64::from_le_bytes(bytes[6..14].try_into().unwrap());
+            let serial = u32::from_le_bytes(bytes[14..18].try_into().unwrap());
+            let sequence = u32::from_le_bytes(bytes[18..22].try_into().unwrap());
+            let segments = bytes[26] as usize;
+            let body: usize = bytes[27..27 + segments].iter().map(|&l| l as usize).sum();
+            let start = 27 + segments;
+            pages.push((
+                flags,
+                granule,
+                serial,
+                sequence,
+                bytes[start..start + body].to_vec(),
+            ));
+            bytes = &bytes[start + body..];
+        }
+        pages
+    }
+
+    fn get(addr: SocketAddr, path: &str) -> TcpStream {
+        let mut socket = TcpStream::connect(addr).unwrap();
+        write!(socket, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        socket
+    }
+
+    #[test]
+    fn resampling_reaches_48k_stereo() {
+        let mut out = VecDeque::new();
+        let mut mono = Resampler::new(24_000, 1);
+        // Split across blocks like a playback's buffers
+        mono.convert(&[0.5; 100], &mut out);
+        mono.convert(&[0.5; 140], &mut out);
+        assert!((out.len() as i64 - 480 * 2).abs() <= 4, "{}", out.len());
+        assert!(out.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+
+        out.clear();
+        let mut native = Resampler::new(48_000, 2);
+        native.convert(&[0.1, -0.1, 0.2, -0.2, 0.3, -0.3], &mut out);
+        assert_eq!(Vec::from(out), [0.1, -0.1, 0.2, -0.2, 0.3, -0.3]);
+    }
+
+    #[test]
+    fn the_tap_wraps_around_and_drops_what_does_not_fit() {
+        let mut tap = Tap::new(8);
+        let mut out = Vec::new();
+        tap.push(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 0.5, 44_100, 2);
+        assert_eq!(tap.take(&mut out), (44_100, 2));
+        assert_eq!(out, [0.5, 1.0, 1.5, 2.0, 2.5, 3.0]);
+
+        // Across the end of the ring, and only the whole frames that fit
+        out.clear();
+        tap.push(&[1.0; 6], 1.0, 44_100, 2);
+        tap.push(&[2.0; 4], 1.0, 44_100, 2);
+        tap.take(&mut out);
+        assert_eq!(out, [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 2.0, 2.0]);
+
+        // A new format replaces what is left of the old one
+        out.clear();
+        tap.push(&[1.0; 4], 1.0, 44_100, 2);
+        tap.push(&[3.0; 3], 1.0, 48_000, 1);
+        assert_eq!(tap.take(&mut out), (48_000, 1));
+        assert_eq!(out, [3.0; 3]);
+    }
+
+    #[test]
+    fn listeners_past_the_cap_are_turned_away() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client_tx, client_rx) = std::sync::mpsc::channel();
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        // One place left: the first takes it while its request is still
+        // coming, so the second gets a 503
+        let mut first = TcpStream::connect(addr).unwrap();
+        let mut second = get(addr, STREAM_PATH);
+        std::thread::sleep(Duration::from_millis(100));
+        accept(&listener, &client_tx, &requests, MAX_LISTENERS - 1);
+        let mut reply = String::new();
+        second.read_to_string(&mut reply).unwrap();
+        assert!(reply.starts_with("HTTP/1.1 503"), "{reply}");
+
+        write!(first, "GET {STREAM_PATH} HTTP/1.1\r\n\r\n").unwrap();
+        assert!(client_rx.recv_timeout(Duration::from_secs(5)).is_ok());
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(requests.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn quiet_stream_is_headers_then_paged_silence_then_end_of_stream() {
+        let server = LiveServer::start("127.0.0.1:0").unwrap();
+        let addr = server.local_addr;
+        assert_eq!(server.url(), format!("http://{addr}/live.ogg"));
+
+        let mut other = get(addr, "/index.html");
+        let mut reply = String::new();
+        other.read_to_string(&mut reply).unwrap();
+        assert!(reply.starts_with("HTTP/1.1 404"), "{reply}");
+
+        let mut socket = get(addr, "/live.ogg?nocache=1");
+        std::thread::sleep(Duration::from_millis(400));
+        // Some real audio, stereo at 44.1 kHz, in among the silence
+        feed(&[0.25; 4410 * 2], 1.0, 44_100, 2);
+        std::thread::sleep(Duration::from_millis(300));
+        drop(server);
+        let mut body = Vec::new();
+        socket.read_to_end(&mut body).unwrap();
+
+        let split = body.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let head = String::from_utf8_lossy(&body[..split]);
+        assert!(head.starts_with("HTTP/1.1 200 OK"), "{head}");
+        assert!(head.contains("Content-Type: audio/ogg"), "{head}");
+
+        let pages = pages(&body[split..]);
+        assert!(pages.len() > 4, "only {} pages", pages.len());
+        let serial = pages[0].2;
+        for (i, page) in pages.iter().enumerate() {
+            assert_eq!((page.2, page.3), (serial, i as u32), "page {i}");
+        }
+        // Headers on pages of their own, the first one opening the stream
+        assert_eq!(pages[0].0, 2);
+        assert!(pages[0].4.starts_with(b"OpusHead"));
+        assert_eq!(pages[0].4[9], 2);
+        assert_eq!(
+            u32::from_le_bytes(pages[0].4[12..16].try_into().unwrap()),
+            RATE
+        );
+        assert!(pages[1].4.starts_with(b"OpusTags"));
+        assert_eq!((pages[0].1, pages[1].1), (0, 0));
+        // Audio pages count whole 20 ms frames and only the last ends the stream
+        let audio = &pages[2..];
+        assert!(audio.windows(2).all(|w| w[0].1 < w[1].1));
+        assert!(audio.iter().all(|p| p.1 % FRAME as u64 == 0 && p.1 > 0));
+        assert!(audio[..audio.len() - 1].iter().all(|p| p.0 & 4 == 0));
+        assert_eq!(audio[audio.len() - 1].0 & 4, 4);
+        // Once the server is gone nothing feeds it
+        assert!(!TAP_OPEN.load(Ordering::Relaxed));
+    }
+}
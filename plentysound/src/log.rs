@@ -1,19 +1,68 @@
-use std::fs::OpenOptions;
+use crate::protocol::LogLevel;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Write as _};
+use std::fs::{File, OpenOptions};
 use std::io::Write;
-use std::path::PathBuf;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 static LOG_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+// Only the daemon reads the config's `log_rotation` and sets this; the TUI
+// and one-shot commands leave it `None` and just append, so the file never
+// rotates by limits other than the configured ones.
+static ROTATION: Mutex<Option<LogRotation>> = Mutex::new(None);
+
+/// When the log moves aside to `plentysound.log.1`, and how many such
+/// files are kept (`.1` newest). `max_bytes: 0` never rotates.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct LogRotation {
+    pub max_bytes: u64,
+    pub keep: usize,
+}
+
+impl LogRotation {
+    const DEFAULT: Self = Self {
+        max_bytes: 5 * 1024 * 1024,
+        keep: 3,
+    };
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Rotate the log by `rotation` from now on; until called, this process
+/// never rotates it.
+pub fn set_rotation(rotation: LogRotation) {
+    *ROTATION.lock().unwrap() = Some(rotation);
+}
+
+/// Write lines up to `level` from now on.
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> LogLevel {
+    LogLevel::ALL[usize::from(LEVEL.load(Ordering::Relaxed))]
+}
 
 fn log_path() -> PathBuf {
-    let mut path = if let Some(dir) = std::env::var_os("XDG_DATA_HOME") {
+    let data_dir = if let Some(dir) = std::env::var_os("XDG_DATA_HOME") {
         PathBuf::from(dir)
     } else if let Some(home) = std::env::var_os("HOME") {
         PathBuf::from(home).join(".local/share")
     } else {
         PathBuf::from(".")
     };
-    path.push("plentysound");
+    let mut path = crate::protocol::instance().dir_in(data_dir);
     let _ = std::fs::create_dir_all(&path);
     path.push("plentysound.log");
     path
@@ -24,28 +73,276 @@ pub fn open_log_file() -> Option<std::fs::File> {
     OpenOptions::new().create(true).append(true).open(&path).ok()
 }
 
-pub fn log_info(msg: &str) {
-    log_write("INFO", msg);
+/// Writes log lines tagged with the part of plentysound they come from.
+#[derive(Clone, Copy, Debug)]
+pub struct Logger {
+    component: &'static str,
+}
+
+/// A logger for `component`, e.g. `daemon`, `pw`, `detector` or `client`.
+pub const fn log_scope(component: &'static str) -> Logger {
+    Logger { component }
+}
+
+impl Logger {
+    pub fn debug(self, msg: &str) {
+        self.log(LogLevel::Debug, msg, &[]);
+    }
+
+    pub fn info(self, msg: &str) {
+        self.log(LogLevel::Info, msg, &[]);
+    }
+
+    pub fn warn(self, msg: &str) {
+        self.log(LogLevel::Warn, msg, &[]);
+    }
+
+    pub fn error(self, msg: &str) {
+        self.log(LogLevel::Error, msg, &[]);
+    }
+
+    /// `msg` followed by `key=value` for each field, e.g.
+    /// `Trigger timing word=bomba total_ms=230`.
+    pub fn log(self, level: LogLevel, msg: &str, fields: &[(&str, &dyn Display)]) {
+        if level > self::level() {
+            return;
+        }
+        let path = {
+            let mut cached = LOG_FILE.lock().unwrap();
+            if cached.is_none() {
+                *cached = Some(log_path());
+            }
+            cached.clone().unwrap()
+        };
+        let thread = std::thread::current();
+        let thread = match thread.name() {
+            Some(name) => name.to_string(),
+            None => format!("{:?}", thread.id()),
+        };
+        let line = format_line(
+            SystemTime::now(),
+            level,
+            self.component,
+            &thread,
+            msg,
+            fields,
+        );
+        let rotation = *ROTATION.lock().unwrap();
+        let _ = append(&path, &line, rotation);
+    }
+}
+
+/// `[<RFC 3339 local time>] [LEVEL] [component] [thread] msg key=value...`
+fn format_line(
+    at: SystemTime,
+    level: LogLevel,
+    component: &str,
+    thread: &str,
+    msg: &str,
+    fields: &[(&str, &dyn Display)],
+) -> String {
+    let level = level.name().to_uppercase();
+    let mut line = format!(
+        "[{}] [{level}] [{component}] [{thread}] {msg}",
+        local_timestamp(at)
+    );
+    for (key, value) in fields {
+        let _ = write!(line, " {key}={value}");
+    }
+    line.push('\n');
+    line
+}
+
+/// `at` in local time as RFC 3339 with milliseconds, e.g.
+/// `2026-10-17T14:03:05.123+02:00`.
+fn local_timestamp(at: SystemTime) -> String {
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        return format!(
+            "{}.{:03}",
+            since_epoch.as_secs(),
+            since_epoch.subsec_millis()
+        );
+    }
+    let offset_mins = tm.tm_gmtoff / 60;
+    let sign = if offset_mins < 0 { '-' } else { '+' };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}{sign}{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        since_epoch.subsec_millis(),
+        offset_mins.abs() / 60,
+        offset_mins.abs() % 60,
+    )
 }
 
-pub fn log_error(msg: &str) {
-    log_write("ERROR", msg);
+/// Append `line` to the log at `path`, first moving the log aside if the
+/// line would take it past `rotation.max_bytes`; with no `rotation` it only
+/// appends. The daemon, the TUI and other instances' tools may share the
+/// file, so this holds an flock on it throughout and only one of them rotates.
+fn append(path: &Path, line: &str, rotation: Option<LogRotation>) -> std::io::Result<()> {
+    let mut file = open_locked(path)?;
+    let size = file.metadata()?.len();
+    let past_cap = |r: &LogRotation| r.max_bytes > 0 && size + line.len() as u64 > r.max_bytes;
+    if let Some(rotation) = rotation.filter(|r| size > 0 && past_cap(r)) {
+        rotate(path, rotation.keep)?;
+        let fresh = open_locked(path)?;
+        follow_on_stderr(&file, &fresh);
+        file = fresh;
+    }
+    file.write_all(line.as_bytes())
 }
 
-fn log_write(level: &str, msg: &str) {
-    let path = {
-        let mut cached = LOG_FILE.lock().unwrap();
-        if cached.is_none() {
-            *cached = Some(log_path());
+/// Open the log and lock it, making sure the lock is on the file still at
+/// `path` and not one someone rotated away in the meantime.
+fn open_locked(path: &Path) -> std::io::Result<File> {
+    loop {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let same_file = std::fs::metadata(path)
+            .is_ok_and(|m| file.metadata().is_ok_and(|f| f.ino() == m.ino()));
+        if same_file {
+            return Ok(file);
         }
-        cached.clone().unwrap()
+    }
+}
+
+/// Shift `path.1` .. `path.{keep-1}` up by one, dropping the oldest, and
+/// move `path` to `path.1`.
+fn rotate(path: &Path, keep: usize) -> std::io::Result<()> {
+    let numbered = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
     };
+    if keep == 0 {
+        return std::fs::remove_file(path);
+    }
+    for n in (1..keep).rev() {
+        match std::fs::rename(numbered(n), numbered(n + 1)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    std::fs::rename(path, numbered(1))
+}
+
+/// A daemon started by the TUI has the log as its stderr; point that at
+/// the new file too, so panics don't land in the rotated one.
+fn follow_on_stderr(old: &File, fresh: &File) {
+    let Ok(old) = old.metadata() else { return };
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(libc::STDERR_FILENO, &mut stat) } != 0 {
+        return;
+    }
+    if stat.st_dev as u64 == old.dev() && stat.st_ino as u64 == old.ino() {
+        unsafe { libc::dup2(fresh.as_raw_fd(), libc::STDERR_FILENO) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::tests::scratch_dir;
+
+    fn size(path: &Path) -> Option<u64> {
+        std::fs::metadata(path).ok().map(|m| m.len())
+    }
+
+    #[test]
+    fn lines_carry_a_local_timestamp_the_source_and_fields() {
+        let at = UNIX_EPOCH + std::time::Duration::from_millis(1_760_000_000_123);
+        let fields: [(&str, &dyn Display); 2] = [("chunk", &123), ("rms", &2041)];
+        let line = format_line(at, LogLevel::Warn, "detector", "main", "Chunk", &fields);
+
+        let (stamp, rest) = line.strip_prefix('[').unwrap().split_once(']').unwrap();
+        assert_eq!(rest, " [WARN] [detector] [main] Chunk chunk=123 rms=2041\n");
+        // The zone is whatever this machine's is, so only the shape is fixed
+        let b = stamp.as_bytes();
+        assert_eq!(
+            stamp.len(),
+            "2025-10-09T08:53:20.123+00:00".len(),
+            "{stamp}"
+        );
+        assert_eq!(
+            (b[4], b[10], b[19], b[26]),
+            (b'-', b'T', b'.', b':'),
+            "{stamp}"
+        );
+        assert_eq!(&stamp[20..23], "123");
+        assert!(matches!(b[23], b'+' | b'-'), "{stamp}");
+    }
+
+    #[test]
+    fn the_log_moves_aside_before_it_outgrows_the_cap() {
+        let dir = scratch_dir("logrotate");
+        let path = dir.join("plentysound.log");
+        let rotation = Some(LogRotation {
+            max_bytes: 100,
+            keep: 2,
+        });
+        let line = format!("{}\n", "x".repeat(29));
+        for _ in 0..10 {
+            append(&path, &line, rotation).unwrap();
+        }
+
+        // Three lines fit under the cap; the fourth starts a new file
+        assert_eq!(size(&path), Some(30));
+        assert_eq!(size(&dir.join("plentysound.log.1")), Some(90));
+        assert_eq!(size(&dir.join("plentysound.log.2")), Some(90));
+        assert_eq!(size(&dir.join("plentysound.log.3")), None);
+
+        // A line bigger than the cap still goes out, on a file of its own
+        let long = format!("{}\n", "y".repeat(150));
+        append(&path, &long, rotation).unwrap();
+        append(&path, &long, rotation).unwrap();
+        assert_eq!(size(&path), Some(151));
+        assert_eq!(size(&dir.join("plentysound.log.1")), Some(151));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn writers_holding_the_old_file_dont_disturb_the_new_one() {
+        let dir = scratch_dir("logshared");
+        let path = dir.join("plentysound.log");
+        let rotation = Some(LogRotation {
+            max_bytes: 10,
+            keep: 1,
+        });
+        append(&path, "before\n", rotation).unwrap();
+        // Like the stderr a spawned daemon inherits
+        let mut child = OpenOptions::new().append(true).open(&path).unwrap();
+
+        append(&path, "after\n", rotation).unwrap();
+        child.write_all(b"late\n").unwrap();
+        append(&path, "again\n", rotation).unwrap();
+
+        let read = |p: &Path| std::fs::read_to_string(p).unwrap();
+        assert_eq!(read(&dir.join("plentysound.log.1")), "after\n");
+        assert_eq!(read(&path), "again\n");
+        assert_eq!(size(&dir.join("plentysound.log.2")), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn without_a_rotation_the_log_only_grows() {
+        let dir = scratch_dir("lognorotate");
+        let path = dir.join("plentysound.log");
+        let line = format!("{}\n", "z".repeat(99));
+        for _ in 0..3 {
+            append(&path, &line, None).unwrap();
+        }
 
-    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-        let _ = writeln!(file, "[{timestamp}] [{level}] {msg}");
+        assert_eq!(size(&path), Some(300));
+        assert_eq!(size(&dir.join("plentysound.log.1")), None);
+        let _ = std::fs::remove_dir_all(&dir);
     }
 }
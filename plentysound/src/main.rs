@@ -3,21 +3,59 @@ mod audio;
 mod client;
 mod daemon;
 mod event;
+#[cfg(feature = "transcriber")]
+mod extract;
 mod filebrowser;
+#[cfg(feature = "live-stream")]
+mod livestream;
 mod log;
+mod mix;
+#[cfg(feature = "mpris")]
+mod mpris;
+mod notify;
+mod pcm;
 mod pipewire;
 mod protocol;
+mod ring;
+mod sidechain;
 mod textinput;
+mod theme;
 mod tray;
+#[cfg(feature = "transcriber")]
+mod tts;
 mod ui;
+mod waveform;
+
+// Lives under tests/ but needs the crate's internals, which a binary crate
+// doesn't expose to integration tests
+#[cfg(test)]
+#[path = "../tests/feature_matrix.rs"]
+mod feature_matrix;
 
 use anyhow::Result;
 
 fn main() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let instance = protocol::Instance::from_args(&mut args, |key| std::env::var(key).ok())?;
+    protocol::set_instance(instance);
     match args.get(1).map(|s| s.as_str()) {
-        Some("daemon") => daemon::run_daemon(),
+        Some("daemon") => match &args[2..] {
+            [] => daemon::run_daemon(false),
+            [flag] if flag == "--verbose" => daemon::run_daemon(true),
+            _ => anyhow::bail!("Usage: plentysound daemon [--verbose]"),
+        },
         Some("stop") => client::send_stop(),
+        Some("stop-playback") => client::send_stop_playback(&args[2..]),
+        Some("songs") => client::run_songs_command(&args[2..]),
+        Some("play") => client::send_play(args.get(2).map(String::as_str)),
+        Some("list") => client::print_list(&args[2..]),
+        Some("volume") => client::send_volume(args.get(2).map(String::as_str)),
+        Some("status") => client::print_status(&args[2..]),
+        Some("log-level") => client::send_log_level(args.get(2).map(String::as_str)),
+        #[cfg(feature = "transcriber")]
+        Some("trigger") => client::send_trigger(args.get(2).map(String::as_str)),
+        #[cfg(feature = "transcriber")]
+        Some("capture") => client::send_capture(args.get(2).map(String::as_str)),
         _ => client::run_or_start(),
     }
 }
@@ -0,0 +1,617 @@
+//! Shaping a playback's samples on their way into the buffer: volume, the
+//! stop fade, the three-band EQ and the limiter, with comfort noise added
+//! on top. Nothing here depends on PipeWire or the rest of the crate, so
+//! benches/mix.rs can build it in by path.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Sections of the Audio FX equalizer: a low shelf, a mid peak at 1 kHz and
+/// a high shelf.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EqBand {
+    Low,
+    Mid,
+    High,
+}
+
+impl EqBand {
+    pub const ALL: [EqBand; 3] = [EqBand::Low, EqBand::Mid, EqBand::High];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EqBand::Low => "low",
+            EqBand::Mid => "mid",
+            EqBand::High => "high",
+        }
+    }
+}
+
+/// Linear gain per EQ band. 1.0 leaves a band flat and so does 0.0, which
+/// switches it off.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct EqGains {
+    pub low: f32,
+    pub mid: f32,
+    pub high: f32,
+}
+
+impl Default for EqGains {
+    // The mid band keeps the boost the single EQ knob used to default to
+    fn default() -> Self {
+        Self {
+            low: 1.0,
+            mid: 1.5,
+            high: 1.0,
+        }
+    }
+}
+
+impl EqGains {
+    pub fn get(&self, band: EqBand) -> f32 {
+        match band {
+            EqBand::Low => self.low,
+            EqBand::Mid => self.mid,
+            EqBand::High => self.high,
+        }
+    }
+
+    pub fn get_mut(&mut self, band: EqBand) -> &mut f32 {
+        match band {
+            EqBand::Low => &mut self.low,
+            EqBand::Mid => &mut self.mid,
+            EqBand::High => &mut self.high,
+        }
+    }
+}
+
+/// Color of the comfort noise. Pink noise falls off 3 dB per octave, which
+/// sounds like room tone rather than hiss.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NoiseKind {
+    #[default]
+    White,
+    Pink,
+    Off,
+}
+
+impl NoiseKind {
+    pub const ALL: [NoiseKind; 3] = [NoiseKind::White, NoiseKind::Pink, NoiseKind::Off];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NoiseKind::White => "white",
+            NoiseKind::Pink => "pink",
+            NoiseKind::Off => "off",
+        }
+    }
+
+    /// The kind after this one in `ALL`, wrapping around.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+/// Samples above this go through the soft limiter's knee.
+pub const LIMIT_THRESHOLD: f32 = 0.9;
+
+/// Accumulates output samples between throttled level reports.
+pub struct LevelMeter {
+    peak: f32,
+    sum_sq: f32,
+    count: usize,
+    limiting: bool,
+    last_report: std::time::Instant,
+}
+
+impl LevelMeter {
+    pub fn new() -> Self {
+        Self {
+            peak: 0.0,
+            sum_sq: 0.0,
+            count: 0,
+            limiting: false,
+            last_report: std::time::Instant::now(),
+        }
+    }
+
+    pub fn add(&mut self, samples: &[f32]) {
+        // Eight running sums rather than one, so the loop vectorizes instead
+        // of waiting on each add
+        let mut peak = [0.0f32; 8];
+        let mut sum_sq = [0.0f32; 8];
+        let chunks = samples.chunks_exact(8);
+        let rest = chunks.remainder();
+        for chunk in chunks {
+            for (i, &sample) in chunk.iter().enumerate() {
+                peak[i] = peak[i].max(sample.abs());
+                sum_sq[i] += sample * sample;
+            }
+        }
+        for (i, &sample) in rest.iter().enumerate() {
+            peak[i] = peak[i].max(sample.abs());
+            sum_sq[i] += sample * sample;
+        }
+        self.peak = peak.into_iter().fold(self.peak, f32::max);
+        self.sum_sq += sum_sq.into_iter().sum::<f32>();
+        self.count += samples.len();
+    }
+
+    /// Peak, RMS and whether the limiter engaged since the last reading,
+    /// once `interval` has passed and there is something to read; the
+    /// meter starts over then.
+    pub fn take(&mut self, interval: Duration) -> Option<(f32, f32, bool)> {
+        if self.count == 0 || self.last_report.elapsed() < interval {
+            return None;
+        }
+        let reading = (
+            self.peak,
+            (self.sum_sq / self.count as f32).sqrt(),
+            self.limiting,
+        );
+        *self = Self::new();
+        Some(reading)
+    }
+}
+
+// Soft limiter: linear below the threshold, tanh knee above it so peaks never pass 1.0
+fn soft_limit(sample: f32) -> f32 {
+    let mag = sample.abs();
+    if mag <= LIMIT_THRESHOLD {
+        return sample;
+    }
+    let over = (mag - LIMIT_THRESHOLD) / (1.0 - LIMIT_THRESHOLD);
+    (LIMIT_THRESHOLD + (1.0 - LIMIT_THRESHOLD) * over.tanh()).copysign(sample)
+}
+
+// Biquad coefficients for one EQ band (Audio EQ Cookbook): shelves at
+// 250 Hz and 4 kHz with slope 1, a peak at 1 kHz with Q = 1. `gain` is linear.
+fn band_biquad(band: EqBand, sample_rate: f32, gain: f32) -> [f32; 5] {
+    let a = gain.sqrt(); // 10^(dB/40)
+    let freq = match band {
+        EqBand::Low => 250.0,
+        EqBand::Mid => 1000.0,
+        EqBand::High => 4000.0,
+    };
+    let w0 = 2.0 * std::f32::consts::PI * freq / sample_rate;
+    let sin_w0 = w0.sin();
+    let cos_w0 = w0.cos();
+    let [b0, b1, b2, a0, a1, a2] = match band {
+        EqBand::Mid => {
+            let alpha = sin_w0 / 2.0;
+            [
+                1.0 + alpha * a,
+                -2.0 * cos_w0,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos_w0,
+                1.0 - alpha / a,
+            ]
+        }
+        EqBand::Low | EqBand::High => {
+            let alpha = sin_w0 / 2.0 * std::f32::consts::SQRT_2;
+            let k = 2.0 * a.sqrt() * alpha;
+            // The high shelf is the low one with the sign of cos(w0) flipped
+            let cos = if band == EqBand::Low { cos_w0 } else { -cos_w0 };
+            let sign = if band == EqBand::Low { 1.0 } else { -1.0 };
+            [
+                a * ((a + 1.0) - (a - 1.0) * cos + k),
+                sign * 2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - k),
+                (a + 1.0) + (a - 1.0) * cos + k,
+                sign * -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - k,
+            ]
+        }
+    };
+    [b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0]
+}
+
+/// The EQ bands as cascaded biquads, with coefficients worked out once per
+/// playback. Flat bands are left out; channels past the eighth pass through.
+pub struct Equalizer {
+    sample_rate: u32,
+    gains: EqGains,
+    /// Band and coefficients of each section.
+    sections: Vec<(EqBand, [f32; 5])>,
+    /// Per section, per channel: [x1, x2, y1, y2].
+    state: Vec<[[f32; 4]; 8]>,
+}
+
+impl Equalizer {
+    pub fn new(sample_rate: u32, gains: EqGains) -> Self {
+        let mut equalizer = Self {
+            sample_rate,
+            gains,
+            sections: Vec::new(),
+            state: Vec::new(),
+        };
+        equalizer.retune(gains);
+        equalizer
+    }
+
+    /// Switch to new gains, if they changed. A band that stays active keeps
+    /// its filter history, so the change doesn't click.
+    pub fn set_gains(&mut self, gains: EqGains) {
+        if gains != self.gains {
+            self.gains = gains;
+            self.retune(gains);
+        }
+    }
+
+    fn retune(&mut self, gains: EqGains) {
+        let sections: Vec<(EqBand, [f32; 5])> = EqBand::ALL
+            .into_iter()
+            .map(|band| (band, gains.get(band)))
+            .filter(|&(_, gain)| gain != 1.0 && gain > 0.0)
+            .map(|(band, gain)| (band, band_biquad(band, self.sample_rate as f32, gain)))
+            .collect();
+        let state = sections
+            .iter()
+            .map(|(band, _)| {
+                let kept = self.sections.iter().position(|(b, _)| b == band);
+                kept.map_or([[0.0; 4]; 8], |i| self.state[i])
+            })
+            .collect();
+        self.sections = sections;
+        self.state = state;
+    }
+
+    /// Filter a block of interleaved samples in place, one section at a
+    /// time across the whole block.
+    pub fn process(&mut self, block: &mut [f32], channels: usize) {
+        for ((_, c), state) in self.sections.iter().zip(&mut self.state) {
+            match channels {
+                1 => filter::<1>(block, c, state),
+                2 => filter::<2>(block, c, state),
+                _ => {
+                    for frame in block.chunks_exact_mut(channels) {
+                        for (sample, st) in frame.iter_mut().zip(state.iter_mut()) {
+                            *sample = biquad(c, st, *sample);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// One step of a biquad section on `x`; `st` holds [x1, x2, y1, y2].
+fn biquad(c: &[f32; 5], st: &mut [f32; 4], x: f32) -> f32 {
+    let y = c[0] * x + c[1] * st[0] + c[2] * st[1] - c[3] * st[2] - c[4] * st[3];
+    *st = [x, st[0], y, st[2]];
+    y
+}
+
+// A section over a mono or stereo block. The channels' histories stay in
+// locals, so each filter step overlaps with the other channel's instead of
+// waiting on memory.
+fn filter<const N: usize>(block: &mut [f32], c: &[f32; 5], state: &mut [[f32; 4]; 8]) {
+    let mut st: [[f32; 4]; N] = std::array::from_fn(|ch| state[ch]);
+    for frame in block.chunks_exact_mut(N) {
+        for (sample, st) in frame.iter_mut().zip(&mut st) {
+            *sample = biquad(c, st, *sample);
+        }
+    }
+    state[..N].copy_from_slice(&st);
+}
+
+/// Rows summed for pink noise. Row `k` changes every `2^(k+1)` samples, so
+/// the slowest one reaches down to about 12 Hz at 48 kHz.
+const PINK_ROWS: usize = 12;
+
+/// Comfort noise generator. White noise is uniform in -1..1; pink noise is
+/// the Voss-McCartney sum of held random rows plus a fresh white sample,
+/// scaled to the same RMS, so `comfort_noise` means the same level for both.
+pub struct Noise {
+    pub kind: NoiseKind,
+    state: u64,
+    rows: [f32; PINK_ROWS],
+    counter: u32,
+}
+
+impl Noise {
+    pub fn new(kind: NoiseKind, seed: u64) -> Self {
+        let mut noise = Self {
+            kind,
+            state: seed.max(1),
+            rows: [0.0; PINK_ROWS],
+            counter: 0,
+        };
+        // Start from random rows rather than a quiet ramp-up
+        for i in 0..PINK_ROWS {
+            noise.rows[i] = noise.white();
+        }
+        noise
+    }
+
+    // Simple xorshift64 PRNG
+    fn white(&mut self) -> f32 {
+        let mut s = self.state;
+        s ^= s << 13;
+        s ^= s >> 7;
+        s ^= s << 17;
+        self.state = s;
+        (s as i64 as f32) / (i64::MAX as f32)
+    }
+
+    pub fn next(&mut self) -> f32 {
+        match self.kind {
+            NoiseKind::White => self.white(),
+            NoiseKind::Pink => {
+                // Exactly one row changes per sample: the one indexed by the
+                // counter's trailing zeros
+                self.counter = self.counter.wrapping_add(1);
+                let row = (self.counter.trailing_zeros() as usize).min(PINK_ROWS - 1);
+                self.rows[row] = self.white();
+                let sum: f32 = self.rows.iter().sum::<f32>() + self.white();
+                sum / ((PINK_ROWS + 1) as f32).sqrt()
+            }
+            NoiseKind::Off => 0.0,
+        }
+    }
+}
+
+/// Linear gain ramp from full down to zero, counted in interleaved samples.
+/// Used for the comfort-noise tail after a clip and the fade on a stop.
+pub struct Ramp {
+    pub len: usize,
+    pub left: usize,
+}
+
+impl Ramp {
+    pub fn new(sample_rate: u32, channels: u32, ms: u32) -> Self {
+        let frames = (sample_rate as u64 * ms as u64 / 1000) as usize;
+        let len = frames * channels.max(1) as usize;
+        Self { len, left: len }
+    }
+
+    pub fn done(&self) -> bool {
+        self.left == 0
+    }
+
+    /// Gain for the next sample, stepping the ramp; zero once it is done.
+    pub fn next_gain(&mut self) -> f32 {
+        if self.left == 0 {
+            return 0.0;
+        }
+        let gain = self.left as f32 / self.len as f32;
+        self.left -= 1;
+        gain
+    }
+}
+
+// Shape a clip's interleaved samples in place: volume, the stop fade while
+// `stop_ramp` is given, EQ and limiter, then `noise` added on top. Each step
+// is its own pass over the block, so none is switched on or off per sample.
+#[allow(clippy::too_many_arguments)]
+pub fn mix_frames(
+    block: &mut [f32],
+    channels: usize,
+    gain: f32,
+    stop_ramp: Option<&mut Ramp>,
+    equalizer: &mut Equalizer,
+    limiter: bool,
+    meter: &mut LevelMeter,
+    mut noise: impl FnMut() -> f32,
+) {
+    match stop_ramp {
+        Some(ramp) => {
+            for out in block.iter_mut() {
+                *out = *out * gain * ramp.next_gain();
+            }
+        }
+        None => {
+            for out in block.iter_mut() {
+                *out *= gain;
+            }
+        }
+    }
+    equalizer.process(block, channels);
+    if limiter {
+        for out in block.iter_mut() {
+            if out.abs() > LIMIT_THRESHOLD {
+                meter.limiting = true;
+                *out = soft_limit(*out);
+            }
+        }
+    }
+    meter.add(block);
+    for out in block.iter_mut() {
+        *out += noise();
+    }
+}
+
+#[cfg(test)]
+#[path = "mix/baseline.rs"]
+mod baseline;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Mutex;
+
+    #[test]
+    fn soft_limit_stays_bounded_and_continuous() {
+        assert_eq!(soft_limit(0.5), 0.5);
+        assert_eq!(soft_limit(-LIMIT_THRESHOLD), -LIMIT_THRESHOLD);
+        // Sweep well past full scale: never above 1.0, and no step is larger
+        // than the step in the input, so there are no jumps at the knee
+        let mut prev = soft_limit(-6.0);
+        for i in 1..=12_000 {
+            let input = -6.0 + i as f32 * 0.001;
+            let out = soft_limit(input);
+            assert!(out.abs() <= 1.0, "{input} -> {out}");
+            assert!((out - prev).abs() <= 0.001 + 1e-6, "jump at {input}");
+            assert_eq!(out.signum(), input.signum());
+            prev = out;
+        }
+        assert!(soft_limit(5.0) > 0.99 && soft_limit(5.0) > soft_limit(1.2));
+    }
+
+    #[test]
+    fn eq_shelves_reach_their_gain_at_the_band_edges() {
+        // Steady-state gain for a constant signal, or one at Nyquist
+        let response = |gains: EqGains, nyquist: bool| {
+            let mut eq = Equalizer::new(48000, gains);
+            let mut block: Vec<f32> = (0..48000)
+                .map(|i| if nyquist && i % 2 == 1 { -0.1 } else { 0.1 })
+                .collect();
+            eq.process(&mut block, 1);
+            block[block.len() - 1].abs() / 0.1
+        };
+        let flat = EqGains {
+            low: 1.0,
+            mid: 1.0,
+            high: 1.0,
+        };
+        assert!(Equalizer::new(48000, flat).sections.is_empty());
+        assert_eq!(
+            Equalizer::new(
+                48000,
+                EqGains {
+                    mid: 0.0,
+                    ..EqGains::default()
+                }
+            )
+            .sections
+            .len(),
+            0
+        );
+
+        let low = EqGains { low: 2.0, ..flat };
+        assert!((response(low, false) - 2.0).abs() < 0.01);
+        assert!((response(low, true) - 1.0).abs() < 0.01);
+        let high = EqGains { high: 0.5, ..flat };
+        assert!((response(high, false) - 1.0).abs() < 0.01);
+        assert!((response(high, true) - 0.5).abs() < 0.01);
+        // The mid peak leaves both ends alone
+        let mid = EqGains { mid: 3.0, ..flat };
+        assert!((response(mid, false) - 1.0).abs() < 0.01);
+        assert!((response(mid, true) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn equalizer_retunes_in_place_and_keeps_the_history_of_bands_left_on() {
+        let flat = EqGains {
+            low: 1.0,
+            mid: 1.0,
+            high: 1.0,
+        };
+        let mut eq = Equalizer::new(48000, EqGains { low: 2.0, ..flat });
+        eq.process(&mut [0.1; 48000], 1);
+        let settled = eq.state[0];
+        eq.set_gains(EqGains {
+            low: 2.0,
+            high: 0.5,
+            ..flat
+        });
+        let bands: Vec<EqBand> = eq.sections.iter().map(|&(band, _)| band).collect();
+        assert_eq!(bands, [EqBand::Low, EqBand::High]);
+        assert_eq!(eq.state[0], settled);
+        assert_eq!(eq.state[1], [[0.0; 4]; 8]);
+
+        eq.set_gains(flat);
+        assert!(eq.sections.is_empty());
+        let mut block = [0.1];
+        eq.process(&mut block, 1);
+        assert_eq!(block, [0.1]);
+    }
+
+    #[test]
+    fn noise_rms_follows_the_level_for_every_kind() {
+        // Three seconds of 48 kHz stereo
+        const SAMPLES: usize = 3 * 48_000 * 2;
+        let rms = |kind, level: f32| {
+            let mut noise = Noise::new(kind, 0xDEADBEEFCAFE);
+            let sum: f64 = (0..SAMPLES)
+                .map(|_| ((noise.next() * level) as f64).powi(2))
+                .sum();
+            (sum / SAMPLES as f64).sqrt() as f32
+        };
+        // Uniform noise in -1..1 has an RMS of 1/sqrt(3)
+        let unit = 1.0 / 3f32.sqrt();
+        for kind in [NoiseKind::White, NoiseKind::Pink] {
+            for level in [0.005, 0.01, 0.05] {
+                let ratio = rms(kind, level) / (level * unit);
+                assert!((ratio - 1.0).abs() < 0.05, "{kind:?} at {level}: {ratio}");
+            }
+        }
+        assert_eq!(rms(NoiseKind::Off, 0.05), 0.0);
+
+        // Neighbouring pink samples share all but one row, white ones nothing
+        let lag1 = |kind| {
+            let mut noise = Noise::new(kind, 0xDEADBEEFCAFE);
+            let samples: Vec<f32> = (0..SAMPLES).map(|_| noise.next()).collect();
+            let energy: f32 = samples.iter().map(|s| s * s).sum();
+            samples.windows(2).map(|w| w[0] * w[1]).sum::<f32>() / energy
+        };
+        assert!(lag1(NoiseKind::White).abs() < 0.05);
+        assert!(lag1(NoiseKind::Pink) > 0.5);
+    }
+
+    #[test]
+    fn mixing_matches_the_old_sample_loop_bit_for_bit() {
+        let bits = |block: &[f32]| block.iter().map(|s| s.to_bits()).collect::<Vec<_>>();
+        let clip: Vec<f32> = (0..4000).map(|i| (i as f32 * 0.037).sin() * 0.8).collect();
+        for boost in [1.0, 1.5, 0.5] {
+            let offset = Mutex::new(0);
+            let old_rng = AtomicU64::new(0xDEADBEEFCAFE);
+            let biquad = baseline::compute_biquad(48000.0, boost);
+            let mut eq_state = [[0.0; 4]; 8];
+
+            let new_rng = AtomicU64::new(0xDEADBEEFCAFE);
+            let gains = EqGains {
+                low: 1.0,
+                mid: boost,
+                high: 1.0,
+            };
+            let mut equalizer = Equalizer::new(48000, gains);
+            let mut meter = LevelMeter::new();
+            let mut read = 0;
+            // The fourth buffer runs out of clip halfway and the fifth is
+            // noise alone
+            for _ in 0..5 {
+                let mut old = [0.0f32; 1024];
+                let written = baseline::process(
+                    &clip,
+                    &offset,
+                    &mut old,
+                    2,
+                    0.7,
+                    boost != 1.0,
+                    biquad,
+                    &mut eq_state,
+                    0.01,
+                    &old_rng,
+                );
+
+                let mut new = [0.0f32; 1024];
+                let to_write = (clip.len() - read).min(new.len());
+                new[..to_write].copy_from_slice(&clip[read..read + to_write]);
+                read += to_write;
+                let noise = || baseline::next_noise(&new_rng) * 0.01;
+                mix_frames(
+                    &mut new[..to_write],
+                    2,
+                    0.7,
+                    None,
+                    &mut equalizer,
+                    false,
+                    &mut meter,
+                    noise,
+                );
+                for out in &mut new[to_write..] {
+                    *out = baseline::next_noise(&new_rng) * 0.01;
+                }
+
+                assert_eq!(written, to_write);
+                assert_eq!(bits(&old), bits(&new), "boost {boost}");
+            }
+        }
+    }
+}
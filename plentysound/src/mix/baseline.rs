@@ -0,0 +1,91 @@
+//! The playback callback's sample loop as it was, with the read position
+//! behind a `Mutex` and the EQ branch taken per sample. Kept as the
+//! reference the current loop is compared with bit for bit (the mix tests)
+//! and timed against (benches/mix.rs).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// One callback's worth of output: volume, the mid-band EQ when `apply_eq`,
+/// comfort noise on top, and noise alone past the end of `samples`. Returns
+/// how many clip samples went out.
+#[allow(clippy::too_many_arguments)]
+pub fn process(
+    samples: &[f32],
+    offset: &Mutex<usize>,
+    out_f32: &mut [f32],
+    channels: usize,
+    volume: f32,
+    apply_eq: bool,
+    biquad: [f32; 5],
+    eq_state: &mut [[f32; 4]; 8],
+    comfort_noise: f32,
+    rng_state: &AtomicU64,
+) -> usize {
+    let out_samples = out_f32.len();
+    let mut pos = offset.lock().unwrap();
+
+    let remaining = samples.len() - *pos;
+    let to_write = out_samples.min(remaining);
+
+    for i in 0..to_write {
+        let mut sample = samples[*pos + i] * volume;
+
+        // Apply biquad EQ
+        if apply_eq {
+            let ch = i % channels;
+            if ch < 8 {
+                let st = &mut eq_state[ch];
+                let y = biquad[0] * sample + biquad[1] * st[0] + biquad[2] * st[1]
+                    - biquad[3] * st[2]
+                    - biquad[4] * st[3];
+                st[1] = st[0];
+                st[0] = sample;
+                st[3] = st[2];
+                st[2] = y;
+                sample = y;
+            }
+        }
+
+        // Add comfort noise
+        out_f32[i] = sample + next_noise(rng_state) * comfort_noise;
+    }
+
+    for out in &mut out_f32[to_write..] {
+        *out = next_noise(rng_state) * comfort_noise;
+    }
+
+    *pos += to_write;
+    to_write
+}
+
+// Peaking EQ biquad coefficients (Audio EQ Cookbook)
+// center_freq = 1000 Hz, Q = 1.0, gain derived from eq_mid_boost
+pub fn compute_biquad(sample_rate: f32, boost: f32) -> [f32; 5] {
+    let gain_db = 20.0 * boost.log10();
+    let a_val = 10.0_f32.powf(gain_db / 40.0);
+    let w0 = 2.0 * std::f32::consts::PI * 1000.0 / sample_rate;
+    let sin_w0 = w0.sin();
+    let cos_w0 = w0.cos();
+    let alpha = sin_w0 / 2.0; // Q = 1.0
+    let b0 = 1.0 + alpha * a_val;
+    let b1 = -2.0 * cos_w0;
+    let b2 = 1.0 - alpha * a_val;
+    let a0 = 1.0 + alpha / a_val;
+    let a1 = -2.0 * cos_w0;
+    let a2 = 1.0 - alpha / a_val;
+    [b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0]
+}
+
+// Simple xorshift64 PRNG for noise generation
+pub fn next_noise(state: &AtomicU64) -> f32 {
+    let mut s = state.load(Ordering::Relaxed);
+    if s == 0 {
+        s = 0xDEADBEEFCAFE;
+    }
+    s ^= s << 13;
+    s ^= s >> 7;
+    s ^= s << 17;
+    state.store(s, Ordering::Relaxed);
+    (s as i64 as f32) / (i64::MAX as f32)
+}
@@ -0,0 +1,257 @@
+//! MPRIS player on the session bus, so media keys, playerctl and desktop
+//! widgets see what the daemon plays and can pause, stop or skip it.
+
+use crate::daemon::TaggedCommand;
+use crate::protocol::ClientCommand;
+use crate::tray::NowPlaying;
+use dbus::arg::{Append, Arg, PropMap, RefArg, Variant};
+use dbus::blocking::stdintf::org_freedesktop_dbus::PropertiesPropertiesChanged;
+use dbus::blocking::LocalConnection;
+use dbus::channel::Sender as _;
+use dbus::message::SignalArgs;
+use dbus_tree::{EmitsChangedSignal, Factory, MTFn, Property};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+const LOG: crate::log::Logger = crate::log::log_scope("mpris");
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.plentysound";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const ROOT_IFACE: &str = "org.mpris.MediaPlayer2";
+const PLAYER_IFACE: &str = "org.mpris.MediaPlayer2.Player";
+/// The one track there is; songs have no stable id to put here.
+const TRACK_PATH: &str = "/org/plentysound/track/current";
+
+/// The running MPRIS service and its thread.
+pub struct MprisThread {
+    stop: Arc<AtomicBool>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl MprisThread {
+    /// Leave the bus and wait for the thread to end.
+    pub fn shutdown(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if self.thread.join().is_err() {
+            LOG.error("MPRIS thread panicked");
+        }
+    }
+}
+
+/// Serve MPRIS until shut down. Player methods go into `cmd_tx` like the
+/// daemon's own commands; properties follow `now_playing`. Without a
+/// session bus the thread logs why and ends.
+pub fn spawn_mpris(
+    cmd_tx: Sender<TaggedCommand>,
+    now_playing: Arc<Mutex<NowPlaying>>,
+) -> MprisThread {
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread = std::thread::spawn(move || {
+        if let Err(e) = run(cmd_tx, now_playing, &thread_stop) {
+            LOG.error(&format!("MPRIS error: {e}"));
+        }
+    });
+    MprisThread { stop, thread }
+}
+
+/// Other instances get a name of their own, as MPRIS asks of players
+/// that run more than once.
+fn bus_name() -> String {
+    let instance = crate::protocol::instance();
+    if instance.name.is_none() && instance.socket.is_none() {
+        BUS_NAME.to_string()
+    } else {
+        format!("{BUS_NAME}.instance{}", std::process::id())
+    }
+}
+
+fn run(
+    cmd_tx: Sender<TaggedCommand>,
+    now_playing: Arc<Mutex<NowPlaying>>,
+    stop: &AtomicBool,
+) -> Result<(), dbus::Error> {
+    let conn = LocalConnection::new_session()?;
+    conn.request_name(bus_name(), false, true, true)?;
+
+    let current = {
+        let now_playing = now_playing.clone();
+        move || {
+            now_playing
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        }
+    };
+    let f = Factory::new_fn::<()>();
+    let mut player = f.interface(PLAYER_IFACE, ());
+    for method in ["Play", "Pause", "PlayPause", "Stop", "Next"] {
+        let (cmd_tx, current) = (cmd_tx.clone(), current.clone());
+        player = player.add_m(f.method(method, (), move |m| {
+            if let Some(cmd) = command_for(method, &current()) {
+                let _ = cmd_tx.send((None, cmd));
+            }
+            Ok(vec![m.msg.method_return()])
+        }));
+    }
+    // No going back: there is no history to go back through
+    player = player.add_m(f.method("Previous", (), |m| Ok(vec![m.msg.method_return()])));
+    let status = current.clone();
+    player = player.add_p(
+        f.property::<String, _>("PlaybackStatus", ())
+            .on_get(move |i, _| {
+                i.append(playback_status(&status()));
+                Ok(())
+            }),
+    );
+    let track = current.clone();
+    player = player.add_p(
+        f.property::<PropMap, _>("Metadata", ())
+            .on_get(move |i, _| {
+                i.append(metadata(&track()));
+                Ok(())
+            }),
+    );
+    for (name, value) in [
+        ("CanPlay", true),
+        ("CanPause", true),
+        ("CanGoNext", true),
+        ("CanGoPrevious", false),
+        ("CanSeek", false),
+        ("CanControl", true),
+    ] {
+        player = player.add_p(constant(&f, name, value));
+    }
+    player = player
+        .add_p(constant(&f, "Rate", 1.0))
+        .add_p(constant(&f, "MinimumRate", 1.0))
+        .add_p(constant(&f, "MaximumRate", 1.0));
+
+    let root = f
+        .interface(ROOT_IFACE, ())
+        .add_m(f.method("Raise", (), |m| Ok(vec![m.msg.method_return()])))
+        // Quitting is the tray's and `plentysound stop`'s job
+        .add_m(f.method("Quit", (), |m| Ok(vec![m.msg.method_return()])))
+        .add_p(constant(&f, "CanQuit", false))
+        .add_p(constant(&f, "CanRaise", false))
+        .add_p(constant(&f, "HasTrackList", false))
+        .add_p(constant(&f, "Identity", "plentysound".to_string()))
+        .add_p(constant(&f, "SupportedUriSchemes", Vec::<String>::new()))
+        .add_p(constant(&f, "SupportedMimeTypes", Vec::<String>::new()));
+
+    f.tree(())
+        .add(
+            f.object_path(OBJECT_PATH, ())
+                .introspectable()
+                .add(root)
+                .add(player),
+        )
+        .start_receive(&conn);
+
+    // Like the tray, poll for changes between bus messages
+    let mut shown = current();
+    while !stop.load(Ordering::SeqCst) {
+        conn.process(Duration::from_millis(50))?;
+        let now = current();
+        if now != shown {
+            let mut changed = PropMap::new();
+            changed.insert(
+                "PlaybackStatus".into(),
+                Variant(Box::new(playback_status(&now))),
+            );
+            changed.insert("Metadata".into(), Variant(Box::new(metadata(&now))));
+            let signal = PropertiesPropertiesChanged {
+                interface_name: PLAYER_IFACE.to_string(),
+                changed_properties: changed,
+                invalidated_properties: Vec::new(),
+            };
+            let _ = conn.send(signal.to_emit_message(&OBJECT_PATH.into()));
+            shown = now;
+        }
+    }
+    Ok(())
+}
+
+/// A read-only property that never changes.
+fn constant<T>(f: &Factory<MTFn<()>, ()>, name: &str, value: T) -> Property<MTFn<()>, ()>
+where
+    T: Arg + Append + Clone + 'static,
+{
+    f.property::<T, _>(name, ())
+        .emits_changed(EmitsChangedSignal::Const)
+        .on_get(move |i, _| {
+            i.append(value.clone());
+            Ok(())
+        })
+}
+
+fn playback_status(np: &NowPlaying) -> String {
+    match (&np.song, np.paused) {
+        (None, _) => "Stopped",
+        (Some(_), true) => "Paused",
+        (Some(_), false) => "Playing",
+    }
+    .to_string()
+}
+
+fn metadata(np: &NowPlaying) -> PropMap {
+    let mut map = PropMap::new();
+    if let Some(song) = &np.song {
+        let track: Box<dyn RefArg> = Box::new(dbus::Path::from(TRACK_PATH));
+        map.insert("mpris:trackid".into(), Variant(track));
+        map.insert("xesam:title".into(), Variant(Box::new(song.clone())));
+    }
+    map
+}
+
+/// What a Player method asks of the daemon, given what it is doing; `None`
+/// when there is nothing to do, e.g. Play while playing.
+fn command_for(method: &str, np: &NowPlaying) -> Option<ClientCommand> {
+    let playing = np.song.is_some();
+    match method {
+        "Play" | "PlayPause" if !playing => Some(ClientCommand::Play),
+        "Play" | "PlayPause" if np.paused => Some(ClientCommand::Resume),
+        "Pause" | "PlayPause" if playing && !np.paused => Some(ClientCommand::Pause),
+        "Stop" if playing => Some(ClientCommand::StopPlayback { clear_queue: false }),
+        "Next" => Some(ClientCommand::Next),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn media_keys_map_onto_what_the_daemon_is_doing() {
+        let stopped = NowPlaying::default();
+        let playing = NowPlaying {
+            song: Some("horn.wav".to_string()),
+            paused: false,
+        };
+        let paused = NowPlaying {
+            paused: true,
+            ..playing.clone()
+        };
+        let cmd = |method, np| format!("{:?}", command_for(method, np));
+
+        assert_eq!(cmd("PlayPause", &stopped), "Some(Play)");
+        assert_eq!(cmd("PlayPause", &playing), "Some(Pause)");
+        assert_eq!(cmd("PlayPause", &paused), "Some(Resume)");
+        assert_eq!(cmd("Play", &playing), "None");
+        assert_eq!(cmd("Pause", &paused), "None");
+        assert_eq!(
+            cmd("Stop", &paused),
+            "Some(StopPlayback { clear_queue: false })"
+        );
+        assert_eq!(cmd("Stop", &stopped), "None");
+        assert_eq!(cmd("Next", &stopped), "Some(Next)");
+
+        assert_eq!(playback_status(&paused), "Paused");
+        assert!(metadata(&stopped).is_empty());
+        let title = metadata(&playing).remove("xesam:title").unwrap();
+        assert_eq!(title.0.as_str(), Some("horn.wav"));
+    }
+}
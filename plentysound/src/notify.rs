@@ -0,0 +1,226 @@
+//! Desktop notifications through `org.freedesktop.Notifications`, so a
+//! word firing is seen even with the TUI closed.
+
+use crate::protocol::DaemonEvent;
+use dbus::arg::PropMap;
+use dbus::blocking::Connection;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+const LOG: crate::log::Logger = crate::log::log_scope("notify");
+
+const SERVICE: &str = "org.freedesktop.Notifications";
+const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+const CALL_TIMEOUT: Duration = Duration::from_secs(2);
+/// Shortest time between two bubbles; what comes in between is shown
+/// together once it has passed.
+const MIN_GAP: Duration = Duration::from_secs(2);
+/// Lines kept in a bubble that gathers several notifications.
+const MAX_LINES: usize = 4;
+
+/// Sends notifications from a thread of its own, so a slow or missing
+/// notification service never holds up the daemon.
+pub struct Notifier {
+    tx: Sender<String>,
+}
+
+impl Notifier {
+    /// Start the sending thread. It connects on the first notification and
+    /// ends when the `Notifier` is dropped.
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            let mut limit = RateLimit::default();
+            let mut bus: Option<Connection> = None;
+            // The id of our last bubble, which the next one replaces
+            let mut shown = 0u32;
+            loop {
+                let wait = limit
+                    .wait(Instant::now())
+                    .unwrap_or(Duration::from_secs(3600));
+                match rx.recv_timeout(wait) {
+                    Ok(text) => limit.offer(text),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                let Some(body) = limit.due(Instant::now()) else {
+                    continue;
+                };
+                if bus.is_none() {
+                    match Connection::new_session() {
+                        Ok(conn) => bus = Some(conn),
+                        Err(e) => {
+                            LOG.warn(&format!("Notifications: no session bus: {e}"));
+                            continue;
+                        }
+                    }
+                }
+                let Some(conn) = &bus else { continue };
+                match send(conn, shown, &body) {
+                    Ok(id) => shown = id,
+                    Err(e) => {
+                        LOG.warn(&format!("Notification not shown: {e}"));
+                        // Connect again next time, in case the bus went away
+                        bus = None;
+                    }
+                }
+            }
+        });
+        Notifier { tx }
+    }
+
+    pub fn send(&self, text: String) {
+        let _ = self.tx.send(text);
+    }
+}
+
+fn send(conn: &Connection, replaces: u32, body: &str) -> Result<u32, dbus::Error> {
+    let proxy = conn.with_proxy(SERVICE, OBJECT_PATH, CALL_TIMEOUT);
+    let (id,): (u32,) = proxy.method_call(
+        SERVICE,
+        "Notify",
+        (
+            "plentysound",
+            replaces,
+            "audio-volume-high",
+            "plentysound",
+            body,
+            Vec::<String>::new(),
+            PropMap::new(),
+            -1i32,
+        ),
+    )?;
+    Ok(id)
+}
+
+/// Holds back notifications that come less than `MIN_GAP` after the last
+/// bubble, then lets them out as one.
+#[derive(Default)]
+struct RateLimit {
+    last_shown: Option<Instant>,
+    pending: Vec<String>,
+}
+
+impl RateLimit {
+    fn offer(&mut self, text: String) {
+        self.pending.push(text);
+        if self.pending.len() > MAX_LINES {
+            self.pending.remove(0);
+        }
+    }
+
+    /// How long until the pending lines are due; `None` with nothing pending.
+    fn wait(&self, now: Instant) -> Option<Duration> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let since = self
+            .last_shown
+            .map_or(MIN_GAP, |at| now.saturating_duration_since(at));
+        Some(MIN_GAP.saturating_sub(since))
+    }
+
+    /// The bubble to show now, if one is due.
+    fn due(&mut self, now: Instant) -> Option<String> {
+        if self.wait(now)? > Duration::ZERO {
+            return None;
+        }
+        self.last_shown = Some(now);
+        Some(std::mem::take(&mut self.pending).join("\n"))
+    }
+}
+
+/// What in `events` deserves a notification: bindings a heard word fired,
+/// and with `on_play` every song that starts. A word's own song isn't
+/// announced twice.
+pub fn announcements(events: &[DaemonEvent], on_play: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+    #[cfg(feature = "transcriber")]
+    let mut announced: Option<&str> = None;
+    for event in events {
+        match event {
+            #[cfg(feature = "transcriber")]
+            DaemonEvent::WordDetected {
+                simulated: true, ..
+            } => {}
+            #[cfg(feature = "transcriber")]
+            DaemonEvent::WordDetected { word, song, .. } => {
+                lines.push(match song {
+                    Some(song) => format!("Heard '{word}' → playing {song}"),
+                    None => format!("Heard '{word}'"),
+                });
+                announced = song.as_deref();
+            }
+            #[cfg(feature = "transcriber")]
+            DaemonEvent::NowPlaying(Some(song)) if announced.take() == Some(song.as_str()) => {}
+            DaemonEvent::NowPlaying(Some(song)) if on_play => {
+                lines.push(format!("Playing {song}"));
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_are_held_back_and_shown_together() {
+        let start = Instant::now();
+        let mut limit = RateLimit::default();
+        assert_eq!(limit.wait(start), None);
+
+        limit.offer("one".to_string());
+        assert_eq!(limit.due(start).as_deref(), Some("one"));
+        limit.offer("two".to_string());
+        limit.offer("three".to_string());
+        let soon = start + MIN_GAP / 2;
+        assert_eq!(limit.wait(soon), Some(MIN_GAP / 2));
+        assert_eq!(limit.due(soon), None);
+        assert_eq!(limit.due(start + MIN_GAP).as_deref(), Some("two\nthree"));
+
+        for i in 0..10 {
+            limit.offer(i.to_string());
+        }
+        assert_eq!(
+            limit.due(start + MIN_GAP * 2).as_deref(),
+            Some("6\n7\n8\n9")
+        );
+    }
+
+    #[test]
+    fn playback_starts_are_announced_only_when_asked_for() {
+        let events = [DaemonEvent::NowPlaying(Some("horn.wav".to_string()))];
+        assert!(announcements(&events, false).is_empty());
+        assert_eq!(announcements(&events, true), ["Playing horn.wav"]);
+        assert!(announcements(&[DaemonEvent::NowPlaying(None)], true).is_empty());
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn a_heard_word_is_announced_with_its_song_once() {
+        let word = |simulated, song: Option<&str>| DaemonEvent::WordDetected {
+            word: "lucas".to_string(),
+            simulated,
+            song: song.map(str::to_string),
+        };
+        let playing = DaemonEvent::NowPlaying(Some("airhorn.mp3".to_string()));
+
+        let events = [word(false, Some("airhorn.mp3")), playing.clone()];
+        assert_eq!(
+            announcements(&events, true),
+            ["Heard 'lucas' → playing airhorn.mp3"]
+        );
+        assert_eq!(
+            announcements(&[word(false, None)], false),
+            ["Heard 'lucas'"]
+        );
+        // Tried from the TUI, where it's already seen
+        assert_eq!(
+            announcements(&[word(true, Some("airhorn.mp3")), playing], true).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Samples into PipeWire's mapped buffer memory, as native-endian f32s.
+//!
+//! The memory comes with no alignment promise, so it is only viewed as
+//! `[f32]` when it turns out to be aligned for it (PipeWire's own buffers
+//! are); otherwise samples go in a byte group at a time.
+
+const SAMPLE: usize = std::mem::size_of::<f32>();
+
+/// Write `samples` into `bytes`, as many as fit; returns how many went in.
+pub fn fill_f32(bytes: &mut [u8], samples: impl Iterator<Item = f32>) -> usize {
+    let mut written = 0;
+    match as_f32s(bytes) {
+        Some(out) => {
+            for (out, sample) in out.iter_mut().zip(samples) {
+                *out = sample;
+                written += 1;
+            }
+        }
+        None => {
+            for (out, sample) in bytes.chunks_exact_mut(SAMPLE).zip(samples) {
+                out.copy_from_slice(&sample.to_ne_bytes());
+                written += 1;
+            }
+        }
+    }
+    written
+}
+
+/// `fill_f32` from a slice, which aligned memory takes in a single copy.
+pub fn copy_f32(bytes: &mut [u8], samples: &[f32]) -> usize {
+    match as_f32s(bytes) {
+        Some(out) => {
+            let len = out.len().min(samples.len());
+            out[..len].copy_from_slice(&samples[..len]);
+            len
+        }
+        None => fill_f32(bytes, samples.iter().copied()),
+    }
+}
+
+/// Split interleaved frames into per-channel planes; planes[c] receives
+/// channel c. Planes shorter than the frame count are filled as far as they
+/// go.
+pub fn deinterleave(block: &[f32], channels: usize, planes: &mut [&mut [u8]]) {
+    if channels == 0 {
+        return;
+    }
+    for (channel, plane) in planes.iter_mut().take(channels).enumerate() {
+        fill_f32(
+            plane,
+            block.chunks_exact(channels).map(|frame| frame[channel]),
+        );
+    }
+}
+
+// The whole samples' worth of `bytes` as `[f32]`, if aligned for it.
+fn as_f32s(bytes: &mut [u8]) -> Option<&mut [f32]> {
+    let whole = bytes.len() / SAMPLE * SAMPLE;
+    bytemuck::try_cast_slice_mut(&mut bytes[..whole]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floats(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes(b.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn deinterleave_splits_frames_into_planes() {
+        let block = [1.0, -1.0, 2.0, -2.0, 3.0, -3.0];
+        let mut left = [0u8; 12];
+        let mut right = [0u8; 12];
+        deinterleave(&block, 2, &mut [&mut left, &mut right]);
+        assert_eq!(floats(&left), [1.0, 2.0, 3.0]);
+        assert_eq!(floats(&right), [-1.0, -2.0, -3.0]);
+    }
+
+    #[test]
+    fn deinterleave_tolerates_short_and_missing_planes() {
+        let block = [1.0, 10.0, 100.0, 2.0, 20.0, 200.0];
+        let mut first = [0u8; 4];
+        let mut second = [0u8; 16];
+        // Third channel has no plane; it is dropped rather than shifting the others
+        deinterleave(&block, 3, &mut [&mut first, &mut second]);
+        assert_eq!(floats(&first), [1.0]);
+        assert_eq!(floats(&second), [10.0, 20.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn samples_land_bit_for_bit_at_any_alignment() {
+        let samples = [
+            0.25,
+            -0.0,
+            f32::MIN_POSITIVE / 2.0,
+            f32::NAN,
+            -1.0,
+            f32::MAX,
+        ];
+        // Backed by f32s, so an offset of 4 is aligned and 1 is not
+        for offset in [1, 4] {
+            let mut memory = [f32::from_bits(0xAAAA_AAAA); 8];
+            let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut memory);
+            let end = offset + 6 * 4;
+            assert_eq!(
+                fill_f32(&mut bytes[offset..end + 3], samples.into_iter()),
+                6
+            );
+            let written = floats(&bytes[offset..end]);
+            let bits: Vec<u32> = written.iter().map(|s| s.to_bits()).collect();
+            assert_eq!(bits, samples.map(f32::to_bits), "offset {offset}");
+            // Bytes around them, and the partial sample at the end, are untouched
+            assert!(bytes[..offset]
+                .iter()
+                .chain(&bytes[end..])
+                .all(|&b| b == 0xAA));
+
+            // A slice goes in the same, however long either side is
+            let mut copy = [f32::from_bits(0xAAAA_AAAA); 8];
+            let copied: &mut [u8] = bytemuck::cast_slice_mut(&mut copy);
+            assert_eq!(copy_f32(&mut copied[offset..end + 3], &samples), 6);
+            assert_eq!(copied, bytes);
+            assert_eq!(copy_f32(&mut copied[offset..offset + 8], &samples), 2);
+        }
+        // A short buffer takes what fits
+        assert_eq!(fill_f32(&mut [0u8; 9], samples.into_iter()), 2);
+    }
+}
@@ -1,3 +1,7 @@
+use crate::audio::{AudioStream, StreamChunk};
+use crate::mix::{mix_frames, Equalizer, LevelMeter, Noise, Ramp};
+use crate::pcm::{copy_f32, deinterleave, fill_f32};
+use crate::protocol::{EqBand, EqGains, FinishReason, NoiseKind, DEFAULT_SINK_ID};
 use anyhow::Result;
 use pipewire::{
     context::Context,
@@ -13,17 +17,40 @@ use pipewire::{
     },
     stream::{Stream, StreamFlags},
 };
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, Sender, TrySendError};
+
+const LOG: crate::log::Logger = crate::log::log_scope("pw");
 
 // ── Types ────────────────────────────────────────────────────────────────────
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Declared in the order the devices list shows them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum DeviceKind {
     Output,
+    /// An application's capture stream; clips are injected into it.
     Input,
+    /// A microphone or virtual source. It can be listened to but not played
+    /// into.
+    Source,
+}
+
+impl DeviceKind {
+    /// As clients and the config see it.
+    pub fn name(self) -> &'static str {
+        match self {
+            DeviceKind::Output => "Output",
+            DeviceKind::Input => "Input",
+            DeviceKind::Source => "Source",
+        }
+    }
+
+    /// Whether the word detector and the sidechain can listen to it.
+    pub fn is_capture(self) -> bool {
+        matches!(self, DeviceKind::Input | DeviceKind::Source)
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PwSink {
     pub id: u32,
     pub name: String,
@@ -32,24 +59,337 @@ pub struct PwSink {
 }
 
 pub enum PwCommand {
+    /// Send the device list now. Changes are pushed without asking; this
+    /// forces a fresh copy and reconnects after PipeWire went away.
     ListSinks,
+    /// Clips play one at a time, in order. With `interrupt` set, whatever is
+    /// playing or waiting is cut short first.
     Play {
+        /// Echoed back in `PlaybackFinished`, along with `song`.
+        id: u64,
+        song: String,
+        interrupt: bool,
         sink_id: u32,
         kind: DeviceKind,
         node_name: String,
-        samples: Vec<f32>,
-        sample_rate: u32,
-        channels: u32,
-        volume: f32,
-        comfort_noise: f32,
-        eq_mid_boost: f32,
+        /// Decoding in the background; playback starts once about
+        /// `PREFILL_MS` of it is ready.
+        audio: AudioStream,
+        options: PlaybackOptions,
+        /// Send `PwEvent::PlaybackStarted` once the first audio is written.
+        timed: bool,
+        /// Output that also plays an injected clip, so the user hears what
+        /// the other side does. Ignored for output sinks.
+        monitor_sink: Option<u32>,
+    },
+    /// Hold every active playback in place (comfort noise keeps playing).
+    SetPaused(bool),
+    /// End the current and all waiting playbacks now; each still reports
+    /// `PlaybackFinished`.
+    StopPlayback,
+    /// End just these playbacks, e.g. the ones on a device that went away;
+    /// each still reports `PlaybackFinished`.
+    StopPlaybacks(Vec<u64>),
+    /// Keep a noise-only stream open on `target_id` so the target never goes
+    /// fully silent between clips; `None` closes it.
+    ContinuousNoise {
+        target_id: Option<u32>,
+        level: f32,
+        kind: NoiseKind,
     },
+    /// Extra properties for every clip stream created from now on.
+    SetStreamProperties(StreamProperties),
+    /// Settings every clip reads on each buffer, so the one playing now
+    /// follows them too.
+    UpdateParams(LiveParams),
+    /// Stop playback, close every stream and end the thread once they have.
+    Shutdown,
+}
+
+/// Properties layered over a clip stream's built-in ones, by target kind.
+/// Later entries win over earlier ones and over the built-ins.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamProperties {
+    /// Streams played to an output device.
+    pub playback: Vec<(String, String)>,
+    /// Streams injected into an application's capture.
+    pub injection: Vec<(String, String)>,
+}
+
+/// Playback settings that may change while a clip plays, sent with
+/// `PwCommand::UpdateParams`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LiveParams {
+    /// Global volume, scaling each clip's own `PlaybackOptions::volume`.
+    pub volume: f32,
+    /// Noise mixed into clip streams; 0 while a continuous noise stream
+    /// already covers the target.
+    pub comfort_noise: f32,
+    pub eq: EqGains,
+}
+
+impl Default for LiveParams {
+    // Same values as a fresh config
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            comfort_noise: 0.01,
+            eq: EqGains::default(),
+        }
+    }
+}
+
+/// `LiveParams` shared with the playback streams, floats stored as bits.
+struct PlaybackParams {
+    volume: std::sync::atomic::AtomicU32,
+    comfort_noise: std::sync::atomic::AtomicU32,
+    eq: [std::sync::atomic::AtomicU32; 3],
+}
+
+impl PlaybackParams {
+    fn new(params: LiveParams) -> Self {
+        let bits = |v: f32| std::sync::atomic::AtomicU32::new(v.to_bits());
+        Self {
+            volume: bits(params.volume),
+            comfort_noise: bits(params.comfort_noise),
+            eq: EqBand::ALL.map(|band| bits(params.eq.get(band))),
+        }
+    }
+
+    fn store(&self, params: LiveParams) {
+        let store = |to: &std::sync::atomic::AtomicU32, v: f32| {
+            to.store(v.to_bits(), std::sync::atomic::Ordering::Relaxed);
+        };
+        store(&self.volume, params.volume);
+        store(&self.comfort_noise, params.comfort_noise);
+        for (to, band) in self.eq.iter().zip(EqBand::ALL) {
+            store(to, params.eq.get(band));
+        }
+    }
+
+    fn load(&self) -> LiveParams {
+        let load = |from: &std::sync::atomic::AtomicU32| {
+            f32::from_bits(from.load(std::sync::atomic::Ordering::Relaxed))
+        };
+        let [low, mid, high] = self.eq.each_ref().map(load);
+        LiveParams {
+            volume: load(&self.volume),
+            comfort_noise: load(&self.comfort_noise),
+            eq: EqGains { low, mid, high },
+        }
+    }
+}
+
+/// Per-playback settings carried by `PwCommand::Play`. Volume, comfort
+/// noise and EQ follow `LiveParams` instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackOptions {
+    /// The clip's own factor on top of the global volume.
+    pub volume: f32,
+    pub noise_kind: NoiseKind,
+    pub fade_in_ms: u32,
+    pub fade_out_ms: u32,
+    /// Comfort noise fades to silence over this long after the last sample.
+    pub noise_tail_ms: u32,
+    pub limiter: bool,
+    pub mono_downmix: bool,
+    /// Follow the mic sidechain gain (duck while the user talks).
+    pub sidechain: bool,
+    /// Leave volume up to 1.0 to the stream's channel volumes, where system
+    /// mixers show it; only the part above is scaled in software.
+    pub native_volume: bool,
+}
+
+impl Default for PlaybackOptions {
+    // Same values as a fresh config, with the optional stages switched off
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            noise_kind: NoiseKind::White,
+            fade_in_ms: 0,
+            fade_out_ms: 0,
+            noise_tail_ms: 50,
+            limiter: false,
+            mono_downmix: false,
+            sidechain: false,
+            native_volume: false,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum PwEvent {
     SinksUpdated(Vec<PwSink>),
-    PlaybackFinished,
+    /// Sent exactly once per `Play`, whether it ran to the end, was cut
+    /// short or never started; `reason` says which.
+    PlaybackFinished {
+        id: u64,
+        song: String,
+        reason: FinishReason,
+    },
+    /// Linear peak/RMS of what playback wrote since the last report.
+    OutputLevel {
+        peak: f32,
+        rms: f32,
+        limiting: bool,
+    },
+    /// How far the active playback got, by samples consumed.
+    PlaybackProgress {
+        position_secs: f64,
+        total_secs: f64,
+    },
+    /// A `timed` play's stream was connected and its first real audio written.
+    PlaybackStarted {
+        id: u64,
+        connected: std::time::Instant,
+        first_audio: std::time::Instant,
+    },
+    /// Why a playback failed, ready to show the user. Comes just before its
+    /// `PlaybackFinished` with `FinishReason::Error`.
+    PlaybackError(String),
+    /// `node.name` of the desktop's default output changed, or it was unset.
+    DefaultSinkChanged(Option<String>),
+}
+
+/// Whether one playback was cut short, and why. Its streams share it, so a
+/// stop ends an injected clip and its monitor copy together.
+#[derive(Clone, Default)]
+struct StopFlag(std::sync::Arc<std::sync::atomic::AtomicU8>);
+
+impl StopFlag {
+    const RUNNING: u8 = 0;
+    const STOPPED: u8 = 1;
+    const REPLACED: u8 = 2;
+
+    /// Only the first stop counts: a clip replaced and then stopped while
+    /// fading out was still replaced.
+    fn stop(&self, reason: FinishReason) {
+        let code = match reason {
+            FinishReason::Replaced => Self::REPLACED,
+            _ => Self::STOPPED,
+        };
+        let _ = self.0.compare_exchange(
+            Self::RUNNING,
+            code,
+            std::sync::atomic::Ordering::SeqCst,
+            std::sync::atomic::Ordering::SeqCst,
+        );
+    }
+
+    fn reason(&self) -> Option<FinishReason> {
+        match self.0.load(std::sync::atomic::Ordering::SeqCst) {
+            Self::RUNNING => None,
+            Self::REPLACED => Some(FinishReason::Replaced),
+            _ => Some(FinishReason::Stopped),
+        }
+    }
+
+    /// A playback still holds a copy.
+    fn in_use(&self) -> bool {
+        std::sync::Arc::strong_count(&self.0) > 1
+    }
+}
+
+/// Stop every playback in `live` that hasn't ended yet, forgetting them all.
+fn stop_playbacks(live: &mut Vec<(u64, StopFlag)>, reason: FinishReason) {
+    for (_, flag) in live.drain(..) {
+        flag.stop(reason);
+    }
+}
+
+/// Shared switches a playback stream checks on every buffer.
+#[derive(Clone)]
+struct PlaybackControl {
+    paused: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    stop: StopFlag,
+    params: std::sync::Arc<PlaybackParams>,
+    /// Playback id for `PwEvent::PlaybackStarted`; only set for timed plays.
+    report_start: Option<u64>,
+    /// Copy the output to the live stream; off for a monitor copy, whose
+    /// injected twin already does.
+    #[cfg(feature = "live-stream")]
+    live: bool,
+}
+
+impl PlaybackControl {
+    fn paused(&self) -> bool {
+        self.paused.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// This playback was stopped or replaced.
+    fn stopped(&self) -> bool {
+        self.stop.reason().is_some()
+    }
+
+    /// How a playback that ran (or was skipped) with this control ended.
+    fn finish_reason(&self, result: &Result<()>) -> FinishReason {
+        if let Some(reason) = self.stop.reason() {
+            reason
+        } else if result.is_err() {
+            FinishReason::Error
+        } else {
+            FinishReason::Completed
+        }
+    }
+}
+
+/// Level reports are throttled to roughly the TUI draw rate.
+const LEVEL_REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+// Send the meter's reading once `LEVEL_REPORT_INTERVAL` has passed.
+fn report_level(meter: &mut LevelMeter, evt_tx: &Sender<PwEvent>) {
+    if let Some((peak, rms, limiting)) = meter.take(LEVEL_REPORT_INTERVAL) {
+        let _ = evt_tx.send(PwEvent::OutputLevel {
+            peak,
+            rms,
+            limiting,
+        });
+    }
+}
+
+/// Milliseconds of consumed audio between `PwEvent::PlaybackProgress` reports.
+const PROGRESS_REPORT_MS: usize = 500;
+
+/// How long a stopped or replaced clip takes to fade out.
+const STOP_FADE_MS: u32 = 100;
+
+/// Throttles progress reports by audio consumed rather than wall-clock, so a
+/// paused playback goes quiet. Reports the start and the end once each.
+struct ProgressReporter {
+    samples_per_sec: usize,
+    /// From the container headers; only a hint until the clip has ended.
+    total_samples: Option<usize>,
+    next_report: usize,
+}
+
+impl ProgressReporter {
+    fn new(total_samples: Option<usize>, sample_rate: u32, channels: u32) -> Self {
+        Self {
+            samples_per_sec: (sample_rate as usize * channels.max(1) as usize).max(1),
+            total_samples,
+            next_report: 0,
+        }
+    }
+
+    fn report(&mut self, pos: usize, done: bool, evt_tx: &Sender<PwEvent>) {
+        let end_due = done && self.next_report != usize::MAX;
+        if pos < self.next_report && !end_due {
+            return;
+        }
+        let total = match self.total_samples {
+            Some(total) if !done => total.max(pos),
+            _ => pos,
+        };
+        let _ = evt_tx.send(PwEvent::PlaybackProgress {
+            position_secs: pos as f64 / self.samples_per_sec as f64,
+            total_secs: total as f64 / self.samples_per_sec as f64,
+        });
+        self.next_report = if done {
+            usize::MAX
+        } else {
+            pos + self.samples_per_sec * PROGRESS_REPORT_MS / 1000
+        };
+    }
 }
 
 // ── PipeWire thread ──────────────────────────────────────────────────────────
@@ -60,286 +400,1182 @@ pub fn spawn_pw_thread(
 ) -> std::thread::JoinHandle<()> {
     std::thread::spawn(move || {
         if let Err(e) = pw_thread_main(cmd_rx, evt_tx) {
-            crate::log::log_error(&format!("PipeWire thread error: {e}"));
+            LOG.error(&format!("PipeWire thread error: {e}"));
         }
     })
 }
 
-fn pw_thread_main(cmd_rx: Receiver<PwCommand>, evt_tx: Sender<PwEvent>) -> Result<()> {
-    // Helper: do a fresh enumeration using a temporary PW connection
-    fn enumerate_devices() -> Result<Vec<PwSink>> {
-        let mainloop = MainLoop::new(None)?;
-        let context = Context::new(&mainloop)?;
-        let core = context.connect(None)?;
-        let registry = core.get_registry()?;
-
-        use std::collections::{HashMap, HashSet};
-
-        struct RawSink {
-            id: u32,
-            name: String,
-            description: String,
-            kind: DeviceKind,
-            client_id: Option<u32>,
-        }
-
-        // Store client globals so we can bind to them later
-        type ClientGlobal = pipewire::registry::GlobalObject<pipewire::properties::Properties>;
-
-        let client_globals = std::sync::Arc::new(std::sync::Mutex::new(Vec::<ClientGlobal>::new()));
-        let client_globals_clone = client_globals.clone();
-        let client_ids_needed = std::sync::Arc::new(std::sync::Mutex::new(HashSet::<u32>::new()));
-        let client_ids_clone = client_ids_needed.clone();
-        let raw_sinks = std::sync::Arc::new(std::sync::Mutex::new(Vec::<RawSink>::new()));
-        let raw_sinks_clone = raw_sinks.clone();
-
-        // Pass 1: collect audio nodes and client globals
-        let _reg_listener = registry
-            .add_listener_local()
-            .global(move |global| {
-                if let Some(props) = global.props {
-                    // Store client globals for later binding
-                    if global.type_ == pipewire::types::ObjectType::Client {
-                        client_globals_clone.lock().unwrap().push(ClientGlobal {
-                            id: global.id,
-                            permissions: global.permissions,
-                            type_: global.type_.clone(),
-                            version: global.version,
-                            props: None,
-                        });
-                        return;
-                    }
+/// Audio devices settle in bursts (a headset brings several nodes), so an
+/// update goes out once the registry has been quiet this long...
+const DEVICE_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(200);
+/// ...or once the first unsent change is this old, whichever comes first.
+const DEVICE_MAX_DELAY: std::time::Duration = std::time::Duration::from_millis(800);
+
+/// An audio node as announced by the registry, before its client's binary
+/// is known.
+#[derive(Debug, Clone, PartialEq)]
+struct RawSink {
+    name: String,
+    description: String,
+    kind: DeviceKind,
+    client_id: Option<u32>,
+}
 
-                    let media_class = props.get("media.class").unwrap_or("");
-                    let kind = match media_class {
-                        "Audio/Sink" => Some(DeviceKind::Output),
-                        "Audio/Source" | "Stream/Input/Audio" => Some(DeviceKind::Input),
-                        _ => None,
-                    };
-                    if let Some(kind) = kind {
-                        let id = global.id;
-                        let name = props.get("node.name").unwrap_or("").to_string();
-                        let client_id: Option<u32> = props
-                            .get("client.id")
-                            .and_then(|s| s.parse().ok());
-                        let is_hw_source = media_class == "Audio/Source";
-                        let description = match kind {
-                            DeviceKind::Input if is_hw_source => {
-                                // Hardware audio source (microphone) — use node.description
-                                props.get("node.description")
-                                    .unwrap_or(&name)
-                                    .to_string()
-                            }
-                            DeviceKind::Input => {
-                                // Application capture stream — resolve client binary
-                                if let Some(cid) = client_id {
-                                    client_ids_clone.lock().unwrap().insert(cid);
-                                }
-                                if name.is_empty() { format!("Stream #{id}") } else { name.clone() }
-                            }
-                            DeviceKind::Output => {
-                                props.get("node.description").unwrap_or(&name).to_string()
-                            }
-                        };
-                        raw_sinks_clone.lock().unwrap().push(RawSink {
-                            id, name, description, kind, client_id,
-                        });
-                    }
+impl RawSink {
+    /// The audio node described by a registry global's properties, if it is
+    /// one. Capture streams that belong to a client remember it so their
+    /// description can name the app.
+    fn from_props<'a>(id: u32, prop: impl Fn(&str) -> Option<&'a str>) -> Option<Self> {
+        let media_class = prop("media.class").unwrap_or("");
+        let kind = match media_class {
+            "Audio/Sink" => DeviceKind::Output,
+            "Stream/Input/Audio" => DeviceKind::Input,
+            "Audio/Source" | "Audio/Source/Virtual" => DeviceKind::Source,
+            _ => return None,
+        };
+        let name = prop("node.name").unwrap_or("").to_string();
+        let description = match kind {
+            // Application capture stream, named after the client below
+            DeviceKind::Input => {
+                if name.is_empty() {
+                    format!("Stream #{id}")
+                } else {
+                    name.clone()
                 }
+            }
+            // Hardware devices have a readable node.description
+            _ => prop("node.description").unwrap_or(&name).to_string(),
+        };
+        let client_id = match media_class {
+            "Stream/Input/Audio" => prop("client.id").and_then(|s| s.parse().ok()),
+            _ => None,
+        };
+        Some(Self {
+            name,
+            description,
+            kind,
+            client_id,
+        })
+    }
+}
+
+/// `node.name` of the "System Default" entry, as `wpctl` spells it.
+pub const DEFAULT_SINK_NAME: &str = "@DEFAULT_AUDIO_SINK@";
+
+/// The desktop's default output from a `default.audio.sink` metadata value,
+/// a JSON object like `{"name":"alsa_output.pci-0000_00_1f.3.analog-stereo"}`.
+fn default_sink_name(value: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(value).ok()?;
+    Some(value.get("name")?.as_str()?.to_string())
+}
+
+/// What the persistent registry connection knows: audio nodes and client
+/// binaries by global id, plus the debounce state for `SinksUpdated`.
+#[derive(Default)]
+struct DeviceRegistry {
+    nodes: std::collections::BTreeMap<u32, RawSink>,
+    binaries: std::collections::HashMap<u32, String>,
+    /// Last `default.audio.sink` reported to the app.
+    default_sink: Option<String>,
+    /// The initial roundtrip finished, so the list is complete.
+    synced: bool,
+    /// First and latest change since the last update went out.
+    pending: Option<(std::time::Instant, std::time::Instant)>,
+    last_sent: Option<Vec<PwSink>>,
+}
+
+impl DeviceRegistry {
+    fn add_node(&mut self, id: u32, sink: RawSink) {
+        if self.nodes.get(&id) != Some(&sink) {
+            self.nodes.insert(id, sink);
+            self.changed();
+        }
+    }
+
+    fn add_binary(&mut self, client_id: u32, binary: String) {
+        if self.binaries.get(&client_id) != Some(&binary) {
+            self.binaries.insert(client_id, binary);
+            self.changed();
+        }
+    }
+
+    /// Forget a global; ids are shared by every object type.
+    fn remove(&mut self, id: u32) {
+        let node = self.nodes.remove(&id).is_some();
+        let client = self.binaries.remove(&id).is_some();
+        if node || client {
+            self.changed();
+        }
+    }
+
+    fn changed(&mut self) {
+        let now = std::time::Instant::now();
+        let first = self.pending.map_or(now, |(first, _)| first);
+        self.pending = Some((first, now));
+    }
+
+    /// A debounced update should go out at `now`.
+    fn update_due(&self, now: std::time::Instant) -> bool {
+        self.synced
+            && self.pending.is_some_and(|(first, last)| {
+                now.duration_since(last) >= DEVICE_DEBOUNCE
+                    || now.duration_since(first) >= DEVICE_MAX_DELAY
             })
-            .register();
+    }
 
-        let pending = core.sync(0).expect("sync failed");
-        let mainloop_weak = mainloop.downgrade();
-        let _core_listener = core
-            .add_listener_local()
-            .done(move |id, seq| {
-                if id == pipewire::core::PW_ID_CORE && seq == pending {
-                    if let Some(ml) = mainloop_weak.upgrade() {
-                        ml.quit();
+    /// Report a new default output right away; it changes rarely and
+    /// doesn't touch the list.
+    fn set_default_sink(&mut self, name: Option<String>, evt_tx: &Sender<PwEvent>) {
+        if self.default_sink != name {
+            self.default_sink = name.clone();
+            let _ = evt_tx.send(PwEvent::DefaultSinkChanged(name));
+        }
+    }
+
+    /// Capture streams are described with the binary of their client. A
+    /// "System Default" entry leads the list while there is any output.
+    fn sinks(&self) -> Vec<PwSink> {
+        let mut sinks: Vec<PwSink> = self
+            .nodes
+            .iter()
+            .map(|(&id, raw)| {
+                let binary = raw.client_id.and_then(|cid| self.binaries.get(&cid));
+                let description = match binary {
+                    Some(binary) if *binary != raw.description => {
+                        format!("{} ({})", raw.description, binary)
                     }
+                    _ => raw.description.clone(),
+                };
+                PwSink {
+                    id,
+                    name: raw.name.clone(),
+                    description,
+                    kind: raw.kind,
                 }
             })
-            .register();
+            .collect();
+        if sinks.iter().any(|s| s.kind == DeviceKind::Output) {
+            let default = PwSink {
+                id: DEFAULT_SINK_ID,
+                name: DEFAULT_SINK_NAME.to_string(),
+                description: "System Default".to_string(),
+                kind: DeviceKind::Output,
+            };
+            sinks.insert(0, default);
+        }
+        sinks
+    }
+
+    /// Send the current list, unless it is what the app already has and
+    /// the send isn't `forced`.
+    fn send(&mut self, evt_tx: &Sender<PwEvent>, forced: bool) {
+        self.pending = None;
+        let sinks = self.sinks();
+        if !forced && self.last_sent.as_ref() == Some(&sinks) {
+            return;
+        }
+        self.last_sent = Some(sinks.clone());
+        let _ = evt_tx.send(PwEvent::SinksUpdated(sinks));
+    }
+}
+
+/// How often the monitor checks whether a debounced update is due.
+const DEVICE_TICK: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// What the device monitor's loop is asked to do.
+enum MonitorRequest {
+    Resync,
+    Quit,
+}
+
+/// Thread holding one PipeWire connection open to follow the registry.
+/// Sends `PwEvent::SinksUpdated` on its own whenever audio nodes come or
+/// go, and on every `resync`.
+struct DeviceMonitor {
+    request_tx: pipewire::channel::Sender<MonitorRequest>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl DeviceMonitor {
+    fn spawn(evt_tx: Sender<PwEvent>) -> Self {
+        let (request_tx, request_rx) = pipewire::channel::channel();
+        let thread = std::thread::spawn(move || {
+            if let Err(e) = monitor_devices(request_rx, &evt_tx) {
+                // PipeWire may not be up yet at boot, or went away; an empty
+                // list makes the daemon keep asking with ListSinks
+                LOG.error(&format!("Device monitor stopped: {e}"));
+                let _ = evt_tx.send(PwEvent::SinksUpdated(Vec::new()));
+            }
+        });
+        Self { request_tx, thread }
+    }
+
+    fn running(&self) -> bool {
+        !self.thread.is_finished()
+    }
 
-        mainloop.run();
+    fn resync(&self) {
+        let _ = self.request_tx.send(MonitorRequest::Resync);
+    }
 
-        // Pass 2: bind to each needed client to get application.process.binary
-        let clients_map = std::sync::Arc::new(std::sync::Mutex::new(HashMap::<u32, String>::new()));
-        let needed = client_ids_needed.lock().unwrap().clone();
-        let stored_globals = client_globals.lock().unwrap();
+    /// Close the connection and wait for the thread to end.
+    fn shutdown(self) {
+        let _ = self.request_tx.send(MonitorRequest::Quit);
+        if self.thread.join().is_err() {
+            LOG.error("Device monitor thread panicked");
+        }
+    }
+}
 
-        // Keep bound proxies and listeners alive until roundtrip completes
-        let mut _bound_clients = Vec::new();
-        let mut _client_listeners = Vec::new();
+fn monitor_devices(
+    request_rx: pipewire::channel::Receiver<MonitorRequest>,
+    evt_tx: &Sender<PwEvent>,
+) -> Result<()> {
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
-        for global in stored_globals.iter().filter(|g| needed.contains(&g.id)) {
-            let clients_map_clone = clients_map.clone();
-            let cid_copy = global.id;
-            match registry.bind::<pipewire::client::Client, _>(global) {
-                Ok(client) => {
+    let mainloop = MainLoop::new(None)?;
+    let context = Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+    let registry = Rc::new(core.get_registry()?);
+    let devices = Rc::new(RefCell::new(DeviceRegistry::default()));
+    // Bound clients, kept alive so their info (the process binary) arrives
+    let clients = Rc::new(RefCell::new(std::collections::HashMap::<
+        u32,
+        (pipewire::client::Client, pipewire::client::ClientListener),
+    >::new()));
+    // The session manager's "default" metadata, which names the default output
+    let metadata = Rc::new(RefCell::new(
+        None::<(
+            u32,
+            pipewire::metadata::Metadata,
+            pipewire::metadata::MetadataListener,
+        )>,
+    ));
+
+    let _reg_listener = registry
+        .add_listener_local()
+        .global({
+            let devices = devices.clone();
+            let clients = clients.clone();
+            let metadata = metadata.clone();
+            let evt_tx = evt_tx.clone();
+            let registry = Rc::downgrade(&registry);
+            move |global| {
+                let Some(props) = global.props else {
+                    return;
+                };
+                if global.type_ == pipewire::types::ObjectType::Metadata {
+                    if props.get("metadata.name") != Some("default") {
+                        return;
+                    }
+                    let Some(proxy) = registry
+                        .upgrade()
+                        .and_then(|r| r.bind::<pipewire::metadata::Metadata, _>(global).ok())
+                    else {
+                        return;
+                    };
+                    let devices = devices.clone();
+                    let evt_tx = evt_tx.clone();
+                    let listener = proxy
+                        .add_listener_local()
+                        .property(move |subject, key, _type, value| {
+                            // A missing key clears every property of the subject
+                            let ours = key.is_none_or(|k| k == "default.audio.sink");
+                            if subject == pipewire::core::PW_ID_CORE && ours {
+                                let name = value.and_then(default_sink_name);
+                                devices.borrow_mut().set_default_sink(name, &evt_tx);
+                            }
+                            0
+                        })
+                        .register();
+                    *metadata.borrow_mut() = Some((global.id, proxy, listener));
+                } else if global.type_ == pipewire::types::ObjectType::Client {
+                    let Some(client) = registry
+                        .upgrade()
+                        .and_then(|r| r.bind::<pipewire::client::Client, _>(global).ok())
+                    else {
+                        return;
+                    };
+                    let client_id = global.id;
+                    let devices = devices.clone();
                     let listener = client
                         .add_listener_local()
                         .info(move |info| {
-                            if let Some(props) = info.props() {
-                                let binary = props
-                                    .get("application.process.binary")
-                                    .unwrap_or("");
-                                if !binary.is_empty() {
-                                    clients_map_clone
-                                        .lock()
-                                        .unwrap()
-                                        .insert(cid_copy, binary.to_string());
-                                }
+                            let binary = info
+                                .props()
+                                .and_then(|p| p.get("application.process.binary"))
+                                .unwrap_or("");
+                            if !binary.is_empty() {
+                                devices
+                                    .borrow_mut()
+                                    .add_binary(client_id, binary.to_string());
                             }
                         })
                         .register();
-                    _client_listeners.push(listener);
-                    _bound_clients.push(client);
+                    clients.borrow_mut().insert(client_id, (client, listener));
+                } else if let Some(sink) = RawSink::from_props(global.id, |k| props.get(k)) {
+                    devices.borrow_mut().add_node(global.id, sink);
                 }
-                Err(_) => {}
+            }
+        })
+        .global_remove({
+            let devices = devices.clone();
+            let clients = clients.clone();
+            let metadata = metadata.clone();
+            move |id| {
+                clients.borrow_mut().remove(&id);
+                devices.borrow_mut().remove(id);
+                if metadata
+                    .borrow()
+                    .as_ref()
+                    .is_some_and(|(meta_id, ..)| *meta_id == id)
+                {
+                    *metadata.borrow_mut() = None;
+                }
+            }
+        })
+        .register();
+
+    // The first list goes out as soon as the initial globals are in
+    let pending = core.sync(0)?;
+    let lost = Rc::new(RefCell::new(None::<String>));
+    let _core_listener = core
+        .add_listener_local()
+        .done({
+            let devices = devices.clone();
+            let evt_tx = evt_tx.clone();
+            move |id, seq| {
+                if id == pipewire::core::PW_ID_CORE && seq == pending {
+                    let mut devices = devices.borrow_mut();
+                    devices.synced = true;
+                    devices.send(&evt_tx, true);
+                }
+            }
+        })
+        .error({
+            let lost = lost.clone();
+            let mainloop = mainloop.downgrade();
+            move |id, _seq, res, message| {
+                if id == pipewire::core::PW_ID_CORE {
+                    *lost.borrow_mut() = Some(format!("{message} ({res})"));
+                    if let Some(ml) = mainloop.upgrade() {
+                        ml.quit();
+                    }
+                }
+            }
+        })
+        .register();
+
+    let timer = mainloop.loop_().add_timer({
+        let devices = devices.clone();
+        let evt_tx = evt_tx.clone();
+        move |_| {
+            let mut devices = devices.borrow_mut();
+            if devices.update_due(std::time::Instant::now()) {
+                devices.send(&evt_tx, false);
             }
         }
+    });
+    timer.update_timer(Some(DEVICE_TICK), Some(DEVICE_TICK));
+
+    let quit = Rc::new(std::cell::Cell::new(false));
+    let _requests = request_rx.attach(mainloop.loop_(), {
+        let devices = devices.clone();
+        let evt_tx = evt_tx.clone();
+        let quit = quit.clone();
+        let mainloop = mainloop.downgrade();
+        move |request| match request {
+            MonitorRequest::Resync => {
+                let mut devices = devices.borrow_mut();
+                if devices.synced {
+                    devices.send(&evt_tx, true);
+                }
+            }
+            MonitorRequest::Quit => {
+                quit.set(true);
+                if let Some(ml) = mainloop.upgrade() {
+                    ml.quit();
+                }
+            }
+        }
+    });
 
-        drop(stored_globals);
+    mainloop.run();
 
-        // Second roundtrip to receive client info
-        if !_bound_clients.is_empty() {
-            let pending2 = core.sync(0).expect("sync failed");
-            let mainloop_weak2 = mainloop.downgrade();
-            let _core_listener2 = core
-                .add_listener_local()
-                .done(move |id, seq| {
-                    if id == pipewire::core::PW_ID_CORE && seq == pending2 {
-                        if let Some(ml) = mainloop_weak2.upgrade() {
-                            ml.quit();
-                        }
-                    }
-                })
-                .register();
-
-            mainloop.run();
-        }
-
-        // Enrich Input descriptions with the resolved app binary
-        let cmap = clients_map.lock().unwrap();
-        let result: Vec<PwSink> = raw_sinks
-            .lock()
-            .unwrap()
-            .drain(..)
-            .map(|raw| {
-                let description = if raw.kind == DeviceKind::Input {
-                    if let Some(binary) = raw.client_id.and_then(|cid| cmap.get(&cid)) {
-                        if binary != &raw.description {
-                            format!("{} ({})", raw.description, binary)
-                        } else {
-                            raw.description
-                        }
-                    } else {
-                        raw.description
-                    }
-                } else {
-                    raw.description
-                };
-                PwSink {
-                    id: raw.id,
-                    name: raw.name,
-                    description,
-                    kind: raw.kind,
-                }
-            })
-            .collect();
-        Ok(result)
+    if quit.get() {
+        return Ok(());
     }
+    let reason = lost.borrow_mut().take();
+    anyhow::bail!("PipeWire connection lost: {}", reason.unwrap_or_default())
+}
 
-    // Initial enumeration
-    let devices = enumerate_devices()?;
-    let _ = evt_tx.send(PwEvent::SinksUpdated(devices));
+fn pw_thread_main(cmd_rx: Receiver<PwCommand>, evt_tx: Sender<PwEvent>) -> Result<()> {
+    // Follows the registry for the thread's lifetime and sends the first
+    // list once connected
+    let mut monitor = DeviceMonitor::spawn(evt_tx.clone());
+
+    // Continuous noise stream: level shared as f32 bits and kind as its index
+    // in `NoiseKind::ALL`, plus the running target
+    let noise_level = std::sync::Arc::new(std::sync::atomic::AtomicU32::new(0));
+    let noise_kind = std::sync::Arc::new(std::sync::atomic::AtomicU8::new(0));
+    let mut noise: Option<(u32, Sender<()>, std::thread::JoinHandle<()>)> = None;
+
+    // Checked by every playback stream on each buffer. Each playback quits
+    // once its own stop flag is set; `live` has the ones that may still run.
+    let paused = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let mut live: Vec<(u64, StopFlag)> = Vec::new();
+    let params = std::sync::Arc::new(PlaybackParams::new(LiveParams::default()));
+    let (playback_tx, playback_worker) = spawn_playback_worker(evt_tx.clone());
+    let mut stream_properties = std::sync::Arc::new(StreamProperties::default());
 
     // Process commands
     for cmd in cmd_rx {
         match cmd {
+            // A forced re-sync; reconnects if the monitor lost PipeWire
             PwCommand::ListSinks => {
-                let devices = enumerate_devices().unwrap_or_default();
-                let _ = evt_tx.send(PwEvent::SinksUpdated(devices));
+                if monitor.running() {
+                    monitor.resync();
+                } else {
+                    monitor = DeviceMonitor::spawn(evt_tx.clone());
+                }
             }
             PwCommand::Play {
+                id,
+                song,
+                interrupt,
                 sink_id,
                 kind,
                 node_name: _,
-                samples,
-                sample_rate,
-                channels,
-                volume,
-                comfort_noise,
-                eq_mid_boost,
+                audio,
+                options,
+                timed,
+                monitor_sink,
             } => {
-                let evt_tx_play = evt_tx.clone();
-                std::thread::spawn(move || {
-                    let result = match kind {
-                        DeviceKind::Output => play_audio_threaded(sink_id, samples, sample_rate, channels, volume, comfort_noise, eq_mid_boost),
-                        DeviceKind::Input => play_to_input_stream(sink_id, samples, sample_rate, channels, volume, comfort_noise, eq_mid_boost),
-                    };
-                    if let Err(e) = result {
-                        crate::log::log_error(&format!("Playback error: {e}"));
+                if interrupt {
+                    stop_playbacks(&mut live, FinishReason::Replaced);
+                }
+                // Ended playbacks dropped their copies
+                live.retain(|(_, flag)| flag.in_use());
+                let stop = StopFlag::default();
+                live.push((id, stop.clone()));
+                let control = PlaybackControl {
+                    paused: paused.clone(),
+                    stop,
+                    params: params.clone(),
+                    report_start: timed.then_some(id),
+                    #[cfg(feature = "live-stream")]
+                    live: true,
+                };
+                let job = PlaybackJob {
+                    id,
+                    song,
+                    sink_id,
+                    kind,
+                    audio,
+                    options,
+                    control,
+                    stream_properties: stream_properties.clone(),
+                    monitor_sink,
+                };
+                if let Err(std::sync::mpsc::SendError(job)) = playback_tx.send(job) {
+                    let _ = evt_tx.send(PwEvent::PlaybackError(format!(
+                        "Failed to play {}: playback worker stopped",
+                        job.song
+                    )));
+                    let _ = evt_tx.send(PwEvent::PlaybackFinished {
+                        id: job.id,
+                        song: job.song,
+                        reason: FinishReason::Error,
+                    });
+                }
+            }
+            PwCommand::SetPaused(value) => {
+                paused.store(value, std::sync::atomic::Ordering::Relaxed);
+            }
+            PwCommand::StopPlayback => {
+                stop_playbacks(&mut live, FinishReason::Stopped);
+                paused.store(false, std::sync::atomic::Ordering::Relaxed);
+            }
+            PwCommand::StopPlaybacks(ids) => {
+                live.retain(|(id, flag)| {
+                    let hit = ids.contains(id);
+                    if hit {
+                        flag.stop(FinishReason::Stopped);
                     }
-                    let _ = evt_tx_play.send(PwEvent::PlaybackFinished);
+                    !hit
                 });
             }
+            PwCommand::ContinuousNoise {
+                target_id,
+                level,
+                kind,
+            } => {
+                noise_level.store(level.to_bits(), std::sync::atomic::Ordering::Relaxed);
+                noise_kind.store(kind as u8, std::sync::atomic::Ordering::Relaxed);
+                if noise.as_ref().map(|(id, ..)| *id) != target_id {
+                    if let Some((_, stop_tx, _)) = noise.take() {
+                        let _ = stop_tx.send(());
+                    }
+                    if let Some(id) = target_id {
+                        let (stop_tx, stop_rx) = std::sync::mpsc::channel();
+                        let level = noise_level.clone();
+                        let kind = noise_kind.clone();
+                        let thread = std::thread::spawn(move || {
+                            if let Err(e) = play_noise_stream(id, level, kind, stop_rx) {
+                                LOG.error(&format!("Continuous noise error: {e}"));
+                            }
+                        });
+                        noise = Some((id, stop_tx, thread));
+                    }
+                }
+            }
+            PwCommand::SetStreamProperties(properties) => {
+                stream_properties = std::sync::Arc::new(properties);
+            }
+            PwCommand::UpdateParams(live) => params.store(live),
+            PwCommand::Shutdown => break,
         }
     }
 
+    // Cut the clip short and let every stream close before returning
+    stop_playbacks(&mut live, FinishReason::Stopped);
+    paused.store(false, std::sync::atomic::Ordering::Relaxed);
+    drop(playback_tx);
+    if playback_worker.join().is_err() {
+        LOG.error("Playback worker panicked");
+    }
+    if let Some((_, stop_tx, thread)) = noise.take() {
+        let _ = stop_tx.send(());
+        if thread.join().is_err() {
+            LOG.error("Continuous noise thread panicked");
+        }
+    }
+    monitor.shutdown();
     Ok(())
 }
 
-// Peaking EQ biquad coefficients (Audio EQ Cookbook)
-// center_freq = 1000 Hz, Q = 1.0, gain derived from eq_mid_boost
-fn compute_biquad(sample_rate: f32, boost: f32) -> [f32; 5] {
-    let gain_db = 20.0 * boost.log10();
-    let a_val = 10.0_f32.powf(gain_db / 40.0);
-    let w0 = 2.0 * std::f32::consts::PI * 1000.0 / sample_rate;
-    let sin_w0 = w0.sin();
-    let cos_w0 = w0.cos();
-    let alpha = sin_w0 / 2.0; // Q = 1.0
-    let b0 = 1.0 + alpha * a_val;
-    let b1 = -2.0 * cos_w0;
-    let b2 = 1.0 - alpha * a_val;
-    let a0 = 1.0 + alpha / a_val;
-    let a1 = -2.0 * cos_w0;
-    let a2 = 1.0 - alpha / a_val;
-    [b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0]
-}
-
-// Simple xorshift64 PRNG for noise generation
-fn next_noise(state: &std::sync::atomic::AtomicU64) -> f32 {
-    use std::sync::atomic::Ordering;
-    let mut s = state.load(Ordering::Relaxed);
-    if s == 0 { s = 0xDEADBEEFCAFE; }
-    s ^= s << 13;
-    s ^= s >> 7;
-    s ^= s << 17;
-    state.store(s, Ordering::Relaxed);
-    (s as i64 as f32) / (i64::MAX as f32)
+/// One `PwCommand::Play` waiting for the playback worker.
+struct PlaybackJob {
+    id: u64,
+    song: String,
+    sink_id: u32,
+    kind: DeviceKind,
+    audio: AudioStream,
+    options: PlaybackOptions,
+    control: PlaybackControl,
+    stream_properties: std::sync::Arc<StreamProperties>,
+    monitor_sink: Option<u32>,
+}
+
+// Plays jobs one after another so clips never overlap. Jobs stopped while
+// they waited are skipped but still reported as finished, with the reason
+// they were stopped.
+fn spawn_playback_worker(
+    evt_tx: Sender<PwEvent>,
+) -> (Sender<PlaybackJob>, std::thread::JoinHandle<()>) {
+    let (job_tx, job_rx) = std::sync::mpsc::channel::<PlaybackJob>();
+    let worker = std::thread::spawn(move || {
+        for job in job_rx {
+            let PlaybackJob {
+                id,
+                song,
+                sink_id,
+                kind,
+                audio,
+                options,
+                control,
+                stream_properties,
+                monitor_sink,
+            } = job;
+            let result = if control.stopped() {
+                Ok(())
+            } else {
+                let stream_control = control.clone();
+                match kind {
+                    DeviceKind::Output => {
+                        let mut reader = ClipReader::new(audio, &options);
+                        reader.prefill();
+                        play_audio_threaded(
+                            sink_id,
+                            reader,
+                            options,
+                            stream_control,
+                            &stream_properties.playback,
+                            evt_tx.clone(),
+                        )
+                    }
+                    DeviceKind::Input => {
+                        // The monitor copy is fed the injected stream's chunks
+                        // as it takes them and shares its control, so a stop
+                        // ends both. It reports nothing: the injected stream
+                        // speaks for the pair
+                        let (tee, monitor_audio) = AudioStream::channel(
+                            audio.sample_rate,
+                            audio.channels,
+                            audio.total_samples,
+                            crate::audio::STREAM_BUFFER_CHUNKS * 2,
+                        );
+                        let mut reader = ClipReader::new(audio, &options);
+                        if monitor_sink.is_some() {
+                            reader.tee = Some(tee);
+                        }
+                        reader.prefill();
+                        let monitor = monitor_sink.map(|monitor_id| {
+                            let control = PlaybackControl {
+                                report_start: None,
+                                #[cfg(feature = "live-stream")]
+                                live: false,
+                                ..stream_control.clone()
+                            };
+                            let stream_properties = stream_properties.clone();
+                            std::thread::spawn(move || {
+                                let (quiet_tx, _) = std::sync::mpsc::channel();
+                                let mut reader = ClipReader::new(monitor_audio, &options);
+                                reader.prefill();
+                                play_audio_threaded(
+                                    monitor_id,
+                                    reader,
+                                    options,
+                                    control,
+                                    &stream_properties.playback,
+                                    quiet_tx,
+                                )
+                            })
+                        });
+                        let result = play_to_input_stream(
+                            sink_id,
+                            reader,
+                            options,
+                            stream_control,
+                            &stream_properties.injection,
+                            evt_tx.clone(),
+                        );
+                        match monitor.map(|handle| handle.join()) {
+                            Some(Ok(Err(e))) => LOG.error(&format!("Monitor playback error: {e}")),
+                            Some(Err(_)) => LOG.error("Monitor playback thread panicked"),
+                            _ => {}
+                        }
+                        result
+                    }
+                    DeviceKind::Source => Err(anyhow::anyhow!(
+                        "node {sink_id} is a capture device, not an output"
+                    )),
+                }
+            };
+            let reason = control.finish_reason(&result);
+            if let Err(e) = &result {
+                LOG.error(&format!("Playback error: {e}"));
+                if reason == FinishReason::Error {
+                    let message = format!("Failed to play {song}: {e}");
+                    let _ = evt_tx.send(PwEvent::PlaybackError(message));
+                }
+            }
+            let _ = evt_tx.send(PwEvent::PlaybackFinished { id, song, reason });
+        }
+    });
+    (job_tx, worker)
+}
+
+// ── Buffer layout ────────────────────────────────────────────────────────────
+
+// True when a negotiated Format param is raw audio with one plane per channel
+fn format_is_planar(param: &Pod) -> bool {
+    use pipewire::spa::param::{
+        format::{MediaSubtype, MediaType},
+        format_utils,
+    };
+
+    match format_utils::parse_format(param) {
+        Ok((media_type, media_subtype))
+            if media_type == MediaType::Audio && media_subtype == MediaSubtype::Raw => {}
+        _ => return false,
+    }
+    let mut info = AudioInfoRaw::new();
+    if info.parse(param).is_err() {
+        return false;
+    }
+    let format = info.format();
+    format == AudioFormat::F32P || format == AudioFormat::S16P
+}
+
+// Copy an interleaved block into the buffer: as-is into the first data for
+// interleaved streams, one channel per data for planar ones. `valid` counts
+// the interleaved samples holding audio; the rest of the block is padding.
+fn write_block(
+    datas: &mut [pipewire::spa::buffer::Data],
+    block: &[f32],
+    valid: usize,
+    channels: u32,
+    planar: bool,
+) {
+    let sample_size = std::mem::size_of::<f32>();
+    let channels = channels.max(1) as usize;
+
+    if planar {
+        {
+            let mut planes: Vec<&mut [u8]> = datas
+                .iter_mut()
+                .take(channels)
+                .filter_map(|data| data.data())
+                .collect();
+            deinterleave(block, channels, &mut planes);
+        }
+        for data in datas.iter_mut().take(channels) {
+            let chunk = data.chunk_mut();
+            *chunk.offset_mut() = 0;
+            *chunk.stride_mut() = sample_size as i32;
+            *chunk.size_mut() = (valid / channels * sample_size) as u32;
+        }
+        return;
+    }
+
+    let Some(data) = datas.first_mut() else {
+        return;
+    };
+    if let Some(slice) = data.data() {
+        copy_f32(slice, block);
+    }
+    let chunk = data.chunk_mut();
+    *chunk.offset_mut() = 0;
+    *chunk.stride_mut() = (sample_size * channels) as i32;
+    *chunk.size_mut() = (valid * sample_size) as u32;
+}
+
+/// Decoded audio a playback waits for before its stream connects.
+const PREFILL_MS: u32 = 1000;
+
+/// Hands a streamed clip to the process callback, applying the stages that
+/// don't depend on stream state: mono downmix and fades. The fade-out needs
+/// to know where the clip ends, so that many frames are held back until the
+/// decoder says it is done.
+struct ClipReader {
+    audio: AudioStream,
+    channels: usize,
+    mono_downmix: bool,
+    fade_in: usize,
+    fade_out: usize,
+    /// Samples taken from the stream and not handed out yet.
+    pending: std::collections::VecDeque<f32>,
+    /// Frames taken from the stream so far.
+    received: usize,
+    /// Samples handed out so far.
+    position: usize,
+    ended: bool,
+    error: Option<anyhow::Error>,
+    /// Gets a copy of every chunk taken, for a monitor stream; dropped
+    /// once the clip ends.
+    tee: Option<std::sync::mpsc::SyncSender<Result<Vec<f32>>>>,
+    /// Chunks the monitor copy missed because it fell a whole buffer behind.
+    tee_dropped: usize,
+}
+
+impl ClipReader {
+    fn new(audio: AudioStream, options: &PlaybackOptions) -> Self {
+        let channels = audio.channels.max(1) as usize;
+        let frames = audio
+            .total_samples
+            .map_or(usize::MAX, |total| total / channels);
+        let fade_frames =
+            |ms: u32| ((audio.sample_rate as u64 * ms as u64 / 1000) as usize).min(frames);
+        Self {
+            channels,
+            mono_downmix: options.mono_downmix && channels > 1,
+            fade_in: fade_frames(options.fade_in_ms),
+            fade_out: fade_frames(options.fade_out_ms),
+            pending: std::collections::VecDeque::new(),
+            received: 0,
+            position: 0,
+            ended: false,
+            error: None,
+            tee: None,
+            tee_dropped: 0,
+            audio,
+        }
+    }
+
+    /// Wait until `PREFILL_MS` of audio is ready or the clip has ended.
+    fn prefill(&mut self) {
+        let frames = (self.audio.sample_rate as u64 * PREFILL_MS as u64 / 1000) as usize;
+        self.pull(frames * self.channels, true);
+    }
+
+    /// Take chunks until `want` samples can be handed out, or the decoder
+    /// has nothing more ready.
+    fn pull(&mut self, want: usize, wait: bool) {
+        let target = want + self.fade_out * self.channels;
+        while !self.ended && self.pending.len() < target {
+            let chunk = if wait {
+                self.audio.next()
+            } else {
+                self.audio.try_next()
+            };
+            match chunk {
+                StreamChunk::Samples(samples) => {
+                    if let Some(tee) = &self.tee {
+                        if let Err(TrySendError::Full(_)) = tee.try_send(Ok(samples.clone())) {
+                            self.tee_dropped += 1;
+                        }
+                    }
+                    self.push(samples);
+                }
+                StreamChunk::Pending => break,
+                StreamChunk::Ended => self.end(None),
+                StreamChunk::Failed(e) => self.end(Some(e)),
+            }
+        }
+    }
+
+    fn push(&mut self, mut samples: Vec<f32>) {
+        let channels = self.channels;
+        for frame in samples.chunks_mut(channels) {
+            if self.mono_downmix {
+                let mean = frame.iter().sum::<f32>() / frame.len() as f32;
+                frame.fill(mean);
+            }
+            if self.received < self.fade_in {
+                let gain = self.received as f32 / self.fade_in as f32;
+                for s in frame.iter_mut() {
+                    *s *= gain;
+                }
+            }
+            self.received += 1;
+        }
+        self.pending.extend(samples);
+    }
+
+    // Everything still pending is the end of the clip: fade out its last frames
+    fn end(&mut self, error: Option<anyhow::Error>) {
+        let tee = self.tee.take();
+        if let (Some(tee), Some(e)) = (tee, &error) {
+            let _ = tee.try_send(Err(anyhow::anyhow!("{e}")));
+        }
+        self.ended = true;
+        self.error = error;
+        // A trailing partial frame would never be handed out
+        self.pending
+            .truncate(self.pending.len() / self.channels * self.channels);
+        let fade_out = self.fade_out.min(self.pending.len() / self.channels);
+        let start = self.pending.len() - fade_out * self.channels;
+        for i in 0..fade_out {
+            let gain = (fade_out - i) as f32 / fade_out as f32;
+            for c in 0..self.channels {
+                self.pending[start + i * self.channels + c] *= gain;
+            }
+        }
+    }
+
+    /// Fill the front of `out` with whatever is ready, without waiting.
+    /// Returns how many samples it wrote, always whole frames; fewer than
+    /// asked for before `finished` means the decoder fell behind.
+    fn read(&mut self, out: &mut [f32]) -> usize {
+        self.pull(out.len(), false);
+        let ready = if self.ended {
+            self.pending.len()
+        } else {
+            self.pending
+                .len()
+                .saturating_sub(self.fade_out * self.channels)
+        };
+        let n = out.len().min(ready) / self.channels * self.channels;
+        for (o, s) in out.iter_mut().zip(self.pending.drain(..n)) {
+            *o = s;
+        }
+        self.position += n;
+        n
+    }
+
+    /// Every sample has been handed out, or decoding failed and what was
+    /// decoded has.
+    fn finished(&self) -> bool {
+        self.ended && self.pending.is_empty()
+    }
+}
+
+// Interleaved samples a buffer of `capacity` f32s per data is filled with:
+// whole frames only, so the next buffer starts on the first channel. A
+// planar buffer holds one channel per data, so the block spans all of them.
+fn block_len(capacity: usize, channels: usize, planar: bool) -> usize {
+    if planar {
+        capacity * channels
+    } else {
+        capacity / channels * channels
+    }
+}
+
+/// Splits a clip's volume between the stream's channel volumes and software
+/// scaling. Without native volume, or once the stream refused its channel
+/// volumes, all of it is done in software.
+struct NodeVolume {
+    native: bool,
+    /// One entry per channel, filled in place so the process callback
+    /// doesn't allocate.
+    volumes: Vec<f32>,
+    applied: Option<f32>,
+}
+
+impl NodeVolume {
+    fn new(native: bool, channels: u32) -> Self {
+        Self {
+            native,
+            volumes: vec![1.0; channels.max(1) as usize],
+            applied: None,
+        }
+    }
+
+    /// Hand the stream its part of `volume`, if that changed. Returns the
+    /// software gain and the node's volume. The first buffer sets it, so the
+    /// clip never starts out at the node's previous volume.
+    fn apply(&mut self, stream: &pipewire::stream::StreamRef, volume: f32) -> (f32, f32) {
+        if self.native {
+            let (node, software) = split_volume(volume);
+            if self.applied == Some(node) {
+                return (software, node);
+            }
+            self.volumes.fill(node);
+            match stream.set_control(pipewire::spa::sys::SPA_PROP_channelVolumes, &self.volumes) {
+                Ok(()) => {
+                    self.applied = Some(node);
+                    return (software, node);
+                }
+                Err(e) => {
+                    let message = format!("Stream volume not set, scaling in software: {e}");
+                    LOG.error(&message);
+                    self.native = false;
+                }
+            }
+        }
+        // Whatever the node was last given stays in effect
+        let node = self.applied.filter(|&v| v > 0.0).unwrap_or(1.0);
+        (volume / node, node)
+    }
+}
+
+/// Lowest channel volume a clip's stream is given. Comfort noise is mixed
+/// into the same samples, so a node at zero would silence it too; below
+/// this the clip is turned down in software instead.
+const NODE_VOLUME_FLOOR: f32 = 1.0 / 16.0;
+
+// The node takes volume between the floor and unity; software boosts past
+// unity, where the limiter can still catch the peaks, and cuts below the
+// floor. Returns (node, software).
+fn split_volume(volume: f32) -> (f32, f32) {
+    let node = volume.clamp(NODE_VOLUME_FLOOR, 1.0);
+    (node, volume / node)
+}
+
+// Fill the padding after a block's real samples: flat noise at `level` while the
+// clip is still going, the next stretch of the tail's ramp once it has ended.
+// Returns how many samples hold sound; the rest are zeroed and left out of the
+// chunk, so a buffer dequeued after the tail carries nothing.
+fn write_noise(
+    out: &mut [f32],
+    level: f32,
+    tail: Option<&mut Ramp>,
+    mut noise: impl FnMut() -> f32,
+) -> usize {
+    let Some(tail) = tail else {
+        for sample in out.iter_mut() {
+            *sample = noise() * level;
+        }
+        return out.len();
+    };
+    let len = out.len().min(tail.left);
+    for sample in &mut out[..len] {
+        *sample = noise() * level * tail.next_gain();
+    }
+    out[len..].fill(0.0);
+    len
+}
+
+/// Slack on top of the queued audio's length and the stream's latency before
+/// a flush is given up on and the playback ends anyway.
+const DRAIN_GRACE: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Latency assumed for a stream PipeWire has no timing for yet.
+const DRAIN_UNKNOWN_LATENCY: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// How often a draining playback checks whether its flush is overdue.
+const DRAIN_TICK: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// The wait between queueing a clip's last buffer and PipeWire reporting the
+/// stream drained; quitting any sooner cuts off what is still in the graph.
+/// Holds the deadline past which the `drained` event is no longer waited for.
+#[derive(Clone, Default)]
+struct Drain(std::rc::Rc<std::cell::Cell<Option<std::time::Instant>>>);
+
+impl Drain {
+    /// Start waiting until `deadline`; false if already waiting.
+    fn begin(&self, deadline: std::time::Instant) -> bool {
+        if self.started() {
+            return false;
+        }
+        self.0.set(Some(deadline));
+        true
+    }
+
+    fn started(&self) -> bool {
+        self.0.get().is_some()
+    }
+
+    fn overdue(&self, now: std::time::Instant) -> bool {
+        self.0.get().is_some_and(|deadline| now >= deadline)
+    }
+}
+
+// Once the last buffer is queued: flush the stream so `drained` quits the
+// mainloop after the tail has played, or quit now if the flush can't start.
+// `queued` is how long that last buffer lasts.
+fn start_drain(
+    stream: &pipewire::stream::StreamRef,
+    drain: &Drain,
+    queued: std::time::Duration,
+    sample_rate: u32,
+    mainloop: &pipewire::main_loop::WeakMainLoop,
+) {
+    let latency = stream_latency(stream, sample_rate);
+    if !drain.begin(drain_deadline(std::time::Instant::now(), queued, latency)) {
+        return;
+    }
+    if let Err(e) = stream.flush(true) {
+        LOG.error(&format!(
+            "Playback flush failed, ending without draining: {e}"
+        ));
+        if let Some(ml) = mainloop.upgrade() {
+            ml.quit();
+        }
+    }
+}
+
+/// When to stop waiting for a drain that started at `now`: once the last
+/// buffer has had time to get through the graph and play, plus some slack.
+fn drain_deadline(
+    now: std::time::Instant,
+    queued: std::time::Duration,
+    latency: Option<std::time::Duration>,
+) -> std::time::Instant {
+    now + queued + latency.unwrap_or(DRAIN_UNKNOWN_LATENCY) + DRAIN_GRACE
+}
+
+// How long audio already handed to `stream` takes to be heard, per PipeWire's
+// timing for it; `None` before the stream has any.
+fn stream_latency(
+    stream: &pipewire::stream::StreamRef,
+    sample_rate: u32,
+) -> Option<std::time::Duration> {
+    use pipewire::sys::{pw_stream_get_time_n, pw_time};
+    // SAFETY: pw_time is plain data, and the call fills in at most `size`
+    // bytes of it; it is safe to make from the process callback
+    let mut time: pw_time = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<pw_time>();
+    if unsafe { pw_stream_get_time_n(stream.as_raw_ptr(), &mut time, size) } < 0 {
+        return None;
+    }
+    playout_latency(
+        (time.delay, time.rate.num, time.rate.denom),
+        time.buffered,
+        sample_rate,
+    )
+}
+
+/// The graph's delay to the device, `delay` ticks of `num/denom` seconds,
+/// plus `buffered` frames still waiting in the stream itself.
+fn playout_latency(
+    (delay, num, denom): (i64, u32, u32),
+    buffered: u64,
+    sample_rate: u32,
+) -> Option<std::time::Duration> {
+    if denom == 0 || sample_rate == 0 {
+        return None;
+    }
+    let delay = delay.max(0) as f64 * f64::from(num) / f64::from(denom);
+    let buffered = buffered as f64 / f64::from(sample_rate);
+    Some(std::time::Duration::from_secs_f64(delay + buffered))
+}
+
+/// A stopped playback whose stream hasn't started draining `limit` after the
+/// stop: its process callback no longer runs (the node went away, or was
+/// never linked), so nothing else is going to end the mainloop.
+fn stop_stalled(
+    drain: &Drain,
+    stopped_at: Option<std::time::Instant>,
+    now: std::time::Instant,
+    limit: std::time::Duration,
+) -> bool {
+    !drain.started() && stopped_at.is_some_and(|at| now >= at + limit)
+}
+
+// Ends the mainloop once a drain is overdue, for a stream whose `drained`
+// event never comes (its node went away mid-flush), or once a stop has gone
+// `stop_limit` without the stream getting to drain at all.
+fn drain_watchdog<'l>(
+    mainloop: &'l MainLoop,
+    drain: &Drain,
+    control: PlaybackControl,
+    stop_limit: std::time::Duration,
+) -> pipewire::loop_::TimerSource<'l> {
+    let timer = mainloop.loop_().add_timer({
+        let drain = drain.clone();
+        let mainloop = mainloop.downgrade();
+        let stopped_at = std::cell::Cell::new(None);
+        move |_| {
+            let now = std::time::Instant::now();
+            if stopped_at.get().is_none() && control.stopped() {
+                stopped_at.set(Some(now));
+            }
+            if drain.overdue(now) || stop_stalled(&drain, stopped_at.get(), now, stop_limit) {
+                if let Some(ml) = mainloop.upgrade() {
+                    ml.quit();
+                }
+            }
+        }
+    });
+    timer.update_timer(Some(DRAIN_TICK), Some(DRAIN_TICK));
+    timer
+}
+
+// Layer configured properties over a stream's built-in ones, logging them so
+// routing problems can be traced back to the config.
+fn add_stream_properties(
+    props: &mut pipewire::properties::Properties,
+    extra: &[(String, String)],
+    stream: &str,
+) {
+    if extra.is_empty() {
+        return;
+    }
+    for (key, value) in extra {
+        props.insert(key.as_str(), value.as_str());
+    }
+    let list: Vec<String> = extra
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    LOG.info(&format!(
+        "{stream}: extra stream properties {}",
+        list.join(", ")
+    ));
 }
 
 fn play_audio_threaded(
     sink_id: u32,
-    samples: Vec<f32>,
-    sample_rate: u32,
-    channels: u32,
-    volume: f32,
-    comfort_noise: f32,
-    eq_mid_boost: f32,
+    reader: ClipReader,
+    options: PlaybackOptions,
+    control: PlaybackControl,
+    extra_properties: &[(String, String)],
+    evt_tx: Sender<PwEvent>,
 ) -> Result<()> {
+    let (sample_rate, channels) = (reader.audio.sample_rate, reader.audio.channels);
     let mainloop = MainLoop::new(None)?;
     let context = Context::new(&mainloop)?;
     let core = context.connect(None)?;
 
-    let props = properties! {
+    let mut props = properties! {
         "media.type"     => "Audio",
         "media.category" => "Playback",
         "media.role"     => "Music",
     };
+    add_stream_properties(&mut props, extra_properties, "plentysound-playback");
 
     let stream = Stream::new(&core, "plentysound-playback", props)?;
 
@@ -359,106 +1595,186 @@ fn play_audio_threaded(
     let pod_bytes = pod_bytes.into_inner();
     let param = Pod::from_bytes(&pod_bytes).unwrap();
 
+    // No target lets the session manager route it to the current default
+    let target = (sink_id != DEFAULT_SINK_ID).then_some(sink_id);
     stream.connect(
         pipewire::spa::utils::Direction::Output,
-        Some(sink_id),
+        target,
         StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS,
         &mut [param],
     )?;
 
-    let total_samples = samples.len();
-    let samples = std::sync::Arc::new(samples);
-    let samples_clone = samples.clone();
-    let offset = std::sync::Arc::new(std::sync::Mutex::new(0usize));
-    let offset_clone = offset.clone();
+    let mut start_report = control
+        .report_start
+        .map(|id| (id, std::time::Instant::now()));
+    let reader = std::rc::Rc::new(std::cell::RefCell::new(reader));
+    let reader_clone = reader.clone();
     let mainloop_weak = mainloop.downgrade();
 
-    let apply_eq = eq_mid_boost != 1.0 && eq_mid_boost > 0.0;
-    let biquad = compute_biquad(sample_rate as f32, if apply_eq { eq_mid_boost } else { 1.0 });
-    let rng_state = std::sync::atomic::AtomicU64::new(0xDEADBEEFCAFE);
-    // Biquad state: [x1, x2, y1, y2] per channel (max 8 channels)
-    let mut eq_state = [[0.0f32; 4]; 8];
+    let PlaybackOptions {
+        volume,
+        noise_kind,
+        noise_tail_ms,
+        limiter,
+        sidechain,
+        native_volume,
+        ..
+    } = options;
+    let mut node_volume = NodeVolume::new(native_volume, channels);
+    let mut equalizer = Equalizer::new(sample_rate, control.params.load().eq);
+    let mut noise_gen = Noise::new(noise_kind, 0xDEADBEEFCAFE);
+    let mut meter = LevelMeter::new();
+    let mut progress =
+        ProgressReporter::new(reader.borrow().audio.total_samples, sample_rate, channels);
+    let mut tail = Ramp::new(sample_rate, channels, noise_tail_ms);
+    let mut stop_ramp = Ramp::new(sample_rate, channels, STOP_FADE_MS);
+    let mut block: Vec<f32> = Vec::new();
+    let planar_flag = std::rc::Rc::new(std::cell::Cell::new(false));
+    let planar_watch = planar_flag.clone();
+    let drain = Drain::default();
+    let drain_watch = drain.clone();
+    let drained_weak = mainloop.downgrade();
+    // The stop fade and noise tail, then the drain's own slack
+    let stop_limit =
+        std::time::Duration::from_millis(u64::from(STOP_FADE_MS + noise_tail_ms)) + DRAIN_GRACE;
+    let stop_watch = control.clone();
 
     let _listener = stream
         .add_local_listener()
+        .param_changed(move |_, _: &mut (), id, param| {
+            if id == ParamType::Format.as_raw() {
+                if let Some(param) = param {
+                    planar_watch.set(format_is_planar(param));
+                }
+            }
+        })
+        .drained(move |_, _: &mut ()| {
+            if let Some(ml) = drained_weak.upgrade() {
+                ml.quit();
+            }
+        })
         .process(move |stream, _: &mut ()| {
+            // Everything is queued; nothing more goes out while it plays
+            if drain_watch.started() {
+                return;
+            }
             if let Some(mut buffer) = stream.dequeue_buffer() {
                 let datas = buffer.datas_mut();
                 if datas.is_empty() {
                     return;
                 }
 
-                let data = &mut datas[0];
-                if let Some(slice) = data.data() {
-                    let out_samples = slice.len() / std::mem::size_of::<f32>();
-                    let mut pos = offset_clone.lock().unwrap();
-
-                    let remaining = samples_clone.len() - *pos;
-                    let to_write = out_samples.min(remaining);
-
-                    let out_f32: &mut [f32] = unsafe {
-                        std::slice::from_raw_parts_mut(
-                            slice.as_mut_ptr() as *mut f32,
-                            out_samples,
-                        )
+                let planar = planar_flag.get() || datas.len() > 1;
+                if let Some(capacity) = datas[0]
+                    .data()
+                    .map(|slice| slice.len() / std::mem::size_of::<f32>())
+                {
+                    let frame_len = channels.max(1) as usize;
+                    let out_samples = block_len(capacity, frame_len, planar);
+                    let mut reader = reader_clone.borrow_mut();
+
+                    // A stop fades the clip out over `STOP_FADE_MS` rather than
+                    // cutting it, even while paused
+                    let stopping = control.stopped();
+                    // While paused only noise goes out and the clip stays put
+                    let mut wanted = if control.paused() && !stopping {
+                        0
+                    } else {
+                        out_samples
                     };
-                    for i in 0..to_write {
-                        let mut sample = samples_clone[*pos + i] * volume;
-
-                        // Apply biquad EQ
-                        if apply_eq {
-                            let ch = i % channels as usize;
-                            if ch < 8 {
-                                let st = &mut eq_state[ch];
-                                let y = biquad[0] * sample + biquad[1] * st[0] + biquad[2] * st[1]
-                                    - biquad[3] * st[2] - biquad[4] * st[3];
-                                st[1] = st[0];
-                                st[0] = sample;
-                                st[3] = st[2];
-                                st[2] = y;
-                                sample = y;
-                            }
-                        }
-
-                        // Add comfort noise
-                        out_f32[i] = sample + next_noise(&rng_state) * comfort_noise;
+                    if stopping {
+                        wanted = wanted.min(stop_ramp.left);
                     }
 
-                    for i in to_write..out_samples {
-                        out_f32[i] = next_noise(&rng_state) * comfort_noise;
+                    block.resize(out_samples, 0.0);
+                    let out_f32 = &mut block[..];
+                    // Short of `wanted` while the decoder catches up; the gap
+                    // gets comfort noise like a pause
+                    let to_write = reader.read(&mut out_f32[..wanted]);
+                    // Read on every buffer so changes reach the clip that is
+                    // playing; the EQ is only retuned when its gains move
+                    let live = control.params.load();
+                    equalizer.set_gains(live.eq);
+                    let (mut gain, node) = node_volume.apply(stream, volume * live.volume);
+                    if sidechain {
+                        gain *= crate::sidechain::playback_gain();
+                    }
+                    // Comfort noise keeps its level whatever the node does to it
+                    let noise_level = live.comfort_noise / node;
+                    mix_frames(
+                        &mut out_f32[..to_write],
+                        frame_len,
+                        gain,
+                        stopping.then_some(&mut stop_ramp),
+                        &mut equalizer,
+                        limiter,
+                        &mut meter,
+                        || noise_gen.next() * noise_level,
+                    );
+
+                    if to_write > 0 {
+                        if let Some((id, connected)) = start_report.take() {
+                            let first_audio = std::time::Instant::now();
+                            let _ = evt_tx.send(PwEvent::PlaybackStarted {
+                                id,
+                                connected,
+                                first_audio,
+                            });
+                        }
                     }
 
-                    let chunk = data.chunk_mut();
-                    *chunk.offset_mut() = 0;
-                    *chunk.stride_mut() = std::mem::size_of::<f32>() as i32 * channels as i32;
-                    *chunk.size_mut() = (to_write * std::mem::size_of::<f32>()) as u32;
+                    // Past the last real sample the noise fades out instead of
+                    // running at full level until the mainloop gets to quit
+                    let ending = stopping || reader.finished();
+                    let noise = write_noise(
+                        &mut out_f32[to_write..],
+                        noise_level,
+                        ending.then_some(&mut tail),
+                        || noise_gen.next(),
+                    );
+
+                    write_block(datas, &block, to_write + noise, channels, planar);
+                    #[cfg(feature = "live-stream")]
+                    if control.live {
+                        let played = &block[..to_write + noise];
+                        crate::livestream::feed(played, node, sample_rate, channels);
+                    }
 
-                    *pos += to_write;
+                    report_level(&mut meter, &evt_tx);
+                    progress.report(reader.position, reader.finished(), &evt_tx);
 
-                    if *pos >= total_samples {
-                        if let Some(ml) = mainloop_weak.upgrade() {
-                            ml.quit();
-                        }
+                    let clip_done = reader.finished() || (stopping && stop_ramp.done());
+                    if ending && clip_done && tail.done() {
+                        let frames = (out_samples / frame_len) as f64;
+                        let queued =
+                            std::time::Duration::from_secs_f64(frames / sample_rate as f64);
+                        start_drain(stream, &drain_watch, queued, sample_rate, &mainloop_weak);
                     }
                 }
             }
         })
         .register()?;
+    let _watchdog = drain_watchdog(&mainloop, &drain, stop_watch, stop_limit);
 
     mainloop.run();
 
-    Ok(())
+    // A decode error ends the clip early; it still counts as failed
+    let error = reader.borrow_mut().error.take();
+    match error {
+        Some(e) => Err(e.context("Decoding failed mid-stream")),
+        None => Ok(()),
+    }
 }
 
 fn play_to_input_stream(
     target_id: u32,
-    samples: Vec<f32>,
-    sample_rate: u32,
-    channels: u32,
-    volume: f32,
-    comfort_noise: f32,
-    eq_mid_boost: f32,
+    reader: ClipReader,
+    options: PlaybackOptions,
+    control: PlaybackControl,
+    extra_properties: &[(String, String)],
+    evt_tx: Sender<PwEvent>,
 ) -> Result<()> {
+    let (sample_rate, channels) = (reader.audio.sample_rate, reader.audio.channels);
     // Same approach as play_audio_threaded, but using node.target property
     // to tell WirePlumber to route our playback into the target capture stream
     let mainloop = MainLoop::new(None)?;
@@ -466,13 +1782,14 @@ fn play_to_input_stream(
     let core = context.connect(None)?;
 
     let target_str = target_id.to_string();
-    let props = properties! {
+    let mut props = properties! {
         "media.type"     => "Audio",
         "media.category" => "Playback",
         "media.role"     => "Music",
         "node.name"      => "plentysound-inject",
         "node.target"    => target_str.as_str(),
     };
+    add_stream_properties(&mut props, extra_properties, "plentysound-inject");
 
     let stream = Stream::new(&core, "plentysound-inject", props)?;
 
@@ -499,83 +1816,643 @@ fn play_to_input_stream(
         &mut [param],
     )?;
 
-    let total_samples = samples.len();
-    let samples = std::sync::Arc::new(samples);
-    let samples_clone = samples.clone();
-    let offset = std::sync::Arc::new(std::sync::Mutex::new(0usize));
-    let offset_clone = offset.clone();
+    let mut start_report = control
+        .report_start
+        .map(|id| (id, std::time::Instant::now()));
+    let reader = std::rc::Rc::new(std::cell::RefCell::new(reader));
+    let reader_clone = reader.clone();
     let mainloop_weak = mainloop.downgrade();
 
-    let apply_eq = eq_mid_boost != 1.0 && eq_mid_boost > 0.0;
-    let biquad = compute_biquad(sample_rate as f32, if apply_eq { eq_mid_boost } else { 1.0 });
-    let rng_state = std::sync::atomic::AtomicU64::new(0xCAFEBABE1234);
-    let mut eq_state = [[0.0f32; 4]; 8];
+    let PlaybackOptions {
+        volume,
+        noise_kind,
+        noise_tail_ms,
+        limiter,
+        sidechain,
+        native_volume,
+        ..
+    } = options;
+    let mut node_volume = NodeVolume::new(native_volume, channels);
+    let mut equalizer = Equalizer::new(sample_rate, control.params.load().eq);
+    let mut noise_gen = Noise::new(noise_kind, 0xCAFEBABE1234);
+    let mut meter = LevelMeter::new();
+    let mut progress =
+        ProgressReporter::new(reader.borrow().audio.total_samples, sample_rate, channels);
+    let mut tail = Ramp::new(sample_rate, channels, noise_tail_ms);
+    let mut stop_ramp = Ramp::new(sample_rate, channels, STOP_FADE_MS);
+    let mut block: Vec<f32> = Vec::new();
+    let planar_flag = std::rc::Rc::new(std::cell::Cell::new(false));
+    let planar_watch = planar_flag.clone();
+    let drain = Drain::default();
+    let drain_watch = drain.clone();
+    let drained_weak = mainloop.downgrade();
+    // The stop fade and noise tail, then the drain's own slack
+    let stop_limit =
+        std::time::Duration::from_millis(u64::from(STOP_FADE_MS + noise_tail_ms)) + DRAIN_GRACE;
+    let stop_watch = control.clone();
 
     let _listener = stream
         .add_local_listener()
+        .param_changed(move |_, _: &mut (), id, param| {
+            if id == ParamType::Format.as_raw() {
+                if let Some(param) = param {
+                    planar_watch.set(format_is_planar(param));
+                }
+            }
+        })
+        .drained(move |_, _: &mut ()| {
+            if let Some(ml) = drained_weak.upgrade() {
+                ml.quit();
+            }
+        })
         .process(move |stream, _: &mut ()| {
+            // Everything is queued; nothing more goes out while it plays
+            if drain_watch.started() {
+                return;
+            }
             if let Some(mut buffer) = stream.dequeue_buffer() {
                 let datas = buffer.datas_mut();
                 if datas.is_empty() {
                     return;
                 }
 
-                let data = &mut datas[0];
-                if let Some(slice) = data.data() {
-                    let out_samples = slice.len() / std::mem::size_of::<f32>();
-                    let mut pos = offset_clone.lock().unwrap();
-
-                    let remaining = samples_clone.len() - *pos;
-                    let to_write = out_samples.min(remaining);
-
-                    let out_f32: &mut [f32] = unsafe {
-                        std::slice::from_raw_parts_mut(
-                            slice.as_mut_ptr() as *mut f32,
-                            out_samples,
-                        )
+                let planar = planar_flag.get() || datas.len() > 1;
+                if let Some(capacity) = datas[0]
+                    .data()
+                    .map(|slice| slice.len() / std::mem::size_of::<f32>())
+                {
+                    let frame_len = channels.max(1) as usize;
+                    let out_samples = block_len(capacity, frame_len, planar);
+                    let mut reader = reader_clone.borrow_mut();
+
+                    // A stop fades the clip out over `STOP_FADE_MS` rather than
+                    // cutting it, even while paused
+                    let stopping = control.stopped();
+                    // While paused only noise goes out and the clip stays put
+                    let mut wanted = if control.paused() && !stopping {
+                        0
+                    } else {
+                        out_samples
                     };
-                    for i in 0..to_write {
-                        let mut sample = samples_clone[*pos + i] * volume;
-
-                        if apply_eq {
-                            let ch = i % channels as usize;
-                            if ch < 8 {
-                                let st = &mut eq_state[ch];
-                                let y = biquad[0] * sample + biquad[1] * st[0] + biquad[2] * st[1]
-                                    - biquad[3] * st[2] - biquad[4] * st[3];
-                                st[1] = st[0];
-                                st[0] = sample;
-                                st[3] = st[2];
-                                st[2] = y;
-                                sample = y;
-                            }
+                    if stopping {
+                        wanted = wanted.min(stop_ramp.left);
+                    }
+
+                    block.resize(out_samples, 0.0);
+                    let out_f32 = &mut block[..];
+                    // Short of `wanted` while the decoder catches up; the gap
+                    // gets comfort noise like a pause
+                    let to_write = reader.read(&mut out_f32[..wanted]);
+                    // Read on every buffer so changes reach the clip that is
+                    // playing; the EQ is only retuned when its gains move
+                    let live = control.params.load();
+                    equalizer.set_gains(live.eq);
+                    let (mut gain, node) = node_volume.apply(stream, volume * live.volume);
+                    if sidechain {
+                        gain *= crate::sidechain::playback_gain();
+                    }
+                    // Comfort noise keeps its level whatever the node does to it
+                    let noise_level = live.comfort_noise / node;
+                    mix_frames(
+                        &mut out_f32[..to_write],
+                        frame_len,
+                        gain,
+                        stopping.then_some(&mut stop_ramp),
+                        &mut equalizer,
+                        limiter,
+                        &mut meter,
+                        || noise_gen.next() * noise_level,
+                    );
+
+                    if to_write > 0 {
+                        if let Some((id, connected)) = start_report.take() {
+                            let first_audio = std::time::Instant::now();
+                            let _ = evt_tx.send(PwEvent::PlaybackStarted {
+                                id,
+                                connected,
+                                first_audio,
+                            });
                         }
+                    }
 
-                        out_f32[i] = sample + next_noise(&rng_state) * comfort_noise;
+                    // Past the last real sample the noise fades out instead of
+                    // running at full level until the mainloop gets to quit
+                    let ending = stopping || reader.finished();
+                    let noise = write_noise(
+                        &mut out_f32[to_write..],
+                        noise_level,
+                        ending.then_some(&mut tail),
+                        || noise_gen.next(),
+                    );
+
+                    write_block(datas, &block, to_write + noise, channels, planar);
+                    #[cfg(feature = "live-stream")]
+                    if control.live {
+                        let played = &block[..to_write + noise];
+                        crate::livestream::feed(played, node, sample_rate, channels);
                     }
 
-                    for i in to_write..out_samples {
-                        out_f32[i] = next_noise(&rng_state) * comfort_noise;
+                    report_level(&mut meter, &evt_tx);
+                    progress.report(reader.position, reader.finished(), &evt_tx);
+
+                    let clip_done = reader.finished() || (stopping && stop_ramp.done());
+                    if ending && clip_done && tail.done() {
+                        let frames = (out_samples / frame_len) as f64;
+                        let queued =
+                            std::time::Duration::from_secs_f64(frames / sample_rate as f64);
+                        start_drain(stream, &drain_watch, queued, sample_rate, &mainloop_weak);
                     }
+                }
+            }
+        })
+        .register()?;
+    let _watchdog = drain_watchdog(&mainloop, &drain, stop_watch, stop_limit);
 
-                    let chunk = data.chunk_mut();
-                    *chunk.offset_mut() = 0;
-                    *chunk.stride_mut() = std::mem::size_of::<f32>() as i32 * channels as i32;
-                    *chunk.size_mut() = (to_write * std::mem::size_of::<f32>()) as u32;
+    mainloop.run();
 
-                    *pos += to_write;
+    let dropped = reader.borrow().tee_dropped;
+    if dropped > 0 {
+        LOG.warn(&format!(
+            "Monitor copy fell behind and skipped {dropped} chunks"
+        ));
+    }
+    // A decode error ends the clip early; it still counts as failed
+    let error = reader.borrow_mut().error.take();
+    match error {
+        Some(e) => Err(e.context("Decoding failed mid-stream")),
+        None => Ok(()),
+    }
+}
 
-                    if *pos >= total_samples {
-                        if let Some(ml) = mainloop_weak.upgrade() {
-                            ml.quit();
-                        }
-                    }
+// Noise-only stream into a capture target, running until `stop_rx` fires or is
+// dropped. The level is re-read every buffer so FX changes apply live.
+fn play_noise_stream(
+    target_id: u32,
+    level: std::sync::Arc<std::sync::atomic::AtomicU32>,
+    kind: std::sync::Arc<std::sync::atomic::AtomicU8>,
+    stop_rx: Receiver<()>,
+) -> Result<()> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    const NOISE_RATE: u32 = 48_000;
+    const NOISE_CHANNELS: u32 = 2;
+
+    let mainloop = MainLoop::new(None)?;
+    let context = Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+
+    let target_str = target_id.to_string();
+    let props = properties! {
+        "media.type"     => "Audio",
+        "media.category" => "Playback",
+        "media.role"     => "Music",
+        "node.name"      => "plentysound-noise",
+        "node.target"    => target_str.as_str(),
+    };
+
+    let stream = Stream::new(&core, "plentysound-noise", props)?;
+
+    let mut audio_info = AudioInfoRaw::new();
+    audio_info.set_format(AudioFormat::F32LE);
+    audio_info.set_rate(NOISE_RATE);
+    audio_info.set_channels(NOISE_CHANNELS);
+
+    let obj = Object {
+        type_: SpaTypes::ObjectParamFormat.as_raw(),
+        id: ParamType::EnumFormat.as_raw(),
+        properties: audio_info.into(),
+    };
+    let pod_value = Value::Object(obj);
+    let (pod_bytes, _) = PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &pod_value)
+        .map_err(|e| anyhow::anyhow!("pod serialize error: {:?}", e))?;
+    let pod_bytes = pod_bytes.into_inner();
+    let param = Pod::from_bytes(&pod_bytes).unwrap();
+
+    stream.connect(
+        pipewire::spa::utils::Direction::Output,
+        Some(target_id),
+        StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS,
+        &mut [param],
+    )?;
+
+    let mut noise_gen = Noise::new(NoiseKind::White, 0x5EED0F7015E);
+
+    let _listener = stream
+        .add_local_listener()
+        .process(move |stream, _: &mut ()| {
+            if let Some(mut buffer) = stream.dequeue_buffer() {
+                let datas = buffer.datas_mut();
+                if datas.is_empty() {
+                    return;
+                }
+
+                // Noise has no frame order to keep, so planar buffers just get
+                // every plane filled independently.
+                let stride_channels = if datas.len() > 1 { 1 } else { NOISE_CHANNELS };
+                let amount = f32::from_bits(level.load(Ordering::Relaxed));
+                noise_gen.kind = NoiseKind::ALL[kind.load(Ordering::Relaxed) as usize];
+                for data in datas.iter_mut() {
+                    let Some(slice) = data.data() else { continue };
+                    let noise = std::iter::repeat_with(|| noise_gen.next() * amount);
+                    let written = fill_f32(slice, noise);
+                    let bytes = written * std::mem::size_of::<f32>();
+
+                    let chunk = data.chunk_mut();
+                    *chunk.offset_mut() = 0;
+                    *chunk.stride_mut() =
+                        std::mem::size_of::<f32>() as i32 * stride_channels as i32;
+                    *chunk.size_mut() = bytes as u32;
                 }
             }
         })
         .register()?;
 
+    let stop_flag = std::sync::Arc::new(AtomicBool::new(false));
+    let stop_flag_watcher = stop_flag.clone();
+    std::thread::spawn(move || {
+        let _ = stop_rx.recv();
+        stop_flag_watcher.store(true, Ordering::SeqCst);
+    });
+
+    let timer = mainloop.loop_().add_timer({
+        let mainloop_weak = mainloop.downgrade();
+        move |_| {
+            if stop_flag.load(Ordering::SeqCst) {
+                if let Some(ml) = mainloop_weak.upgrade() {
+                    ml.quit();
+                }
+            }
+        }
+    });
+    timer.update_timer(
+        Some(std::time::Duration::from_millis(100)),
+        Some(std::time::Duration::from_millis(100)),
+    );
+
     mainloop.run();
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_registry_follows_globals_and_names_capture_apps() {
+        let props = |pairs: &'static [(&'static str, &'static str)]| {
+            move |key: &str| pairs.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+        };
+        let speakers = props(&[
+            ("media.class", "Audio/Sink"),
+            ("node.name", "alsa_out"),
+            ("node.description", "Speakers"),
+        ]);
+        let speakers = RawSink::from_props(40, speakers).unwrap();
+        let discord = props(&[
+            ("media.class", "Stream/Input/Audio"),
+            ("node.name", "WEBRTC VoiceEngine"),
+            ("client.id", "70"),
+        ]);
+        let discord = RawSink::from_props(71, discord).unwrap();
+        let mic = props(&[
+            ("media.class", "Audio/Source"),
+            ("node.name", "alsa_in"),
+            ("node.description", "Built-in Microphone"),
+        ]);
+        let mic = RawSink::from_props(50, mic).unwrap();
+        assert_eq!(mic.kind, DeviceKind::Source);
+        assert_eq!(mic.description, "Built-in Microphone");
+        // Without a description a source goes by its node name
+        let virtual_mic = props(&[
+            ("media.class", "Audio/Source/Virtual"),
+            ("node.name", "mic"),
+        ]);
+        let virtual_mic = RawSink::from_props(51, virtual_mic).unwrap();
+        assert_eq!(virtual_mic.kind, DeviceKind::Source);
+        assert_eq!(virtual_mic.description, "mic");
+        assert!(RawSink::from_props(5, props(&[("media.class", "Video/Source")])).is_none());
+
+        let mut devices = DeviceRegistry::default();
+        devices.add_node(40, speakers.clone());
+        devices.add_node(71, discord);
+        devices.add_binary(70, "Discord".to_string());
+        let names: Vec<_> = devices.sinks().into_iter().map(|s| s.description).collect();
+        assert_eq!(
+            names,
+            ["System Default", "Speakers", "WEBRTC VoiceEngine (Discord)"]
+        );
+        assert_eq!(devices.sinks()[0].id, DEFAULT_SINK_ID);
+
+        // Nothing goes out before the initial roundtrip, then once per change
+        let (evt_tx, evt_rx) = std::sync::mpsc::channel();
+        let later = std::time::Instant::now() + DEVICE_MAX_DELAY;
+        assert!(!devices.update_due(later));
+        devices.synced = true;
+        assert!(devices.update_due(later));
+        devices.send(&evt_tx, false);
+        assert!(!devices.update_due(later));
+
+        // Re-announcing a known node is not a change; removing nodes is
+        devices.add_node(40, speakers);
+        assert!(devices.pending.is_none());
+        devices.remove(71);
+        devices.remove(70);
+        devices.remove(40);
+        let now = std::time::Instant::now();
+        assert!(!devices.update_due(now));
+        assert!(devices.update_due(now + DEVICE_DEBOUNCE));
+        devices.send(&evt_tx, false);
+        // An unchanged list is only resent when forced
+        devices.send(&evt_tx, false);
+        devices.send(&evt_tx, true);
+        let sent: Vec<usize> = evt_rx
+            .try_iter()
+            .map(|e| match e {
+                PwEvent::SinksUpdated(sinks) => sinks.len(),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        // Without an output there is no default to follow
+        assert_eq!(sent, [3, 0, 0]);
+
+        // The default output is reported once per change
+        devices.set_default_sink(default_sink_name(r#"{"name":"alsa_out"}"#), &evt_tx);
+        devices.set_default_sink(Some("alsa_out".to_string()), &evt_tx);
+        devices.set_default_sink(default_sink_name("not json"), &evt_tx);
+        let defaults: Vec<_> = evt_rx
+            .try_iter()
+            .map(|e| match e {
+                PwEvent::DefaultSinkChanged(name) => name,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(defaults, [Some("alsa_out".to_string()), None]);
+    }
+
+    #[test]
+    fn noise_tail_is_bounded_and_fades_to_silence() {
+        let mut tail = Ramp::new(48000, 2, 50);
+        assert_eq!(tail.len, 4800);
+        // Constant "noise" so the output is the envelope itself
+        let mut written = Vec::new();
+        let mut block = [0.0f32; 1024];
+        loop {
+            let n = write_noise(&mut block, 0.5, Some(&mut tail), || 1.0);
+            written.extend_from_slice(&block[..n]);
+            assert!(block[n..].iter().all(|&s| s == 0.0));
+            if n < block.len() {
+                break;
+            }
+        }
+        assert_eq!(written.len(), 4800);
+        assert!(tail.done());
+        assert!(written[0] <= 0.5);
+        assert!(written.windows(2).all(|w| w[1] < w[0]));
+        assert!(written[written.len() - 1] < 0.5 / 4000.0);
+
+        // Once the tail is spent, further buffers are empty
+        assert_eq!(write_noise(&mut block, 0.5, Some(&mut tail), || 1.0), 0);
+        assert!(block.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn noise_before_the_end_stays_at_full_level() {
+        let mut block = [0.0f32; 8];
+        assert_eq!(write_noise(&mut block, 0.25, None, || -1.0), 8);
+        assert!(block.iter().all(|&s| s == -0.25));
+        let mut silent = Ramp::new(48000, 2, 0);
+        assert!(silent.done());
+        assert_eq!(write_noise(&mut block, 0.25, Some(&mut silent), || 1.0), 0);
+    }
+
+    #[test]
+    fn a_paused_clip_sends_whole_blocks_of_noise_and_stays_put() {
+        let (tx, audio) = AudioStream::channel(48_000, 2, None, 64);
+        tx.send(Ok(vec![0.5; 32])).unwrap();
+        drop(tx);
+        let mut reader = ClipReader::new(audio, &PlaybackOptions::default());
+
+        // As the process callback does while paused: nothing is wanted from
+        // the clip, and the padding fills the buffer even with no noise
+        for level in [0.01, 0.0] {
+            let mut block = [1.0f32; 16];
+            let to_write = reader.read(&mut block[..0]);
+            let noise = write_noise(&mut block[to_write..], level, None, || 1.0);
+            assert_eq!(to_write + noise, block.len(), "level {level}");
+            assert!(block.iter().all(|&s| s == level), "{block:?}");
+        }
+        assert_eq!(reader.position, 0);
+        assert!(!reader.finished());
+    }
+
+    #[test]
+    fn clip_reader_holds_back_the_fade_out_until_the_stream_ends() {
+        // 1 kHz mono, so one millisecond is one sample
+        let (tx, audio) = AudioStream::channel(1000, 1, None, 8);
+        let options = PlaybackOptions {
+            fade_out_ms: 4,
+            ..Default::default()
+        };
+        let mut reader = ClipReader::new(audio, &options);
+        tx.send(Ok(vec![1.0; 6])).unwrap();
+
+        let mut out = [0.0f32; 10];
+        assert_eq!(reader.read(&mut out), 2);
+        // An underrun hands out nothing but isn't the end of the clip
+        assert_eq!(reader.read(&mut out), 0);
+        assert!(!reader.finished());
+
+        drop(tx);
+        assert_eq!(reader.read(&mut out), 4);
+        assert_eq!(out[..4], [1.0, 0.75, 0.5, 0.25]);
+        assert!(reader.finished() && reader.error.is_none());
+        assert_eq!(reader.position, 6);
+    }
+
+    #[test]
+    fn drain_waits_for_its_deadline_and_only_starts_once() {
+        let drain = Drain::default();
+        let now = std::time::Instant::now();
+        assert!(!drain.started());
+        assert!(!drain.overdue(now));
+
+        let deadline = now + std::time::Duration::from_millis(60);
+        assert!(drain.begin(deadline));
+        assert!(!drain.begin(deadline + std::time::Duration::from_secs(1)));
+        assert!(drain.clone().started());
+        assert!(!drain.overdue(now));
+        assert!(drain.overdue(deadline));
+    }
+
+    #[test]
+    fn a_drain_behind_a_slow_device_gets_its_latency_to_finish() {
+        let ms = std::time::Duration::from_millis;
+        // A Bluetooth headset: 8192 ticks at 48 kHz plus 1024 frames buffered
+        let latency = playout_latency((8192, 1, 48000), 1024, 48000).unwrap();
+        assert_eq!(latency.as_millis(), 192);
+
+        let now = std::time::Instant::now();
+        let queued = ms(21);
+        let drain = Drain::default();
+        drain.begin(drain_deadline(now, queued, Some(latency)));
+        // Its `drained` comes well after the last buffer and the slack alone
+        let drained = now + queued + latency + ms(10);
+        assert!(!drain.overdue(now + queued + DRAIN_GRACE));
+        assert!(!drain.overdue(drained));
+        assert!(drain.overdue(drained + DRAIN_GRACE));
+
+        // Before the stream has timing, a generous latency is assumed
+        assert_eq!(playout_latency((0, 0, 0), 0, 48000), None);
+        let unknown = drain_deadline(now, queued, None);
+        assert_eq!(unknown, now + queued + DRAIN_UNKNOWN_LATENCY + DRAIN_GRACE);
+    }
+
+    #[test]
+    fn each_playback_keeps_the_reason_it_was_stopped_for() {
+        let (first, second) = (StopFlag::default(), StopFlag::default());
+        let mut live = vec![(1, first.clone())];
+        stop_playbacks(&mut live, FinishReason::Replaced);
+        live.push((2, second.clone()));
+        stop_playbacks(&mut live, FinishReason::Stopped);
+        // A stop while the replaced clip fades out doesn't rewrite its reason
+        first.stop(FinishReason::Stopped);
+        assert_eq!(first.reason(), Some(FinishReason::Replaced));
+        assert_eq!(second.reason(), Some(FinishReason::Stopped));
+        assert!(live.is_empty());
+        assert!(!first.in_use());
+    }
+
+    #[test]
+    fn a_stop_that_never_drains_is_given_up_on() {
+        let limit = std::time::Duration::from_millis(200);
+        let stopped = std::time::Instant::now();
+        let drain = Drain::default();
+        assert!(!stop_stalled(&drain, None, stopped + limit, limit));
+        assert!(!stop_stalled(&drain, Some(stopped), stopped, limit));
+        assert!(stop_stalled(&drain, Some(stopped), stopped + limit, limit));
+        // Once draining, the drain's own deadline decides instead
+        drain.begin(stopped + 2 * limit);
+        assert!(!stop_stalled(&drain, Some(stopped), stopped + limit, limit));
+    }
+
+    #[test]
+    fn node_takes_volume_up_to_unity_and_software_the_rest() {
+        for volume in [0.0, 0.01, 0.3, 1.0, 1.7, 4.0] {
+            let (node, software) = split_volume(volume);
+            assert!(
+                (NODE_VOLUME_FLOOR..=1.0).contains(&node),
+                "{volume}: {node} {software}"
+            );
+            assert!(
+                (node * software - volume).abs() < 1e-6,
+                "{volume}: {node} {software}"
+            );
+        }
+        assert_eq!(split_volume(0.3), (0.3, 1.0));
+        assert_eq!(split_volume(1.7), (1.0, 1.7));
+        // Muted, the clip is cut in software and the node stays open for
+        // comfort noise
+        assert_eq!(split_volume(0.0), (NODE_VOLUME_FLOOR, 0.0));
+    }
+
+    #[test]
+    fn odd_buffer_sizes_never_swap_stereo_channels() {
+        let (tx, audio) = AudioStream::channel(48_000, 2, None, 64);
+        let options = PlaybackOptions {
+            fade_out_ms: 0,
+            ..Default::default()
+        };
+        let mut reader = ClipReader::new(audio, &options);
+        // Left above zero, right below, decoded in chunks that split frames
+        let mut at = 0;
+        for len in [5, 3, 7, 1, 40] {
+            let chunk = (at..at + len)
+                .map(|i| if i % 2 == 0 { 0.25 } else { -0.25 })
+                .collect();
+            tx.send(Ok(chunk)).unwrap();
+            at += len;
+        }
+        drop(tx);
+        let mut equalizer = Equalizer::new(
+            48_000,
+            EqGains {
+                low: 2.0,
+                mid: 1.0,
+                high: 1.0,
+            },
+        );
+        let mut meter = LevelMeter::new();
+
+        let mut played = Vec::new();
+        let mut block = Vec::new();
+        for capacity in [7, 3, 9, 1, 5, 11, 13, 15, 17] {
+            let len = block_len(capacity, 2, false);
+            assert_eq!(len % 2, 0, "capacity {capacity}");
+            block.resize(len, 0.0);
+            let n = reader.read(&mut block[..len]);
+            assert_eq!(n % 2, 0, "read of {len} ended mid-frame");
+            mix_frames(
+                &mut block[..n],
+                2,
+                1.0,
+                None,
+                &mut equalizer,
+                false,
+                &mut meter,
+                || 0.0,
+            );
+            played.extend_from_slice(&block[..n]);
+        }
+        assert!(reader.finished());
+        assert_eq!(played.len(), 56);
+        for (i, frame) in played.chunks_exact(2).enumerate() {
+            assert!(
+                frame[0] > 0.0 && frame[1] < 0.0,
+                "frame {i} swapped: {frame:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn clip_reader_plays_what_decoded_before_an_error() {
+        let (tx, audio) = AudioStream::channel(1000, 2, Some(8), 8);
+        let options = PlaybackOptions {
+            mono_downmix: true,
+            ..Default::default()
+        };
+        let mut reader = ClipReader::new(audio, &options);
+        let (tee_tx, monitor) = AudioStream::channel(1000, 2, Some(8), 8);
+        reader.tee = Some(tee_tx);
+        tx.send(Ok(vec![1.0, 0.0, 0.5, 0.5])).unwrap();
+        tx.send(Err(anyhow::anyhow!("bad packet"))).unwrap();
+
+        let mut out = [0.0f32; 8];
+        assert_eq!(reader.read(&mut out), 4);
+        assert_eq!(out[..4], [0.5; 4]);
+        assert!(reader.finished());
+        assert!(reader.error.is_some());
+
+        // The monitor copy gets the same chunks and the same failure
+        assert!(matches!(monitor.try_next(), StreamChunk::Samples(s) if s == [1.0, 0.0, 0.5, 0.5]));
+        assert!(matches!(monitor.try_next(), StreamChunk::Failed(_)));
+        assert!(matches!(monitor.try_next(), StreamChunk::Ended));
+        assert_eq!(reader.tee_dropped, 0);
+    }
+
+    #[test]
+    fn clip_reader_counts_chunks_a_stalled_monitor_missed() {
+        let (tx, audio) = AudioStream::channel(1000, 1, None, 8);
+        let mut reader = ClipReader::new(audio, &PlaybackOptions::default());
+        let (tee_tx, _monitor) = AudioStream::channel(1000, 1, None, 2);
+        reader.tee = Some(tee_tx);
+        for _ in 0..5 {
+            tx.send(Ok(vec![0.5; 4])).unwrap();
+        }
+
+        // The injected stream gets everything; the monitor only what fit
+        let mut out = [0.0f32; 20];
+        assert_eq!(reader.read(&mut out), 20);
+        assert_eq!(reader.tee_dropped, 3);
+    }
+}
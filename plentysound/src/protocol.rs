@@ -1,31 +1,184 @@
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub use crate::mix::{EqBand, EqGains, NoiseKind};
+
+// Accepted ranges for the numeric settings; every value is clamped to `0.0..=MAX`.
+pub const VOLUME_MAX: f32 = 5.0;
+pub const COMFORT_NOISE_MAX: f32 = 0.05;
+pub const EQ_GAIN_MAX: f32 = 3.0;
+pub const FADE_SECS_MAX: f32 = 1.0;
+pub const SONG_VOLUME_MAX: f32 = 2.0;
+
+/// Largest frame either side will send or accept.
+pub const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// Songs embedded in a `State` snapshot, and the largest page `GetSongs`
+/// returns. Keeps snapshots far below `MAX_MESSAGE_LEN` for big libraries.
+pub const SONG_PAGE_LIMIT: usize = 500;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ClientCommand {
+    /// Optional, right after connecting: how this client shows up in logs
+    /// and stats, e.g. `{ name: "waybar", kind: "status-bar" }`.
+    Identify {
+        name: String,
+        kind: String,
+    },
+    /// `command` with an id the daemon echoes back: the events it produces
+    /// come to this client in one `Reply`. Other clients still get the
+    /// broadcasts, unless the command only asks for something.
+    Request {
+        request_id: u64,
+        command: Box<ClientCommand>,
+    },
     GetState,
+    /// Ask for a `Stats` reply, sent to this client only.
+    GetStats,
+    /// Log the current state and stats as pretty JSON (what SIGUSR1 does).
+    DumpState,
+    /// Re-read the config file from disk (what SIGHUP does).
+    ReloadConfig,
+    /// Log more or less from now on, until the daemon restarts or the
+    /// config's `log_level` changes.
+    SetLogLevel(LogLevel),
     SelectSink(usize),
+    /// Output that also plays clips injected into a capture stream, or
+    /// `None` to stop monitoring.
+    SetMonitorSink(Option<usize>),
     SelectSong(usize),
+    /// Play the selected song on the selected sink.
     Play,
+    /// Play song `index` on the selected sink, leaving the song selection
+    /// where it is.
+    PlaySong(usize),
+    /// Hold the current playback in place; ignored when nothing is playing.
+    Pause,
+    Resume,
+    /// Cut the current playback short; ignored when nothing is playing.
+    /// With `clear_queue` the song queue is emptied too, otherwise it waits
+    /// for the next playback to end.
+    StopPlayback {
+        clear_queue: bool,
+    },
+    /// Cut the current playback short and start the next queued song; just
+    /// stops when the queue is empty.
+    Next,
+    /// Play a song once everything before it has ended, on the sink
+    /// selected at that point; plays right away when idle.
+    Enqueue(usize),
+    ClearQueue,
+    /// Drop the entry at this position of `DaemonState::queue`.
+    RemoveFromQueue(usize),
+    /// Take the queue entry at `from` out and put it back at `to`.
+    ReorderQueue {
+        from: usize,
+        to: usize,
+    },
+    /// Replay the current song each time it ends, until switched off.
+    SetLoop(bool),
     SetVolume(f32),
     SetComfortNoise(f32),
-    SetEqMidBoost(f32),
+    SetNoiseKind(NoiseKind),
+    /// Linear gain of one EQ band; 1.0 leaves it flat.
+    SetEqBand {
+        band: EqBand,
+        gain: f32,
+    },
+    /// Fade-in and fade-out length for each clip, in seconds.
+    SetFade(f32),
+    /// Soft-limit peaks after the EQ so loud settings don't clip.
+    SetLimiter(bool),
+    /// Keep comfort noise playing on the injection target between clips.
+    SetContinuousNoise(bool),
+    /// Duck song playback while the configured mic picks up speech.
+    SetSidechain(bool),
     AddSong(String),
+    /// Add several files with one reply: files already in the library are
+    /// skipped and counted.
+    AddSongs(Vec<String>),
     RemoveSong(usize),
+    /// Take the song at `from` out of the library and put it back at `to`;
+    /// the selection stays on the same song.
+    MoveSong {
+        from: usize,
+        to: usize,
+    },
+    /// Sort the library by `key` and keep songs added later in that order,
+    /// until a `MoveSong`.
+    SortSongs(SortKey),
+    /// Drop every song whose file is gone, and the word bindings left
+    /// without songs by that.
+    PruneMissing,
+    /// Color tag for a song (a theme palette name), or `None` to clear it.
+    SetSongTag {
+        index: usize,
+        tag_color: Option<String>,
+    },
+    /// Gain for one song on top of the global volume, or `None` for 1.0x.
+    SetSongVolume {
+        index: usize,
+        volume: Option<f32>,
+    },
+    /// Fetch songs past the ones embedded in the last snapshot.
+    GetSongs {
+        offset: usize,
+        limit: usize,
+    },
+    /// Ask for a song's peak envelope; a `Waveform` event follows once the
+    /// daemon has decoded it.
+    GetWaveform(usize),
     RefreshSinks,
+    /// The first-run wizard was completed or skipped; don't offer it again.
+    FinishSetup,
     Quit,
     #[cfg(feature = "transcriber")]
     StartModelDownload,
+    /// Abort a running model download and drop what was fetched so far.
+    #[cfg(feature = "transcriber")]
+    CancelModelDownload,
+    /// Bind `word` to one or more songs; `selection` picks among them each
+    /// time the word fires.
     #[cfg(feature = "transcriber")]
     AddWordMapping {
         word: String,
-        song_index: usize,
+        song_indices: Vec<usize>,
+        selection: SongSelection,
+        source_description: String,
+        output_description: String,
+    },
+    /// Bind `word` to speaking `phrase` through the configured TTS command.
+    #[cfg(feature = "transcriber")]
+    AddSpeakMapping {
+        word: String,
+        phrase: String,
         source_description: String,
         output_description: String,
     },
     #[cfg(feature = "transcriber")]
     RemoveWordMapping(usize),
+    /// Switch one binding off (or back on) while keeping its configuration.
+    #[cfg(feature = "transcriber")]
+    SetWordMappingEnabled {
+        index: usize,
+        enabled: bool,
+    },
+    /// Ignore every binding without touching their own enabled flags.
+    #[cfg(feature = "transcriber")]
+    SetWordMappingsMuted(bool),
+    /// Restrict a binding to its own input, or lift the restriction.
+    #[cfg(feature = "transcriber")]
+    SetWordMappingSourceOnly {
+        index: usize,
+        only: bool,
+    },
+    /// Restarts a running detector in the new mode.
+    #[cfg(feature = "transcriber")]
+    SetDetectorPowerMode(DetectorPowerMode),
     #[cfg(feature = "transcriber")]
     StartWordDetector(u32),
     #[cfg(feature = "transcriber")]
@@ -34,20 +187,93 @@ pub enum ClientCommand {
     ModelDownloadComplete,
     #[cfg(feature = "transcriber")]
     ModelDownloadFailed(String),
+    #[cfg(feature = "transcriber")]
+    ResetWordStats,
+    /// Record what the detector feeds the recognizer to a WAV at this path
+    /// (daemon side), for up to a minute.
+    #[cfg(feature = "transcriber")]
+    StartDetectorCapture(String),
+    #[cfg(feature = "transcriber")]
+    StopDetectorCapture,
+    /// Handle `word` as if the detector had just matched it.
+    #[cfg(feature = "transcriber")]
+    SimulateWord(String),
+}
+
+impl ClientCommand {
+    /// Unwrap a `Request`, returning its id alongside the command.
+    pub fn into_request(self) -> (Option<u64>, ClientCommand) {
+        match self {
+            ClientCommand::Request {
+                request_id,
+                command,
+            } => (Some(request_id), *command),
+            command => (None, command),
+        }
+    }
+
+    /// Only asks for something, changing nothing; as a `Request` its answer
+    /// goes to the requester alone.
+    pub fn is_query(&self) -> bool {
+        matches!(
+            self,
+            ClientCommand::GetState | ClientCommand::GetStats | ClientCommand::GetSongs { .. }
+        )
+    }
 }
 
+/// Id of the "System Default" output. Playing on it follows whatever the
+/// desktop's sound settings pick.
+pub const DEFAULT_SINK_ID: u32 = u32::MAX;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SinkInfo {
     pub id: u32,
     pub name: String,
     pub description: String,
-    pub kind: String, // "Output" or "Input"
+    pub kind: String, // "Output", "Input" (app capture stream) or "Source" (microphone)
+    /// The device "System Default" plays on right now.
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+#[cfg(feature = "transcriber")]
+impl SinkInfo {
+    /// Whether the word detector can listen to it.
+    pub fn is_capture(&self) -> bool {
+        self.kind == "Input" || self.kind == "Source"
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SongInfo {
     pub path: String,
     pub name: String,
+    /// No decoder for this file; it stays in the library but can't play.
+    #[serde(default)]
+    pub undecodable: bool,
+    /// The file wasn't there when last looked for. It stays in the library
+    /// and plays again once the file is back.
+    #[serde(default)]
+    pub missing: bool,
+    /// Name of a tag in the palette, e.g. "green".
+    #[serde(default)]
+    pub tag_color: Option<String>,
+    /// Gain on top of the global volume; `None` plays at 1.0x.
+    #[serde(default)]
+    pub volume: Option<f32>,
+    /// Length in seconds from the file's headers; `None` until probed, or
+    /// when they don't say.
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+/// A song tag and how it is drawn: a color name such as "light blue", a
+/// 256-color index, or `#rrggbb`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TagColor {
+    pub name: String,
+    pub color: String,
 }
 
 #[cfg(feature = "transcriber")]
@@ -61,51 +287,715 @@ pub enum WordDetectorStatus {
     Running,
 }
 
+/// How much goes into the daemon log, from errors only to everything.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|level| level.name() == name)
+    }
+}
+
+/// Order the daemon keeps the library in: by name, newest first, or
+/// shortest first with unknown lengths last.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Name,
+    Recent,
+    Duration,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 3] = [SortKey::Name, SortKey::Recent, SortKey::Duration];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Recent => "recent",
+            SortKey::Duration => "duration",
+        }
+    }
+
+    /// The key after this one in `ALL`, wrapping around.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+/// How much CPU the word detector may spend, from always listening closely
+/// (`performance`) to skipping silence and waking up less (`efficiency`).
+#[cfg(feature = "transcriber")]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectorPowerMode {
+    #[default]
+    Performance,
+    Balanced,
+    Efficiency,
+}
+
+#[cfg(feature = "transcriber")]
+impl DetectorPowerMode {
+    pub const ALL: [DetectorPowerMode; 3] = [
+        DetectorPowerMode::Performance,
+        DetectorPowerMode::Balanced,
+        DetectorPowerMode::Efficiency,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DetectorPowerMode::Performance => "performance",
+            DetectorPowerMode::Balanced => "balanced",
+            DetectorPowerMode::Efficiency => "efficiency",
+        }
+    }
+
+    /// The mode after this one in `ALL`, wrapping around.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+/// Per-binding detection counters, persisted with the mapping.
+#[cfg(feature = "transcriber")]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WordStats {
+    pub fired: u64,
+    /// Hits dropped by the detector's repeat cooldown.
+    #[serde(default)]
+    pub suppressed_duplicate: u64,
+    /// Hits dropped because the bound song is no longer in the library.
+    #[serde(default)]
+    pub suppressed_excluded: u64,
+}
+
 #[cfg(feature = "transcriber")]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct WordMapping {
     pub word: String,
-    pub song_name: String,
-    pub song_path: String,
+    /// Names of `song_paths`, in the same order.
+    pub song_names: Vec<String>,
+    pub song_paths: Vec<String>,
+    #[serde(default)]
+    pub selection: SongSelection,
     #[serde(default)]
     pub source_description: String,
     #[serde(default)]
     pub output_description: String,
+    /// Only fire while the detector listens to the input with this
+    /// description. Such a binding wins over an unrestricted one for the
+    /// same word, which covers every other input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only_from_source: Option<String>,
+    #[serde(default)]
+    pub stats: WordStats,
+    /// Disabled bindings stay configured but are left out of the detector
+    /// grammar and never fire.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub action: MappingAction,
+}
+
+/// How a binding with several songs picks the one to play.
+#[cfg(feature = "transcriber")]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SongSelection {
+    /// Any song but the one played last time.
+    #[default]
+    Random,
+    /// Each song in turn.
+    RoundRobin,
+}
+
+#[cfg(feature = "transcriber")]
+impl SongSelection {
+    pub fn name(self) -> &'static str {
+        match self {
+            SongSelection::Random => "random",
+            SongSelection::RoundRobin => "round robin",
+        }
+    }
+
+    pub fn is_random(&self) -> bool {
+        *self == SongSelection::Random
+    }
+}
+
+/// What a binding does when its word is heard.
+#[cfg(feature = "transcriber")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MappingAction {
+    /// Play the bound song.
+    #[default]
+    PlaySong,
+    /// Synthesize this phrase with the configured TTS command and play it.
+    /// Speak bindings have no song; `{time}` in the phrase becomes the
+    /// local time.
+    Speak(String),
+}
+
+#[cfg(feature = "transcriber")]
+impl MappingAction {
+    pub fn is_play_song(&self) -> bool {
+        *self == MappingAction::PlaySong
+    }
 }
 
+#[cfg(feature = "transcriber")]
+fn default_enabled() -> bool {
+    true
+}
+
+/// Everything a client shows. `plentysound status --json` prints it as is,
+/// so scripts read these field names too: keep them stable.
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct DaemonState {
     pub sinks: Vec<SinkInfo>,
+    /// The first `SONG_PAGE_LIMIT` songs; the rest are fetched with `GetSongs`.
     pub songs: Vec<SongInfo>,
+    #[serde(default)]
+    pub songs_total: usize,
+    /// Bumped whenever the library changes, so stale pages can be dropped.
+    #[serde(default)]
+    pub songs_revision: u64,
     pub selected_sink: usize,
+    /// Index of the monitor output, when it is connected.
+    #[serde(default)]
+    pub monitor_sink: Option<usize>,
     pub selected_song: usize,
     pub volume: f32,
     pub comfort_noise: f32,
-    pub eq_mid_boost: f32,
+    #[serde(default)]
+    pub noise_kind: NoiseKind,
+    #[serde(default)]
+    pub eq: EqGains,
+    #[serde(default)]
+    pub fade_secs: f32,
+    #[serde(default)]
+    pub limiter: bool,
     pub now_playing: Option<String>,
+    #[serde(default)]
+    pub paused: bool,
+    /// Paths of the songs waiting to play, next first.
+    #[serde(default)]
+    pub queue: Vec<String>,
+    #[serde(default)]
+    pub loop_enabled: bool,
+    #[serde(default)]
+    pub continuous_noise: bool,
+    #[serde(default)]
+    pub sidechain_enabled: bool,
+    /// No config existed at startup and the library is still empty; clients
+    /// offer the setup wizard.
+    #[serde(default)]
+    pub first_run: bool,
+    /// Deleting a song or word binding in the TUI waits for `y`.
+    #[serde(default)]
+    pub confirm_delete: bool,
+    /// Song tags in the order the TUI cycles through them; empty for the
+    /// built-in palette.
+    #[serde(default)]
+    pub tag_palette: Vec<TagColor>,
+    /// What the library is kept sorted by; `None` for the order songs were
+    /// added or moved into.
+    #[serde(default)]
+    pub song_sort: Option<SortKey>,
     #[cfg(feature = "transcriber")]
     #[serde(default)]
     pub word_detector_status: WordDetectorStatus,
     #[cfg(feature = "transcriber")]
     #[serde(default)]
     pub word_mappings: Vec<WordMapping>,
+    /// Every binding is off regardless of its own `enabled` flag.
+    #[cfg(feature = "transcriber")]
+    #[serde(default)]
+    pub word_mappings_muted: bool,
+    /// A debug capture of the detector input is being written.
+    #[cfg(feature = "transcriber")]
+    #[serde(default)]
+    pub detector_capturing: bool,
+    /// Input the running detector listens to, e.g. "Chromium (node 57)".
+    #[cfg(feature = "transcriber")]
+    #[serde(default)]
+    pub detector_source: Option<String>,
+    #[cfg(feature = "transcriber")]
+    #[serde(default)]
+    pub detector_power_mode: DetectorPowerMode,
+    /// Why libvosk can't be used; the detector stays unavailable while set.
+    #[cfg(feature = "transcriber")]
+    #[serde(default)]
+    pub vosk_missing: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClientInfo {
+    /// The `Identify` name, or "client-N" for clients that never sent one.
+    pub name: String,
+    #[serde(default)]
+    pub kind: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DaemonStats {
+    pub clients: Vec<ClientInfo>,
+    /// One entry per bounded history the daemon keeps in memory.
+    #[serde(default)]
+    pub history: Vec<HistoryStats>,
+}
+
+/// How full one daemon history is and how much it has dropped.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryStats {
+    pub name: String,
+    pub len: usize,
+    pub cap: usize,
+    /// Oldest entries dropped to stay under `cap`; anything but zero means
+    /// history was lost.
+    pub evicted: u64,
+}
+
+/// Why a playback ended, as reported in `DaemonEvent::SongEnded`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FinishReason {
+    /// Played to the end.
+    Completed,
+    /// Cut short by `StopPlayback`.
+    Stopped,
+    /// Cut short (or never started) because another song was played.
+    Replaced,
+    /// The audio device failed; nothing or only part of it was heard.
+    Error,
+}
+
+/// Outcome of a single command, sent back so clients can report it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CommandResult {
+    Ok(String),
+    Warning(String),
+    Error(String),
+}
+
+impl CommandResult {
+    pub fn message(&self) -> &str {
+        match self {
+            CommandResult::Ok(msg) | CommandResult::Warning(msg) | CommandResult::Error(msg) => msg,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, CommandResult::Error(_))
+    }
+}
+
+/// The FX panel's settings; same values as in `DaemonState`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct FxSettings {
+    pub comfort_noise: f32,
+    pub noise_kind: NoiseKind,
+    pub eq: EqGains,
+    pub fade_secs: f32,
+    pub limiter: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum DaemonEvent {
-    State(DaemonState),
+    /// Everything, sent on connect and for `GetState`. Smaller changes come
+    /// as the events below where one covers them.
+    State(Box<DaemonState>),
     SinksUpdated(Vec<SinkInfo>),
+    VolumeChanged(f32),
+    FxChanged(FxSettings),
+    /// Songs were edited in place, none added or removed: the first page
+    /// as in `DaemonState`, under a new revision.
+    SongsUpdated {
+        revision: u64,
+        songs: Vec<SongInfo>,
+    },
+    /// Nothing is playing or queued any more.
     PlaybackFinished,
+    /// One playback ended, sent for every song played. Comes before the
+    /// `NowPlaying` for whatever plays next.
+    SongEnded {
+        song: String,
+        reason: FinishReason,
+    },
     NowPlaying(Option<String>),
+    /// The song queue changed; same content as `DaemonState::queue`.
+    QueueUpdated(Vec<String>),
+    /// Something the user asked for can't happen right now, or a playback
+    /// failed. `context` is what went wrong ("playback" or "decode");
+    /// `message` is a full sentence for the user.
+    Error {
+        context: String,
+        message: String,
+    },
+    /// Playback was paused or resumed. A new playback always starts unpaused.
+    Paused(bool),
+    /// Linear peak/RMS of the active playback, sent about every 50 ms.
+    OutputLevel {
+        peak: f32,
+        rms: f32,
+        limiting: bool,
+    },
+    /// Position in the song that is playing, sent every 500 ms of audio.
+    PlaybackProgress {
+        position_secs: f64,
+        total_secs: f64,
+    },
+    CommandResult(CommandResult),
+    Stats(DaemonStats),
+    Songs {
+        revision: u64,
+        offset: usize,
+        total: usize,
+        songs: Vec<SongInfo>,
+    },
+    /// Peak envelope of the song at `path`: `WAVEFORM_BUCKETS` levels from
+    /// silence (0) to full scale (255).
+    Waveform {
+        path: String,
+        peaks: Vec<u8>,
+    },
+    /// Everything a `ClientCommand::Request` produced, sent to the requester
+    /// only; empty for commands answered later or not at all.
+    Reply {
+        request_id: u64,
+        events: Vec<DaemonEvent>,
+    },
     Shutdown,
+    /// `song` is the one the binding picked, if it played one.
+    #[cfg(feature = "transcriber")]
+    WordDetected {
+        word: String,
+        simulated: bool,
+        #[serde(default)]
+        song: Option<String>,
+    },
+    /// A word trigger's first audio went out `total_ms` after the word was
+    /// recognized; `decode_ms` and `connect_ms` are the slowest stages.
     #[cfg(feature = "transcriber")]
-    WordDetected(String),
+    TriggerLatency {
+        word: String,
+        total_ms: u64,
+        decode_ms: u64,
+        connect_ms: u64,
+    },
+    /// The word bindings changed; same content as `DaemonState::word_mappings`.
+    #[cfg(feature = "transcriber")]
+    MappingsUpdated(Vec<WordMapping>),
+}
+
+/// JSON document the daemon keeps at the configured `status_file` for
+/// status bars. Field names are part of the public interface; don't rename.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StatusFile {
+    pub now_playing: Option<String>,
+    pub volume: f32,
+    /// "unavailable", "downloading", "download_failed", "ready" or "running".
+    pub detector_status: String,
+    pub last_detected_word: Option<String>,
+    /// Unix time of the write, in seconds.
+    pub ts: u64,
+}
+
+const SOCKET_NAME: &str = "plentysound.sock";
+
+/// Fallbacks for `--socket` and `--instance` when they aren't given.
+pub const SOCKET_ENV: &str = "PLENTYSOUND_SOCKET";
+pub const INSTANCE_ENV: &str = "PLENTYSOUND_INSTANCE";
+
+/// Which daemon this process is, or talks to. The default instance uses
+/// the usual socket, config and log; a named one gets its own of each, so
+/// several daemons can run side by side.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Instance {
+    /// Exact socket path, instead of looking in the usual places.
+    pub socket: Option<PathBuf>,
+    pub name: Option<String>,
+}
+
+static INSTANCE: std::sync::OnceLock<Instance> = std::sync::OnceLock::new();
+
+/// Set once by `main`, before anything looks at a path.
+pub fn set_instance(instance: Instance) {
+    let _ = INSTANCE.set(instance);
+}
+
+pub fn instance() -> &'static Instance {
+    INSTANCE.get_or_init(Instance::default)
+}
+
+impl Instance {
+    /// Take `--socket <path>` and `--instance <name>` out of `args`,
+    /// wherever they are, falling back to `env` for the ones not given.
+    pub fn from_args(
+        args: &mut Vec<String>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Self> {
+        let mut socket = None;
+        let mut name = None;
+        let mut i = 0;
+        while i < args.len() {
+            let slot = match args[i].as_str() {
+                "--socket" => &mut socket,
+                "--instance" => &mut name,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let flag = args.remove(i);
+            if i >= args.len() {
+                anyhow::bail!("{} needs a value", flag);
+            }
+            *slot = Some(args.remove(i));
+        }
+        let socket = socket.or_else(|| env(SOCKET_ENV)).filter(|s| !s.is_empty());
+        let name = name.or_else(|| env(INSTANCE_ENV)).filter(|n| !n.is_empty());
+        if let Some(name) = &name {
+            let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+            if !name.chars().all(valid) {
+                anyhow::bail!(
+                    "Instance name '{}' may only use letters, digits, - and _",
+                    name
+                );
+            }
+        }
+        Ok(Self {
+            socket: socket.map(PathBuf::from),
+            name,
+        })
+    }
+
+    /// The flags that select this instance again, for a spawned daemon.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(socket) = &self.socket {
+            args.extend(["--socket".to_string(), socket.display().to_string()]);
+        }
+        if let Some(name) = &self.name {
+            args.extend(["--instance".to_string(), name.clone()]);
+        }
+        args
+    }
+
+    /// This instance's directory under `base`, e.g. `~/.config`: the
+    /// shared `plentysound/`, or a subdirectory of it for a named instance.
+    pub fn dir_in(&self, base: PathBuf) -> PathBuf {
+        let dir = base.join("plentysound");
+        match self.namespace() {
+            Some(name) => dir.join(name),
+            None => dir,
+        }
+    }
+
+    /// The subdirectory name: `name`, or for a `--socket` without one,
+    /// `socket-<file stem>-<hash of the path>`, so daemons on different
+    /// sockets don't share a config, state and log.
+    fn namespace(&self) -> Option<String> {
+        if let Some(name) = &self.name {
+            return Some(name.clone());
+        }
+        let socket = self.socket.as_ref()?;
+        let socket = std::path::absolute(socket).unwrap_or_else(|_| socket.clone());
+        let stem = socket.file_stem().unwrap_or_default().to_string_lossy();
+        let stem: String = stem
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        // FNV-1a, which unlike the std hasher stays the same across builds
+        let bytes = socket.as_os_str().as_encoded_bytes();
+        let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, &b| {
+            (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+        Some(format!("socket-{stem}-{:08x}", hash as u32))
+    }
+
+    /// Where the daemon socket may live, most preferred first:
+    /// `$XDG_RUNTIME_DIR`, `~/.cache/plentysound/`, then a per-user
+    /// directory under /tmp. Clients look in the same order. Only the
+    /// `socket` path when one is set.
+    pub fn socket_candidates(&self) -> Vec<PathBuf> {
+        if let Some(socket) = &self.socket {
+            return vec![socket.clone()];
+        }
+        let file = match &self.name {
+            Some(name) => format!("plentysound-{name}.sock"),
+            None => SOCKET_NAME.to_string(),
+        };
+        let mut dirs = Vec::new();
+        if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+            dirs.push(PathBuf::from(dir));
+        }
+        if let Some(home) = std::env::var_os("HOME").filter(|h| !h.is_empty()) {
+            dirs.push(PathBuf::from(home).join(".cache/plentysound"));
+        }
+        dirs.push(PathBuf::from(format!("/tmp/plentysound-{}", current_uid())));
+        dirs.into_iter().map(|d| d.join(&file)).collect()
+    }
+}
+
+/// `Instance::socket_candidates` for this process's instance.
+pub fn socket_candidates() -> Vec<PathBuf> {
+    instance().socket_candidates()
+}
+
+/// Pick the socket location for the daemon: a `--socket` path as given,
+/// else the runtime dir if it exists, otherwise the first fallback
+/// directory that can be made private (0700 and owned by us).
+pub fn socket_path() -> std::io::Result<PathBuf> {
+    if let Some(socket) = &instance().socket {
+        return Ok(socket.clone());
+    }
+    let mut last_err = None;
+    for (i, path) in socket_candidates().into_iter().enumerate() {
+        let Some(dir) = path.parent() else {
+            continue;
+        };
+        let runtime = std::env::var_os("XDG_RUNTIME_DIR").is_some_and(|d| !d.is_empty());
+        let ready = if i == 0 && runtime {
+            // Created and locked down by the session manager
+            if dir.is_dir() {
+                Ok(())
+            } else {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "runtime dir missing",
+                ))
+            }
+        } else {
+            private_dir(dir)
+        };
+        match ready {
+            Ok(()) => return Ok(path),
+            Err(e) => {
+                last_err = Some(std::io::Error::new(
+                    e.kind(),
+                    format!("{}: {e}", dir.display()),
+                ))
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| std::io::Error::other("no socket directory")))
+}
+
+/// Where files that can be made again are kept, shared by every instance:
+/// `$XDG_CACHE_HOME/plentysound`, `~/.cache/plentysound`, or with neither
+/// set a per-user directory under /tmp. Whatever goes under it should be
+/// made with `private_dir` first.
+pub fn cache_root() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME").filter(|d| !d.is_empty()) {
+        PathBuf::from(dir).join("plentysound")
+    } else if let Some(home) = std::env::var_os("HOME").filter(|h| !h.is_empty()) {
+        PathBuf::from(home).join(".cache/plentysound")
+    } else {
+        PathBuf::from(format!("/tmp/plentysound-{}", current_uid()))
+    }
+}
+
+/// Create `dir` if needed and make sure only we can get into it.
+pub fn private_dir(dir: &std::path::Path) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    let meta = std::fs::symlink_metadata(dir)?;
+    if !meta.is_dir() || meta.uid() != current_uid() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "not a directory owned by us",
+        ));
+    }
+    if meta.mode() & 0o077 != 0 {
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// Bind the daemon socket, readable and writable by us only.
+pub fn bind_private(path: &std::path::Path) -> std::io::Result<UnixListener> {
+    use std::os::unix::fs::PermissionsExt;
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
 }
 
-pub fn socket_path() -> PathBuf {
-    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
-        .unwrap_or_else(|_| "/tmp".to_string());
-    PathBuf::from(runtime_dir).join("plentysound.sock")
+fn current_uid() -> u32 {
+    unsafe { libc::geteuid() }
+}
+
+/// UID of the process at the other end of a connected unix socket.
+pub fn peer_uid(stream: &UnixStream) -> std::io::Result<u32> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let rc = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+/// Refuse a peer running as another user.
+pub fn check_peer(stream: &UnixStream) -> std::io::Result<()> {
+    check_peer_uid(stream, current_uid())
+}
+
+fn check_peer_uid(stream: &UnixStream, uid: u32) -> std::io::Result<()> {
+    let peer = peer_uid(stream)?;
+    if peer != uid {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("peer runs as uid {peer}, expected {uid}"),
+        ));
+    }
+    Ok(())
 }
 
 #[cfg(feature = "transcriber")]
@@ -133,6 +1023,13 @@ pub fn model_path() -> PathBuf {
 
 pub fn send_message<T: Serialize>(stream: &mut impl Write, msg: &T) -> std::io::Result<()> {
     let json = serde_json::to_vec(msg).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    if json.len() > MAX_MESSAGE_LEN {
+        // The peer would reject it anyway; refuse here and keep the stream usable
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "message too large",
+        ));
+    }
     let len = (json.len() as u32).to_le_bytes();
     stream.write_all(&len)?;
     stream.write_all(&json)?;
@@ -143,10 +1040,422 @@ pub fn recv_message<T: DeserializeOwned>(stream: &mut impl Read) -> std::io::Res
     let mut len_buf = [0u8; 4];
     stream.read_exact(&mut len_buf)?;
     let len = u32::from_le_bytes(len_buf) as usize;
-    if len > 16 * 1024 * 1024 {
+    if len > MAX_MESSAGE_LEN {
+        // Skip the payload so the next frame still lines up
+        std::io::copy(&mut stream.take(len as u64), &mut std::io::sink())?;
         return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "message too large"));
     }
     let mut buf = vec![0u8; len];
     stream.read_exact(&mut buf)?;
     serde_json::from_slice(&buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
 }
+
+/// Reads the frames `send_message` writes, keeping whatever part of a frame
+/// has arrived across calls. Unlike `recv_message`, a read that fails
+/// halfway (`WouldBlock`, a timeout) loses nothing, so the framing can't
+/// slip on nonblocking or timed reads.
+pub struct FrameReader<R> {
+    inner: R,
+    buf: Vec<u8>,
+    /// Bytes of an oversized frame still to be thrown away.
+    skip: usize,
+}
+
+impl<R> FrameReader<R> {
+    pub fn new(inner: R) -> Self {
+        FrameReader {
+            inner,
+            buf: Vec::new(),
+            skip: 0,
+        }
+    }
+
+    /// The stream, e.g. to send on it. Reading from it directly would
+    /// take bytes from under the buffered frame.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Part of a frame has come in and the rest hasn't.
+    pub fn in_frame(&self) -> bool {
+        !self.buf.is_empty() || self.skip > 0
+    }
+
+    /// The next whole frame in the buffer, if there is one.
+    fn next_frame<T: DeserializeOwned>(&mut self) -> Option<std::io::Result<T>> {
+        let skipped = self.skip.min(self.buf.len());
+        self.buf.drain(..skipped);
+        self.skip -= skipped;
+        if self.skip > 0 || self.buf.len() < 4 {
+            return None;
+        }
+        let len = u32::from_le_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]) as usize;
+        if len > MAX_MESSAGE_LEN {
+            // Dropped as it comes in, so the next frame still lines up
+            self.buf.drain(..4);
+            self.skip = len;
+            let skipped = self.skip.min(self.buf.len());
+            self.buf.drain(..skipped);
+            self.skip -= skipped;
+            let err = std::io::Error::new(std::io::ErrorKind::InvalidData, "message too large");
+            return Some(Err(err));
+        }
+        if self.buf.len() < 4 + len {
+            return None;
+        }
+        let msg = serde_json::from_slice(&self.buf[4..4 + len])
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+        self.buf.drain(..4 + len);
+        Some(msg)
+    }
+
+    /// Append one read's worth to the buffer; the end of the stream is an
+    /// `UnexpectedEof`, as with `read_exact`.
+    fn fill(
+        &mut self,
+        mut read: impl FnMut(&mut R, &mut [u8]) -> std::io::Result<usize>,
+    ) -> std::io::Result<()> {
+        const CHUNK: usize = 64 * 1024;
+        let start = self.buf.len();
+        self.buf.resize(start + CHUNK, 0);
+        let result = loop {
+            match read(&mut self.inner, &mut self.buf[start..]) {
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
+        let n = *result.as_ref().unwrap_or(&0);
+        self.buf.truncate(start + n);
+        match result? {
+            0 => Err(std::io::ErrorKind::UnexpectedEof.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<R: Read> FrameReader<R> {
+    /// The next message, reading as needed. Errors from the stream are
+    /// passed on; calling again after one carries on where it stopped.
+    pub fn recv<T: DeserializeOwned>(&mut self) -> std::io::Result<T> {
+        loop {
+            if let Some(msg) = self.next_frame() {
+                return msg;
+            }
+            self.fill(|inner, buf| inner.read(buf))?;
+        }
+    }
+}
+
+impl FrameReader<UnixStream> {
+    /// `recv` that never waits, however the socket is set up: `WouldBlock`
+    /// until a whole message is in.
+    pub fn try_recv<T: DeserializeOwned>(&mut self) -> std::io::Result<T> {
+        loop {
+            if let Some(msg) = self.next_frame() {
+                return msg;
+            }
+            self.fill(|stream, buf| {
+                let n = unsafe {
+                    libc::recv(
+                        stream.as_raw_fd(),
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len(),
+                        libc::MSG_DONTWAIT,
+                    )
+                };
+                if n < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(n as usize)
+            })?;
+        }
+    }
+
+    /// `recv` on a blocking socket that waits as long as it takes for a
+    /// message to start, but once part of one is in, fails with `TimedOut`
+    /// unless the rest follows within `timeout`.
+    pub fn recv_timeout<T: DeserializeOwned>(&mut self, timeout: Duration) -> std::io::Result<T> {
+        let mut deadline = None;
+        loop {
+            if let Some(msg) = self.next_frame() {
+                return msg;
+            }
+            if self.in_frame() {
+                let deadline = *deadline.get_or_insert_with(|| Instant::now() + timeout);
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return Err(std::io::ErrorKind::TimedOut.into());
+                }
+                self.inner.set_read_timeout(Some(left))?;
+            } else {
+                self.inner.set_read_timeout(None)?;
+            }
+            if let Err(e) = self.fill(|stream, buf| stream.read(buf)) {
+                use std::io::ErrorKind::{TimedOut, WouldBlock};
+                // A timed out read; the deadline check above decides
+                if !matches!(e.kind(), WouldBlock | TimedOut) {
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn oversized_frame_is_skipped_and_stream_stays_usable() {
+        let mut wire = Vec::new();
+        let oversized = MAX_MESSAGE_LEN + 1;
+        wire.extend_from_slice(&(oversized as u32).to_le_bytes());
+        wire.resize(wire.len() + oversized, b' ');
+        send_message(&mut wire, &ClientCommand::GetState).unwrap();
+
+        let mut reader = Cursor::new(wire);
+        let err = recv_message::<ClientCommand>(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let next: ClientCommand = recv_message(&mut reader).unwrap();
+        assert!(matches!(next, ClientCommand::GetState));
+    }
+
+    /// Hands out its pieces one read at a time, with a `WouldBlock` before
+    /// each, like a nonblocking socket that data trickles into.
+    struct Trickle {
+        pieces: std::collections::VecDeque<Vec<u8>>,
+        ready: bool,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if !std::mem::replace(&mut self.ready, true) {
+                return Err(std::io::ErrorKind::WouldBlock.into());
+            }
+            self.pieces.retain(|piece| !piece.is_empty());
+            let Some(piece) = self.pieces.front_mut() else {
+                return Ok(0);
+            };
+            let n = piece.len().min(buf.len());
+            buf[..n].copy_from_slice(&piece[..n]);
+            piece.drain(..n);
+            if piece.is_empty() {
+                self.pieces.pop_front();
+                self.ready = false;
+            }
+            Ok(n)
+        }
+    }
+
+    /// Everything `reader` yields until the stream ends, retrying on
+    /// `WouldBlock` and noting skipped frames as `None`.
+    fn drain(reader: &mut FrameReader<Trickle>) -> Vec<Option<ClientCommand>> {
+        let mut got = Vec::new();
+        loop {
+            match reader.recv() {
+                Ok(cmd) => got.push(Some(cmd)),
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(e) if e.kind() == std::io::ErrorKind::InvalidData => got.push(None),
+                Err(e) => {
+                    assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof);
+                    return got;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn frames_split_anywhere_come_out_whole_and_in_order() {
+        let sent = || {
+            let song = "/sounds/horn.wav".to_string();
+            [
+                ClientCommand::SelectSong(3),
+                ClientCommand::AddSong(song),
+                ClientCommand::GetState,
+            ]
+        };
+        let mut wire = Vec::new();
+        for cmd in sent() {
+            send_message(&mut wire, &cmd).unwrap();
+        }
+        let expected = format!("{:?}", sent().map(Some));
+        let check = |pieces, split: &str| {
+            let got = drain(&mut FrameReader::new(Trickle {
+                pieces,
+                ready: false,
+            }));
+            assert_eq!(format!("{got:?}"), expected, "split {split}");
+        };
+
+        for at in 0..=wire.len() {
+            check(
+                [wire[..at].to_vec(), wire[at..].to_vec()].into(),
+                &format!("at {at}"),
+            );
+        }
+        check(wire.iter().map(|&b| vec![b]).collect(), "into bytes");
+    }
+
+    #[test]
+    fn oversized_frame_is_skipped_as_it_trickles_in() {
+        let mut wire = Vec::new();
+        let oversized = MAX_MESSAGE_LEN + 1;
+        wire.extend_from_slice(&(oversized as u32).to_le_bytes());
+        wire.resize(wire.len() + oversized, b' ');
+        send_message(&mut wire, &ClientCommand::GetState).unwrap();
+
+        let pieces = wire.chunks(1 << 20).map(<[u8]>::to_vec).collect();
+        let got = drain(&mut FrameReader::new(Trickle {
+            pieces,
+            ready: false,
+        }));
+        assert!(
+            matches!(got[..], [None, Some(ClientCommand::GetState)]),
+            "{got:?}"
+        );
+    }
+
+    #[test]
+    fn socket_reads_keep_a_partial_frame_until_the_rest_comes() {
+        let (client, mut daemon) = UnixStream::pair().unwrap();
+        let mut wire = Vec::new();
+        send_message(&mut wire, &ClientCommand::GetState).unwrap();
+
+        // Polling a blocking socket never waits
+        let mut reader = FrameReader::new(client);
+        let err = reader.try_recv::<ClientCommand>().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        daemon.write_all(&wire[..2]).unwrap();
+        let err = reader.try_recv::<ClientCommand>().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        assert!(reader.in_frame());
+
+        // A stalled frame times out, and still completes if the rest comes
+        let err = reader
+            .recv_timeout::<ClientCommand>(Duration::from_millis(50))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        daemon.write_all(&wire[2..]).unwrap();
+        let cmd = reader.recv_timeout(Duration::from_millis(50)).unwrap();
+        assert!(matches!(cmd, ClientCommand::GetState));
+        assert!(!reader.in_frame());
+    }
+
+    #[test]
+    fn peer_uid_is_checked_on_both_ends() {
+        let dir = crate::app::tests::scratch_dir("peercred");
+        let path = dir.join(SOCKET_NAME);
+        let listener = bind_private(&path).unwrap();
+        let client = UnixStream::connect(&path).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        check_peer(&server).unwrap();
+        check_peer(&client).unwrap();
+        let err = check_peer_uid(&server, current_uid().wrapping_add(1)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn fallback_socket_dir_is_made_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = crate::app::tests::scratch_dir("sockdir").join("plentysound");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        private_dir(&dir).unwrap();
+        assert_eq!(
+            std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777,
+            0o700
+        );
+        let _ = std::fs::remove_dir_all(dir.parent().unwrap());
+    }
+
+    #[test]
+    fn instance_flags_are_taken_from_anywhere_and_passed_on() {
+        let no_env = |_: &str| None;
+        let mut args: Vec<String> = [
+            "plentysound",
+            "--instance",
+            "test",
+            "status",
+            "--socket",
+            "/tmp/x.sock",
+            "--json",
+        ]
+        .map(String::from)
+        .into();
+        let instance = Instance::from_args(&mut args, no_env).unwrap();
+        assert_eq!(args, ["plentysound", "status", "--json"]);
+        assert_eq!(
+            instance.socket.as_deref(),
+            Some(std::path::Path::new("/tmp/x.sock"))
+        );
+        assert_eq!(instance.name.as_deref(), Some("test"));
+        assert_eq!(instance.socket_candidates(), [PathBuf::from("/tmp/x.sock")]);
+        let mut again = instance.args();
+        assert_eq!(Instance::from_args(&mut again, no_env).unwrap(), instance);
+        assert!(again.is_empty());
+
+        // Flags win over the environment, which fills in the rest
+        let env = |key: &str| Some(format!("env-{key}"));
+        let mut args = vec!["--instance".to_string(), "work".to_string()];
+        let instance = Instance::from_args(&mut args, env).unwrap();
+        assert_eq!(instance.name.as_deref(), Some("work"));
+        assert_eq!(
+            instance.socket,
+            Some(PathBuf::from(format!("env-{SOCKET_ENV}")))
+        );
+        assert_eq!(
+            instance.socket_candidates(),
+            [PathBuf::from(format!("env-{SOCKET_ENV}"))]
+        );
+
+        let base = PathBuf::from("/home/me/.config");
+        assert_eq!(instance.dir_in(base.clone()), base.join("plentysound/work"));
+        assert_eq!(
+            Instance::default().dir_in(base.clone()),
+            base.join("plentysound")
+        );
+        // A bare socket gets its own directory, the same one each time
+        let on = |path: &str| Instance {
+            socket: Some(PathBuf::from(path)),
+            name: None,
+        };
+        let dir = on("/run/user/1000/my.sock").dir_in(base.clone());
+        assert!(dir.starts_with(base.join("plentysound")) && dir != base.join("plentysound"));
+        assert!(dir
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("socket-my-"));
+        assert_eq!(on("/run/user/1000/my.sock").dir_in(base.clone()), dir);
+        assert_ne!(on("/tmp/my.sock").dir_in(base.clone()), dir);
+        let named = Instance {
+            name: Some("work".to_string()),
+            ..Default::default()
+        };
+        assert!(named
+            .socket_candidates()
+            .iter()
+            .all(|p| p.ends_with("plentysound-work.sock")));
+
+        assert!(Instance::from_args(&mut vec!["--socket".to_string()], no_env).is_err());
+        let mut bad = vec!["--instance".to_string(), "../x".to_string()];
+        assert!(Instance::from_args(&mut bad, no_env).is_err());
+    }
+
+    #[test]
+    fn oversized_message_is_not_sent() {
+        let mut wire = Vec::new();
+        let huge = ClientCommand::AddSong("x".repeat(MAX_MESSAGE_LEN));
+        let err = send_message(&mut wire, &huge).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(wire.is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::VecDeque;
+
+/// A history that keeps only its newest `cap` entries, so a daemon left
+/// running for weeks doesn't keep growing. Counts what it dropped so users
+/// can tell when history was lost.
+#[derive(Debug, Clone)]
+pub struct Ring<T> {
+    items: VecDeque<T>,
+    cap: usize,
+    evicted: u64,
+}
+
+impl<T> Ring<T> {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            items: VecDeque::with_capacity(cap.min(1024)),
+            cap,
+            evicted: 0,
+        }
+    }
+
+    /// Append `item`, dropping the oldest entry when full.
+    pub fn push(&mut self, item: T) {
+        self.items.push_back(item);
+        self.trim();
+    }
+
+    /// Change the cap; shrinking drops the oldest entries and counts them.
+    pub fn set_cap(&mut self, cap: usize) {
+        self.cap = cap;
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.items.len() > self.cap {
+            self.items.pop_front();
+            self.evicted += 1;
+        }
+    }
+
+    /// Retained entries, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    /// Entries dropped to stay under the cap since the ring was made.
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+}
+
+// Only the retained window goes into snapshots, as a plain list
+impl<T: Serialize> Serialize for Ring<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<T> Extend<T> for Ring<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+/// A ring read back from a snapshot holds exactly the window it was given.
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Ring<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let items = VecDeque::<T>::deserialize(deserializer)?;
+        Ok(Self {
+            cap: items.len(),
+            items,
+            evicted: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_around_keeping_the_newest_entries_in_order() {
+        let mut ring = Ring::new(3);
+        for i in 0..5 {
+            ring.push(i);
+        }
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), [2, 3, 4]);
+        assert_eq!((ring.len(), ring.cap(), ring.evicted()), (3, 3, 2));
+
+        ring.push(5);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), [3, 4, 5]);
+        assert_eq!(ring.evicted(), 3);
+    }
+
+    #[test]
+    fn shrinking_the_cap_evicts_the_oldest() {
+        let mut ring = Ring::new(4);
+        ring.extend(["a", "b", "c", "d"]);
+        ring.set_cap(2);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), ["c", "d"]);
+        assert_eq!(ring.evicted(), 2);
+
+        ring.set_cap(5);
+        ring.push("e");
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), ["c", "d", "e"]);
+        assert_eq!(ring.evicted(), 2);
+
+        let mut empty = Ring::new(0);
+        empty.push("f");
+        assert_eq!((empty.len(), empty.evicted()), (0, 1));
+    }
+
+    #[test]
+    fn serializes_only_the_retained_window() {
+        let mut ring = Ring::new(2);
+        ring.extend([1, 2, 3]);
+        let json = serde_json::to_string(&ring).unwrap();
+        assert_eq!(json, "[2,3]");
+
+        let back: Ring<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.iter().copied().collect::<Vec<_>>(), [2, 3]);
+        assert_eq!((back.cap(), back.evicted()), (2, 0));
+    }
+}
@@ -0,0 +1,265 @@
+use anyhow::Result;
+use pipewire::{
+    context::Context,
+    main_loop::MainLoop,
+    properties::properties,
+    spa::{
+        param::{
+            audio::{AudioFormat, AudioInfoRaw},
+            ParamType,
+        },
+        pod::{serialize::PodSerializer, Object, Pod, Value},
+        utils::SpaTypes,
+    },
+    stream::{Stream, StreamFlags},
+};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::Receiver;
+
+const LOG: crate::log::Logger = crate::log::log_scope("sidechain");
+
+/// Mic capture rate; mono F32 is all the level detector needs.
+const CAPTURE_RATE: u32 = 48_000;
+
+/// Time constant of the smoothed mic level.
+const LEVEL_SMOOTHING_MS: f32 = 50.0;
+
+/// How long it takes to move the full reduction down (attack) and back up (release).
+const ATTACK_MS: f32 = 30.0;
+const RELEASE_MS: f32 = 400.0;
+
+/// Gain applied to song playback, stored as f32 bits. 1.0 when nobody talks.
+static PLAYBACK_GAIN: AtomicU32 = AtomicU32::new(0x3f80_0000);
+
+/// Current sidechain gain for playback streams that opted into ducking.
+pub fn playback_gain() -> f32 {
+    f32::from_bits(PLAYBACK_GAIN.load(Ordering::Relaxed))
+}
+
+fn set_playback_gain(gain: f32) {
+    PLAYBACK_GAIN.store(gain.to_bits(), Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SidechainSettings {
+    pub threshold_db: f32,
+    pub reduction_db: f32,
+    pub hold_ms: u32,
+}
+
+/// Turns mic levels into a playback gain: a smoothed level above the
+/// threshold ramps the gain down by `reduction_db`, and it ramps back up
+/// once the level has stayed below the threshold for `hold_ms`.
+pub struct Ducker {
+    settings: SidechainSettings,
+    level_db: f32,
+    hold_left_ms: f32,
+    gain_db: f32,
+}
+
+impl Ducker {
+    pub fn new(settings: SidechainSettings) -> Self {
+        Self {
+            settings,
+            level_db: -120.0,
+            hold_left_ms: 0.0,
+            gain_db: 0.0,
+        }
+    }
+
+    /// Feed one block of mono samples lasting `block_ms`; returns the linear gain.
+    pub fn process(&mut self, samples: &[f32], block_ms: f32) -> f32 {
+        let block_db = rms_db(samples);
+        let alpha = (block_ms / LEVEL_SMOOTHING_MS).min(1.0);
+        self.level_db += (block_db - self.level_db) * alpha;
+
+        if self.level_db > self.settings.threshold_db {
+            self.hold_left_ms = self.settings.hold_ms as f32;
+        } else {
+            self.hold_left_ms = (self.hold_left_ms - block_ms).max(0.0);
+        }
+
+        let reduction = self.settings.reduction_db.max(0.0);
+        let target_db = if self.hold_left_ms > 0.0 {
+            -reduction
+        } else {
+            0.0
+        };
+        if reduction > 0.0 {
+            let (ramp_ms, step_sign) = if target_db < self.gain_db {
+                (ATTACK_MS, -1.0)
+            } else {
+                (RELEASE_MS, 1.0)
+            };
+            let step = reduction * block_ms / ramp_ms;
+            self.gain_db = if step_sign < 0.0 {
+                (self.gain_db - step).max(target_db)
+            } else {
+                (self.gain_db + step).min(target_db)
+            };
+        } else {
+            self.gain_db = 0.0;
+        }
+
+        10.0_f32.powf(self.gain_db / 20.0)
+    }
+}
+
+fn rms_db(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return -120.0;
+    }
+    let mean_sq = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+    (10.0 * mean_sq.max(1e-12).log10()).max(-120.0)
+}
+
+/// Spawn the sidechain capture on `node_id`. It runs until `stop_rx` fires or
+/// is dropped, and always leaves the playback gain at 1.0 when it exits.
+pub fn spawn_sidechain(
+    node_id: u32,
+    settings: SidechainSettings,
+    stop_rx: Receiver<()>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        if let Err(e) = run_sidechain(node_id, settings, stop_rx) {
+            LOG.error(&format!("Sidechain error: {e}"));
+        }
+        set_playback_gain(1.0);
+    })
+}
+
+fn run_sidechain(node_id: u32, settings: SidechainSettings, stop_rx: Receiver<()>) -> Result<()> {
+    let mainloop = MainLoop::new(None)?;
+    let context = Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+
+    let target_str = node_id.to_string();
+    let stream = Stream::new(
+        &core,
+        "plentysound-sidechain",
+        properties! {
+            "media.type"     => "Audio",
+            "media.category" => "Capture",
+            "media.role"     => "Communication",
+            "node.target"    => target_str.as_str(),
+        },
+    )?;
+
+    let mut ducker = Ducker::new(settings);
+    let _listener = stream
+        .add_local_listener()
+        .process(move |stream, _: &mut ()| {
+            if let Some(mut buffer) = stream.dequeue_buffer() {
+                let datas = buffer.datas_mut();
+                if let Some(data) = datas.first_mut() {
+                    let size = data.chunk().size() as usize;
+                    if let Some(slice) = data.data() {
+                        let valid = &slice[..size.min(slice.len())];
+                        let samples: Vec<f32> = valid
+                            .chunks_exact(4)
+                            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                            .collect();
+                        if !samples.is_empty() {
+                            let block_ms = samples.len() as f32 * 1000.0 / CAPTURE_RATE as f32;
+                            set_playback_gain(ducker.process(&samples, block_ms));
+                        }
+                    }
+                }
+            }
+        })
+        .register()?;
+
+    let mut audio_info = AudioInfoRaw::new();
+    audio_info.set_format(AudioFormat::F32LE);
+    audio_info.set_rate(CAPTURE_RATE);
+    audio_info.set_channels(1);
+
+    let obj = Object {
+        type_: SpaTypes::ObjectParamFormat.as_raw(),
+        id: ParamType::EnumFormat.as_raw(),
+        properties: audio_info.into(),
+    };
+    let pod_value = Value::Object(obj);
+    let (pod_bytes, _) = PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &pod_value)
+        .map_err(|e| anyhow::anyhow!("pod serialize error: {:?}", e))?;
+    let pod_bytes = pod_bytes.into_inner();
+    let param = Pod::from_bytes(&pod_bytes)
+        .ok_or_else(|| anyhow::anyhow!("Failed to create Pod from bytes"))?;
+
+    stream.connect(
+        pipewire::spa::utils::Direction::Input,
+        Some(node_id),
+        StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS,
+        &mut [param],
+    )?;
+
+    // Same stop handling as the word detector: a watcher thread flips a flag
+    // that a timer on the main loop polls.
+    let stop_flag = std::sync::Arc::new(AtomicBool::new(false));
+    let stop_flag_watcher = stop_flag.clone();
+    std::thread::spawn(move || {
+        let _ = stop_rx.recv();
+        stop_flag_watcher.store(true, Ordering::SeqCst);
+    });
+
+    let timer = mainloop.loop_().add_timer({
+        let mainloop_weak = mainloop.downgrade();
+        move |_| {
+            if stop_flag.load(Ordering::SeqCst) {
+                if let Some(ml) = mainloop_weak.upgrade() {
+                    ml.quit();
+                }
+            }
+        }
+    });
+    timer.update_timer(
+        Some(std::time::Duration::from_millis(100)),
+        Some(std::time::Duration::from_millis(100)),
+    );
+
+    mainloop.run();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: SidechainSettings = SidechainSettings {
+        threshold_db: -30.0,
+        reduction_db: 12.0,
+        hold_ms: 200,
+    };
+
+    fn run(ducker: &mut Ducker, amplitude: f32, ms: u32) -> f32 {
+        let block = vec![amplitude; 480];
+        let mut gain = 1.0;
+        for _ in 0..ms / 10 {
+            gain = ducker.process(&block, 10.0);
+        }
+        gain
+    }
+
+    #[test]
+    fn silence_leaves_gain_at_unity() {
+        let mut ducker = Ducker::new(SETTINGS);
+        assert_eq!(run(&mut ducker, 0.0, 1000), 1.0);
+    }
+
+    #[test]
+    fn speech_ducks_then_releases_after_hold() {
+        let mut ducker = Ducker::new(SETTINGS);
+        let reduced = 10.0_f32.powf(-12.0 / 20.0);
+
+        let ducked = run(&mut ducker, 0.5, 300);
+        assert!((ducked - reduced).abs() < 1e-4, "ducked gain {ducked}");
+
+        // Still held shortly after the speaker stops
+        let held = run(&mut ducker, 0.0, 100);
+        assert!((held - reduced).abs() < 1e-4, "held gain {held}");
+
+        let released = run(&mut ducker, 0.0, 1000);
+        assert_eq!(released, 1.0);
+    }
+}
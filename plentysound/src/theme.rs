@@ -0,0 +1,44 @@
+use ratatui::style::Color;
+
+use crate::protocol::TagColor;
+
+/// Song tags, in the order `c` cycles through them, and how each is drawn,
+/// unless the config sets its own `tag_palette`. Songs store the name, so
+/// changing a color here recolors existing tags.
+pub const TAG_PALETTE: &[(&str, Color)] = &[
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("red", Color::Red),
+    ("blue", Color::Blue),
+];
+
+// The tags in `palette`, the daemon's configured ones, or `TAG_PALETTE`
+// when it sent none.
+fn tags(palette: &[TagColor]) -> impl Iterator<Item = (&str, Color)> {
+    let default = TAG_PALETTE
+        .iter()
+        .copied()
+        .filter(move |_| palette.is_empty());
+    let configured = palette
+        .iter()
+        .map(|tag| (tag.name.as_str(), tag.color.parse().unwrap_or(Color::Gray)));
+    default.chain(configured)
+}
+
+/// Color for a tag name; names not in the palette (a hand-edited config)
+/// are drawn gray.
+pub fn tag_color(palette: &[TagColor], name: &str) -> Color {
+    tags(palette)
+        .find(|&(n, _)| n == name)
+        .map_or(Color::Gray, |(_, c)| c)
+}
+
+/// The tag after `current` in the palette; past the last one (or from an
+/// unknown name) the tag is cleared, and from no tag it starts over.
+pub fn next_tag<'a>(palette: &'a [TagColor], current: Option<&str>) -> Option<&'a str> {
+    let next = match current {
+        None => 0,
+        Some(name) => tags(palette).position(|(n, _)| n == name)? + 1,
+    };
+    tags(palette).nth(next).map(|(n, _)| n)
+}
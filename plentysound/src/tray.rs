@@ -1,9 +1,18 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
+const LOG: crate::log::Logger = crate::log::log_scope("tray");
+
+/// What the daemon is playing, as the tray (and MPRIS) show it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NowPlaying {
+    pub song: Option<String>,
+    pub paused: bool,
+}
+
 struct PlentySoundTray {
     shutdown: Arc<AtomicBool>,
-    now_playing: Arc<Mutex<Option<String>>>,
+    now_playing: Arc<Mutex<NowPlaying>>,
 }
 
 impl ksni::Tray for PlentySoundTray {
@@ -16,7 +25,12 @@ impl ksni::Tray for PlentySoundTray {
     }
 
     fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
-        let np_label = match self.now_playing.lock().unwrap().as_ref() {
+        let now_playing = self
+            .now_playing
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone();
+        let np_label = match now_playing.song {
             Some(name) => format!("Now Playing: {}", name),
             None => "Not playing".to_string(),
         };
@@ -39,13 +53,33 @@ impl ksni::Tray for PlentySoundTray {
     }
 }
 
-pub fn spawn_tray(shutdown: Arc<AtomicBool>, now_playing: Arc<Mutex<Option<String>>>) {
-    std::thread::spawn(move || {
-        let tray = PlentySoundTray {
-            shutdown,
-            now_playing,
-        };
-        let service = ksni::TrayService::new(tray);
-        service.run();
+/// The running tray service and its thread.
+pub struct TrayThread {
+    handle: ksni::Handle<PlentySoundTray>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl TrayThread {
+    /// Stop the D-Bus loop and wait for the thread to end.
+    pub fn shutdown(self) {
+        self.handle.shutdown();
+        if self.thread.join().is_err() {
+            LOG.error("Tray thread panicked");
+        }
+    }
+}
+
+pub fn spawn_tray(shutdown: Arc<AtomicBool>, now_playing: Arc<Mutex<NowPlaying>>) -> TrayThread {
+    let tray = PlentySoundTray {
+        shutdown,
+        now_playing,
+    };
+    let service = ksni::TrayService::new(tray);
+    let handle = service.handle();
+    let thread = std::thread::spawn(move || {
+        if let Err(e) = service.run() {
+            LOG.error(&format!("Tray error: {e}"));
+        }
     });
+    TrayThread { handle, thread }
 }
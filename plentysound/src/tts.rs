@@ -0,0 +1,180 @@
+use anyhow::{bail, Context, Result};
+use std::hash::{Hash, Hasher};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Where synthesized phrases are kept, one WAV per distinct text and command.
+pub fn cache_dir() -> PathBuf {
+    crate::protocol::cache_root().join("tts")
+}
+
+/// Fill the placeholders a Speak phrase may use: `{time}` becomes the local
+/// time as HH:MM.
+pub fn expand_phrase(phrase: &str, (hour, minute): (u32, u32)) -> String {
+    phrase.replace("{time}", &format!("{hour:02}:{minute:02}"))
+}
+
+/// Current local hour and minute.
+pub fn local_time() -> (u32, u32) {
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        (tm.tm_hour as u32, tm.tm_min as u32)
+    }
+}
+
+/// Render `text` to a WAV file with the `template` command, reusing an earlier
+/// result for the same text and command. The template is split on
+/// whitespace; `{out}` and `{text}` are replaced inside each argument, and
+/// the text goes to stdin when there is no `{text}`.
+pub fn synthesize(template: &str, text: &str, cache_dir: &Path) -> Result<PathBuf> {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (template, text).hash(&mut hasher);
+    let out = cache_dir.join(format!("{:016x}.wav", hasher.finish()));
+    if out.metadata().is_ok_and(|m| m.len() > 0) {
+        return Ok(out);
+    }
+    // Another user able to write here could swap in their own audio
+    crate::protocol::private_dir(cache_dir)
+        .with_context(|| format!("Failed to create {}", cache_dir.display()))?;
+
+    // Written aside and renamed, so a half-written file is never cached
+    static PART: AtomicU64 = AtomicU64::new(0);
+    let part = out.with_extension(format!(
+        "{}.{}.part.wav",
+        std::process::id(),
+        PART.fetch_add(1, Ordering::Relaxed)
+    ));
+    let part_str = part.display().to_string();
+    let mut args = template
+        .split_whitespace()
+        .map(|arg| arg.replace("{out}", &part_str).replace("{text}", text));
+    let Some(program) = args.next() else {
+        bail!("tts_command is empty");
+    };
+    let text_on_stdin = !template.contains("{text}");
+
+    let mut child = Command::new(&program)
+        .args(args)
+        .stdin(if text_on_stdin {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => anyhow::anyhow!("TTS command '{program}' not found"),
+            _ => anyhow::anyhow!("Failed to run TTS command '{program}': {e}"),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that exits without reading fails below with its own error
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .context("Failed to wait for TTS command")?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&part);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last = stderr
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("")
+            .trim();
+        bail!("TTS command '{program}' failed ({}): {last}", output.status);
+    }
+    if !part.metadata().is_ok_and(|m| m.len() > 0) {
+        let _ = std::fs::remove_file(&part);
+        bail!("TTS command '{program}' wrote no audio to {{out}}");
+    }
+    std::fs::rename(&part, &out)
+        .with_context(|| format!("Failed to move speech into {}", out.display()))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::tests::{scratch_dir, write_file};
+
+    /// A fake TTS that copies a WAV to `{out}` and counts its runs.
+    fn fake_tts(dir: &Path) -> String {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/silence.wav");
+        let script = format!(
+            "echo run >> {runs}\ncp {wav} \"$1\"\n",
+            runs = dir.join("runs").display(),
+            wav = fixture.display()
+        );
+        let script = write_file(dir, "tts.sh", script.as_bytes());
+        format!("sh {} {{out}} {{text}}", script.display())
+    }
+
+    #[test]
+    fn phrases_are_synthesized_once_per_text() {
+        let dir = scratch_dir("tts-cache");
+        let cache = dir.join("cache");
+        let template = fake_tts(&dir);
+        let runs = || {
+            std::fs::read_to_string(dir.join("runs"))
+                .unwrap()
+                .lines()
+                .count()
+        };
+
+        let first = synthesize(&template, "são 10:30", &cache).unwrap();
+        assert!(first.metadata().unwrap().len() > 0);
+        assert_eq!(synthesize(&template, "são 10:30", &cache).unwrap(), first);
+        assert_eq!(runs(), 1);
+
+        let other = synthesize(&template, "são 10:31", &cache).unwrap();
+        assert_ne!(other, first);
+        assert_eq!(runs(), 2);
+        // Only finished files are left behind, where only we can get at them
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 2);
+        let mode = cache.metadata().unwrap().permissions();
+        assert_eq!(
+            std::os::unix::fs::PermissionsExt::mode(&mode) & 0o777,
+            0o700
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn missing_commands_and_failures_are_errors() {
+        let dir = scratch_dir("tts-errors");
+        let missing = synthesize("plentysound-no-such-tts {out}", "hi", &dir).unwrap_err();
+        assert_eq!(
+            missing.to_string(),
+            "TTS command 'plentysound-no-such-tts' not found"
+        );
+
+        let script = write_file(&dir, "fail.sh", b"echo 'voice not found' >&2\nexit 3\n");
+        let template = format!("sh {} {{out}}", script.display());
+        let failed = synthesize(&template, "hi", &dir).unwrap_err().to_string();
+        assert!(failed.contains("voice not found"), "{failed}");
+
+        let silent = synthesize("true {out}", "hi", &dir)
+            .unwrap_err()
+            .to_string();
+        assert!(silent.contains("wrote no audio"), "{silent}");
+        assert!(!std::fs::read_dir(&dir).unwrap().any(|e| {
+            e.unwrap()
+                .file_name()
+                .to_string_lossy()
+                .ends_with(".part.wav")
+        }));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn time_placeholder_is_filled() {
+        assert_eq!(expand_phrase("são {time}", (9, 5)), "são 09:05");
+        assert_eq!(expand_phrase("no placeholder", (9, 5)), "no placeholder");
+    }
+}
@@ -0,0 +1,189 @@
+use super::{inner_offset, Action, Component};
+use crate::client::{ClientApp, Panel};
+use crate::protocol::DetectorPowerMode;
+use ratatui::{
+    layout::{Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+/// Word bindings of the selected song, a few lines each. Only the bindings
+/// that fit are built, scrolled so the selection stays in view.
+pub struct WordBindingsPanel;
+
+impl Component for WordBindingsPanel {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        let border_style = if app.focus == Panel::WordBindings {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+
+        let muted = if app.state.word_mappings_muted {
+            " (all muted)"
+        } else {
+            ""
+        };
+        // Performance is the default, so only the lighter modes are named
+        let power = match app.state.detector_power_mode {
+            DetectorPowerMode::Performance => String::new(),
+            mode => format!(" [{}]", mode.name()),
+        };
+        let title = match &app.state.detector_source {
+            Some(source) => format!(" Word Bindings{muted}{power} — listening to: {} ", source),
+            None => format!(" Word Bindings{muted}{power} "),
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(border_style);
+
+        let count = app.binding_count();
+        let inner = block.inner(area);
+
+        if count == 0 {
+            f.render_widget(block, area);
+            if inner.width > 0 && inner.height > 0 {
+                let text = Paragraph::new(Line::from(Span::styled(
+                    "No bindings",
+                    Style::default().fg(Color::DarkGray),
+                )));
+                f.render_widget(text, inner);
+            }
+            return;
+        }
+
+        let selected = app.selected_word_binding.min(count - 1);
+        let scroll = scroll_into_view(app.word_binding_scroll, selected, inner.height, |pos| {
+            app.binding_row_height(pos)
+        });
+        app.word_binding_scroll = scroll;
+        let mut rows = 0;
+        let visible: Vec<usize> = (scroll..count)
+            .take_while(|&pos| {
+                let fits = rows < inner.height;
+                rows += app.binding_row_height(pos);
+                fits
+            })
+            .collect();
+
+        let is_focused = app.focus == Panel::WordBindings;
+        let items: Vec<ListItem> = visible
+            .iter()
+            .filter_map(|&pos| Some((pos, app.binding(pos)?.1)))
+            .map(|(pos, wm)| {
+                let is_selected = is_focused && pos == selected;
+                let off = !wm.enabled || app.state.word_mappings_muted;
+                let word_style = if is_selected {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                let word_style = if off {
+                    word_style.add_modifier(Modifier::DIM)
+                } else {
+                    word_style
+                };
+                let detail_style = if is_selected {
+                    Style::default().fg(Color::Cyan)
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+                let mut word_spans = Vec::new();
+                if !wm.enabled {
+                    word_spans.push(Span::styled("[off] ", Style::default().fg(Color::DarkGray)));
+                }
+                let fired = format!(" ×{}", wm.stats.fired);
+                word_spans.push(Span::styled(wm.word.clone(), word_style));
+                if wm.song_paths.len() > 1 {
+                    let songs =
+                        format!(" → {} songs ({})", wm.song_paths.len(), wm.selection.name());
+                    word_spans.push(Span::styled(songs, detail_style));
+                }
+                word_spans.push(Span::styled(fired, Style::default().fg(Color::DarkGray)));
+                let line1 = Line::from(word_spans);
+                let src = if wm.source_description.is_empty() {
+                    "—"
+                } else {
+                    &wm.source_description
+                };
+                let out = if wm.output_description.is_empty() {
+                    "—"
+                } else {
+                    &wm.output_description
+                };
+                let line2 = match &wm.only_from_source {
+                    Some(only) => format!("├─ [Only] {}", only),
+                    None => format!("├─ [In] {}", src),
+                };
+                let line2 = Line::from(Span::styled(line2, detail_style));
+                let line3 = Line::from(Span::styled(format!("└─ [Out] {}", out), detail_style));
+                let mut lines = vec![line1, line2, line3];
+                if is_selected {
+                    lines.push(Line::from(Span::styled(
+                        format!(
+                            "   fired {} · cooldown {} · song missing {}",
+                            wm.stats.fired,
+                            wm.stats.suppressed_duplicate,
+                            wm.stats.suppressed_excluded
+                        ),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                }
+                ListItem::new(lines)
+            })
+            .collect();
+
+        let mut state = ListState::default();
+        state.select(Some(selected - scroll));
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn hit(&self, app: &ClientApp, pos: Position) -> Option<Action> {
+        let area = app.layout.word_bindings_area;
+        if !area.contains(pos) {
+            return None;
+        }
+        let (_, mut inner_y) = inner_offset(area, pos);
+        // Walk the rows as drawn, from the first visible binding
+        for pos in app.word_binding_scroll..app.binding_count() {
+            let height = app.binding_row_height(pos);
+            if inner_y < height {
+                return Some(Action::SelectBinding(pos));
+            }
+            inner_y -= height;
+        }
+        Some(Action::Focus(Panel::WordBindings))
+    }
+}
+
+/// First item to draw so that `selected` is fully in view, moving as little
+/// as possible from `offset`. `height` gives each item's line count.
+fn scroll_into_view(
+    offset: usize,
+    selected: usize,
+    viewport: u16,
+    height: impl Fn(usize) -> u16,
+) -> usize {
+    let mut offset = offset.min(selected);
+    let mut used: usize = (offset..=selected).map(|i| height(i) as usize).sum();
+    while used > viewport as usize && offset < selected {
+        used -= height(offset) as usize;
+        offset += 1;
+    }
+    offset
+}
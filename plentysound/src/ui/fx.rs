@@ -0,0 +1,167 @@
+use super::{inner_offset, Action, Component};
+use crate::client::{ClientApp, Panel, FX_LIMITER_ROW};
+use crate::protocol::{EqBand, NoiseKind, COMFORT_NOISE_MAX, EQ_GAIN_MAX, FADE_SECS_MAX};
+use ratatui::{
+    layout::{Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// One slider row per effect, then the limiter toggle and the noise mode.
+pub struct AudioFxPanel;
+
+impl Component for AudioFxPanel {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        let border_style = if app.focus == Panel::AudioFx {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+
+        let block = Block::default()
+            .title(" Audio FX ")
+            .borders(Borders::ALL)
+            .border_style(border_style);
+
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        if inner.width == 0 || inner.height < 2 {
+            return;
+        }
+
+        let eq_row = |label, band| {
+            let gain = app.eq_gain(band);
+            (label, gain, EQ_GAIN_MAX, format!("{:.1}x", gain))
+        };
+        let controls: [(&str, f32, f32, String); FX_LIMITER_ROW] = [
+            (
+                "Noise:",
+                app.comfort_noise(),
+                COMFORT_NOISE_MAX,
+                // White is the default, so only the other colors are named
+                match app.noise_kind() {
+                    NoiseKind::White => format!("{:.3}", app.comfort_noise()),
+                    NoiseKind::Off => "off".to_string(),
+                    kind => format!("{:.3} {}", app.comfort_noise(), kind.name()),
+                },
+            ),
+            eq_row("EQ Low:", EqBand::Low),
+            eq_row("EQ Mid:", EqBand::Mid),
+            eq_row("EQ Hi:", EqBand::High),
+            (
+                "Fade:",
+                app.fade_secs(),
+                FADE_SECS_MAX,
+                format!("{:.0}ms", app.fade_secs() * 1000.0),
+            ),
+        ];
+
+        for (idx, (label, value, max, ref value_str)) in controls.iter().enumerate() {
+            let y = inner.y + idx as u16;
+            if y >= inner.y + inner.height {
+                break;
+            }
+
+            let label_width = 7u16;
+            let value_label_width = value_str.len() as u16 + 1;
+            let bar_width = inner
+                .width
+                .saturating_sub(label_width + value_label_width + 1);
+
+            let is_selected = app.focus == Panel::AudioFx && app.selected_fx == idx;
+
+            let label_style = if is_selected {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let label_span = Span::styled(format!("{:<7}", label), label_style);
+
+            let ratio = if *max > 0.0 { value / max } else { 0.0 };
+            let filled = (ratio * bar_width as f32).round() as u16;
+
+            let bar_spans: Vec<Span> = (0..bar_width)
+                .map(|i| {
+                    if i < filled {
+                        Span::styled("\u{2588}", Style::default().fg(Color::Magenta))
+                    } else {
+                        Span::styled("\u{2591}", Style::default().fg(Color::DarkGray))
+                    }
+                })
+                .collect();
+
+            let val_span =
+                Span::styled(format!(" {}", value_str), Style::default().fg(Color::White));
+
+            let mut spans = vec![label_span];
+            spans.extend(bar_spans);
+            spans.push(val_span);
+
+            let line = Line::from(spans);
+            let row_area = Rect::new(inner.x, y, inner.width, 1);
+            f.render_widget(Paragraph::new(line), row_area);
+        }
+
+        let limiter_y = inner.y + FX_LIMITER_ROW as u16;
+        if inner.height as usize > FX_LIMITER_ROW {
+            let selected = app.focus == Panel::AudioFx && app.selected_fx == FX_LIMITER_ROW;
+            let label_style = if selected {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let (state, state_color) = if app.limiter() {
+                ("[x] on", Color::Green)
+            } else {
+                ("[ ] off", Color::DarkGray)
+            };
+            let line = Line::from(vec![
+                Span::styled(format!("{:<7}", "Limit:"), label_style),
+                Span::styled(state, Style::default().fg(state_color)),
+            ]);
+            f.render_widget(
+                Paragraph::new(line),
+                Rect::new(inner.x, limiter_y, inner.width, 1),
+            );
+        }
+
+        if inner.height as usize > FX_LIMITER_ROW + 1 {
+            let mode = if app.state.continuous_noise {
+                "continuous"
+            } else {
+                "during clips"
+            };
+            let line = Line::from(vec![
+                Span::styled(format!("{:<7}", "Mode:"), Style::default().fg(Color::White)),
+                Span::styled(
+                    format!("noise {}", mode),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]);
+            let mode_area = Rect::new(inner.x, limiter_y + 1, inner.width, 1);
+            f.render_widget(Paragraph::new(line), mode_area);
+        }
+    }
+
+    fn hit(&self, app: &ClientApp, pos: Position) -> Option<Action> {
+        let area = app.layout.audio_fx_area;
+        if !area.contains(pos) {
+            return None;
+        }
+        let (inner_x, inner_y) = inner_offset(area, pos);
+        let row = inner_y as usize;
+        if row > FX_LIMITER_ROW {
+            return Some(Action::Focus(Panel::AudioFx));
+        }
+        // Sliders take the click's share of the whole row, label included
+        let inner_width = area.width.saturating_sub(2);
+        let ratio =
+            (row < FX_LIMITER_ROW && inner_width > 0).then(|| inner_x as f32 / inner_width as f32);
+        Some(Action::Fx { row, ratio })
+    }
+}
@@ -0,0 +1,677 @@
+//! Each part of the screen is a [`Component`] that draws itself and answers
+//! clicks in the area it was last drawn in, so the row math for drawing and
+//! for hit-testing sits side by side.
+
+#[cfg(feature = "transcriber")]
+mod bindings;
+mod fx;
+mod overlays;
+mod sinks;
+mod songs;
+mod volume;
+
+use crate::client::{AppLayout, ClientApp, Panel, SetupStep};
+use crate::protocol::SongInfo;
+use crate::theme;
+use fx::AudioFxPanel;
+use overlays::Overlays;
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Position, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Wrap},
+    Frame,
+};
+use sinks::SinksPanel;
+use songs::{AddButton, PlaybackProgress, QueuePanel, SongList, WaveformStrip};
+use volume::VolumePanel;
+
+#[cfg(feature = "transcriber")]
+use crate::client::TranscriberOverlay;
+#[cfg(feature = "transcriber")]
+use bindings::WordBindingsPanel;
+#[cfg(feature = "transcriber")]
+use songs::WordDetectorButton;
+
+/// Smallest terminal the normal layout is drawn in.
+pub const MIN_WIDTH: u16 = 60;
+pub const MIN_HEIGHT: u16 = 15;
+
+pub trait Component {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect);
+
+    /// What a left click at `pos` does, or `None` when it lands outside.
+    fn hit(&self, _app: &ClientApp, _pos: Position) -> Option<Action> {
+        None
+    }
+}
+
+/// Result of clicking a component; every action also focuses its panel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Just focus, e.g. a click on a header or an empty row.
+    Focus(Panel),
+    SelectSink(usize),
+    SetVolume(f32),
+    /// Select an Audio FX row; a slider also jumps to `ratio` of its range.
+    Fx {
+        row: usize,
+        ratio: Option<f32>,
+    },
+    /// Press a button.
+    Activate(Panel),
+    PlaySong(usize),
+    /// Highlight the entry at this position of the queue.
+    SelectQueued(usize),
+    #[cfg(feature = "transcriber")]
+    SelectBinding(usize),
+}
+
+/// Clickable components, in the order clicks are offered to them.
+const CLICKABLE: &[&dyn Component] = &[
+    &SinksPanel,
+    &VolumePanel,
+    &AudioFxPanel,
+    &AddButton,
+    #[cfg(feature = "transcriber")]
+    &WordDetectorButton,
+    #[cfg(feature = "transcriber")]
+    &WordBindingsPanel,
+    &SongList,
+    &QueuePanel,
+];
+
+/// Action for a left click at `pos` on the main screen.
+pub fn hit_test(app: &ClientApp, pos: Position) -> Option<Action> {
+    CLICKABLE
+        .iter()
+        .find_map(|component| component.hit(app, pos))
+}
+
+/// Offset of `pos` from the top-left inside a bordered `area`; clicks on the
+/// border count as the nearest inner row or column.
+fn inner_offset(area: Rect, pos: Position) -> (u16, u16) {
+    (
+        pos.x.saturating_sub(area.x + 1),
+        pos.y.saturating_sub(area.y + 1),
+    )
+}
+
+pub fn draw(f: &mut Frame, app: &mut ClientApp) {
+    let size = f.area();
+
+    if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+        // Nothing is clickable until the terminal grows again
+        app.layout = AppLayout::default();
+        draw_too_small(f, size);
+        return;
+    }
+
+    let outer = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(size);
+
+    let main_area = outer[0];
+    let help_area = outer[1];
+
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(main_area);
+
+    let left_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(4),
+            Constraint::Length(9),
+        ])
+        .split(main_chunks[0]);
+
+    app.layout.sinks_area = left_chunks[0];
+    app.layout.volume_area = left_chunks[1];
+    app.layout.audio_fx_area = left_chunks[2];
+
+    SinksPanel.draw(f, app, left_chunks[0]);
+    VolumePanel.draw(f, app, left_chunks[1]);
+    AudioFxPanel.draw(f, app, left_chunks[2]);
+    draw_right_panel(f, app, main_chunks[1]);
+
+    // Help text / status bar
+    if let Some(msg) = &app.status_message {
+        let help = Paragraph::new(Line::from(Span::styled(
+            msg.as_str(),
+            Style::default().fg(Color::Red),
+        )));
+        f.render_widget(help, help_area);
+    } else if app.disconnected() {
+        let banner = Paragraph::new(Line::from(Span::styled(
+            "Daemon not reachable, reconnecting...  [q] Quit",
+            Style::default().fg(Color::Red),
+        )));
+        f.render_widget(banner, help_area);
+    } else {
+        let help_text = help_text_for_state(app);
+        let help = Paragraph::new(Line::from(Span::styled(
+            help_text,
+            Style::default().fg(Color::DarkGray),
+        )));
+        f.render_widget(help, help_area);
+    }
+
+    Overlays.draw(f, app, size);
+}
+
+fn draw_right_panel(f: &mut Frame, app: &mut ClientApp, area: Rect) {
+    // The progress row only exists while a song is playing, the waveform
+    // row while the highlighted song's envelope has been fetched. The queue
+    // shows up to five entries under what is playing.
+    let progress = app.progress.filter(|_| app.now_playing().is_some());
+    let waveform = app.selected_waveform().is_some();
+    let queue_rows = if app.show_queue {
+        app.state.queue.len().min(5) as u16 + 3
+    } else {
+        0
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(queue_rows),
+            Constraint::Length(u16::from(waveform)),
+            Constraint::Length(u16::from(progress.is_some())),
+        ])
+        .split(area);
+
+    let button_row = chunks[0];
+    let songs_area = chunks[1];
+    app.layout.songs_area = songs_area;
+    app.layout.queue_area = chunks[2];
+    if app.show_queue {
+        QueuePanel.draw(f, app, chunks[2]);
+    }
+    if waveform {
+        WaveformStrip.draw(f, app, chunks[3]);
+    }
+    if progress.is_some() {
+        PlaybackProgress.draw(f, app, chunks[4]);
+    }
+
+    #[cfg(feature = "transcriber")]
+    {
+        // Split button row: AddButton (50%) | WordDetectorButton (50%)
+        let btn_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(button_row);
+
+        app.layout.add_button_area = btn_chunks[0];
+        app.layout.word_detector_button_area = btn_chunks[1];
+
+        AddButton.draw(f, app, btn_chunks[0]);
+        WordDetectorButton.draw(f, app, btn_chunks[1]);
+
+        if app.panel_shown(Panel::WordBindings) {
+            let h_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .split(songs_area);
+            app.layout.songs_area = h_chunks[0];
+            app.layout.word_bindings_area = h_chunks[1];
+            SongList.draw(f, app, h_chunks[0]);
+            WordBindingsPanel.draw(f, app, h_chunks[1]);
+            return;
+        }
+    }
+
+    #[cfg(not(feature = "transcriber"))]
+    {
+        app.layout.add_button_area = button_row;
+        AddButton.draw(f, app, button_row);
+    }
+
+    SongList.draw(f, app, songs_area);
+}
+
+fn draw_too_small(f: &mut Frame, area: Rect) {
+    let msg = format!(
+        "terminal too small: need {MIN_WIDTH}\u{d7}{MIN_HEIGHT}, have {}\u{d7}{}",
+        area.width, area.height
+    );
+    // Rough vertical centering; long messages wrap downwards
+    let lines = (msg.len() as u16).div_ceil(area.width.max(1));
+    let top = area.height.saturating_sub(lines) / 2;
+    let msg_area = Rect {
+        y: area.y + top,
+        height: area.height - top,
+        ..area
+    };
+    let paragraph = Paragraph::new(msg)
+        .style(Style::default().fg(Color::Yellow))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, msg_area);
+}
+
+fn help_text_for_state(app: &ClientApp) -> &'static str {
+    match app.setup {
+        Some(SetupStep::PickSink { .. }) => {
+            return "[Up/Down] Navigate  [Enter] Use this output  [Esc] Skip";
+        }
+        Some(SetupStep::AddSongs) => {
+            return "[Up/Down] Navigate  [Enter] Open / add file  [a] Add this folder  [Backspace] Parent dir  [Esc] Skip";
+        }
+        #[cfg(feature = "transcriber")]
+        Some(SetupStep::EnableDetector) => return "[Enter] Download model  [Esc] Skip",
+        Some(SetupStep::Finish) => return "[Enter] Finish",
+        None => {}
+    }
+    if app.pending_delete.is_some() {
+        return "[y/Enter] Delete  [n/Esc] Keep";
+    }
+    if app.file_browser.is_some() {
+        return "[Up/Down] Navigate  [Enter] Open  [a] Add folder  [A] With subfolders  [Backspace] Parent dir  [Esc] Close";
+    }
+    #[cfg(feature = "transcriber")]
+    if let Some(TranscriberOverlay::SelectSource { .. }) = app.transcriber_overlay {
+        return "[Up/Down] Navigate  [Enter] Select  [m] Mute/unmute all bindings  [Esc] Close";
+    }
+    #[cfg(feature = "transcriber")]
+    if let Some(TranscriberOverlay::PickSong { .. }) = app.transcriber_overlay {
+        return "[Up/Down] Navigate  [Space] Add/remove song  [Tab] Random/round robin  [Enter] Bind  [Esc] Close";
+    }
+    #[cfg(feature = "transcriber")]
+    if app.transcriber_overlay.is_some() {
+        return "[Up/Down] Navigate  [Enter] Select  [Esc] Close";
+    }
+    #[cfg(feature = "transcriber")]
+    if app.focus == Panel::WordBindings {
+        return "[Left/Right] Switch panel  [Up/Down] Navigate  [Enter] Test binding  [Space] On/off  [d] Delete binding  [x] Reset stats  [o] Own input only  [p] Power mode  [Tab/Shift+Tab] Cycle panels";
+    }
+    if app.focus == Panel::Sinks {
+        return "[Left/Right] Switch panel  [Up/Down] Navigate  [Enter] Select  [m] Monitor output  [d] Delete song  [r] Refresh  [n] Noise mode  [s] Duck on talk  [Tab/Shift+Tab] Cycle  [q] Quit";
+    }
+    if app.focus == Panel::Queue {
+        return "[Left/Right] Switch panel  [Up/Down] Navigate  [Shift+Up/Down] Move entry  [d] Remove entry  [C] Clear queue  [Q] Hide queue  [Tab/Shift+Tab] Cycle  [q] Quit";
+    }
+    if app.focus == Panel::Songs {
+        return "[Left/Right] Switch panel  [Up/Down] Navigate  [Enter] Play  [Space] Pause  [S] Stop  [e] Queue  [E] Clear queue  [Q] Show queue  [l] Loop  [d] Delete song  [Shift+Up/Down] Move song  [o] Sort  [c] Color tag  [v] Waveform  [+/-] Song volume  [F] Filter by tag  [Tab/Shift+Tab] Cycle  [q] Quit";
+    }
+    "[Left/Right] Switch panel  [Up/Down] Navigate  [Enter] Select  [d] Delete song  [r] Refresh  [n] Noise mode  [s] Duck on talk  [Tab/Shift+Tab] Cycle  [q] Quit"
+}
+
+/// Colored dot for a tagged song, nothing otherwise.
+fn tag_prefix(app: &ClientApp, song: &SongInfo) -> Vec<Span<'static>> {
+    match &song.tag_color {
+        Some(tag) => {
+            let color = theme::tag_color(&app.state.tag_palette, tag);
+            vec![Span::styled("\u{25cf} ", Style::default().fg(color))]
+        }
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::FX_LIMITER_ROW;
+    use crate::protocol::{send_message, DaemonEvent, DaemonState, NoiseKind, SinkInfo};
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+
+    fn sink(id: u32, kind: &str, description: &str) -> SinkInfo {
+        SinkInfo {
+            id,
+            name: format!("node{id}"),
+            description: description.to_string(),
+            kind: kind.to_string(),
+            is_default: false,
+        }
+    }
+
+    fn song(name: &str, tag_color: Option<&str>, volume: Option<f32>) -> SongInfo {
+        SongInfo {
+            path: format!("/sounds/{name}"),
+            name: name.to_string(),
+            undecodable: name.ends_with(".ogg"),
+            missing: false,
+            tag_color: tag_color.map(str::to_string),
+            volume,
+            duration: None,
+        }
+    }
+
+    /// A connected client showing a small library mid-playback.
+    fn scene(edit: impl FnOnce(&mut DaemonState)) -> ClientApp {
+        let mut state = DaemonState {
+            sinks: vec![
+                sink(1, "Input", "Built-in Microphone"),
+                sink(2, "Output", "Speakers"),
+                sink(
+                    3,
+                    "Output",
+                    "HDMI / DisplayPort 2 Output (Navi 21 High Definition Audio)",
+                ),
+            ],
+            songs: vec![
+                SongInfo {
+                    duration: Some(2.4),
+                    ..song("airhorn.wav", Some("green"), Some(1.5))
+                },
+                SongInfo {
+                    duration: Some(40.2),
+                    ..song("drumroll.mp3", None, None)
+                },
+                song("broken.ogg", None, None),
+            ],
+            songs_total: 3,
+            selected_sink: 1,
+            selected_song: 1,
+            volume: 1.2,
+            comfort_noise: 0.01,
+            fade_secs: 0.2,
+            limiter: true,
+            now_playing: Some("drumroll.mp3".to_string()),
+            queue: vec!["/sounds/airhorn.wav".to_string()],
+            loop_enabled: true,
+            ..Default::default()
+        };
+        edit(&mut state);
+        let (client, mut daemon) = UnixStream::pair().unwrap();
+        send_message(&mut daemon, &DaemonEvent::State(Box::new(state))).unwrap();
+        let mut app = ClientApp::new(client).unwrap();
+        app.focus = Panel::Songs;
+        app.progress = Some((12.0, 40.0));
+        app.meter.rms = 0.2;
+        app.meter.peak_hold = 0.4;
+        app
+    }
+
+    /// The screen's text, then how it is styled: one line per run of cells
+    /// on a row sharing foreground, background and modifiers, so a color
+    /// change shows up in the golden file as well.
+    fn render(app: &mut ClientApp, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        let frame = terminal.draw(|f| draw(f, app)).unwrap();
+        let buffer = frame.buffer;
+        let (width, height) = (buffer.area.width, buffer.area.height);
+        let mut text = String::new();
+        for y in 0..height {
+            let row: String = (0..width).map(|x| buffer[(x, y)].symbol()).collect();
+            text.push_str(row.trim_end());
+            text.push('\n');
+        }
+        text.push_str("--- styles\n");
+        let style = |x, y| {
+            let cell = &buffer[(x, y)];
+            (cell.fg, cell.bg, cell.modifier)
+        };
+        for y in 0..height {
+            let mut x = 0;
+            while x < width {
+                let (start, run) = (x, style(x, y));
+                while x < width && style(x, y) == run {
+                    x += 1;
+                }
+                let (fg, bg, modifier) = run;
+                let end = x - 1;
+                text.push_str(&format!("{y} {start}-{end} fg={fg} bg={bg} {modifier:?}\n"));
+            }
+        }
+        text
+    }
+
+    /// Compare a rendered screen with `tests/golden/<name>.txt`. Run with
+    /// `UPDATE_GOLDEN=1` to rewrite the file after an intended change.
+    fn assert_golden(name: &str, screen: &str) {
+        let suffix = if cfg!(feature = "transcriber") {
+            "-transcriber"
+        } else {
+            ""
+        };
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{name}{suffix}.txt"));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, screen).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("{}: {e}; run with UPDATE_GOLDEN=1", path.display()));
+        assert!(
+            expected == screen,
+            "{name} differs from {}:\n{screen}",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn main_screen_matches_golden() {
+        let mut app = scene(|_| {});
+        assert_golden("main", &render(&mut app, 80, 24));
+    }
+
+    #[test]
+    fn audio_fx_focus_matches_golden() {
+        let mut app = scene(|state| {
+            state.sidechain_enabled = true;
+            state.continuous_noise = true;
+            state.noise_kind = NoiseKind::Pink;
+            state.paused = true;
+        });
+        app.focus = Panel::AudioFx;
+        app.selected_fx = 2;
+        assert_golden("audio_fx", &render(&mut app, 80, 24));
+    }
+
+    #[test]
+    fn long_device_names_get_a_tooltip_matching_golden() {
+        let mut app = scene(|state| state.selected_sink = 2);
+        app.focus = Panel::Sinks;
+        assert_golden("sinks_tooltip", &render(&mut app, 60, 24));
+    }
+
+    #[test]
+    fn empty_and_too_small_screens_match_golden() {
+        let mut app = scene(|state| *state = DaemonState::default());
+        app.progress = None;
+        assert_golden("empty", &render(&mut app, 70, 20));
+        assert_golden("too_small", &render(&mut app, 50, 12));
+    }
+
+    #[test]
+    fn missing_songs_are_crossed_out() {
+        let mut app = scene(|state| state.songs[0].missing = true);
+        let screen = render(&mut app, 80, 24);
+        assert!(
+            screen.contains("\u{2717} airhorn.wav (1.5x) (missing)"),
+            "{screen}"
+        );
+    }
+
+    #[test]
+    fn waveform_strip_matches_golden() {
+        let mut app = scene(|_| {});
+        let rise = (0..=255u8).step_by(8);
+        let peaks = rise.clone().chain(rise.rev()).collect();
+        app.waveform = Some(("/sounds/drumroll.mp3".to_string(), peaks));
+        assert_golden("waveform", &render(&mut app, 80, 24));
+    }
+
+    #[test]
+    fn queue_panel_matches_golden() {
+        let mut app = scene(|state| state.queue.push("/sounds/broken.ogg".to_string()));
+        app.show_queue = true;
+        app.focus = Panel::Queue;
+        app.selected_queue = 1;
+        assert_golden("queue", &render(&mut app, 80, 24));
+    }
+
+    #[test]
+    fn delete_confirmation_matches_golden() {
+        let mut app = scene(|_| {});
+        let path = app.songs()[0].path.clone();
+        app.pending_delete = Some(crate::client::PendingDelete::Song(path));
+        assert_golden("confirm_delete", &render(&mut app, 80, 24));
+    }
+
+    #[test]
+    fn setup_wizard_matches_golden() {
+        let mut app = scene(|_| {});
+        app.setup = Some(SetupStep::PickSink { selected: 1 });
+        assert_golden("setup", &render(&mut app, 80, 24));
+    }
+
+    #[test]
+    fn system_default_entry_stands_out() {
+        use crate::protocol::DEFAULT_SINK_ID;
+        let mut app = scene(|state| {
+            let mut speakers = sink(2, "Output", "Speakers");
+            speakers.is_default = true;
+            let mut default = sink(DEFAULT_SINK_ID, "Output", "System Default");
+            default.name = "@DEFAULT_AUDIO_SINK@".to_string();
+            state.sinks = vec![default, speakers, sink(1, "Input", "Built-in Microphone")];
+            state.selected_sink = 0;
+        });
+        let mut terminal = Terminal::new(TestBackend::new(140, 24)).unwrap();
+        let buffer = terminal.draw(|f| draw(f, &mut app)).unwrap().buffer.clone();
+        let row = |y: u16| -> String {
+            (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect()
+        };
+        // Row 1 is the Outputs header
+        assert!(row(2).contains("[Sys] System Default"), "{}", row(2));
+        assert!(row(3).contains("[Out] Speakers (default)"), "{}", row(3));
+        let italic = |text: &str, y: u16| {
+            let x = row(y).find(text).unwrap() as u16;
+            buffer[(x, y)]
+                .modifier
+                .contains(ratatui::style::Modifier::ITALIC)
+        };
+        assert!(italic("System", 2));
+        assert!(!italic("Speakers", 3));
+    }
+
+    #[test]
+    fn clicks_land_on_what_was_drawn_there() {
+        let mut app = scene(|_| {});
+        render(&mut app, 80, 24);
+        let at = |area: Rect, x: u16, y: u16| hit_test(&app, Position::new(area.x + x, area.y + y));
+
+        // Row 1 is the Outputs header, row 2 the selected Speakers
+        let sinks = app.layout.sinks_area;
+        assert_eq!(at(sinks, 3, 1), Some(Action::Focus(Panel::Sinks)));
+        assert_eq!(at(sinks, 3, 2), Some(Action::SelectSink(1)));
+
+        let fx = app.layout.audio_fx_area;
+        let limiter = 1 + FX_LIMITER_ROW as u16;
+        assert_eq!(
+            at(fx, 5, limiter),
+            Some(Action::Fx {
+                row: FX_LIMITER_ROW,
+                ratio: None
+            })
+        );
+        assert_eq!(at(fx, 5, limiter + 1), Some(Action::Focus(Panel::AudioFx)));
+        let Some(Action::Fx {
+            row: 2,
+            ratio: Some(ratio),
+        }) = at(fx, 12, 3)
+        else {
+            panic!("EQ Mid slider not hit");
+        };
+        assert!((ratio - 0.5).abs() < 0.01, "{ratio}");
+
+        let songs = app.layout.songs_area;
+        assert_eq!(at(songs, 3, 2), Some(Action::PlaySong(1)));
+        assert_eq!(at(songs, 3, 6), Some(Action::Focus(Panel::Songs)));
+        assert_eq!(
+            at(app.layout.add_button_area, 4, 1),
+            Some(Action::Activate(Panel::AddButton))
+        );
+
+        // The help bar and the progress gauge take no clicks
+        assert_eq!(hit_test(&app, Position::new(10, 23)), None);
+        assert_eq!(hit_test(&app, Position::new(40, 22)), None);
+    }
+
+    #[test]
+    fn long_song_lists_keep_the_selection_in_view_and_clicks_follow() {
+        for height in [MIN_HEIGHT, 20, 24, 40] {
+            let mut app = scene(|state| {
+                state.songs = (0..60)
+                    .map(|i| song(&format!("clip{i}.wav"), None, None))
+                    .collect();
+                state.songs_total = 60;
+                state.selected_song = 59;
+            });
+            render(&mut app, 80, height);
+            let songs = app.layout.songs_area;
+            let rows = songs.height - 2;
+            let row = |y: u16| Position::new(songs.x + 3, songs.y + 1 + y);
+            assert_eq!(app.song_scroll, 60 - rows as usize, "height {height}");
+            assert_eq!(hit_test(&app, row(rows - 1)), Some(Action::PlaySong(59)));
+            assert_eq!(
+                hit_test(&app, row(0)),
+                Some(Action::PlaySong(60 - rows as usize))
+            );
+
+            // Back at the top the list scrolls back with it
+            app.state.selected_song = 0;
+            render(&mut app, 80, height);
+            assert_eq!(app.song_scroll, 0, "height {height}");
+            assert_eq!(hit_test(&app, row(0)), Some(Action::PlaySong(0)));
+        }
+    }
+
+    #[cfg(feature = "transcriber")]
+    #[test]
+    fn word_bindings_and_overlays_match_golden() {
+        use crate::protocol::{
+            DetectorPowerMode, SongSelection, WordDetectorStatus, WordMapping, WordStats,
+        };
+        let mapping = |word: &str, enabled| WordMapping {
+            word: word.to_string(),
+            song_names: vec!["drumroll.mp3".to_string()],
+            song_paths: vec!["/sounds/drumroll.mp3".to_string()],
+            selection: Default::default(),
+            source_description: "Built-in Microphone".to_string(),
+            output_description: String::new(),
+            only_from_source: None,
+            stats: WordStats {
+                fired: 3,
+                ..Default::default()
+            },
+            enabled,
+            action: Default::default(),
+        };
+        let mut app = scene(|state| {
+            state.word_detector_status = WordDetectorStatus::Running;
+            let mut kit = mapping("kit", true);
+            kit.song_names
+                .extend(["snare.wav".to_string(), "cymbal.wav".to_string()]);
+            kit.song_paths
+                .extend(["/sounds/snare.wav", "/sounds/cymbal.wav"].map(String::from));
+            kit.only_from_source = Some("Built-in Microphone".to_string());
+            state.word_mappings = vec![mapping("drum", true), mapping("roll", false), kit];
+            state.detector_source = Some("Built-in Microphone".to_string());
+            state.detector_power_mode = DetectorPowerMode::Efficiency;
+        });
+        app.focus = Panel::WordBindings;
+        assert_golden("word_bindings", &render(&mut app, 100, 30));
+
+        app.transcriber_overlay = Some(TranscriberOverlay::PickSong {
+            word: "drum".to_string(),
+            selected: 1,
+            chosen: vec![0, 1],
+            selection: SongSelection::RoundRobin,
+        });
+        assert_golden("pick_song", &render(&mut app, 100, 30));
+    }
+}
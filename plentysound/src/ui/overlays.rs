@@ -0,0 +1,411 @@
+use super::Component;
+use crate::client::{ClientApp, PendingDelete, SetupStep};
+use crate::filebrowser::FileBrowser;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+#[cfg(feature = "transcriber")]
+use super::tag_prefix;
+#[cfg(feature = "transcriber")]
+use crate::client::TranscriberOverlay;
+#[cfg(feature = "transcriber")]
+use crate::protocol::SongSelection;
+#[cfg(feature = "transcriber")]
+use ratatui::text::{Line, Span};
+
+/// Popups drawn over the whole screen: the file browser, the setup wizard,
+/// the word detector dialogs and the delete confirmation. They take no
+/// clicks; the keyboard drives them while they are open.
+pub struct Overlays;
+
+impl Component for Overlays {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        if app.file_browser.is_some() {
+            let popup_area = centered_rect(60, 70, area);
+            app.layout.file_browser_area = popup_area;
+            if let Some(fb) = &mut app.file_browser {
+                draw_file_browser(f, fb, popup_area);
+            }
+        }
+
+        if let Some(step) = app.setup {
+            if step != SetupStep::AddSongs {
+                draw_setup_overlay(f, app, area, step);
+            }
+        }
+
+        #[cfg(feature = "transcriber")]
+        if let Some(overlay) = &app.transcriber_overlay {
+            match overlay {
+                TranscriberOverlay::SelectSource { selected } => {
+                    draw_source_select_overlay(f, app, area, *selected);
+                }
+                TranscriberOverlay::SelectOutput { selected } => {
+                    draw_output_select_overlay(f, app, area, *selected);
+                }
+                TranscriberOverlay::EnterWord { input } => {
+                    draw_word_input_overlay(f, area, input);
+                }
+                TranscriberOverlay::PickSong {
+                    word,
+                    selected,
+                    chosen,
+                    selection,
+                } => {
+                    draw_song_picker_overlay(f, app, area, word, *selected, chosen, *selection);
+                }
+            }
+        }
+
+        if let Some(target) = &app.pending_delete {
+            draw_delete_overlay(f, app, area, target);
+        }
+    }
+}
+
+fn draw_delete_overlay(f: &mut Frame, app: &ClientApp, area: Rect, target: &PendingDelete) {
+    let what = match target {
+        PendingDelete::Song(path) => {
+            let song = app.songs().iter().find(|song| song.path == *path);
+            format!("'{}'", song.map_or("", |song| song.name.as_str()))
+        }
+        #[cfg(feature = "transcriber")]
+        PendingDelete::WordBinding(idx) => format!(
+            "the binding for '{}'",
+            app.state
+                .word_mappings
+                .get(*idx)
+                .map_or("", |wm| wm.word.as_str())
+        ),
+    };
+    let popup_area = centered_rect(40, 20, area);
+    let popup_area = Rect {
+        height: popup_area.height.max(5),
+        ..popup_area
+    };
+    f.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Delete ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red));
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+
+    if inner.width > 0 && inner.height > 1 {
+        let question = Paragraph::new(format!("Delete {what}? y/N")).wrap(Wrap { trim: true });
+        f.render_widget(
+            question,
+            Rect {
+                y: inner.y + 1,
+                height: inner.height - 1,
+                ..inner
+            },
+        );
+    }
+}
+
+fn draw_file_browser(f: &mut Frame, fb: &mut FileBrowser, popup_area: Rect) {
+    f.render_widget(Clear, popup_area);
+
+    let title = format!(" {} ", fb.current_dir.display());
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+
+    let items: Vec<ListItem> = fb
+        .entries
+        .iter()
+        .map(|entry| {
+            if entry.is_dir {
+                ListItem::new(format!("\u{1f4c1} {}/", entry.name))
+                    .style(Style::default().fg(Color::Blue))
+            } else {
+                ListItem::new(format!("  {}", entry.name))
+            }
+        })
+        .collect();
+
+    let mut state = ListState::default().with_offset(fb.scroll);
+    if !fb.entries.is_empty() {
+        state.select(Some(fb.selected));
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_stateful_widget(list, popup_area, &mut state);
+    fb.scroll = state.offset();
+}
+
+#[cfg(feature = "transcriber")]
+fn draw_source_select_overlay(
+    f: &mut Frame,
+    app: &ClientApp,
+    area: Rect,
+    selected: usize,
+) {
+    let popup_area = centered_rect(50, 50, area);
+    f.render_widget(Clear, popup_area);
+
+    let title = if app.state.word_mappings_muted {
+        " Select Audio Source — all bindings muted "
+    } else {
+        " Select Audio Source "
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+
+    let input_sinks: Vec<_> = app.sinks().iter().filter(|s| s.is_capture()).collect();
+
+    let items: Vec<ListItem> = input_sinks
+        .iter()
+        .map(|sink| {
+            let prefix = if sink.kind == "Source" {
+                "[Mic] "
+            } else {
+                "[App] "
+            };
+            ListItem::new(format!("  {}{}", prefix, sink.description))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    if !input_sinks.is_empty() {
+        state.select(Some(selected.min(input_sinks.len().saturating_sub(1))));
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+#[cfg(feature = "transcriber")]
+fn draw_output_select_overlay(
+    f: &mut Frame,
+    app: &ClientApp,
+    area: Rect,
+    selected: usize,
+) {
+    let popup_area = centered_rect(50, 50, area);
+    f.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Select Audio Output ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+
+    let output_sinks: Vec<_> = app.sinks().iter().filter(|s| s.kind == "Output").collect();
+
+    let items: Vec<ListItem> = output_sinks
+        .iter()
+        .map(|sink| ListItem::new(format!("  {}", sink.description)))
+        .collect();
+
+    let mut state = ListState::default();
+    if !output_sinks.is_empty() {
+        state.select(Some(selected.min(output_sinks.len().saturating_sub(1))));
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+#[cfg(feature = "transcriber")]
+fn draw_word_input_overlay(
+    f: &mut Frame,
+    area: Rect,
+    input: &crate::textinput::TextInput,
+) {
+    let popup_area = centered_rect(40, 20, area);
+    // Ensure minimum height of 5
+    let popup_area = Rect {
+        height: popup_area.height.max(5),
+        ..popup_area
+    };
+    f.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Enter Word to Detect ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+
+    if inner.width > 0 && inner.height > 0 {
+        let text = format!("> {}_", input.as_str());
+        let paragraph = Paragraph::new(Line::from(Span::styled(
+            text,
+            Style::default().fg(Color::White),
+        )));
+        f.render_widget(paragraph, Rect::new(inner.x, inner.y + 1, inner.width, 1));
+
+        let hint = Paragraph::new(Line::from(Span::styled(
+            "Type a word, then press Enter",
+            Style::default().fg(Color::DarkGray),
+        )));
+        if inner.height > 2 {
+            f.render_widget(
+                hint,
+                Rect::new(inner.x, inner.y + inner.height - 1, inner.width, 1),
+            );
+        }
+    }
+}
+
+#[cfg(feature = "transcriber")]
+fn draw_song_picker_overlay(
+    f: &mut Frame,
+    app: &ClientApp,
+    area: Rect,
+    word: &str,
+    selected: usize,
+    chosen: &[usize],
+    selection: SongSelection,
+) {
+    let popup_area = centered_rect(50, 50, area);
+    f.render_widget(Clear, popup_area);
+
+    let mut title = match chosen.len() {
+        0 => format!(" Pick Song for \"{}\" ", word),
+        n => format!(
+            " Pick Songs for \"{}\" — {} chosen, {} ",
+            word,
+            n,
+            selection.name()
+        ),
+    };
+    if let Some(filter) = app.song_filter.as_ref().filter(|f| !f.input.is_empty()) {
+        title.push_str(&format!("/{} ", filter.input.as_str()));
+    }
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+
+    let order = app.song_order();
+    let items: Vec<ListItem> = order
+        .iter()
+        .map(|&i| {
+            let song = &app.songs()[i];
+            let mark = if chosen.contains(&i) { "[x] " } else { "[ ] " };
+            let mut spans = vec![Span::raw(mark)];
+            spans.extend(tag_prefix(app, song));
+            spans.push(Span::raw(song.name.as_str()));
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    if !order.is_empty() {
+        state.select(Some(selected.min(order.len() - 1)));
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+fn draw_setup_overlay(f: &mut Frame, app: &ClientApp, area: Rect, step: SetupStep) {
+    let popup_area = centered_rect(50, 50, area);
+    f.render_widget(Clear, popup_area);
+
+    let (n, total) = step.progress();
+    let block = Block::default()
+        .title(format!(" Setup ({}/{}) ", n, total))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+
+    let intro = match step {
+        SetupStep::PickSink { .. } => "Pick the output songs should play on:",
+        #[cfg(feature = "transcriber")]
+        SetupStep::EnableDetector => {
+            "Enable the word detector? It needs a speech model, downloaded once in the background."
+        }
+        SetupStep::Finish => "All set. Press Enter to save and start using plentysound.",
+        SetupStep::AddSongs => "",
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)])
+        .split(inner);
+    f.render_widget(Paragraph::new(intro).wrap(Wrap { trim: true }), chunks[0]);
+
+    if let SetupStep::PickSink { selected } = step {
+        let outputs = app.output_sinks();
+        let items: Vec<ListItem> = outputs
+            .iter()
+            .map(|&i| ListItem::new(format!("  {}", app.sinks()[i].description)))
+            .collect();
+        let mut state = ListState::default();
+        if !outputs.is_empty() {
+            state.select(Some(selected.min(outputs.len() - 1)));
+        }
+        let list = List::new(items)
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+        f.render_stateful_widget(list, chunks[1], &mut state);
+    }
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
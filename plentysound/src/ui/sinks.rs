@@ -0,0 +1,168 @@
+use super::{inner_offset, Action, Component};
+use crate::client::{ClientApp, Panel, SinkRow};
+use crate::protocol::{SinkInfo, DEFAULT_SINK_ID};
+use ratatui::{
+    layout::{Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+/// Devices grouped under kind headers, with the selected name shown in full
+/// as a tooltip when the panel cuts it off.
+pub struct SinksPanel;
+
+impl Component for SinksPanel {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        let border_style = if app.focus == Panel::Sinks {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+
+        let rows = app.sink_rows();
+        let selected_row = rows
+            .iter()
+            .position(|row| *row == SinkRow::Sink(app.selected_sink()));
+
+        let total = rows
+            .iter()
+            .filter(|row| matches!(row, SinkRow::Sink(_)))
+            .count();
+        let title = match selected_row {
+            Some(row) => {
+                // Rank among sinks, not rows: headers above it don't count
+                let rank = rows[..=row]
+                    .iter()
+                    .filter(|r| matches!(r, SinkRow::Sink(_)))
+                    .count();
+                format!(" PipeWire Devices ({}/{}) ", rank, total)
+            }
+            None => " PipeWire Devices ".to_string(),
+        };
+
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(border_style);
+
+        if rows.is_empty() {
+            let inner = block.inner(area);
+            f.render_widget(block, area);
+            let hint = Paragraph::new(
+                "No audio devices found yet. The daemon keeps retrying; press [r] to look now.",
+            )
+            .style(Style::default().fg(Color::DarkGray))
+            .wrap(Wrap { trim: true });
+            f.render_widget(hint, inner);
+            return;
+        }
+
+        let max_width = (area.width as usize).saturating_sub(4);
+
+        let items: Vec<ListItem> = rows
+            .iter()
+            .map(|row| match *row {
+                SinkRow::Header(title) => ListItem::new(Line::from(Span::styled(
+                    title,
+                    Style::default().add_modifier(Modifier::BOLD),
+                ))),
+                SinkRow::Sink(i) => {
+                    let sink = &app.sinks()[i];
+                    let marker = if i == app.selected_sink() {
+                        " \u{2713}"
+                    } else {
+                        ""
+                    };
+                    let monitor = if app.monitor_sink() == Some(i) {
+                        " [x] monitor"
+                    } else {
+                        ""
+                    };
+                    let full = format!("{}{}{}", sink_label(sink), marker, monitor);
+                    let text = truncate_with_ellipsis(&full, max_width);
+                    let style = if sink.id == DEFAULT_SINK_ID {
+                        Style::default()
+                            .fg(Color::Magenta)
+                            .add_modifier(Modifier::ITALIC)
+                    } else {
+                        Style::default()
+                    };
+                    ListItem::new(text).style(style)
+                }
+            })
+            .collect();
+
+        let mut state = ListState::default().with_offset(app.sink_scroll);
+        state.select(selected_row);
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+
+        f.render_stateful_widget(list, area, &mut state);
+        app.sink_scroll = state.offset();
+
+        if app.focus == Panel::Sinks && !app.sinks().is_empty() {
+            let full_name = sink_label(&app.sinks()[app.selected_sink()]);
+
+            if full_name.len() > max_width {
+                let row = selected_row.unwrap_or(0).saturating_sub(app.sink_scroll);
+                let tooltip_y = area.y + 1 + row as u16;
+                if tooltip_y < area.y + area.height.saturating_sub(1) {
+                    let tooltip_width =
+                        (full_name.len() as u16 + 2).min(f.area().width.saturating_sub(area.x));
+                    let tooltip_area = Rect::new(area.x, tooltip_y, tooltip_width, 1);
+                    f.render_widget(Clear, tooltip_area);
+                    let tooltip = Paragraph::new(Line::from(Span::styled(
+                        format!(" {} ", full_name),
+                        Style::default().fg(Color::Yellow).bg(Color::DarkGray),
+                    )));
+                    f.render_widget(tooltip, tooltip_area);
+                }
+            }
+        }
+    }
+
+    fn hit(&self, app: &ClientApp, pos: Position) -> Option<Action> {
+        let area = app.layout.sinks_area;
+        if !area.contains(pos) {
+            return None;
+        }
+        let (_, inner_y) = inner_offset(area, pos);
+        // Rows scrolled out of view still count, headers don't select anything
+        match app.sink_rows().get(app.sink_scroll + inner_y as usize) {
+            Some(SinkRow::Sink(idx)) => Some(Action::SelectSink(*idx)),
+            _ => Some(Action::Focus(Panel::Sinks)),
+        }
+    }
+}
+
+/// A device as listed: kind, name, and whether "System Default" plays on it.
+fn sink_label(sink: &SinkInfo) -> String {
+    let prefix = match sink.kind.as_str() {
+        _ if sink.id == DEFAULT_SINK_ID => "[Sys] ",
+        "Input" => "[In] ",
+        _ => "[Out] ",
+    };
+    let default = if sink.is_default { " (default)" } else { "" };
+    format!("{}{}{}", prefix, sink.description, default)
+}
+
+fn truncate_with_ellipsis(s: &str, max_width: usize) -> String {
+    if s.len() <= max_width {
+        s.to_string()
+    } else if max_width <= 3 {
+        s.chars().take(max_width).collect()
+    } else {
+        let mut truncated: String = s.chars().take(max_width - 3).collect();
+        truncated.push_str("...");
+        truncated
+    }
+}
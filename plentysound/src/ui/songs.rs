@@ -0,0 +1,389 @@
+use super::{inner_offset, tag_prefix, Action, Component};
+use crate::client::{ClientApp, Panel};
+use ratatui::{
+    layout::{Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, HighlightSpacing, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+#[cfg(feature = "transcriber")]
+use crate::protocol::WordDetectorStatus;
+
+/// Opens the file browser.
+pub struct AddButton;
+
+impl Component for AddButton {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        let border_style = if app.focus == Panel::AddButton {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+
+        let text = if app.focus == Panel::AddButton {
+            Span::styled(
+                " [ + Add Songs ] ",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled(" [ + Add Songs ] ", Style::default().fg(Color::White))
+        };
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(border_style);
+
+        let paragraph = Paragraph::new(Line::from(text)).block(block);
+        f.render_widget(paragraph, area);
+    }
+
+    fn hit(&self, app: &ClientApp, pos: Position) -> Option<Action> {
+        app.layout
+            .add_button_area
+            .contains(pos)
+            .then_some(Action::Activate(Panel::AddButton))
+    }
+}
+
+/// Enables, starts or stops the word detector depending on its status.
+#[cfg(feature = "transcriber")]
+pub struct WordDetectorButton;
+
+#[cfg(feature = "transcriber")]
+impl Component for WordDetectorButton {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        let is_focused = app.focus == Panel::WordDetectorButton;
+        let border_style = if is_focused {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+
+        let (label, color) = match &app.state.word_detector_status {
+            _ if app.state.vosk_missing.is_some() => ("Vosk library missing", Color::Red),
+            WordDetectorStatus::Unavailable => ("Enable Word Detector", Color::White),
+            WordDetectorStatus::Downloading => ("Downloading Model... (cancel)", Color::Yellow),
+            WordDetectorStatus::DownloadFailed(_) => ("Download Failed (retry)", Color::Red),
+            WordDetectorStatus::Ready => ("Word Detector", Color::White),
+            WordDetectorStatus::Running => ("Word Detector [ON]", Color::Green),
+        };
+
+        let text_style = if is_focused {
+            Style::default().fg(color).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(color)
+        };
+
+        let mut spans = vec![Span::styled(format!(" [ {} ] ", label), text_style)];
+        if app.state.detector_capturing {
+            spans.push(Span::styled(
+                "\u{25cf} REC",
+                Style::default().fg(Color::Red),
+            ));
+        }
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(border_style);
+
+        let paragraph = Paragraph::new(Line::from(spans)).block(block);
+        f.render_widget(paragraph, area);
+    }
+
+    fn hit(&self, app: &ClientApp, pos: Position) -> Option<Action> {
+        app.layout
+            .word_detector_button_area
+            .contains(pos)
+            .then_some(Action::Activate(Panel::WordDetectorButton))
+    }
+}
+
+/// The library in display order, marking the playing and queued songs.
+/// Clicking a song plays it.
+pub struct SongList;
+
+impl Component for SongList {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        let border_style = if app.focus == Panel::Songs {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+
+        let order = app.song_order();
+        let total: f64 = order.iter().filter_map(|&i| app.songs()[i].duration).sum();
+        let mut title = match order.len() {
+            0 => " Songs ".to_string(),
+            n if total > 0.0 => format!(" Songs ({n}, {} total) ", clock(total)),
+            n => format!(" Songs ({n}) "),
+        };
+        if let Some(tag) = &app.tag_filter {
+            title.push_str(&format!("[{tag}] "));
+        }
+        let filter = app
+            .song_filter
+            .as_ref()
+            .filter(|f| f.editing || !f.input.is_empty());
+        if let Some(filter) = filter {
+            let cursor = if filter.editing { "_" } else { "" };
+            title.push_str(&format!("/{}{cursor} ", filter.input.as_str()));
+        }
+        if let Some(key) = app.state.song_sort {
+            title.push_str(&format!("[by {}] ", key.name()));
+        }
+        if app.state.loop_enabled {
+            title.push_str("[loop] ");
+        }
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(border_style);
+
+        // Inside the border and the highlight symbol, with a space to spare
+        let width = area.width.saturating_sub(5) as usize;
+        let items: Vec<ListItem> = order
+            .iter()
+            .map(|&i| {
+                let song = &app.songs()[i];
+                let playing = app.now_playing().is_some_and(|np| np == song.name);
+                let name = match song.volume {
+                    Some(v) => format!("{} ({v:.1}x)", song.name),
+                    None => song.name.clone(),
+                };
+                let mut text = if playing && app.paused() {
+                    format!("\u{25b6} {name} (paused)")
+                } else if playing {
+                    format!("\u{25b6} {name} (playing)")
+                } else if song.missing {
+                    format!("\u{2717} {name} (missing)")
+                } else if song.undecodable {
+                    format!("{name} (cannot decode)")
+                } else {
+                    name
+                };
+                // Queue positions count the playing song as #1
+                let queued: Vec<String> = app
+                    .state
+                    .queue
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, path)| **path == song.path)
+                    .map(|(pos, _)| format!("#{}", pos + 2))
+                    .collect();
+                if !queued.is_empty() {
+                    text = format!("{text} {}", queued.join(", "));
+                }
+                let style = if song.undecodable || song.missing {
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::DIM)
+                } else {
+                    Style::default()
+                };
+                let mut spans = tag_prefix(app, song);
+                spans.push(Span::styled(text, style));
+                let length = song.duration.map_or_else(|| "--:--".to_string(), clock);
+                let used = Line::from(spans.clone()).width() + length.len();
+                spans.push(Span::raw(" ".repeat(width.saturating_sub(used).max(1))));
+                spans.push(Span::styled(length, Style::default().fg(Color::DarkGray)));
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let mut state = ListState::default().with_offset(app.song_scroll);
+        state.select(order.iter().position(|&i| i == app.selected_song()));
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        f.render_stateful_widget(list, area, &mut state);
+        app.song_scroll = state.offset();
+    }
+
+    fn hit(&self, app: &ClientApp, pos: Position) -> Option<Action> {
+        let area = app.layout.songs_area;
+        if !area.contains(pos) {
+            return None;
+        }
+        let (_, inner_y) = inner_offset(area, pos);
+        match app.song_order().get(app.song_scroll + inner_y as usize) {
+            Some(&idx) => Some(Action::PlaySong(idx)),
+            None => Some(Action::Focus(Panel::Songs)),
+        }
+    }
+}
+
+/// Section under the Songs panel, toggled with `Q`: what is playing with its
+/// progress, then each queued song with its place. Clicking an entry
+/// highlights it, and dragging it moves it.
+pub struct QueuePanel;
+
+impl Component for QueuePanel {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        let border_style = if app.focus == Panel::Queue {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        let title = match app.state.queue.len() {
+            0 => " Queue ".to_string(),
+            n => format!(" Queue ({n}) "),
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(border_style);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        if inner.height == 0 {
+            return;
+        }
+
+        // Lined up with the entries past their highlight symbol
+        let now = match app.now_playing() {
+            Some(name) => {
+                let playing = format!("  #1 \u{25b6} {name}");
+                let mut spans = vec![Span::styled(playing, Style::default().fg(Color::Green))];
+                if let Some((position, total)) = app.progress {
+                    let time = format!("  {} / {}", clock(position), clock(total));
+                    spans.push(Span::styled(time, Style::default().fg(Color::DarkGray)));
+                }
+                Line::from(spans)
+            }
+            None => Line::from(Span::styled(
+                "  Nothing playing",
+                Style::default().fg(Color::DarkGray),
+            )),
+        };
+        f.render_widget(Paragraph::new(now), Rect { height: 1, ..inner });
+
+        // Queue positions count the playing song as #1, as in the Songs panel
+        let items: Vec<ListItem> = app
+            .state
+            .queue
+            .iter()
+            .enumerate()
+            .map(|(pos, path)| {
+                let name = match app.songs().iter().find(|s| s.path == *path) {
+                    Some(song) => song.name.clone(),
+                    None => path.rsplit('/').next().unwrap_or(path).to_string(),
+                };
+                ListItem::new(format!("#{} {name}", pos + 2))
+            })
+            .collect();
+        let mut state = ListState::default().with_offset(app.queue_scroll);
+        state.select((!items.is_empty()).then_some(app.selected_queue));
+        let list = List::new(items)
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        let entries = Rect {
+            y: inner.y + 1,
+            height: inner.height - 1,
+            ..inner
+        };
+        f.render_stateful_widget(list, entries, &mut state);
+        app.queue_scroll = state.offset();
+    }
+
+    fn hit(&self, app: &ClientApp, pos: Position) -> Option<Action> {
+        let area = app.layout.queue_area;
+        if !area.contains(pos) {
+            return None;
+        }
+        // Row 0 is what is playing
+        let (_, inner_y) = inner_offset(area, pos);
+        match inner_y
+            .checked_sub(1)
+            .map(|row| app.queue_scroll + row as usize)
+        {
+            Some(entry) if entry < app.state.queue.len() => Some(Action::SelectQueued(entry)),
+            _ => Some(Action::Focus(Panel::Queue)),
+        }
+    }
+}
+
+/// `m:ss`, with as many minutes as it takes.
+fn clock(secs: f64) -> String {
+    let secs = secs.max(0.0) as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Thin gauge under the Songs panel: elapsed / total and a bar.
+pub struct PlaybackProgress;
+
+impl Component for PlaybackProgress {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        let Some((position, total)) = app.progress else {
+            return;
+        };
+        let label = format!(" {} / {} ", clock(position), clock(total));
+        let width = area.width.saturating_sub(label.len() as u16 + 1);
+        let ratio = if total > 0.0 {
+            (position / total).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let filled = (ratio * width as f64).round() as u16;
+        let color = if app.paused() {
+            Color::Yellow
+        } else {
+            Color::Green
+        };
+
+        let mut spans = vec![Span::styled(label, Style::default().fg(Color::DarkGray))];
+        spans.extend((0..width).map(|i| {
+            if i < filled {
+                Span::styled("\u{2501}", Style::default().fg(color))
+            } else {
+                Span::styled("\u{2500}", Style::default().fg(Color::DarkGray))
+            }
+        }));
+        f.render_widget(Paragraph::new(Line::from(spans)), area);
+    }
+}
+
+/// Envelope of the highlighted song under the Songs panel, asked for with `v`.
+pub struct WaveformStrip;
+
+impl Component for WaveformStrip {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        let Some(peaks) = app.selected_waveform() else {
+            return;
+        };
+        let bars = sparkline(peaks, area.width as usize);
+        let line = Line::from(Span::styled(bars, Style::default().fg(Color::Cyan)));
+        f.render_widget(Paragraph::new(line), area);
+    }
+}
+
+// One bar per column, each the loudest of the levels it covers
+fn sparkline(peaks: &[u8], width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    if peaks.is_empty() {
+        return String::new();
+    }
+    (0..width)
+        .map(|column| {
+            let start = column * peaks.len() / width;
+            let end = ((column + 1) * peaks.len() / width).max(start + 1);
+            let peak = peaks[start..end].iter().copied().max().unwrap_or(0);
+            BARS[(peak as usize * 7 + 127) / 255]
+        })
+        .collect()
+}
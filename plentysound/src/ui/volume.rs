@@ -0,0 +1,140 @@
+use super::{inner_offset, Action, Component};
+use crate::client::{ClientApp, Panel};
+use crate::protocol::VOLUME_MAX;
+use ratatui::{
+    layout::{Position, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// Volume slider with the output meter under it. Clicking sets the volume
+/// to that point of the bar.
+pub struct VolumePanel;
+
+impl Component for VolumePanel {
+    fn draw(&self, f: &mut Frame, app: &mut ClientApp, area: Rect) {
+        let border_style = if app.focus == Panel::Volume {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+
+        let title = if app.state.sidechain_enabled {
+            " Volume — duck on talk "
+        } else {
+            " Volume "
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(border_style);
+
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        if inner.width == 0 || inner.height == 0 {
+            return;
+        }
+
+        let ratio = app.volume() / VOLUME_MAX;
+        let filled = (ratio * inner.width as f32).round() as u16;
+        let pct = (app.volume() * 100.0).round() as u16;
+        let label = format!("{}%", pct);
+
+        let label_start = inner.width.saturating_sub(label.len() as u16) / 2;
+        let label_end = label_start + label.len() as u16;
+
+        let spans: Vec<Span> = (0..inner.width)
+            .map(|i| {
+                let ch = if i < filled { "\u{2588}" } else { "\u{2591}" };
+                let in_label = i >= label_start && i < label_end;
+                if in_label {
+                    let label_idx = (i - label_start) as usize;
+                    let label_char = &label[label_idx..label_idx + 1];
+                    if i < filled {
+                        Span::styled(
+                            label_char,
+                            Style::default().fg(Color::Black).bg(Color::Green),
+                        )
+                    } else {
+                        Span::styled(
+                            label_char,
+                            Style::default().fg(Color::White).bg(Color::DarkGray),
+                        )
+                    }
+                } else if i < filled {
+                    Span::styled(ch, Style::default().fg(Color::Green))
+                } else {
+                    Span::styled(ch, Style::default().fg(Color::DarkGray))
+                }
+            })
+            .collect();
+
+        let line = Line::from(spans);
+        let paragraph = Paragraph::new(line);
+        f.render_widget(paragraph, Rect { height: 1, ..inner });
+
+        if inner.height > 1 {
+            draw_output_meter(
+                f,
+                app,
+                Rect {
+                    y: inner.y + 1,
+                    height: 1,
+                    ..inner
+                },
+            );
+        }
+    }
+
+    fn hit(&self, app: &ClientApp, pos: Position) -> Option<Action> {
+        let area = app.layout.volume_area;
+        if !area.contains(pos) {
+            return None;
+        }
+        let (inner_x, _) = inner_offset(area, pos);
+        let inner_width = area.width.saturating_sub(2);
+        if inner_width == 0 {
+            return Some(Action::Focus(Panel::Volume));
+        }
+        let volume = (inner_x as f32 / inner_width as f32 * VOLUME_MAX).clamp(0.0, VOLUME_MAX);
+        Some(Action::SetVolume(volume))
+    }
+}
+
+/// Level of the active playback on a -60..0 dBFS scale, with a peak-hold
+/// marker. Red while the limiter is engaged.
+fn draw_output_meter(f: &mut Frame, app: &ClientApp, area: Rect) {
+    const FLOOR_DB: f32 = -60.0;
+    let position = |amplitude: f32| {
+        let db = 20.0 * amplitude.max(1e-6).log10();
+        let ratio = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+        (ratio * area.width as f32).round() as u16
+    };
+
+    let meter = &app.meter;
+    let filled = position(meter.rms);
+    let peak = position(meter.peak_hold).checked_sub(1);
+    let color = if meter.limiting {
+        Color::Red
+    } else if meter.rms > 0.5 {
+        Color::Yellow
+    } else {
+        Color::Green
+    };
+
+    let spans: Vec<Span> = (0..area.width)
+        .map(|i| {
+            if i < filled {
+                Span::styled("\u{25AE}", Style::default().fg(color))
+            } else if Some(i) == peak {
+                Span::styled("\u{2502}", Style::default().fg(color))
+            } else {
+                Span::styled(" ", Style::default())
+            }
+        })
+        .collect();
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
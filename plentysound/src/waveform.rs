@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::audio::StreamChunk;
+
+const LOG: crate::log::Logger = crate::log::log_scope("waveform");
+
+/// Levels in a song's envelope; the TUI squeezes or stretches them to fit.
+pub const WAVEFORM_BUCKETS: usize = 200;
+
+/// Audio folded into each level while decoding, before bucketing. Fine
+/// enough that a short clip still fills every bucket with its own level.
+const FINE_MS: usize = 5;
+
+/// Where envelopes are kept, one file per song path and modification time.
+pub fn cache_dir() -> PathBuf {
+    crate::protocol::cache_root().join("waveforms")
+}
+
+/// Peak envelope of `path`: `WAVEFORM_BUCKETS` max-abs levels scaled to
+/// 0..=255. Reuses an earlier result until the file is modified.
+pub fn peaks(path: &Path, cache_dir: &Path) -> Result<Vec<u8>> {
+    let modified = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (path, modified).hash(&mut hasher);
+    let cached = cache_dir.join(format!("{:016x}.peaks", hasher.finish()));
+    if let Ok(peaks) = std::fs::read(&cached) {
+        if peaks.len() == WAVEFORM_BUCKETS {
+            return Ok(peaks);
+        }
+    }
+
+    let peaks = decode_peaks(path)?;
+    // A cache that can't be written only costs a decode next time
+    if let Err(e) = store(&cached, &peaks) {
+        LOG.warn(&format!(
+            "Failed to cache waveform of {}: {e:#}",
+            path.display()
+        ));
+    }
+    Ok(peaks)
+}
+
+// Written aside and renamed, so a half-written file is never read back
+fn store(cached: &Path, peaks: &[u8]) -> Result<()> {
+    let dir = cached.parent().context("cache path has no directory")?;
+    crate::protocol::private_dir(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    let part = cached.with_extension(format!("{}.part", std::process::id()));
+    std::fs::write(&part, peaks).with_context(|| format!("Failed to write {}", part.display()))?;
+    std::fs::rename(&part, cached).with_context(|| format!("Failed to move {}", part.display()))
+}
+
+// Decode the whole file, keeping one level per `FINE_MS`, and fold those
+// into the envelope's buckets.
+fn decode_peaks(path: &Path) -> Result<Vec<u8>> {
+    let stream = crate::audio::stream_file(path)?;
+    let frame = stream.channels.max(1) as usize;
+    let fine_len = (stream.sample_rate as usize * FINE_MS / 1000).max(1) * frame;
+    let mut levels = Vec::new();
+    let (mut level, mut counted) = (0.0f32, 0);
+    loop {
+        match stream.next() {
+            StreamChunk::Samples(samples) => {
+                for sample in samples {
+                    level = level.max(sample.abs());
+                    counted += 1;
+                    if counted == fine_len {
+                        levels.push(std::mem::take(&mut level));
+                        counted = 0;
+                    }
+                }
+            }
+            StreamChunk::Pending => {}
+            StreamChunk::Ended => break,
+            StreamChunk::Failed(e) => return Err(e.context("Decoding failed mid-stream")),
+        }
+    }
+    if counted > 0 {
+        levels.push(level);
+    }
+    Ok(bucket(&levels, WAVEFORM_BUCKETS))
+}
+
+/// Fold `levels` into `buckets` maxima scaled to 0..=255. With fewer levels
+/// than buckets each level spans several, so the envelope keeps its shape.
+fn bucket(levels: &[f32], buckets: usize) -> Vec<u8> {
+    if levels.is_empty() {
+        return vec![0; buckets];
+    }
+    (0..buckets)
+        .map(|b| {
+            let start = b * levels.len() / buckets;
+            let end = ((b + 1) * levels.len() / buckets).max(start + 1);
+            let peak = levels[start..end]
+                .iter()
+                .fold(0.0f32, |peak, &l| peak.max(l));
+            (peak.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::tests::{scratch_dir, write_file};
+
+    /// 16-bit mono WAV of `samples` at 8 kHz.
+    fn wav(samples: &[i16]) -> Vec<u8> {
+        let data_len = samples.len() as u32 * 2;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&8000u32.to_le_bytes());
+        bytes.extend_from_slice(&16000u32.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn envelope_follows_the_loud_half_and_is_cached_per_mtime() {
+        let dir = scratch_dir("waveform");
+        let cache = dir.join("cache");
+        // A quiet second, then a loud one
+        let mut samples = vec![1000i16; 8000];
+        samples.extend(std::iter::repeat_n(-32000i16, 8000));
+        let song = write_file(&dir, "song.wav", &wav(&samples));
+
+        let envelope = peaks(&song, &cache).unwrap();
+        assert_eq!(envelope.len(), WAVEFORM_BUCKETS);
+        assert!(
+            envelope[..WAVEFORM_BUCKETS / 2 - 1].iter().all(|&p| p < 10),
+            "{envelope:?}"
+        );
+        assert!(
+            envelope[WAVEFORM_BUCKETS / 2 + 1..]
+                .iter()
+                .all(|&p| p > 240),
+            "{envelope:?}"
+        );
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 1);
+        let mode = cache.metadata().unwrap().permissions();
+        assert_eq!(
+            std::os::unix::fs::PermissionsExt::mode(&mode) & 0o777,
+            0o700
+        );
+
+        // Served from the cache while the file is unchanged...
+        let cached = std::fs::read_dir(&cache)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        std::fs::write(&cached, vec![7; WAVEFORM_BUCKETS]).unwrap();
+        assert_eq!(peaks(&song, &cache).unwrap(), vec![7; WAVEFORM_BUCKETS]);
+
+        // ...and computed again once it is modified
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+        std::fs::File::options()
+            .write(true)
+            .open(&song)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(peaks(&song, &cache).unwrap(), envelope);
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn short_clips_stretch_and_long_ones_keep_their_peaks() {
+        assert_eq!(bucket(&[], 4), vec![0; 4]);
+        assert_eq!(bucket(&[0.5, 1.0], 4), vec![128, 128, 255, 255]);
+        assert_eq!(
+            bucket(&[0.1, 0.9, 0.2, 0.0, 2.0, 0.3], 3),
+            vec![230, 51, 255]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Checks that must hold with and without the `transcriber` feature. Run it
+//! under both (`make test` does): the cfg-gated code of one build is never
+//! compiled by the other.
+//!
+//! plentysound is a binary crate, so this file is built into its unit tests
+//! through `main.rs` rather than as an integration test.
+
+use crate::app::tests::{command_result, test_daemon};
+use crate::client::{AppLayout, ClientApp, Panel, PANEL_ORDER};
+use crate::protocol::{recv_message, send_message};
+use crate::protocol::{ClientCommand, CommandResult, DaemonEvent, DaemonState, SinkInfo};
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use ratatui::{
+    backend::TestBackend,
+    layout::{Position, Rect},
+    Terminal,
+};
+use std::os::unix::net::UnixStream;
+
+#[cfg(feature = "transcriber")]
+use crate::protocol::{SongSelection, WordDetectorStatus};
+
+fn client(state: DaemonState) -> (ClientApp, UnixStream) {
+    let (client, mut daemon) = UnixStream::pair().unwrap();
+    send_message(&mut daemon, &DaemonEvent::State(Box::new(state))).unwrap();
+    (ClientApp::new(client).unwrap(), daemon)
+}
+
+fn press(app: &mut ClientApp, code: KeyCode) {
+    app.handle_event(Event::Key(KeyEvent::from(code)));
+}
+
+fn area(layout: &AppLayout, panel: Panel) -> Rect {
+    match panel {
+        Panel::Sinks => layout.sinks_area,
+        Panel::Volume => layout.volume_area,
+        Panel::AudioFx => layout.audio_fx_area,
+        Panel::AddButton => layout.add_button_area,
+        #[cfg(feature = "transcriber")]
+        Panel::WordDetectorButton => layout.word_detector_button_area,
+        Panel::Songs => layout.songs_area,
+        Panel::Queue => layout.queue_area,
+        #[cfg(feature = "transcriber")]
+        Panel::WordBindings => layout.word_bindings_area,
+    }
+}
+
+/// Panels Tab visits from `Sinks`, back to it; BackTab must visit the same
+/// ones in reverse.
+fn tab_cycle(app: &mut ClientApp) -> Vec<Panel> {
+    app.focus = Panel::Sinks;
+    let mut forward = Vec::new();
+    loop {
+        press(app, KeyCode::Tab);
+        forward.push(app.focus);
+        if app.focus == Panel::Sinks || forward.len() > PANEL_ORDER.len() {
+            break;
+        }
+    }
+    let mut back = Vec::new();
+    for _ in &forward {
+        press(app, KeyCode::BackTab);
+        back.push(app.focus);
+    }
+    back.reverse();
+    let mut expected_back = vec![Panel::Sinks];
+    expected_back.extend(&forward[..forward.len() - 1]);
+    assert_eq!(back, expected_back, "BackTab doesn't retrace Tab");
+    forward
+}
+
+#[test]
+fn tab_visits_every_shown_panel_once() {
+    let (mut app, _daemon) = client(DaemonState::default());
+    #[cfg(not(feature = "transcriber"))]
+    let expected = [
+        Panel::Volume,
+        Panel::AudioFx,
+        Panel::AddButton,
+        Panel::Songs,
+        Panel::Sinks,
+    ];
+    #[cfg(feature = "transcriber")]
+    let expected = [
+        Panel::Volume,
+        Panel::AudioFx,
+        Panel::AddButton,
+        Panel::WordDetectorButton,
+        Panel::Songs,
+        Panel::Sinks,
+    ];
+    assert_eq!(tab_cycle(&mut app), expected);
+
+    // The queue joins the cycle once shown, after the songs
+    app.show_queue = true;
+    let with_queue = tab_cycle(&mut app);
+    let songs = with_queue.iter().position(|&p| p == Panel::Songs).unwrap();
+    assert_eq!(with_queue[songs + 1], Panel::Queue);
+
+    // The bindings panel joins the cycle once the detector is usable, and
+    // focus leaves it the usual way when it goes away again
+    #[cfg(feature = "transcriber")]
+    {
+        app.state.word_detector_status = WordDetectorStatus::Ready;
+        let all: Vec<Panel> = PANEL_ORDER[1..]
+            .iter()
+            .chain(&PANEL_ORDER[..1])
+            .copied()
+            .collect();
+        assert_eq!(tab_cycle(&mut app), all);
+        app.focus = Panel::WordBindings;
+        app.state.word_detector_status = WordDetectorStatus::Unavailable;
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.focus, Panel::Sinks);
+    }
+}
+
+#[test]
+fn every_shown_panel_gets_its_own_area() {
+    let sink = |id, kind: &str| SinkInfo {
+        id,
+        name: format!("node{id}"),
+        description: format!("Device {id}"),
+        kind: kind.to_string(),
+        is_default: false,
+    };
+    let sinks = vec![sink(1, "Output"), sink(2, "Input")];
+    let state = DaemonState {
+        sinks,
+        ..Default::default()
+    };
+    let (mut app, _daemon) = client(state);
+    app.show_queue = true;
+    #[cfg(feature = "transcriber")]
+    {
+        app.state.word_detector_status = WordDetectorStatus::Running;
+    }
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    let frame_area = terminal
+        .draw(|f| crate::ui::draw(f, &mut app))
+        .unwrap()
+        .area;
+
+    let shown: Vec<Panel> = PANEL_ORDER
+        .iter()
+        .copied()
+        .filter(|&p| app.panel_shown(p))
+        .collect();
+    assert_eq!(shown, PANEL_ORDER);
+    for (i, &panel) in shown.iter().enumerate() {
+        let r = area(&app.layout, panel);
+        assert!(r.width > 0 && r.height > 0, "{panel:?} has no area");
+        assert_eq!(r.intersection(frame_area), r, "{panel:?} is off screen");
+        for &other in &shown[i + 1..] {
+            assert!(
+                !r.intersects(area(&app.layout, other)),
+                "{panel:?} overlaps {other:?}"
+            );
+        }
+        // The middle of every panel takes clicks
+        let middle = Position::new(r.x + r.width / 2, r.y + r.height / 2);
+        assert!(
+            crate::ui::hit_test(&app, middle).is_some(),
+            "{panel:?} takes no clicks"
+        );
+    }
+}
+
+#[test]
+fn commands_shared_by_both_builds_behave_the_same() {
+    let mut t = test_daemon("feature-matrix");
+    let snapshot = |events: Vec<DaemonEvent>| {
+        events.into_iter().find_map(|e| match e {
+            DaemonEvent::State(state) => Some(state),
+            _ => None,
+        })
+    };
+
+    let state = snapshot(t.app.apply_command(ClientCommand::GetState)).unwrap();
+    assert!(state.songs.is_empty() && state.sinks.is_empty());
+    assert!(matches!(
+        &t.app.apply_command(ClientCommand::Play)[..],
+        [DaemonEvent::Error { .. }]
+    ));
+
+    let events = t.app.apply_command(ClientCommand::SetVolume(0.5));
+    assert!(matches!(events[..], [DaemonEvent::VolumeChanged(v)] if v == 0.5));
+
+    let song = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/silence.wav");
+    let added = t
+        .app
+        .apply_command(ClientCommand::AddSong(song.display().to_string()));
+    assert!(matches!(command_result(added), Some(CommandResult::Ok(_))));
+    let bad_index = t.app.apply_command(ClientCommand::RemoveSong(5));
+    assert!(matches!(
+        command_result(bad_index),
+        Some(CommandResult::Error(_))
+    ));
+
+    #[cfg(feature = "transcriber")]
+    {
+        let events = t.app.apply_command(ClientCommand::AddWordMapping {
+            word: "horn".to_string(),
+            song_indices: vec![0],
+            selection: SongSelection::Random,
+            source_description: String::new(),
+            output_description: String::new(),
+        });
+        assert!(matches!(&events[..], [DaemonEvent::MappingsUpdated(m)] if m.len() == 1));
+    }
+}
+
+#[test]
+fn client_commands_reach_the_daemon_in_both_builds() {
+    let (mut app, mut daemon) = client(DaemonState::default());
+    app.focus = Panel::Volume;
+    press(&mut app, KeyCode::Right);
+    daemon.set_nonblocking(true).unwrap();
+    let sent: Vec<ClientCommand> = std::iter::from_fn(|| recv_message(&mut daemon).ok()).collect();
+    assert!(
+        sent.iter()
+            .any(|cmd| matches!(cmd, ClientCommand::SetVolume(_))),
+        "{sent:?}"
+    );
+}